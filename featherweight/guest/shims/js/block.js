@@ -0,0 +1,62 @@
+// Example Featherweight Block in JavaScript.
+//
+// Build with componentize-js through jco (see ../README.md). The
+// `featherweight:block/store@0.1.0` import is provided by the host.
+
+import { listChildren, readEncoded, writeEncoded } from "featherweight:block/store@0.1.0";
+
+const JSON_FORMAT = "application/json";
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/** Read the value at `path`, or null if nothing is there. */
+function read(path) {
+  const result = readEncoded(path, JSON_FORMAT);
+  switch (result.tag) {
+    case "found":
+      return JSON.parse(decoder.decode(result.val));
+    case "not-found":
+      return null;
+    default:
+      throw new Error(`read ${path}: ${result.val}`);
+  }
+}
+
+/** Write `value` to `path` and return the path written. */
+function write(path, value) {
+  const result = writeEncoded(path, JSON_FORMAT, encoder.encode(JSON.stringify(value)));
+  if (result.tag === "written") {
+    return result.val;
+  }
+  throw new Error(`write ${path}: ${result.val}`);
+}
+
+/** Names of the children of `path`, or null if nothing is there. */
+function children(path) {
+  const result = listChildren(path);
+  switch (result.tag) {
+    case "found":
+      return result.val;
+    case "not-found":
+      return null;
+    default:
+      throw new Error(`list ${path}: ${result.val}`);
+  }
+}
+
+function main() {
+  const user = read("input/user") ?? { name: "World", roles: [] };
+  write("output/greeting", {
+    message: `Hello, ${user.name}!`,
+    admin: (user.roles ?? []).includes("admin"),
+  });
+  write("output/status", "completed");
+}
+
+export const block = {
+  // A thrown error becomes the `err` result, which the host records in the
+  // Block's failure report
+  run() {
+    main();
+  },
+};
@@ -0,0 +1,68 @@
+"""Example Featherweight Block in Python.
+
+Build with componentize-py (see ../README.md). The bindings module
+`block_world` is generated from ../../wit/world.wit at build time.
+"""
+
+import json
+
+from block_world import exports
+from block_world.imports import store
+
+JSON = "application/json"
+
+
+class StoreError(Exception):
+    """A read, write or list the host rejected."""
+
+
+def read(path):
+    """Read the value at `path`, or None if nothing is there."""
+    result = store.read_encoded(path, JSON)
+    if isinstance(result, store.ReadEncodedResult_Found):
+        return json.loads(bytes(result.value).decode("utf-8"))
+    if isinstance(result, store.ReadEncodedResult_NotFound):
+        return None
+    raise StoreError(f"read {path}: {result.value}")
+
+
+def write(path, value):
+    """Write `value` to `path` and return the path written."""
+    data = json.dumps(value).encode("utf-8")
+    result = store.write_encoded(path, JSON, data)
+    if isinstance(result, store.WriteResult_Written):
+        return result.value
+    raise StoreError(f"write {path}: {result.value}")
+
+
+def children(path):
+    """Names of the children of `path`, or None if nothing is there."""
+    result = store.list_children(path)
+    if isinstance(result, store.ListResult_Found):
+        return result.value
+    if isinstance(result, store.ListResult_NotFound):
+        return None
+    raise StoreError(f"list {path}: {result.value}")
+
+
+def main():
+    user = read("input/user") or {"name": "World", "roles": []}
+    write(
+        "output/greeting",
+        {
+            "message": f"Hello, {user['name']}!",
+            "admin": "admin" in user.get("roles", []),
+        },
+    )
+    write("output/status", "completed")
+
+
+class Block(exports.Block):
+    def run(self):
+        # Errors become the Block's failure report instead of a trap
+        try:
+            main()
+        except Exception as e:
+            from block_world.types import Err
+
+            raise Err(str(e))
@@ -1,7 +1,12 @@
 //! Featherweight Guest Library
 //!
 //! This crate provides the guest-side implementation for WASM Blocks.
-//! It uses wit-bindgen to generate bindings from the WIT file.
+//! It uses wit-bindgen to generate bindings from the WIT file, and [`rpc`]
+//! helps Blocks call services and answer calls.
+//!
+//! Long-running Blocks should poll `featherweight::block::store::should_cancel`
+//! between units of work and return from `run` once it is true, so the
+//! runtime can stop them without trapping them mid-write.
 
 // Generate bindings from the WIT file
 wit_bindgen::generate!({
@@ -9,6 +14,8 @@ wit_bindgen::generate!({
     path: "wit/world.wit",
 });
 
+pub mod rpc;
+
 use exports::featherweight::block::block::Guest;
 use featherweight::block::store::{read, write, ReadResult, Value, WriteResult};
 
@@ -0,0 +1,115 @@
+//! Request/response calls from inside a Block.
+//!
+//! These follow the protocol of the runtime's `RpcStore`: a caller writes a
+//! request to `{service}/requests` and waits on
+//! `{written path}/response/wait/{ms}`; a service claims request ids from
+//! `next/id` and answers at `requests/{id}/response`.
+
+use crate::featherweight::block::store::{read, write, ReadResult, Value, WriteResult};
+
+/// How long a call waits for its response, by default.
+pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Calls a service mounted in the Block's root.
+pub struct RpcClient {
+    service: String,
+    timeout_ms: u64,
+}
+
+impl RpcClient {
+    /// A client for the service mounted at `service`.
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.trim_end_matches('/').to_string(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+
+    /// Give up on calls after `timeout_ms` milliseconds.
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Send `request` and wait for the response.
+    pub fn call(&self, request: &Value) -> Result<Value, String> {
+        let call = self.send(request)?;
+        match read(&format!("{}/response/wait/{}", call, self.timeout_ms)) {
+            ReadResult::Found(value) => Ok(value),
+            ReadResult::NotFound => Err(format!("Request '{}' was abandoned", call)),
+            ReadResult::ReadError(e) => {
+                // Abandon the call so the service does not answer it later
+                let _ = write(&call, &Value::ValNull);
+                Err(e)
+            }
+        }
+    }
+
+    /// Send `request` without waiting, returning the path of the call.
+    pub fn send(&self, request: &Value) -> Result<String, String> {
+        match write(&format!("{}/requests", self.service), request) {
+            WriteResult::Written(call) => Ok(call),
+            WriteResult::WriteError(e) => Err(e),
+        }
+    }
+
+    /// The correlation id of the call at `call`, a path returned by
+    /// [`send`](Self::send).
+    pub fn correlation_id(call: &str) -> Option<String> {
+        match read(&format!("{}/correlation_id", call)) {
+            ReadResult::Found(Value::ValText(id)) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// Answers the requests of a service the Block serves, exported to its
+/// callers and mounted in the Block's root at `service`.
+pub struct RpcServer {
+    service: String,
+}
+
+impl RpcServer {
+    /// A server for the requests at `service`.
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Claim the oldest unclaimed request, returning its id and the
+    /// request.
+    pub fn next(&self) -> Result<Option<(u64, Value)>, String> {
+        let id = match read(&format!("{}/next/id", self.service)) {
+            ReadResult::Found(Value::ValInteger(id)) => id as u64,
+            ReadResult::Found(_) => return Err("next/id must be an integer".to_string()),
+            ReadResult::NotFound => return Ok(None),
+            ReadResult::ReadError(e) => return Err(e),
+        };
+        match read(&format!("{}/requests/{}/request", self.service, id)) {
+            ReadResult::Found(request) => Ok(Some((id, request))),
+            ReadResult::NotFound => Ok(None),
+            ReadResult::ReadError(e) => Err(e),
+        }
+    }
+
+    /// Answer request `id`.
+    pub fn respond(&self, id: u64, response: &Value) -> Result<(), String> {
+        self.finish(id, "response", response)
+    }
+
+    /// Fail request `id` with `message`.
+    pub fn fail(&self, id: u64, message: &str) -> Result<(), String> {
+        self.finish(id, "error", &Value::ValText(message.to_string()))
+    }
+
+    fn finish(&self, id: u64, field: &str, value: &Value) -> Result<(), String> {
+        match write(
+            &format!("{}/requests/{}/{}", self.service, id, field),
+            value,
+        ) {
+            WriteResult::Written(_) => Ok(()),
+            WriteResult::WriteError(e) => Err(e),
+        }
+    }
+}
@@ -12,6 +12,7 @@ use structfs_core_store::{Reader, Writer};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use crate::cancel::Cancellation;
 use crate::error::Result;
 
 /// Unique identifier for a Block.
@@ -33,6 +34,21 @@ impl BlockId {
     pub fn as_uuid(&self) -> Uuid {
         self.0
     }
+
+    /// This ID as a valid path component: an underscore followed by the
+    /// UUID's 32 hex digits.
+    pub fn path_component(&self) -> String {
+        format!("_{}", self.0.simple())
+    }
+
+    /// Parse an ID from its [`path_component`](Self::path_component) form.
+    pub fn from_path_component(component: &str) -> Option<Self> {
+        let hex = component.strip_prefix('_')?;
+        if hex.len() != 32 {
+            return None;
+        }
+        Uuid::try_parse(hex).ok().map(Self)
+    }
 }
 
 impl Default for BlockId {
@@ -109,6 +125,9 @@ pub struct BlockContext<S> {
 
     /// Stores exported by this Block for other Blocks to mount.
     exports: BTreeMap<String, ExportedStore>,
+
+    /// Set when the Block is asked to stop.
+    cancellation: Cancellation,
 }
 
 /// A type-erased exported store.
@@ -154,9 +173,28 @@ impl<S> BlockContext<S> {
             id,
             root,
             exports: BTreeMap::new(),
+            cancellation: Cancellation::new(),
         }
     }
 
+    /// Let `cancellation` ask the Block to stop.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Whether the Block has been asked to stop. Long-running Blocks should
+    /// check this between units of work and return from `run` when it is
+    /// set. See the [`cancel`](crate::cancel) module.
+    pub fn should_cancel(&self) -> bool {
+        self.cancellation.should_cancel()
+    }
+
+    /// The Block's cancellation, for handing to work it starts.
+    pub fn cancellation(&self) -> &Cancellation {
+        &self.cancellation
+    }
+
     /// Export a store for other Blocks to mount.
     ///
     /// The store will be available at the given name for other Blocks
@@ -225,6 +263,16 @@ mod tests {
         assert_eq!(id.as_uuid(), uuid);
     }
 
+    #[test]
+    fn block_id_path_component() {
+        let id = BlockId::new();
+        let component = id.path_component();
+        assert!(Path::parse(&component).is_ok());
+        assert_eq!(BlockId::from_path_component(&component), Some(id));
+        assert_eq!(BlockId::from_path_component(&id.to_string()), None);
+        assert_eq!(BlockId::from_path_component("_abc"), None);
+    }
+
     #[tokio::test]
     async fn block_handle_state() {
         let handle = BlockHandle::new(BlockId::new());
@@ -0,0 +1,101 @@
+//! Asking a Block to stop.
+//!
+//! The runtime never traps a running Block to stop it. Instead each Block
+//! gets a [`Cancellation`] that becomes set when the Block is asked to stop
+//! and that the Block polls between units of work, finishing what it is
+//! writing and returning from `run` on its own:
+//!
+//! - [`Runtime::cancel`](crate::Runtime::cancel), or a write to
+//!   `blocks/{id}/cancel` on the [`BlocksStore`](crate::BlocksStore), is a
+//!   supervisor's request to stop
+//! - [`RuntimeConfig::block_deadline`](crate::RuntimeConfig::block_deadline)
+//!   asks every Block to stop once it has run that long
+//!
+//! Native Blocks call [`BlockContext::should_cancel`](crate::BlockContext::should_cancel).
+//! WASM Blocks call the `should-cancel` function of the WIT `store`
+//! interface:
+//!
+//! ```ignore
+//! while !should_cancel() {
+//!     process_next_item()?;
+//! }
+//! Ok(())
+//! ```
+//!
+//! Store operations are not interrupted, so a Block that was asked to stop
+//! can still write its final state.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use structfs_core_store::CancellationToken;
+
+use crate::block::BlockId;
+
+/// Cancellation of each registered Block, shared by the runtime and its
+/// stores.
+pub(crate) type CancellationTable = Arc<Mutex<BTreeMap<BlockId, Cancellation>>>;
+
+/// Whether a Block has been asked to stop.
+///
+/// Clones share the request, so the runtime keeps one and the Block
+/// another.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    token: CancellationToken,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// A cancellation that is not set and has no deadline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the Block to stop once `timeout` from now has passed.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Ask the Block to stop once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Ask the Block to stop now.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// When the Block will be asked to stop, if it has a deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Whether the Block has been asked to stop, by a request or because
+    /// its deadline has passed.
+    pub fn should_cancel(&self) -> bool {
+        self.token.is_cancelled() || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_and_deadlines_both_cancel() {
+        let cancellation = Cancellation::new();
+        let block_side = cancellation.clone();
+        assert!(!block_side.should_cancel());
+        cancellation.cancel();
+        assert!(block_side.should_cancel());
+
+        let later = Cancellation::new().with_timeout(Duration::from_secs(3600));
+        assert!(!later.should_cancel());
+        let past = Cancellation::new().with_deadline(Instant::now());
+        assert!(past.should_cancel());
+    }
+}
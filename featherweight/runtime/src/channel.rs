@@ -2,12 +2,35 @@
 //!
 //! ChannelStore provides a StructFS interface over tokio channels,
 //! enabling Blocks to communicate through familiar read/write operations.
+//!
+//! By default a message is gone once read. A receiver built
+//! [`with_acks`](ChannelStore::with_acks) delivers at least once instead:
+//! each read leases a message, and a message not acknowledged within the
+//! visibility timeout is delivered again, so a Block that dies mid-task does
+//! not lose its work.
+//!
+//! Messages written to `send` can carry a priority and a delay. Among the
+//! messages that are due, a receiver delivers the highest priority first,
+//! then the one due earliest, then the one received first, so messages of
+//! one priority from one sender arrive in the order sent. A delayed message
+//! is never delivered before its delay has passed. Redelivered leases come
+//! before new messages.
+//!
+//! A receiver takes at most the channel's capacity of messages off the
+//! channel to order them, so senders still see backpressure. While that
+//! many delayed messages are waiting, messages behind them in the channel
+//! wait too.
 
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
-use structfs_core_store::{Error, Path, Reader, Record, Value, Writer};
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
 use tokio::sync::mpsc;
 
+/// The longest `deliver_after_ms` a message can be sent or nacked with.
+pub const MAX_DELAY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
 /// A store backed by tokio channels for async message passing.
 ///
 /// ChannelStore allows one Block to send messages to another through
@@ -15,7 +38,22 @@ use tokio::sync::mpsc;
 ///
 /// - **Write to root**: Sends a message through the channel
 /// - **Read from root**: Receives a message (blocks until available)
-/// - **Read from `pending`**: Returns the number of pending messages
+/// - **Write `{message, priority?, deliver_after_ms?}` to `send`**: Sends a
+///   message with a priority (higher first, default 0) and a delay
+/// - **Read from `pending`**: Returns the number of received messages
+///   waiting for their delay to pass
+/// - **Read from `queued`**: Returns the number of received messages not
+///   yet read, including those not yet due
+///
+/// With acknowledgements enabled, reads from root return a lease,
+/// `{id, attempt, message}`, and:
+///
+/// - **Write null to `ack/{id}`**: The message is done; drop it
+/// - **Write null to `nack/{id}`**: Deliver the message again now, or
+///   write `{deliver_after_ms}` to deliver it again after a delay
+/// - **Read from `leases`**: Outstanding leases, `{id: {attempt, message}}`
+/// - **Read from `dead_letters`**: Messages that ran out of attempts, if no
+///   dead-letter channel is set or it could not take them
 ///
 /// # Example
 ///
@@ -34,13 +72,75 @@ use tokio::sync::mpsc;
 /// ```
 pub struct ChannelStore {
     /// Sender half of the channel.
-    tx: Option<mpsc::Sender<Value>>,
+    tx: Option<mpsc::Sender<Message>>,
 
     /// Receiver half of the channel.
-    rx: Option<mpsc::Receiver<Value>>,
+    rx: Option<mpsc::Receiver<Message>>,
+
+    /// Received messages not yet read, with the order they arrived in.
+    buffer: Vec<(u64, Message)>,
+
+    /// Most messages taken off the channel into the buffer at once.
+    capacity: usize,
+
+    /// Messages received so far.
+    received: u64,
+
+    /// Lease tracking, if reads must be acknowledged.
+    acks: Option<Acks>,
+}
+
+/// A message in the channel, with its delivery options.
+struct Message {
+    value: Value,
+    priority: i64,
+    /// When the message may be delivered.
+    due: Instant,
+}
+
+/// Settings for at-least-once delivery.
+#[derive(Debug, Clone)]
+pub struct AckConfig {
+    /// How long a read message stays leased before it is delivered again.
+    pub visibility_timeout: Duration,
+    /// Deliveries of a message before it goes to the dead letters.
+    pub max_attempts: u32,
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        Self {
+            visibility_timeout: Duration::from_secs(30),
+            max_attempts: 5,
+        }
+    }
+}
 
-    /// Buffer for received messages (for sync interface).
-    buffer: Vec<Value>,
+/// A message read but not yet acknowledged.
+struct Lease {
+    message: Value,
+    attempt: u32,
+    expires: Instant,
+}
+
+impl Lease {
+    fn envelope(&self, id: u64) -> Value {
+        Value::Map(BTreeMap::from([
+            ("id".to_string(), Value::Integer(id as i64)),
+            ("attempt".to_string(), Value::Integer(self.attempt as i64)),
+            ("message".to_string(), self.message.clone()),
+        ]))
+    }
+}
+
+struct Acks {
+    config: AckConfig,
+    leases: BTreeMap<u64, Lease>,
+    next_id: u64,
+    /// Where messages go after their last attempt. Kept locally if unset or
+    /// if the channel fails to take them.
+    dead_letter_channel: Option<Box<ChannelStore>>,
+    dead_letters: Vec<Value>,
 }
 
 // Safety: ChannelStore is Send + Sync because mpsc::Sender and mpsc::Receiver are Send.
@@ -59,12 +159,18 @@ impl ChannelStore {
             tx: Some(tx),
             rx: None,
             buffer: Vec::new(),
+            capacity,
+            received: 0,
+            acks: None,
         };
 
         let receiver = Self {
             tx: None,
             rx: Some(rx),
             buffer: Vec::new(),
+            capacity,
+            received: 0,
+            acks: None,
         };
 
         (sender, receiver)
@@ -82,17 +188,49 @@ impl ChannelStore {
             tx: Some(tx1),
             rx: Some(rx2),
             buffer: Vec::new(),
+            capacity,
+            received: 0,
+            acks: None,
         };
 
         let store2 = Self {
             tx: Some(tx2),
             rx: Some(rx1),
             buffer: Vec::new(),
+            capacity,
+            received: 0,
+            acks: None,
         };
 
         (store1, store2)
     }
 
+    /// Require reads to be acknowledged, delivering unacknowledged
+    /// messages again.
+    pub fn with_acks(mut self, config: AckConfig) -> Self {
+        self.acks = Some(Acks {
+            config,
+            leases: BTreeMap::new(),
+            next_id: 0,
+            dead_letter_channel: None,
+            dead_letters: Vec::new(),
+        });
+        self
+    }
+
+    /// Send messages that run out of attempts to `channel`, as
+    /// `{message, attempts}`, instead of keeping them under `dead_letters`.
+    /// Messages the channel cannot take, such as when it is full, are still
+    /// kept under `dead_letters`.
+    ///
+    /// Has no effect unless acknowledgements are enabled.
+    pub fn with_dead_letter_channel(mut self, channel: ChannelStore) -> Self {
+        if let Some(acks) = &mut self.acks {
+            acks.dead_letter_channel = Some(Box::new(channel));
+        }
+        self
+    }
+
     /// Check if this store can send messages.
     pub fn can_send(&self) -> bool {
         self.tx.is_some()
@@ -103,17 +241,182 @@ impl ChannelStore {
         self.rx.is_some()
     }
 
-    /// Try to receive a message without blocking.
+    /// Move messages waiting in the channel into the buffer, until the
+    /// buffer holds the channel's capacity.
+    fn drain(&mut self) {
+        if let Some(ref mut rx) = self.rx {
+            while self.buffer.len() < self.capacity {
+                let Ok(message) = rx.try_recv() else {
+                    break;
+                };
+                self.buffer.push((self.received, message));
+                self.received += 1;
+            }
+        }
+    }
+
+    /// Try to receive the next due message without blocking.
     fn try_recv(&mut self) -> Option<Value> {
-        if !self.buffer.is_empty() {
-            return Some(self.buffer.remove(0));
+        self.drain();
+        let now = Instant::now();
+        let next = self
+            .buffer
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, m))| m.due <= now)
+            .min_by_key(|(_, (seq, m))| (Reverse(m.priority), m.due, *seq))
+            .map(|(i, _)| i)?;
+        Some(self.buffer.remove(next).1.value)
+    }
+
+    fn send(&mut self, message: Message) -> Result<(), Error> {
+        let Some(ref tx) = self.tx else {
+            return Err(Error::store(
+                "channel",
+                "write",
+                "this channel store cannot send",
+            ));
+        };
+        tx.try_send(message)
+            .map_err(|e| Error::store("channel", "write", e.to_string()))
+    }
+
+    /// Lease the next message: an expired lease first, oldest first, then
+    /// a new message.
+    fn lease(&mut self) -> Result<Option<Value>, Error> {
+        let now = Instant::now();
+        loop {
+            let acks = self.acks.as_mut().expect("lease requires acks");
+            let Some((&id, _)) = acks.leases.iter().find(|(_, l)| l.expires <= now) else {
+                break;
+            };
+            if acks.leases[&id].attempt >= acks.config.max_attempts {
+                acks.dead_letter(id);
+                continue;
+            }
+            let lease = acks.leases.get_mut(&id).unwrap();
+            lease.attempt += 1;
+            lease.expires = now + acks.config.visibility_timeout;
+            return Ok(Some(lease.envelope(id)));
         }
 
-        if let Some(ref mut rx) = self.rx {
-            rx.try_recv().ok()
+        let Some(message) = self.try_recv() else {
+            return Ok(None);
+        };
+        let acks = self.acks.as_mut().expect("lease requires acks");
+        let id = acks.next_id;
+        acks.next_id += 1;
+        let lease = Lease {
+            message,
+            attempt: 1,
+            expires: now + acks.config.visibility_timeout,
+        };
+        let envelope = lease.envelope(id);
+        acks.leases.insert(id, lease);
+        Ok(Some(envelope))
+    }
+
+    fn settle(&mut self, path: &Path, record: Record) -> Result<Path, Error> {
+        let Some(acks) = self.acks.as_mut() else {
+            return Err(Error::store(
+                "channel",
+                "ack",
+                "acknowledgements are not enabled on this channel store",
+            ));
+        };
+        let id = path[1].parse::<u64>().ok();
+        let Some(lease) = id.and_then(|id| acks.leases.get_mut(&id)) else {
+            return Err(Error::store(
+                "channel",
+                "ack",
+                format!("no outstanding lease {}", path[1]),
+            ));
+        };
+        if path[0] == "ack" {
+            acks.leases.remove(&id.unwrap());
         } else {
-            None
+            let options = record.into_value(&NoCodec)?;
+            lease.expires = due_after(delay(&options)?)?;
         }
+        Ok(path.clone())
+    }
+}
+
+/// The `deliver_after_ms` delay in a map of options, if any.
+fn delay(options: &Value) -> Result<Duration, Error> {
+    let Value::Map(map) = options else {
+        return Ok(Duration::ZERO);
+    };
+    match map.get("deliver_after_ms") {
+        None | Some(Value::Null) => Ok(Duration::ZERO),
+        Some(Value::Integer(ms)) if *ms >= 0 => Ok(Duration::from_millis(*ms as u64)),
+        Some(_) => Err(Error::store(
+            "channel",
+            "write",
+            "deliver_after_ms must be a non-negative integer",
+        )),
+    }
+}
+
+/// The instant `delay` from now, or an error if the delay is longer than
+/// [`MAX_DELAY`] or too far away to represent.
+fn due_after(delay: Duration) -> Result<Instant, Error> {
+    Some(delay)
+        .filter(|delay| *delay <= MAX_DELAY)
+        .and_then(|delay| Instant::now().checked_add(delay))
+        .ok_or_else(|| Error::store("channel", "write", "deliver_after_ms is too large"))
+}
+
+/// A message written to `send`: `{message, priority?, deliver_after_ms?}`.
+fn parse_send(value: Value) -> Result<Message, Error> {
+    let delay = delay(&value)?;
+    let Value::Map(mut map) = value else {
+        return Err(Error::store(
+            "channel",
+            "write",
+            "send requires {message, priority?, deliver_after_ms?}",
+        ));
+    };
+    let priority = match map.get("priority") {
+        None | Some(Value::Null) => 0,
+        Some(Value::Integer(p)) => *p,
+        Some(_) => {
+            return Err(Error::store(
+                "channel",
+                "write",
+                "priority must be an integer",
+            ))
+        }
+    };
+    let value = map
+        .remove("message")
+        .ok_or_else(|| Error::store("channel", "write", "send requires a 'message' field"))?;
+    Ok(Message {
+        value,
+        priority,
+        due: due_after(delay)?,
+    })
+}
+
+impl Acks {
+    /// Move lease `id`, out of attempts, to the dead-letter channel if it
+    /// takes it and to `dead_letters` otherwise. The lease is dropped only
+    /// once the message is kept.
+    fn dead_letter(&mut self, id: u64) {
+        let lease = &self.leases[&id];
+        let letter = Value::Map(BTreeMap::from([
+            ("message".to_string(), lease.message.clone()),
+            ("attempts".to_string(), Value::Integer(lease.attempt as i64)),
+        ]));
+        let sent = self.dead_letter_channel.as_mut().is_some_and(|channel| {
+            channel
+                .write(&Path::parse("").unwrap(), Record::parsed(letter.clone()))
+                .is_ok()
+        });
+        if !sent {
+            self.dead_letters.push(letter);
+        }
+        self.leases.remove(&id);
     }
 }
 
@@ -121,6 +424,7 @@ impl Reader for ChannelStore {
     fn read(&mut self, path: &Path) -> Result<Option<Record>, Error> {
         let path_str = path.to_string();
         match path_str.as_str() {
+            "" if self.acks.is_some() => Ok(self.lease()?.map(Record::parsed)),
             "" => {
                 // Read next message
                 match self.try_recv() {
@@ -129,10 +433,37 @@ impl Reader for ChannelStore {
                 }
             }
             "pending" => {
-                // Return count of buffered messages
+                // Count buffered messages still waiting on a delay
+                self.drain();
+                let now = Instant::now();
+                let count = self.buffer.iter().filter(|(_, m)| m.due > now).count();
+                Ok(Some(Record::parsed(Value::Integer(count as i64))))
+            }
+            "queued" => {
+                self.drain();
                 let count = self.buffer.len() as i64;
                 Ok(Some(Record::parsed(Value::Integer(count))))
             }
+            "leases" | "dead_letters" if self.acks.is_some() => {
+                let acks = self.acks.as_ref().unwrap();
+                let value = if path_str == "leases" {
+                    Value::Map(
+                        acks.leases
+                            .iter()
+                            .map(|(id, lease)| {
+                                let Value::Map(mut map) = lease.envelope(*id) else {
+                                    unreachable!("envelopes are maps");
+                                };
+                                map.remove("id");
+                                (id.to_string(), Value::Map(map))
+                            })
+                            .collect(),
+                    )
+                } else {
+                    Value::Array(acks.dead_letters.clone())
+                };
+                Ok(Some(Record::parsed(value)))
+            }
             "docs" => {
                 let mut docs = BTreeMap::new();
                 docs.insert("title".to_string(), Value::String("Channel Store".into()));
@@ -141,8 +472,13 @@ impl Reader for ChannelStore {
                     Value::String(
                         "A store backed by async channels for inter-Block communication.\n\n\
                         Read from root to receive the next message.\n\
-                        Write to root to send a message.\n\
-                        Read from `pending` to get the count of buffered messages."
+                        Write to root to send a message, or {message, priority, deliver_after_ms} \
+                        to `send` to send one with a priority (higher first) or a delay.\n\
+                        Read from `pending` to get the count of messages waiting on a delay, \
+                        and `queued` for the count of received messages not yet read.\n\n\
+                        With acknowledgements enabled, root reads return {id, attempt, message}; \
+                        write null to ack/{id} when done or nack/{id} to retry. Unacknowledged \
+                        messages are delivered again after the visibility timeout."
                             .into(),
                     ),
                 );
@@ -162,18 +498,21 @@ impl Writer for ChannelStore {
         let path_str = path.to_string();
         match path_str.as_str() {
             "" => {
-                if let Some(ref tx) = self.tx {
-                    let value = record.into_value(&structfs_core_store::NoCodec)?;
-                    tx.try_send(value)
-                        .map_err(|e| Error::store("channel", "write", e.to_string()))?;
-                    Ok(path.clone())
-                } else {
-                    Err(Error::store(
-                        "channel",
-                        "write",
-                        "this channel store cannot send",
-                    ))
-                }
+                let value = record.into_value(&NoCodec)?;
+                self.send(Message {
+                    value,
+                    priority: 0,
+                    due: Instant::now(),
+                })?;
+                Ok(path.clone())
+            }
+            "send" => {
+                let message = parse_send(record.into_value(&NoCodec)?)?;
+                self.send(message)?;
+                Ok(path.clone())
+            }
+            _ if path.len() == 2 && (path[0] == "ack" || path[0] == "nack") => {
+                self.settle(path, record)
             }
             _ => Err(Error::store(
                 "channel",
@@ -187,7 +526,6 @@ impl Writer for ChannelStore {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use structfs_core_store::NoCodec;
 
     fn path(s: &str) -> Path {
         Path::parse(s).unwrap()
@@ -282,6 +620,235 @@ mod tests {
         assert!(result.is_err());
     }
 
+    fn lease(receiver: &mut ChannelStore) -> Option<BTreeMap<String, Value>> {
+        match receiver
+            .read(&path(""))
+            .unwrap()?
+            .into_value(&NoCodec)
+            .unwrap()
+        {
+            Value::Map(map) => Some(map),
+            other => panic!("expected a lease, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn acked_messages_are_not_redelivered() {
+        let (mut sender, receiver) = ChannelStore::pair(10);
+        let mut receiver = receiver.with_acks(AckConfig::default());
+        for msg in ["a", "b"] {
+            sender
+                .write(&path(""), Record::parsed(Value::from(msg)))
+                .unwrap();
+        }
+
+        let first = lease(&mut receiver).unwrap();
+        assert_eq!(first["message"], Value::from("a"));
+        assert_eq!(first["attempt"], Value::Integer(1));
+        receiver
+            .write(&path("ack/0"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(receiver
+            .write(&path("ack/0"), Record::parsed(Value::Null))
+            .is_err());
+
+        // Nacked messages come back before new ones, counting the attempt
+        let second = lease(&mut receiver).unwrap();
+        assert_eq!(second["id"], Value::Integer(1));
+        receiver
+            .write(&path("nack/1"), Record::parsed(Value::Null))
+            .unwrap();
+        let again = lease(&mut receiver).unwrap();
+        assert_eq!(again["message"], Value::from("b"));
+        assert_eq!(again["attempt"], Value::Integer(2));
+
+        let leases = receiver.read(&path("leases")).unwrap().unwrap();
+        let Value::Map(leases) = leases.into_value(&NoCodec).unwrap() else {
+            panic!("expected a map");
+        };
+        assert_eq!(leases.keys().collect::<Vec<_>>(), ["1"]);
+        assert!(lease(&mut receiver).is_none());
+    }
+
+    #[test]
+    fn expired_leases_redeliver_then_dead_letter() {
+        let (mut sender, receiver) = ChannelStore::pair(10);
+        let (dead_tx, mut dead_rx) = ChannelStore::pair(10);
+        let config = AckConfig {
+            visibility_timeout: Duration::ZERO,
+            max_attempts: 2,
+        };
+        let mut receiver = receiver.with_acks(config).with_dead_letter_channel(dead_tx);
+        sender
+            .write(&path(""), Record::parsed(Value::from("job")))
+            .unwrap();
+
+        // Every lease expires at once, so each read delivers it again
+        assert_eq!(lease(&mut receiver).unwrap()["attempt"], Value::Integer(1));
+        assert_eq!(lease(&mut receiver).unwrap()["attempt"], Value::Integer(2));
+        assert!(lease(&mut receiver).is_none());
+
+        let letter = dead_rx.read(&path("")).unwrap().unwrap();
+        let Value::Map(letter) = letter.into_value(&NoCodec).unwrap() else {
+            panic!("expected a map");
+        };
+        assert_eq!(letter["message"], Value::from("job"));
+        assert_eq!(letter["attempts"], Value::Integer(2));
+    }
+
+    #[test]
+    fn full_dead_letter_channel_keeps_messages_locally() {
+        let (mut sender, receiver) = ChannelStore::pair(10);
+        let (mut dead_tx, mut dead_rx) = ChannelStore::pair(1);
+        dead_tx
+            .write(&path(""), Record::parsed(Value::from("earlier")))
+            .unwrap();
+        let config = AckConfig {
+            visibility_timeout: Duration::ZERO,
+            max_attempts: 1,
+        };
+        let mut receiver = receiver.with_acks(config).with_dead_letter_channel(dead_tx);
+        sender
+            .write(&path(""), Record::parsed(Value::from("job")))
+            .unwrap();
+
+        assert_eq!(lease(&mut receiver).unwrap()["attempt"], Value::Integer(1));
+        assert!(lease(&mut receiver).is_none());
+
+        let kept = receiver.read(&path("dead_letters")).unwrap().unwrap();
+        let Value::Array(kept) = kept.into_value(&NoCodec).unwrap() else {
+            panic!("expected an array");
+        };
+        assert_eq!(kept.len(), 1);
+        let Value::Map(letter) = &kept[0] else {
+            panic!("expected a map");
+        };
+        assert_eq!(letter["message"], Value::from("job"));
+        assert_eq!(recv(&mut dead_rx), Some(Value::from("earlier")));
+    }
+
+    fn send(sender: &mut ChannelStore, message: &str, priority: i64, delay_ms: i64) {
+        let options = Value::Map(BTreeMap::from([
+            ("message".to_string(), Value::from(message)),
+            ("priority".to_string(), Value::Integer(priority)),
+            ("deliver_after_ms".to_string(), Value::Integer(delay_ms)),
+        ]));
+        sender
+            .write(&path("send"), Record::parsed(options))
+            .unwrap();
+    }
+
+    fn recv(receiver: &mut ChannelStore) -> Option<Value> {
+        receiver
+            .read(&path(""))
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn higher_priority_first_then_in_order_sent() {
+        let (mut sender, mut receiver) = ChannelStore::pair(10);
+        send(&mut sender, "low", -1, 0);
+        send(&mut sender, "a", 0, 0);
+        sender
+            .write(&path(""), Record::parsed(Value::from("b")))
+            .unwrap();
+        send(&mut sender, "urgent", 5, 0);
+
+        let order: Vec<Value> = std::iter::from_fn(|| recv(&mut receiver)).collect();
+        assert_eq!(order, ["urgent", "a", "b", "low"].map(Value::from).to_vec());
+    }
+
+    #[test]
+    fn delayed_messages_wait_until_due() {
+        let (mut sender, mut receiver) = ChannelStore::pair(10);
+        send(&mut sender, "later", 9, 60_000);
+        send(&mut sender, "soon", 0, 20);
+        send(&mut sender, "now", 0, 0);
+
+        assert_eq!(recv(&mut receiver), Some(Value::from("now")));
+        assert_eq!(recv(&mut receiver), None);
+        let pending = receiver.read(&path("pending")).unwrap().unwrap();
+        assert_eq!(pending.into_value(&NoCodec).unwrap(), Value::Integer(2));
+
+        std::thread::sleep(Duration::from_millis(30));
+        let queued = receiver.read(&path("queued")).unwrap().unwrap();
+        assert_eq!(queued.into_value(&NoCodec).unwrap(), Value::Integer(2));
+        let pending = receiver.read(&path("pending")).unwrap().unwrap();
+        assert_eq!(pending.into_value(&NoCodec).unwrap(), Value::Integer(1));
+        assert_eq!(recv(&mut receiver), Some(Value::from("soon")));
+        assert_eq!(recv(&mut receiver), None);
+
+        assert!(sender
+            .write(&path("send"), Record::parsed(Value::from("bare")))
+            .is_err());
+    }
+
+    #[test]
+    fn receivers_buffer_at_most_the_channel_capacity() {
+        let (mut sender, mut receiver) = ChannelStore::pair(2);
+        send(&mut sender, "a", 0, 60_000);
+        send(&mut sender, "b", 0, 60_000);
+        assert_eq!(recv(&mut receiver), None);
+
+        // The buffer is full, so the channel is too once it refills
+        send(&mut sender, "c", 0, 0);
+        send(&mut sender, "d", 0, 0);
+        let full = sender.write(&path(""), Record::parsed(Value::from("e")));
+        assert!(full.is_err());
+        let queued = receiver.read(&path("queued")).unwrap().unwrap();
+        assert_eq!(queued.into_value(&NoCodec).unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn huge_delays_are_rejected() {
+        let (mut sender, receiver) = ChannelStore::pair(10);
+        let huge = |message: bool| {
+            let mut options =
+                BTreeMap::from([("deliver_after_ms".to_string(), Value::Integer(i64::MAX))]);
+            if message {
+                options.insert("message".to_string(), Value::from("x"));
+            }
+            Record::parsed(Value::Map(options))
+        };
+        assert!(sender.write(&path("send"), huge(true)).is_err());
+
+        let mut receiver = receiver.with_acks(AckConfig::default());
+        sender
+            .write(&path(""), Record::parsed(Value::from("retry")))
+            .unwrap();
+        lease(&mut receiver).unwrap();
+        assert!(receiver.write(&path("nack/0"), huge(false)).is_err());
+    }
+
+    #[test]
+    fn nack_with_delay() {
+        let (mut sender, receiver) = ChannelStore::pair(10);
+        let mut receiver = receiver.with_acks(AckConfig::default());
+        sender
+            .write(&path(""), Record::parsed(Value::from("retry")))
+            .unwrap();
+        lease(&mut receiver).unwrap();
+
+        let later = Value::Map(BTreeMap::from([(
+            "deliver_after_ms".to_string(),
+            Value::Integer(60_000),
+        )]));
+        receiver
+            .write(&path("nack/0"), Record::parsed(later))
+            .unwrap();
+        assert!(lease(&mut receiver).is_none());
+    }
+
+    #[test]
+    fn acks_need_acks_enabled() {
+        let (_, mut receiver) = ChannelStore::pair(10);
+        assert!(receiver
+            .write(&path("ack/0"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(receiver.read(&path("leases")).is_err());
+    }
+
     #[test]
     fn receiver_cannot_send() {
         let (_, mut receiver) = ChannelStore::pair(10);
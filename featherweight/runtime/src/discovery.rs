@@ -0,0 +1,487 @@
+//! Finding services exported by other runtimes.
+//!
+//! A [`DiscoveryStore`] keeps a registry of services in a backing store that
+//! every runtime mounts, such as a Redis-backed store or a remote store
+//! reached over the wire protocol. Runtimes register the exports they serve
+//! and Blocks resolve them by name, so no Block hardcodes where a service
+//! runs:
+//!
+//! ```text
+//! services/{name}/instances/{id}   {address, health, runtime, updated_ms}
+//! services/{name}/resolve          address of a healthy instance
+//! services                         every service and its instances
+//! ```
+//!
+//! Writing `{address}` (and optionally `health`, `"healthy"` or
+//! `"unhealthy"`) to an instance path registers it, stamped with the
+//! registering runtime and the time; writing it again is a heartbeat.
+//! Writing `null` deregisters it. An instance whose last heartbeat is older
+//! than the store's TTL reads as `"stale"`, and `resolve` only returns
+//! healthy instances, rotating between them.
+//!
+//! The whole registry is one value at `services` in the backing store, so
+//! any store that can hold a value works as a backend. Each change reads,
+//! modifies and writes that value back; runtimes racing to register can
+//! lose a registration, which the next heartbeat restores.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use structfs_core_store::{Error as StoreError, NoCodec, Path, Reader, Record, Value, Writer};
+
+/// How long an instance stays healthy without a heartbeat, by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// Whether an instance should receive traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// Serving.
+    Healthy,
+    /// Registered but reported as not serving.
+    Unhealthy,
+    /// No heartbeat within the TTL.
+    Stale,
+}
+
+impl Health {
+    fn as_str(self) -> &'static str {
+        match self {
+            Health::Healthy => "healthy",
+            Health::Unhealthy => "unhealthy",
+            Health::Stale => "stale",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "healthy" => Some(Health::Healthy),
+            "unhealthy" => Some(Health::Unhealthy),
+            "stale" => Some(Health::Stale),
+            _ => None,
+        }
+    }
+}
+
+/// A registered instance of a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    /// Where to reach the instance, such as a URL.
+    pub address: String,
+    /// Its health as last reported, or `Stale` once its heartbeat lapses.
+    pub health: Health,
+    /// The runtime that registered it.
+    pub runtime: String,
+    /// Milliseconds since the Unix epoch of its last heartbeat.
+    pub updated_ms: i64,
+}
+
+impl Instance {
+    fn to_value(&self) -> Value {
+        Value::Map(BTreeMap::from([
+            ("address".to_string(), Value::from(self.address.as_str())),
+            ("health".to_string(), Value::from(self.health.as_str())),
+            ("runtime".to_string(), Value::from(self.runtime.as_str())),
+            ("updated_ms".to_string(), Value::Integer(self.updated_ms)),
+        ]))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let field = |name: &str| match value.get(&Path::parse(name).ok()?) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        Some(Self {
+            address: field("address")?,
+            health: Health::parse(&field("health")?)?,
+            runtime: field("runtime").unwrap_or_default(),
+            updated_ms: match value.get(&Path::parse("updated_ms").ok()?) {
+                Some(Value::Integer(ms)) => *ms,
+                _ => 0,
+            },
+        })
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+fn error(operation: &'static str, message: impl Into<String>) -> StoreError {
+    StoreError::store("discovery", operation, message)
+}
+
+/// Registry of service instances across runtimes. See the
+/// [module docs](self).
+pub struct DiscoveryStore<S> {
+    backend: S,
+    key: Path,
+    runtime: String,
+    ttl: Duration,
+    /// Where each service's `resolve` rotation is up to
+    cursors: BTreeMap<String, usize>,
+}
+
+impl<S: Reader + Writer> DiscoveryStore<S> {
+    /// A registry kept at `services` in `backend`. Instances registered
+    /// through this store are stamped with `runtime`.
+    pub fn new(backend: S, runtime: impl Into<String>) -> Self {
+        Self {
+            backend,
+            key: Path::parse("services").unwrap(),
+            runtime: runtime.into(),
+            ttl: DEFAULT_TTL,
+            cursors: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the registry at `key` in the backend instead.
+    pub fn with_key(mut self, key: Path) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Treat instances without a heartbeat for `ttl` as stale.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The backing store.
+    pub fn backend(&self) -> &S {
+        &self.backend
+    }
+
+    /// Register instance `id` of `service`, or renew its heartbeat.
+    pub fn register(
+        &mut self,
+        service: &str,
+        id: &str,
+        address: impl Into<String>,
+        health: Health,
+    ) -> Result<(), StoreError> {
+        let instance = Instance {
+            address: address.into(),
+            health,
+            runtime: self.runtime.clone(),
+            updated_ms: now_ms(),
+        };
+        self.update(service, id, Some(instance.to_value()))
+    }
+
+    /// Remove instance `id` of `service`.
+    pub fn deregister(&mut self, service: &str, id: &str) -> Result<(), StoreError> {
+        self.update(service, id, None)
+    }
+
+    /// Every instance of `service`, with stale ones marked.
+    pub fn instances(&mut self, service: &str) -> Result<BTreeMap<String, Instance>, StoreError> {
+        let registry = self.load()?;
+        let Some(Value::Map(instances)) = registry.get(&Path::from_components(vec![
+            service.into(),
+            "instances".into(),
+        ])) else {
+            return Ok(BTreeMap::new());
+        };
+
+        let now = now_ms();
+        let ttl = self.ttl.as_millis() as i64;
+        Ok(instances
+            .iter()
+            .filter_map(|(id, value)| {
+                let mut instance = Instance::from_value(value)?;
+                if now.saturating_sub(instance.updated_ms) > ttl {
+                    instance.health = Health::Stale;
+                }
+                Some((id.clone(), instance))
+            })
+            .collect())
+    }
+
+    /// The address of a healthy instance of `service`, rotating between
+    /// them on each call.
+    pub fn resolve(&mut self, service: &str) -> Result<Option<String>, StoreError> {
+        let healthy: Vec<String> = self
+            .instances(service)?
+            .into_values()
+            .filter(|instance| instance.health == Health::Healthy)
+            .map(|instance| instance.address)
+            .collect();
+        if healthy.is_empty() {
+            return Ok(None);
+        }
+        let cursor = self.cursors.entry(service.to_string()).or_default();
+        let address = healthy[*cursor % healthy.len()].clone();
+        *cursor = cursor.wrapping_add(1);
+        Ok(Some(address))
+    }
+
+    fn load(&mut self) -> Result<Value, StoreError> {
+        match self.backend.read(&self.key)? {
+            Some(record) => record.into_value(&NoCodec),
+            None => Ok(Value::Map(BTreeMap::new())),
+        }
+    }
+
+    fn update(
+        &mut self,
+        service: &str,
+        id: &str,
+        instance: Option<Value>,
+    ) -> Result<(), StoreError> {
+        Path::try_from_components(vec![service.to_string(), id.to_string()])?;
+        let Value::Map(mut registry) = self.load()? else {
+            return Err(error("write", "The registry is not a map"));
+        };
+
+        let entry = registry
+            .entry(service.to_string())
+            .or_insert_with(|| Value::Map(BTreeMap::new()));
+        let Value::Map(entry) = entry else {
+            return Err(error(
+                "write",
+                format!("Service '{}' is not a map", service),
+            ));
+        };
+        let instances = entry
+            .entry("instances".to_string())
+            .or_insert_with(|| Value::Map(BTreeMap::new()));
+        let Value::Map(instances) = instances else {
+            return Err(error(
+                "write",
+                format!("Service '{}' has no instance map", service),
+            ));
+        };
+        match instance {
+            Some(instance) => {
+                instances.insert(id.to_string(), instance);
+            }
+            None => {
+                instances.remove(id);
+                if instances.is_empty() {
+                    registry.remove(service);
+                }
+            }
+        }
+
+        self.backend
+            .write(&self.key, Record::parsed(Value::Map(registry)))?;
+        Ok(())
+    }
+
+    /// Every service and its instances, as read from `services`.
+    fn registry(&mut self) -> Result<Value, StoreError> {
+        let Value::Map(registry) = self.load()? else {
+            return Ok(Value::Map(BTreeMap::new()));
+        };
+        let mut services = BTreeMap::new();
+        for service in registry.keys() {
+            let instances = self
+                .instances(service)?
+                .into_iter()
+                .map(|(id, instance)| (id, instance.to_value()))
+                .collect();
+            services.insert(
+                service.clone(),
+                Value::Map(BTreeMap::from([(
+                    "instances".to_string(),
+                    Value::Map(instances),
+                )])),
+            );
+        }
+        Ok(Value::Map(services))
+    }
+}
+
+impl<S: Reader + Writer> Reader for DiscoveryStore<S> {
+    fn read(&mut self, path: &Path) -> Result<Option<Record>, StoreError> {
+        if path.is_empty() || path[0] != "services" {
+            return Ok(None);
+        }
+        if path.len() == 3 && path[2] == "resolve" {
+            return Ok(self
+                .resolve(&path[1])?
+                .map(|a| Record::parsed(Value::from(a))));
+        }
+        let registry = self.registry()?;
+        Ok(registry
+            .get(&path.slice(1, path.len()))
+            .cloned()
+            .map(Record::parsed))
+    }
+}
+
+impl<S: Reader + Writer> Writer for DiscoveryStore<S> {
+    fn write(&mut self, path: &Path, record: Record) -> Result<Path, StoreError> {
+        if path.len() != 4 || path[0] != "services" || path[2] != "instances" {
+            return Err(error(
+                "write",
+                format!(
+                    "Register instances at services/{{name}}/instances/{{id}}, not '{}'",
+                    path
+                ),
+            ));
+        }
+        let (service, id) = (&path[1], &path[3]);
+
+        let value = record.into_value(&NoCodec)?;
+        if value.is_null() {
+            self.deregister(service, id)?;
+            return Ok(path.clone());
+        }
+        let address = match value.get(&Path::parse("address").unwrap()) {
+            Some(Value::String(address)) => address.clone(),
+            _ => return Err(error("write", "An instance needs a string 'address'")),
+        };
+        let health = match value.get(&Path::parse("health").unwrap()) {
+            None => Health::Healthy,
+            Some(Value::String(s)) if s != "stale" => {
+                Health::parse(s).ok_or_else(|| error("write", format!("Unknown health '{}'", s)))?
+            }
+            Some(other) => {
+                return Err(error(
+                    "write",
+                    format!(
+                        "health must be \"healthy\" or \"unhealthy\", not {:?}",
+                        other
+                    ),
+                ))
+            }
+        };
+        self.register(service, id, address, health)?;
+        Ok(path.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use structfs_core_store::path;
+
+    /// A backend shared by several runtimes' discovery stores.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Option<Value>>>);
+
+    impl Reader for Shared {
+        fn read(&mut self, _: &Path) -> Result<Option<Record>, StoreError> {
+            Ok(self.0.lock().unwrap().clone().map(Record::parsed))
+        }
+    }
+
+    impl Writer for Shared {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, StoreError> {
+            *self.0.lock().unwrap() = Some(data.into_value(&NoCodec)?);
+            Ok(to.clone())
+        }
+    }
+
+    fn read(store: &mut impl Reader, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    fn instance(address: &str) -> Record {
+        Record::parsed(Value::Map(BTreeMap::from([(
+            "address".to_string(),
+            Value::from(address),
+        )])))
+    }
+
+    #[test]
+    fn runtimes_share_a_registry() {
+        let backend = Shared::default();
+        let mut east = DiscoveryStore::new(backend.clone(), "east");
+        let mut west = DiscoveryStore::new(backend.clone(), "west");
+
+        east.write(&path!("services/db/instances/a"), instance("10.0.0.1:5432"))
+            .unwrap();
+        west.write(&path!("services/db/instances/b"), instance("10.0.1.1:5432"))
+            .unwrap();
+
+        let instances = east.instances("db").unwrap();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances["b"].runtime, "west");
+        assert_eq!(
+            read(&mut west, &path!("services/db/instances/a/address")),
+            Some(Value::from("10.0.0.1:5432"))
+        );
+
+        // Resolution rotates between healthy instances
+        let first = read(&mut east, &path!("services/db/resolve")).unwrap();
+        let second = read(&mut east, &path!("services/db/resolve")).unwrap();
+        assert_ne!(first, second);
+
+        west.write(
+            &path!("services/db/instances/b"),
+            Record::parsed(Value::Null),
+        )
+        .unwrap();
+        assert_eq!(
+            east.resolve("db").unwrap().as_deref(),
+            Some("10.0.0.1:5432")
+        );
+        assert_eq!(read(&mut east, &path!("services/cache/resolve")), None);
+    }
+
+    #[test]
+    fn unhealthy_and_stale_instances_are_not_resolved() {
+        let backend = Shared::default();
+        let mut store = DiscoveryStore::new(backend.clone(), "east");
+        store
+            .register("api", "down", "http://down", Health::Unhealthy)
+            .unwrap();
+        store
+            .register("api", "old", "http://old", Health::Healthy)
+            .unwrap();
+        assert_eq!(store.resolve("api").unwrap().as_deref(), Some("http://old"));
+
+        // Backdate the heartbeat past the TTL
+        let mut registry = read(&mut backend.clone(), &path!("services")).unwrap();
+        let Value::Map(services) = &mut registry else {
+            panic!("expected a map");
+        };
+        let Some(Value::Map(api)) = services.get_mut("api") else {
+            panic!("expected the api service");
+        };
+        let Some(Value::Map(instances)) = api.get_mut("instances") else {
+            panic!("expected instances");
+        };
+        let Some(Value::Map(old)) = instances.get_mut("old") else {
+            panic!("expected the old instance");
+        };
+        old.insert("updated_ms".to_string(), Value::Integer(0));
+        backend
+            .clone()
+            .write(&path!("services"), Record::parsed(registry))
+            .unwrap();
+
+        assert_eq!(store.resolve("api").unwrap(), None);
+        assert_eq!(
+            read(&mut store, &path!("services/api/instances/old/health")),
+            Some(Value::from("stale"))
+        );
+    }
+
+    #[test]
+    fn malformed_registrations_are_rejected() {
+        let mut store = DiscoveryStore::new(Shared::default(), "east");
+        assert!(store.write(&path!("services/db"), instance("x")).is_err());
+        assert!(store
+            .write(
+                &path!("services/db/instances/a"),
+                Record::parsed(Value::from("x"))
+            )
+            .is_err());
+        let stale = Record::parsed(Value::Map(BTreeMap::from([
+            ("address".to_string(), Value::from("x")),
+            ("health".to_string(), Value::from("stale")),
+        ])));
+        assert!(store
+            .write(&path!("services/db/instances/a"), stale)
+            .is_err());
+    }
+}
@@ -37,6 +37,14 @@ pub enum RuntimeError {
     /// The export was not found.
     #[error("export not found: {0}")]
     ExportNotFound(String),
+
+    /// A Block manifest conflicts with the dependency graph.
+    #[error("invalid manifest: {0}")]
+    InvalidManifest(String),
+
+    /// A Block trapped or returned an error.
+    #[error("block failed: {0}")]
+    BlockFailed(Box<crate::report::TrapReport>),
 }
 
 /// Result type alias for runtime operations.
@@ -0,0 +1,504 @@
+//! Dependencies between Blocks and ordered startup.
+//!
+//! A [`BlockManifest`] names a Block, the exports it provides and the
+//! exports of other Blocks it needs:
+//!
+//! ```json
+//! {"name": "api", "exports": ["requests"], "needs": ["db/queries", "cache/entries"]}
+//! ```
+//!
+//! [`Runtime::spawn_manifest`](crate::Runtime::spawn_manifest) registers
+//! the Block at once but holds it back until every export it needs has been
+//! registered with
+//! [`Runtime::register_export`](crate::Runtime::register_export), so Blocks
+//! start in dependency order whatever order they are spawned in. An export
+//! counts as healthy from its registration until its Block is removed.
+//! Spawning a manifest that would close a dependency cycle, reuses a name or
+//! needs an export its provider does not declare fails.
+//!
+//! Mount [`Runtime::graph_store`](crate::Runtime::graph_store)
+//! (conventionally at `runtime`) and read `runtime/graph` to visualize the
+//! graph:
+//!
+//! ```text
+//! blocks/{name}   {id, state, exports, needs, waiting_on}
+//! edges           [{from, to, export}], from provider to dependent
+//! order           names in startup order
+//! ```
+//!
+//! `state` is `"waiting"` until the Block is released to run, then
+//! `"started"`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use structfs_core_store::{Error as StoreError, Path, Reader, Record, Value, Writer};
+use tokio::sync::oneshot;
+
+use crate::block::BlockId;
+use crate::error::{Result, RuntimeError};
+
+/// An export of another Block that a Block needs, written `block/export`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Dependency {
+    /// Name of the providing Block.
+    pub block: String,
+    /// Name of the export.
+    pub export: String,
+}
+
+impl Dependency {
+    /// The export `export` of the Block named `block`.
+    pub fn new(block: impl Into<String>, export: impl Into<String>) -> Self {
+        Self {
+            block: block.into(),
+            export: export.into(),
+        }
+    }
+}
+
+impl TryFrom<String> for Dependency {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, String> {
+        match s.split_once('/') {
+            Some((block, export)) if !block.is_empty() && !export.is_empty() => {
+                Ok(Self::new(block, export))
+            }
+            _ => Err(format!("dependency {:?} is not block/export", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.block, self.export)
+    }
+}
+
+/// A Block's name, what it exports and what it needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct BlockManifest {
+    /// Name other manifests refer to the Block by.
+    pub name: String,
+    /// Exports the Block registers.
+    pub exports: Vec<String>,
+    /// Exports of other Blocks that must be healthy before it starts.
+    pub needs: Vec<Dependency>,
+}
+
+impl BlockManifest {
+    /// A manifest with no exports or dependencies.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Declare an export.
+    pub fn export(mut self, name: impl Into<String>) -> Self {
+        self.exports.push(name.into());
+        self
+    }
+
+    /// Need `export` of the Block named `block`.
+    pub fn needs(mut self, block: impl Into<String>, export: impl Into<String>) -> Self {
+        self.needs.push(Dependency::new(block, export));
+        self
+    }
+}
+
+/// A Block in the graph.
+struct Node {
+    manifest: BlockManifest,
+    id: BlockId,
+    /// Exports registered so far.
+    healthy: BTreeSet<String>,
+    /// Releases the Block; `None` once it has started.
+    start: Option<oneshot::Sender<()>>,
+}
+
+/// Blocks spawned from manifests, by name.
+#[derive(Default)]
+pub(crate) struct DependencyGraph {
+    nodes: BTreeMap<String, Node>,
+}
+
+/// The dependency graph, shared by the runtime and its graph stores.
+pub(crate) type SharedGraph = Arc<Mutex<DependencyGraph>>;
+
+impl DependencyGraph {
+    /// Add a Block. Returns a receiver that completes when its dependencies
+    /// are healthy, or `None` if they already are.
+    pub(crate) fn insert(
+        &mut self,
+        manifest: BlockManifest,
+        id: BlockId,
+    ) -> Result<Option<oneshot::Receiver<()>>> {
+        let name = manifest.name.clone();
+        if name.is_empty() || self.nodes.contains_key(&name) {
+            return Err(RuntimeError::InvalidManifest(format!(
+                "block name {:?} is empty or taken",
+                name
+            )));
+        }
+        let (start, waiting) = oneshot::channel();
+        self.nodes.insert(
+            name.clone(),
+            Node {
+                manifest,
+                id,
+                healthy: BTreeSet::new(),
+                start: Some(start),
+            },
+        );
+
+        if let Err(e) = self.validate() {
+            self.nodes.remove(&name);
+            return Err(e);
+        }
+        Ok(match self.release(&name) {
+            true => None,
+            false => Some(waiting),
+        })
+    }
+
+    /// Check that every declared provider declares the exports needed of it
+    /// and that there is no cycle.
+    fn validate(&self) -> Result<()> {
+        for node in self.nodes.values() {
+            for need in &node.manifest.needs {
+                if let Some(provider) = self.nodes.get(&need.block) {
+                    if !provider.manifest.exports.contains(&need.export) {
+                        return Err(RuntimeError::InvalidManifest(format!(
+                            "{} needs {}, which {} does not export",
+                            node.manifest.name, need, need.block
+                        )));
+                    }
+                }
+            }
+        }
+        self.order().map(|_| ())
+    }
+
+    /// Block names in startup order: each after the declared Blocks it
+    /// needs, ties broken by name. Fails on a cycle.
+    pub(crate) fn order(&self) -> Result<Vec<String>> {
+        let mut pending: BTreeMap<&str, usize> = self
+            .nodes
+            .iter()
+            .map(|(name, node)| {
+                let declared = node
+                    .providers()
+                    .into_iter()
+                    .filter(|p| self.nodes.contains_key(*p))
+                    .count();
+                (name.as_str(), declared)
+            })
+            .collect();
+        let mut ready: BTreeSet<&str> = pending
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(name, _)| *name)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(name) = ready.pop_first() {
+            pending.remove(name);
+            order.push(name.to_string());
+            for (dependent, node) in &self.nodes {
+                if node.providers().contains(name) {
+                    if let Some(count) = pending.get_mut(dependent.as_str()) {
+                        *count -= 1;
+                        if *count == 0 {
+                            ready.insert(dependent.as_str());
+                        }
+                    }
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            let cycle: Vec<&str> = pending.keys().copied().collect();
+            return Err(RuntimeError::InvalidManifest(format!(
+                "dependency cycle among {}",
+                cycle.join(", ")
+            )));
+        }
+        Ok(order)
+    }
+
+    /// Mark an export of Block `id` healthy and release the Blocks that were
+    /// only waiting for it.
+    pub(crate) fn mark_healthy(&mut self, id: BlockId, export: &str) {
+        let Some(node) = self.nodes.values_mut().find(|node| node.id == id) else {
+            return;
+        };
+        node.healthy.insert(export.to_string());
+
+        let waiting: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.start.is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in waiting {
+            self.release(&name);
+        }
+    }
+
+    /// Drop Block `id`. Its exports stop counting as healthy; a Block still
+    /// waiting on it keeps waiting for a replacement.
+    pub(crate) fn remove(&mut self, id: BlockId) {
+        self.nodes.retain(|_, node| node.id != id);
+    }
+
+    /// The dependencies of `name` that are not healthy yet.
+    fn waiting_on(&self, name: &str) -> Vec<&Dependency> {
+        let Some(node) = self.nodes.get(name) else {
+            return Vec::new();
+        };
+        node.manifest
+            .needs
+            .iter()
+            .filter(|need| {
+                !self
+                    .nodes
+                    .get(&need.block)
+                    .is_some_and(|provider| provider.healthy.contains(&need.export))
+            })
+            .collect()
+    }
+
+    /// Start `name` if nothing it needs is missing. Returns whether it has
+    /// started.
+    fn release(&mut self, name: &str) -> bool {
+        if !self.waiting_on(name).is_empty() {
+            return false;
+        }
+        if let Some(start) = self.nodes.get_mut(name).and_then(|node| node.start.take()) {
+            // The Block's task may be gone if it was removed meanwhile
+            let _ = start.send(());
+        }
+        true
+    }
+
+    /// The graph as `{blocks, edges, order}`.
+    pub(crate) fn to_value(&self) -> Value {
+        let strings =
+            |items: Vec<String>| Value::Array(items.into_iter().map(Value::String).collect());
+
+        let mut blocks = BTreeMap::new();
+        let mut edges = Vec::new();
+        for (name, node) in &self.nodes {
+            let state = match node.start {
+                Some(_) => "waiting",
+                None => "started",
+            };
+            let waiting_on = self
+                .waiting_on(name)
+                .iter()
+                .map(|d| d.to_string())
+                .collect();
+            blocks.insert(
+                name.clone(),
+                Value::Map(BTreeMap::from([
+                    ("id".to_string(), Value::String(node.id.to_string())),
+                    ("state".to_string(), Value::from(state)),
+                    (
+                        "exports".to_string(),
+                        strings(node.manifest.exports.clone()),
+                    ),
+                    (
+                        "needs".to_string(),
+                        strings(node.manifest.needs.iter().map(|d| d.to_string()).collect()),
+                    ),
+                    ("waiting_on".to_string(), strings(waiting_on)),
+                ])),
+            );
+            for need in &node.manifest.needs {
+                edges.push(Value::Map(BTreeMap::from([
+                    ("from".to_string(), Value::String(need.block.clone())),
+                    ("to".to_string(), Value::String(name.clone())),
+                    ("export".to_string(), Value::String(need.export.clone())),
+                ])));
+            }
+        }
+
+        Value::Map(BTreeMap::from([
+            ("blocks".to_string(), Value::Map(blocks)),
+            ("edges".to_string(), Value::Array(edges)),
+            (
+                "order".to_string(),
+                strings(self.order().unwrap_or_default()),
+            ),
+        ]))
+    }
+}
+
+impl Node {
+    /// Names of the Blocks this one needs.
+    fn providers(&self) -> BTreeSet<&str> {
+        self.manifest
+            .needs
+            .iter()
+            .map(|need| need.block.as_str())
+            .collect()
+    }
+}
+
+/// Read-only store serving the dependency graph at `graph`.
+///
+/// See the [module documentation](self) for its shape.
+#[derive(Clone)]
+pub struct GraphStore {
+    graph: SharedGraph,
+}
+
+impl GraphStore {
+    pub(crate) fn new(graph: SharedGraph) -> Self {
+        Self { graph }
+    }
+}
+
+impl Reader for GraphStore {
+    fn read(&mut self, path: &Path) -> std::result::Result<Option<Record>, StoreError> {
+        if path.is_empty() || path[0] != "graph" {
+            return Ok(None);
+        }
+        let graph = self.graph.lock().unwrap().to_value();
+        Ok(graph
+            .get(&path.slice(1, path.len()))
+            .map(|value| Record::parsed(value.clone())))
+    }
+}
+
+impl Writer for GraphStore {
+    fn write(&mut self, _path: &Path, _record: Record) -> std::result::Result<Path, StoreError> {
+        Err(StoreError::store(
+            "runtime",
+            "write",
+            "the dependency graph is read-only",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, NoCodec};
+
+    fn graph(manifests: &[BlockManifest]) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for manifest in manifests {
+            graph.insert(manifest.clone(), BlockId::new()).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn manifests_parse_dependencies() {
+        let manifest: BlockManifest = serde_json::from_str(
+            r#"{"name": "api", "exports": ["requests"], "needs": ["db/queries"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest,
+            BlockManifest::new("api")
+                .export("requests")
+                .needs("db", "queries")
+        );
+        assert!(
+            serde_json::from_str::<BlockManifest>(r#"{"name": "a", "needs": ["db"]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn order_follows_dependencies() {
+        let graph = graph(&[
+            BlockManifest::new("api")
+                .needs("db", "queries")
+                .needs("cache", "entries"),
+            BlockManifest::new("cache")
+                .export("entries")
+                .needs("db", "queries"),
+            BlockManifest::new("db").export("queries"),
+            BlockManifest::new("metrics"),
+        ]);
+        assert_eq!(graph.order().unwrap(), ["db", "cache", "api", "metrics"]);
+    }
+
+    #[test]
+    fn invalid_manifests_are_rejected() {
+        let mut graph = graph(&[
+            BlockManifest::new("a").export("x").needs("b", "y"),
+            BlockManifest::new("c").export("z"),
+        ]);
+
+        let cycle = BlockManifest::new("b").export("y").needs("a", "x");
+        let err = graph.insert(cycle, BlockId::new()).unwrap_err();
+        assert!(err.to_string().contains("cycle among a, b"));
+
+        let unexported = BlockManifest::new("d").needs("c", "missing");
+        assert!(graph.insert(unexported, BlockId::new()).is_err());
+        assert!(graph
+            .insert(BlockManifest::new("c"), BlockId::new())
+            .is_err());
+
+        // Rejected manifests leave the graph as it was
+        assert_eq!(graph.order().unwrap(), ["a", "c"]);
+    }
+
+    #[test]
+    fn exports_release_waiting_blocks() {
+        let mut graph = DependencyGraph::default();
+        let db = BlockId::new();
+        assert!(graph
+            .insert(BlockManifest::new("db").export("queries"), db)
+            .unwrap()
+            .is_none());
+        let mut api = graph
+            .insert(
+                BlockManifest::new("api").needs("db", "queries"),
+                BlockId::new(),
+            )
+            .unwrap()
+            .unwrap();
+        assert!(api.try_recv().is_err());
+
+        let mut store = GraphStore::new(Arc::new(Mutex::new(graph)));
+        let read = |store: &mut GraphStore, path: &Path| {
+            store
+                .read(path)
+                .unwrap()
+                .unwrap()
+                .into_value(&NoCodec)
+                .unwrap()
+        };
+        assert_eq!(
+            read(&mut store, &path!("graph/blocks/api/waiting_on")),
+            Value::Array(vec![Value::from("db/queries")])
+        );
+
+        store.graph.lock().unwrap().mark_healthy(db, "queries");
+        assert!(api.try_recv().is_ok());
+        assert_eq!(
+            read(&mut store, &path!("graph/blocks/api/state")),
+            Value::from("started")
+        );
+        let edge = Value::Map(BTreeMap::from([
+            ("from".to_string(), Value::from("db")),
+            ("to".to_string(), Value::from("api")),
+            ("export".to_string(), Value::from("queries")),
+        ]));
+        assert_eq!(
+            read(&mut store, &path!("graph/edges")),
+            Value::Array(vec![edge])
+        );
+        assert!(store.read(&path!("other")).unwrap().is_none());
+    }
+}
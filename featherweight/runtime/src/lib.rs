@@ -42,6 +42,126 @@
 //! The Runtime coordinates these exports, allowing Block A's export to be
 //! mounted into Block B's root store.
 //!
+//! ### Output Streaming
+//!
+//! The host can watch what a Block writes without polling. Every spawned
+//! Block's root is an [`ObservedStore`], and [`Runtime::on_output`] registers
+//! a callback for writes under a prefix:
+//!
+//! ```ignore
+//! let handle = runtime.spawn(block, root).await?;
+//! runtime.on_output(handle.id, path!("output"), |path, record| {
+//!     println!("{} written", path);
+//! })?;
+//! ```
+//!
+//! ### Quotas
+//!
+//! Blocks frequently share a backing store. [`RuntimeConfig::quota`] sets a
+//! [`BlockQuota`] of operations and bytes per second for each Block, which is
+//! either throttled or failed with `RateLimited` when it exceeds it.
+//!
+//! ### Failure Reports
+//!
+//! When a Block traps or returns an error, the runtime records a
+//! [`TrapReport`] with the message, WASM backtrace, fuel consumed and the
+//! Block's last store operations. Read it with [`Runtime::last_error`], or
+//! mount [`Runtime::blocks_store`] (conventionally at `blocks`) and read
+//! `blocks/{id}/last_error`.
+//!
+//! ### Filesystem Sandboxes
+//!
+//! With [`RuntimeConfig::fs_sandbox`] set, [`Runtime::spawn_sandboxed`]
+//! mounts a private directory at `fs/` in the Block's root, confined so the
+//! Block cannot reach files outside it. [`Runtime::remove`] deletes the
+//! directory.
+//!
+//! ### Dependencies
+//!
+//! Spawn a Block with [`Runtime::spawn_manifest`] and a [`BlockManifest`]
+//! naming the exports it provides and the exports of other Blocks it needs.
+//! The runtime holds it back until those exports are registered, rejects
+//! manifests that would form a cycle, and serves the graph and startup order
+//! from [`Runtime::graph_store`], conventionally mounted at `runtime` and
+//! read at `runtime/graph`. See the [`graph`] module.
+//!
+//! ### Network Policy
+//!
+//! [`RuntimeConfig::network`] limits the hosts and schemes Blocks reach
+//! through HTTP mounts. Build a Block's root as a
+//! [`MountStore`](structfs_core_store::mount_store::MountStore) over
+//! [`Runtime::network_factory`], or over a [`NetworkFactory`] with a policy of
+//! its own, and every HTTP store mounted in it carries the policy, which the
+//! HTTP executor checks before each request and redirect.
+//!
+//! ### Handle Leases
+//!
+//! Each spawned Block runs under a [`Lease`](structfs_core_store::Lease)
+//! that the runtime releases when the Block stops, whether it returned,
+//! failed or panicked. Stores built on
+//! [`HandleBroker`](structfs_core_store::HandleBroker), such as the
+//! filesystem and HTTP stores, then close the handles the Block left open.
+//!
+//! ### Access Control
+//!
+//! [`RuntimeConfig::policy`] holds a [`Policy`] of allow and deny rules on
+//! Block ids, path prefixes and operations, checked on every root-store
+//! access. Load it from a JSON document with [`SharedPolicy::from_file`] and
+//! [`SharedPolicy::reload`] it while Blocks run; denied operations fail with
+//! the rule that denied them, and [`SharedPolicy::explain`] answers the same
+//! question without performing the operation.
+//!
+//! ### Warm Pools
+//!
+//! Request-scoped WASM Blocks should be compiled once with
+//! [`Runtime::prepare_wasm`] and spawned from the resulting
+//! [`PreparedWasmBlock`], which only instantiates per spawn. Setting
+//! [`RuntimeConfig::warm_pool`] also reserves instance slots up front so
+//! spawns skip memory allocation.
+//!
+//! ### Service Discovery
+//!
+//! Runtimes that share a backing store, such as a Redis-backed store or a
+//! remote store, can each mount a [`DiscoveryStore`] over it. A runtime
+//! registers the exports it serves at `services/{name}/instances/{id}` and
+//! Blocks read `services/{name}/resolve` for the address of a healthy
+//! instance, instead of hardcoding where a service runs.
+//!
+//! ### Request/Response Calls
+//!
+//! A service Block exports an [`RpcStore`]. Callers write a request to
+//! `requests`, which returns `requests/{id}`, and read
+//! `requests/{id}/response/wait` for the answer; the service claims requests
+//! from `next` and writes each response back. [`RpcClient`] makes a call in
+//! one step, with a timeout, and every call carries the caller's trace id as
+//! its correlation id.
+//!
+//! ### Tracing
+//!
+//! Run an external request inside [`trace::request_context`] to trace it
+//! across Blocks. Every operation on a Block's root store and on a mounted
+//! export becomes a `tracing` span carrying the W3C trace and span ids, so a
+//! request that touches several Blocks can be followed end to end and
+//! exported to OpenTelemetry with a `tracing-opentelemetry` layer.
+//!
+//! ### Record and Replay
+//!
+//! [`Runtime::spawn_recorded`] writes every read result and write of a
+//! Block to an [`IoRecorder`]'s trace file. [`Runtime::spawn_replayed`]
+//! runs a Block against an [`IoReplayer`] of such a trace instead of its
+//! root store, answering reads with the recorded results and failing at the
+//! first operation that differs, to reproduce timing-dependent bugs and to
+//! use traces as golden tests.
+//!
+//! ### Cancellation
+//!
+//! Blocks are never trapped to stop them. [`Runtime::cancel`], a write to
+//! `{id}/cancel` on the [`BlocksStore`], or
+//! [`RuntimeConfig::block_deadline`] sets the Block's [`Cancellation`],
+//! which native Blocks poll with [`BlockContext::should_cancel`] and WASM
+//! Blocks with the WIT `should-cancel` function, stopping at a point of
+//! their choosing.
+//!
 //! ## Example: Two Blocks Communicating
 //!
 //! ```ignore
@@ -152,15 +272,40 @@
 //! These limitations will be addressed as the implementation matures.
 
 pub mod block;
+pub mod cancel;
 pub mod channel;
+pub mod discovery;
 pub mod error;
+pub mod graph;
+pub mod network;
+pub mod output;
+pub mod policy;
+pub mod quota;
+pub mod replay;
+pub mod report;
+pub mod rpc;
 pub mod runtime;
+pub mod sandbox;
+pub mod trace;
+pub mod warm_pool;
 pub mod wasm_block;
 
 pub use block::{
     Block, BlockContext, BlockHandle, BlockId, BlockState, ErasedStore, ExportedStore,
 };
-pub use channel::ChannelStore;
+pub use cancel::Cancellation;
+pub use channel::{AckConfig, ChannelStore};
+pub use discovery::DiscoveryStore;
 pub use error::{Result, RuntimeError};
+pub use graph::{BlockManifest, Dependency, GraphStore};
+pub use network::NetworkFactory;
+pub use output::{ObservedStore, OutputCallback, OutputId, OutputListeners};
+pub use policy::{Decision, Effect, Policy, PolicyRule, SharedPolicy};
+pub use quota::{BlockQuota, QuotaMode};
+pub use replay::{IoEvent, IoRecorder, IoReplayer};
+pub use report::{BlocksStore, FailureKind, StoreOpKind, StoreOpRecord, TrapReport};
+pub use rpc::{RpcClient, RpcRequest, RpcStore};
 pub use runtime::{Runtime, RuntimeConfig, SharedStoreAdapter};
-pub use wasm_block::WasmBlock;
+pub use sandbox::{FsSandbox, SandboxedRoot};
+pub use warm_pool::WarmPoolConfig;
+pub use wasm_block::{PreparedWasmBlock, WasmBlock};
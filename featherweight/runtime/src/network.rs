@@ -0,0 +1,185 @@
+//! Outbound network policy for Blocks' HTTP mounts.
+//!
+//! A [`NetworkFactory`] wraps the [`StoreFactory`] behind a Block's
+//! [`MountStore`] and sets an [`HttpNetwork`] on every HTTP mount it creates,
+//! replacing any the mount config asked for. The HTTP executor checks each
+//! request and redirect against it, so a compromised Block can mount HTTP
+//! stores and write requests, but cannot reach hosts or schemes outside its
+//! policy.
+//!
+//! ```ignore
+//! let mut runtime = Runtime::new(RuntimeConfig {
+//!     network: Some(HttpNetwork {
+//!         allow_hosts: vec!["*.example.com".into()],
+//!         schemes: vec!["https".into()],
+//!         ..Default::default()
+//!     }),
+//!     ..Default::default()
+//! });
+//!
+//! // HTTP mounts made in this root only reach https://*.example.com
+//! let root = MountStore::new(runtime.network_factory(factory));
+//! let handle = runtime.spawn(block, root).await?;
+//!
+//! // A Block with a policy of its own
+//! let root = MountStore::new(NetworkFactory::new(factory, billing_only));
+//! ```
+//!
+//! `structfs` mounts to a URL outside the policy are refused when mounted.
+//! Plugin mounts are passed through unchanged, so factories that build
+//! network stores of their own must enforce the policy themselves.
+
+use structfs_core_store::mount_store::{HttpNetwork, HttpOptions, MountConfig, StoreFactory};
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::Error as StoreError;
+
+/// A [`StoreFactory`] that confines the HTTP mounts it creates to an
+/// [`HttpNetwork`].
+pub struct NetworkFactory<F> {
+    inner: F,
+    network: HttpNetwork,
+}
+
+impl<F: StoreFactory> NetworkFactory<F> {
+    /// Create mounts with `inner`, confining HTTP mounts to `network`.
+    pub fn new(inner: F, network: HttpNetwork) -> Self {
+        Self { inner, network }
+    }
+
+    /// The policy applied to HTTP mounts.
+    pub fn network(&self) -> &HttpNetwork {
+        &self.network
+    }
+
+    fn confine(&self, options: &HttpOptions) -> HttpOptions {
+        HttpOptions {
+            network: Some(self.network.clone()),
+            ..options.clone()
+        }
+    }
+
+    fn check(&self, url: &str) -> Result<(), StoreError> {
+        self.network
+            .check(url)
+            .map(drop)
+            .map_err(|message| StoreError::store("runtime", "mount", message))
+    }
+}
+
+impl<F: StoreFactory> StoreFactory for NetworkFactory<F> {
+    fn create(&self, config: &MountConfig) -> Result<StoreBox, StoreError> {
+        let confined = match config {
+            MountConfig::Http { url, options } => {
+                self.check(url)?;
+                MountConfig::Http {
+                    url: url.clone(),
+                    options: self.confine(options),
+                }
+            }
+            MountConfig::HttpBroker { options } => MountConfig::HttpBroker {
+                options: self.confine(options),
+            },
+            MountConfig::AsyncHttpBroker { options } => MountConfig::AsyncHttpBroker {
+                options: self.confine(options),
+            },
+            MountConfig::Structfs { url } => {
+                self.check(url)?;
+                return self.inner.create(config);
+            }
+            _ => return self.inner.create(config),
+        };
+        self.inner.create(&confined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection_literals::btree;
+    use std::sync::{Arc, Mutex};
+    use structfs_core_store::mount_store::MountStore;
+    use structfs_core_store::overlay_store::OverlayStore;
+    use structfs_core_store::{path, Value, Writer};
+
+    /// Records the configs it is asked to create and returns empty stores.
+    #[derive(Clone, Default)]
+    struct Recording(Arc<Mutex<Vec<MountConfig>>>);
+
+    impl StoreFactory for Recording {
+        fn create(&self, config: &MountConfig) -> Result<StoreBox, StoreError> {
+            self.0.lock().unwrap().push(config.clone());
+            Ok(Box::new(OverlayStore::new()))
+        }
+    }
+
+    fn policy() -> HttpNetwork {
+        HttpNetwork {
+            allow_hosts: vec!["api.example.com".to_string()],
+            schemes: vec!["https".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn http_mounts_get_the_policy() {
+        let recording = Recording::default();
+        let mut root = MountStore::new(NetworkFactory::new(recording.clone(), policy()));
+
+        // The Block asks for a wider policy than it is given
+        let options = HttpOptions {
+            network: Some(HttpNetwork::default()),
+            timeout_ms: Some(1_000),
+            ..Default::default()
+        };
+        let wide = Value::Map(btree! {
+            "type".to_string() => Value::from("httpbroker"),
+            "options".to_string() => options.to_value(),
+        });
+        root.write(&path!("ctx/mounts/api"), wide.into()).unwrap();
+        let memory = Value::Map(btree! {"type".to_string() => Value::from("memory")});
+        root.write(&path!("ctx/mounts/data"), memory.into())
+            .unwrap();
+
+        let created = recording.0.lock().unwrap().clone();
+        assert_eq!(
+            created,
+            vec![
+                MountConfig::HttpBroker {
+                    options: HttpOptions {
+                        network: Some(policy()),
+                        timeout_ms: Some(1_000),
+                        ..Default::default()
+                    },
+                },
+                MountConfig::Memory,
+            ]
+        );
+    }
+
+    #[test]
+    fn mounts_outside_the_policy_are_refused() {
+        let recording = Recording::default();
+        let factory = NetworkFactory::new(recording.clone(), policy());
+
+        let err = factory
+            .create(&MountConfig::Http {
+                url: "https://evil.example.net".to_string(),
+                options: HttpOptions::default(),
+            })
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("not allowed"));
+        assert!(factory
+            .create(&MountConfig::Structfs {
+                url: "http://api.example.com".to_string(),
+            })
+            .is_err());
+        assert!(factory
+            .create(&MountConfig::Http {
+                url: "https://api.example.com/v1".to_string(),
+                options: HttpOptions::default(),
+            })
+            .is_ok());
+        assert_eq!(recording.0.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,498 @@
+//! Streaming Block output to the host.
+//!
+//! Every Block spawned by the [`Runtime`](crate::Runtime) sees its root store
+//! through an [`ObservedStore`]. When the Block writes under a prefix the host
+//! registered with [`Runtime::on_output`](crate::Runtime::on_output), the
+//! host's callback runs with the written path and record, so the host does
+//! not need to poll the Block's output subtree.
+
+use std::sync::{Arc, Mutex};
+
+use structfs_core_store::{
+    Deleter, Error as StoreError, Lease, OpContext, Path, Reader, Record, Value, WeakLease, Writer,
+};
+
+use crate::block::BlockId;
+use crate::policy::SharedPolicy;
+use crate::quota::{record_size, BlockQuota, QuotaMeter};
+use crate::replay::{IoEvent, IoRecorder, IoReplayer};
+use crate::report::StoreOpKind;
+use crate::trace::traced;
+
+/// Host callback for Block output.
+///
+/// Runs on the Block's task after each successful write, so it should
+/// return quickly.
+pub type OutputCallback = Arc<dyn Fn(&Path, &Record) + Send + Sync>;
+
+/// Identifies a registered output callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputId(u64);
+
+#[derive(Default)]
+struct ListenerTable {
+    next_id: u64,
+    listeners: Vec<(OutputId, Path, OutputCallback)>,
+}
+
+/// Output callbacks registered for one Block.
+///
+/// Clones share the same callbacks.
+#[derive(Clone, Default)]
+pub struct OutputListeners {
+    table: Arc<Mutex<ListenerTable>>,
+}
+
+impl OutputListeners {
+    /// Create an empty set of callbacks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` for writes at or under `prefix`.
+    pub fn add(&self, prefix: Path, callback: OutputCallback) -> OutputId {
+        let mut table = self.table.lock().unwrap();
+        let id = OutputId(table.next_id);
+        table.next_id += 1;
+        table.listeners.push((id, prefix, callback));
+        id
+    }
+
+    /// Remove a callback. Returns false if it was not registered.
+    pub fn remove(&self, id: OutputId) -> bool {
+        let mut table = self.table.lock().unwrap();
+        let before = table.listeners.len();
+        table.listeners.retain(|(listener, _, _)| *listener != id);
+        table.listeners.len() != before
+    }
+
+    /// Callbacks whose prefix covers `path`.
+    fn matching(&self, path: &Path) -> Vec<OutputCallback> {
+        let table = self.table.lock().unwrap();
+        table
+            .listeners
+            .iter()
+            .filter(|(_, prefix, _)| path.has_prefix(prefix))
+            .map(|(_, _, callback)| callback.clone())
+            .collect()
+    }
+}
+
+/// A Block's root store, reporting writes to the host.
+///
+/// Reads and writes go to the wrapped store unchanged if the Block's
+/// [policy](crate::policy) allows them, metered against the Block's
+/// [`BlockQuota`] if it has one. After a successful write, every
+/// callback registered for a prefix of the written path runs. Inside a
+/// [trace](crate::trace), each operation runs in its own span. With a
+/// [lease](structfs_core_store::lease), handles the Block opens are tied to
+/// it and closed once the runtime releases it. Operations can also be
+/// [recorded or replayed](crate::replay).
+pub struct ObservedStore<S> {
+    inner: S,
+    listeners: OutputListeners,
+    meter: Option<QuotaMeter>,
+    label: Arc<str>,
+    policy: Option<(BlockId, SharedPolicy)>,
+    lease: Option<WeakLease>,
+    io: Option<BlockIo>,
+}
+
+/// Where a Block's operations are recorded to or replayed from.
+pub(crate) enum BlockIo {
+    Record(IoRecorder),
+    Replay(IoReplayer),
+}
+
+impl<S> ObservedStore<S> {
+    /// Wrap `inner`, reporting writes to `listeners`.
+    pub fn new(inner: S, listeners: OutputListeners) -> Self {
+        Self {
+            inner,
+            listeners,
+            meter: None,
+            label: Arc::from("block"),
+            policy: None,
+            lease: None,
+            io: None,
+        }
+    }
+
+    /// Check operations of `block` against `policy`.
+    pub fn with_policy(mut self, block: BlockId, policy: SharedPolicy) -> Self {
+        self.policy = Some((block, policy));
+        self
+    }
+
+    fn check_policy(&self, op: StoreOpKind, path: &Path) -> Result<(), StoreError> {
+        match &self.policy {
+            Some((block, policy)) => policy.check(*block, op, path),
+            None => Ok(()),
+        }
+    }
+
+    /// Run operations under `lease`, without keeping it alive.
+    pub fn with_lease(mut self, lease: &Lease) -> Self {
+        self.lease = Some(lease.downgrade());
+        self
+    }
+
+    /// Name the store's spans after `label`, such as the Block id.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Arc::from(label.into());
+        self
+    }
+
+    /// Append every operation and its outcome to `recorder`.
+    pub fn with_recorder(mut self, recorder: IoRecorder) -> Self {
+        self.io = Some(BlockIo::Record(recorder));
+        self
+    }
+
+    /// Answer operations from `replayer` instead of the wrapped store,
+    /// which is left untouched. Output callbacks still run for replayed
+    /// writes.
+    pub fn with_replayer(mut self, replayer: IoReplayer) -> Self {
+        self.io = Some(BlockIo::Replay(replayer));
+        self
+    }
+
+    pub(crate) fn with_io(mut self, io: Option<BlockIo>) -> Self {
+        self.io = io;
+        self
+    }
+
+    /// Append `event` to the trace, if recording. A trace that cannot be
+    /// written is logged rather than failing the Block.
+    fn record(&self, event: impl FnOnce() -> Result<IoEvent, StoreError>) {
+        let Some(BlockIo::Record(recorder)) = &self.io else {
+            return;
+        };
+        if let Err(e) = event().and_then(|event| recorder.record(&event)) {
+            tracing::warn!(store = &*self.label, "cannot record store operation: {}", e);
+        }
+    }
+
+    /// Meter operations against `quota`.
+    pub fn with_quota(mut self, quota: BlockQuota) -> Self {
+        self.meter = (!quota.is_unlimited()).then(|| QuotaMeter::new(quota));
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The wrapped store, mutably. Writes made here are not reported.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Run `f` in a context carrying `lease`, if there is one.
+fn leased<R>(lease: Option<WeakLease>, f: impl FnOnce() -> R) -> R {
+    match lease {
+        Some(lease) => OpContext {
+            lease: Some(lease),
+            ..OpContext::default()
+        }
+        .scope(f),
+        None => f(),
+    }
+}
+
+impl<S: Reader> Reader for ObservedStore<S> {
+    fn read(&mut self, path: &Path) -> Result<Option<Record>, StoreError> {
+        if let Some(BlockIo::Replay(replayer)) = &self.io {
+            return replayer.read(path);
+        }
+        let label = self.label.clone();
+        let result = leased(self.lease.clone(), || {
+            traced(&label, "read", path, || self.read_metered(path))
+        });
+        self.record(|| Ok(IoEvent::read(path, &result)));
+        result
+    }
+}
+
+impl<S: Reader> ObservedStore<S> {
+    fn read_metered(&mut self, path: &Path) -> Result<Option<Record>, StoreError> {
+        self.check_policy(StoreOpKind::Read, path)?;
+        let Some(meter) = &mut self.meter else {
+            return self.inner.read(path);
+        };
+        meter.acquire()?;
+        let record = self.inner.read(path)?;
+        meter.charge(record.as_ref().map_or(0, record_size));
+        Ok(record)
+    }
+}
+
+impl<S: Writer> Writer for ObservedStore<S> {
+    fn write(&mut self, path: &Path, record: Record) -> Result<Path, StoreError> {
+        self.write_or_delete(path, record, false)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Deletes with the wrapped store's [`Deleter`] if it has one. Policy,
+/// quotas, output callbacks and traces see a delete as a write of null.
+impl<S: Writer> Deleter for ObservedStore<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), StoreError> {
+        self.write_or_delete(path, Record::parsed(Value::Null), true)
+            .map(|_| ())
+    }
+}
+
+impl<S: Writer> ObservedStore<S> {
+    /// Write `record`, or delete the path if `delete` is set.
+    fn write_or_delete(
+        &mut self,
+        path: &Path,
+        record: Record,
+        delete: bool,
+    ) -> Result<Path, StoreError> {
+        match &self.io {
+            Some(BlockIo::Replay(replayer)) => {
+                let written = replayer.write(path, record.clone())?;
+                for callback in self.listeners.matching(path) {
+                    callback(path, &record);
+                }
+                Ok(written)
+            }
+            Some(BlockIo::Record(_)) => {
+                let recorded = record.clone();
+                let result = self.write_traced(path, record, delete);
+                self.record(|| IoEvent::write(path, recorded, &result));
+                result
+            }
+            None => self.write_traced(path, record, delete),
+        }
+    }
+
+    fn write_traced(
+        &mut self,
+        path: &Path,
+        record: Record,
+        delete: bool,
+    ) -> Result<Path, StoreError> {
+        let label = self.label.clone();
+        let op = if delete { "delete" } else { "write" };
+        leased(self.lease.clone(), || {
+            traced(&label, op, path, || {
+                self.write_observed(path, record, delete)
+            })
+        })
+    }
+
+    fn write_observed(
+        &mut self,
+        path: &Path,
+        record: Record,
+        delete: bool,
+    ) -> Result<Path, StoreError> {
+        self.check_policy(StoreOpKind::Write, path)?;
+        if let Some(meter) = &mut self.meter {
+            meter.acquire()?;
+            meter.charge(record_size(&record));
+        }
+
+        let callbacks = self.listeners.matching(path);
+        if callbacks.is_empty() {
+            return self.write_inner(path, record, delete);
+        }
+
+        let written = self.write_inner(path, record.clone(), delete)?;
+        for callback in callbacks {
+            callback(path, &record);
+        }
+        Ok(written)
+    }
+
+    fn write_inner(
+        &mut self,
+        path: &Path,
+        record: Record,
+        delete: bool,
+    ) -> Result<Path, StoreError> {
+        match self.inner.as_deleter() {
+            Some(deleter) if delete => deleter.delete(path).map(|()| path.clone()),
+            _ => self.inner.write(path, record),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, NoCodec, Value};
+
+    struct NullStore;
+
+    impl Writer for NullStore {
+        fn write(&mut self, path: &Path, _record: Record) -> Result<Path, StoreError> {
+            Ok(path.clone())
+        }
+    }
+
+    type Seen = Arc<Mutex<Vec<(String, Value)>>>;
+
+    fn recorder() -> (OutputCallback, Seen) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let callback: OutputCallback = Arc::new(move |path: &Path, record: &Record| {
+            let value = record.clone().into_value(&NoCodec).unwrap();
+            sink.lock().unwrap().push((path.to_string(), value));
+        });
+        (callback, seen)
+    }
+
+    #[test]
+    fn callbacks_fire_for_writes_under_prefix() {
+        let listeners = OutputListeners::new();
+        let (callback, seen) = recorder();
+        listeners.add(path!("output"), callback);
+
+        let mut store = ObservedStore::new(NullStore, listeners);
+        store
+            .write(&path!("output/result"), Record::parsed(Value::from(1)))
+            .unwrap();
+        store
+            .write(&path!("scratch"), Record::parsed(Value::from(2)))
+            .unwrap();
+        store
+            .write(&path!("output"), Record::parsed(Value::from(3)))
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("output/result".to_string(), Value::from(1)),
+                ("output".to_string(), Value::from(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_callbacks_stop_firing() {
+        let listeners = OutputListeners::new();
+        let (callback, seen) = recorder();
+        let id = listeners.add(path!(""), callback);
+
+        let mut store = ObservedStore::new(NullStore, listeners.clone());
+        store
+            .write(&path!("a"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(listeners.remove(id));
+        assert!(!listeners.remove(id));
+        store
+            .write(&path!("b"), Record::parsed(Value::Null))
+            .unwrap();
+
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn quota_limits_reads_and_writes() {
+        struct EchoStore;
+        impl Reader for EchoStore {
+            fn read(&mut self, _path: &Path) -> Result<Option<Record>, StoreError> {
+                Ok(Some(Record::parsed(Value::Null)))
+            }
+        }
+        impl Writer for EchoStore {
+            fn write(&mut self, path: &Path, _record: Record) -> Result<Path, StoreError> {
+                Ok(path.clone())
+            }
+        }
+
+        let mut store =
+            ObservedStore::new(EchoStore, OutputListeners::new()).with_quota(BlockQuota {
+                ops_per_sec: Some(2),
+                mode: crate::quota::QuotaMode::Fail,
+                ..Default::default()
+            });
+        store.read(&path!("a")).unwrap();
+        store
+            .write(&path!("a"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(matches!(
+            store.read(&path!("a")),
+            Err(StoreError::RateLimited { .. })
+        ));
+    }
+
+    #[test]
+    fn deletes_reach_the_wrapped_deleter() {
+        use std::collections::BTreeMap;
+        use structfs_core_store::Store;
+
+        #[derive(Default)]
+        struct MapStore(BTreeMap<Path, Value>);
+        impl Reader for MapStore {
+            fn read(&mut self, path: &Path) -> Result<Option<Record>, StoreError> {
+                Ok(self.0.get(path).cloned().map(Record::parsed))
+            }
+        }
+        impl Writer for MapStore {
+            fn write(&mut self, path: &Path, record: Record) -> Result<Path, StoreError> {
+                self.0.insert(path.clone(), record.into_value(&NoCodec)?);
+                Ok(path.clone())
+            }
+            fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+                Some(self)
+            }
+        }
+        impl Deleter for MapStore {
+            fn delete(&mut self, path: &Path) -> Result<(), StoreError> {
+                self.0.retain(|p, _| !p.has_prefix(path));
+                Ok(())
+            }
+        }
+
+        let listeners = OutputListeners::new();
+        let (callback, seen) = recorder();
+        listeners.add(path!("output"), callback);
+        let mut store = ObservedStore::new(MapStore::default(), listeners);
+        store
+            .write(&path!("output/a"), Record::parsed(Value::from(1)))
+            .unwrap();
+        Store::delete(&mut store, &path!("output/a")).unwrap();
+
+        // Deleted rather than overwritten with null
+        assert!(store.read(&path!("output/a")).unwrap().is_none());
+        assert_eq!(
+            seen.lock().unwrap()[1],
+            ("output/a".to_string(), Value::Null)
+        );
+    }
+
+    #[test]
+    fn failed_writes_are_not_reported() {
+        struct FailingStore;
+        impl Writer for FailingStore {
+            fn write(&mut self, _path: &Path, _record: Record) -> Result<Path, StoreError> {
+                Err(StoreError::store("test", "write", "failed"))
+            }
+        }
+
+        let listeners = OutputListeners::new();
+        let (callback, seen) = recorder();
+        listeners.add(path!(""), callback);
+
+        let mut store = ObservedStore::new(FailingStore, listeners);
+        assert!(store
+            .write(&path!("a"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(seen.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+//! Access-control policy for Block root stores.
+//!
+//! A [`Policy`] is an ordered list of allow and deny rules, each matching a
+//! Block id, a path prefix and the operations it covers. Every read and
+//! write on a Block's root store is checked against the policy in
+//! [`RuntimeConfig::policy`](crate::RuntimeConfig::policy): the first rule
+//! that matches decides, and the policy's default applies when none does.
+//!
+//! Policies are usually loaded from a document:
+//!
+//! ```json
+//! {
+//!   "default": "deny",
+//!   "rules": [
+//!     {"effect": "deny", "path": "secrets"},
+//!     {"effect": "allow", "ops": ["read"]},
+//!     {"effect": "allow", "path": "output", "ops": ["write"]}
+//!   ]
+//! }
+//! ```
+//!
+//! A rule without `block` applies to every Block, one without `path` to
+//! every path and one without `ops` to reads and writes. `block` is the
+//! Block's UUID or its [path component](crate::BlockId::path_component).
+//!
+//! The runtime holds the policy in a [`SharedPolicy`], so it can be
+//! replaced or reloaded from its file while Blocks run. A denied operation
+//! fails with a `policy` error whose detail is the [`Decision`], naming the
+//! rule that denied it; [`SharedPolicy::explain`] gives the same answer
+//! without performing the operation.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use serde::Deserialize;
+use structfs_core_store::{Error as StoreError, Path, Value};
+
+use crate::block::BlockId;
+use crate::report::StoreOpKind;
+
+/// Whether a rule allows or denies the operations it matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl std::fmt::Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Allow => write!(f, "allow"),
+            Effect::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// One allow or deny rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRule {
+    /// What happens to matching operations.
+    pub effect: Effect,
+    /// The Block the rule applies to, or `None` for every Block.
+    pub block: Option<String>,
+    /// The path prefix the rule applies to. The root matches every path.
+    pub path: Path,
+    /// The operations the rule applies to. Empty means all of them.
+    pub ops: Vec<StoreOpKind>,
+}
+
+impl PolicyRule {
+    /// A rule matching every operation of every Block.
+    pub fn new(effect: Effect) -> Self {
+        Self {
+            effect,
+            block: None,
+            path: Path::parse("").unwrap(),
+            ops: Vec::new(),
+        }
+    }
+
+    /// Allow matching operations.
+    pub fn allow() -> Self {
+        Self::new(Effect::Allow)
+    }
+
+    /// Deny matching operations.
+    pub fn deny() -> Self {
+        Self::new(Effect::Deny)
+    }
+
+    /// Only match operations of `block`.
+    pub fn block(mut self, block: BlockId) -> Self {
+        self.block = Some(block.to_string());
+        self
+    }
+
+    /// Only match paths at or under `prefix`.
+    pub fn path(mut self, prefix: Path) -> Self {
+        self.path = prefix;
+        self
+    }
+
+    /// Only match operations of `kind`. May be called more than once.
+    pub fn op(mut self, kind: StoreOpKind) -> Self {
+        self.ops.push(kind);
+        self
+    }
+
+    /// Whether the rule covers `op` by `block` on `path`.
+    pub fn matches(&self, block: BlockId, op: StoreOpKind, path: &Path) -> bool {
+        let block_matches = self.block.as_deref().is_none_or(|b| {
+            b == "*" || b.eq_ignore_ascii_case(&block.to_string()) || b == block.path_component()
+        });
+        block_matches
+            && path.has_prefix(&self.path)
+            && (self.ops.is_empty() || self.ops.contains(&op))
+    }
+}
+
+impl std::fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} block {}",
+            self.effect,
+            self.block.as_deref().unwrap_or("*")
+        )?;
+        write!(f, " path /{}", self.path)?;
+        if !self.ops.is_empty() {
+            let ops: Vec<String> = self.ops.iter().map(ToString::to_string).collect();
+            write!(f, " ops {}", ops.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of checking an operation against a [`Policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether the operation may go ahead.
+    pub allowed: bool,
+    /// Index of the rule that decided, or `None` if the default did.
+    pub rule: Option<usize>,
+    /// The deciding rule, or a note that the default applied.
+    pub reason: String,
+}
+
+impl Decision {
+    /// `{allowed, rule, reason}`, with `rule` null for the default.
+    pub fn to_value(&self) -> Value {
+        Value::Map(BTreeMap::from([
+            ("allowed".to_string(), Value::Bool(self.allowed)),
+            (
+                "rule".to_string(),
+                self.rule.map_or(Value::Null, |i| Value::Integer(i as i64)),
+            ),
+            ("reason".to_string(), Value::String(self.reason.clone())),
+        ]))
+    }
+}
+
+/// An ordered list of rules with a default effect.
+///
+/// The default policy allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Effect when no rule matches.
+    pub default: Effect,
+    /// Rules, checked in order.
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyDocument {
+    #[serde(default)]
+    default: Effect,
+    #[serde(default)]
+    rules: Vec<RuleDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDocument {
+    effect: Effect,
+    block: Option<String>,
+    path: Option<String>,
+    #[serde(default)]
+    ops: Vec<String>,
+}
+
+impl Policy {
+    /// A policy with no rules and the given default.
+    pub fn new(default: Effect) -> Self {
+        Self {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Append a rule.
+    pub fn rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Parse a policy document. See the [module docs](self).
+    pub fn from_value(value: Value) -> Result<Self, StoreError> {
+        let document: PolicyDocument = structfs_serde_store::from_value(value)?;
+        let rules = document
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let ops = rule
+                    .ops
+                    .iter()
+                    .map(|op| match op.as_str() {
+                        "read" => Ok(StoreOpKind::Read),
+                        "write" => Ok(StoreOpKind::Write),
+                        other => Err(StoreError::store(
+                            "policy",
+                            "load",
+                            format!("rule {}: unknown operation '{}'", i, other),
+                        )),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(PolicyRule {
+                    effect: rule.effect,
+                    block: rule.block,
+                    path: Path::parse(rule.path.as_deref().unwrap_or(""))?,
+                    ops,
+                })
+            })
+            .collect::<Result<_, StoreError>>()?;
+        Ok(Self {
+            default: document.default,
+            rules,
+        })
+    }
+
+    /// Parse a policy document from JSON.
+    pub fn from_json(json: &str) -> Result<Self, StoreError> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| {
+            StoreError::store("policy", "load", format!("invalid policy JSON: {}", e))
+        })?;
+        Self::from_value(structfs_serde_store::json_to_value(value))
+    }
+
+    /// Check `op` by `block` on `path`.
+    pub fn evaluate(&self, block: BlockId, op: StoreOpKind, path: &Path) -> Decision {
+        match self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(block, op, path))
+        {
+            Some((i, rule)) => Decision {
+                allowed: rule.effect == Effect::Allow,
+                rule: Some(i),
+                reason: format!("rule {}: {}", i, rule),
+            },
+            None => Decision {
+                allowed: self.default == Effect::Allow,
+                rule: None,
+                reason: format!("no rule matched; default is {}", self.default),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PolicySlot {
+    policy: Policy,
+    source: Option<PathBuf>,
+}
+
+/// A [`Policy`] shared by the runtime and every Block's root store.
+///
+/// Clones share the same policy. Replacing or reloading it takes effect on
+/// the next operation of every Block.
+#[derive(Debug, Clone, Default)]
+pub struct SharedPolicy {
+    slot: Arc<RwLock<PolicySlot>>,
+}
+
+impl SharedPolicy {
+    /// Share `policy`.
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            slot: Arc::new(RwLock::new(PolicySlot {
+                policy,
+                source: None,
+            })),
+        }
+    }
+
+    /// Load a JSON policy document from `path`, remembering it for
+    /// [`reload`](Self::reload).
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path = path.into();
+        let policy = read_policy_file(&path)?;
+        Ok(Self {
+            slot: Arc::new(RwLock::new(PolicySlot {
+                policy,
+                source: Some(path),
+            })),
+        })
+    }
+
+    /// Replace the policy.
+    pub fn replace(&self, policy: Policy) {
+        self.slot.write().unwrap().policy = policy;
+    }
+
+    /// Read the policy file again. On error the current policy stays in
+    /// force. Fails if the policy did not come from a file.
+    pub fn reload(&self) -> Result<(), StoreError> {
+        let source = self.slot.read().unwrap().source.clone().ok_or_else(|| {
+            StoreError::store("policy", "reload", "policy was not loaded from a file")
+        })?;
+        let policy = read_policy_file(&source)?;
+        self.replace(policy);
+        Ok(())
+    }
+
+    /// A copy of the policy in force.
+    pub fn get(&self) -> Policy {
+        self.slot.read().unwrap().policy.clone()
+    }
+
+    /// Check `op` by `block` on `path` without performing it.
+    pub fn explain(&self, block: BlockId, op: StoreOpKind, path: &Path) -> Decision {
+        self.slot.read().unwrap().policy.evaluate(block, op, path)
+    }
+
+    /// Fail with the [`Decision`] as detail if the policy denies `op`.
+    pub(crate) fn check(
+        &self,
+        block: BlockId,
+        op: StoreOpKind,
+        path: &Path,
+    ) -> Result<(), StoreError> {
+        let decision = self.explain(block, op, path);
+        if decision.allowed {
+            return Ok(());
+        }
+        let operation = match op {
+            StoreOpKind::Read => "read",
+            StoreOpKind::Write => "write",
+        };
+        Err(StoreError::store_with_detail(
+            "policy",
+            operation,
+            format!("{} of /{} denied by {}", operation, path, decision.reason),
+            decision.to_value(),
+        ))
+    }
+}
+
+fn read_policy_file(path: &std::path::Path) -> Result<Policy, StoreError> {
+    let json = std::fs::read_to_string(path).map_err(|e| {
+        StoreError::store(
+            "policy",
+            "load",
+            format!("cannot read {}: {}", path.display(), e),
+        )
+    })?;
+    Policy::from_json(&json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn sample() -> Policy {
+        Policy::from_json(
+            r#"{
+                "default": "deny",
+                "rules": [
+                    {"effect": "deny", "path": "secrets"},
+                    {"effect": "allow", "ops": ["read"]},
+                    {"effect": "allow", "path": "output", "ops": ["write"]}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let policy = sample();
+        let block = BlockId::new();
+
+        let denied = policy.evaluate(block, StoreOpKind::Read, &path!("secrets/key"));
+        assert!(!denied.allowed);
+        assert_eq!(denied.rule, Some(0));
+        assert!(denied.reason.contains("path /secrets"));
+
+        assert!(
+            policy
+                .evaluate(block, StoreOpKind::Read, &path!("data"))
+                .allowed
+        );
+        assert!(
+            policy
+                .evaluate(block, StoreOpKind::Write, &path!("output/x"))
+                .allowed
+        );
+
+        let fallback = policy.evaluate(block, StoreOpKind::Write, &path!("data"));
+        assert!(!fallback.allowed);
+        assert_eq!(fallback.rule, None);
+    }
+
+    #[test]
+    fn rules_match_block_ids() {
+        let (a, b) = (BlockId::new(), BlockId::new());
+        let policy = Policy::new(Effect::Allow)
+            .rule(PolicyRule::deny().block(a).op(StoreOpKind::Write))
+            .rule(PolicyRule {
+                block: Some(b.path_component()),
+                ..PolicyRule::deny().path(path!("private"))
+            });
+
+        assert!(!policy.evaluate(a, StoreOpKind::Write, &path!("x")).allowed);
+        assert!(policy.evaluate(a, StoreOpKind::Read, &path!("x")).allowed);
+        assert!(
+            !policy
+                .evaluate(b, StoreOpKind::Read, &path!("private/y"))
+                .allowed
+        );
+        assert!(policy.evaluate(b, StoreOpKind::Write, &path!("x")).allowed);
+    }
+
+    #[test]
+    fn bad_documents_are_rejected() {
+        assert!(Policy::from_json(r#"{"rules": [{"effect": "maybe"}]}"#).is_err());
+        assert!(
+            Policy::from_json(r#"{"rules": [{"effect": "allow", "ops": ["delete"]}]}"#).is_err()
+        );
+        assert!(Policy::from_json(r#"{"rules": [{"effect": "allow", "path": "a-b"}]}"#).is_err());
+        assert_eq!(Policy::from_json("{}").unwrap(), Policy::default());
+    }
+
+    #[test]
+    fn denial_carries_the_decision() {
+        let shared = SharedPolicy::new(sample());
+        let err = shared
+            .check(BlockId::new(), StoreOpKind::Read, &path!("secrets"))
+            .unwrap_err();
+        let Some(Value::Map(detail)) = err.detail() else {
+            panic!("expected a decision");
+        };
+        assert_eq!(detail["allowed"], Value::Bool(false));
+        assert_eq!(detail["rule"], Value::Integer(0));
+    }
+
+    #[test]
+    fn reloads_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("policy.json");
+        std::fs::write(&file, r#"{"default": "allow"}"#).unwrap();
+
+        let shared = SharedPolicy::from_file(&file).unwrap();
+        let block = BlockId::new();
+        assert!(
+            shared
+                .explain(block, StoreOpKind::Write, &path!("x"))
+                .allowed
+        );
+
+        std::fs::write(&file, r#"{"default": "deny"}"#).unwrap();
+        shared.reload().unwrap();
+        assert!(
+            !shared
+                .explain(block, StoreOpKind::Write, &path!("x"))
+                .allowed
+        );
+
+        std::fs::write(&file, "not json").unwrap();
+        assert!(shared.reload().is_err());
+        assert_eq!(shared.get().default, Effect::Deny);
+
+        assert!(SharedPolicy::new(Policy::default()).reload().is_err());
+    }
+}
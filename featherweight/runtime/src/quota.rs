@@ -0,0 +1,257 @@
+//! Per-Block store-operation quotas.
+//!
+//! Blocks often share a backing store. A [`BlockQuota`] in the
+//! [`RuntimeConfig`](crate::RuntimeConfig) caps how many operations and bytes
+//! per second each Block may push through its root store, so one chatty Block
+//! cannot starve the rest.
+//!
+//! Quotas are token buckets holding one second's worth of budget, so a Block
+//! may burst up to its per-second rate after being idle. Bytes are counted
+//! after an operation completes; a large read may overdraw the byte budget,
+//! in which case later operations wait until it recovers.
+
+use std::time::{Duration, Instant};
+
+use structfs_core_store::{Error as StoreError, Record, Value};
+
+/// What happens when a Block exceeds its quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuotaMode {
+    /// Block the operation until budget is available.
+    #[default]
+    Throttle,
+    /// Fail the operation with [`StoreError::RateLimited`].
+    Fail,
+}
+
+/// Store-operation limits applied to each Block's root store.
+///
+/// The default is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockQuota {
+    /// Maximum reads and writes per second.
+    pub ops_per_sec: Option<u32>,
+
+    /// Maximum bytes read and written per second.
+    pub bytes_per_sec: Option<u64>,
+
+    /// Whether to wait or fail when the quota is exceeded.
+    pub mode: QuotaMode,
+}
+
+impl BlockQuota {
+    /// Whether this quota limits anything.
+    pub fn is_unlimited(&self) -> bool {
+        self.ops_per_sec.is_none() && self.bytes_per_sec.is_none()
+    }
+}
+
+/// A token bucket refilled at `rate` per second, holding at most `rate`.
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        Self { rate, tokens: rate }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+    }
+
+    /// Time until the bucket holds `needed` tokens.
+    fn wait_for(&self, needed: f64) -> Duration {
+        if self.tokens >= needed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) / self.rate)
+        }
+    }
+}
+
+/// Meters one Block's store operations against its [`BlockQuota`].
+#[derive(Debug)]
+pub(crate) struct QuotaMeter {
+    mode: QuotaMode,
+    ops: Option<Bucket>,
+    bytes: Option<Bucket>,
+    last: Instant,
+}
+
+impl QuotaMeter {
+    pub(crate) fn new(quota: BlockQuota) -> Self {
+        Self {
+            mode: quota.mode,
+            ops: quota.ops_per_sec.map(|r| Bucket::new(r as f64)),
+            bytes: quota.bytes_per_sec.map(|r| Bucket::new(r as f64)),
+            last: Instant::now(),
+        }
+    }
+
+    /// Take budget for one operation, waiting or failing as configured.
+    pub(crate) fn acquire(&mut self) -> Result<(), StoreError> {
+        loop {
+            let wait = self.try_acquire(Instant::now());
+            if wait.is_zero() {
+                return Ok(());
+            }
+            match self.mode {
+                QuotaMode::Throttle => std::thread::sleep(wait),
+                QuotaMode::Fail => {
+                    return Err(StoreError::RateLimited {
+                        store: "block_quota",
+                        retry_after: wait,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Charge `bytes` moved by a completed operation.
+    pub(crate) fn charge(&mut self, bytes: usize) {
+        if let Some(bucket) = &mut self.bytes {
+            bucket.tokens -= bytes as f64;
+        }
+    }
+
+    /// Take budget for one operation as of `now`, or return how long to wait.
+    fn try_acquire(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last);
+        self.last = now;
+
+        let mut wait = Duration::ZERO;
+        if let Some(ops) = &mut self.ops {
+            ops.refill(elapsed);
+            wait = wait.max(ops.wait_for(1.0));
+        }
+        if let Some(bytes) = &mut self.bytes {
+            bytes.refill(elapsed);
+            // Any remaining budget admits the operation; its size is charged
+            // once known
+            wait = wait.max(bytes.wait_for(f64::MIN_POSITIVE));
+        }
+
+        if wait.is_zero() {
+            if let Some(ops) = &mut self.ops {
+                ops.tokens -= 1.0;
+            }
+        }
+        wait
+    }
+}
+
+/// Approximate size of a record in bytes.
+///
+/// Raw records count their encoded length. Parsed values count string, byte
+/// and key lengths, and 8 bytes for each scalar.
+pub(crate) fn record_size(record: &Record) -> usize {
+    match record {
+        Record::Raw { bytes, .. } => bytes.len(),
+        Record::Parsed(value) => value_size(value),
+    }
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) => 8,
+        Value::String(s) => s.len(),
+        Value::Bytes(b) => b.len(),
+        Value::Array(items) => items.iter().map(value_size).sum(),
+        Value::Map(map) => map.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meter(ops: Option<u32>, bytes: Option<u64>) -> QuotaMeter {
+        QuotaMeter::new(BlockQuota {
+            ops_per_sec: ops,
+            bytes_per_sec: bytes,
+            mode: QuotaMode::Fail,
+        })
+    }
+
+    #[test]
+    fn default_quota_is_unlimited() {
+        assert!(BlockQuota::default().is_unlimited());
+        let mut meter = QuotaMeter::new(BlockQuota::default());
+        for _ in 0..10_000 {
+            meter.acquire().unwrap();
+        }
+    }
+
+    #[test]
+    fn ops_refill_over_time() {
+        let mut meter = meter(Some(10), None);
+        let start = meter.last;
+        for _ in 0..10 {
+            assert!(meter.try_acquire(start).is_zero());
+        }
+        let wait = meter.try_acquire(start);
+        assert_eq!(wait, Duration::from_millis(100));
+
+        // 100ms later one more op is available
+        let later = start + Duration::from_millis(100);
+        assert!(meter.try_acquire(later).is_zero());
+        assert!(!meter.try_acquire(later).is_zero());
+    }
+
+    #[test]
+    fn byte_overdraft_delays_later_ops() {
+        let mut meter = meter(None, Some(1000));
+        let start = meter.last;
+        assert!(meter.try_acquire(start).is_zero());
+        meter.charge(1500);
+
+        // 500 bytes in debt at 1000 bytes/sec
+        let wait = meter.try_acquire(start);
+        assert!(wait > Duration::from_millis(499) && wait <= Duration::from_millis(501));
+        assert!(meter
+            .try_acquire(start + Duration::from_millis(501))
+            .is_zero());
+    }
+
+    #[test]
+    fn fail_mode_returns_rate_limited() {
+        let mut meter = meter(Some(1), None);
+        meter.acquire().unwrap();
+        assert!(matches!(
+            meter.acquire(),
+            Err(StoreError::RateLimited {
+                store: "block_quota",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn throttle_mode_waits() {
+        let mut meter = QuotaMeter::new(BlockQuota {
+            ops_per_sec: Some(100),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        for _ in 0..105 {
+            meter.acquire().unwrap();
+        }
+        // The last five ops wait about 10ms each
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn record_sizes() {
+        let raw = Record::raw(vec![0u8; 12], structfs_core_store::Format::OCTET_STREAM);
+        assert_eq!(record_size(&raw), 12);
+
+        let parsed = Record::parsed(Value::Map(
+            [("key".to_string(), Value::from("value"))].into(),
+        ));
+        assert_eq!(record_size(&parsed), 8);
+        assert_eq!(record_size(&Record::parsed(Value::Integer(1))), 8);
+    }
+}
@@ -0,0 +1,469 @@
+//! Recording a Block's store I/O and replaying it.
+//!
+//! [`Runtime::spawn_recorded`](crate::Runtime::spawn_recorded) runs a Block
+//! as usual and appends every operation on its root store to an
+//! [`IoRecorder`]'s trace: each read with the record it found, or its
+//! error, and each write with the record written and where it landed.
+//!
+//! [`Runtime::spawn_replayed`](crate::Runtime::spawn_replayed) runs a Block
+//! against an [`IoReplayer`] instead of its root store. Reads are answered
+//! with the recorded results in order, so a Block that misbehaved once does
+//! so again, however timing-dependent the original reads were. Writes are
+//! checked against the recorded ones and not performed; output callbacks
+//! still see them. The first operation that does not match the trace fails
+//! with the event it expected, which makes a recorded trace a golden test:
+//!
+//! ```ignore
+//! let replay = IoReplayer::open("golden/ingest.jsonl")?;
+//! let handle = runtime.spawn_replayed(block, root, replay.clone()).await?;
+//! // ... wait for the Block to stop
+//! replay.finish()?; // every recorded operation happened, in order
+//! ```
+//!
+//! A trace is a file of JSON lines, one event each:
+//!
+//! ```text
+//! {"op":"read","path":"config/limit","record":{"path":"config/limit","format":"application/json","bytes":"MTA="}}
+//! {"op":"read","path":"queue/next"}
+//! {"op":"write","path":"output/0","record":{...},"written":"output/0"}
+//! ```
+//!
+//! Records are encoded as entries of a [`dump`](structfs_core_store::dump),
+//! keeping their format and bytes. Errors keep only their message, and
+//! replay as store errors with that message.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use structfs_core_store::dump::{DumpEntry, DumpReader, DumpWriter};
+use structfs_core_store::{Error as StoreError, Format, Path, Record};
+
+use crate::report::StoreOpKind;
+
+/// One store operation of a Block, with its outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IoEvent {
+    /// A read and the record it found, `None` if there was none.
+    Read {
+        path: Path,
+        result: Result<Option<DumpEntry>, String>,
+    },
+    /// A write of `record` and the path it was written to.
+    Write {
+        path: Path,
+        record: DumpEntry,
+        result: Result<Path, String>,
+    },
+}
+
+impl IoEvent {
+    /// A read of `path` that returned `result`.
+    pub fn read(path: &Path, result: &Result<Option<Record>, StoreError>) -> Self {
+        let result = match result {
+            Ok(record) => record
+                .clone()
+                .map(|record| DumpEntry::from_record(path.clone(), record))
+                .transpose()
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        IoEvent::Read {
+            path: path.clone(),
+            result,
+        }
+    }
+
+    /// A write of `record` to `path` that returned `result`.
+    pub fn write(
+        path: &Path,
+        record: Record,
+        result: &Result<Path, StoreError>,
+    ) -> Result<Self, StoreError> {
+        Ok(IoEvent::Write {
+            path: path.clone(),
+            record: DumpEntry::from_record(path.clone(), record)?,
+            result: result.as_ref().cloned().map_err(|e| e.to_string()),
+        })
+    }
+
+    fn op(&self) -> StoreOpKind {
+        match self {
+            IoEvent::Read { .. } => StoreOpKind::Read,
+            IoEvent::Write { .. } => StoreOpKind::Write,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            IoEvent::Read { path, .. } | IoEvent::Write { path, .. } => path,
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{} of '{}'", op_name(self.op()), self.path())
+    }
+}
+
+fn op_name(op: StoreOpKind) -> &'static str {
+    match op {
+        StoreOpKind::Read => "read",
+        StoreOpKind::Write => "write",
+    }
+}
+
+/// An event as it is encoded in a trace.
+#[derive(Serialize, Deserialize)]
+struct WireEvent {
+    op: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    written: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn entry_to_json(entry: &DumpEntry) -> Result<serde_json::Value, StoreError> {
+    let mut dump = DumpWriter::jsonl(Vec::new());
+    dump.write_entry(entry)?;
+    serde_json::from_slice(&dump.into_inner())
+        .map_err(|e| StoreError::encode(Format::JSON, e.to_string()))
+}
+
+fn entry_from_json(json: serde_json::Value) -> Result<DumpEntry, StoreError> {
+    let line =
+        serde_json::to_vec(&json).map_err(|e| StoreError::decode(Format::JSON, e.to_string()))?;
+    DumpReader::jsonl(line.as_slice())
+        .read_entry()?
+        .ok_or_else(|| StoreError::decode(Format::JSON, "empty record in trace"))
+}
+
+impl WireEvent {
+    fn from_event(event: &IoEvent) -> Result<Self, StoreError> {
+        let mut wire = WireEvent {
+            op: op_name(event.op()).to_string(),
+            path: event.path().to_string(),
+            record: None,
+            written: None,
+            error: None,
+        };
+        match event {
+            IoEvent::Read { result, .. } => match result {
+                Ok(entry) => wire.record = entry.as_ref().map(entry_to_json).transpose()?,
+                Err(e) => wire.error = Some(e.clone()),
+            },
+            IoEvent::Write { record, result, .. } => {
+                wire.record = Some(entry_to_json(record)?);
+                match result {
+                    Ok(written) => wire.written = Some(written.to_string()),
+                    Err(e) => wire.error = Some(e.clone()),
+                }
+            }
+        }
+        Ok(wire)
+    }
+
+    fn into_event(self) -> Result<IoEvent, StoreError> {
+        let parse = |path: &str| {
+            Path::parse(path).map_err(|e| StoreError::decode(Format::JSON, e.to_string()))
+        };
+        let path = parse(&self.path)?;
+        match self.op.as_str() {
+            "read" => Ok(IoEvent::Read {
+                path,
+                result: match self.error {
+                    Some(error) => Err(error),
+                    None => Ok(self.record.map(entry_from_json).transpose()?),
+                },
+            }),
+            "write" => {
+                let record = self.record.ok_or_else(|| {
+                    StoreError::decode(Format::JSON, "write event without a record")
+                })?;
+                let result = match (self.error, self.written) {
+                    (Some(error), _) => Err(error),
+                    (None, Some(written)) => Ok(parse(&written)?),
+                    (None, None) => {
+                        return Err(StoreError::decode(
+                            Format::JSON,
+                            "write event without 'written' or 'error'",
+                        ))
+                    }
+                };
+                Ok(IoEvent::Write {
+                    path,
+                    record: entry_from_json(record)?,
+                    result,
+                })
+            }
+            other => Err(StoreError::decode(
+                Format::JSON,
+                format!("unknown trace op '{}'", other),
+            )),
+        }
+    }
+}
+
+/// Appends a Block's store operations to a trace.
+///
+/// Clones share the trace. Each event is flushed as it is written, so a
+/// trace is complete up to the last operation even if the process dies.
+#[derive(Clone)]
+pub struct IoRecorder {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl IoRecorder {
+    /// Record to a new file at `path`, replacing any file there.
+    pub fn create(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Record to `out`.
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Arc::new(Mutex::new(Box::new(out))),
+        }
+    }
+
+    /// Append `event` to the trace.
+    pub fn record(&self, event: &IoEvent) -> Result<(), StoreError> {
+        let wire = WireEvent::from_event(event)?;
+        let mut line = serde_json::to_vec(&wire)
+            .map_err(|e| StoreError::encode(Format::JSON, e.to_string()))?;
+        line.push(b'\n');
+        let mut out = self.out.lock().unwrap();
+        out.write_all(&line)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Serves a Block's store operations from a recorded trace.
+///
+/// Clones share the position in the trace.
+#[derive(Clone)]
+pub struct IoReplayer {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+struct ReplayState {
+    events: VecDeque<IoEvent>,
+    /// Events already replayed
+    position: usize,
+}
+
+impl IoReplayer {
+    /// Replay the trace in the file at `path`.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, StoreError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Replay the trace read from `reader`.
+    pub fn from_reader(reader: impl Read) -> Result<Self, StoreError> {
+        let mut events = Vec::new();
+        for (n, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let wire: WireEvent = serde_json::from_str(&line).map_err(|e| {
+                StoreError::decode(Format::JSON, format!("trace line {}: {}", n + 1, e))
+            })?;
+            events.push(wire.into_event()?);
+        }
+        Ok(Self::from_events(events))
+    }
+
+    /// Replay `events`.
+    pub fn from_events(events: impl IntoIterator<Item = IoEvent>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ReplayState {
+                events: events.into_iter().collect(),
+                position: 0,
+            })),
+        }
+    }
+
+    /// How many recorded events have not been replayed.
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().events.len()
+    }
+
+    /// Check that every recorded event was replayed.
+    pub fn finish(&self) -> Result<(), StoreError> {
+        let state = self.state.lock().unwrap();
+        match state.events.front() {
+            None => Ok(()),
+            Some(next) => Err(StoreError::store(
+                "replay",
+                "finish",
+                format!(
+                    "{} recorded operation(s) did not happen, starting with event {}: {}",
+                    state.events.len(),
+                    state.position,
+                    next.describe()
+                ),
+            )),
+        }
+    }
+
+    /// Take the next event if it is an operation `op` on `path`.
+    fn next(&self, op: StoreOpKind, path: &Path) -> Result<IoEvent, StoreError> {
+        let op_name = op_name(op);
+        let mut state = self.state.lock().unwrap();
+        let expected = match state.events.front() {
+            Some(event) if event.op() == op && event.path() == path => None,
+            Some(event) => Some(event.describe()),
+            None => Some("the end of the trace".to_string()),
+        };
+        if let Some(expected) = expected {
+            return Err(StoreError::store(
+                "replay",
+                op_name,
+                format!(
+                    "Block diverged from the trace at event {}: expected {}, got {} of '{}'",
+                    state.position, expected, op_name, path
+                ),
+            ));
+        }
+        state.position += 1;
+        Ok(state.events.pop_front().unwrap())
+    }
+
+    /// The recorded result of reading `path`.
+    pub fn read(&self, path: &Path) -> Result<Option<Record>, StoreError> {
+        let IoEvent::Read { result, .. } = self.next(StoreOpKind::Read, path)? else {
+            unreachable!("next matched a read");
+        };
+        match result {
+            Ok(entry) => entry.map(DumpEntry::into_record).transpose(),
+            Err(message) => Err(StoreError::store("replay", "read", message)),
+        }
+    }
+
+    /// The recorded result of writing `record` to `path`, which must be
+    /// the record written then.
+    pub fn write(&self, path: &Path, record: Record) -> Result<Path, StoreError> {
+        let written = DumpEntry::from_record(path.clone(), record)?;
+        let state = self.state.lock().unwrap();
+        if let Some(IoEvent::Write {
+            path: p, record, ..
+        }) = state.events.front()
+        {
+            if p == path && *record != written {
+                return Err(StoreError::store(
+                    "replay",
+                    "write",
+                    format!(
+                        "Block diverged from the trace at event {}: wrote a different record to '{}'",
+                        state.position, path
+                    ),
+                ));
+            }
+        }
+        drop(state);
+        let IoEvent::Write { result, .. } = self.next(StoreOpKind::Write, path)? else {
+            unreachable!("next matched a write");
+        };
+        result.map_err(|message| StoreError::store("replay", "write", message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, Value};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn events() -> Vec<IoEvent> {
+        let value = Record::parsed(Value::Integer(10));
+        let raw = Record::raw(
+            structfs_core_store::Bytes::from_static(b"{\"a\":1}"),
+            Format::JSON,
+        );
+        vec![
+            IoEvent::read(&path!("config/limit"), &Ok(Some(value))),
+            IoEvent::read(&path!("missing"), &Ok(None)),
+            IoEvent::read(
+                &path!("denied"),
+                &Err(StoreError::store("policy", "read", "denied")),
+            ),
+            IoEvent::write(&path!("out"), raw, &Ok(path!("out/0"))).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn traces_round_trip_through_json_lines() {
+        let out = Shared::default();
+        let recorder = IoRecorder::new(out.clone());
+        for event in events() {
+            recorder.record(&event).unwrap();
+        }
+        let trace = out.0.lock().unwrap().clone();
+        assert_eq!(trace.iter().filter(|&&b| b == b'\n').count(), 4);
+
+        let replay = IoReplayer::from_reader(trace.as_slice()).unwrap();
+        assert_eq!(replay.remaining(), 4);
+        assert_eq!(
+            replay
+                .read(&path!("config/limit"))
+                .unwrap()
+                .unwrap()
+                .into_value(&structfs_core_store::NoCodec)
+                .unwrap(),
+            Value::Integer(10)
+        );
+        assert!(replay.read(&path!("missing")).unwrap().is_none());
+        let err = replay.read(&path!("denied")).unwrap_err();
+        assert!(err.to_string().contains("denied"), "{}", err);
+        let raw = Record::raw(
+            structfs_core_store::Bytes::from_static(b"{\"a\":1}"),
+            Format::JSON,
+        );
+        assert_eq!(replay.write(&path!("out"), raw).unwrap(), path!("out/0"));
+        replay.finish().unwrap();
+    }
+
+    #[test]
+    fn divergence_names_the_expected_event() {
+        let replay = IoReplayer::from_events(events());
+        let err = replay.read(&path!("config/other")).unwrap_err().to_string();
+        assert!(
+            err.contains("event 0: expected read of 'config/limit'"),
+            "{}",
+            err
+        );
+
+        replay.read(&path!("config/limit")).unwrap();
+        replay.read(&path!("missing")).unwrap();
+        assert!(replay
+            .finish()
+            .unwrap_err()
+            .to_string()
+            .contains("2 recorded"));
+        replay.read(&path!("denied")).unwrap_err();
+        let err = replay
+            .write(&path!("out"), Record::parsed(Value::Null))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("different record"), "{}", err);
+        assert_eq!(replay.remaining(), 1);
+    }
+}
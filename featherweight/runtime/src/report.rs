@@ -0,0 +1,354 @@
+//! Structured reports for failed Blocks.
+//!
+//! When a Block traps or returns an error, the runtime keeps a [`TrapReport`]
+//! describing what happened: the message, the WASM backtrace and fuel
+//! consumed (for WASM Blocks), the Block's last few store operations and the
+//! path that failed, if any. The [`Runtime`](crate::Runtime) keeps the last
+//! report for each Block and serves it through [`BlocksStore`] at
+//! `{id}/last_error`, where `{id}` is the Block's
+//! [path component](crate::BlockId::path_component). Writing anything to
+//! `{id}/cancel` asks a running Block to stop.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use structfs_core_store::{Error as StoreError, Path, Reader, Record, Value, Writer};
+
+use crate::block::BlockId;
+use crate::cancel::CancellationTable;
+use crate::error::RuntimeError;
+
+/// Number of store operations kept for a failure report.
+pub const RECENT_OPS: usize = 16;
+
+/// How a Block failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The WASM guest trapped (unreachable, out of bounds, out of fuel, ...).
+    Trap,
+    /// The guest returned an error from `run`.
+    GuestError,
+    /// A native Block returned an error, or the host failed to run the Block.
+    Error,
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureKind::Trap => write!(f, "trap"),
+            FailureKind::GuestError => write!(f, "guest_error"),
+            FailureKind::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Kind of store operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOpKind {
+    Read,
+    Write,
+}
+
+impl std::fmt::Display for StoreOpKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreOpKind::Read => write!(f, "read"),
+            StoreOpKind::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// A store operation made by a Block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreOpRecord {
+    /// Read or write.
+    pub kind: StoreOpKind,
+    /// The path as the Block gave it.
+    pub path: String,
+    /// The error, if the operation failed.
+    pub error: Option<String>,
+}
+
+/// The most recent store operations of a Block, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct RecentOps {
+    ops: VecDeque<StoreOpRecord>,
+}
+
+impl RecentOps {
+    /// Record an operation, dropping the oldest beyond [`RECENT_OPS`].
+    pub fn record(&mut self, kind: StoreOpKind, path: &str, error: Option<String>) {
+        if self.ops.len() == RECENT_OPS {
+            self.ops.pop_front();
+        }
+        self.ops.push_back(StoreOpRecord {
+            kind,
+            path: path.to_string(),
+            error,
+        });
+    }
+
+    /// Path of the most recent failed operation.
+    pub fn failing_path(&self) -> Option<String> {
+        self.ops
+            .iter()
+            .rev()
+            .find(|op| op.error.is_some())
+            .map(|op| op.path.clone())
+    }
+
+    /// The recorded operations, oldest first.
+    pub fn to_vec(&self) -> Vec<StoreOpRecord> {
+        self.ops.iter().cloned().collect()
+    }
+}
+
+/// Why a Block stopped with an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrapReport {
+    /// The failed Block.
+    pub block: BlockId,
+    /// How it failed.
+    pub kind: FailureKind,
+    /// The trap or error message.
+    pub message: String,
+    /// WASM frames, innermost first. Empty for native Blocks.
+    pub backtrace: Vec<String>,
+    /// Fuel used before the failure, for WASM Blocks.
+    pub fuel_consumed: Option<u64>,
+    /// The Block's last store operations, oldest first.
+    pub recent_ops: Vec<StoreOpRecord>,
+    /// Path of the most recent failed store operation.
+    pub failing_path: Option<String>,
+}
+
+impl TrapReport {
+    /// A report carrying only a message, for failures outside WASM.
+    pub fn from_error(block: BlockId, error: &RuntimeError) -> Self {
+        Self {
+            block,
+            kind: FailureKind::Error,
+            message: error.to_string(),
+            backtrace: Vec::new(),
+            fuel_consumed: None,
+            recent_ops: Vec::new(),
+            failing_path: None,
+        }
+    }
+
+    /// The report as a `Value` map.
+    pub fn to_value(&self) -> Value {
+        let opt_string = |s: &Option<String>| s.clone().map_or(Value::Null, Value::String);
+        let ops = self
+            .recent_ops
+            .iter()
+            .map(|op| {
+                Value::Map(BTreeMap::from([
+                    ("op".to_string(), Value::String(op.kind.to_string())),
+                    ("path".to_string(), Value::String(op.path.clone())),
+                    ("error".to_string(), opt_string(&op.error)),
+                ]))
+            })
+            .collect();
+
+        Value::Map(BTreeMap::from([
+            ("block".to_string(), Value::String(self.block.to_string())),
+            ("kind".to_string(), Value::String(self.kind.to_string())),
+            ("message".to_string(), Value::String(self.message.clone())),
+            (
+                "backtrace".to_string(),
+                Value::Array(self.backtrace.iter().cloned().map(Value::String).collect()),
+            ),
+            (
+                "fuel_consumed".to_string(),
+                self.fuel_consumed.map_or(Value::Null, |f| {
+                    Value::Integer(f.min(i64::MAX as u64) as i64)
+                }),
+            ),
+            ("recent_ops".to_string(), Value::Array(ops)),
+            ("failing_path".to_string(), opt_string(&self.failing_path)),
+        ]))
+    }
+}
+
+impl std::fmt::Display for TrapReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+        if let Some(path) = &self.failing_path {
+            write!(f, " (last failed path: {})", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Last failure report of each Block, shared by the runtime and its stores.
+pub(crate) type FailureTable = Arc<Mutex<BTreeMap<BlockId, TrapReport>>>;
+
+/// Store of Block failure reports and cancellation requests.
+///
+/// Serves `{id}/last_error` with the Block's last [`TrapReport`], and `{id}`
+/// with a map holding it. Blocks without a failure read as not found.
+///
+/// Writing any value to `{id}/cancel` asks the registered Block to stop, as
+/// [`Runtime::cancel`](crate::Runtime::cancel) does. Nothing else is
+/// writable.
+#[derive(Clone)]
+pub struct BlocksStore {
+    failures: FailureTable,
+    cancellations: CancellationTable,
+}
+
+impl BlocksStore {
+    pub(crate) fn new(failures: FailureTable, cancellations: CancellationTable) -> Self {
+        Self {
+            failures,
+            cancellations,
+        }
+    }
+}
+
+impl Reader for BlocksStore {
+    fn read(&mut self, path: &Path) -> Result<Option<Record>, StoreError> {
+        let Some(id) = path
+            .iter()
+            .next()
+            .and_then(|component| BlockId::from_path_component(component))
+        else {
+            return Ok(None);
+        };
+        let failures = self.failures.lock().unwrap();
+        let Some(report) = failures.get(&id) else {
+            return Ok(None);
+        };
+
+        let report = report.to_value();
+        let value = match path.len() {
+            1 => Value::Map(BTreeMap::from([("last_error".to_string(), report)])),
+            _ if path[1] == "last_error" => match report.get(&path.slice(2, path.len())) {
+                Some(value) => value.clone(),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(Record::parsed(value)))
+    }
+}
+
+impl Writer for BlocksStore {
+    fn write(&mut self, path: &Path, _record: Record) -> Result<Path, StoreError> {
+        let id = match path.len() {
+            2 if path[1] == "cancel" => BlockId::from_path_component(&path[0]),
+            _ => None,
+        };
+        let Some(id) = id else {
+            return Err(StoreError::store(
+                "blocks",
+                "write",
+                "only {id}/cancel is writable in the blocks store",
+            ));
+        };
+        match self.cancellations.lock().unwrap().get(&id) {
+            Some(cancellation) => {
+                cancellation.cancel();
+                Ok(path.clone())
+            }
+            None => Err(StoreError::store(
+                "blocks",
+                "write",
+                format!("no registered block {}", path[0]),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::NoCodec;
+
+    fn report(block: BlockId) -> TrapReport {
+        let mut ops = RecentOps::default();
+        ops.record(StoreOpKind::Read, "input/name", None);
+        ops.record(StoreOpKind::Write, "output/x", Some("denied".to_string()));
+        TrapReport {
+            block,
+            kind: FailureKind::Trap,
+            message: "wasm trap: unreachable".to_string(),
+            backtrace: vec!["inner".to_string(), "run".to_string()],
+            fuel_consumed: Some(1234),
+            failing_path: ops.failing_path(),
+            recent_ops: ops.to_vec(),
+        }
+    }
+
+    #[test]
+    fn recent_ops_keeps_the_last_few() {
+        let mut ops = RecentOps::default();
+        for i in 0..RECENT_OPS + 4 {
+            ops.record(StoreOpKind::Write, &format!("p{}", i), None);
+        }
+        let ops = ops.to_vec();
+        assert_eq!(ops.len(), RECENT_OPS);
+        assert_eq!(ops[0].path, "p4");
+    }
+
+    #[test]
+    fn failing_path_is_most_recent_failure() {
+        let mut ops = RecentOps::default();
+        assert_eq!(ops.failing_path(), None);
+        ops.record(StoreOpKind::Read, "a", Some("x".to_string()));
+        ops.record(StoreOpKind::Read, "b", Some("y".to_string()));
+        ops.record(StoreOpKind::Read, "c", None);
+        assert_eq!(ops.failing_path(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn report_display() {
+        let report = report(BlockId::new());
+        assert_eq!(
+            report.to_string(),
+            "trap: wasm trap: unreachable (last failed path: output/x)"
+        );
+    }
+
+    #[test]
+    fn blocks_store_serves_last_error() {
+        let id = BlockId::new();
+        let failures: FailureTable = Default::default();
+        failures.lock().unwrap().insert(id, report(id));
+        let mut store = BlocksStore::new(failures, Default::default());
+
+        let read = |store: &mut BlocksStore, path: String| {
+            store
+                .read(&Path::parse(&path).unwrap())
+                .unwrap()
+                .map(|r| r.into_value(&NoCodec).unwrap())
+        };
+
+        let component = id.path_component();
+        let last_error = read(&mut store, format!("{}/last_error", component)).unwrap();
+        assert_eq!(last_error, report(id).to_value());
+        assert_eq!(
+            read(
+                &mut store,
+                format!("{}/last_error/fuel_consumed", component)
+            ),
+            Some(Value::Integer(1234))
+        );
+        assert_eq!(
+            read(
+                &mut store,
+                format!("{}/last_error/recent_ops/1/error", component)
+            ),
+            Some(Value::from("denied"))
+        );
+        assert!(read(&mut store, component.clone()).is_some());
+        assert!(read(&mut store, format!("{}/other", component)).is_none());
+        assert!(read(&mut store, BlockId::new().path_component()).is_none());
+        assert!(read(&mut store, "nonsense".to_string()).is_none());
+
+        assert!(store
+            .write(&Path::parse("x").unwrap(), Record::parsed(Value::Null))
+            .is_err());
+    }
+}
@@ -0,0 +1,515 @@
+//! Request/response calls between Blocks.
+//!
+//! A service Block exports an [`RpcStore`] and its callers mount it. Every
+//! call follows the same protocol, so no service invents its own:
+//!
+//! ```text
+//! write {request} to requests                 → requests/{id}
+//! read  requests/{id}/response/wait           → the response, once answered
+//! read  requests/{id}/response/wait/{ms}      → the same, waiting at most ms
+//! read  requests/{id}/response                → the response, or nothing yet
+//! read  requests/{id}                         → {id, correlation_id, status, ...}
+//! write null to requests/{id}                 → abandon the call
+//! ```
+//!
+//! and on the service's side:
+//!
+//! ```text
+//! read  next                                  → {id, correlation_id, request}
+//! read  next/id                               → the id alone
+//! read  requests/{id}/request                 → the request
+//! write {response} to requests/{id}/response  → answer the call
+//! write "message" to requests/{id}/error      → fail the call
+//! ```
+//!
+//! Reading `next` claims the oldest request no one has claimed, so several
+//! workers can share one service. Each call gets a correlation id, the
+//! caller's trace id if it has one, which the service can adopt as its own
+//! trace id so its operations are traced with the caller's.
+//!
+//! Waits end at the caller's deadline, the `{ms}` given, or the store's
+//! timeout ([`DEFAULT_TIMEOUT`] unless changed), whichever comes first,
+//! with `DeadlineExceeded`. A call is forgotten once its response or error
+//! has been read, or when the lease of the Block that made it is released.
+//! Waiting blocks the calling thread, like the HTTP broker's waits.
+//!
+//! [`RpcClient`] wraps the caller's side for host code and native Blocks.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use collection_literals::btree;
+use structfs_core_store::{
+    path, Error, HandleBroker, NoCodec, OpContext, Path, Reader, Record, Value, Writer,
+};
+use uuid::Uuid;
+
+/// How long a wait lasts when neither the caller nor the path limits it.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn error(operation: &'static str, message: impl Into<String>) -> Error {
+    Error::store("rpc", operation, message)
+}
+
+/// A request waiting for, or holding, its response.
+struct Call {
+    correlation_id: String,
+    request: Value,
+    claimed: bool,
+    outcome: Option<Result<Value, String>>,
+}
+
+impl Call {
+    fn status(&self) -> &'static str {
+        match (&self.outcome, self.claimed) {
+            (Some(Ok(_)), _) => "answered",
+            (Some(Err(_)), _) => "failed",
+            (None, true) => "claimed",
+            (None, false) => "pending",
+        }
+    }
+}
+
+struct State {
+    calls: HandleBroker<Call>,
+    /// Unclaimed requests, oldest first
+    queue: VecDeque<u64>,
+}
+
+/// A request taken by the service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcRequest {
+    /// The id to answer with.
+    pub id: u64,
+    /// The caller's correlation id.
+    pub correlation_id: String,
+    /// What the caller wrote.
+    pub request: Value,
+}
+
+impl RpcRequest {
+    fn to_value(&self) -> Value {
+        Value::Map(btree! {
+            "id".into() => Value::Integer(self.id as i64),
+            "correlation_id".into() => Value::from(self.correlation_id.as_str()),
+            "request".into() => self.request.clone(),
+        })
+    }
+}
+
+/// The requests of one service. See the [module docs](self).
+///
+/// Clones share the requests, so the service keeps one clone and exports
+/// another.
+#[derive(Clone)]
+pub struct RpcStore {
+    state: Arc<(Mutex<State>, Condvar)>,
+    timeout: Duration,
+}
+
+impl Default for RpcStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RpcStore {
+    /// A service with no requests.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new((
+                Mutex::new(State {
+                    calls: HandleBroker::new("requests", "rpc-request"),
+                    queue: VecDeque::new(),
+                }),
+                Condvar::new(),
+            )),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Let waits last up to `timeout` instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `request` as if a caller had written it, returning its id.
+    pub fn submit(&self, request: Value) -> u64 {
+        let correlation_id = OpContext::current()
+            .trace_id
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let mut state = self.lock();
+        let id = state.calls.insert(Call {
+            correlation_id,
+            request,
+            claimed: false,
+            outcome: None,
+        });
+        state.queue.push_back(id);
+        id
+    }
+
+    /// Claim the oldest unclaimed request, if there is one.
+    pub fn next_request(&self) -> Option<RpcRequest> {
+        let mut state = self.lock();
+        state.calls.sweep();
+        while let Some(id) = state.queue.pop_front() {
+            // Abandoned and swept calls stay queued until they come up
+            if let Some(call) = state.calls.touch(id) {
+                call.claimed = true;
+                return Some(RpcRequest {
+                    id,
+                    correlation_id: call.correlation_id.clone(),
+                    request: call.request.clone(),
+                });
+            }
+        }
+        None
+    }
+
+    /// Answer request `id`. Fails if the call was abandoned or already
+    /// answered.
+    pub fn respond(&self, id: u64, response: Value) -> Result<(), Error> {
+        self.finish(id, Ok(response))
+    }
+
+    /// Fail request `id` with `message`.
+    pub fn fail(&self, id: u64, message: impl Into<String>) -> Result<(), Error> {
+        self.finish(id, Err(message.into()))
+    }
+
+    fn finish(&self, id: u64, outcome: Result<Value, String>) -> Result<(), Error> {
+        let mut state = self.lock();
+        let call = state
+            .calls
+            .touch(id)
+            .ok_or_else(|| error("respond", format!("No request {}", id)))?;
+        if call.outcome.is_some() {
+            return Err(error(
+                "respond",
+                format!("Request {} was already answered", id),
+            ));
+        }
+        call.outcome = Some(outcome);
+        state.queue.retain(|queued| *queued != id);
+        drop(state);
+        self.state.1.notify_all();
+        Ok(())
+    }
+
+    /// Take the outcome of call `id`, waiting up to `wait` for it. The call
+    /// is forgotten once its outcome is taken.
+    fn take_outcome(&self, id: u64, wait: Option<Duration>) -> Result<Option<Record>, Error> {
+        let deadline = wait.map(|wait| Instant::now() + wait);
+        let mut state = self.lock();
+        loop {
+            let Some(call) = state.calls.touch(id) else {
+                return Ok(None);
+            };
+            if call.outcome.is_some() {
+                let call = state.calls.remove(id).expect("call was just found");
+                return match call.outcome {
+                    Some(Ok(response)) => Ok(Some(Record::parsed(response))),
+                    Some(Err(message)) => Err(error("call", message)),
+                    None => unreachable!(),
+                };
+            }
+            let Some(deadline) = deadline else {
+                return Ok(None);
+            };
+            OpContext::check_current("rpc")?;
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(Error::DeadlineExceeded { store: "rpc" });
+            }
+            state = self
+                .state
+                .1
+                .wait_timeout(state, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// How long a wait on `rest` (after `response/wait`) may last.
+    fn wait_time(&self, rest: &[String]) -> Result<Duration, Error> {
+        let mut wait = self.timeout;
+        match rest {
+            [] => {}
+            [ms] => {
+                let ms: u64 = ms
+                    .parse()
+                    .map_err(|_| error("read", format!("Invalid wait time '{}'", ms)))?;
+                wait = wait.min(Duration::from_millis(ms));
+            }
+            _ => return Err(error("read", "Expected response/wait/{ms}")),
+        }
+        if let Some(remaining) = OpContext::current_remaining() {
+            wait = wait.min(remaining);
+        }
+        Ok(wait)
+    }
+}
+
+impl Reader for RpcStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let components: Vec<&str> = from.components.iter().map(String::as_str).collect();
+        match components.as_slice() {
+            ["next"] => Ok(self.next_request().map(|r| Record::parsed(r.to_value()))),
+            ["next", "id"] => Ok(self
+                .next_request()
+                .map(|r| Record::parsed(Value::Integer(r.id as i64)))),
+            _ => {
+                let mut state = self.lock();
+                if state.calls.is_listing(from) {
+                    return Ok(Some(Record::parsed(state.calls.listing())));
+                }
+                let Some((id, rest)) = state.calls.parse(from) else {
+                    return Ok(None);
+                };
+                let Some(call) = state.calls.get(id) else {
+                    return Ok(None);
+                };
+                match rest.components.as_slice() {
+                    [] => {
+                        let Some(Value::Map(mut status)) = state.calls.status(id) else {
+                            return Ok(None);
+                        };
+                        status.insert(
+                            "correlation_id".into(),
+                            Value::from(call.correlation_id.as_str()),
+                        );
+                        status.insert("status".into(), Value::from(call.status()));
+                        if let Some(Err(message)) = &call.outcome {
+                            status.insert("error".into(), Value::from(message.as_str()));
+                        }
+                        Ok(Some(Record::parsed(Value::Map(status))))
+                    }
+                    [field] if field == "request" => Ok(Some(Record::parsed(call.request.clone()))),
+                    [field] if field == "correlation_id" => Ok(Some(Record::parsed(Value::from(
+                        call.correlation_id.as_str(),
+                    )))),
+                    [field] if field == "status" => {
+                        Ok(Some(Record::parsed(Value::from(call.status()))))
+                    }
+                    [field] if field == "response" => {
+                        drop(state);
+                        self.take_outcome(id, None)
+                    }
+                    [field, wait, rest @ ..] if field == "response" && wait == "wait" => {
+                        drop(state);
+                        let wait = self.wait_time(rest)?;
+                        self.take_outcome(id, Some(wait))
+                    }
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+impl Writer for RpcStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let value = data.into_value(&NoCodec)?;
+        let state = self.lock();
+        if state.calls.is_listing(to) {
+            drop(state);
+            let id = self.submit(value);
+            return Ok(self.lock().calls.path(id));
+        }
+        let Some((id, rest)) = state.calls.parse(to) else {
+            return Err(error("write", format!("Cannot write to '{}'", to)));
+        };
+        drop(state);
+        match (rest.components.as_slice(), value) {
+            ([], Value::Null) => {
+                let mut state = self.lock();
+                state.calls.remove(id);
+                state.queue.retain(|queued| *queued != id);
+                Ok(to.clone())
+            }
+            ([field], response) if field == "response" => {
+                self.respond(id, response)?;
+                Ok(to.clone())
+            }
+            ([field], Value::String(message)) if field == "error" => {
+                self.fail(id, message)?;
+                Ok(to.clone())
+            }
+            ([field], _) if field == "error" => Err(error("write", "Expected an error message")),
+            _ => Err(error("write", format!("Cannot write to '{}'", to))),
+        }
+    }
+}
+
+/// The caller's side of the protocol, for host code and native Blocks.
+///
+/// ```ignore
+/// let client = RpcClient::new(path!("services/resize")).with_timeout(Duration::from_secs(5));
+/// let thumbnail = client.call(&mut root, request)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    service: Path,
+    timeout: Duration,
+}
+
+impl RpcClient {
+    /// A client for the service mounted at `service`.
+    pub fn new(service: Path) -> Self {
+        Self {
+            service,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Give up on calls after `timeout` instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send `request` and wait for the response.
+    ///
+    /// A call that times out is abandoned, so the service does not answer
+    /// it later. The service's error message is returned as a store error.
+    pub fn call<S: Reader + Writer + ?Sized>(
+        &self,
+        store: &mut S,
+        request: Value,
+    ) -> Result<Value, Error> {
+        OpContext::new().with_timeout(self.timeout).scope(|| {
+            let handle = store.write(
+                &self.service.join(&path!("requests")),
+                Record::parsed(request),
+            )?;
+            match store.read(&handle.join(&path!("response/wait"))) {
+                Ok(Some(record)) => record.into_value(&NoCodec),
+                Ok(None) => Err(error("call", format!("Request '{}' was abandoned", handle))),
+                Err(e) => {
+                    if matches!(e, Error::DeadlineExceeded { .. }) {
+                        let _ = store.write(&handle, Record::parsed(Value::Null));
+                    }
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use structfs_core_store::overlay_store::OverlayStore;
+
+    fn value(record: Option<Record>) -> Value {
+        record.unwrap().into_value(&NoCodec).unwrap()
+    }
+
+    #[test]
+    fn requests_are_answered_through_paths() {
+        let mut service = RpcStore::new();
+        let mut root = OverlayStore::new();
+        root.mount(path!("svc"), service.clone());
+
+        let handle = root
+            .write(&path!("svc/requests"), Record::parsed(Value::from("ping")))
+            .unwrap();
+        assert_eq!(handle, path!("svc/requests/0"));
+        assert_eq!(
+            value(root.read(&path!("svc/requests/0/status")).unwrap()),
+            Value::from("pending")
+        );
+        assert!(root
+            .read(&path!("svc/requests/0/response"))
+            .unwrap()
+            .is_none());
+
+        let next = value(service.read(&path!("next")).unwrap());
+        assert_eq!(next.get(&path!("request")), Some(&Value::from("ping")));
+        assert!(service.read(&path!("next")).unwrap().is_none());
+        service
+            .write(
+                &path!("requests/0/response"),
+                Record::parsed(Value::from("pong")),
+            )
+            .unwrap();
+
+        assert_eq!(
+            value(root.read(&path!("svc/requests/0/response/wait")).unwrap()),
+            Value::from("pong")
+        );
+        // Reading the response forgets the call
+        assert!(root.read(&path!("svc/requests/0")).unwrap().is_none());
+    }
+
+    #[test]
+    fn clients_wait_for_a_worker_and_time_out() {
+        let service = RpcStore::new();
+        let mut root = OverlayStore::new();
+        root.mount(path!("svc"), service.clone());
+
+        let worker = {
+            let service = service.clone();
+            thread::spawn(move || loop {
+                if let Some(request) = service.next_request() {
+                    let Value::Integer(n) = request.request else {
+                        service.fail(request.id, "Expected a number").unwrap();
+                        continue;
+                    };
+                    service.respond(request.id, Value::Integer(n * 2)).unwrap();
+                    return;
+                }
+                thread::sleep(Duration::from_millis(5));
+            })
+        };
+
+        let client = RpcClient::new(path!("svc"));
+        let trace = OpContext::new().with_trace_id("trace-1");
+        let doubled = trace.scope(|| client.call(&mut root, Value::Integer(21)));
+        assert_eq!(doubled.unwrap(), Value::Integer(42));
+        worker.join().unwrap();
+
+        // No worker is left, so the next call times out and is abandoned
+        let client = client.with_timeout(Duration::from_millis(20));
+        let err = client.call(&mut root, Value::Integer(1)).unwrap_err();
+        assert!(
+            matches!(err, Error::DeadlineExceeded { store: "rpc" }),
+            "{}",
+            err
+        );
+        assert!(service.next_request().is_none());
+    }
+
+    #[test]
+    fn correlation_ids_and_failures_reach_the_caller() {
+        let mut service = RpcStore::new();
+        let id = OpContext::new()
+            .with_trace_id("trace-7")
+            .scope(|| service.submit(Value::from("bad")));
+
+        let request = service.next_request().unwrap();
+        assert_eq!(request.id, id);
+        assert_eq!(request.correlation_id, "trace-7");
+        service
+            .write(
+                &path!("requests/0/error"),
+                Record::parsed(Value::from("Bad input")),
+            )
+            .unwrap();
+
+        let status = value(service.read(&path!("requests/0")).unwrap());
+        assert_eq!(status.get(&path!("status")), Some(&Value::from("failed")));
+        let err = service
+            .read(&path!("requests/0/response/wait/10"))
+            .unwrap_err();
+        assert!(err.to_string().contains("Bad input"), "{}", err);
+    }
+}
@@ -6,26 +6,74 @@
 //! - Providing the execution environment for Blocks
 
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use structfs_core_store::{Error as StoreError, Path, Reader, Record, Writer};
-use tokio::sync::Mutex;
+use structfs_core_store::mount_store::{HttpNetwork, StoreFactory};
+use structfs_core_store::{Error as StoreError, Lease, Path, Reader, Record, Writer};
+use tokio::sync::{oneshot, Mutex};
+use wasmtime::Engine;
 
 use crate::block::{
     Block, BlockContext, BlockHandle, BlockId, BlockState, ErasedStore, ExportedStore,
 };
+use crate::cancel::{Cancellation, CancellationTable};
 use crate::error::{Result, RuntimeError};
+use crate::graph::{BlockManifest, GraphStore, SharedGraph};
+use crate::network::NetworkFactory;
+use crate::output::{BlockIo, ObservedStore, OutputId, OutputListeners};
+use crate::policy::SharedPolicy;
+use crate::quota::BlockQuota;
+use crate::replay::{IoRecorder, IoReplayer};
+use crate::report::{BlocksStore, FailureTable, TrapReport};
+use crate::sandbox::{FsSandbox, SandboxedRoot};
+use crate::trace::traced;
+use crate::warm_pool::{block_engine, WarmPoolConfig};
+use crate::wasm_block::{PreparedWasmBlock, WasmBlock};
 
 /// Configuration for the Featherweight runtime.
 #[derive(Debug, Clone)]
 pub struct RuntimeConfig {
     /// Maximum number of concurrent Blocks.
     pub max_blocks: usize,
+
+    /// Store-operation quota applied to each Block's root store.
+    pub quota: BlockQuota,
+
+    /// Base directory for the per-Block filesystems of
+    /// [`Runtime::spawn_sandboxed`].
+    pub fs_sandbox: Option<PathBuf>,
+
+    /// Access-control policy checked on every root-store operation. `None`
+    /// allows everything.
+    pub policy: Option<SharedPolicy>,
+
+    /// Pool of pre-reserved instance slots for Blocks from
+    /// [`Runtime::prepare_wasm`]. `None` allocates each instance on demand.
+    pub warm_pool: Option<WarmPoolConfig>,
+
+    /// How long a Block may run before it is asked to stop. Blocks see the
+    /// request through [`BlockContext::should_cancel`]; `None` never asks.
+    pub block_deadline: Option<Duration>,
+
+    /// Hosts and schemes HTTP mounts made through
+    /// [`Runtime::network_factory`] may reach. `None` allows any `http` or
+    /// `https` host.
+    pub network: Option<HttpNetwork>,
 }
 
 impl Default for RuntimeConfig {
     fn default() -> Self {
-        Self { max_blocks: 1024 }
+        Self {
+            max_blocks: 1024,
+            quota: BlockQuota::default(),
+            fs_sandbox: None,
+            policy: None,
+            warm_pool: None,
+            block_deadline: None,
+            network: None,
+        }
     }
 }
 
@@ -33,6 +81,9 @@ impl Default for RuntimeConfig {
 struct RegisteredBlock {
     handle: BlockHandle,
     exports: BTreeMap<String, ExportedStore>,
+    outputs: OutputListeners,
+    /// Whether the Block has a filesystem sandbox to clean up.
+    sandboxed: bool,
 }
 
 /// The Featherweight runtime.
@@ -57,6 +108,18 @@ pub struct Runtime {
 
     /// Registered Blocks by ID.
     blocks: BTreeMap<BlockId, RegisteredBlock>,
+
+    /// Last failure report of each Block that failed.
+    failures: FailureTable,
+
+    /// Cancellation of each registered Block.
+    cancellations: CancellationTable,
+
+    /// Engine for prepared WASM Blocks, created on first use.
+    engine: Option<Engine>,
+
+    /// Blocks spawned from manifests and their dependencies.
+    graph: SharedGraph,
 }
 
 impl Runtime {
@@ -65,6 +128,10 @@ impl Runtime {
         Self {
             config,
             blocks: BTreeMap::new(),
+            failures: FailureTable::default(),
+            cancellations: CancellationTable::default(),
+            engine: None,
+            graph: SharedGraph::default(),
         }
     }
 
@@ -72,20 +139,228 @@ impl Runtime {
     ///
     /// The Block will be started in a new tokio task. The returned
     /// handle can be used to monitor and control the Block.
-    pub async fn spawn<B, S>(&mut self, mut block: B, root: S) -> Result<BlockHandle>
+    ///
+    /// The Block sees `root` through an [`ObservedStore`], so its writes can
+    /// be streamed to the host with [`on_output`](Self::on_output) and its
+    /// operations are metered against the configured [`BlockQuota`].
+    pub async fn spawn<B, S>(&mut self, block: B, root: S) -> Result<BlockHandle>
+    where
+        B: Block<ObservedStore<S>> + 'static,
+        S: Send + 'static,
+    {
+        self.check_capacity()?;
+        self.spawn_with_id(BlockId::new(), block, root, false, None, None)
+            .await
+    }
+
+    /// Spawn a Block that starts once the exports its manifest needs are
+    /// registered.
+    ///
+    /// The Block is registered at once, so its own exports can be
+    /// registered, but it does not run until every Block it needs has been
+    /// spawned from a manifest and has registered the export with
+    /// [`register_export`](Self::register_export).
+    /// Fails if the manifest's name is taken, it needs an export its provider
+    /// does not declare, or it would form a dependency cycle. See the
+    /// [`graph`](crate::graph) module.
+    pub async fn spawn_manifest<B, S>(
+        &mut self,
+        manifest: BlockManifest,
+        block: B,
+        root: S,
+    ) -> Result<BlockHandle>
+    where
+        B: Block<ObservedStore<S>> + 'static,
+        S: Send + 'static,
+    {
+        self.check_capacity()?;
+        let id = BlockId::new();
+        let start = self.graph.lock().unwrap().insert(manifest, id)?;
+        self.spawn_with_id(id, block, root, false, None, start)
+            .await
+    }
+
+    /// Spawn a Block with a private directory mounted at `fs/` in its root.
+    ///
+    /// The directory is allocated under [`RuntimeConfig::fs_sandbox`] and
+    /// deleted by [`remove`](Self::remove). See the [`sandbox`](crate::sandbox)
+    /// module.
+    pub async fn spawn_sandboxed<B, S>(&mut self, block: B, root: S) -> Result<BlockHandle>
+    where
+        B: Block<ObservedStore<SandboxedRoot<S>>> + 'static,
+        S: Send + 'static,
+    {
+        let sandbox = self.fs_sandbox().ok_or_else(|| {
+            RuntimeError::Store(StoreError::store(
+                "runtime",
+                "spawn",
+                "no filesystem sandbox directory configured",
+            ))
+        })?;
+        self.check_capacity()?;
+
+        let id = BlockId::new();
+        let root = SandboxedRoot::new(root, sandbox.create(id)?);
+        self.spawn_with_id(id, block, root, true, None, None).await
+    }
+
+    /// Spawn a Block, recording every operation on its root store and the
+    /// outcome to `recorder`.
+    ///
+    /// See the [`replay`](crate::replay) module.
+    pub async fn spawn_recorded<B, S>(
+        &mut self,
+        block: B,
+        root: S,
+        recorder: IoRecorder,
+    ) -> Result<BlockHandle>
     where
-        B: Block<S> + 'static,
+        B: Block<ObservedStore<S>> + 'static,
         S: Send + 'static,
     {
+        self.check_capacity()?;
+        let io = Some(BlockIo::Record(recorder));
+        self.spawn_with_id(BlockId::new(), block, root, false, io, None)
+            .await
+    }
+
+    /// Spawn a Block whose root store operations are answered by
+    /// `replayer` from a recorded trace.
+    ///
+    /// `root` is never touched; it only gives the Block's root its type.
+    /// See the [`replay`](crate::replay) module.
+    pub async fn spawn_replayed<B, S>(
+        &mut self,
+        block: B,
+        root: S,
+        replayer: IoReplayer,
+    ) -> Result<BlockHandle>
+    where
+        B: Block<ObservedStore<S>> + 'static,
+        S: Send + 'static,
+    {
+        self.check_capacity()?;
+        let io = Some(BlockIo::Replay(replayer));
+        self.spawn_with_id(BlockId::new(), block, root, false, io, None)
+            .await
+    }
+
+    /// Unregister a Block, dropping its exports and output callbacks and
+    /// deleting its filesystem sandbox, if it has one.
+    ///
+    /// Remove Blocks after they stop; a running Block keeps running but loses
+    /// its files.
+    pub fn remove(&mut self, block_id: BlockId) -> Result<()> {
+        let block = self
+            .blocks
+            .remove(&block_id)
+            .ok_or(RuntimeError::BlockNotFound(block_id.as_uuid()))?;
+        self.cancellations.lock().unwrap().remove(&block_id);
+        self.graph.lock().unwrap().remove(block_id);
+
+        match self.fs_sandbox() {
+            Some(sandbox) if block.sandboxed => sandbox.remove(block_id),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ask a Block to stop.
+    ///
+    /// The Block sees the request the next time it checks
+    /// [`BlockContext::should_cancel`] and stops on its own; it is never
+    /// interrupted mid-operation. See the [`cancel`](crate::cancel) module.
+    pub fn cancel(&self, block_id: BlockId) -> Result<()> {
+        self.cancellations
+            .lock()
+            .unwrap()
+            .get(&block_id)
+            .ok_or(RuntimeError::BlockNotFound(block_id.as_uuid()))?
+            .cancel();
+        Ok(())
+    }
+
+    /// Compile and link a WASM Block once so it can be spawned quickly.
+    ///
+    /// Spawn clones of the result with [`spawn`](Self::spawn); each spawn
+    /// only instantiates the component, using the
+    /// [warm pool](RuntimeConfig::warm_pool) if one is configured.
+    pub fn prepare_wasm<S: Reader + Writer + Send + 'static>(
+        &mut self,
+        block: &WasmBlock,
+    ) -> Result<PreparedWasmBlock<ObservedStore<S>>> {
+        let engine = match &self.engine {
+            Some(engine) => engine,
+            None => self
+                .engine
+                .insert(block_engine(self.config.warm_pool.as_ref())?),
+        };
+        block.prepare_in(engine)
+    }
+
+    /// Wrap `factory` so the HTTP mounts it creates are confined to
+    /// [`RuntimeConfig::network`].
+    ///
+    /// Use it as the factory of a Block's root
+    /// [`MountStore`](structfs_core_store::mount_store::MountStore). See the
+    /// [`network`](crate::network) module.
+    pub fn network_factory<F: StoreFactory>(&self, factory: F) -> NetworkFactory<F> {
+        NetworkFactory::new(factory, self.config.network.clone().unwrap_or_default())
+    }
+
+    /// A read-only store serving the dependency graph of Blocks spawned
+    /// with [`spawn_manifest`](Self::spawn_manifest) at `graph`.
+    pub fn graph_store(&self) -> GraphStore {
+        GraphStore::new(self.graph.clone())
+    }
+
+    fn fs_sandbox(&self) -> Option<FsSandbox> {
+        self.config.fs_sandbox.as_ref().map(FsSandbox::new)
+    }
+
+    fn check_capacity(&self) -> Result<()> {
         if self.blocks.len() >= self.config.max_blocks {
             return Err(RuntimeError::Io(std::io::Error::other(
                 "maximum blocks reached",
             )));
         }
+        Ok(())
+    }
 
-        let id = BlockId::new();
+    async fn spawn_with_id<B, S>(
+        &mut self,
+        id: BlockId,
+        mut block: B,
+        root: S,
+        sandboxed: bool,
+        io: Option<BlockIo>,
+        start: Option<oneshot::Receiver<()>>,
+    ) -> Result<BlockHandle>
+    where
+        B: Block<ObservedStore<S>> + 'static,
+        S: Send + 'static,
+    {
         let handle = BlockHandle::new(id);
-        let ctx = BlockContext::new(id, root);
+        let outputs = OutputListeners::new();
+        let mut root = ObservedStore::new(root, outputs.clone())
+            .with_quota(self.config.quota)
+            .with_label(id.to_string())
+            .with_io(io);
+        if let Some(policy) = &self.config.policy {
+            root = root.with_policy(id, policy.clone());
+        }
+        // Handles the Block opens close once this is dropped, when the task
+        // ends however the Block stopped
+        let lease = Lease::new(id.to_string());
+        let root = root.with_lease(&lease);
+        let mut cancellation = Cancellation::new();
+        if let Some(deadline) = self.config.block_deadline {
+            cancellation = cancellation.with_timeout(deadline);
+        }
+        self.cancellations
+            .lock()
+            .unwrap()
+            .insert(id, cancellation.clone());
+        let ctx = BlockContext::new(id, root).with_cancellation(cancellation);
 
         // Register the Block
         self.blocks.insert(
@@ -93,33 +368,54 @@ impl Runtime {
             RegisteredBlock {
                 handle: BlockHandle::new(id),
                 exports: BTreeMap::new(),
+                outputs,
+                sandboxed,
             },
         );
 
         // Clone handle for the task
         let task_handle = BlockHandle::new(id);
+        let failures = self.failures.clone();
 
         // Spawn the Block in a new task
+        let waiting = start.is_some();
         tokio::spawn(async move {
+            let _lease = lease;
+            if let Some(start) = start {
+                // Dropped without a signal when the Block is removed first
+                if start.await.is_err() {
+                    task_handle.set_state(BlockState::Stopped).await;
+                    return;
+                }
+            }
             task_handle.set_state(BlockState::Running).await;
 
             match block.run(ctx).await {
                 Ok(()) => {
                     task_handle.set_state(BlockState::Stopped).await;
                 }
-                Err(_) => {
+                Err(e) => {
+                    let report = match e {
+                        RuntimeError::BlockFailed(report) => *report,
+                        other => TrapReport::from_error(id, &other),
+                    };
+                    failures.lock().unwrap().insert(id, report);
                     task_handle.set_state(BlockState::Failed).await;
                 }
             }
         });
 
-        handle.set_state(BlockState::Running).await;
+        if !waiting {
+            handle.set_state(BlockState::Running).await;
+        }
         Ok(handle)
     }
 
     /// Register an export from a Block.
     ///
-    /// This makes a store available for other Blocks to mount.
+    /// This makes a store available for other Blocks to mount, and starts
+    /// Blocks spawned with [`spawn_manifest`](Self::spawn_manifest) that were
+    /// only waiting for it.
     pub fn register_export<S: Reader + Writer + Send + 'static>(
         &mut self,
         block_id: BlockId,
@@ -135,6 +431,7 @@ impl Runtime {
             name.to_string(),
             Arc::new(Mutex::new(Box::new(store) as Box<dyn ErasedStore>)),
         );
+        self.graph.lock().unwrap().mark_healthy(block_id, name);
         Ok(())
     }
 
@@ -155,6 +452,44 @@ impl Runtime {
             .ok_or_else(|| RuntimeError::ExportNotFound(name.to_string()))
     }
 
+    /// Call `callback` whenever the Block writes at or under `prefix`.
+    ///
+    /// The callback runs on the Block's task after each successful write and
+    /// receives the written path and record. Writes made before the callback
+    /// is registered are not reported.
+    pub fn on_output<F>(&self, block_id: BlockId, prefix: Path, callback: F) -> Result<OutputId>
+    where
+        F: Fn(&Path, &Record) + Send + Sync + 'static,
+    {
+        let block = self
+            .blocks
+            .get(&block_id)
+            .ok_or(RuntimeError::BlockNotFound(block_id.as_uuid()))?;
+
+        Ok(block.outputs.add(prefix, Arc::new(callback)))
+    }
+
+    /// Remove an output callback. Returns false if it was not registered.
+    pub fn remove_output(&self, block_id: BlockId, id: OutputId) -> Result<bool> {
+        let block = self
+            .blocks
+            .get(&block_id)
+            .ok_or(RuntimeError::BlockNotFound(block_id.as_uuid()))?;
+
+        Ok(block.outputs.remove(id))
+    }
+
+    /// The last failure report of a Block, if it has failed.
+    pub fn last_error(&self, block_id: BlockId) -> Option<TrapReport> {
+        self.failures.lock().unwrap().get(&block_id).cloned()
+    }
+
+    /// A store of failure reports, serving `{id}/last_error`, that cancels
+    /// Blocks on writes to `{id}/cancel`.
+    pub fn blocks_store(&self) -> BlocksStore {
+        BlocksStore::new(self.failures.clone(), self.cancellations.clone())
+    }
+
     /// List all Block IDs.
     pub fn blocks(&self) -> impl Iterator<Item = BlockId> + '_ {
         self.blocks.keys().copied()
@@ -173,30 +508,46 @@ impl Runtime {
 
 /// Adapter to make a shared store (ExportedStore) usable as a Reader + Writer.
 ///
-/// This is used when mounting inter-Block exports.
+/// This is used when mounting inter-Block exports. Inside a
+/// [trace](crate::trace), each operation on the export runs in its own span,
+/// so calls into another Block show up nested under the caller's.
 pub struct SharedStoreAdapter {
     inner: ExportedStore,
+    label: String,
 }
 
 impl SharedStoreAdapter {
     /// Create a new adapter wrapping a shared store.
     pub fn new(store: ExportedStore) -> Self {
-        Self { inner: store }
+        Self {
+            inner: store,
+            label: "export".to_string(),
+        }
+    }
+
+    /// Name the adapter's spans after `label`, such as `{block}/{export}`.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
     }
 }
 
 impl Reader for SharedStoreAdapter {
     fn read(&mut self, path: &Path) -> std::result::Result<Option<Record>, StoreError> {
-        // Block on the mutex - this is a sync interface over async storage
-        let mut guard = self.inner.blocking_lock();
-        guard.read(path)
+        traced(&self.label, "read", path, || {
+            // Block on the mutex - this is a sync interface over async storage
+            let mut guard = self.inner.blocking_lock();
+            guard.read(path)
+        })
     }
 }
 
 impl Writer for SharedStoreAdapter {
     fn write(&mut self, path: &Path, record: Record) -> std::result::Result<Path, StoreError> {
-        let mut guard = self.inner.blocking_lock();
-        guard.write(path, record)
+        traced(&self.label, "write", path, || {
+            let mut guard = self.inner.blocking_lock();
+            guard.write(path, record)
+        })
     }
 }
 
@@ -204,12 +555,14 @@ impl Writer for SharedStoreAdapter {
 mod tests {
     use super::*;
     use async_trait::async_trait;
+    use structfs_core_store::overlay_store::OverlayStore;
     use structfs_core_store::{NoCodec, Value};
 
     #[test]
     fn runtime_config_default() {
         let config = RuntimeConfig::default();
         assert_eq!(config.max_blocks, 1024);
+        assert!(config.quota.is_unlimited());
     }
 
     #[test]
@@ -224,8 +577,8 @@ mod tests {
     }
 
     #[async_trait]
-    impl crate::block::Block<()> for TestBlock {
-        async fn run(&mut self, _ctx: BlockContext<()>) -> crate::error::Result<()> {
+    impl<S: Send + 'static> crate::block::Block<S> for TestBlock {
+        async fn run(&mut self, _ctx: BlockContext<S>) -> crate::error::Result<()> {
             if self.success {
                 Ok(())
             } else {
@@ -248,7 +601,10 @@ mod tests {
 
     #[tokio::test]
     async fn runtime_spawn_max_blocks() {
-        let config = RuntimeConfig { max_blocks: 1 };
+        let config = RuntimeConfig {
+            max_blocks: 1,
+            ..Default::default()
+        };
         let mut runtime = Runtime::new(config);
 
         // First spawn succeeds
@@ -319,6 +675,59 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    /// Reports its name on `started` when it runs.
+    struct StartBlock {
+        name: &'static str,
+        started: tokio::sync::mpsc::UnboundedSender<&'static str>,
+    }
+
+    #[async_trait]
+    impl<S: Send + 'static> crate::block::Block<S> for StartBlock {
+        async fn run(&mut self, _ctx: BlockContext<S>) -> crate::error::Result<()> {
+            let _ = self.started.send(self.name);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn manifests_start_after_their_dependencies() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let (started, mut order) = tokio::sync::mpsc::unbounded_channel();
+        let block = |name| StartBlock {
+            name,
+            started: started.clone(),
+        };
+
+        let api = BlockManifest::new("api").needs("db", "queries");
+        runtime.spawn_manifest(api, block("api"), ()).await.unwrap();
+        let db = BlockManifest::new("db").export("queries");
+        let db = runtime.spawn_manifest(db, block("db"), ()).await.unwrap();
+        assert_eq!(order.recv().await, Some("db"));
+        tokio::task::yield_now().await;
+        assert!(order.try_recv().is_err());
+
+        let taken = runtime
+            .spawn_manifest(BlockManifest::new("db"), block("db"), ())
+            .await;
+        assert!(matches!(taken, Err(RuntimeError::InvalidManifest(_))));
+
+        runtime
+            .register_export(db.id, "queries", OverlayStore::new())
+            .unwrap();
+        assert_eq!(order.recv().await, Some("api"));
+
+        let mut graph = runtime.graph_store();
+        let order = Reader::read(&mut graph, &Path::parse("graph/order").unwrap())
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(
+            order,
+            Value::Array(vec![Value::from("db"), Value::from("api")])
+        );
+    }
+
     #[tokio::test]
     async fn runtime_get_export_not_found() {
         let mut runtime = Runtime::new(RuntimeConfig::default());
@@ -369,6 +778,434 @@ mod tests {
         ));
     }
 
+    /// Writes to `log`, then each of its values to `output/<n>`, once started.
+    struct ProducerBlock {
+        values: Vec<i64>,
+        start: Option<tokio::sync::oneshot::Receiver<()>>,
+    }
+
+    #[async_trait]
+    impl<S: Writer + Send + 'static> crate::block::Block<S> for ProducerBlock {
+        async fn run(&mut self, mut ctx: BlockContext<S>) -> crate::error::Result<()> {
+            if let Some(start) = self.start.take() {
+                let _ = start.await;
+            }
+            ctx.root
+                .write(&Path::parse("log").unwrap(), Record::parsed(Value::Null))?;
+            for (i, value) in self.values.iter().enumerate() {
+                let path = Path::parse(&format!("output/{}", i)).unwrap();
+                ctx.root
+                    .write(&path, Record::parsed(Value::Integer(*value)))?;
+            }
+            Ok(())
+        }
+    }
+
+    struct NullStore;
+    impl Writer for NullStore {
+        fn write(&mut self, path: &Path, _record: Record) -> std::result::Result<Path, StoreError> {
+            Ok(path.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_on_output_streams_writes() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let (start_tx, start_rx) = tokio::sync::oneshot::channel();
+        let block = ProducerBlock {
+            values: vec![10, 20],
+            start: Some(start_rx),
+        };
+        let handle = runtime.spawn(block, NullStore).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        runtime
+            .on_output(
+                handle.id,
+                Path::parse("output").unwrap(),
+                move |path: &Path, record: &Record| {
+                    let value = record.clone().into_value(&NoCodec).unwrap();
+                    tx.send((path.to_string(), value)).unwrap();
+                },
+            )
+            .unwrap();
+        start_tx.send(()).unwrap();
+
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            ("output/0".to_string(), Value::Integer(10))
+        );
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            ("output/1".to_string(), Value::Integer(20))
+        );
+        // The earlier write to `log` is outside the prefix
+        assert!(rx.try_recv().is_err());
+    }
+
+    /// Reads `counter` and writes it to `output/seen`, once started.
+    struct CounterBlock {
+        start: Option<tokio::sync::oneshot::Receiver<()>>,
+    }
+
+    #[async_trait]
+    impl<S: Reader + Writer + Send + 'static> crate::block::Block<S> for CounterBlock {
+        async fn run(&mut self, mut ctx: BlockContext<S>) -> crate::error::Result<()> {
+            if let Some(start) = self.start.take() {
+                let _ = start.await;
+            }
+            let seen = ctx.root.read(&Path::parse("counter").unwrap())?.unwrap();
+            ctx.root.write(&Path::parse("output/seen").unwrap(), seen)?;
+            Ok(())
+        }
+    }
+
+    /// Counts its reads, answering each with the count so far.
+    struct CountingStore(i64);
+    impl Reader for CountingStore {
+        fn read(&mut self, _path: &Path) -> std::result::Result<Option<Record>, StoreError> {
+            self.0 += 1;
+            Ok(Some(Record::parsed(Value::Integer(self.0))))
+        }
+    }
+    impl Writer for CountingStore {
+        fn write(&mut self, path: &Path, _record: Record) -> std::result::Result<Path, StoreError> {
+            Ok(path.clone())
+        }
+    }
+
+    fn counter_block() -> (CounterBlock, tokio::sync::oneshot::Sender<()>) {
+        let (start_tx, start_rx) = tokio::sync::oneshot::channel();
+        let block = CounterBlock {
+            start: Some(start_rx),
+        };
+        (block, start_tx)
+    }
+
+    /// Start a spawned [`CounterBlock`] and return what it saw.
+    async fn counter_seen(
+        runtime: &Runtime,
+        handle: BlockHandle,
+        start: tokio::sync::oneshot::Sender<()>,
+    ) -> Value {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        runtime
+            .on_output(
+                handle.id,
+                Path::parse("output").unwrap(),
+                move |_: &Path, record: &Record| {
+                    tx.send(record.clone().into_value(&NoCodec).unwrap())
+                        .unwrap()
+                },
+            )
+            .unwrap();
+        start.send(()).unwrap();
+        rx.recv().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn runtime_replays_recorded_reads() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let trace = dir.path().join("trace.jsonl");
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+
+        let recorder = IoRecorder::create(&trace).unwrap();
+        let (block, start) = counter_block();
+        let handle = runtime
+            .spawn_recorded(block, CountingStore(0), recorder)
+            .await
+            .unwrap();
+        let seen = counter_seen(&runtime, handle, start).await;
+        assert_eq!(seen, Value::Integer(1));
+
+        // The store would now answer 2, but the replay answers as recorded
+        let replayer = IoReplayer::open(&trace).unwrap();
+        let (block, start) = counter_block();
+        let handle = runtime
+            .spawn_replayed(block, CountingStore(1), replayer.clone())
+            .await
+            .unwrap();
+        let seen = counter_seen(&runtime, handle, start).await;
+        assert_eq!(seen, Value::Integer(1));
+        replayer.finish().unwrap();
+    }
+
+    /// Works until asked to stop, then writes `stopped`.
+    struct LoopBlock;
+
+    #[async_trait]
+    impl<S: Writer + Send + 'static> crate::block::Block<S> for LoopBlock {
+        async fn run(&mut self, mut ctx: BlockContext<S>) -> crate::error::Result<()> {
+            while !ctx.should_cancel() {
+                tokio::task::yield_now().await;
+            }
+            ctx.root
+                .write(
+                    &Path::parse("stopped").unwrap(),
+                    Record::parsed(Value::Null),
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    /// Receives a message once the Block writes `stopped`.
+    fn on_stopped(runtime: &Runtime, id: BlockId) -> tokio::sync::mpsc::UnboundedReceiver<()> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        runtime
+            .on_output(
+                id,
+                Path::parse("stopped").unwrap(),
+                move |_: &Path, _: &Record| tx.send(()).unwrap(),
+            )
+            .unwrap();
+        rx
+    }
+
+    #[tokio::test]
+    async fn runtime_cancel_stops_blocks() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let first = runtime.spawn(LoopBlock, NullStore).await.unwrap();
+        let second = runtime.spawn(LoopBlock, NullStore).await.unwrap();
+        let mut first_stopped = on_stopped(&runtime, first.id);
+        let mut second_stopped = on_stopped(&runtime, second.id);
+
+        runtime.cancel(first.id).unwrap();
+        first_stopped.recv().await.unwrap();
+        assert!(second_stopped.try_recv().is_err());
+
+        // Supervisors cancel through the blocks store
+        let cancel = Path::parse(&format!("{}/cancel", second.id.path_component())).unwrap();
+        let mut blocks = runtime.blocks_store();
+        Writer::write(&mut blocks, &cancel, Record::parsed(Value::Bool(true))).unwrap();
+        second_stopped.recv().await.unwrap();
+
+        runtime.remove(first.id).unwrap();
+        assert!(matches!(
+            runtime.cancel(first.id),
+            Err(crate::error::RuntimeError::BlockNotFound(_))
+        ));
+        assert!(Writer::write(
+            &mut blocks,
+            &cancel.slice(0, 1),
+            Record::parsed(Value::Null)
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn runtime_block_deadline_cancels() {
+        let mut runtime = Runtime::new(RuntimeConfig {
+            block_deadline: Some(Duration::from_millis(20)),
+            ..Default::default()
+        });
+        let handle = runtime.spawn(LoopBlock, NullStore).await.unwrap();
+        on_stopped(&runtime, handle.id).recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn runtime_spawn_sandboxed_and_remove() {
+        let base = tempfile::TempDir::new().unwrap();
+        let mut runtime = Runtime::new(RuntimeConfig {
+            fs_sandbox: Some(base.path().to_path_buf()),
+            ..Default::default()
+        });
+        let (start_tx, start_rx) = tokio::sync::oneshot::channel();
+        let block = FsBlock {
+            start: Some(start_rx),
+        };
+
+        let handle = runtime.spawn_sandboxed(block, NullStore).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        runtime
+            .on_output(
+                handle.id,
+                Path::parse("done").unwrap(),
+                move |_: &Path, _: &Record| tx.send(()).unwrap(),
+            )
+            .unwrap();
+        start_tx.send(()).unwrap();
+        rx.recv().await.unwrap();
+
+        let dir = FsSandbox::new(base.path()).dir(handle.id);
+        assert!(dir.join("scratch").is_dir());
+
+        runtime.remove(handle.id).unwrap();
+        assert!(!dir.exists());
+        assert_eq!(runtime.block_count(), 0);
+        assert!(matches!(
+            runtime.remove(handle.id),
+            Err(crate::error::RuntimeError::BlockNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn runtime_spawn_sandboxed_requires_config() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let block = TestBlock { success: true };
+        assert!(runtime.spawn_sandboxed(block, ()).await.is_err());
+        assert_eq!(runtime.block_count(), 0);
+    }
+
+    /// Creates `fs/scratch` in its sandbox, then writes `done`.
+    struct FsBlock {
+        start: Option<tokio::sync::oneshot::Receiver<()>>,
+    }
+
+    #[async_trait]
+    impl<S: Writer + Send + 'static> crate::block::Block<S> for FsBlock {
+        async fn run(&mut self, mut ctx: BlockContext<S>) -> crate::error::Result<()> {
+            if let Some(start) = self.start.take() {
+                let _ = start.await;
+            }
+            let mkdir = Value::Map(collection_literals::btree! {
+                "path".to_string() => Value::String("scratch".into()),
+            });
+            ctx.root
+                .write(&Path::parse("fs/mkdir").unwrap(), Record::parsed(mkdir))?;
+            ctx.root
+                .write(&Path::parse("done").unwrap(), Record::parsed(Value::Null))?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_remove_output() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let block = TestBlock { success: true };
+        let handle = runtime.spawn(block, ()).await.unwrap();
+
+        let id = runtime
+            .on_output(
+                handle.id,
+                Path::parse("").unwrap(),
+                |_: &Path, _: &Record| {},
+            )
+            .unwrap();
+        assert!(runtime.remove_output(handle.id, id).unwrap());
+        assert!(!runtime.remove_output(handle.id, id).unwrap());
+
+        let fake_id = BlockId::new();
+        assert!(matches!(
+            runtime.on_output(fake_id, Path::parse("").unwrap(), |_: &Path, _: &Record| {}),
+            Err(crate::error::RuntimeError::BlockNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn runtime_releases_handles_of_stopped_blocks() {
+        use structfs_core_store::HandleBroker;
+
+        /// Opens a handle on every write.
+        #[derive(Clone)]
+        struct HandleStore(Arc<std::sync::Mutex<HandleBroker<()>>>);
+        impl Writer for HandleStore {
+            fn write(&mut self, _: &Path, _: Record) -> std::result::Result<Path, StoreError> {
+                let mut handles = self.0.lock().unwrap();
+                let id = handles.insert(());
+                Ok(handles.path(id))
+            }
+        }
+
+        let store = HandleStore(Arc::new(std::sync::Mutex::new(HandleBroker::new(
+            "handles", "handle",
+        ))));
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let block = ProducerBlock {
+            values: vec![1],
+            start: None,
+        };
+        let handle = runtime.spawn(block, store.clone()).await.unwrap();
+
+        // Both writes open a handle; both close once the Block stops
+        let mut released = false;
+        for _ in 0..1000 {
+            released = {
+                let mut handles = store.0.lock().unwrap();
+                handles.sweep();
+                handles.next_id() == 2 && handles.is_empty()
+            };
+            if released {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert!(released, "block {} leaked handles", handle.id);
+    }
+
+    #[tokio::test]
+    async fn runtime_records_block_failure() {
+        let mut runtime = Runtime::new(RuntimeConfig::default());
+        let ok = runtime
+            .spawn(TestBlock { success: true }, ())
+            .await
+            .unwrap();
+        let failed = runtime
+            .spawn(TestBlock { success: false }, ())
+            .await
+            .unwrap();
+
+        let mut blocks = runtime.blocks_store();
+        let path = Path::parse(&format!("{}/last_error/kind", failed.id.path_component())).unwrap();
+        let mut kind = None;
+        for _ in 0..100 {
+            kind = Reader::read(&mut blocks, &path).unwrap();
+            if kind.is_some() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        let kind = kind
+            .expect("failure recorded")
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(kind, Value::from("error"));
+
+        let report = runtime.last_error(failed.id).unwrap();
+        assert!(report.message.contains("test failure"));
+        assert!(runtime.last_error(ok.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn runtime_enforces_policy() {
+        use crate::policy::{Effect, Policy, PolicyRule, SharedPolicy};
+
+        let policy = SharedPolicy::new(
+            Policy::new(Effect::Allow)
+                .rule(PolicyRule::deny().path(Path::parse("output").unwrap())),
+        );
+        let mut runtime = Runtime::new(RuntimeConfig {
+            policy: Some(policy.clone()),
+            ..Default::default()
+        });
+        let (start_tx, start_rx) = tokio::sync::oneshot::channel();
+        let block = ProducerBlock {
+            values: vec![1],
+            start: Some(start_rx),
+        };
+        let handle = runtime.spawn(block, NullStore).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        runtime
+            .on_output(handle.id, Path::parse("").unwrap(), move |path, _| {
+                tx.send(path.to_string()).unwrap();
+            })
+            .unwrap();
+        start_tx.send(()).unwrap();
+
+        assert_eq!(rx.recv().await.unwrap(), "log");
+        let mut report = None;
+        for _ in 0..100 {
+            report = runtime.last_error(handle.id);
+            if report.is_some() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        let report = report.expect("denial recorded");
+        assert!(report.message.contains("denied by rule 0"));
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn shared_store_adapter() {
         struct TestStore {
@@ -416,4 +1253,47 @@ mod tests {
             _ => panic!("Expected Ok(Some(Record))"),
         }
     }
+
+    #[test]
+    fn trace_follows_operations_into_exports() {
+        use std::sync::Mutex as StdMutex;
+        use structfs_core_store::OpContext;
+
+        type Seen = Arc<StdMutex<Vec<OpContext>>>;
+
+        /// Records the context each read runs in.
+        struct ContextStore(Seen);
+        impl Reader for ContextStore {
+            fn read(&mut self, _path: &Path) -> std::result::Result<Option<Record>, StoreError> {
+                self.0.lock().unwrap().push(OpContext::current());
+                Ok(None)
+            }
+        }
+        impl Writer for ContextStore {
+            fn write(&mut self, path: &Path, _: Record) -> std::result::Result<Path, StoreError> {
+                Ok(path.clone())
+            }
+        }
+
+        let seen = Seen::default();
+        let export: ExportedStore = Arc::new(Mutex::new(
+            Box::new(ContextStore(seen.clone())) as Box<dyn ErasedStore>
+        ));
+        let mut root = ObservedStore::new(
+            SharedStoreAdapter::new(export).with_label("service/api"),
+            OutputListeners::new(),
+        )
+        .with_label("client");
+
+        let ctx = crate::trace::request_context(None);
+        ctx.scope(|| Reader::read(&mut root, &Path::parse("x").unwrap()))
+            .unwrap();
+        Reader::read(&mut root, &Path::parse("x").unwrap()).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].trace_id, ctx.trace_id);
+        assert!(seen[0].span_id.is_some());
+        assert_ne!(seen[0].span_id, ctx.span_id);
+        assert!(seen[1].trace_id.is_none());
+    }
 }
@@ -0,0 +1,192 @@
+//! Per-Block filesystem sandboxes.
+//!
+//! An [`FsSandbox`] gives each Block its own directory under a base
+//! directory and mounts it at `fs/` in the Block's root as a chrooted
+//! [`FsStore`]. The Block can create, read and delete files there, but
+//! cannot reach anything outside its directory.
+//!
+//! ```ignore
+//! let mut runtime = Runtime::new(RuntimeConfig {
+//!     fs_sandbox: Some("/var/lib/featherweight/blocks".into()),
+//!     ..Default::default()
+//! });
+//!
+//! // The Block sees its scratch directory at fs/
+//! let handle = runtime.spawn_sandboxed(block, root).await?;
+//!
+//! // Deletes the Block's directory
+//! runtime.remove(handle.id)?;
+//! ```
+//!
+//! Directories are named after the Block's
+//! [`path_component`](crate::BlockId::path_component) and live until the
+//! Block is removed with [`Runtime::remove`](crate::Runtime::remove), so
+//! files survive the Block stopping or failing.
+
+use std::path::{Path as FsPath, PathBuf};
+
+use structfs_core_store::{Error as StoreError, Path, Reader, Record, Writer};
+use structfs_sys::FsStore;
+
+use crate::block::BlockId;
+use crate::error::Result;
+
+/// Where a sandboxed Block's filesystem is mounted in its root.
+pub const FS_MOUNT: &str = "fs";
+
+/// Allocates and removes per-Block directories under a base directory.
+#[derive(Debug, Clone)]
+pub struct FsSandbox {
+    base: PathBuf,
+}
+
+impl FsSandbox {
+    /// Allocate Block directories under `base`, which is created if needed.
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+
+    /// The base directory.
+    pub fn base(&self) -> &FsPath {
+        &self.base
+    }
+
+    /// The directory belonging to `id`.
+    pub fn dir(&self, id: BlockId) -> PathBuf {
+        self.base.join(id.path_component())
+    }
+
+    /// Create the directory for `id` and a store confined to it.
+    pub fn create(&self, id: BlockId) -> Result<FsStore> {
+        let dir = self.dir(id);
+        std::fs::create_dir_all(&dir)?;
+        Ok(FsStore::chrooted(dir)?)
+    }
+
+    /// Delete the directory for `id` and everything in it. Succeeds if it
+    /// does not exist.
+    pub fn remove(&self, id: BlockId) -> Result<()> {
+        match std::fs::remove_dir_all(self.dir(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A Block root with a sandboxed filesystem mounted at `fs/`.
+///
+/// Paths under `fs/` go to the [`FsStore`]; everything else goes to the
+/// wrapped root, which never sees the `fs` subtree.
+pub struct SandboxedRoot<S> {
+    root: S,
+    fs: FsStore,
+    prefix: Path,
+}
+
+impl<S> SandboxedRoot<S> {
+    /// Mount `fs` at `fs/` over `root`.
+    pub fn new(root: S, fs: FsStore) -> Self {
+        Self {
+            root,
+            fs,
+            prefix: Path::parse(FS_MOUNT).unwrap(),
+        }
+    }
+
+    /// The wrapped root.
+    pub fn inner(&self) -> &S {
+        &self.root
+    }
+
+    /// The sandboxed filesystem store.
+    pub fn fs(&self) -> &FsStore {
+        &self.fs
+    }
+
+    fn fs_path(&self, path: &Path) -> Option<Path> {
+        path.has_prefix(&self.prefix)
+            .then(|| path.slice(self.prefix.len(), path.len()))
+    }
+}
+
+impl<S: Reader> Reader for SandboxedRoot<S> {
+    fn read(&mut self, from: &Path) -> std::result::Result<Option<Record>, StoreError> {
+        match self.fs_path(from) {
+            Some(rest) => self.fs.read(&rest),
+            None => self.root.read(from),
+        }
+    }
+}
+
+impl<S: Writer> Writer for SandboxedRoot<S> {
+    fn write(&mut self, to: &Path, data: Record) -> std::result::Result<Path, StoreError> {
+        match self.fs_path(to) {
+            Some(rest) => Ok(self.prefix.join(&self.fs.write(&rest, data)?)),
+            None => self.root.write(to, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection_literals::btree;
+    use structfs_core_store::{path, NoCodec, Value};
+
+    struct Recording(Vec<Path>);
+
+    impl Reader for Recording {
+        fn read(&mut self, _from: &Path) -> std::result::Result<Option<Record>, StoreError> {
+            Ok(None)
+        }
+    }
+
+    impl Writer for Recording {
+        fn write(&mut self, to: &Path, _data: Record) -> std::result::Result<Path, StoreError> {
+            self.0.push(to.clone());
+            Ok(to.clone())
+        }
+    }
+
+    #[test]
+    fn fs_paths_go_to_sandbox() {
+        let base = tempfile::TempDir::new().unwrap();
+        let sandbox = FsSandbox::new(base.path().join("blocks"));
+        let id = BlockId::new();
+        let mut root = SandboxedRoot::new(Recording(Vec::new()), sandbox.create(id).unwrap());
+
+        let handle = root
+            .write(
+                &path!("fs/open"),
+                Record::parsed(Value::Map(btree! {
+                    "path".into() => Value::String("/scratch.txt".into()),
+                    "mode".into() => Value::String("write".into()),
+                    "encoding".into() => Value::String("utf8".into()),
+                })),
+            )
+            .unwrap();
+        assert_eq!(handle[0], "fs");
+        root.write(&handle, Record::parsed(Value::String("saved".into())))
+            .unwrap();
+        root.write(&handle.join(&path!("close")), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(sandbox.dir(id).join("scratch.txt")).unwrap(),
+            "saved"
+        );
+
+        let listing = root.read(&path!("fs/handles")).unwrap().unwrap();
+        assert!(matches!(
+            listing.into_value(&NoCodec).unwrap(),
+            Value::Map(_)
+        ));
+
+        root.write(&path!("output/done"), Record::parsed(Value::Bool(true)))
+            .unwrap();
+        assert_eq!(root.inner().0, vec![path!("output/done")]);
+
+        sandbox.remove(id).unwrap();
+        assert!(!sandbox.dir(id).exists());
+        sandbox.remove(id).unwrap();
+    }
+}
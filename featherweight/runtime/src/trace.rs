@@ -0,0 +1,128 @@
+//! Tracing requests across Blocks.
+//!
+//! A request that enters the runtime from outside gets a trace with
+//! [`request_context`]. Running the work inside that context's
+//! [`scope`](OpContext::scope) makes every store operation it causes part of
+//! the trace: each Block's root store and each mounted export open a child
+//! span per operation and make it current for the store underneath, so an
+//! operation that crosses into another Block's export nests inside the span
+//! of the operation that caused it.
+//!
+//! Spans are emitted as [`tracing`] spans named `structfs.op`, with the W3C
+//! `trace_id`, `span_id` and `parent_span_id` as fields. A
+//! `tracing-opentelemetry` layer, or any subscriber that reads those
+//! fields, exports them as OpenTelemetry spans. HTTP stores forward the
+//! current span to upstream services in the `traceparent` header.
+//!
+//! Operations outside a trace are not traced.
+
+use structfs_core_store::{OpContext, Path};
+use uuid::Uuid;
+
+/// A new random W3C trace id: 32 lowercase hex digits.
+pub fn new_trace_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// A new random W3C span id: 16 lowercase hex digits.
+pub fn new_span_id() -> String {
+    let id = Uuid::new_v4().simple().to_string();
+    id[..16].to_string()
+}
+
+/// The context for an external request entering the runtime.
+///
+/// Continues the trace of `traceparent`, the W3C header of the caller, when
+/// it is valid, and starts a new trace otherwise. The request's root span
+/// gets a new span id either way.
+pub fn request_context(traceparent: Option<&str>) -> OpContext {
+    let parent = traceparent.and_then(OpContext::from_traceparent);
+    let trace_id = parent
+        .as_ref()
+        .and_then(|parent| parent.trace_id.clone())
+        .unwrap_or_else(new_trace_id);
+    let ctx = OpContext::new()
+        .with_trace_id(trace_id)
+        .with_span_id(new_span_id());
+    let span = tracing::info_span!(
+        "structfs.request",
+        trace_id = ctx.trace_id.as_deref(),
+        span_id = ctx.span_id.as_deref(),
+        parent_span_id = parent.as_ref().and_then(|parent| parent.span_id.as_deref()),
+    );
+    span.in_scope(|| tracing::debug!("request entered the runtime"));
+    ctx
+}
+
+/// Run the store operation `f` in a child span of the current trace.
+///
+/// `target` names what is being operated on, such as a Block id or an
+/// export. Outside a trace, `f` runs unchanged.
+pub(crate) fn traced<R>(target: &str, op: &'static str, path: &Path, f: impl FnOnce() -> R) -> R {
+    let current = OpContext::current();
+    let Some(trace_id) = current.trace_id.as_deref() else {
+        return f();
+    };
+    let span_id = new_span_id();
+    let span = tracing::info_span!(
+        "structfs.op",
+        otel.name = %format_args!("{} {}", op, path),
+        trace_id,
+        span_id = span_id.as_str(),
+        parent_span_id = current.span_id.as_deref(),
+        store = target,
+        op,
+        path = %path,
+    );
+    let _entered = span.enter();
+    OpContext::new().with_span_id(span_id).scope(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    #[test]
+    fn ids_have_w3c_lengths() {
+        assert_eq!(new_trace_id().len(), 32);
+        assert_eq!(new_span_id().len(), 16);
+        assert_ne!(new_span_id(), new_span_id());
+    }
+
+    #[test]
+    fn request_context_continues_incoming_trace() {
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let ctx = request_context(Some(incoming));
+        assert_eq!(
+            ctx.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_ne!(ctx.span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert!(ctx.traceparent().is_some());
+
+        let fresh = request_context(Some("garbage"));
+        assert_ne!(fresh.trace_id, ctx.trace_id);
+        assert!(fresh.traceparent().is_some());
+    }
+
+    #[test]
+    fn traced_operations_nest() {
+        let ctx = request_context(None);
+        let root_span = ctx.span_id.clone();
+        let (outer, inner) = ctx.scope(|| {
+            traced("a", "read", &path!("x"), || {
+                let outer = OpContext::current();
+                let inner = traced("b", "write", &path!("y"), OpContext::current);
+                (outer, inner)
+            })
+        });
+        assert_eq!(outer.trace_id, ctx.trace_id);
+        assert_eq!(inner.trace_id, ctx.trace_id);
+        assert_ne!(outer.span_id, root_span);
+        assert_ne!(inner.span_id, outer.span_id);
+
+        let untraced = traced("a", "read", &path!("x"), OpContext::current);
+        assert!(untraced.span_id.is_none());
+    }
+}
@@ -0,0 +1,70 @@
+//! Warm instance pools for request-scoped WASM Blocks.
+//!
+//! Running a [`WasmBlock`](crate::WasmBlock) from scratch compiles its
+//! component, links it and allocates its memories, which takes
+//! milliseconds. Blocks spawned per request can skip all of that:
+//! [`Runtime::prepare_wasm`](crate::Runtime::prepare_wasm) compiles and
+//! links a module once into a [`PreparedWasmBlock`](crate::PreparedWasmBlock),
+//! and each spawn of it only instantiates, which takes microseconds.
+//!
+//! With [`RuntimeConfig::warm_pool`](crate::RuntimeConfig::warm_pool) set,
+//! prepared Blocks also draw their memories and tables from a pool of
+//! [`WarmPoolConfig::instances`] slots reserved up front and kept resident
+//! between runs, so instantiation does not map fresh memory either. Spawning
+//! more prepared Blocks at once than the pool holds fails until one
+//! finishes.
+
+use structfs_core_store::Error as StoreError;
+use wasmtime::{Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig};
+
+use crate::error::{Result, RuntimeError};
+
+/// Core instances, memories and tables reserved per pooled Block.
+const SLOTS_PER_INSTANCE: u32 = 16;
+
+/// Size and limits of the warm instance pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmPoolConfig {
+    /// Block instances that may run at once from the pool.
+    pub instances: u32,
+
+    /// Largest linear memory a pooled Block may grow to, in bytes.
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        Self {
+            instances: 16,
+            max_memory_bytes: 64 << 20,
+        }
+    }
+}
+
+/// The engine Blocks are compiled for: component model and fuel metering,
+/// plus the pooling allocator if `pool` is set.
+pub(crate) fn block_engine(pool: Option<&WarmPoolConfig>) -> Result<Engine> {
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    config.consume_fuel(true);
+
+    if let Some(pool) = pool {
+        let slots = pool.instances.saturating_mul(SLOTS_PER_INSTANCE);
+        let mut pooling = PoolingAllocationConfig::new();
+        pooling
+            .total_component_instances(pool.instances)
+            .max_core_instances_per_component(SLOTS_PER_INSTANCE)
+            .max_memories_per_component(SLOTS_PER_INSTANCE)
+            .max_tables_per_component(SLOTS_PER_INSTANCE)
+            .total_core_instances(slots)
+            .total_memories(slots)
+            .total_tables(slots)
+            .max_memory_size(pool.max_memory_bytes)
+            .linear_memory_keep_resident(pool.max_memory_bytes.min(1 << 20));
+        config.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling));
+        config.memory_reservation(pool.max_memory_bytes as u64);
+    }
+
+    Engine::new(&config)
+        .map_err(|e| RuntimeError::Store(StoreError::store("wasmtime", "engine", e.to_string())))
+}
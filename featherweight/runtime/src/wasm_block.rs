@@ -2,15 +2,36 @@
 //!
 //! This module provides the ability to load and run Blocks compiled to
 //! WebAssembly components.
+//!
+//! ## Encoded values
+//!
+//! The WIT `value` type only carries scalars. `read-encoded` and
+//! `write-encoded` exchange whole values as bytes in a format the guest
+//! names, so Blocks written in Python or JavaScript can pass maps and arrays
+//! as JSON and parse them with their standard library. The host encodes and
+//! decodes with the Block's codec, [`JsonCodec`] unless
+//! [`WasmBlock::with_codec`] sets another. A record the root store already
+//! holds in the requested format is passed through without re-encoding.
+//!
+//! See `featherweight/guest/shims/README.md` for the guest side.
 
 use std::sync::{Arc, Mutex};
 
-use structfs_core_store::{Error as StoreError, NoCodec, Path, Reader, Record, Value, Writer};
+use async_trait::async_trait;
+
+use structfs_core_store::{
+    Bytes, Codec, Error as StoreError, Format, NoCodec, Path, Reader, Record, RecordLimits, Value,
+    Writer,
+};
+use structfs_serde_store::JsonCodec;
 use wasmtime::component::{bindgen, Component, Linker, ResourceTable};
-use wasmtime::{Config, Engine, Store};
+use wasmtime::{Engine, Store, Trap, WasmBacktrace};
 
-use crate::block::BlockId;
+use crate::block::{Block, BlockContext, BlockId};
+use crate::cancel::Cancellation;
 use crate::error::{Result, RuntimeError};
+use crate::report::{FailureKind, RecentOps, StoreOpKind, TrapReport};
+use crate::warm_pool::block_engine;
 
 // Generate bindings from the WIT file
 bindgen!({
@@ -28,6 +49,19 @@ pub struct WasmBlockState<S> {
 
     /// Resource table for component model.
     pub table: ResourceTable,
+
+    /// Limits applied to values the Block writes.
+    /// [`RecordLimits::untrusted`] unless set.
+    pub limits: RecordLimits,
+
+    /// The Block's most recent store operations, for failure reports.
+    pub recent_ops: RecentOps,
+
+    /// Codec for `read-encoded` and `write-encoded`.
+    pub codec: Arc<dyn Codec>,
+
+    /// Answers `should-cancel`.
+    pub cancellation: Cancellation,
 }
 
 impl<S> WasmBlockState<S> {
@@ -37,8 +71,31 @@ impl<S> WasmBlockState<S> {
             id,
             root: Arc::new(Mutex::new(root)),
             table: ResourceTable::new(),
+            limits: RecordLimits::untrusted(),
+            recent_ops: RecentOps::default(),
+            codec: Arc::new(JsonCodec),
+            cancellation: Cancellation::new(),
         }
     }
+
+    /// Reject guest writes that exceed `limits` instead of
+    /// [`RecordLimits::untrusted`].
+    pub fn with_limits(mut self, limits: RecordLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Encode and decode values for encoded reads and writes with `codec`.
+    pub fn with_codec(mut self, codec: Arc<dyn Codec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Answer the guest's `should-cancel` from `cancellation`.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
 }
 
 /// Convert a StructFS Value to a WIT Value.
@@ -69,26 +126,101 @@ fn wit_to_value(val: featherweight::block::store::Value) -> Value {
     }
 }
 
-/// Implementation of the store interface for WASM Blocks.
-impl<S: Reader + Writer + Send + 'static> featherweight::block::store::Host for WasmBlockState<S> {
-    fn read(&mut self, path: String) -> featherweight::block::store::ReadResult {
-        use featherweight::block::store::ReadResult;
+impl<S: Writer> WasmBlockState<S> {
+    fn write_value(
+        &mut self,
+        path: &str,
+        value: Value,
+    ) -> featherweight::block::store::WriteResult {
+        use featherweight::block::store::WriteResult;
 
-        let path = match Path::parse(&path) {
+        let parsed_path = match Path::parse(path) {
             Ok(p) => p,
-            Err(e) => return ReadResult::ReadError(format!("invalid path: {}", e)),
+            Err(e) => return WriteResult::WriteError(format!("invalid path: {}", e)),
         };
+        if let Err(e) = self.limits.check(&value) {
+            return WriteResult::WriteError(e.to_string());
+        }
 
         let mut root = self.root.lock().unwrap();
-        match root.read(&path) {
-            Ok(Some(record)) => match record.into_value(&NoCodec) {
-                Ok(value) => ReadResult::Found(value_to_wit(&value)),
-                Err(e) => ReadResult::ReadError(e.to_string()),
+        match root.write(&parsed_path, Record::parsed(value)) {
+            Ok(result_path) => WriteResult::Written(result_path.to_string()),
+            Err(e) => WriteResult::WriteError(e.to_string()),
+        }
+    }
+
+    fn write_encoded_value(
+        &mut self,
+        path: &str,
+        format: String,
+        data: Vec<u8>,
+    ) -> featherweight::block::store::WriteResult {
+        use featherweight::block::store::WriteResult;
+
+        let record = Record::raw(Bytes::from(data), Format::new(format));
+        match record.into_value_limited(self.codec.as_ref(), &self.limits) {
+            Ok(value) => self.write_value(path, value),
+            Err(e) => WriteResult::WriteError(e.to_string()),
+        }
+    }
+}
+
+impl<S: Reader> WasmBlockState<S> {
+    fn read_encoded_value(
+        &mut self,
+        path: &str,
+        format: String,
+    ) -> featherweight::block::store::ReadEncodedResult {
+        use featherweight::block::store::ReadEncodedResult;
+
+        let parsed_path = match Path::parse(path) {
+            Ok(p) => p,
+            Err(e) => return ReadEncodedResult::ReadError(format!("invalid path: {}", e)),
+        };
+        let format = Format::new(format);
+        if !self.codec.supports(&format) {
+            return ReadEncodedResult::ReadError(StoreError::UnsupportedFormat(format).to_string());
+        }
+
+        let mut root = self.root.lock().unwrap();
+        match root.read(&parsed_path) {
+            Ok(Some(record)) => match record.into_bytes(self.codec.as_ref(), &format) {
+                Ok(bytes) => ReadEncodedResult::Found(bytes.to_vec()),
+                Err(e) => ReadEncodedResult::ReadError(e.to_string()),
             },
-            Ok(None) => ReadResult::NotFound,
-            Err(e) => ReadResult::ReadError(e.to_string()),
+            Ok(None) => ReadEncodedResult::NotFound,
+            Err(e) => ReadEncodedResult::ReadError(e.to_string()),
         }
     }
+}
+
+/// Implementation of the store interface for WASM Blocks.
+impl<S: Reader + Writer + Send + 'static> featherweight::block::store::Host for WasmBlockState<S> {
+    fn read(&mut self, path: String) -> featherweight::block::store::ReadResult {
+        use featherweight::block::store::ReadResult;
+
+        let result = match Path::parse(&path) {
+            Ok(parsed) => {
+                let mut root = self.root.lock().unwrap();
+                match root.read(&parsed) {
+                    Ok(Some(record)) => match record.into_value(&NoCodec) {
+                        Ok(value) => ReadResult::Found(value_to_wit(&value)),
+                        Err(e) => ReadResult::ReadError(e.to_string()),
+                    },
+                    Ok(None) => ReadResult::NotFound,
+                    Err(e) => ReadResult::ReadError(e.to_string()),
+                }
+            }
+            Err(e) => ReadResult::ReadError(format!("invalid path: {}", e)),
+        };
+
+        let error = match &result {
+            ReadResult::ReadError(e) => Some(e.clone()),
+            _ => None,
+        };
+        self.recent_ops.record(StoreOpKind::Read, &path, error);
+        result
+    }
 
     fn write(
         &mut self,
@@ -97,19 +229,73 @@ impl<S: Reader + Writer + Send + 'static> featherweight::block::store::Host for
     ) -> featherweight::block::store::WriteResult {
         use featherweight::block::store::WriteResult;
 
-        let parsed_path = match Path::parse(&path) {
-            Ok(p) => p,
-            Err(e) => return WriteResult::WriteError(format!("invalid path: {}", e)),
+        let result = self.write_value(&path, wit_to_value(val));
+        let error = match &result {
+            WriteResult::WriteError(e) => Some(e.clone()),
+            WriteResult::Written(_) => None,
         };
+        self.recent_ops.record(StoreOpKind::Write, &path, error);
+        result
+    }
 
-        let value = wit_to_value(val);
-        let record = Record::parsed(value);
+    fn read_encoded(
+        &mut self,
+        path: String,
+        format: String,
+    ) -> featherweight::block::store::ReadEncodedResult {
+        use featherweight::block::store::ReadEncodedResult;
 
-        let mut root = self.root.lock().unwrap();
-        match root.write(&parsed_path, record) {
-            Ok(result_path) => WriteResult::Written(result_path.to_string()),
-            Err(e) => WriteResult::WriteError(e.to_string()),
-        }
+        let result = self.read_encoded_value(&path, format);
+        let error = match &result {
+            ReadEncodedResult::ReadError(e) => Some(e.clone()),
+            _ => None,
+        };
+        self.recent_ops.record(StoreOpKind::Read, &path, error);
+        result
+    }
+
+    fn write_encoded(
+        &mut self,
+        path: String,
+        format: String,
+        data: Vec<u8>,
+    ) -> featherweight::block::store::WriteResult {
+        use featherweight::block::store::WriteResult;
+
+        let result = self.write_encoded_value(&path, format, data);
+        let error = match &result {
+            WriteResult::WriteError(e) => Some(e.clone()),
+            WriteResult::Written(_) => None,
+        };
+        self.recent_ops.record(StoreOpKind::Write, &path, error);
+        result
+    }
+
+    fn list_children(&mut self, path: String) -> featherweight::block::store::ListResult {
+        use featherweight::block::store::ListResult;
+
+        let result = match Path::parse(&path) {
+            Ok(parsed) => {
+                let mut root = self.root.lock().unwrap();
+                match structfs_core_store::Store::list(&mut *root, &parsed) {
+                    Ok(Some(names)) => ListResult::Found(names),
+                    Ok(None) => ListResult::NotFound,
+                    Err(e) => ListResult::ListError(e.to_string()),
+                }
+            }
+            Err(e) => ListResult::ListError(format!("invalid path: {}", e)),
+        };
+
+        let error = match &result {
+            ListResult::ListError(e) => Some(e.clone()),
+            _ => None,
+        };
+        self.recent_ops.record(StoreOpKind::Read, &path, error);
+        result
+    }
+
+    fn should_cancel(&mut self) -> bool {
+        self.cancellation.should_cancel()
     }
 }
 
@@ -117,12 +303,36 @@ impl<S: Reader + Writer + Send + 'static> featherweight::block::store::Host for
 pub struct WasmBlock {
     /// The compiled WASM component bytes.
     component_bytes: Vec<u8>,
+
+    /// Limits applied to values the Block writes.
+    limits: RecordLimits,
+
+    /// Codec for encoded reads and writes.
+    codec: Arc<dyn Codec>,
 }
 
 impl WasmBlock {
     /// Create a new WasmBlock from component bytes.
     pub fn new(component_bytes: Vec<u8>) -> Self {
-        Self { component_bytes }
+        Self {
+            component_bytes,
+            limits: RecordLimits::untrusted(),
+            codec: Arc::new(JsonCodec),
+        }
+    }
+
+    /// Reject writes from the Block that exceed `limits` instead of
+    /// [`RecordLimits::untrusted`].
+    pub fn with_limits(mut self, limits: RecordLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Serve `read-encoded` and `write-encoded` with `codec` instead of
+    /// JSON only.
+    pub fn with_codec(mut self, codec: Arc<dyn Codec>) -> Self {
+        self.codec = codec;
+        self
     }
 
     /// Load a WasmBlock from a file.
@@ -132,58 +342,189 @@ impl WasmBlock {
     }
 
     /// Run this WASM Block with the given root store.
+    ///
+    /// Compiles the component on every call; Blocks spawned often should be
+    /// [prepared](crate::Runtime::prepare_wasm) once instead.
+    ///
+    /// If the guest traps or returns an error, the result is
+    /// [`RuntimeError::BlockFailed`] with a [`TrapReport`].
     pub fn run<S: Reader + Writer + Send + 'static>(&self, id: BlockId, root: S) -> Result<()> {
-        // Create the Wasmtime engine with component model support
-        let mut config = Config::new();
-        config.wasm_component_model(true);
-        let engine = Engine::new(&config).map_err(|e| {
-            RuntimeError::Store(StoreError::store("wasmtime", "engine", e.to_string()))
-        })?;
+        self.prepare::<S>()?.run(id, root)
+    }
+
+    /// Run this WASM Block, answering its `should-cancel` calls from
+    /// `cancellation`.
+    pub fn run_cancellable<S: Reader + Writer + Send + 'static>(
+        &self,
+        id: BlockId,
+        root: S,
+        cancellation: Cancellation,
+    ) -> Result<()> {
+        self.prepare::<S>()?.run_cancellable(id, root, cancellation)
+    }
 
+    /// Compile and link this Block once, for running many times.
+    pub fn prepare<S: Reader + Writer + Send + 'static>(&self) -> Result<PreparedWasmBlock<S>> {
+        self.prepare_in(&block_engine(None)?)
+    }
+
+    /// Compile and link this Block for `engine`.
+    pub(crate) fn prepare_in<S: Reader + Writer + Send + 'static>(
+        &self,
+        engine: &Engine,
+    ) -> Result<PreparedWasmBlock<S>> {
         // Create the component from bytes
-        let component = Component::new(&engine, &self.component_bytes).map_err(|e| {
+        let component = Component::new(engine, &self.component_bytes).map_err(|e| {
             RuntimeError::Store(StoreError::store("wasmtime", "component", e.to_string()))
         })?;
 
         // Create the linker and add the store interface
-        let mut linker = Linker::<WasmBlockState<S>>::new(&engine);
+        let mut linker = Linker::<WasmBlockState<S>>::new(engine);
         BlockWorld::add_to_linker::<
             WasmBlockState<S>,
             wasmtime::component::HasSelf<WasmBlockState<S>>,
         >(&mut linker, |state: &mut WasmBlockState<S>| state)
         .map_err(|e| RuntimeError::Store(StoreError::store("wasmtime", "linker", e.to_string())))?;
 
-        // Create the store with our state
-        let state = WasmBlockState::new(id, root);
-        let mut store = Store::new(&engine, state);
+        let pre = linker
+            .instantiate_pre(&component)
+            .and_then(BlockWorldPre::new)
+            .map_err(|e| {
+                RuntimeError::Store(StoreError::store("wasmtime", "link", e.to_string()))
+            })?;
+
+        Ok(PreparedWasmBlock {
+            engine: engine.clone(),
+            pre,
+            limits: self.limits,
+            codec: self.codec.clone(),
+        })
+    }
+}
+
+/// A [`WasmBlock`] compiled and linked ahead of time.
+///
+/// Each run only instantiates the component, drawing from the runtime's
+/// [warm pool](crate::warm_pool) if it has one. Clones share the compiled
+/// code, so one prepared Block can be spawned any number of times.
+pub struct PreparedWasmBlock<S: 'static> {
+    engine: Engine,
+    pre: BlockWorldPre<WasmBlockState<S>>,
+    limits: RecordLimits,
+    codec: Arc<dyn Codec>,
+}
+
+impl<S: 'static> Clone for PreparedWasmBlock<S> {
+    fn clone(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            pre: self.pre.clone(),
+            limits: self.limits,
+            codec: self.codec.clone(),
+        }
+    }
+}
+
+impl<S: Reader + Writer + Send + 'static> PreparedWasmBlock<S> {
+    /// Instantiate and run the Block with the given root store.
+    ///
+    /// If the guest traps or returns an error, the result is
+    /// [`RuntimeError::BlockFailed`] with a [`TrapReport`].
+    pub fn run(&self, id: BlockId, root: S) -> Result<()> {
+        self.run_cancellable(id, root, Cancellation::new())
+    }
+
+    /// Instantiate and run the Block, answering its `should-cancel` calls
+    /// from `cancellation`.
+    pub fn run_cancellable(&self, id: BlockId, root: S, cancellation: Cancellation) -> Result<()> {
+        // Create the store with our state, metering fuel so failure reports
+        // can say how much work the guest did
+        let state = WasmBlockState::new(id, root)
+            .with_limits(self.limits)
+            .with_codec(self.codec.clone())
+            .with_cancellation(cancellation);
+        let mut store = Store::new(&self.engine, state);
+        store.set_fuel(u64::MAX).map_err(|e| {
+            RuntimeError::Store(StoreError::store("wasmtime", "fuel", e.to_string()))
+        })?;
 
         // Instantiate the component
-        let instance = BlockWorld::instantiate(&mut store, &component, &linker).map_err(|e| {
+        let instance = self.pre.instantiate(&mut store).map_err(|e| {
             RuntimeError::Store(StoreError::store("wasmtime", "instantiate", e.to_string()))
         })?;
 
         // Call the block's run function
-        let result = instance
-            .featherweight_block_block()
-            .call_run(&mut store)
-            .map_err(|e| {
-                RuntimeError::Store(StoreError::store("wasmtime", "call_run", e.to_string()))
-            })?;
+        let (kind, message, backtrace) =
+            match instance.featherweight_block_block().call_run(&mut store) {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(msg)) => (FailureKind::GuestError, msg, Vec::new()),
+                Err(e) => {
+                    let message = match e.downcast_ref::<Trap>() {
+                        Some(trap) => format!("wasm trap: {}", trap),
+                        None => e.to_string(),
+                    };
+                    let backtrace = e
+                        .downcast_ref::<WasmBacktrace>()
+                        .map(backtrace_frames)
+                        .unwrap_or_default();
+                    (FailureKind::Trap, message, backtrace)
+                }
+            };
 
-        match result {
-            Ok(()) => Ok(()),
-            Err(msg) => Err(RuntimeError::Store(StoreError::store(
-                "wasm_block",
-                "run",
-                msg,
-            ))),
-        }
+        let fuel_consumed = store.get_fuel().ok().map(|left| u64::MAX - left);
+        let ops = &store.data().recent_ops;
+        Err(RuntimeError::BlockFailed(Box::new(TrapReport {
+            block: id,
+            kind,
+            message,
+            backtrace,
+            fuel_consumed,
+            recent_ops: ops.to_vec(),
+            failing_path: ops.failing_path(),
+        })))
+    }
+}
+
+/// Frames of a WASM backtrace, innermost first, as `module!function`.
+fn backtrace_frames(backtrace: &WasmBacktrace) -> Vec<String> {
+    backtrace
+        .frames()
+        .iter()
+        .map(|frame| {
+            let func = match frame.func_name() {
+                Some(name) => name.to_string(),
+                None => format!("<wasm function {}>", frame.func_index()),
+            };
+            match frame.module().name() {
+                Some(module) => format!("{}!{}", module, func),
+                None => func,
+            }
+        })
+        .collect()
+}
+
+/// Runs the component through the [`Runtime`](crate::Runtime), so traps are
+/// recorded as the Block's last failure.
+#[async_trait]
+impl<S: Reader + Writer + Send + 'static> Block<S> for WasmBlock {
+    async fn run(&mut self, ctx: BlockContext<S>) -> Result<()> {
+        let cancellation = ctx.cancellation().clone();
+        WasmBlock::run_cancellable(self, ctx.id, ctx.root, cancellation)
+    }
+}
+
+#[async_trait]
+impl<S: Reader + Writer + Send + 'static> Block<S> for PreparedWasmBlock<S> {
+    async fn run(&mut self, ctx: BlockContext<S>) -> Result<()> {
+        let cancellation = ctx.cancellation().clone();
+        PreparedWasmBlock::run_cancellable(self, ctx.id, ctx.root, cancellation)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::warm_pool::WarmPoolConfig;
     use collection_literals::btree;
 
     #[test]
@@ -259,6 +600,9 @@ mod tests {
         let id = BlockId::new();
         let state = WasmBlockState::new(id, TestStore);
         assert_eq!(state.id, id);
+        // Guest writes are untrusted until told otherwise
+        assert_eq!(state.limits, RecordLimits::untrusted());
+        assert_eq!(WasmBlock::new(Vec::new()).limits, RecordLimits::untrusted());
     }
 
     #[test]
@@ -421,6 +765,192 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn wasm_block_state_host_write_exceeds_limits() {
+        struct TestStore;
+        impl Reader for TestStore {
+            fn read(
+                &mut self,
+                _path: &Path,
+            ) -> std::result::Result<Option<Record>, structfs_core_store::Error> {
+                Ok(None)
+            }
+        }
+        impl Writer for TestStore {
+            fn write(
+                &mut self,
+                path: &Path,
+                _record: Record,
+            ) -> std::result::Result<Path, structfs_core_store::Error> {
+                Ok(path.clone())
+            }
+        }
+
+        use featherweight::block::store::Host;
+        let mut state = WasmBlockState::new(BlockId::new(), TestStore).with_limits(RecordLimits {
+            max_bytes: Some(4),
+            ..Default::default()
+        });
+        let result = state.write(
+            "output/test".to_string(),
+            featherweight::block::store::Value::ValText("too long".to_string()),
+        );
+        assert!(matches!(
+            result,
+            featherweight::block::store::WriteResult::WriteError(e) if e.contains("bytes limit")
+        ));
+    }
+
+    /// A component whose `run` calls a helper that traps.
+    const TRAPPING_COMPONENT: &str = r#"
+        (component
+          (core module $m
+            (memory (export "memory") 1)
+            (func $explode unreachable)
+            (func (export "run") (result i32)
+              call $explode
+              i32.const 0))
+          (core instance $i (instantiate $m))
+          (func $run (result (result (error string)))
+            (canon lift (core func $i "run") (memory $i "memory")))
+          (instance $block (export "run" (func $run)))
+          (export "featherweight:block/block@0.1.0" (instance $block)))
+    "#;
+
+    /// A component whose `run` returns `Err("boom")`.
+    const FAILING_COMPONENT: &str = r#"
+        (component
+          (core module $m
+            (memory (export "memory") 1)
+            (data (i32.const 0) "\01\00\00\00\10\00\00\00\04\00\00\00")
+            (data (i32.const 16) "boom")
+            (func (export "run") (result i32) i32.const 0))
+          (core instance $i (instantiate $m))
+          (func $run (result (result (error string)))
+            (canon lift (core func $i "run") (memory $i "memory")))
+          (instance $block (export "run" (func $run)))
+          (export "featherweight:block/block@0.1.0" (instance $block)))
+    "#;
+
+    struct EmptyStore;
+    impl Reader for EmptyStore {
+        fn read(
+            &mut self,
+            _path: &Path,
+        ) -> std::result::Result<Option<Record>, structfs_core_store::Error> {
+            Ok(None)
+        }
+    }
+    impl Writer for EmptyStore {
+        fn write(
+            &mut self,
+            path: &Path,
+            _record: Record,
+        ) -> std::result::Result<Path, structfs_core_store::Error> {
+            Ok(path.clone())
+        }
+    }
+
+    fn run_to_report(component: &str) -> TrapReport {
+        let block = WasmBlock::new(component.as_bytes().to_vec());
+        match block.run(BlockId::new(), EmptyStore) {
+            Err(RuntimeError::BlockFailed(report)) => *report,
+            other => panic!("expected BlockFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wasm_block_trap_report() {
+        let report = run_to_report(TRAPPING_COMPONENT);
+        assert_eq!(report.kind, FailureKind::Trap);
+        assert!(report.message.contains("unreachable"), "{}", report.message);
+        assert!(
+            report.backtrace.iter().any(|f| f.ends_with("explode")),
+            "{:?}",
+            report.backtrace
+        );
+        assert!(report.fuel_consumed.is_some_and(|f| f > 0));
+    }
+
+    #[test]
+    fn wasm_block_guest_error_report() {
+        let report = run_to_report(FAILING_COMPONENT);
+        assert_eq!(report.kind, FailureKind::GuestError);
+        assert_eq!(report.message, "boom");
+        assert!(report.backtrace.is_empty());
+    }
+
+    #[test]
+    fn prepared_block_runs_from_warm_pool() {
+        let pool = WarmPoolConfig {
+            instances: 1,
+            ..Default::default()
+        };
+        let engine = block_engine(Some(&pool)).unwrap();
+        let prepared = WasmBlock::new(FAILING_COMPONENT.as_bytes().to_vec())
+            .prepare_in::<EmptyStore>(&engine)
+            .unwrap();
+        // Each run gives its slot back, so one slot serves every run
+        for _ in 0..3 {
+            match prepared.clone().run(BlockId::new(), EmptyStore) {
+                Err(RuntimeError::BlockFailed(report)) => assert_eq!(report.message, "boom"),
+                other => panic!("expected BlockFailed, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_spawns_prepared_blocks() {
+        let mut runtime = crate::Runtime::new(crate::RuntimeConfig {
+            warm_pool: Some(WarmPoolConfig::default()),
+            ..Default::default()
+        });
+        let prepared = runtime
+            .prepare_wasm::<EmptyStore>(&WasmBlock::new(FAILING_COMPONENT.as_bytes().to_vec()))
+            .unwrap();
+        let handles = [
+            runtime.spawn(prepared.clone(), EmptyStore).await.unwrap(),
+            runtime.spawn(prepared, EmptyStore).await.unwrap(),
+        ];
+
+        for handle in handles {
+            let mut report = None;
+            for _ in 0..1000 {
+                report = runtime.last_error(handle.id);
+                if report.is_some() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(report.expect("failure recorded").message, "boom");
+        }
+    }
+
+    #[test]
+    fn wasm_block_state_records_recent_ops() {
+        use featherweight::block::store::Host;
+        let mut state = WasmBlockState::new(BlockId::new(), EmptyStore);
+        state.read("input/name".to_string());
+        state.write(
+            "bad-path".to_string(),
+            featherweight::block::store::Value::ValNull,
+        );
+        state.write(
+            "output/x".to_string(),
+            featherweight::block::store::Value::ValNull,
+        );
+
+        let ops = state.recent_ops.to_vec();
+        assert_eq!(ops.len(), 3);
+        assert_eq!(ops[0].kind, StoreOpKind::Read);
+        assert_eq!(ops[0].error, None);
+        assert!(ops[1].error.is_some());
+        assert_eq!(
+            state.recent_ops.failing_path(),
+            Some("bad-path".to_string())
+        );
+    }
+
     #[test]
     fn wasm_block_new() {
         let bytes = vec![0x00, 0x61, 0x73, 0x6d]; // WASM magic bytes
@@ -549,4 +1079,135 @@ mod tests {
             featherweight::block::store::ReadResult::ReadError(_)
         ));
     }
+
+    /// Store backed by a shared map, holding whatever records it is given.
+    #[derive(Clone, Default)]
+    struct MapStore(Arc<Mutex<std::collections::BTreeMap<Path, Record>>>);
+
+    impl Reader for MapStore {
+        fn read(
+            &mut self,
+            path: &Path,
+        ) -> std::result::Result<Option<Record>, structfs_core_store::Error> {
+            Ok(self.0.lock().unwrap().get(path).cloned())
+        }
+    }
+    impl Writer for MapStore {
+        fn write(
+            &mut self,
+            path: &Path,
+            record: Record,
+        ) -> std::result::Result<Path, structfs_core_store::Error> {
+            self.0.lock().unwrap().insert(path.clone(), record);
+            Ok(path.clone())
+        }
+    }
+
+    #[test]
+    fn wasm_block_state_host_read_encoded_json() {
+        use featherweight::block::store::{Host, ReadEncodedResult};
+        let store = MapStore::default();
+        store.0.lock().unwrap().insert(
+            structfs_core_store::path!("config"),
+            Record::parsed(Value::Map(btree! {
+                "tags".to_string() => Value::Array(vec![Value::from("a")]),
+            })),
+        );
+        let mut state = WasmBlockState::new(BlockId::new(), store);
+
+        let result = state.read_encoded("config".to_string(), "application/json".to_string());
+        let ReadEncodedResult::Found(bytes) = result else {
+            panic!("expected found, got {:?}", result);
+        };
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json, serde_json::json!({"tags": ["a"]}));
+
+        assert!(matches!(
+            state.read_encoded("missing".to_string(), "application/json".to_string()),
+            ReadEncodedResult::NotFound
+        ));
+        assert!(matches!(
+            state.read_encoded("config".to_string(), "application/x-unknown".to_string()),
+            ReadEncodedResult::ReadError(e) if e.contains("application/x-unknown")
+        ));
+    }
+
+    #[test]
+    fn wasm_block_state_host_list() {
+        use featherweight::block::store::{Host, ListResult};
+        let store = MapStore::default();
+        store.0.lock().unwrap().insert(
+            structfs_core_store::path!("config"),
+            Record::parsed(Value::Map(btree! {
+                "name".to_string() => Value::from("a"),
+                "tags".to_string() => Value::Array(vec![]),
+            })),
+        );
+        let mut state = WasmBlockState::new(BlockId::new(), store);
+
+        let result = state.list_children("config".to_string());
+        assert!(
+            matches!(&result, ListResult::Found(names) if names == &["name", "tags"]),
+            "{:?}",
+            result
+        );
+        assert!(matches!(
+            state.list_children("missing".to_string()),
+            ListResult::NotFound
+        ));
+    }
+
+    #[test]
+    fn wasm_block_state_host_read_encoded_passes_raw_through() {
+        use featherweight::block::store::{Host, ReadEncodedResult};
+        let store = MapStore::default();
+        // Not valid JSON, so it can only come back unchanged
+        store.0.lock().unwrap().insert(
+            structfs_core_store::path!("blob"),
+            Record::raw(&b"{not json"[..], Format::JSON),
+        );
+        let mut state = WasmBlockState::new(BlockId::new(), store);
+
+        let result = state.read_encoded("blob".to_string(), "application/json".to_string());
+        assert!(matches!(result, ReadEncodedResult::Found(bytes) if bytes == b"{not json"));
+    }
+
+    #[test]
+    fn wasm_block_state_host_write_encoded_json() {
+        use featherweight::block::store::{Host, WriteResult};
+        let store = MapStore::default();
+        let mut state =
+            WasmBlockState::new(BlockId::new(), store.clone()).with_limits(RecordLimits {
+                max_depth: Some(2),
+                ..RecordLimits::unlimited()
+            });
+
+        let result = state.write_encoded(
+            "output/user".to_string(),
+            "application/json".to_string(),
+            br#"{"name": "Ada", "roles": ["admin"]}"#.to_vec(),
+        );
+        assert!(matches!(result, WriteResult::Written(p) if p == "output/user"));
+        let written = store.0.lock().unwrap()[&structfs_core_store::path!("output/user")].clone();
+        assert_eq!(
+            written.into_value(&NoCodec).unwrap(),
+            Value::Map(btree! {
+                "name".to_string() => Value::from("Ada"),
+                "roles".to_string() => Value::Array(vec![Value::from("admin")]),
+            })
+        );
+
+        for data in [&b"{broken"[..], &br#"[[["too deep"]]]"#[..]] {
+            let result = state.write_encoded(
+                "output/bad".to_string(),
+                "application/json".to_string(),
+                data.to_vec(),
+            );
+            assert!(matches!(result, WriteResult::WriteError(_)));
+        }
+        assert_eq!(
+            state.recent_ops.failing_path(),
+            Some("output/bad".to_string())
+        );
+    }
 }
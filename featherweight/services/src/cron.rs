@@ -0,0 +1,323 @@
+//! Scheduled writes.
+//!
+//! A [`CronBlock`] holds a set of jobs, each writing a fixed value to a path
+//! in the Block's root store at a fixed interval or on a cron expression.
+//! Mount whatever should receive the writes (a channel, a queue, another
+//! Block's export) at the job's path.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use featherweight_runtime::{Block, BlockContext, Result};
+use structfs_core_store::{Path, Record, Value, Writer};
+use structfs_sys::CronSchedule;
+use tokio::time::Instant;
+
+/// When a [`CronJob`] runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// At a fixed interval, first one interval after the Block starts.
+    Every(Duration),
+
+    /// At the fire times of a cron expression, in UTC.
+    Cron(CronSchedule),
+}
+
+use crate::Shutdown;
+
+/// A job run by a [`CronBlock`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronJob {
+    /// Name used when reporting failures.
+    pub name: String,
+
+    /// When the job runs.
+    pub schedule: Schedule,
+
+    /// Path in the root store to write to.
+    pub path: Path,
+
+    /// Value written on each run.
+    pub value: Value,
+}
+
+/// Service Block that writes values on a schedule.
+///
+/// Interval jobs first run one interval after the Block starts; cron jobs at
+/// the first fire time after it starts. A failed write is logged and the job
+/// runs again at its next time. Runs missed while the Block was busy are
+/// skipped, not made up.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use featherweight_services::CronBlock;
+/// use structfs_core_store::{path, Value};
+///
+/// let cron = CronBlock::new()
+///     .every("heartbeat", Duration::from_secs(30), path!("status/heartbeat"), Value::Bool(true))
+///     .cron("report", "0 0 6 * * mon-fri", path!("reports/daily"), Value::Null)
+///     .unwrap();
+/// assert_eq!(cron.jobs().len(), 2);
+/// ```
+#[derive(Default)]
+pub struct CronBlock {
+    jobs: Vec<CronJob>,
+    shutdown: Shutdown,
+}
+
+impl CronBlock {
+    /// Create a Block with no jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a job.
+    pub fn job(mut self, job: CronJob) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// Add a job that writes `value` to `path` every `every`.
+    pub fn every(
+        self,
+        name: impl Into<String>,
+        every: Duration,
+        path: Path,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.job(CronJob {
+            name: name.into(),
+            schedule: Schedule::Every(every),
+            path,
+            value: value.into(),
+        })
+    }
+
+    /// Add a job that writes `value` to `path` at the fire times of the
+    /// cron expression `expr`.
+    ///
+    /// Fails if `expr` is not a valid expression; see [`CronSchedule`].
+    pub fn cron(
+        self,
+        name: impl Into<String>,
+        expr: &str,
+        path: Path,
+        value: impl Into<Value>,
+    ) -> Result<Self> {
+        let schedule = CronSchedule::parse(expr)?;
+        Ok(self.job(CronJob {
+            name: name.into(),
+            schedule: Schedule::Cron(schedule),
+            path,
+            value: value.into(),
+        }))
+    }
+
+    /// The configured jobs.
+    pub fn jobs(&self) -> &[CronJob] {
+        &self.jobs
+    }
+
+    /// The signal that stops this Block.
+    pub fn shutdown(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+}
+
+#[async_trait]
+impl<S: Writer + Send + 'static> Block<S> for CronBlock {
+    async fn run(&mut self, mut ctx: BlockContext<S>) -> Result<()> {
+        let clock = Clock::now();
+        let mut next: Vec<Option<Instant>> = self
+            .jobs
+            .iter()
+            .map(|job| match &job.schedule {
+                Schedule::Every(every) => Some(clock.start + *every),
+                Schedule::Cron(cron) => cron.next_after(clock.wall).map(|at| clock.instant(at)),
+            })
+            .collect();
+
+        loop {
+            let Some((index, due)) = next
+                .iter()
+                .enumerate()
+                .filter_map(|(index, at)| Some((index, (*at)?)))
+                .min_by_key(|(_, at)| *at)
+            else {
+                // Nothing scheduled; idle until stopped
+                self.shutdown.wait().await;
+                return Ok(());
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(due) => {}
+                _ = self.shutdown.wait() => return Ok(()),
+            }
+
+            let job = &self.jobs[index];
+            if let Err(e) = ctx.root.write(&job.path, Record::parsed(job.value.clone())) {
+                tracing::warn!(block = %ctx.id, job = %job.name, "cron write failed: {}", e);
+            }
+            // Schedule from the due time so runs do not drift, skipping
+            // any that were missed
+            let now = Instant::now();
+            next[index] = match &job.schedule {
+                Schedule::Every(every) => {
+                    let mut at = due + *every;
+                    while at <= now {
+                        at += *every;
+                    }
+                    Some(at)
+                }
+                Schedule::Cron(cron) => cron
+                    .next_after(clock.wall(now.max(due)))
+                    .map(|at| clock.instant(at)),
+            };
+        }
+    }
+}
+
+/// Maps wall-clock fire times onto the tokio clock, which timers run on.
+///
+/// Anchored once when the Block starts, so cron jobs keep to paused time
+/// in tests; a later jump of the system clock is not followed.
+struct Clock {
+    start: Instant,
+    wall: DateTime<Utc>,
+}
+
+impl Clock {
+    fn now() -> Self {
+        Self {
+            start: Instant::now(),
+            wall: Utc::now(),
+        }
+    }
+
+    fn instant(&self, at: DateTime<Utc>) -> Instant {
+        self.start + (at - self.wall).to_std().unwrap_or_default()
+    }
+
+    fn wall(&self, at: Instant) -> DateTime<Utc> {
+        self.wall
+            + chrono::Duration::from_std(at - self.start)
+                .unwrap_or_else(|_| chrono::Duration::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use featherweight_runtime::BlockId;
+    use std::sync::{Arc, Mutex};
+    use structfs_core_store::{path, Error};
+
+    /// Records every write with the time it arrived.
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<(Duration, Path)>>>, Option<Instant>);
+
+    impl Writer for Log {
+        fn write(&mut self, path: &Path, _record: Record) -> std::result::Result<Path, Error> {
+            let start = self.1.expect("start time");
+            self.0.lock().unwrap().push((start.elapsed(), path.clone()));
+            Ok(path.clone())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jobs_run_on_their_schedules() {
+        let log = Log(Default::default(), Some(Instant::now()));
+        let writes = log.0.clone();
+
+        let mut cron = CronBlock::new()
+            .every("fast", Duration::from_secs(2), path!("fast"), 1)
+            .every("slow", Duration::from_secs(3), path!("slow"), 2);
+        let shutdown = cron.shutdown();
+        let task =
+            tokio::spawn(async move { cron.run(BlockContext::new(BlockId::new(), log)).await });
+
+        tokio::time::sleep(Duration::from_millis(6500)).await;
+        shutdown.trigger();
+        task.await.unwrap().unwrap();
+
+        let writes: Vec<(u64, String)> = writes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(at, path)| (at.as_secs(), path.to_string()))
+            .collect();
+        assert_eq!(
+            writes,
+            vec![
+                (2, "fast".to_string()),
+                (3, "slow".to_string()),
+                (4, "fast".to_string()),
+                (6, "fast".to_string()),
+                (6, "slow".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_writes_do_not_stop_the_block() {
+        struct Failing(Arc<Mutex<usize>>);
+        impl Writer for Failing {
+            fn write(&mut self, _path: &Path, _record: Record) -> std::result::Result<Path, Error> {
+                *self.0.lock().unwrap() += 1;
+                Err(Error::store("test", "write", "unavailable"))
+            }
+        }
+
+        let attempts = Arc::new(Mutex::new(0));
+        let mut cron = CronBlock::new().every("job", Duration::from_secs(1), path!("x"), 1);
+        let shutdown = cron.shutdown();
+        let root = Failing(attempts.clone());
+        let task =
+            tokio::spawn(async move { cron.run(BlockContext::new(BlockId::new(), root)).await });
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        shutdown.trigger();
+        task.await.unwrap().unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cron_jobs_run_at_fire_times() {
+        let log = Log(Default::default(), Some(Instant::now()));
+        let writes = log.0.clone();
+
+        let mut cron = CronBlock::new()
+            .cron("tick", "* * * * * *", path!("tick"), 1)
+            .unwrap();
+        let shutdown = cron.shutdown();
+        let task =
+            tokio::spawn(async move { cron.run(BlockContext::new(BlockId::new(), log)).await });
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        shutdown.trigger();
+        task.await.unwrap().unwrap();
+
+        // Once per second, at whole seconds of the wall clock
+        let writes = writes.lock().unwrap();
+        assert!((3..=4).contains(&writes.len()), "{:?}", writes);
+        for pair in writes.windows(2) {
+            assert_eq!(pair[1].0 - pair[0].0, Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn invalid_cron_expressions_are_rejected() {
+        let result = CronBlock::new().cron("bad", "not a schedule", path!("x"), 1);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn no_jobs_waits_for_shutdown() {
+        let mut cron = CronBlock::new();
+        cron.shutdown().trigger();
+        cron.run(BlockContext::new(BlockId::new(), Log::default()))
+            .await
+            .unwrap();
+    }
+}
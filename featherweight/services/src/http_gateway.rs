@@ -0,0 +1,190 @@
+//! HTTP gateway service.
+//!
+//! The gateway maps path prefixes to upstream HTTP services, so Blocks reach
+//! every backend through a single mount. With a route from `users` to
+//! `https://users.internal/api`, reading `users/42` from the gateway store
+//! sends `GET https://users.internal/api/42`.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use featherweight_runtime::{Block, BlockContext, Result};
+use structfs_core_store::overlay_store::{OverlayStore, Store};
+use structfs_core_store::{Error, Path, Reader, Record, Writer};
+use structfs_http::HttpClientStore;
+
+use crate::Shutdown;
+
+/// Store interface to an [`HttpGatewayBlock`]'s routes.
+///
+/// Clones share the same routes. Paths with no route fail with
+/// [`Error::NoRoute`].
+#[derive(Clone)]
+pub struct HttpGatewayStore {
+    routes: Arc<Mutex<OverlayStore>>,
+}
+
+impl Reader for HttpGatewayStore {
+    fn read(&mut self, path: &Path) -> std::result::Result<Option<Record>, Error> {
+        self.routes.lock().unwrap().read(path)
+    }
+}
+
+impl Writer for HttpGatewayStore {
+    fn write(&mut self, path: &Path, record: Record) -> std::result::Result<Path, Error> {
+        self.routes.lock().unwrap().write(path, record)
+    }
+}
+
+/// HTTP gateway service Block.
+///
+/// Routes can be added while the Block is running; the Block itself only
+/// waits for shutdown.
+#[derive(Default)]
+pub struct HttpGatewayBlock {
+    routes: Arc<Mutex<OverlayStore>>,
+    shutdown: Shutdown,
+}
+
+impl HttpGatewayBlock {
+    /// Create a gateway with no routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route `prefix` to the HTTP service at `base_url`.
+    pub fn route(self, prefix: &str, base_url: &str) -> std::result::Result<Self, Error> {
+        let store = HttpClientStore::new(base_url)
+            .map_err(|e| Error::store("http_gateway", "route", e.to_string()))?;
+        self.route_store(prefix, store)
+    }
+
+    /// Route `prefix` to any store, such as an [`HttpClientStore`] with
+    /// custom headers or executor.
+    pub fn route_store<S: Store + Send + Sync + 'static>(
+        self,
+        prefix: &str,
+        store: S,
+    ) -> std::result::Result<Self, Error> {
+        let prefix = Path::parse(prefix)?;
+        self.routes.lock().unwrap().mount(prefix, store);
+        Ok(self)
+    }
+
+    /// The store to export for other Blocks.
+    pub fn store(&self) -> HttpGatewayStore {
+        HttpGatewayStore {
+            routes: self.routes.clone(),
+        }
+    }
+
+    /// The signal that stops this Block.
+    pub fn shutdown(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+}
+
+#[async_trait]
+impl<S: Send + 'static> Block<S> for HttpGatewayBlock {
+    async fn run(&mut self, _ctx: BlockContext<S>) -> Result<()> {
+        self.shutdown.wait().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use featherweight_runtime::BlockId;
+    use std::collections::HashMap;
+    use structfs_core_store::{path, NoCodec, Value};
+    use structfs_http::executor::HttpExecutor;
+    use structfs_http::{HttpRequest, HttpResponse};
+
+    /// Answers every request with its own URL.
+    struct EchoExecutor;
+
+    impl HttpExecutor for EchoExecutor {
+        fn execute(&self, request: &HttpRequest) -> std::result::Result<HttpResponse, String> {
+            Ok(HttpResponse {
+                status: 200,
+                status_text: "OK".to_string(),
+                headers: HashMap::new(),
+                body: serde_json::Value::String(request.path.clone()),
+                body_text: None,
+            })
+        }
+    }
+
+    fn echo(base_url: &str) -> HttpClientStore<EchoExecutor> {
+        HttpClientStore::with_executor(base_url, EchoExecutor).unwrap()
+    }
+
+    fn read_string(store: &mut HttpGatewayStore, path: &Path) -> Value {
+        store
+            .read(path)
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap()
+    }
+
+    #[test]
+    fn routes_by_prefix() {
+        let gateway = HttpGatewayBlock::new()
+            .route_store("users", echo("https://users.test/api/"))
+            .unwrap()
+            .route_store("billing/v2", echo("https://billing.test/"))
+            .unwrap();
+        let mut store = gateway.store();
+
+        assert_eq!(
+            read_string(&mut store, &path!("users/42")),
+            Value::from("https://users.test/api/42")
+        );
+        assert_eq!(
+            read_string(&mut store, &path!("billing/v2/invoices")),
+            Value::from("https://billing.test/invoices")
+        );
+    }
+
+    #[test]
+    fn unrouted_path_fails() {
+        let mut store = HttpGatewayBlock::new().store();
+        assert!(matches!(
+            store.read(&path!("nowhere")),
+            Err(Error::NoRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn route_rejects_bad_input() {
+        assert!(HttpGatewayBlock::new().route("api", "not a url").is_err());
+        assert!(HttpGatewayBlock::new()
+            .route_store("bad-prefix", echo("https://x.test/"))
+            .is_err());
+    }
+
+    #[test]
+    fn routes_added_after_store_is_taken() {
+        let gateway = HttpGatewayBlock::new();
+        let mut store = gateway.store();
+        let _gateway = gateway
+            .route_store("late", echo("https://late.test/"))
+            .unwrap();
+        assert_eq!(
+            read_string(&mut store, &path!("late/x")),
+            Value::from("https://late.test/x")
+        );
+    }
+
+    #[tokio::test]
+    async fn block_runs_until_shutdown() {
+        let mut gateway = HttpGatewayBlock::new();
+        gateway.shutdown().trigger();
+        gateway
+            .run(BlockContext::new(BlockId::new(), ()))
+            .await
+            .unwrap();
+    }
+}
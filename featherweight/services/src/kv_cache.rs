@@ -0,0 +1,362 @@
+//! Key-value cache service.
+//!
+//! Blocks write values at any path of the cache store and read them back
+//! until they expire or are evicted. Writing `null` removes an entry. Reading
+//! the root of the store returns cache statistics:
+//!
+//! ```text
+//! {"entries": 12, "hits": 40, "misses": 3, "evictions": 0, "expirations": 1}
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use featherweight_runtime::{Block, BlockContext, Result};
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+use tokio::time::Instant;
+
+use crate::Shutdown;
+
+/// Configuration for a [`KvCacheBlock`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KvCacheConfig {
+    /// Maximum number of entries. When full, the least recently used entry
+    /// is evicted.
+    pub max_entries: Option<usize>,
+
+    /// How long an entry lives after it is written.
+    pub ttl: Option<Duration>,
+
+    /// How often the Block removes expired entries.
+    pub sweep_interval: Duration,
+}
+
+impl Default for KvCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: Some(10_000),
+            ttl: None,
+            sweep_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+struct Entry {
+    value: Value,
+    expires: Option<Instant>,
+    last_used: u64,
+}
+
+/// Cache contents, shared between the Block and its store.
+#[derive(Default)]
+struct CacheState {
+    max_entries: Option<usize>,
+    ttl: Option<Duration>,
+    entries: BTreeMap<Path, Entry>,
+    /// Entry keys by last use, oldest first.
+    recency: BTreeMap<u64, Path>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    expirations: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: &Path) -> Option<Value> {
+        let now = Instant::now();
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.expires.is_some_and(|at| at <= now),
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+        if expired {
+            self.remove(key);
+            self.expirations += 1;
+            self.misses += 1;
+            return None;
+        }
+
+        let tick = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(tick, key.clone());
+        entry.last_used = tick;
+        self.hits += 1;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: Path, value: Value) {
+        self.remove(&key);
+        if let Some(max) = self.max_entries {
+            while self.entries.len() >= max {
+                let Some((_, oldest)) = self.recency.pop_first() else {
+                    break;
+                };
+                self.entries.remove(&oldest);
+                self.evictions += 1;
+            }
+            if max == 0 {
+                return;
+            }
+        }
+
+        let tick = self.tick();
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                expires: self.ttl.map(|ttl| Instant::now() + ttl),
+                last_used: tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &Path) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        Some(entry)
+    }
+
+    /// Remove every expired entry.
+    fn sweep(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Path> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires.is_some_and(|at| at <= now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.remove(&key);
+            self.expirations += 1;
+        }
+    }
+
+    fn stats(&self) -> Value {
+        let count = |n: u64| Value::Integer(n as i64);
+        Value::Map(BTreeMap::from([
+            ("entries".to_string(), count(self.entries.len() as u64)),
+            ("hits".to_string(), count(self.hits)),
+            ("misses".to_string(), count(self.misses)),
+            ("evictions".to_string(), count(self.evictions)),
+            ("expirations".to_string(), count(self.expirations)),
+        ]))
+    }
+}
+
+/// Store interface to a [`KvCacheBlock`]'s cache.
+///
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct KvCacheStore {
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl Reader for KvCacheStore {
+    fn read(&mut self, path: &Path) -> std::result::Result<Option<Record>, Error> {
+        let mut state = self.state.lock().unwrap();
+        if path.is_empty() {
+            return Ok(Some(Record::parsed(state.stats())));
+        }
+        Ok(state.get(path).map(Record::parsed))
+    }
+}
+
+impl Writer for KvCacheStore {
+    fn write(&mut self, path: &Path, record: Record) -> std::result::Result<Path, Error> {
+        if path.is_empty() {
+            return Err(Error::store(
+                "kv_cache",
+                "write",
+                "cannot write to the cache root",
+            ));
+        }
+        let value = record.into_value(&NoCodec)?;
+        let mut state = self.state.lock().unwrap();
+        if value.is_null() {
+            state.remove(path);
+        } else {
+            state.insert(path.clone(), value);
+        }
+        Ok(path.clone())
+    }
+}
+
+/// Key-value cache service Block.
+///
+/// The Block sweeps expired entries every `sweep_interval`; entries also
+/// expire lazily when read.
+pub struct KvCacheBlock {
+    state: Arc<Mutex<CacheState>>,
+    sweep_interval: Duration,
+    shutdown: Shutdown,
+}
+
+impl KvCacheBlock {
+    /// Create a cache with the given configuration.
+    pub fn new(config: KvCacheConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState {
+                max_entries: config.max_entries,
+                ttl: config.ttl,
+                ..Default::default()
+            })),
+            sweep_interval: config.sweep_interval,
+            shutdown: Shutdown::new(),
+        }
+    }
+
+    /// The store to export for other Blocks.
+    pub fn store(&self) -> KvCacheStore {
+        KvCacheStore {
+            state: self.state.clone(),
+        }
+    }
+
+    /// The signal that stops this Block.
+    pub fn shutdown(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+}
+
+#[async_trait]
+impl<S: Send + 'static> Block<S> for KvCacheBlock {
+    async fn run(&mut self, _ctx: BlockContext<S>) -> Result<()> {
+        let state = self.state.clone();
+        self.shutdown
+            .every(self.sweep_interval, || state.lock().unwrap().sweep())
+            .await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use featherweight_runtime::BlockId;
+    use structfs_core_store::path;
+
+    fn read(store: &mut KvCacheStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    fn stat(store: &mut KvCacheStore, name: &str) -> i64 {
+        match read(store, &Path::parse("").unwrap()) {
+            Some(Value::Map(map)) => match map[name] {
+                Value::Integer(n) => n,
+                _ => panic!("expected integer"),
+            },
+            _ => panic!("expected stats map"),
+        }
+    }
+
+    #[test]
+    fn write_read_remove() {
+        let mut store = KvCacheBlock::new(KvCacheConfig::default()).store();
+        store
+            .write(&path!("users/1"), Record::parsed(Value::from("ada")))
+            .unwrap();
+        assert_eq!(
+            read(&mut store, &path!("users/1")),
+            Some(Value::from("ada"))
+        );
+
+        store
+            .write(&path!("users/1"), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(read(&mut store, &path!("users/1")), None);
+        assert_eq!(stat(&mut store, "hits"), 1);
+        assert_eq!(stat(&mut store, "misses"), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut store = KvCacheBlock::new(KvCacheConfig {
+            max_entries: Some(2),
+            ..Default::default()
+        })
+        .store();
+        store.write(&path!("a"), Record::parsed(1.into())).unwrap();
+        store.write(&path!("b"), Record::parsed(2.into())).unwrap();
+        // Touch a so b is the oldest
+        read(&mut store, &path!("a"));
+        store.write(&path!("c"), Record::parsed(3.into())).unwrap();
+
+        assert_eq!(read(&mut store, &path!("a")), Some(Value::from(1)));
+        assert_eq!(read(&mut store, &path!("b")), None);
+        assert_eq!(read(&mut store, &path!("c")), Some(Value::from(3)));
+        assert_eq!(stat(&mut store, "evictions"), 1);
+    }
+
+    #[test]
+    fn overwrite_does_not_evict() {
+        let mut store = KvCacheBlock::new(KvCacheConfig {
+            max_entries: Some(1),
+            ..Default::default()
+        })
+        .store();
+        store.write(&path!("a"), Record::parsed(1.into())).unwrap();
+        store.write(&path!("a"), Record::parsed(2.into())).unwrap();
+        assert_eq!(read(&mut store, &path!("a")), Some(Value::from(2)));
+        assert_eq!(stat(&mut store, "evictions"), 0);
+    }
+
+    #[test]
+    fn root_is_not_writable() {
+        let mut store = KvCacheBlock::new(KvCacheConfig::default()).store();
+        assert!(store
+            .write(&Path::parse("").unwrap(), Record::parsed(1.into()))
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn entries_expire_on_read() {
+        let mut store = KvCacheBlock::new(KvCacheConfig {
+            ttl: Some(Duration::from_secs(5)),
+            ..Default::default()
+        })
+        .store();
+        store.write(&path!("k"), Record::parsed(1.into())).unwrap();
+
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert!(read(&mut store, &path!("k")).is_some());
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(read(&mut store, &path!("k")).is_none());
+        assert_eq!(stat(&mut store, "expirations"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn block_sweeps_expired_entries() {
+        let mut block = KvCacheBlock::new(KvCacheConfig {
+            ttl: Some(Duration::from_secs(2)),
+            sweep_interval: Duration::from_secs(1),
+            ..Default::default()
+        });
+        let mut store = block.store();
+        let shutdown = block.shutdown();
+        store.write(&path!("k"), Record::parsed(1.into())).unwrap();
+
+        let task =
+            tokio::spawn(async move { block.run(BlockContext::new(BlockId::new(), ())).await });
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        assert_eq!(stat(&mut store, "entries"), 0);
+        assert_eq!(stat(&mut store, "expirations"), 1);
+
+        shutdown.trigger();
+        task.await.unwrap().unwrap();
+    }
+}
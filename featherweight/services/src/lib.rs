@@ -0,0 +1,157 @@
+//! # Featherweight Services
+//!
+//! A standard library of service Blocks, so a new deployment has something
+//! useful to mount on day one. Each service is a [`Block`] paired with a store
+//! that other Blocks mount to use it:
+//!
+//! - [`KvCacheBlock`]: key-value cache with a capacity and TTL
+//! - [`CronBlock`]: writes values into its root store at intervals or on
+//!   cron expressions
+//! - [`HttpGatewayBlock`]: routes path prefixes to upstream HTTP services
+//! - [`LogAggregatorBlock`]: collects log records from many Blocks and
+//!   forwards them in batches
+//!
+//! ## Deploying a service
+//!
+//! Spawn the Block, then register its store as an export so other Blocks can
+//! mount it. Services run until their [`Shutdown`] is triggered.
+//!
+//! ```rust
+//! use featherweight_runtime::{Runtime, RuntimeConfig};
+//! use featherweight_services::{KvCacheBlock, KvCacheConfig};
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let mut runtime = Runtime::new(RuntimeConfig::default());
+//!
+//! let cache = KvCacheBlock::new(KvCacheConfig::default());
+//! let store = cache.store();
+//! let shutdown = cache.shutdown();
+//!
+//! let handle = runtime.spawn(cache, ()).await.unwrap();
+//! runtime.register_export(handle.id, "cache", store).unwrap();
+//!
+//! shutdown.trigger();
+//! # });
+//! ```
+//!
+//! ## WASM builds
+//!
+//! The services run as native Blocks. WASM Blocks cannot yet export stores,
+//! so prebuilt components are still to come; the remaining work is tracked
+//! in `docs/plans/featherweight-services-wasm.md`.
+//!
+//! [`Block`]: featherweight_runtime::Block
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+pub mod cron;
+pub mod http_gateway;
+pub mod kv_cache;
+pub mod log_aggregator;
+
+pub use cron::{CronBlock, CronJob, Schedule};
+pub use http_gateway::{HttpGatewayBlock, HttpGatewayStore};
+pub use kv_cache::{KvCacheBlock, KvCacheConfig, KvCacheStore};
+pub use log_aggregator::{LogAggregatorBlock, LogAggregatorConfig, LogStore};
+
+/// Signal that stops a running service Block.
+///
+/// Clones share the same signal. Once triggered it stays triggered.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// Create an untriggered signal.
+    pub fn new() -> Self {
+        Self {
+            tx: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Stop every Block watching this signal.
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Whether the signal has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Wait until the signal is triggered.
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        loop {
+            let stop = *rx.borrow_and_update();
+            if stop || rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Call `tick` every `period` until the signal is triggered.
+    ///
+    /// The first tick happens immediately.
+    pub(crate) async fn every(&self, period: Duration, mut tick: impl FnMut() + Send) {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => tick(),
+                _ = self.wait() => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_wait_returns_after_trigger() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_triggered());
+
+        let waiter = shutdown.clone();
+        let task = tokio::spawn(async move { waiter.wait().await });
+        shutdown.trigger();
+        task.await.unwrap();
+        assert!(shutdown.is_triggered());
+    }
+
+    #[tokio::test]
+    async fn shutdown_wait_after_trigger_is_immediate() {
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        shutdown.wait().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn every_ticks_until_triggered() {
+        let shutdown = Shutdown::new();
+        let stopper = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            stopper.trigger();
+        });
+
+        let mut ticks = 0;
+        shutdown
+            .every(Duration::from_millis(100), || ticks += 1)
+            .await;
+        // Ticks at 0, 100 and 200ms
+        assert_eq!(ticks, 3);
+    }
+}
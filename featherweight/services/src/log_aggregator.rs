@@ -0,0 +1,301 @@
+//! Log aggregation service.
+//!
+//! Blocks write log messages to the log store at a path naming their source,
+//! such as `api/requests`. Each message is kept as an entry:
+//!
+//! ```text
+//! {"seq": 7, "source": "api/requests", "time_ms": 1700000000000, "message": <value>}
+//! ```
+//!
+//! Reading the root of the store returns every buffered entry; reading a
+//! source path returns the entries from that source and the sources below it.
+//! When a sink is configured, the Block periodically writes batches of new
+//! entries, as an array, to the sink path in its root store.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use featherweight_runtime::{Block, BlockContext, Result};
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+
+use crate::Shutdown;
+
+/// Configuration for a [`LogAggregatorBlock`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogAggregatorConfig {
+    /// Number of entries kept in memory. The oldest are dropped first.
+    pub capacity: usize,
+
+    /// How often new entries are forwarded to the sink.
+    pub flush_interval: Duration,
+
+    /// Path in the Block's root store that receives batches of entries.
+    pub sink: Option<Path>,
+}
+
+impl Default for LogAggregatorConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            flush_interval: Duration::from_secs(1),
+            sink: None,
+        }
+    }
+}
+
+struct Entry {
+    seq: u64,
+    source: Path,
+    value: Value,
+}
+
+#[derive(Default)]
+struct LogState {
+    capacity: usize,
+    entries: VecDeque<Entry>,
+    next_seq: u64,
+    /// Sequence number of the first entry not yet sent to the sink.
+    unflushed: u64,
+}
+
+impl LogState {
+    fn push(&mut self, source: Path, message: Value) {
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let value = Value::Map(BTreeMap::from([
+            ("seq".to_string(), Value::Integer(seq as i64)),
+            ("source".to_string(), Value::String(source.to_string())),
+            ("time_ms".to_string(), Value::Integer(time_ms)),
+            ("message".to_string(), message),
+        ]));
+        self.entries.push_back(Entry { seq, source, value });
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    fn entries_under(&self, prefix: &Path) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .filter(|e| e.source.has_prefix(prefix))
+                .map(|e| e.value.clone())
+                .collect(),
+        )
+    }
+
+    /// Entries added since the last flush. Entries dropped before they could
+    /// be flushed are skipped.
+    fn take_unflushed(&mut self) -> Vec<Value> {
+        let batch = self
+            .entries
+            .iter()
+            .filter(|e| e.seq >= self.unflushed)
+            .map(|e| e.value.clone())
+            .collect();
+        self.unflushed = self.next_seq;
+        batch
+    }
+}
+
+/// Store interface to a [`LogAggregatorBlock`]'s buffer.
+///
+/// Clones share the same buffer.
+#[derive(Clone)]
+pub struct LogStore {
+    state: Arc<Mutex<LogState>>,
+}
+
+impl Reader for LogStore {
+    fn read(&mut self, path: &Path) -> std::result::Result<Option<Record>, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(Some(Record::parsed(state.entries_under(path))))
+    }
+}
+
+impl Writer for LogStore {
+    fn write(&mut self, path: &Path, record: Record) -> std::result::Result<Path, Error> {
+        let message = record.into_value(&NoCodec)?;
+        self.state.lock().unwrap().push(path.clone(), message);
+        Ok(path.clone())
+    }
+}
+
+/// Log aggregator service Block.
+pub struct LogAggregatorBlock {
+    state: Arc<Mutex<LogState>>,
+    flush_interval: Duration,
+    sink: Option<Path>,
+    shutdown: Shutdown,
+}
+
+impl LogAggregatorBlock {
+    /// Create an aggregator with the given configuration.
+    pub fn new(config: LogAggregatorConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LogState {
+                capacity: config.capacity,
+                ..Default::default()
+            })),
+            flush_interval: config.flush_interval,
+            sink: config.sink,
+            shutdown: Shutdown::new(),
+        }
+    }
+
+    /// The store to export for other Blocks.
+    pub fn store(&self) -> LogStore {
+        LogStore {
+            state: self.state.clone(),
+        }
+    }
+
+    /// The signal that stops this Block.
+    pub fn shutdown(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+}
+
+#[async_trait]
+impl<S: Writer + Send + 'static> Block<S> for LogAggregatorBlock {
+    async fn run(&mut self, mut ctx: BlockContext<S>) -> Result<()> {
+        let Some(sink) = self.sink.clone() else {
+            self.shutdown.wait().await;
+            return Ok(());
+        };
+
+        let state = self.state.clone();
+        let flush = |root: &mut S| {
+            let batch = state.lock().unwrap().take_unflushed();
+            if batch.is_empty() {
+                return;
+            }
+            if let Err(e) = root.write(&sink, Record::parsed(Value::Array(batch))) {
+                tracing::warn!(block = %ctx.id, "log flush failed: {}", e);
+            }
+        };
+
+        let root = &mut ctx.root;
+        self.shutdown
+            .every(self.flush_interval, || flush(&mut *root))
+            .await;
+        // Forward anything written since the last tick
+        flush(root);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use featherweight_runtime::BlockId;
+    use structfs_core_store::path;
+
+    fn read(store: &mut LogStore, path: &Path) -> Vec<Value> {
+        match store.read(path).unwrap().unwrap().into_value(&NoCodec) {
+            Ok(Value::Array(entries)) => entries,
+            other => panic!("expected array, got {:?}", other),
+        }
+    }
+
+    fn field<'a>(entry: &'a Value, name: &str) -> &'a Value {
+        match entry {
+            Value::Map(map) => &map[name],
+            _ => panic!("expected map"),
+        }
+    }
+
+    #[test]
+    fn entries_by_source() {
+        let mut store = LogAggregatorBlock::new(LogAggregatorConfig::default()).store();
+        store
+            .write(&path!("api/requests"), Record::parsed("GET /".into()))
+            .unwrap();
+        store
+            .write(&path!("api/errors"), Record::parsed("boom".into()))
+            .unwrap();
+        store
+            .write(&path!("worker"), Record::parsed("started".into()))
+            .unwrap();
+
+        let all = read(&mut store, &path!(""));
+        assert_eq!(all.len(), 3);
+        assert_eq!(field(&all[0], "seq"), &Value::Integer(0));
+        assert_eq!(field(&all[0], "source"), &Value::from("api/requests"));
+        assert_eq!(field(&all[0], "message"), &Value::from("GET /"));
+
+        assert_eq!(read(&mut store, &path!("api")).len(), 2);
+        assert_eq!(read(&mut store, &path!("api/errors")).len(), 1);
+        assert!(read(&mut store, &path!("nothing")).is_empty());
+    }
+
+    #[test]
+    fn oldest_entries_dropped_at_capacity() {
+        let mut store = LogAggregatorBlock::new(LogAggregatorConfig {
+            capacity: 2,
+            ..Default::default()
+        })
+        .store();
+        for i in 0..3 {
+            store.write(&path!("x"), Record::parsed(i.into())).unwrap();
+        }
+        let entries = read(&mut store, &path!(""));
+        let messages: Vec<_> = entries.iter().map(|e| field(e, "message")).collect();
+        assert_eq!(messages, vec![&Value::from(1), &Value::from(2)]);
+    }
+
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<(Path, Value)>>>);
+
+    impl Writer for Sink {
+        fn write(&mut self, path: &Path, record: Record) -> std::result::Result<Path, Error> {
+            let value = record.into_value(&NoCodec)?;
+            self.0.lock().unwrap().push((path.clone(), value));
+            Ok(path.clone())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn block_flushes_batches_to_sink() {
+        let mut block = LogAggregatorBlock::new(LogAggregatorConfig {
+            sink: Some(path!("archive/logs")),
+            flush_interval: Duration::from_secs(1),
+            ..Default::default()
+        });
+        let mut store = block.store();
+        let shutdown = block.shutdown();
+        let sink = Sink::default();
+        let batches = sink.0.clone();
+
+        store.write(&path!("a"), Record::parsed(1.into())).unwrap();
+        store.write(&path!("b"), Record::parsed(2.into())).unwrap();
+        let task =
+            tokio::spawn(async move { block.run(BlockContext::new(BlockId::new(), sink)).await });
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        store.write(&path!("c"), Record::parsed(3.into())).unwrap();
+        shutdown.trigger();
+        task.await.unwrap().unwrap();
+
+        let batches = batches.lock().unwrap();
+        let sizes: Vec<usize> = batches
+            .iter()
+            .map(|(path, batch)| {
+                assert_eq!(path, &path!("archive/logs"));
+                match batch {
+                    Value::Array(entries) => entries.len(),
+                    _ => panic!("expected array"),
+                }
+            })
+            .collect();
+        // Two entries on the first tick, the last one on shutdown
+        assert_eq!(sizes, vec![2, 1]);
+    }
+}
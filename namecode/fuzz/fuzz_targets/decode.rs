@@ -0,0 +1,7 @@
+//! Decode arbitrary input; see `namecode::fuzz::check_decode`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| namecode::fuzz::check_decode(data));
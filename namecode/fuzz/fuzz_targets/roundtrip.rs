@@ -0,0 +1,7 @@
+//! Encode and decode arbitrary strings; see `namecode::fuzz::check_roundtrip`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| namecode::fuzz::check_roundtrip(data));
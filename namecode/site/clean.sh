@@ -11,6 +11,9 @@ echo "    removed _site/"
 rm -rf "$SITE_DIR/src/wasm/pkg"
 echo "    removed src/wasm/pkg/"
 
+rm -rf "$SITE_DIR/wasm/pkg"
+echo "    removed wasm/pkg/"
+
 rm -rf "$SITE_DIR/wasm/target"
 echo "    removed wasm/target/"
 
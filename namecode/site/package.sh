@@ -0,0 +1,29 @@
+#!/usr/bin/env bash
+# Build the npm packages for the namecode WASM bindings.
+#
+#   ./package.sh            # build into wasm/pkg/{web,nodejs,bundler}
+#   ./package.sh --publish  # build, then publish the bundler package
+#
+# The bundler package is the one published to npm, as
+# @structfs/namecode-wasm (override the scope with NPM_SCOPE). The web and
+# nodejs builds are for loading without a bundler.
+set -euo pipefail
+
+SITE_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+SCOPE="${NPM_SCOPE:-structfs}"
+
+for target in web nodejs bundler; do
+  echo "==> Building npm package for $target..."
+  wasm-pack build --release --target "$target" \
+    --scope "$SCOPE" \
+    --out-name namecode \
+    --out-dir "$SITE_DIR/wasm/pkg/$target" \
+    "$SITE_DIR/wasm"
+done
+
+if [[ "${1:-}" == "--publish" ]]; then
+  echo "==> Publishing..."
+  (cd "$SITE_DIR/wasm/pkg/bundler" && npm publish --access public)
+fi
+
+echo "==> Done. Packages in $SITE_DIR/wasm/pkg/"
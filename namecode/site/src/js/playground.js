@@ -1,4 +1,4 @@
-import init, { encode, decode, is_xid_identifier } from "/wasm/pkg/namecode_wasm.js";
+import init, { encode, decode, is_xid_identifier, DecodeError } from "/wasm/pkg/namecode_wasm.js";
 
 const statusEl = document.getElementById("wasm-status");
 const playgroundEl = document.getElementById("playground");
@@ -20,9 +20,12 @@ function update() {
   propXid.className = "prop-val " + (isXid ? "yes" : "no");
 
   // Decode
-  const result = decode(input);
-  if (result.startsWith("Error: ")) {
-    if (isXid) {
+  try {
+    decodedEl.value = decode(input);
+    decodePaneEl.classList.remove("inactive");
+  } catch (e) {
+    if (!(e instanceof DecodeError)) throw e;
+    if (isXid && e.kind === "NotEncoded") {
       // Valid XID that isn't namecode — decode is identity
       decodedEl.value = input;
       decodePaneEl.classList.remove("inactive");
@@ -30,9 +33,6 @@ function update() {
       decodedEl.value = "";
       decodePaneEl.classList.add("inactive");
     }
-  } else {
-    decodedEl.value = result;
-    decodePaneEl.classList.remove("inactive");
   }
 }
 
@@ -1,3 +1,11 @@
+//! WebAssembly bindings for namecode.
+//!
+//! Single values go through [`encode`] and [`decode`]; `decode` throws a
+//! [`DecodeError`] object rather than returning an error string. Many values
+//! go through the batch functions, which report each failure in place, or
+//! through [`EncodeStream`] and [`DecodeStream`], which accept text in
+//! arbitrary chunks and process it a line at a time.
+
 use wasm_bindgen::prelude::*;
 
 /// Encode a Unicode string into a valid UAX 31 identifier.
@@ -7,13 +15,11 @@ pub fn encode(input: &str) -> String {
 }
 
 /// Decode a namecode-encoded string back to Unicode.
-/// Returns the decoded string, or an error message prefixed with "Error: ".
+///
+/// Throws a `DecodeError` if the input is not a valid encoding.
 #[wasm_bindgen]
-pub fn decode(input: &str) -> String {
-    match namecode::decode(input) {
-        Ok(s) => s,
-        Err(e) => format!("Error: {e}"),
-    }
+pub fn decode(input: &str) -> Result<String, DecodeError> {
+    namecode::decode(input).map_err(DecodeError::from)
 }
 
 /// Check if a string is a valid XID identifier (UAX 31).
@@ -21,3 +27,281 @@ pub fn decode(input: &str) -> String {
 pub fn is_xid_identifier(input: &str) -> bool {
     namecode::is_xid_identifier(input)
 }
+
+/// Encode each string.
+#[wasm_bindgen]
+pub fn encode_all(inputs: Vec<String>) -> Vec<String> {
+    inputs.iter().map(|s| namecode::encode(s)).collect()
+}
+
+/// Decode each string, reporting failures per entry instead of throwing.
+#[wasm_bindgen]
+pub fn decode_all(inputs: Vec<String>) -> Vec<DecodeResult> {
+    inputs.iter().map(|s| DecodeResult::of(s)).collect()
+}
+
+/// Encode each line of `text`, returning the encodings one per line.
+#[wasm_bindgen]
+pub fn encode_lines(text: &str) -> String {
+    text.lines()
+        .map(namecode::encode)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Decode each line of `text`, reporting failures per line.
+#[wasm_bindgen]
+pub fn decode_lines(text: &str) -> Vec<DecodeResult> {
+    text.lines().map(DecodeResult::of).collect()
+}
+
+/// Why a string could not be decoded.
+///
+/// `kind` is one of `NotEncoded`, `InvalidDigit`, `UnexpectedEnd`,
+/// `InvalidCodepoint` or `Overflow`. `invalid_digit` and `codepoint` carry
+/// the offending value for the kinds they are named after.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    kind: &'static str,
+    message: String,
+    invalid_digit: Option<char>,
+    codepoint: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl DecodeError {
+    /// The error variant name.
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// A human-readable description.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// The character that is not a valid digit, for `InvalidDigit`.
+    #[wasm_bindgen(getter)]
+    pub fn invalid_digit(&self) -> Option<char> {
+        self.invalid_digit
+    }
+
+    /// The decoded value that is not a Unicode scalar, for
+    /// `InvalidCodepoint`.
+    #[wasm_bindgen(getter)]
+    pub fn codepoint(&self) -> Option<u32> {
+        self.codepoint
+    }
+
+    /// The message, so the error prints well when thrown.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        format!("DecodeError: {}", self.message)
+    }
+}
+
+impl From<namecode::DecodeError> for DecodeError {
+    fn from(e: namecode::DecodeError) -> Self {
+        use namecode::DecodeError as E;
+
+        let (kind, invalid_digit, codepoint) = match e {
+            E::NotEncoded => ("NotEncoded", None, None),
+            E::InvalidDigit(c) => ("InvalidDigit", Some(c), None),
+            E::UnexpectedEnd => ("UnexpectedEnd", None, None),
+            E::InvalidCodepoint(cp) => ("InvalidCodepoint", None, Some(cp)),
+            E::Overflow => ("Overflow", None, None),
+        };
+        DecodeError {
+            kind,
+            message: e.to_string(),
+            invalid_digit,
+            codepoint,
+        }
+    }
+}
+
+/// The outcome of decoding one entry of a batch.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeResult {
+    input: String,
+    value: Option<String>,
+    error: Option<DecodeError>,
+}
+
+impl DecodeResult {
+    fn of(input: &str) -> Self {
+        let (value, error) = match namecode::decode(input) {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e.into())),
+        };
+        DecodeResult {
+            input: input.to_string(),
+            value,
+            error,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DecodeResult {
+    /// Whether decoding succeeded.
+    #[wasm_bindgen(getter)]
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The string that was decoded.
+    #[wasm_bindgen(getter)]
+    pub fn input(&self) -> String {
+        self.input.clone()
+    }
+
+    /// The decoded string, if decoding succeeded.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<String> {
+        self.value.clone()
+    }
+
+    /// Why decoding failed, if it did.
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<DecodeError> {
+        self.error.clone()
+    }
+}
+
+/// Splits chunked text into complete lines.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    /// Append `chunk`, returning the lines it completed.
+    fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(chunk);
+        let Some(end) = self.pending.rfind('\n') else {
+            return Vec::new();
+        };
+        let rest = self.pending.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.pending, rest);
+        complete
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect()
+    }
+
+    /// The final line, if the text did not end with a newline.
+    fn finish(&mut self) -> Option<String> {
+        let last = std::mem::take(&mut self.pending);
+        (!last.is_empty()).then(|| last.trim_end_matches('\r').to_string())
+    }
+}
+
+/// Encodes text that arrives in chunks, such as from a `ReadableStream`.
+///
+/// ```js
+/// const stream = new EncodeStream();
+/// for await (const chunk of readable) output.push(...stream.push(chunk));
+/// output.push(...stream.finish());
+/// ```
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct EncodeStream {
+    lines: LineBuffer,
+}
+
+#[wasm_bindgen]
+impl EncodeStream {
+    /// Start an empty stream.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk of text, returning encodings of the lines it completed.
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        encode_all(self.lines.push(chunk))
+    }
+
+    /// End the stream, encoding a final line with no trailing newline.
+    pub fn finish(&mut self) -> Vec<String> {
+        encode_all(self.lines.finish().into_iter().collect())
+    }
+}
+
+/// Decodes text that arrives in chunks, reporting failures per line.
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct DecodeStream {
+    lines: LineBuffer,
+}
+
+#[wasm_bindgen]
+impl DecodeStream {
+    /// Start an empty stream.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk of text, returning results for the lines it completed.
+    pub fn push(&mut self, chunk: &str) -> Vec<DecodeResult> {
+        decode_all(self.lines.push(chunk))
+    }
+
+    /// End the stream, decoding a final line with no trailing newline.
+    pub fn finish(&mut self) -> Vec<DecodeResult> {
+        decode_all(self.lines.finish().into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_errors_are_typed() {
+        let err = decode("plain").unwrap_err();
+        assert_eq!(err.kind(), "NotEncoded");
+        assert!(err.to_js_string().starts_with("DecodeError: "));
+
+        let err = decode("_N_foo__!").unwrap_err();
+        assert_eq!(err.kind(), "InvalidDigit");
+        assert_eq!(err.invalid_digit(), Some('!'));
+    }
+
+    #[test]
+    fn batches_report_each_entry() {
+        let encoded = encode_lines("hello world\nfoo\nfoo-bar");
+        assert_eq!(encoded, "_N_helloworld__fa0b\nfoo\n_N_foobar__da1d");
+
+        let results = decode_lines(&encoded);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].value().as_deref(), Some("hello world"));
+        assert!(!results[1].ok());
+        assert_eq!(results[1].error().unwrap().kind(), "NotEncoded");
+        assert_eq!(results[2].input(), "_N_foobar__da1d");
+    }
+
+    #[test]
+    fn streams_split_lines_across_chunks() {
+        let mut stream = EncodeStream::new();
+        assert!(stream.push("hello ").is_empty());
+        assert_eq!(stream.push("world\r\nfoo-"), ["_N_helloworld__fa0b"]);
+        assert_eq!(stream.push("bar\n"), ["_N_foobar__da1d"]);
+        assert!(stream.finish().is_empty());
+
+        let mut stream = DecodeStream::new();
+        let first = stream.push("foo\n_N_helloworld");
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].error().unwrap().kind(), "NotEncoded");
+        assert!(stream.push("__fa0b").is_empty());
+        let last = stream.finish();
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].value().as_deref(), Some("hello world"));
+    }
+}
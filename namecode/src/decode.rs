@@ -20,6 +20,28 @@ use crate::DecodeError;
 /// assert_eq!(decode("foo"), Err(DecodeError::NotEncoded));
 /// ```
 pub fn decode(input: &str) -> Result<String, DecodeError> {
+    decode_bounded(input, usize::MAX)
+}
+
+/// Decode a Namecode string, rejecting it if the decoded string would be
+/// longer than `max_len` bytes.
+///
+/// Use this for untrusted input. Decoding stops with
+/// [`DecodeError::TooLong`] as soon as the output is known to exceed the
+/// limit, before it is built.
+///
+/// # Examples
+///
+/// ```
+/// use namecode::{decode_bounded, DecodeError};
+///
+/// assert_eq!(decode_bounded("_N_helloworld__fa0b", 11).unwrap(), "hello world");
+/// assert_eq!(
+///     decode_bounded("_N_helloworld__fa0b", 10),
+///     Err(DecodeError::TooLong(10))
+/// );
+/// ```
+pub fn decode_bounded(input: &str, max_len: usize) -> Result<String, DecodeError> {
     // Check for prefix
     if !input.starts_with(PREFIX) {
         return Err(DecodeError::NotEncoded);
@@ -33,7 +55,7 @@ pub fn decode(input: &str) -> Result<String, DecodeError> {
         let encoded = &without_prefix[delim_pos + DELIMITER.len()..];
 
         // Decode the insertions
-        let insertions = decode_insertions(encoded)?;
+        let insertions = decode_insertions(encoded, basic.len(), max_len)?;
 
         // Reconstruct the original string
         reconstruct(basic, &insertions)
@@ -45,6 +67,9 @@ pub fn decode(input: &str) -> Result<String, DecodeError> {
         {
             return Err(DecodeError::NotEncoded);
         }
+        if without_prefix.len() > max_len {
+            return Err(DecodeError::TooLong(max_len));
+        }
         Ok(without_prefix.to_string())
     }
 }
@@ -52,7 +77,18 @@ pub fn decode(input: &str) -> Result<String, DecodeError> {
 /// Decode the encoded insertions.
 ///
 /// Each insertion is encoded as: position_delta (varint), codepoint (varint)
-fn decode_insertions(encoded: &str) -> Result<Vec<(usize, char)>, DecodeError> {
+///
+/// Fails with `TooLong` once the `basic_len` bytes of basic characters and
+/// the insertions so far come to more than `max_len` bytes.
+fn decode_insertions(
+    encoded: &str,
+    basic_len: usize,
+    max_len: usize,
+) -> Result<Vec<(usize, char)>, DecodeError> {
+    let mut len = basic_len;
+    if len > max_len {
+        return Err(DecodeError::TooLong(max_len));
+    }
     if encoded.is_empty() {
         return Ok(Vec::new());
     }
@@ -83,6 +119,10 @@ fn decode_insertions(encoded: &str) -> Result<Vec<(usize, char)>, DecodeError> {
         bias = adapt_bias(cp, (idx + 2) as u32, false);
 
         let c = char::from_u32(cp).ok_or(DecodeError::InvalidCodepoint(cp))?;
+        len += c.len_utf8();
+        if len > max_len {
+            return Err(DecodeError::TooLong(max_len));
+        }
         insertions.push((pos, c));
 
         prev_pos = pos;
@@ -217,6 +257,18 @@ mod tests {
         assert_eq!(decoded, Ok(original.to_string()));
     }
 
+    #[test]
+    fn test_decode_bounded_counts_bytes() {
+        let encoded = encode("名 前");
+        assert_eq!(decode_bounded(&encoded, 7), Ok("名 前".to_string()));
+        assert_eq!(decode_bounded(&encoded, 6), Err(DecodeError::TooLong(6)));
+
+        // Basic characters alone can pass the limit
+        assert_eq!(decode_bounded("_N_foo", 2), Err(DecodeError::TooLong(2)));
+        assert_eq!(decode_bounded("_N_foo__a", 2), Err(DecodeError::TooLong(2)));
+        assert_eq!(decode_bounded("foo", 0), Err(DecodeError::NotEncoded));
+    }
+
     #[test]
     fn test_reconstruct_overflow() {
         // Test the overflow error in reconstruct when positions are malformed
@@ -0,0 +1,82 @@
+//! Invariant checks for fuzzing.
+//!
+//! Each function takes arbitrary bytes, runs them through the codec and
+//! panics if an invariant does not hold. A panic from inside the codec is
+//! a bug too, so a fuzzer driving these finds both. The targets in
+//! `namecode/fuzz` call them:
+//!
+//! ```text
+//! cargo +nightly fuzz run decode
+//! cargo +nightly fuzz run roundtrip
+//! ```
+//!
+//! The property tests run the same checks on every `cargo test`.
+
+use crate::{decode, decode_bounded, encode, is_xid_identifier, DecodeError};
+
+/// Decode the rest of `data` as untrusted input, with its first byte as the
+/// limit for [`decode_bounded`].
+///
+/// Checks that decoding returns rather than panics, that
+/// [`decode_bounded`] agrees with [`decode`] and never returns more than its
+/// limit, and that decoded strings encode to valid identifiers.
+pub fn check_decode(data: &[u8]) {
+    let (max_len, input) = match data.split_first() {
+        Some((&limit, rest)) => match std::str::from_utf8(rest) {
+            Ok(input) => (limit as usize, input),
+            Err(_) => return,
+        },
+        None => return,
+    };
+
+    let unbounded = decode(input);
+    let bounded = decode_bounded(input, max_len);
+    match (&unbounded, &bounded) {
+        (Ok(full), Ok(out)) => {
+            assert_eq!(full, out);
+            assert!(out.len() <= max_len, "{:?} exceeds {} bytes", out, max_len);
+        }
+        (Ok(full), Err(DecodeError::TooLong(max))) => {
+            assert_eq!(*max, max_len);
+            assert!(full.len() > max_len, "{:?} rejected as too long", full);
+        }
+        (Err(_), Err(DecodeError::TooLong(_))) => {}
+        (Err(a), Err(b)) => assert_eq!(a, b),
+        (unbounded, bounded) => panic!(
+            "decode gave {:?} but decode_bounded gave {:?}",
+            unbounded, bounded
+        ),
+    }
+    if let Ok(decoded) = unbounded {
+        let encoded = encode(&decoded);
+        assert!(
+            encoded.is_empty() || is_xid_identifier(&encoded),
+            "{:?}",
+            encoded
+        );
+    }
+}
+
+/// Encode `data` as a string and decode it again.
+///
+/// Checks that the encoding is a valid identifier, that it decodes back to
+/// the input, and that the input's own length is a limit it fits in.
+pub fn check_roundtrip(data: &[u8]) {
+    let input = String::from_utf8_lossy(data);
+    let encoded = encode(&input);
+    if encoded == input {
+        return;
+    }
+    assert!(is_xid_identifier(&encoded), "{:?}", encoded);
+    assert_eq!(decode(&encoded).as_deref(), Ok(&*input));
+    assert_eq!(
+        decode_bounded(&encoded, input.len()).as_deref(),
+        Ok(&*input)
+    );
+    if !input.is_empty() {
+        assert_eq!(
+            decode_bounded(&encoded, input.len() - 1),
+            Err(DecodeError::TooLong(input.len() - 1))
+        );
+    }
+}
@@ -30,15 +30,44 @@
 //! let encoded = encode(original);
 //! assert_eq!(decode(&encoded).unwrap(), original);
 //! ```
+//!
+//! # Normalization
+//!
+//! Encoding preserves exact codepoints. With the default `normalize`
+//! feature, [`encode_normalized`] first brings input to NFC or NFKC so that
+//! equivalent spellings of a name share one encoding, and
+//! [`is_confusable`] flags identifiers that NFKC would change.
+//!
+//! # Untrusted Input
+//!
+//! Decoding never panics: malformed input is an [`DecodeError`], and the
+//! decoder does work linear in its input. Decoded names often become path
+//! components, so cap their size with [`decode_bounded`], which rejects an
+//! encoding as soon as its output would pass a limit. The checks in
+//! [`fuzz`] state these guarantees as assertions for fuzz targets and
+//! property tests.
+//!
+//! # Renaming Many Names
+//!
+//! [`MappingTable`] encodes a whole set of names for migration tooling,
+//! reporting names that collide and adjusting those that encode to a
+//! keyword, and writes the result as JSON to check into a repository.
 
 #![warn(missing_docs)]
 
 mod bootstring;
 mod decode;
 mod encode;
+pub mod fuzz;
+mod mapping;
+#[cfg(feature = "normalize")]
+mod normalize;
 
-pub use decode::decode;
+pub use decode::{decode, decode_bounded};
 pub use encode::{encode, is_xid_identifier};
+pub use mapping::{Collision, Mapping, MappingTable, RESERVED_WORDS};
+#[cfg(feature = "normalize")]
+pub use normalize::{are_confusable, encode_normalized, is_confusable, skeleton, Normalization};
 
 /// Errors that can occur during Namecode decoding.
 ///
@@ -64,6 +93,9 @@ pub enum DecodeError {
     InvalidCodepoint(u32),
     /// Overflow during delta calculation.
     Overflow,
+    /// Decoded string would be longer than the limit given to
+    /// [`decode_bounded`], in bytes.
+    TooLong(usize),
 }
 
 impl std::fmt::Display for DecodeError {
@@ -74,6 +106,7 @@ impl std::fmt::Display for DecodeError {
             DecodeError::UnexpectedEnd => write!(f, "encoded data ended unexpectedly"),
             DecodeError::InvalidCodepoint(cp) => write!(f, "invalid Unicode codepoint: {}", cp),
             DecodeError::Overflow => write!(f, "overflow during decoding"),
+            DecodeError::TooLong(max) => write!(f, "decoded string is longer than {} bytes", max),
         }
     }
 }
@@ -578,6 +611,26 @@ mod proptests {
             }
         }
 
+        /// Arbitrary bytes never make decoding panic or break its limit
+        #[test]
+        fn prop_fuzz_decode(data in proptest::collection::vec(any::<u8>(), 0..64)) {
+            fuzz::check_decode(&data);
+        }
+
+        /// Inputs shaped like encodings reach deeper into the decoder
+        #[test]
+        fn prop_fuzz_decode_encoded(limit in any::<u8>(), tail in "[a-z0-9_]{0,40}") {
+            let mut data = vec![limit];
+            data.extend_from_slice(format!("_N_{}", tail).as_bytes());
+            fuzz::check_decode(&data);
+        }
+
+        /// Arbitrary bytes roundtrip within their own length
+        #[test]
+        fn prop_fuzz_roundtrip(data in proptest::collection::vec(any::<u8>(), 0..64)) {
+            fuzz::check_roundtrip(&data);
+        }
+
         /// Roundtrip with Unicode (strings that need encoding due to non-XID chars)
         #[test]
         fn prop_roundtrip_unicode(s in "[a-z ]{1,10}") {
@@ -7,14 +7,66 @@ fn print_usage() {
     eprintln!();
     eprintln!("Usage:");
     eprintln!("  namecode encode <string>     Encode a string");
+    eprintln!("  namecode encode --nfc ...    Normalize to NFC first (or --nfkc)");
     eprintln!("  namecode decode <string>     Decode a namecode string");
     eprintln!("  namecode encode              Read strings from stdin, one per line");
     eprintln!("  namecode decode              Read encoded strings from stdin");
+    eprintln!("  namecode table               Map names from stdin to a JSON table");
     eprintln!();
     eprintln!("Examples:");
     eprintln!("  namecode encode 'hello world'");
     eprintln!("  namecode decode '_N_helloworld__fa0b'");
     eprintln!("  echo 'foo-bar' | namecode encode");
+    eprintln!("  namecode table < columns.txt > columns.json");
+}
+
+/// Encoding with the options given on the command line.
+struct Encoder {
+    #[cfg(feature = "normalize")]
+    form: namecode::Normalization,
+}
+
+impl Encoder {
+    /// Take `--nfc`/`--nfkc` from `args`, returning the remaining inputs.
+    fn from_args(args: &[String]) -> (Self, Vec<&String>) {
+        #[cfg(feature = "normalize")]
+        let mut form = namecode::Normalization::None;
+        let mut inputs = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                #[cfg(feature = "normalize")]
+                "--nfc" => form = namecode::Normalization::Nfc,
+                #[cfg(feature = "normalize")]
+                "--nfkc" => form = namecode::Normalization::Nfkc,
+                _ => inputs.push(arg),
+            }
+        }
+        (
+            Encoder {
+                #[cfg(feature = "normalize")]
+                form,
+            },
+            inputs,
+        )
+    }
+
+    #[cfg(feature = "normalize")]
+    fn encode(&self, input: &str) -> String {
+        let normalized = self.form.apply(input);
+        if namecode::is_confusable(&normalized) {
+            eprintln!(
+                "warning: '{}' is confusable with '{}' (use --nfkc to normalize)",
+                input,
+                namecode::skeleton(&normalized)
+            );
+        }
+        namecode::encode(&normalized)
+    }
+
+    #[cfg(not(feature = "normalize"))]
+    fn encode(&self, input: &str) -> String {
+        namecode::encode(input)
+    }
 }
 
 fn main() {
@@ -27,10 +79,11 @@ fn main() {
 
     match args[1].as_str() {
         "encode" => {
-            if args.len() > 2 {
+            let (encoder, inputs) = Encoder::from_args(&args[2..]);
+            if !inputs.is_empty() {
                 // Encode arguments
-                for arg in &args[2..] {
-                    println!("{}", namecode::encode(arg));
+                for arg in inputs {
+                    println!("{}", encoder.encode(arg));
                 }
             } else {
                 // Read from stdin
@@ -41,7 +94,7 @@ fn main() {
                 for line in stdin.lock().lines() {
                     match line {
                         Ok(s) => {
-                            let _ = writeln!(stdout, "{}", namecode::encode(&s));
+                            let _ = writeln!(stdout, "{}", encoder.encode(&s));
                         }
                         Err(e) => {
                             eprintln!("Error reading input: {}", e);
@@ -88,6 +141,34 @@ fn main() {
                 }
             }
         }
+        "table" => {
+            let names: Vec<String> = if args.len() > 2 {
+                args[2..].to_vec()
+            } else {
+                let stdin = io::stdin();
+                let lines: Result<Vec<String>, _> = stdin.lock().lines().collect();
+                match lines {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        eprintln!("Error reading input: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            };
+
+            let table = namecode::MappingTable::new(&names);
+            print!("{}", table.to_json());
+            for collision in table.collisions() {
+                eprintln!(
+                    "collision: {} all map to '{}'",
+                    collision.originals.join(", "),
+                    collision.encoded
+                );
+            }
+            if !table.collisions().is_empty() {
+                std::process::exit(1);
+            }
+        }
         "help" | "--help" | "-h" => {
             print_usage();
         }
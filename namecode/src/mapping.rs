@@ -0,0 +1,406 @@
+//! Mapping tables for renaming many names at once.
+//!
+//! Migration tools that rename database columns or generate code from
+//! schemas need more than [`encode`] applied name by name: they need to know
+//! that no two names ended up with the same identifier, that no identifier
+//! is a keyword in a target language, and they need the result written down
+//! so a later run can be checked against it. [`MappingTable`] does all
+//! three:
+//!
+//! ```
+//! use namecode::MappingTable;
+//!
+//! let table = MappingTable::new(["user id", "type", "name"]);
+//! assert_eq!(table.encoded("user id"), Some("_N_userid__ea0b"));
+//! // Keywords get a trailing underscore
+//! assert_eq!(table.encoded("type"), Some("type_"));
+//! assert_eq!(table.original("type_"), Some("type"));
+//! assert!(table.collisions().is_empty());
+//! ```
+//!
+//! [`MappingTable::to_json`] writes the table with its entries sorted, so
+//! the same names always give byte-for-byte the same file.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::encode;
+
+/// Keywords and reserved words of Rust, Go, JavaScript and Python, the
+/// languages namecode identifiers target. Sorted.
+pub const RESERVED_WORDS: &[&str] = &[
+    "False",
+    "None",
+    "Self",
+    "True",
+    "abstract",
+    "and",
+    "as",
+    "assert",
+    "async",
+    "await",
+    "become",
+    "box",
+    "break",
+    "case",
+    "catch",
+    "chan",
+    "class",
+    "const",
+    "continue",
+    "crate",
+    "debugger",
+    "def",
+    "default",
+    "defer",
+    "del",
+    "delete",
+    "do",
+    "dyn",
+    "elif",
+    "else",
+    "enum",
+    "except",
+    "export",
+    "extends",
+    "extern",
+    "fallthrough",
+    "false",
+    "final",
+    "finally",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "global",
+    "go",
+    "goto",
+    "if",
+    "impl",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "is",
+    "lambda",
+    "let",
+    "loop",
+    "macro",
+    "map",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "new",
+    "nonlocal",
+    "not",
+    "null",
+    "or",
+    "override",
+    "package",
+    "pass",
+    "priv",
+    "private",
+    "protected",
+    "pub",
+    "public",
+    "raise",
+    "range",
+    "ref",
+    "return",
+    "select",
+    "self",
+    "static",
+    "struct",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "unsafe",
+    "unsized",
+    "use",
+    "var",
+    "virtual",
+    "void",
+    "where",
+    "while",
+    "with",
+    "yield",
+];
+
+/// How one name is spelled as an identifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    /// The name as given.
+    pub original: String,
+    /// The identifier it maps to.
+    pub encoded: String,
+    /// Whether `encoded` had a `_` appended because [`encode`] gave a
+    /// reserved word.
+    pub reserved: bool,
+}
+
+/// Names that map to the same identifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// The shared identifier.
+    pub encoded: String,
+    /// The names that map to it, sorted.
+    pub originals: Vec<String>,
+}
+
+/// The identifiers for a set of names, with the names that collide.
+///
+/// Mappings are sorted by original name and each name appears once, however
+/// often it was given. Empty names are skipped, since no identifier spells
+/// them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingTable {
+    mappings: Vec<Mapping>,
+    collisions: Vec<Collision>,
+}
+
+impl MappingTable {
+    /// Map `names`, adjusting those that encode to one of [`RESERVED_WORDS`].
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::with_reserved(names, RESERVED_WORDS)
+    }
+
+    /// Map `names`, adjusting those that encode to one of `reserved`.
+    ///
+    /// An empty `reserved` list maps every name exactly as [`encode`] does.
+    pub fn with_reserved<I, S>(names: I, reserved: &[&str]) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let reserved: BTreeSet<&str> = reserved.iter().copied().collect();
+        let originals: BTreeSet<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        let mut by_encoded: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mappings: Vec<Mapping> = originals
+            .into_iter()
+            .map(|original| {
+                let mut encoded = encode(&original);
+                let is_reserved = reserved.contains(encoded.as_str());
+                if is_reserved {
+                    encoded.push('_');
+                }
+                by_encoded
+                    .entry(encoded.clone())
+                    .or_default()
+                    .push(original.clone());
+                Mapping {
+                    original,
+                    encoded,
+                    reserved: is_reserved,
+                }
+            })
+            .collect();
+
+        let collisions = by_encoded
+            .into_iter()
+            .filter(|(_, originals)| originals.len() > 1)
+            .map(|(encoded, originals)| Collision { encoded, originals })
+            .collect();
+        MappingTable {
+            mappings,
+            collisions,
+        }
+    }
+
+    /// Every mapping, sorted by original name.
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// Identifiers shared by more than one name, sorted by identifier.
+    ///
+    /// Two names collide when one is already the encoding of the other, or
+    /// when a reserved-word adjustment lands on a name that was given too.
+    pub fn collisions(&self) -> &[Collision] {
+        &self.collisions
+    }
+
+    /// The identifier for `original`.
+    pub fn encoded(&self, original: &str) -> Option<&str> {
+        self.mappings
+            .binary_search_by(|m| m.original.as_str().cmp(original))
+            .ok()
+            .map(|i| self.mappings[i].encoded.as_str())
+    }
+
+    /// The name whose identifier is `encoded`, if exactly one name has it.
+    pub fn original(&self, encoded: &str) -> Option<&str> {
+        let mut found = self.mappings.iter().filter(|m| m.encoded == encoded);
+        match (found.next(), found.next()) {
+            (Some(mapping), None) => Some(&mapping.original),
+            _ => None,
+        }
+    }
+
+    /// The table as JSON, one entry per line so that changes diff cleanly:
+    ///
+    /// ```json
+    /// {
+    ///   "mappings": [
+    ///     {"original": "type", "encoded": "type_", "reserved": true},
+    ///     {"original": "user id", "encoded": "_N_userid__ea0b"}
+    ///   ],
+    ///   "collisions": []
+    /// }
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"mappings\": [");
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str("    {\"original\": ");
+            push_json_string(&mut out, &mapping.original);
+            out.push_str(", \"encoded\": ");
+            push_json_string(&mut out, &mapping.encoded);
+            if mapping.reserved {
+                out.push_str(", \"reserved\": true");
+            }
+            out.push('}');
+        }
+        if !self.mappings.is_empty() {
+            out.push_str("\n  ");
+        }
+        out.push_str("],\n  \"collisions\": [");
+        for (i, collision) in self.collisions.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str("    {\"encoded\": ");
+            push_json_string(&mut out, &collision.encoded);
+            out.push_str(", \"originals\": [");
+            for (j, original) in collision.originals.iter().enumerate() {
+                if j > 0 {
+                    out.push_str(", ");
+                }
+                push_json_string(&mut out, original);
+            }
+            out.push_str("]}");
+        }
+        if !self.collisions.is_empty() {
+            out.push_str("\n  ");
+        }
+        out.push_str("]\n}\n");
+        out
+    }
+}
+
+/// Append `s` as a JSON string literal. Non-ASCII characters are written as
+/// they are, so names stay readable in the file.
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn reserved_words_are_sorted_identifiers() {
+        let mut sorted = RESERVED_WORDS.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted, RESERVED_WORDS);
+        assert!(RESERVED_WORDS.iter().all(|w| encode(w) == *w));
+    }
+
+    #[test]
+    fn table_is_sorted_and_deduplicated() {
+        let table = MappingTable::new(["zeta", "foo-bar", "", "alpha", "zeta"]);
+        let originals: Vec<&str> = table
+            .mappings()
+            .iter()
+            .map(|m| m.original.as_str())
+            .collect();
+        assert_eq!(originals, ["alpha", "foo-bar", "zeta"]);
+        assert_eq!(table.encoded("foo-bar"), Some("_N_foobar__da1d"));
+        assert_eq!(
+            decode(table.encoded("foo-bar").unwrap()).unwrap(),
+            "foo-bar"
+        );
+        assert_eq!(table.encoded("missing"), None);
+    }
+
+    #[test]
+    fn collisions_are_reported() {
+        // An encoding given as a name passes through, onto the name it encodes
+        let table = MappingTable::new(["foo-bar", "_N_foobar__da1d", "class", "class_"]);
+        assert_eq!(
+            table.collisions(),
+            [
+                Collision {
+                    encoded: "_N_foobar__da1d".to_string(),
+                    originals: vec!["_N_foobar__da1d".to_string(), "foo-bar".to_string()],
+                },
+                Collision {
+                    encoded: "class_".to_string(),
+                    originals: vec!["class".to_string(), "class_".to_string()],
+                },
+            ]
+        );
+        assert_eq!(table.original("class_"), None);
+
+        let table = MappingTable::with_reserved(["class", "class_"], &[]);
+        assert!(table.collisions().is_empty());
+        assert_eq!(table.original("class"), Some("class"));
+    }
+
+    #[test]
+    fn json_is_stable() {
+        let table = MappingTable::new(["user id", "type", "say \"hi\"\n", "type_"]);
+        assert_eq!(
+            table.to_json(),
+            MappingTable::new(["type_", "type", "say \"hi\"\n", "user id"]).to_json()
+        );
+        assert_eq!(
+            table.to_json(),
+            format!(
+                "{{\n  \"mappings\": [\n    \
+                 {{\"original\": \"say \\\"hi\\\"\\n\", \"encoded\": \"{}\"}},\n    \
+                 {{\"original\": \"type\", \"encoded\": \"type_\", \"reserved\": true}},\n    \
+                 {{\"original\": \"type_\", \"encoded\": \"type_\"}},\n    \
+                 {{\"original\": \"user id\", \"encoded\": \"_N_userid__ea0b\"}}\n  \
+                 ],\n  \"collisions\": [\n    \
+                 {{\"encoded\": \"type_\", \"originals\": [\"type\", \"type_\"]}}\n  \
+                 ]\n}}\n",
+                encode("say \"hi\"\n")
+            )
+        );
+        assert_eq!(
+            MappingTable::new(Vec::<String>::new()).to_json(),
+            "{\n  \"mappings\": [],\n  \"collisions\": []\n}\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Unicode normalization before encoding.
+//!
+//! Namecode preserves exact codepoints, so `é` written as one codepoint
+//! (NFC) and as `e` plus a combining accent (NFD) encode differently.
+//! Systems that treat the two as the same name should normalize first.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfc, is_nfkc, UnicodeNormalization};
+
+use crate::encode::encode;
+
+/// A Unicode normalization form to apply before encoding.
+///
+/// # Examples
+///
+/// ```
+/// use namecode::Normalization;
+///
+/// let nfd = "cafe\u{301}";
+/// assert_eq!(Normalization::Nfc.apply(nfd), "café");
+/// assert_eq!(Normalization::Nfkc.apply("ﬁle"), "file");
+/// assert_eq!(Normalization::None.apply(nfd), nfd);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Leave codepoints as they are.
+    None,
+    /// Canonical composition: merges canonically equivalent sequences
+    /// such as precomposed and combining accents.
+    Nfc,
+    /// Compatibility composition: also folds compatibility variants such
+    /// as ligatures, fullwidth forms and superscripts.
+    Nfkc,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::None
+    }
+}
+
+impl Normalization {
+    /// Normalize `input`, borrowing it if it is already in this form.
+    pub fn apply(self, input: &str) -> Cow<'_, str> {
+        match self {
+            Normalization::None => Cow::Borrowed(input),
+            Normalization::Nfc if is_nfc(input) => Cow::Borrowed(input),
+            Normalization::Nfc => Cow::Owned(input.nfc().collect()),
+            Normalization::Nfkc if is_nfkc(input) => Cow::Borrowed(input),
+            Normalization::Nfkc => Cow::Owned(input.nfkc().collect()),
+        }
+    }
+
+    /// Whether `input` is already in this form.
+    pub fn is_normalized(self, input: &str) -> bool {
+        match self {
+            Normalization::None => true,
+            Normalization::Nfc => is_nfc(input),
+            Normalization::Nfkc => is_nfkc(input),
+        }
+    }
+}
+
+/// Normalize `input` to `form`, then encode it.
+///
+/// Equivalent spellings of a name encode to the same identifier:
+///
+/// ```
+/// use namecode::{encode_normalized, Normalization};
+///
+/// assert_eq!(
+///     encode_normalized("cafe\u{301} menu", Normalization::Nfc),
+///     encode_normalized("café menu", Normalization::Nfc),
+/// );
+/// ```
+pub fn encode_normalized(input: &str, form: Normalization) -> String {
+    encode(&form.apply(input))
+}
+
+/// The form used to compare identifiers for confusability: NFKC.
+///
+/// This covers compatibility variants (`ﬁ` and `fi`, `Ａ` and `A`) and
+/// canonical equivalents. Look-alikes from different scripts, such as
+/// Cyrillic `а` and Latin `a`, are not folded.
+pub fn skeleton(input: &str) -> String {
+    input.nfkc().collect()
+}
+
+/// Whether `input` could be mistaken for a different identifier: it
+/// changes under NFKC.
+///
+/// # Examples
+///
+/// ```
+/// use namecode::is_confusable;
+///
+/// assert!(is_confusable("ﬁle"));
+/// assert!(is_confusable("cafe\u{301}"));
+/// assert!(!is_confusable("file"));
+/// ```
+pub fn is_confusable(input: &str) -> bool {
+    !is_nfkc(input)
+}
+
+/// Whether two different strings have the same [`skeleton`].
+///
+/// # Examples
+///
+/// ```
+/// use namecode::are_confusable;
+///
+/// assert!(are_confusable("ﬁle", "file"));
+/// assert!(!are_confusable("file", "file"));
+/// assert!(!are_confusable("file", "pile"));
+/// ```
+pub fn are_confusable(a: &str, b: &str) -> bool {
+    a != b && skeleton(a) == skeleton(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn equivalent_spellings_encode_alike() {
+        let nfc = "naïve name";
+        let nfd = "nai\u{308}ve name";
+        assert_ne!(encode(nfc), encode(nfd));
+        assert_eq!(
+            encode_normalized(nfc, Normalization::Nfc),
+            encode_normalized(nfd, Normalization::Nfc)
+        );
+        assert_eq!(
+            decode(&encode_normalized(nfd, Normalization::Nfc)).unwrap(),
+            nfc
+        );
+    }
+
+    #[test]
+    fn nfkc_folds_compatibility_forms() {
+        assert_eq!(encode_normalized("Ｆｏｏ", Normalization::Nfkc), "Foo");
+        assert_eq!(encode_normalized("x²", Normalization::Nfkc), "x2");
+        assert_eq!(encode_normalized("x²", Normalization::Nfc), encode("x²"));
+    }
+
+    #[test]
+    fn normalized_input_is_borrowed() {
+        assert!(matches!(Normalization::Nfc.apply("café"), Cow::Borrowed(_)));
+        assert!(matches!(
+            Normalization::Nfkc.apply("plain"),
+            Cow::Borrowed(_)
+        ));
+        assert!(Normalization::Nfc.is_normalized("ﬁle"));
+        assert!(!Normalization::Nfkc.is_normalized("ﬁle"));
+        assert_eq!(Normalization::default(), Normalization::None);
+    }
+
+    #[test]
+    fn confusables() {
+        assert!(is_confusable("Ｆｏｏ"));
+        assert!(!is_confusable("名前"));
+        assert!(are_confusable("Ｆｏｏ", "Foo"));
+        assert!(are_confusable("cafe\u{301}", "café"));
+        // Cross-script look-alikes are out of scope
+        assert!(!are_confusable("\u{430}pple", "apple"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Mount resolution cost as the number of mounts grows.
+//!
+//! Compares `OverlayStore`, which walks a component trie, against a linear
+//! longest-prefix scan over the same mount table. Trie lookup depends on the
+//! depth of the path; the scan grows with the number of mounts. The `read`
+//! column is a full read through the overlay, including building the suffix
+//! path handed to the mounted store.
+//!
+//! Run with `cargo bench -p structfs-core-store --bench overlay_routing`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use structfs_core_store::overlay_store::OverlayStore;
+use structfs_core_store::{Error, Path, Reader, Record, Value, Writer};
+
+const ITERATIONS: u32 = 200_000;
+
+/// Store that answers every read with an empty record.
+struct NullStore;
+
+impl Reader for NullStore {
+    fn read(&mut self, _from: &Path) -> Result<Option<Record>, Error> {
+        Ok(Some(Record::parsed(Value::Null)))
+    }
+}
+
+impl Writer for NullStore {
+    fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+        Ok(to.clone())
+    }
+}
+
+/// Mount points spread across two levels, e.g. `svc17/v3`.
+fn mount_paths(count: usize) -> Vec<Path> {
+    (0..count)
+        .map(|i| Path::parse(&format!("svc{}/v{}", i / 4, i % 4)).unwrap())
+        .collect()
+}
+
+/// Baseline router: scan every mount for the longest matching prefix.
+fn linear_resolve<'a>(mounts: &'a [Path], path: &Path) -> Option<&'a Path> {
+    mounts
+        .iter()
+        .filter(|mount| path.components.starts_with(&mount.components))
+        .max_by_key(|mount| mount.len())
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "{:>8}  {:>12}  {:>12}  {:>12}",
+        "mounts", "trie (ns)", "linear (ns)", "read (ns)"
+    );
+
+    for count in [10, 100, 500, 1000] {
+        let paths = mount_paths(count);
+        let mut overlay = OverlayStore::new();
+        for path in &paths {
+            overlay.mount(path.clone(), NullStore);
+        }
+
+        // The last mount, so the scan cannot stop early
+        let target = paths[count - 1].join(&Path::parse("items/42/name").unwrap());
+
+        let trie = time(|| {
+            black_box(overlay.has_route(black_box(&target)));
+        });
+        let linear = time(|| {
+            black_box(linear_resolve(black_box(&paths), black_box(&target)));
+        });
+        let read = time(|| {
+            black_box(overlay.read(black_box(&target)).unwrap());
+        });
+
+        println!(
+            "{:>8}  {:>12}  {:>12}  {:>12}",
+            count,
+            trie.as_nanos(),
+            linear.as_nanos(),
+            read.as_nanos()
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Store changes as async streams.
+//!
+//! [`Watch`] streams the changes to the record at one path, so an async
+//! Block can `select!` over store changes next to timers and channels. It
+//! subscribes through the store's [`Watcher`](crate::Watcher) when it has
+//! one and is woken by each change as it happens; other stores are wrapped
+//! in a [`PollWatcher`] and read again on a tokio interval.
+//!
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use structfs_core_store::{path, StreamWatchExt, Watch};
+//!
+//! let mut changes = Watch::new(store, path!("config"), Duration::from_millis(100))
+//!     .debounce(Duration::from_millis(500));
+//! loop {
+//!     tokio::select! {
+//!         Some(change) = changes.next() => reload(change?),
+//!         _ = shutdown.changed() => break,
+//!     }
+//! }
+//! ```
+//!
+//! Bursts of changes can be coalesced with [`debounce`](StreamWatchExt::debounce).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
+
+use crate::{Error, Path, PollWatcher, Reader, Record, Subscription, Watcher};
+
+/// What a read found, for telling whether it changed.
+#[derive(PartialEq)]
+enum Seen {
+    Missing,
+    Record(RecordKey),
+}
+
+#[derive(PartialEq)]
+enum RecordKey {
+    Raw(crate::Format, bytes::Bytes),
+    Parsed(crate::Value),
+}
+
+impl Seen {
+    fn of(record: &Option<Record>) -> Self {
+        match record {
+            None => Seen::Missing,
+            Some(Record::Raw { bytes, format }) => {
+                Seen::Record(RecordKey::Raw(format.clone(), bytes.clone()))
+            }
+            Some(Record::Parsed(value)) => Seen::Record(RecordKey::Parsed(value.clone())),
+        }
+    }
+}
+
+/// A stream of the changes to the record at one path.
+///
+/// The first item is the record when the watch starts, `None` if there is
+/// none. After that an item is yielded only when the record differs from
+/// the last one yielded. A failed read yields the error and the watch goes
+/// on; the stream never ends.
+///
+/// Stores with a [`Watcher`] wake the stream on each change. For the rest,
+/// the path is read once per interval through a [`PollWatcher`].
+pub struct Watch<R> {
+    store: PollWatcher<R>,
+    path: Path,
+    interval: Interval,
+    subscription: Option<Subscription>,
+    native: bool,
+    last: Option<Seen>,
+}
+
+impl<R: Reader> Watch<R> {
+    /// Watch `path` in `store`, reading it every `interval` if the store
+    /// can't report its own changes.
+    ///
+    /// The store is subscribed to on the first poll; if that fails, the
+    /// error is yielded and it is tried again after `interval`.
+    ///
+    /// Must be called inside a tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(store: R, path: Path, interval: Duration) -> Self {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            store: PollWatcher::new(store),
+            path,
+            interval,
+            subscription: None,
+            native: false,
+            last: None,
+        }
+    }
+
+    /// The watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The watched store.
+    pub fn store_mut(&mut self) -> &mut R {
+        self.store.inner_mut()
+    }
+
+    /// Unwrap the store.
+    pub fn into_inner(self) -> R {
+        self.store.into_inner()
+    }
+
+    /// Subscribe to the path, natively if the store allows, and read the
+    /// record it starts from.
+    fn subscribe(&mut self) -> Result<Option<Record>, Error> {
+        let (subscription, native) = match self.store.inner_mut().as_watcher() {
+            Some(watcher) => (watcher.watch(&self.path)?, true),
+            None => (self.store.watch(&self.path)?, false),
+        };
+        let record = self.store.read(&self.path)?;
+        self.subscription = Some(subscription);
+        self.native = native;
+        Ok(record)
+    }
+
+    /// Remember `record` if it differs from the last one yielded.
+    fn changed(&mut self, record: &Option<Record>) -> bool {
+        let seen = Seen::of(record);
+        if self.last.as_ref() == Some(&seen) {
+            return false;
+        }
+        self.last = Some(seen);
+        true
+    }
+}
+
+impl<R: Reader + Unpin> Stream for Watch<R> {
+    type Item = Result<Option<Record>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let Some(subscription) = &this.subscription else {
+                if this.interval.poll_tick(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                match this.subscribe() {
+                    Ok(record) if this.changed(&record) => return Poll::Ready(Some(Ok(record))),
+                    Ok(_) => continue,
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            };
+
+            if let Poll::Ready(change) = subscription.poll_next(cx) {
+                // Changes below the path leave the record at it to be read
+                let record = if change.path == this.path {
+                    change.new
+                } else {
+                    match this.store.read(&this.path) {
+                        Ok(record) => record,
+                        Err(e) => return Poll::Ready(Some(Err(e))),
+                    }
+                };
+                if this.changed(&record) {
+                    return Poll::Ready(Some(Ok(record)));
+                }
+                continue;
+            }
+
+            if this.native || this.interval.poll_tick(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if let Err(e) = this.store.poll() {
+                return Poll::Ready(Some(Err(e)));
+            }
+        }
+    }
+}
+
+/// Yields the latest item of a stream once it has been quiet for a while.
+///
+/// Created with [`StreamWatchExt::debounce`].
+pub struct Debounce<S: Stream> {
+    inner: S,
+    quiet: Duration,
+    pending: Option<S::Item>,
+    sleep: Pin<Box<Sleep>>,
+    done: bool,
+}
+
+// Items are never pinned, only moved in and out of `pending`
+impl<S: Stream + Unpin> Unpin for Debounce<S> {}
+
+impl<S: Stream + Unpin> Stream for Debounce<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        while !this.done {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.pending = Some(item);
+                    this.sleep.as_mut().reset(Instant::now() + this.quiet);
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.pending.is_none() {
+            return if this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        // The inner stream ended: nothing newer can arrive, so don't wait
+        if this.done || this.sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(this.pending.take());
+        }
+        Poll::Pending
+    }
+}
+
+/// Stream combinators for watches.
+pub trait StreamWatchExt: Stream + Sized {
+    /// Coalesce bursts: hold each item until `quiet` passes without
+    /// another, then yield only the latest.
+    ///
+    /// Must be called inside a tokio runtime.
+    fn debounce(self, quiet: Duration) -> Debounce<Self> {
+        Debounce {
+            inner: self,
+            quiet,
+            pending: None,
+            sleep: Box::pin(tokio::time::sleep(quiet)),
+            done: false,
+        }
+    }
+
+    /// The next item, for use in `select!` and loops.
+    fn next(&mut self) -> Next<'_, Self>
+    where
+        Self: Unpin,
+    {
+        Next { stream: self }
+    }
+}
+
+impl<S: Stream> StreamWatchExt for S {}
+
+/// Future returned by [`StreamWatchExt::next`].
+pub struct Next<'a, S> {
+    stream: &'a mut S,
+}
+
+impl<S: Stream + Unpin> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.get_mut().stream).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Value, Watchers};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Reads whatever the test last put in the shared slot.
+    #[derive(Clone, Default)]
+    struct Slot(Arc<Mutex<Option<Value>>>);
+
+    impl Slot {
+        fn set(&self, value: i64) {
+            *self.0.lock().unwrap() = Some(Value::Integer(value));
+        }
+    }
+
+    impl Reader for Slot {
+        fn read(&mut self, _from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.lock().unwrap().clone().map(Record::parsed))
+        }
+    }
+
+    /// A value that reports its own changes and counts its reads.
+    #[derive(Clone, Default)]
+    struct Reporting {
+        state: Arc<Mutex<(Option<Value>, Watchers)>>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl Reporting {
+        fn set(&self, at: &Path, value: i64) {
+            let mut state = self.state.lock().unwrap();
+            let (slot, watchers) = &mut *state;
+            let pending = watchers.before(at, |_| slot.clone().map(Record::parsed));
+            *slot = Some(Value::Integer(value));
+            pending.send(|_| slot.clone().map(Record::parsed));
+        }
+    }
+
+    impl Reader for Reporting {
+        fn read(&mut self, _from: &Path) -> Result<Option<Record>, Error> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(self.state.lock().unwrap().0.clone().map(Record::parsed))
+        }
+
+        fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+            Some(self)
+        }
+    }
+
+    impl Watcher for Reporting {
+        fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+            Ok(self.state.lock().unwrap().1.subscribe(path))
+        }
+    }
+
+    fn value(item: Option<Result<Option<Record>, Error>>) -> Option<Value> {
+        item.unwrap()
+            .unwrap()
+            .map(|record| record.as_value().unwrap().clone())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watch_yields_only_changes() {
+        let slot = Slot::default();
+        let mut watch = Watch::new(slot.clone(), path!("x"), Duration::from_millis(10));
+
+        assert_eq!(value(watch.next().await), None);
+        slot.set(1);
+        assert_eq!(value(watch.next().await), Some(Value::Integer(1)));
+
+        // Unchanged reads are skipped until the value changes
+        let unchanged = tokio::time::timeout(Duration::from_millis(50), watch.next()).await;
+        assert!(unchanged.is_err());
+        slot.set(2);
+        assert_eq!(value(watch.next().await), Some(Value::Integer(2)));
+    }
+
+    #[tokio::test]
+    async fn watch_uses_the_store_watcher() {
+        let store = Reporting::default();
+        let mut watch = Watch::new(store.clone(), path!("x"), Duration::from_secs(3600));
+        assert_eq!(value(watch.next().await), None);
+
+        let writer = store.clone();
+        tokio::spawn(async move { writer.set(&path!("x"), 1) });
+        let change = tokio::time::timeout(Duration::from_secs(5), watch.next()).await;
+        assert_eq!(value(change.unwrap()), Some(Value::Integer(1)));
+
+        // The change carried the record, so only the initial read was made
+        assert_eq!(store.reads.load(Ordering::SeqCst), 1);
+
+        // A change below the path is read from the store
+        store.set(&path!("x/y"), 2);
+        assert_eq!(value(watch.next().await), Some(Value::Integer(2)));
+        assert_eq!(store.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounce_coalesces_bursts() {
+        let slot = Slot::default();
+        slot.set(0);
+        let mut changes = Watch::new(slot.clone(), path!("x"), Duration::from_millis(10))
+            .debounce(Duration::from_millis(100));
+
+        let writer = slot.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            for i in 1..=5 {
+                writer.set(i);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        // The initial value settles first, then the burst arrives as one
+        assert_eq!(value(changes.next().await), Some(Value::Integer(0)));
+        assert_eq!(value(changes.next().await), Some(Value::Integer(5)));
+    }
+}
@@ -21,11 +21,25 @@
 //! let ll_store = CoreToLL::new(core_store, JsonCodec, Format::JSON);
 //! // Now use ll_store as an LLReader/LLWriter
 //! ```
+//!
+//! # Deletes
+//!
+//! The LL layer has no delete, so `LLToCore` deletes by writing a null value
+//! in its write format, and `CoreToLL::ll_delete` passes a delete from LL
+//! callers to the Core store's [`Store::delete`].
+//!
+//! # Listing
+//!
+//! Likewise `LLToCore` lists a path by reading and decoding it, and
+//! `CoreToLL::ll_list` passes a listing to the Core store's [`Store::list`].
 
 use bytes::Bytes;
 use structfs_ll_store::{LLError, LLPath, LLReader, LLWriter};
 
-use crate::{Codec, Error, Format, Path, PathError, Reader, Record, Writer};
+use crate::traits::children_of;
+use crate::{
+    Codec, Deleter, Error, Format, Lister, Path, PathError, Reader, Record, Store, Value, Writer,
+};
 
 /// Adapts an LL store to the Core Store interface.
 ///
@@ -79,7 +93,7 @@ impl<T, C> LLToCore<T, C> {
     }
 }
 
-impl<T: LLReader, C: Send + Sync> Reader for LLToCore<T, C> {
+impl<T: LLReader, C: Codec + Send + Sync> Reader for LLToCore<T, C> {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         // Convert Path to &[&[u8]]
         let components: Vec<&[u8]> = from.components.iter().map(|s| s.as_bytes()).collect();
@@ -94,6 +108,20 @@ impl<T: LLReader, C: Send + Sync> Reader for LLToCore<T, C> {
         // Wrap as Raw record with our format hint
         Ok(Some(Record::raw(bytes, self.read_format.clone())))
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+}
+
+/// Reads the path and decodes it with the bridge's codec to list it.
+impl<T: LLReader, C: Codec + Send + Sync> Lister for LLToCore<T, C> {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        match Reader::read(self, path)? {
+            Some(record) => Ok(Some(children_of(&record.into_value(&self.codec)?))),
+            None => Ok(None),
+        }
+    }
 }
 
 impl<T: LLWriter, C: Codec + Send + Sync> Writer for LLToCore<T, C> {
@@ -110,6 +138,16 @@ impl<T: LLWriter, C: Codec + Send + Sync> Writer for LLToCore<T, C> {
         // Convert result back to Path
         path_from_ll(&result_path)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl<T: LLWriter, C: Codec + Send + Sync> Deleter for LLToCore<T, C> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        Writer::write(self, path, Record::parsed(Value::Null)).map(|_| ())
+    }
 }
 
 /// Adapts a Core store to the LL Store interface.
@@ -208,6 +246,35 @@ impl<T: Writer, C: Send + Sync> LLWriter for CoreToLL<T, C> {
     }
 }
 
+impl<T: Store, C> CoreToLL<T, C> {
+    /// Delete the data at LL path components from the Core store.
+    pub fn ll_delete(&mut self, path: &[&[u8]]) -> Result<(), LLError> {
+        let path = path_from_bytes(path).map_err(|e| LLError::Protocol {
+            code: 1,
+            detail: Bytes::copy_from_slice(e.to_string().as_bytes()),
+        })?;
+
+        Store::delete(&mut self.inner, &path).map_err(|e| LLError::Protocol {
+            code: 2,
+            detail: Bytes::copy_from_slice(e.to_string().as_bytes()),
+        })
+    }
+
+    /// Names of the entries below LL path components in the Core store.
+    pub fn ll_list(&mut self, path: &[&[u8]]) -> Result<Option<Vec<Bytes>>, LLError> {
+        let path = path_from_bytes(path).map_err(|e| LLError::Protocol {
+            code: 1,
+            detail: Bytes::copy_from_slice(e.to_string().as_bytes()),
+        })?;
+
+        let names = Store::list(&mut self.inner, &path).map_err(|e| LLError::Protocol {
+            code: 2,
+            detail: Bytes::copy_from_slice(e.to_string().as_bytes()),
+        })?;
+        Ok(names.map(|names| names.into_iter().map(Bytes::from).collect()))
+    }
+}
+
 /// Convert LL path components to Core Path.
 pub(crate) fn path_from_bytes(components: &[&[u8]]) -> Result<Path, PathError> {
     let mut strings = Vec::with_capacity(components.len());
@@ -286,6 +353,109 @@ mod tests {
             self.data.insert(to.clone(), data);
             Ok(to.clone())
         }
+
+        fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+            Some(self)
+        }
+    }
+
+    impl Deleter for TestCoreStore {
+        fn delete(&mut self, path: &Path) -> Result<(), Error> {
+            self.data.remove(path);
+            Ok(())
+        }
+    }
+
+    /// Encodes every value as its debug form.
+    struct DebugCodec;
+
+    impl Codec for DebugCodec {
+        fn decode(&self, _bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+            Err(Error::store("debug", "decode", format.to_string()))
+        }
+
+        fn encode(&self, value: &Value, _format: &Format) -> Result<Bytes, Error> {
+            Ok(Bytes::from(format!("{:?}", value)))
+        }
+
+        fn supports(&self, _format: &Format) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn ll_to_core_delete_writes_null() {
+        let mut bridge = LLToCore::new(TestLLStore::new(), DebugCodec, Format::JSON);
+        Store::delete(&mut bridge, &path!("users/1")).unwrap();
+
+        let key = vec![b"users".to_vec(), b"1".to_vec()];
+        assert_eq!(bridge.inner().data[&key], Bytes::from("Null"));
+    }
+
+    #[test]
+    fn core_to_ll_delete() {
+        let mut core = TestCoreStore::new();
+        core.data
+            .insert(path!("users/1"), Record::parsed(Value::from("Alice")));
+
+        let mut bridge = CoreToLL::new(core, NoCodec, Format::VALUE);
+        bridge.ll_delete(&[b"users", b"1"]).unwrap();
+        assert!(bridge.inner().data.is_empty());
+        assert!(bridge.ll_delete(&[&[0xff]]).is_err());
+    }
+
+    #[test]
+    fn ll_to_core_list_decodes() {
+        struct ListCodec;
+
+        impl Codec for ListCodec {
+            fn decode(&self, bytes: &Bytes, _format: &Format) -> Result<Value, Error> {
+                let items = bytes
+                    .split(|b| *b == b',')
+                    .map(|item| Value::from(String::from_utf8_lossy(item).into_owned()))
+                    .collect();
+                Ok(Value::Array(items))
+            }
+
+            fn encode(&self, _value: &Value, format: &Format) -> Result<Bytes, Error> {
+                Err(Error::store("list", "encode", format.to_string()))
+            }
+
+            fn supports(&self, _format: &Format) -> bool {
+                true
+            }
+        }
+
+        let mut ll = TestLLStore::new();
+        ll.data
+            .insert(vec![b"tags".to_vec()], Bytes::from_static(b"a,b,c"));
+        let mut bridge = LLToCore::new(ll, ListCodec, Format::JSON);
+
+        let store: &mut dyn Store = &mut bridge;
+        assert_eq!(
+            store.list(&path!("tags")).unwrap().unwrap(),
+            ["0", "1", "2"]
+        );
+        assert_eq!(store.list(&path!("missing")).unwrap(), None);
+    }
+
+    #[test]
+    fn core_to_ll_list() {
+        let mut core = TestCoreStore::new();
+        core.data.insert(
+            path!("users"),
+            Record::parsed(Value::Map(std::collections::BTreeMap::from([(
+                "alice".to_string(),
+                Value::Null,
+            )]))),
+        );
+
+        let mut bridge = CoreToLL::new(core, NoCodec, Format::VALUE);
+        assert_eq!(
+            bridge.ll_list(&[b"users"]).unwrap(),
+            Some(vec![Bytes::from("alice")])
+        );
+        assert_eq!(bridge.ll_list(&[b"missing"]).unwrap(), None);
     }
 
     #[test]
@@ -0,0 +1,152 @@
+//! Checks that stores follow the [`Reader`], [`Writer`] and
+//! [`Deleter`](crate::Deleter) contract.
+//!
+//! Every bundled store runs these from its tests, and third-party stores can
+//! too by enabling the `conformance` feature in their dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! structfs-core-store = { version = "...", features = ["conformance"] }
+//! ```
+//!
+//! The checks panic with a description of the violation, so they can be
+//! called directly from `#[test]` functions:
+//!
+//! ```rust
+//! use structfs_core_store::{conformance, path, Reader, Record, Error, Path};
+//!
+//! struct Empty;
+//!
+//! impl Reader for Empty {
+//!     fn read(&mut self, _: &Path) -> Result<Option<Record>, Error> {
+//!         Ok(None)
+//!     }
+//! }
+//!
+//! conformance::assert_missing(&mut Empty, &path!("anything"));
+//! ```
+//!
+//! The contract the checks enforce:
+//!
+//! - A read of a path where nothing exists returns `Ok(None)`. That includes
+//!   handles that were never created or have been deleted. `Err` is reserved
+//!   for failures: the store could not tell whether anything is there.
+//! - Reading the same path twice without writes in between gives the same
+//!   kind of answer both times.
+//! - A successful write can be read back from the path it returned.
+//! - After a successful delete the path reads as missing, and deleting it
+//!   again still succeeds.
+
+use crate::{NoCodec, Path, ReadOutcome, Reader, Record, Store, Value, Writer};
+
+/// Assert that nothing exists at `path`, consistently.
+pub fn assert_missing<S: Reader + ?Sized>(store: &mut S, path: &Path) {
+    for attempt in ["first", "second"] {
+        match store.read_outcome(path) {
+            ReadOutcome::NotFound => {}
+            ReadOutcome::Found(record) => panic!(
+                "{} read of missing path '{}' found {:?}",
+                attempt, path, record
+            ),
+            ReadOutcome::Error(e) => panic!(
+                "{} read of missing path '{}' failed instead of returning None: {}",
+                attempt, path, e
+            ),
+        }
+    }
+}
+
+/// Assert that data exists at `path`, consistently, and return it.
+pub fn assert_found<S: Reader + ?Sized>(store: &mut S, path: &Path) -> Value {
+    let [first, _] = ["first", "second"].map(|attempt| match store.read_outcome(path) {
+        ReadOutcome::Found(record) => record.into_value(&NoCodec).unwrap_or_else(|e| {
+            panic!(
+                "{} read of '{}' returned an undecodable record: {}",
+                attempt, path, e
+            )
+        }),
+        ReadOutcome::NotFound => panic!("{} read of '{}' found nothing", attempt, path),
+        ReadOutcome::Error(e) => panic!("{} read of '{}' failed: {}", attempt, path, e),
+    });
+    first
+}
+
+/// Assert that writing `value` to `path` succeeds and that the value can be
+/// read back from the returned path.
+pub fn assert_round_trip<S: Reader + Writer + ?Sized>(store: &mut S, path: &Path, value: Value) {
+    let written = store
+        .write(path, Record::parsed(value.clone()))
+        .unwrap_or_else(|e| panic!("write to '{}' failed: {}", path, e));
+    let read = assert_found(store, &written);
+    assert_eq!(
+        read, value,
+        "value read back from '{}' differs from the value written to '{}'",
+        written, path
+    );
+}
+
+/// Assert that `value` written to `path` can be deleted, that the path then
+/// reads as missing, and that deleting it again succeeds.
+///
+/// The store must delete natively: [`Writer::as_deleter`] has to return it.
+pub fn assert_deletes<S: Store + ?Sized>(store: &mut S, path: &Path, value: Value) {
+    assert_round_trip(store, path, value);
+    assert!(
+        store.as_deleter().is_some(),
+        "store does not expose a Deleter through as_deleter"
+    );
+    for attempt in ["first", "second"] {
+        Store::delete(store, path)
+            .unwrap_or_else(|e| panic!("{} delete of '{}' failed: {}", attempt, path, e));
+        assert_missing(store, path);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{path, Deleter, Error};
+
+    /// A flat map of paths to records that deletes natively, for running
+    /// the checks over the wrappers in this crate.
+    #[derive(Default)]
+    pub(crate) struct MapStore(pub std::collections::BTreeMap<Path, Record>);
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.get(from).cloned())
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data);
+            Ok(to.clone())
+        }
+
+        fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+            Some(self)
+        }
+    }
+
+    impl Deleter for MapStore {
+        fn delete(&mut self, path: &Path) -> Result<(), Error> {
+            self.0.retain(|p, _| !p.has_prefix(path));
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl Reader for Failing {
+        fn read(&mut self, _: &Path) -> Result<Option<Record>, Error> {
+            Err(Error::store("failing", "read", "Handle 1 not found"))
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed instead of returning None")]
+    fn missing_as_error_is_rejected() {
+        assert_missing(&mut Failing, &path!("handles/1"));
+    }
+}
@@ -0,0 +1,442 @@
+//! Per-operation context: deadlines, cancellation and trace ids.
+//!
+//! An [`OpContext`] bounds how long a store operation may take and lets a
+//! caller abandon it. The context is ambient: [`OpContext::scope`] makes it
+//! current for everything called inside, so it reaches the store that does
+//! the work through any number of overlay and mount layers without changing
+//! the [`Reader`](crate::Reader) and [`Writer`](crate::Writer) signatures.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use structfs_core_store::{OpContext, Reader, Path, Error};
+//! # struct Slow;
+//! # impl Reader for Slow {
+//! #     fn read(&mut self, _: &Path) -> Result<Option<structfs_core_store::Record>, Error> {
+//! #         OpContext::check_current("slow")?;
+//! #         Ok(None)
+//! #     }
+//! # }
+//! # let mut store = Slow;
+//!
+//! let ctx = OpContext::new().with_timeout(Duration::from_secs(2));
+//! let result = store.read_with_context(&ctx, &Path::parse("data").unwrap());
+//! ```
+//!
+//! Stores that block, such as HTTP and filesystem stores, call
+//! [`OpContext::check_current`] before doing work and use
+//! [`OpContext::remaining`] to bound their own timeouts. Stores that hand
+//! work to another thread capture [`OpContext::current`] and re-enter it
+//! there. The context is per thread; a scope does not follow work onto other
+//! threads by itself.
+//!
+//! A trace id with a span id identifies where an operation sits in a
+//! distributed trace. [`OpContext::traceparent`] and
+//! [`OpContext::from_traceparent`] convert them to and from the W3C
+//! `traceparent` header.
+//!
+//! A tenant id names whose data an operation may touch;
+//! [`NamespacedStore`](crate::NamespacedStore) confines each operation to its
+//! tenant's subtree.
+//!
+//! A [lease](crate::lease) names the session or Block an operation runs
+//! for; handles opened under it are closed once the lease is released.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::lease::{Lease, WeakLease};
+use crate::Error;
+
+/// A flag shared between a caller and the operations it may cancel.
+///
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<Flag>);
+
+#[derive(Debug, Default)]
+struct Flag {
+    cancelled: AtomicBool,
+    /// Tokens whose cancellation also cancels this one
+    parents: Vec<CancellationToken>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token cancelled along with this one, that can also be cancelled
+    /// on its own without affecting this one.
+    pub fn child(&self) -> Self {
+        Self::linked(vec![self.clone()])
+    }
+
+    fn linked(parents: Vec<CancellationToken>) -> Self {
+        Self(Arc::new(Flag {
+            cancelled: AtomicBool::new(false),
+            parents,
+        }))
+    }
+
+    /// Cancel every operation using this token.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called on this token or a
+    /// token it was derived from.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+            || self.0.parents.iter().any(CancellationToken::is_cancelled)
+    }
+}
+
+/// Deadline, cancellation, tracing and tenancy for store operations.
+///
+/// The default context has no deadline, cannot be cancelled and carries no
+/// trace id or tenant.
+#[derive(Debug, Clone, Default)]
+pub struct OpContext {
+    /// When the operation must be finished.
+    pub deadline: Option<Instant>,
+    /// Token the caller may use to abandon the operation.
+    pub cancellation: Option<CancellationToken>,
+    /// Identifier propagated to remote services, such as in HTTP headers.
+    pub trace_id: Option<String>,
+    /// Identifier of the span the operation runs in, within the trace.
+    pub span_id: Option<String>,
+    /// The tenant the operation runs for.
+    pub tenant: Option<String>,
+    /// The lease of the session or Block the operation runs for.
+    pub lease: Option<WeakLease>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<OpContext>> = const { RefCell::new(None) };
+}
+
+impl OpContext {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the deadline to `timeout` from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Set the deadline.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Set the trace id.
+    pub fn with_trace_id(mut self, trace_id: impl Into<String>) -> Self {
+        self.trace_id = Some(trace_id.into());
+        self
+    }
+
+    /// Set the span id.
+    pub fn with_span_id(mut self, span_id: impl Into<String>) -> Self {
+        self.span_id = Some(span_id.into());
+        self
+    }
+
+    /// Set the tenant.
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Set the lease, without keeping it alive.
+    pub fn with_lease(mut self, lease: &Lease) -> Self {
+        self.lease = Some(lease.downgrade());
+        self
+    }
+
+    /// A context from a W3C `traceparent` header value, such as
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    ///
+    /// Returns `None` if the value is malformed or uses all-zero ids.
+    pub fn from_traceparent(header: &str) -> Option<OpContext> {
+        let mut parts = header.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let valid = |id: &str, len: usize| {
+            id.len() == len
+                && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                && id.bytes().any(|b| b != b'0')
+        };
+        if version.len() != 2
+            || version == "ff"
+            || flags.len() != 2
+            || (version == "00" && parts.next().is_some())
+            || !valid(trace_id, 32)
+            || !valid(span_id, 16)
+        {
+            return None;
+        }
+        Some(
+            OpContext::new()
+                .with_trace_id(trace_id)
+                .with_span_id(span_id),
+        )
+    }
+
+    /// The W3C `traceparent` header value for this context's trace and
+    /// span, if both are set and in W3C form.
+    pub fn traceparent(&self) -> Option<String> {
+        let (trace_id, span_id) = (self.trace_id.as_deref()?, self.span_id.as_deref()?);
+        let header = format!("00-{}-{}-01", trace_id, span_id);
+        OpContext::from_traceparent(&header).map(|_| header)
+    }
+
+    /// Time left before the deadline, or `None` without a deadline.
+    ///
+    /// Returns zero once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the operation was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Fail if the operation was cancelled or its deadline has passed.
+    ///
+    /// `store` names the store doing the check, for the error.
+    pub fn check(&self, store: &'static str) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled { store });
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Error::DeadlineExceeded { store });
+        }
+        Ok(())
+    }
+
+    /// A copy of the current thread's context, or an empty context.
+    pub fn current() -> OpContext {
+        CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    /// Check the current thread's context. See [`check`](Self::check).
+    pub fn check_current(store: &'static str) -> Result<(), Error> {
+        CURRENT.with(|current| match &*current.borrow() {
+            Some(ctx) => ctx.check(store),
+            None => Ok(()),
+        })
+    }
+
+    /// Time left before the current thread's deadline, if it has one.
+    pub fn current_remaining() -> Option<Duration> {
+        CURRENT.with(|current| current.borrow().as_ref().and_then(OpContext::remaining))
+    }
+
+    /// The current thread's tenant, if it has one.
+    pub fn current_tenant() -> Option<String> {
+        CURRENT.with(|current| current.borrow().as_ref().and_then(|c| c.tenant.clone()))
+    }
+
+    /// The current thread's lease, if it has one.
+    pub fn current_lease() -> Option<WeakLease> {
+        CURRENT.with(|current| current.borrow().as_ref().and_then(|c| c.lease.clone()))
+    }
+
+    /// Run `f` with this context as the current thread's context.
+    ///
+    /// Inside a scope that already has a context, the two are combined: the
+    /// earlier deadline wins, cancelling either context's token cancels the
+    /// operation, and this context's trace id and span id replace the outer
+    /// ones when set. An outer tenant and lease are kept, so code running for
+    /// one tenant or Block cannot switch to another. The outer context is restored when `f` returns or panics.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<OpContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT.with(|current| *current.borrow_mut() = previous);
+            }
+        }
+
+        let previous = CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            let combined = match &*current {
+                Some(outer) => self.within(outer),
+                None => self.clone(),
+            };
+            current.replace(combined)
+        });
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// This context, nested inside `outer`.
+    fn within(&self, outer: &OpContext) -> OpContext {
+        let deadline = match (self.deadline, outer.deadline) {
+            (Some(inner), Some(outer)) => Some(inner.min(outer)),
+            (inner, outer) => inner.or(outer),
+        };
+        let cancellation = match (&self.cancellation, &outer.cancellation) {
+            (Some(inner), Some(outer)) if !Arc::ptr_eq(&inner.0, &outer.0) => {
+                Some(CancellationToken::linked(vec![
+                    inner.clone(),
+                    outer.clone(),
+                ]))
+            }
+            (inner, outer) => inner.clone().or_else(|| outer.clone()),
+        };
+        OpContext {
+            deadline,
+            cancellation,
+            trace_id: self.trace_id.clone().or_else(|| outer.trace_id.clone()),
+            span_id: self.span_id.clone().or_else(|| outer.span_id.clone()),
+            tenant: outer.tenant.clone().or_else(|| self.tenant.clone()),
+            lease: outer.lease.clone().or_else(|| self.lease.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_context_never_fails() {
+        let ctx = OpContext::new();
+        assert!(ctx.check("test").is_ok());
+        assert_eq!(ctx.remaining(), None);
+        assert!(OpContext::check_current("test").is_ok());
+    }
+
+    #[test]
+    fn expired_deadline_fails() {
+        let ctx = OpContext::new().with_deadline(Instant::now() - Duration::from_millis(1));
+        assert!(matches!(
+            ctx.check("test"),
+            Err(Error::DeadlineExceeded { store: "test" })
+        ));
+        assert_eq!(ctx.remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn cancellation_fails() {
+        let token = CancellationToken::new();
+        let ctx = OpContext::new().with_cancellation(token.clone());
+        assert!(ctx.check("test").is_ok());
+        token.cancel();
+        assert!(matches!(
+            ctx.check("test"),
+            Err(Error::Cancelled { store: "test" })
+        ));
+    }
+
+    #[test]
+    fn scope_sets_and_restores_current() {
+        let outer = OpContext::new()
+            .with_timeout(Duration::from_secs(1))
+            .with_trace_id("outer");
+        let inner = OpContext::new()
+            .with_timeout(Duration::from_secs(60))
+            .with_cancellation(CancellationToken::new());
+
+        outer.scope(|| {
+            assert_eq!(OpContext::current().trace_id.as_deref(), Some("outer"));
+            inner.scope(|| {
+                let current = OpContext::current();
+                // The outer deadline is earlier, so it still applies
+                assert_eq!(current.deadline, outer.deadline);
+                assert!(current.cancellation.is_some());
+                assert_eq!(current.trace_id.as_deref(), Some("outer"));
+            });
+            assert!(OpContext::current().cancellation.is_none());
+        });
+        assert!(OpContext::current().deadline.is_none());
+    }
+
+    #[test]
+    fn nested_scope_keeps_outer_cancellation() {
+        let outer_token = CancellationToken::new();
+        let inner_token = CancellationToken::new();
+        let outer = OpContext::new().with_cancellation(outer_token.clone());
+        let inner = OpContext::new().with_cancellation(inner_token.clone());
+
+        outer.scope(|| {
+            inner.scope(|| {
+                assert!(OpContext::check_current("test").is_ok());
+                outer_token.cancel();
+                assert!(matches!(
+                    OpContext::check_current("test"),
+                    Err(Error::Cancelled { store: "test" })
+                ));
+            });
+        });
+
+        // The inner token alone cancels only the inner scope
+        inner_token.cancel();
+        let outer_token = CancellationToken::new();
+        let outer = OpContext::new().with_cancellation(outer_token.clone());
+        outer.scope(|| {
+            inner.scope(|| assert!(OpContext::current().is_cancelled()));
+            assert!(!OpContext::current().is_cancelled());
+        });
+    }
+
+    #[test]
+    fn child_token_follows_its_parent() {
+        let parent = CancellationToken::new();
+        let child = parent.child();
+        child.cancel();
+        assert!(!parent.is_cancelled());
+
+        let child = parent.child();
+        parent.cancel();
+        assert!(child.is_cancelled());
+    }
+
+    #[test]
+    fn traceparent_round_trips() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let ctx = OpContext::from_traceparent(header).unwrap();
+        assert_eq!(
+            ctx.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(ctx.span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(ctx.traceparent().as_deref(), Some(header));
+
+        assert!(OpContext::from_traceparent("00-abc-00f067aa0ba902b7-01").is_none());
+        assert!(OpContext::from_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        assert!(OpContext::new().with_trace_id("x").traceparent().is_none());
+    }
+
+    #[test]
+    fn scope_restores_after_panic() {
+        let ctx = OpContext::new().with_trace_id("x");
+        let result = std::panic::catch_unwind(|| ctx.scope(|| panic!("boom")));
+        assert!(result.is_err());
+        assert!(OpContext::current().trace_id.is_none());
+    }
+}
@@ -0,0 +1,411 @@
+//! Capture of failed writes for later retry.
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::traits::delete_in;
+use crate::{Deleter, Error, Path, Reader, Record, Value, Writer};
+
+/// Default subtree where dead letters are exposed.
+pub const DEFAULT_DEAD_LETTER_PREFIX: &str = "dead_letters";
+
+/// Default number of dead letters kept before the oldest are dropped.
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// A write that failed.
+struct DeadLetter {
+    path: Path,
+    record: Record,
+    /// Whether the write was a delete, retried as one.
+    delete: bool,
+    error: String,
+    failed_at_ms: u64,
+    attempts: u64,
+}
+
+impl DeadLetter {
+    fn to_value(&self) -> Value {
+        let (value, format) = match &self.record {
+            Record::Parsed(value) => (value.clone(), None),
+            Record::Raw { bytes, format } => (
+                Value::Bytes(bytes.to_vec()),
+                Some(Value::String(format.to_string())),
+            ),
+        };
+        let mut map = BTreeMap::from([
+            ("path".to_string(), Value::String(self.path.to_string())),
+            ("value".to_string(), value),
+            ("error".to_string(), Value::String(self.error.clone())),
+            (
+                "failed_at_ms".to_string(),
+                Value::Integer(self.failed_at_ms as i64),
+            ),
+            ("attempts".to_string(), Value::Integer(self.attempts as i64)),
+        ]);
+        if let Some(format) = format {
+            map.insert("format".to_string(), format);
+        }
+        Value::Map(map)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// A store wrapper that keeps writes the inner store rejected.
+///
+/// Blocks often write and move on without checking the result. Wrapping
+/// their store in a `DeadLetterStore` keeps every failed write, with the
+/// error and time of failure, under a reserved subtree (`dead_letters/` by
+/// default) where it can be inspected and retried:
+///
+/// | Path | Operation | Result |
+/// |------|-----------|--------|
+/// | `read dead_letters` | List failed writes | `{id: {path, value, error, failed_at_ms, attempts}}` |
+/// | `read dead_letters/{id}` | One failed write | `{path, value, error, failed_at_ms, attempts}` |
+/// | `write dead_letters/{id}/retry null` | Retry one write | The path written, or the new error |
+/// | `write dead_letters/retry null` | Retry every write | Failures stay in the list |
+/// | `write dead_letters/{id} null` | Discard a failed write | |
+///
+/// The failed write still returns its error to the caller. Raw records are
+/// shown as bytes with a `format` field. Once the list holds
+/// [`capacity`](Self::with_capacity) letters, the oldest is dropped to make
+/// room. The reserved subtree shadows the inner store's paths of the same
+/// name.
+pub struct DeadLetterStore<S> {
+    inner: S,
+    prefix: Path,
+    letters: BTreeMap<u64, DeadLetter>,
+    next_id: u64,
+    capacity: usize,
+}
+
+impl<S> DeadLetterStore<S> {
+    /// Wrap `inner`, exposing dead letters at `dead_letters/`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            prefix: Path::parse(DEFAULT_DEAD_LETTER_PREFIX).unwrap(),
+            letters: BTreeMap::new(),
+            next_id: 0,
+            capacity: DEFAULT_DEAD_LETTER_CAPACITY,
+        }
+    }
+
+    /// Expose dead letters at `prefix` instead of `dead_letters/`.
+    pub fn with_prefix(mut self, prefix: Path) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Keep at most `capacity` dead letters.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Number of failed writes kept.
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    /// Whether no failed writes are kept.
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    fn capture(&mut self, path: Path, record: Record, delete: bool, error: &Error) {
+        while self.letters.len() >= self.capacity {
+            self.letters.pop_first();
+        }
+        self.letters.insert(
+            self.next_id,
+            DeadLetter {
+                path,
+                record,
+                delete,
+                error: error.to_string(),
+                failed_at_ms: now_ms(),
+                attempts: 1,
+            },
+        );
+        self.next_id += 1;
+    }
+
+    /// The part of `path` below the dead letter subtree, if it is in it.
+    fn strip_prefix(&self, path: &Path) -> Option<Path> {
+        path.has_prefix(&self.prefix)
+            .then(|| path.slice(self.prefix.len(), path.len()))
+    }
+
+    fn letter_id(component: &str) -> Option<u64> {
+        component.parse().ok()
+    }
+}
+
+impl<S: Writer> DeadLetterStore<S> {
+    /// Retry one dead letter, removing it if the write succeeds.
+    pub fn retry(&mut self, id: u64) -> Result<Path, Error> {
+        let letter = self.letters.get_mut(&id).ok_or_else(|| {
+            Error::store(
+                "dead_letter",
+                "retry",
+                format!("No dead letter with id {}", id),
+            )
+        })?;
+
+        let result = if letter.delete {
+            delete_in(&mut self.inner, &letter.path).map(|()| letter.path.clone())
+        } else {
+            self.inner.write(&letter.path, letter.record.clone())
+        };
+        match result {
+            Ok(written) => {
+                self.letters.remove(&id);
+                Ok(written)
+            }
+            Err(e) => {
+                letter.error = e.to_string();
+                letter.failed_at_ms = now_ms();
+                letter.attempts += 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// Retry every dead letter in order. Returns how many still failed.
+    pub fn retry_all(&mut self) -> usize {
+        let ids: Vec<u64> = self.letters.keys().copied().collect();
+        ids.into_iter()
+            .filter(|id| self.retry(*id).is_err())
+            .count()
+    }
+
+    fn write_control(&mut self, to: &Path, rest: &Path, data: Record) -> Result<Path, Error> {
+        let discard = matches!(data, Record::Parsed(Value::Null));
+        match rest.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["retry"] => {
+                self.retry_all();
+                Ok(to.clone())
+            }
+            [id, "retry"] => match Self::letter_id(id) {
+                Some(id) => self.retry(id),
+                None => Err(Error::store(
+                    "dead_letter",
+                    "retry",
+                    format!("Invalid dead letter id '{}'", id),
+                )),
+            },
+            [id] if discard => {
+                if let Some(id) = Self::letter_id(id) {
+                    self.letters.remove(&id);
+                }
+                Ok(to.clone())
+            }
+            _ => Err(Error::store(
+                "dead_letter",
+                "write",
+                format!(
+                    "Cannot write to '{}'. Write null to {}/{{id}} to discard or to {}/{{id}}/retry to retry.",
+                    to, self.prefix, self.prefix
+                ),
+            )),
+        }
+    }
+}
+
+impl<S: Reader> Reader for DeadLetterStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let Some(rest) = self.strip_prefix(from) else {
+            return self.inner.read(from);
+        };
+
+        let value = match rest.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [] => Value::Map(
+                self.letters
+                    .iter()
+                    .map(|(id, letter)| (id.to_string(), letter.to_value()))
+                    .collect(),
+            ),
+            [id] => match Self::letter_id(id).and_then(|id| self.letters.get(&id)) {
+                Some(letter) => letter.to_value(),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(Record::parsed(value)))
+    }
+}
+
+impl<S: Writer> Writer for DeadLetterStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if let Some(rest) = self.strip_prefix(to) {
+            return self.write_control(to, &rest, data);
+        }
+
+        match self.inner.write(to, data.clone()) {
+            Ok(written) => Ok(written),
+            Err(e) => {
+                self.capture(to.clone(), data, false, &e);
+                Err(e)
+            }
+        }
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Deletes in the subtree discard dead letters, like writes of null. A
+/// failed delete elsewhere is kept, with a null value, and retried as a
+/// delete.
+impl<S: Writer> Deleter for DeadLetterStore<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if let Some(rest) = self.strip_prefix(path) {
+            return self
+                .write_control(path, &rest, Record::parsed(Value::Null))
+                .map(|_| ());
+        }
+
+        delete_in(&mut self.inner, path).inspect_err(|e| {
+            self.capture(path.clone(), Record::parsed(Value::Null), true, e);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, NoCodec};
+    use std::sync::{Arc, Mutex};
+
+    /// Store that rejects writes while `down` is set.
+    #[derive(Clone, Default)]
+    struct Flaky {
+        down: Arc<Mutex<bool>>,
+        written: Arc<Mutex<Vec<Path>>>,
+    }
+
+    impl Reader for Flaky {
+        fn read(&mut self, _from: &Path) -> Result<Option<Record>, Error> {
+            Ok(None)
+        }
+    }
+
+    impl Writer for Flaky {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+            if *self.down.lock().unwrap() {
+                return Err(Error::store("flaky", "write", "backend down"));
+            }
+            self.written.lock().unwrap().push(to.clone());
+            Ok(to.clone())
+        }
+    }
+
+    fn read_map(store: &mut impl Reader, path: &Path) -> BTreeMap<String, Value> {
+        match store.read(path).unwrap().unwrap().into_value(&NoCodec) {
+            Ok(Value::Map(map)) => map,
+            other => panic!("expected a map, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_write_is_captured() {
+        let backend = Flaky::default();
+        *backend.down.lock().unwrap() = true;
+        let mut store = DeadLetterStore::new(backend);
+
+        let err = store
+            .write(&path!("events/1"), Record::parsed(Value::Integer(7)))
+            .unwrap_err();
+        assert!(err.to_string().contains("backend down"));
+        assert_eq!(store.len(), 1);
+
+        let letter = read_map(&mut store, &path!("dead_letters/0"));
+        assert_eq!(letter["path"], Value::String("events/1".into()));
+        assert_eq!(letter["value"], Value::Integer(7));
+        assert_eq!(letter["attempts"], Value::Integer(1));
+        assert!(matches!(&letter["error"], Value::String(e) if e.contains("backend down")));
+
+        assert!(read_map(&mut store, &path!("dead_letters")).contains_key("0"));
+        assert!(store.read(&path!("dead_letters/9")).unwrap().is_none());
+    }
+
+    #[test]
+    fn retry_through_paths() {
+        let backend = Flaky::default();
+        *backend.down.lock().unwrap() = true;
+        let mut store = DeadLetterStore::new(backend.clone());
+        for i in 0..2 {
+            let path = Path::parse(&format!("events/{}", i)).unwrap();
+            let _ = store.write(&path, Record::parsed(Value::Null));
+        }
+
+        // Still down: the retry fails and counts the attempt
+        assert!(store
+            .write(&path!("dead_letters/0/retry"), Record::parsed(Value::Null))
+            .is_err());
+        let letter = read_map(&mut store, &path!("dead_letters/0"));
+        assert_eq!(letter["attempts"], Value::Integer(2));
+
+        *backend.down.lock().unwrap() = false;
+        let written = store
+            .write(&path!("dead_letters/0/retry"), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(written, path!("events/0"));
+
+        store
+            .write(&path!("dead_letters/retry"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(store.is_empty());
+        assert_eq!(
+            *backend.written.lock().unwrap(),
+            vec![path!("events/0"), path!("events/1")]
+        );
+    }
+
+    #[test]
+    fn discard_and_capacity() {
+        let backend = Flaky::default();
+        *backend.down.lock().unwrap() = true;
+        let mut store = DeadLetterStore::new(backend)
+            .with_prefix(path!("failed"))
+            .with_capacity(2);
+        for _ in 0..3 {
+            let _ = store.write(&path!("events"), Record::parsed(Value::Null));
+        }
+
+        // The oldest letter was dropped
+        let letters = read_map(&mut store, &path!("failed"));
+        assert_eq!(letters.keys().collect::<Vec<_>>(), ["1", "2"]);
+
+        store
+            .write(&path!("failed/1"), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(store.len(), 1);
+        assert!(store
+            .write(&path!("failed/2"), Record::parsed(Value::Integer(1)))
+            .is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut store = DeadLetterStore::new(MapStore::default());
+        conformance::assert_missing(&mut store, &path!("orders/1"));
+        conformance::assert_missing(&mut store, &path!("dead_letters/0"));
+        conformance::assert_round_trip(&mut store, &path!("orders/1"), Value::Integer(1));
+        conformance::assert_deletes(&mut store, &path!("orders/1"), Value::Integer(1));
+        assert!(store.is_empty());
+    }
+}
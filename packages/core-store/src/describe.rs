@@ -0,0 +1,400 @@
+//! Stores that describe their own paths.
+//!
+//! By convention a store answers two read-only lenses next to its data:
+//!
+//! - `meta/...` is for programs: what a path is, which actions it accepts
+//!   and where they lead, as [`Reference`]s and [`Action`] descriptors.
+//! - `docs` is for people: a title, a description, the paths with one line
+//!   each, and an example session, built with [`Docs`].
+//!
+//! A store implements [`SelfDescribing`] and routes those reads through
+//! [`read_lens`]. Mounting it with
+//! [`OverlayStore::mount_described`](crate::overlay_store::OverlayStore::mount_described)
+//! also lists it under the overlay's own `meta/` tree.
+
+use std::collections::BTreeMap;
+
+use collection_literals::btree;
+
+use crate::{Error, Path, Record, Reference, Value};
+
+/// First path component of the `meta/` lens.
+pub const META_PREFIX: &str = "meta";
+
+/// First path component of the `docs` lens.
+pub const DOCS_PREFIX: &str = "docs";
+
+/// A store that describes its paths under `meta/` and documents itself
+/// under `docs`.
+pub trait SelfDescribing {
+    /// Describe `path`, which is relative to `meta/`. The empty path is the
+    /// store's overview.
+    ///
+    /// Returns `Ok(None)` for paths with nothing to describe, such as a
+    /// handle that does not exist, and an error for malformed ones.
+    fn describe(&self, path: &Path) -> Result<Option<Value>, Error>;
+
+    /// Documentation served at `docs`. `None` if the store has none.
+    fn docs(&self) -> Option<Value> {
+        None
+    }
+}
+
+/// Serve a read of `from` from `store`'s `meta/` or `docs` lens.
+///
+/// Returns `None` when `from` is in neither, so the store can go on to
+/// read its data:
+///
+/// ```rust,ignore
+/// fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+///     if let Some(result) = read_lens(self, from) {
+///         return result;
+///     }
+///     // ...
+/// }
+/// ```
+pub fn read_lens<S: SelfDescribing + ?Sized>(
+    store: &S,
+    from: &Path,
+) -> Option<Result<Option<Record>, Error>> {
+    let first = from.iter().next()?;
+    if first == META_PREFIX {
+        let rest = from.slice(1, from.len());
+        Some(store.describe(&rest).map(|v| v.map(Record::parsed)))
+    } else if first == DOCS_PREFIX {
+        Some(Ok(store.docs().map(Record::parsed)))
+    } else {
+        None
+    }
+}
+
+/// `{name: type_name}`, the `type` field of descriptors.
+pub fn type_value(type_name: &str) -> Value {
+    Value::Map(btree! {
+        "name".into() => Value::String(type_name.to_string()),
+    })
+}
+
+/// One field an [`Action`] accepts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    type_name: String,
+    required: Option<bool>,
+    values: Vec<Value>,
+}
+
+impl Field {
+    /// A field of type `type_name`, such as `string` or `boolean`.
+    pub fn new(type_name: &str) -> Self {
+        Self {
+            type_name: type_name.to_string(),
+            required: None,
+            values: Vec::new(),
+        }
+    }
+
+    /// A `string` field.
+    pub fn string() -> Self {
+        Self::new("string")
+    }
+
+    /// A `boolean` field.
+    pub fn boolean() -> Self {
+        Self::new("boolean")
+    }
+
+    /// Mark the field as required.
+    #[must_use]
+    pub fn required(mut self) -> Self {
+        self.required = Some(true);
+        self
+    }
+
+    /// Mark the field as explicitly optional.
+    #[must_use]
+    pub fn optional(mut self) -> Self {
+        self.required = Some(false);
+        self
+    }
+
+    /// Restrict the field to these string values.
+    #[must_use]
+    pub fn one_of<I, S>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.values = values
+            .into_iter()
+            .map(|v| Value::String(v.into()))
+            .collect();
+        self
+    }
+
+    /// `{type, required?, values?}`.
+    pub fn to_value(&self) -> Value {
+        let mut map = btree! {
+            "type".into() => type_value(&self.type_name),
+        };
+        if let Some(required) = self.required {
+            map.insert("required".into(), Value::Bool(required));
+        }
+        if !self.values.is_empty() {
+            map.insert("values".into(), Value::Array(self.values.clone()));
+        }
+        Value::Map(map)
+    }
+}
+
+/// Descriptor of an action: a write to `target` that does something.
+///
+/// ```rust
+/// use structfs_core_store::describe::{Action, Field};
+///
+/// let open = Action::write("open")
+///     .accepts("path", Field::string().required())
+///     .returns_collection("handle", "handles")
+///     .to_value();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    target: String,
+    accepts: Option<Value>,
+    returns: Option<Value>,
+}
+
+impl Action {
+    /// An action performed by writing to `target`.
+    pub fn write(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            accepts: None,
+            returns: None,
+        }
+    }
+
+    /// Add a field to the map the action accepts.
+    #[must_use]
+    pub fn accepts(mut self, name: &str, field: Field) -> Self {
+        let mut fields = match self.accepts.take() {
+            Some(Value::Map(fields)) => fields,
+            _ => BTreeMap::new(),
+        };
+        fields.insert(name.to_string(), field.to_value());
+        self.accepts = Some(Value::Map(fields));
+        self
+    }
+
+    /// Accept a single value of `type_name`, such as `null`.
+    #[must_use]
+    pub fn accepts_only(mut self, type_name: &str) -> Self {
+        self.accepts = Some(Value::String(type_name.to_string()));
+        self
+    }
+
+    /// What the action returns, as a raw descriptor.
+    #[must_use]
+    pub fn returns(mut self, returns: Value) -> Self {
+        self.returns = Some(returns);
+        self
+    }
+
+    /// The action returns a value of `type_name`.
+    #[must_use]
+    pub fn returns_type(self, type_name: &str) -> Self {
+        self.returns(Value::Map(btree! {
+            "type".into() => type_value(type_name),
+        }))
+    }
+
+    /// The action returns a path to a new `type_name` in `collection`.
+    #[must_use]
+    pub fn returns_collection(self, type_name: &str, collection: &str) -> Self {
+        self.returns(Value::Map(btree! {
+            "type".into() => type_value(type_name),
+            "collection".into() => Reference::new(collection).to_value(),
+        }))
+    }
+
+    /// `{type: action, method: write, target, accepts?, returns?}`.
+    pub fn to_value(&self) -> Value {
+        let mut map = btree! {
+            "type".into() => type_value("action"),
+            "method".into() => Value::String("write".into()),
+            "target".into() => Reference::new(self.target.clone()).to_value(),
+        };
+        if let Some(accepts) = &self.accepts {
+            map.insert("accepts".into(), accepts.clone());
+        }
+        if let Some(returns) = &self.returns {
+            map.insert("returns".into(), returns.clone());
+        }
+        Value::Map(map)
+    }
+}
+
+impl From<Action> for Value {
+    fn from(action: Action) -> Self {
+        action.to_value()
+    }
+}
+
+/// Documentation for the `docs` lens.
+///
+/// ```rust
+/// use structfs_core_store::describe::Docs;
+///
+/// let docs = Docs::new("Counter", "Counts writes.")
+///     .path("read /", "The count")
+///     .path("write / null", "Add one")
+///     .example(["write / null", "read /", "# Returns: 1"])
+///     .to_value();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Docs {
+    fields: BTreeMap<String, Value>,
+    paths: BTreeMap<String, Value>,
+}
+
+impl Docs {
+    /// Documentation with a title and a one-paragraph description.
+    pub fn new(title: &str, description: &str) -> Self {
+        Self {
+            fields: btree! {
+                "title".into() => Value::String(title.to_string()),
+                "description".into() => Value::String(description.to_string()),
+            },
+            paths: BTreeMap::new(),
+        }
+    }
+
+    /// Describe an operation, such as `read /outstanding/{id}`, in a line.
+    #[must_use]
+    pub fn path(mut self, operation: &str, description: &str) -> Self {
+        self.paths.insert(
+            operation.to_string(),
+            Value::String(description.to_string()),
+        );
+        self
+    }
+
+    /// An example session, one line per command or `#` comment.
+    #[must_use]
+    pub fn example<I, S>(self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let lines = lines.into_iter().map(|l| Value::String(l.into())).collect();
+        self.field("example", Value::Array(lines))
+    }
+
+    /// Any other top-level field, such as `errors`.
+    #[must_use]
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.fields.insert(name.to_string(), value);
+        self
+    }
+
+    /// `{title, description, paths?, example?, ...}`.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.fields.clone();
+        if !self.paths.is_empty() {
+            map.insert("paths".into(), Value::Map(self.paths.clone()));
+        }
+        Value::Map(map)
+    }
+}
+
+impl From<Docs> for Value {
+    fn from(docs: Docs) -> Self {
+        docs.to_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+
+    struct Counter;
+
+    impl SelfDescribing for Counter {
+        fn describe(&self, path: &Path) -> Result<Option<Value>, Error> {
+            match path.len() {
+                0 => Ok(Some(Action::write("").accepts_only("null").into())),
+                _ => Ok(None),
+            }
+        }
+
+        fn docs(&self) -> Option<Value> {
+            Some(Docs::new("Counter", "Counts writes.").into())
+        }
+    }
+
+    #[test]
+    fn builders_match_the_descriptor_shape() {
+        let action = Action::write("open")
+            .accepts("path", Field::string().required())
+            .accepts("mode", Field::string().one_of(["read", "write"]))
+            .accepts("body", Field::string().optional())
+            .returns_collection("handle", "handles");
+        assert_eq!(
+            action.to_value(),
+            Value::Map(btree! {
+                "type".into() => type_value("action"),
+                "method".into() => Value::String("write".into()),
+                "target".into() => Reference::new("open").to_value(),
+                "accepts".into() => Value::Map(btree! {
+                    "path".into() => Value::Map(btree! {
+                        "type".into() => type_value("string"),
+                        "required".into() => Value::Bool(true),
+                    }),
+                    "mode".into() => Value::Map(btree! {
+                        "type".into() => type_value("string"),
+                        "values".into() => Value::Array(vec![
+                            Value::String("read".into()),
+                            Value::String("write".into()),
+                        ]),
+                    }),
+                    "body".into() => Value::Map(btree! {
+                        "type".into() => type_value("string"),
+                        "required".into() => Value::Bool(false),
+                    }),
+                }),
+                "returns".into() => Value::Map(btree! {
+                    "type".into() => type_value("handle"),
+                    "collection".into() => Reference::new("handles").to_value(),
+                }),
+            })
+        );
+
+        let docs = Docs::new("T", "D")
+            .path("read /", "Root")
+            .example(["read /"])
+            .to_value();
+        let Value::Map(docs) = docs else {
+            panic!("expected a map");
+        };
+        assert_eq!(docs["title"], Value::String("T".into()));
+        assert!(matches!(&docs["paths"], Value::Map(p) if p.len() == 1));
+        assert!(matches!(&docs["example"], Value::Array(e) if e.len() == 1));
+    }
+
+    #[test]
+    fn read_lens_routes_meta_and_docs() {
+        let counter = Counter;
+        let meta = read_lens(&counter, &path!("meta")).unwrap().unwrap();
+        assert!(meta.is_some());
+        assert!(read_lens(&counter, &path!("meta/x"))
+            .unwrap()
+            .unwrap()
+            .is_none());
+        assert!(read_lens(&counter, &path!("docs/anything"))
+            .unwrap()
+            .unwrap()
+            .is_some());
+        assert!(read_lens(&counter, &path!("data")).is_none());
+        assert!(read_lens(&counter, &path!("")).is_none());
+    }
+}
@@ -0,0 +1,584 @@
+//! A streaming dump format for copying subtrees between stores.
+//!
+//! A dump is a sequence of entries, one per record:
+//!
+//! ```text
+//! {"path":"users/alice","format":"application/json","bytes":"eyJhZ2UiOjMwfQ=="}
+//! {"path":"users/bob","format":"application/x-structfs-value","bytes":"oWNhZ2UYKA=="}
+//! ```
+//!
+//! Each entry holds a record's full path, its format and its bytes. Raw
+//! records keep the format and bytes they were read with. Parsed records
+//! are stored as CBOR under [`Format::VALUE`], which keeps every [`Value`]
+//! variant (byte strings, integers and floats) as it was, and are written
+//! back parsed.
+//!
+//! Dumps come in two encodings. [`DumpFormat::Jsonl`] writes each entry as
+//! a line of JSON with the bytes in base64, so a dump can be read and
+//! edited with text tools. [`DumpFormat::Cbor`] writes each entry as a CBOR
+//! map, one after another (a CBOR sequence), and is smaller.
+//!
+//! Entries are written and read one at a time, so neither side holds the
+//! whole dump in memory:
+//!
+//! ```rust
+//! use structfs_core_store::dump::{export_subtree, import_subtree, DumpReader, DumpWriter};
+//! # use structfs_core_store::{path, Error, Path, Reader, Record, Writer};
+//! # fn copy(from: &mut impl Reader, to: &mut impl Writer) -> Result<(), Error> {
+//! let mut dump = DumpWriter::jsonl(Vec::new());
+//! export_subtree(from, &path!("users"), &mut dump)?;
+//!
+//! let bytes = dump.into_inner();
+//! import_subtree(to, &mut DumpReader::jsonl(bytes.as_slice()))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Format, Path, Reader, Record, Value, Writer};
+
+/// How the entries of a dump are encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// One JSON object per line, with the bytes in base64.
+    #[default]
+    Jsonl,
+    /// One CBOR map per entry, back to back.
+    Cbor,
+}
+
+impl DumpFormat {
+    /// The format of a dump file with this extension: `cbor` for CBOR,
+    /// anything else for JSONL.
+    pub fn from_extension(extension: &str) -> Self {
+        if extension.eq_ignore_ascii_case("cbor") {
+            DumpFormat::Cbor
+        } else {
+            DumpFormat::Jsonl
+        }
+    }
+
+    fn wire_format(self) -> Format {
+        match self {
+            DumpFormat::Jsonl => Format::JSON,
+            DumpFormat::Cbor => Format::CBOR,
+        }
+    }
+}
+
+/// One record in a dump.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DumpEntry {
+    /// Where the record was read from.
+    pub path: Path,
+    /// The format of `bytes`.
+    pub format: Format,
+    /// The record's bytes.
+    pub bytes: Bytes,
+}
+
+impl DumpEntry {
+    /// The entry for `record`, read from `path`.
+    pub fn from_record(path: Path, record: Record) -> Result<Self, Error> {
+        let (format, bytes) = match record {
+            Record::Raw { bytes, format } => (format, bytes),
+            Record::Parsed(value) => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(&value_to_cbor(value), &mut bytes)
+                    .map_err(|e| Error::encode(Format::VALUE, e.to_string()))?;
+                (Format::VALUE, Bytes::from(bytes))
+            }
+        };
+        Ok(Self {
+            path,
+            format,
+            bytes,
+        })
+    }
+
+    /// The record to write back: parsed for [`Format::VALUE`] entries, raw
+    /// otherwise.
+    pub fn into_record(self) -> Result<Record, Error> {
+        if !self.format.is_value() {
+            return Ok(Record::raw(self.bytes, self.format));
+        }
+        let cbor: ciborium::Value = ciborium::from_reader(self.bytes.as_ref())
+            .map_err(|e| Error::decode(Format::VALUE, e.to_string()))?;
+        Ok(Record::parsed(cbor_to_value(cbor)?))
+    }
+}
+
+/// An entry as it is encoded in a dump.
+#[derive(Serialize, Deserialize)]
+struct WireEntry {
+    path: String,
+    format: String,
+    #[serde(with = "wire_bytes")]
+    bytes: Vec<u8>,
+}
+
+/// Bytes as base64 in JSON, and as a byte string in CBOR.
+mod wire_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a byte string or base64 string")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Vec<u8>, E> {
+                STANDARD.decode(s).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(bytes.to_vec())
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+                Ok(bytes)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Visitor)
+        } else {
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+}
+
+fn value_to_cbor(value: Value) -> ciborium::Value {
+    match value {
+        Value::Null => ciborium::Value::Null,
+        Value::Bool(b) => ciborium::Value::Bool(b),
+        Value::Integer(i) => ciborium::Value::Integer(i.into()),
+        Value::Float(f) => ciborium::Value::Float(f),
+        Value::String(s) => ciborium::Value::Text(s),
+        Value::Bytes(b) => ciborium::Value::Bytes(b),
+        Value::Array(items) => {
+            ciborium::Value::Array(items.into_iter().map(value_to_cbor).collect())
+        }
+        Value::Map(map) => ciborium::Value::Map(
+            map.into_iter()
+                .map(|(k, v)| (ciborium::Value::Text(k), value_to_cbor(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn cbor_to_value(cbor: ciborium::Value) -> Result<Value, Error> {
+    let invalid = |message: String| Error::decode(Format::VALUE, message);
+    Ok(match cbor {
+        ciborium::Value::Null => Value::Null,
+        ciborium::Value::Bool(b) => Value::Bool(b),
+        ciborium::Value::Integer(i) => Value::Integer(
+            i64::try_from(i).map_err(|_| invalid("Integer out of range".to_string()))?,
+        ),
+        ciborium::Value::Float(f) => Value::Float(f),
+        ciborium::Value::Text(s) => Value::String(s),
+        ciborium::Value::Bytes(b) => Value::Bytes(b),
+        ciborium::Value::Tag(_, inner) => cbor_to_value(*inner)?,
+        ciborium::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(cbor_to_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        ciborium::Value::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| match k {
+                    ciborium::Value::Text(k) => Ok((k, cbor_to_value(v)?)),
+                    other => Err(invalid(format!("Map key {:?} is not a string", other))),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        other => return Err(invalid(format!("Unsupported CBOR value {:?}", other))),
+    })
+}
+
+/// Writes dump entries to `W`, one at a time.
+pub struct DumpWriter<W: Write> {
+    writer: W,
+    format: DumpFormat,
+}
+
+impl<W: Write> DumpWriter<W> {
+    /// Write entries to `writer`, encoded as `format`.
+    pub fn new(writer: W, format: DumpFormat) -> Self {
+        Self { writer, format }
+    }
+
+    /// Write entries as lines of JSON.
+    pub fn jsonl(writer: W) -> Self {
+        Self::new(writer, DumpFormat::Jsonl)
+    }
+
+    /// Write entries as a CBOR sequence.
+    pub fn cbor(writer: W) -> Self {
+        Self::new(writer, DumpFormat::Cbor)
+    }
+
+    /// The encoding entries are written in.
+    pub fn format(&self) -> DumpFormat {
+        self.format
+    }
+
+    /// Append `entry` to the dump.
+    pub fn write_entry(&mut self, entry: &DumpEntry) -> Result<(), Error> {
+        let wire = WireEntry {
+            path: entry.path.to_string(),
+            format: entry.format.as_str().to_string(),
+            bytes: entry.bytes.to_vec(),
+        };
+        let encode_error = |e: String| Error::encode(self.format.wire_format(), e);
+        match self.format {
+            DumpFormat::Jsonl => {
+                serde_json::to_writer(&mut self.writer, &wire)
+                    .map_err(|e| encode_error(e.to_string()))?;
+                self.writer.write_all(b"\n")?;
+            }
+            DumpFormat::Cbor => ciborium::into_writer(&wire, &mut self.writer)
+                .map_err(|e| encode_error(e.to_string()))?,
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads dump entries from `R`, one at a time.
+///
+/// Iterating yields each entry in turn. Blank lines in a JSONL dump are
+/// skipped. An entry that cannot be decoded is an error naming its
+/// position in the dump, counting from 1.
+pub struct DumpReader<R: Read> {
+    reader: BufReader<R>,
+    format: DumpFormat,
+    position: usize,
+}
+
+impl<R: Read> DumpReader<R> {
+    /// Read entries from `reader`, encoded as `format`.
+    pub fn new(reader: R, format: DumpFormat) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            format,
+            position: 0,
+        }
+    }
+
+    /// Read entries from lines of JSON.
+    pub fn jsonl(reader: R) -> Self {
+        Self::new(reader, DumpFormat::Jsonl)
+    }
+
+    /// Read entries from a CBOR sequence.
+    pub fn cbor(reader: R) -> Self {
+        Self::new(reader, DumpFormat::Cbor)
+    }
+
+    /// The next entry, or `None` at the end of the dump.
+    pub fn read_entry(&mut self) -> Result<Option<DumpEntry>, Error> {
+        let wire: WireEntry = match self.format {
+            DumpFormat::Jsonl => {
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        return Ok(None);
+                    }
+                    self.position += 1;
+                    if !line.trim().is_empty() {
+                        break;
+                    }
+                }
+                serde_json::from_str(&line).map_err(|e| self.decode_error(e.to_string()))?
+            }
+            DumpFormat::Cbor => {
+                if self.reader.fill_buf()?.is_empty() {
+                    return Ok(None);
+                }
+                self.position += 1;
+                ciborium::from_reader(&mut self.reader)
+                    .map_err(|e| self.decode_error(e.to_string()))?
+            }
+        };
+        let path = Path::parse(&wire.path).map_err(|e| self.decode_error(e.to_string()))?;
+        Ok(Some(DumpEntry {
+            path,
+            format: Format::new(wire.format),
+            bytes: Bytes::from(wire.bytes),
+        }))
+    }
+
+    fn decode_error(&self, message: String) -> Error {
+        let unit = match self.format {
+            DumpFormat::Jsonl => "line",
+            DumpFormat::Cbor => "entry",
+        };
+        Error::decode(
+            self.format.wire_format(),
+            format!("Dump {} {}: {}", unit, self.position, message),
+        )
+    }
+}
+
+impl<R: Read> Iterator for DumpReader<R> {
+    type Item = Result<DumpEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+/// Write every record at or below `prefix` in `store` to `dump`. Returns
+/// how many entries were written.
+///
+/// A parsed map is walked by reading each of its keys in turn, so records
+/// a store keeps raw, or a mount below `prefix`, are dumped as the store
+/// returns them. A map whose keys are not all valid path components is
+/// dumped whole, as is an empty map. Null values are left out, since
+/// writing null deletes.
+pub fn export_subtree<S, W>(
+    store: &mut S,
+    prefix: &Path,
+    dump: &mut DumpWriter<W>,
+) -> Result<usize, Error>
+where
+    S: Reader + ?Sized,
+    W: Write,
+{
+    let count = walk_subtree(store, prefix, &mut |path, record| {
+        dump.write_entry(&DumpEntry::from_record(path, record)?)
+    })?;
+    dump.flush()?;
+    Ok(count)
+}
+
+/// Call `visit` with every record at or below `prefix` in `store`, walked
+/// as [`export_subtree`] walks it. Returns how many records were visited.
+pub(crate) fn walk_subtree<S>(
+    store: &mut S,
+    prefix: &Path,
+    visit: &mut dyn FnMut(Path, Record) -> Result<(), Error>,
+) -> Result<usize, Error>
+where
+    S: Reader + ?Sized,
+{
+    match store.read(prefix)? {
+        Some(record) => walk_record(store, prefix.clone(), record, visit),
+        None => Ok(0),
+    }
+}
+
+fn walk_record<S>(
+    store: &mut S,
+    path: Path,
+    record: Record,
+    visit: &mut dyn FnMut(Path, Record) -> Result<(), Error>,
+) -> Result<usize, Error>
+where
+    S: Reader + ?Sized,
+{
+    match record {
+        Record::Parsed(Value::Null) => Ok(0),
+        Record::Parsed(Value::Map(map))
+            if !map.is_empty()
+                && map
+                    .keys()
+                    .all(|k| Path::try_from_components(vec![k.clone()]).is_ok()) =>
+        {
+            let mut count = 0;
+            for (key, value) in map {
+                let child = path.join(&Path::from_components(vec![key]));
+                let record = store.read(&child)?.unwrap_or(Record::parsed(value));
+                count += walk_record(store, child, record, visit)?;
+            }
+            Ok(count)
+        }
+        record => {
+            visit(path, record)?;
+            Ok(1)
+        }
+    }
+}
+
+/// Write every entry in `dump` to `store`, at the path it was exported
+/// from. Returns how many records were written.
+///
+/// Entries are written as they are read; if one fails, those before it
+/// stay written.
+pub fn import_subtree<S, R>(store: &mut S, dump: &mut DumpReader<R>) -> Result<usize, Error>
+where
+    S: Writer + ?Sized,
+    R: Read,
+{
+    let mut count = 0;
+    while let Some(entry) = dump.read_entry()? {
+        let path = entry.path.clone();
+        store.write(&path, entry.into_record()?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, NoCodec};
+    use std::collections::BTreeMap;
+
+    /// Keeps each record at its own path, and answers reads of a prefix
+    /// with a map of what is below it.
+    #[derive(Default)]
+    struct FlatStore(BTreeMap<Path, Record>);
+
+    impl Reader for FlatStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            if let Some(record) = self.0.get(from) {
+                return Ok(Some(record.clone()));
+            }
+            let keys: BTreeMap<String, Value> = self
+                .0
+                .keys()
+                .filter(|p| p.len() > from.len() && p.has_prefix(from))
+                .map(|p| (p[from.len()].clone(), Value::Null))
+                .collect();
+            Ok((!keys.is_empty()).then(|| Record::parsed(Value::Map(keys))))
+        }
+    }
+
+    impl Writer for FlatStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data);
+            Ok(to.clone())
+        }
+    }
+
+    fn sample() -> FlatStore {
+        let mut store = FlatStore::default();
+        let alice = Value::Map(BTreeMap::from([
+            ("age".to_string(), Value::Integer(30)),
+            ("key".to_string(), Value::Bytes(vec![0, 1, 255])),
+        ]));
+        store
+            .write(&path!("users/alice"), Record::parsed(alice))
+            .unwrap();
+        store
+            .write(
+                &path!("users/bob"),
+                Record::raw(Bytes::from_static(b"{\"age\":40}"), Format::JSON),
+            )
+            .unwrap();
+        store
+            .write(
+                &path!("users/carol/score"),
+                Record::parsed(Value::Float(1.5)),
+            )
+            .unwrap();
+        store
+            .write(&path!("other"), Record::parsed(Value::Bool(true)))
+            .unwrap();
+        store
+    }
+
+    fn round_trip(format: DumpFormat) {
+        let mut source = sample();
+        let mut dump = DumpWriter::new(Vec::new(), format);
+        let exported = export_subtree(&mut source, &path!("users"), &mut dump).unwrap();
+        // alice/age, alice/key, bob and carol/score
+        assert_eq!(exported, 4);
+
+        let bytes = dump.into_inner();
+        let mut target = FlatStore::default();
+        let imported =
+            import_subtree(&mut target, &mut DumpReader::new(bytes.as_slice(), format)).unwrap();
+        assert_eq!(imported, 4);
+
+        let value = |store: &mut FlatStore, path: &Path| {
+            store
+                .read(path)
+                .unwrap()
+                .unwrap()
+                .into_value(&NoCodec)
+                .unwrap()
+        };
+        let bob = target.read(&path!("users/bob")).unwrap().unwrap();
+        assert_eq!(bob.format(), Format::JSON);
+        assert_eq!(bob.as_bytes().unwrap().as_ref(), b"{\"age\":40}");
+        assert_eq!(
+            value(&mut target, &path!("users/alice/key")),
+            Value::Bytes(vec![0, 1, 255])
+        );
+        assert_eq!(
+            value(&mut target, &path!("users/alice/age")),
+            Value::Integer(30)
+        );
+        assert_eq!(
+            value(&mut target, &path!("users/carol/score")),
+            Value::Float(1.5)
+        );
+        assert!(!target.0.contains_key(&path!("other")));
+    }
+
+    #[test]
+    fn round_trips_jsonl_and_cbor() {
+        round_trip(DumpFormat::Jsonl);
+        round_trip(DumpFormat::Cbor);
+    }
+
+    #[test]
+    fn jsonl_is_one_entry_per_line() {
+        let mut dump = DumpWriter::jsonl(Vec::new());
+        export_subtree(&mut sample(), &path!("users/bob"), &mut dump).unwrap();
+        let text = String::from_utf8(dump.into_inner()).unwrap();
+        assert_eq!(
+            text,
+            "{\"path\":\"users/bob\",\"format\":\"application/json\",\"bytes\":\"eyJhZ2UiOjQwfQ==\"}\n"
+        );
+
+        // Blank lines are skipped; bad lines are reported by number
+        let bad = format!("\n{}not json\n", text);
+        let mut reader = DumpReader::jsonl(bad.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap().path, path!("users/bob"));
+        let err = reader.next().unwrap().unwrap_err().to_string();
+        assert!(err.contains("line 3"), "{}", err);
+    }
+
+    #[test]
+    fn missing_prefix_exports_nothing() {
+        let mut dump = DumpWriter::cbor(Vec::new());
+        assert_eq!(
+            export_subtree(&mut sample(), &path!("missing"), &mut dump).unwrap(),
+            0
+        );
+        assert!(dump.into_inner().is_empty());
+    }
+}
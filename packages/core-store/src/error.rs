@@ -1,7 +1,9 @@
 //! Error types for the Core layer.
 
 use crate::format::Format;
+use crate::limits::Limit;
 use crate::path::{Path, PathError};
+use crate::value::Value;
 
 /// Whether a codec error occurred during encoding or decoding.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -53,6 +55,35 @@ pub enum Error {
         operation: &'static str,
         message: String,
     },
+
+    /// A record exceeded a configured size or shape limit.
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+        actual: usize,
+    },
+
+    /// An operation was refused because a rate limit was exceeded. It may
+    /// succeed if retried after `retry_after`.
+    RateLimited {
+        store: &'static str,
+        retry_after: std::time::Duration,
+    },
+
+    /// The operation's [`OpContext`](crate::OpContext) deadline passed.
+    DeadlineExceeded { store: &'static str },
+
+    /// The operation's [`OpContext`](crate::OpContext) was cancelled.
+    Cancelled { store: &'static str },
+
+    /// Store-specific error with a structured payload, such as the JSON body
+    /// of a failed HTTP response.
+    Detailed {
+        store: &'static str,
+        operation: &'static str,
+        message: String,
+        detail: Value,
+    },
 }
 
 impl Error {
@@ -65,6 +96,29 @@ impl Error {
         }
     }
 
+    /// Create a store-specific error carrying a structured `detail` value.
+    pub fn store_with_detail(
+        store: &'static str,
+        operation: &'static str,
+        message: impl Into<String>,
+        detail: Value,
+    ) -> Self {
+        Error::Detailed {
+            store,
+            operation,
+            message: message.into(),
+            detail,
+        }
+    }
+
+    /// The structured payload of a [`Error::Detailed`] error, if any.
+    pub fn detail(&self) -> Option<&Value> {
+        match self {
+            Error::Detailed { detail, .. } => Some(detail),
+            _ => None,
+        }
+    }
+
     /// Create a codec decode error.
     pub fn decode(format: Format, message: impl Into<String>) -> Self {
         Error::Codec {
@@ -101,10 +155,27 @@ impl std::fmt::Display for Error {
             }
             Error::Ll(e) => write!(f, "low-level error: {}", e),
             Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::LimitExceeded { limit, max, actual } => {
+                write!(f, "record exceeds {} limit: {} > {}", limit, actual, max)
+            }
+            Error::RateLimited { store, retry_after } => write!(
+                f,
+                "{}: rate limited, retry after {}ms",
+                store,
+                retry_after.as_millis()
+            ),
+            Error::DeadlineExceeded { store } => write!(f, "{}: deadline exceeded", store),
+            Error::Cancelled { store } => write!(f, "{}: cancelled", store),
             Error::Store {
                 store,
                 operation,
                 message,
+            }
+            | Error::Detailed {
+                store,
+                operation,
+                message,
+                ..
             } => write!(f, "{}::{}: {}", store, operation, message),
         }
     }
@@ -191,6 +262,26 @@ mod tests {
         assert!(display.contains("low-level error"));
     }
 
+    #[test]
+    fn rate_limited_display() {
+        let e = Error::RateLimited {
+            store: "block_quota",
+            retry_after: std::time::Duration::from_millis(250),
+        };
+        assert_eq!(
+            e.to_string(),
+            "block_quota: rate limited, retry after 250ms"
+        );
+    }
+
+    #[test]
+    fn context_error_display() {
+        let e = Error::DeadlineExceeded { store: "http" };
+        assert_eq!(e.to_string(), "http: deadline exceeded");
+        let e = Error::Cancelled { store: "fs" };
+        assert_eq!(e.to_string(), "fs: cancelled");
+    }
+
     #[test]
     fn store_error_display() {
         let e = Error::store("http_broker", "read", "Request 42 not found");
@@ -228,6 +319,19 @@ mod tests {
         assert!(StdError::source(&e).is_some());
     }
 
+    #[test]
+    fn detailed_error_display_and_detail() {
+        let detail = Value::Map(
+            [("code".to_string(), Value::from("NoSuchBucket"))]
+                .into_iter()
+                .collect(),
+        );
+        let e = Error::store_with_detail("http_client", "read", "HTTP 404", detail.clone());
+        assert_eq!(e.to_string(), "http_client::read: HTTP 404");
+        assert_eq!(e.detail(), Some(&detail));
+        assert!(Error::store("test", "op", "message").detail().is_none());
+    }
+
     #[test]
     fn store_error_source_is_none() {
         let e = Error::store("test", "op", "message");
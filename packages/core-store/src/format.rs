@@ -28,6 +28,12 @@ impl Format {
     /// CBOR (`application/cbor`)
     pub const CBOR: Format = Format(Cow::Borrowed("application/cbor"));
 
+    /// YAML (`application/yaml`)
+    pub const YAML: Format = Format(Cow::Borrowed("application/yaml"));
+
+    /// TOML (`application/toml`)
+    pub const TOML: Format = Format(Cow::Borrowed("application/toml"));
+
     /// Opaque binary data (`application/octet-stream`)
     pub const OCTET_STREAM: Format = Format(Cow::Borrowed("application/octet-stream"));
 
@@ -56,6 +62,26 @@ impl Format {
         self == &Self::JSON
     }
 
+    /// Check if this is CBOR format.
+    pub fn is_cbor(&self) -> bool {
+        self == &Self::CBOR
+    }
+
+    /// Check if this is MessagePack format.
+    pub fn is_msgpack(&self) -> bool {
+        self == &Self::MSGPACK
+    }
+
+    /// Check if this is YAML format.
+    pub fn is_yaml(&self) -> bool {
+        self == &Self::YAML
+    }
+
+    /// Check if this is TOML format.
+    pub fn is_toml(&self) -> bool {
+        self == &Self::TOML
+    }
+
     /// Check if this is protobuf format.
     pub fn is_protobuf(&self) -> bool {
         self == &Self::PROTOBUF
@@ -173,6 +199,8 @@ mod tests {
         // Cover all constant definitions
         assert_eq!(Format::MSGPACK.as_str(), "application/msgpack");
         assert_eq!(Format::CBOR.as_str(), "application/cbor");
+        assert_eq!(Format::YAML.as_str(), "application/yaml");
+        assert_eq!(Format::TOML.as_str(), "application/toml");
         assert_eq!(Format::OCTET_STREAM.as_str(), "application/octet-stream");
         assert_eq!(Format::VALUE.as_str(), "application/x-structfs-value");
     }
@@ -0,0 +1,377 @@
+//! Id allocation and bookkeeping for stores that hand out handles.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use collection_literals::btree;
+
+use crate::lease::WeakLease;
+use crate::{OpContext, Path, Reference, Value};
+
+struct Entry<T> {
+    value: T,
+    created: Instant,
+    last_used: Instant,
+    /// The lease current when the handle was opened
+    lease: Option<WeakLease>,
+}
+
+impl<T> Entry<T> {
+    fn new(value: T) -> Self {
+        let now = Instant::now();
+        Self {
+            value,
+            created: now,
+            last_used: now,
+            lease: OpContext::current_lease(),
+        }
+    }
+}
+
+/// The open handles of a store that follows the handle pattern.
+///
+/// Many stores turn a write into something to come back to: the write
+/// returns `{prefix}/{id}`, reads under that path report on or use the
+/// handle, and a final write closes it. `HandleBroker` keeps the handles and
+/// supplies the parts of that pattern every such store shares:
+///
+/// - Ids are allocated in increasing order per broker and never reused.
+/// - [`parse`](Self::parse) splits `{prefix}/{id}/rest...` into the id and
+///   the rest of the path.
+/// - [`listing`](Self::listing) is the `{items: [references]}` value read
+///   from `{prefix}`.
+/// - [`status`](Self::status) is `{id, age_ms, idle_ms, expires_in_ms,
+///   owner}`, for stores to merge into what they report about a handle.
+/// - With a [TTL](Self::with_ttl), handles not [touched](Self::touch) for
+///   that long are dropped the next time the broker is changed or listed.
+/// - Handles opened under a [lease](crate::lease) are dropped the same way
+///   once the lease is released, so a crashed session or Block does not
+///   leak them.
+///
+/// ```rust
+/// use structfs_core_store::{path, HandleBroker};
+///
+/// let mut handles = HandleBroker::new("handles", "handle");
+/// let id = handles.insert("report.txt");
+/// assert_eq!(handles.path(id), path!("handles/0"));
+/// assert_eq!(handles.parse(&path!("handles/0/position")), Some((0, path!("position"))));
+/// assert_eq!(handles.remove(id), Some("report.txt"));
+/// ```
+pub struct HandleBroker<T> {
+    prefix: Path,
+    type_name: String,
+    entries: BTreeMap<u64, Entry<T>>,
+    next_id: u64,
+    ttl: Option<Duration>,
+}
+
+impl<T> HandleBroker<T> {
+    /// Create a broker whose handles live at `{prefix}/{id}` and are
+    /// referenced with type `type_name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` is not a valid path.
+    pub fn new(prefix: &str, type_name: &str) -> Self {
+        Self {
+            prefix: Path::parse(prefix).expect("invalid handle prefix"),
+            type_name: type_name.to_string(),
+            entries: BTreeMap::new(),
+            next_id: 0,
+            ttl: None,
+        }
+    }
+
+    /// Drop handles that go unused for `ttl`.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Change how long unused handles are kept. `None` keeps them until
+    /// they are removed.
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    /// The path handles live under.
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    /// Add a handle, returning its id.
+    pub fn insert(&mut self, value: T) -> u64 {
+        self.insert_with(|_| value)
+    }
+
+    /// Add a handle built from its id, returning the id.
+    pub fn insert_with(&mut self, make: impl FnOnce(u64) -> T) -> u64 {
+        self.sweep();
+        let id = self.next_id;
+        let value = make(id);
+        self.next_id += 1;
+        self.entries.insert(id, Entry::new(value));
+        id
+    }
+
+    /// Put back a handle kept from an earlier run under its old id.
+    ///
+    /// New ids are allocated above every restored one. Replaces any handle
+    /// already open with `id`.
+    pub fn restore(&mut self, id: u64, value: T) {
+        self.next_id = self.next_id.max(id + 1);
+        self.entries.insert(id, Entry::new(value));
+    }
+
+    /// Never allocate ids below `next_id`, so ids handed out before a
+    /// restart are not reused after it.
+    pub fn reserve_below(&mut self, next_id: u64) {
+        self.next_id = self.next_id.max(next_id);
+    }
+
+    /// The id the next handle will get.
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// The path of handle `id`: `{prefix}/{id}`.
+    pub fn path(&self, id: u64) -> Path {
+        self.prefix
+            .join(&Path::from_components(vec![id.to_string()]))
+    }
+
+    /// Split `{prefix}/{id}/rest...` into the id and the rest.
+    ///
+    /// Returns `None` for paths outside the prefix, for the prefix itself
+    /// and for ids that are not numbers. The handle need not exist.
+    pub fn parse(&self, path: &Path) -> Option<(u64, Path)> {
+        let rest = path.strip_prefix(&self.prefix)?;
+        let id = rest.iter().next()?.parse().ok()?;
+        Some((id, rest.slice(1, rest.len())))
+    }
+
+    /// Whether `path` is the prefix itself, where the listing is read.
+    pub fn is_listing(&self, path: &Path) -> bool {
+        *path == self.prefix
+    }
+
+    /// Mark handle `id` as used now and return it.
+    ///
+    /// Expired handles are dropped first, so this is the lookup to use
+    /// when serving a request for a handle.
+    pub fn touch(&mut self, id: u64) -> Option<&mut T> {
+        self.sweep();
+        let entry = self.entries.get_mut(&id)?;
+        entry.last_used = Instant::now();
+        Some(&mut entry.value)
+    }
+
+    /// The handle with `id`, without marking it used.
+    pub fn get(&self, id: u64) -> Option<&T> {
+        self.entries.get(&id).map(|entry| &entry.value)
+    }
+
+    /// Mutable access to the handle with `id`, without marking it used.
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut T> {
+        self.entries.get_mut(&id).map(|entry| &mut entry.value)
+    }
+
+    /// The owner of the lease handle `id` was opened under, while the
+    /// lease is held.
+    pub fn owner(&self, id: u64) -> Option<String> {
+        self.entries.get(&id)?.lease.as_ref()?.owner()
+    }
+
+    /// Whether a handle with `id` exists.
+    pub fn contains(&self, id: u64) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    /// Remove and return handle `id`.
+    pub fn remove(&mut self, id: u64) -> Option<T> {
+        self.entries.remove(&id).map(|entry| entry.value)
+    }
+
+    /// Ids of the open handles, in order.
+    pub fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries.keys().copied()
+    }
+
+    /// The open handles, in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.entries.iter().map(|(id, entry)| (*id, &entry.value))
+    }
+
+    /// Number of open handles.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no open handles.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop handles unused for longer than the TTL and handles whose lease
+    /// was released, returning them.
+    pub fn sweep(&mut self) -> Vec<(u64, T)> {
+        let ttl = self.ttl;
+        let expired: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                ttl.is_some_and(|ttl| entry.last_used.elapsed() >= ttl)
+                    || entry.lease.as_ref().is_some_and(|lease| !lease.is_alive())
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.remove(id).map(|value| (id, value)))
+            .collect()
+    }
+
+    /// References to every open handle under `base`, typed `type_name`.
+    ///
+    /// The listing uses the broker's own prefix and type; this is for
+    /// parallel trees such as `meta/{prefix}/{id}`.
+    pub fn references(&self, base: &str, type_name: &str) -> Vec<Value> {
+        self.ids()
+            .map(|id| Reference::with_type(format!("{}/{}", base, id), type_name).to_value())
+            .collect()
+    }
+
+    /// `{items: [references]}` for every open handle, after dropping
+    /// expired ones.
+    pub fn listing(&mut self) -> Value {
+        self.sweep();
+        Value::Map(btree! {
+            "items".into() => Value::Array(self.references(&self.prefix.to_string(), &self.type_name)),
+        })
+    }
+
+    /// Bookkeeping for handle `id`: `{id, age_ms, idle_ms, expires_in_ms,
+    /// owner}`.
+    ///
+    /// `expires_in_ms` is `null` when handles do not expire, and `owner` is
+    /// the lease's owner, or `null` for a handle opened outside a lease.
+    pub fn status(&self, id: u64) -> Option<Value> {
+        let entry = self.entries.get(&id)?;
+        let idle = entry.last_used.elapsed();
+        let expires_in = self.ttl.map_or(Value::Null, |ttl| {
+            Value::Integer(ttl.saturating_sub(idle).as_millis() as i64)
+        });
+        Some(Value::Map(btree! {
+            "id".into() => Value::Integer(id as i64),
+            "age_ms".into() => Value::Integer(entry.created.elapsed().as_millis() as i64),
+            "idle_ms".into() => Value::Integer(idle.as_millis() as i64),
+            "expires_in_ms".into() => expires_in,
+            "owner".into() => entry
+                .lease
+                .as_ref()
+                .and_then(WeakLease::owner)
+                .map_or(Value::Null, Value::String),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lease::Lease;
+    use crate::path;
+    use std::thread;
+
+    #[test]
+    fn ids_are_sequential_and_not_reused() {
+        let mut handles = HandleBroker::new("outstanding", "request-handle");
+        assert_eq!(handles.insert('a'), 0);
+        assert_eq!(handles.insert('b'), 1);
+        assert_eq!(handles.remove(1), Some('b'));
+        assert_eq!(handles.insert('c'), 2);
+        assert_eq!(handles.ids().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(handles.len(), 2);
+    }
+
+    #[test]
+    fn restored_ids_are_not_reallocated() {
+        let mut handles = HandleBroker::new("outstanding", "request-handle");
+        handles.restore(4, 'a');
+        assert_eq!(handles.next_id(), 5);
+        handles.reserve_below(9);
+        assert_eq!(handles.insert('b'), 9);
+        handles.reserve_below(2);
+        assert_eq!(handles.insert('c'), 10);
+        assert_eq!(handles.get(4), Some(&'a'));
+    }
+
+    #[test]
+    fn parses_handle_paths() {
+        let handles = HandleBroker::<()>::new("outstanding", "request-handle");
+        assert_eq!(
+            handles.parse(&path!("outstanding/3/response/status")),
+            Some((3, path!("response/status")))
+        );
+        assert_eq!(handles.parse(&path!("outstanding/3")), Some((3, path!(""))));
+        assert_eq!(handles.parse(&path!("outstanding")), None);
+        assert_eq!(handles.parse(&path!("outstanding/x")), None);
+        assert_eq!(handles.parse(&path!("other/3")), None);
+        assert!(handles.is_listing(&path!("outstanding")));
+    }
+
+    #[test]
+    fn listing_and_status() {
+        let mut handles = HandleBroker::new("handles", "handle");
+        let id = handles.insert(());
+        assert_eq!(
+            handles.listing(),
+            Value::Map(btree! {
+                "items".into() => Value::Array(vec![
+                    Reference::with_type("handles/0", "handle").to_value(),
+                ]),
+            })
+        );
+        let Some(Value::Map(status)) = handles.status(id) else {
+            panic!("expected a status map");
+        };
+        assert_eq!(status["id"], Value::Integer(0));
+        assert_eq!(status["expires_in_ms"], Value::Null);
+        assert!(handles.status(7).is_none());
+    }
+
+    #[test]
+    fn unused_handles_expire() {
+        let mut handles =
+            HandleBroker::new("handles", "handle").with_ttl(Duration::from_millis(30));
+        let idle = handles.insert("idle");
+        let busy = handles.insert("busy");
+        for _ in 0..4 {
+            thread::sleep(Duration::from_millis(10));
+            assert!(handles.touch(busy).is_some());
+        }
+        assert!(handles.touch(idle).is_none());
+        assert!(handles.contains(busy));
+        assert_eq!(handles.get(busy), Some(&"busy"));
+    }
+
+    #[test]
+    fn handles_close_with_their_lease() {
+        let mut handles = HandleBroker::new("handles", "handle");
+        let lease = Lease::new("block-1");
+        let leased = OpContext::new()
+            .with_lease(&lease)
+            .scope(|| handles.insert("leased"));
+        let free = handles.insert("free");
+        assert_eq!(handles.owner(leased).as_deref(), Some("block-1"));
+        let Some(Value::Map(status)) = handles.status(leased) else {
+            panic!("expected a status map");
+        };
+        assert_eq!(status["owner"], Value::from("block-1"));
+
+        drop(lease);
+        assert_eq!(handles.sweep(), [(leased, "leased")]);
+        assert!(handles.contains(free));
+        assert_eq!(handles.owner(free), None);
+    }
+}
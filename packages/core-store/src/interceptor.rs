@@ -0,0 +1,260 @@
+//! Hooks around every operation on an [`OverlayStore`](crate::overlay_store::OverlayStore).
+//!
+//! An [`Interceptor`] sees each read and write before it is routed and its
+//! result afterwards. Before hooks can rewrite the path (and a write's
+//! record) or veto the operation by returning an error; after hooks can
+//! replace the result, for example to annotate a record or to turn an error
+//! into a fallback value. Policy checks, metrics and tracing can all be
+//! built this way without wrapping each mounted store.
+//!
+//! Interceptors nest like layers: before hooks run in the order the
+//! interceptors were added, after hooks in the reverse order. An interceptor
+//! whose before hook ran always gets its after hook, even if a later
+//! interceptor vetoed the operation; one that vetoes does not.
+
+use crate::{Error, Path, Record};
+
+/// Hooks run around each operation on an overlay.
+///
+/// Every hook does nothing by default, so an interceptor implements only
+/// the ones it needs.
+pub trait Interceptor: Send + Sync {
+    /// Called before a read is routed. May rewrite `path`, or return an
+    /// error to refuse the read.
+    fn before_read(&mut self, path: &mut Path) -> Result<(), Error> {
+        let _ = path;
+        Ok(())
+    }
+
+    /// Called with the result of a read of `path`, the path as this
+    /// interceptor passed it on. May replace the result.
+    fn after_read(&mut self, path: &Path, result: &mut Result<Option<Record>, Error>) {
+        let _ = (path, result);
+    }
+
+    /// Called before a write is routed. May rewrite `path` or `data`, or
+    /// return an error to refuse the write.
+    fn before_write(&mut self, path: &mut Path, data: &mut Record) -> Result<(), Error> {
+        let _ = (path, data);
+        Ok(())
+    }
+
+    /// Called with the result of a write to `path`, the path as this
+    /// interceptor passed it on. May replace the result.
+    fn after_write(&mut self, path: &Path, result: &mut Result<Path, Error>) {
+        let _ = (path, result);
+    }
+}
+
+/// Run `read` on `from` through `interceptors`.
+pub(crate) fn intercept_read(
+    interceptors: &mut [Box<dyn Interceptor>],
+    from: &Path,
+    read: impl FnOnce(&Path) -> Result<Option<Record>, Error>,
+) -> Result<Option<Record>, Error> {
+    let mut path = from.clone();
+    // The path each interceptor passed on, for its after hook
+    let mut passed = Vec::with_capacity(interceptors.len());
+    let mut vetoed = None;
+    for interceptor in interceptors.iter_mut() {
+        if let Err(e) = interceptor.before_read(&mut path) {
+            vetoed = Some(e);
+            break;
+        }
+        passed.push(path.clone());
+    }
+
+    let mut result = match vetoed {
+        Some(e) => Err(e),
+        None => read(&path),
+    };
+    for (interceptor, path) in interceptors[..passed.len()].iter_mut().zip(&passed).rev() {
+        interceptor.after_read(path, &mut result);
+    }
+    result
+}
+
+/// Run `write` of `data` to `to` through `interceptors`.
+pub(crate) fn intercept_write(
+    interceptors: &mut [Box<dyn Interceptor>],
+    to: &Path,
+    mut data: Record,
+    write: impl FnOnce(&Path, Record) -> Result<Path, Error>,
+) -> Result<Path, Error> {
+    let mut path = to.clone();
+    let mut passed = Vec::with_capacity(interceptors.len());
+    let mut vetoed = None;
+    for interceptor in interceptors.iter_mut() {
+        if let Err(e) = interceptor.before_write(&mut path, &mut data) {
+            vetoed = Some(e);
+            break;
+        }
+        passed.push(path.clone());
+    }
+
+    let mut result = match vetoed {
+        Some(e) => Err(e),
+        None => write(&path, data),
+    };
+    for (interceptor, path) in interceptors[..passed.len()].iter_mut().zip(&passed).rev() {
+        interceptor.after_write(path, &mut result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overlay_store::OverlayStore;
+    use crate::{path, NoCodec, Reader, Value, Writer};
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MapStore(BTreeMap<Path, Value>);
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.get(from).cloned().map(Record::parsed))
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data.into_value(&NoCodec)?);
+            Ok(to.clone())
+        }
+    }
+
+    /// Logs each hook, renames `old` to `new`, refuses `secret`, and wraps
+    /// read values in `{"via": name, "value": ..}`.
+    struct Logger {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Interceptor for Logger {
+        fn before_read(&mut self, path: &mut Path) -> Result<(), Error> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} before {}", self.name, path));
+            if path.components.first().is_some_and(|c| c == "secret") {
+                return Err(Error::store("logger", "read", "Refused"));
+            }
+            if path.components.first().is_some_and(|c| c == "old") {
+                path.components[0] = "new".to_string();
+            }
+            Ok(())
+        }
+
+        fn after_read(&mut self, path: &Path, result: &mut Result<Option<Record>, Error>) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} after {}", self.name, path));
+            if let Ok(Some(record)) = result {
+                let value = record.as_value().cloned().unwrap_or(Value::Null);
+                let mut map = BTreeMap::new();
+                map.insert("via".to_string(), Value::from(self.name));
+                map.insert("value".to_string(), value);
+                *record = Record::parsed(Value::Map(map));
+            }
+        }
+
+        fn before_write(&mut self, _path: &mut Path, data: &mut Record) -> Result<(), Error> {
+            if data.as_value() == Some(&Value::Null) {
+                return Err(Error::store("logger", "write", "Refused"));
+            }
+            Ok(())
+        }
+    }
+
+    fn overlay(log: &Arc<Mutex<Vec<String>>>) -> OverlayStore {
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!("new"), MapStore::default());
+        overlay
+            .write(&path!("new/x"), Record::parsed(Value::Integer(2)))
+            .unwrap();
+        for name in ["outer", "inner"] {
+            overlay.add_interceptor(Box::new(Logger {
+                name,
+                log: log.clone(),
+            }));
+        }
+        overlay
+    }
+
+    #[test]
+    fn hooks_nest_and_rewrite_paths() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut overlay = overlay(&log);
+
+        let value = overlay
+            .read(&path!("old/x"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        // The inner interceptor wraps first, the outer one last
+        let Value::Map(outer) = value else {
+            panic!("expected a map, got {:?}", value);
+        };
+        assert_eq!(outer["via"], Value::from("outer"));
+        let Value::Map(inner) = &outer["value"] else {
+            panic!("expected a nested map");
+        };
+        assert_eq!(inner["via"], Value::from("inner"));
+        assert_eq!(inner["value"], Value::Integer(2));
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "outer before old/x",
+                "inner before new/x",
+                "inner after new/x",
+                "outer after new/x",
+            ]
+        );
+    }
+
+    #[test]
+    fn deletes_are_intercepted_as_null_writes() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut overlay = overlay(&log);
+
+        let result = crate::Deleter::delete(&mut overlay, &path!("new/x"));
+        assert!(result.is_err());
+        assert!(overlay.read(&path!("new/x")).unwrap().is_some());
+    }
+
+    #[test]
+    fn listings_are_intercepted_as_reads() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut overlay = overlay(&log);
+
+        assert!(crate::Lister::list(&mut overlay, &path!("secret")).is_err());
+        assert_eq!(
+            crate::Lister::list(&mut overlay, &path!("old/x")).unwrap(),
+            Some(vec![])
+        );
+        assert!(log
+            .lock()
+            .unwrap()
+            .contains(&"inner before new/x".to_string()));
+    }
+
+    #[test]
+    fn vetoes_skip_the_store_and_later_hooks() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut overlay = overlay(&log);
+
+        assert!(overlay.read(&path!("secret/x")).is_err());
+        assert_eq!(*log.lock().unwrap(), ["outer before secret/x"]);
+
+        assert!(overlay
+            .write(&path!("new/x"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(overlay.read(&path!("new/x")).unwrap().is_some());
+    }
+}
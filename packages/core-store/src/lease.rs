@@ -0,0 +1,88 @@
+//! Tying handles to the session or Block that opened them.
+//!
+//! A runtime creates a [`Lease`] for each session or Block it runs and runs
+//! the owner's operations inside `OpContext::new().with_lease(&lease).scope(..)`.
+//! A [`HandleBroker`](crate::HandleBroker) records the current lease with
+//! every handle it hands out, holding it only weakly. Once the runtime drops
+//! the lease, because the owner exited or crashed, the broker closes that
+//! owner's handles the next time it is changed or listed, the same way it
+//! drops handles whose TTL ran out. Handles opened outside any lease are
+//! kept until they are removed or expire.
+
+use std::fmt;
+use std::sync::{Arc, Weak};
+
+/// Proof that an owner of handles is still alive.
+///
+/// Dropping the lease, or calling [`release`](Self::release), releases every
+/// handle opened under it. A lease cannot be cloned, so exactly one place,
+/// normally the runtime, decides when the owner is gone.
+pub struct Lease(Arc<String>);
+
+impl Lease {
+    /// A lease for `owner`, such as a session id or Block name.
+    pub fn new(owner: impl Into<String>) -> Self {
+        Self(Arc::new(owner.into()))
+    }
+
+    /// The owner named when the lease was created.
+    pub fn owner(&self) -> &str {
+        &self.0
+    }
+
+    /// A weak reference that does not keep the lease alive.
+    pub fn downgrade(&self) -> WeakLease {
+        WeakLease(Arc::downgrade(&self.0))
+    }
+
+    /// Release the lease now rather than when it is dropped.
+    pub fn release(self) {}
+}
+
+impl fmt::Debug for Lease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Lease").field(&self.owner()).finish()
+    }
+}
+
+/// A reference to a [`Lease`] that does not keep it alive.
+#[derive(Clone)]
+pub struct WeakLease(Weak<String>);
+
+impl WeakLease {
+    /// Whether the lease is still held.
+    pub fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+
+    /// The lease's owner, or `None` once it has been released.
+    pub fn owner(&self) -> Option<String> {
+        self.0.upgrade().map(|owner| owner.to_string())
+    }
+}
+
+impl fmt::Debug for WeakLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.owner() {
+            Some(owner) => f.debug_tuple("WeakLease").field(&owner).finish(),
+            None => f.write_str("WeakLease(released)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_leases_see_the_release() {
+        let lease = Lease::new("block-1");
+        let weak = lease.downgrade();
+        assert!(weak.is_alive());
+        assert_eq!(weak.owner().as_deref(), Some("block-1"));
+
+        lease.release();
+        assert!(!weak.is_alive());
+        assert_eq!(weak.owner(), None);
+    }
+}
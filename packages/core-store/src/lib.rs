@@ -24,28 +24,66 @@
 pub use bytes::Bytes;
 
 mod bridge;
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
+mod context;
+mod dead_letter;
+pub mod describe;
+pub mod dump;
 mod error;
 mod format;
+mod handle_broker;
+pub mod interceptor;
 mod lazy_record;
+pub mod lease;
+pub mod limits;
 pub mod mount_store;
+mod namespaced;
+mod outcome;
 pub mod overlay_store;
 mod path;
+pub mod path_pattern;
 pub mod path_trie;
+pub mod query;
 mod record;
 mod reference;
+mod session;
+pub mod spillover;
+pub mod stats;
+pub mod sync_engine;
 mod traits;
+pub mod transaction;
+pub mod trash;
 mod value;
+pub mod watch;
 
 pub use bridge::{CoreToLL, LLToCore};
+pub use context::{CancellationToken, OpContext};
+pub use dead_letter::DeadLetterStore;
+pub use describe::SelfDescribing;
 pub use error::{CodecOperation, Error};
 pub use format::Format;
+pub use handle_broker::HandleBroker;
+pub use interceptor::Interceptor;
 pub use lazy_record::LazyRecord;
+pub use lease::{Lease, WeakLease};
+pub use limits::{Limit, LimitedCodec, RecordLimits};
+pub use namespaced::NamespacedStore;
+pub use outcome::ReadOutcome;
 pub use path::{Path, PathError};
+pub use path_pattern::PathPattern;
 pub use path_trie::PathTrie;
+pub use query::{Filter, Querier};
 pub use record::Record;
 pub use reference::{Reference, TypeDescriptor, TypeInfo};
-pub use traits::{Codec, NoCodec, Reader, Store, Writer};
+pub use session::SessionStore;
+pub use spillover::SpilloverStore;
+pub use sync_engine::{SyncEngine, SyncStore};
+pub use traits::{Codec, Deleter, Lister, NoCodec, Reader, Store, Writer};
+pub use transaction::{StagedOp, Transaction, Transactor};
+pub use trash::TrashStore;
 pub use value::Value;
+pub use watch::{Change, PollWatcher, Subscription, Watcher, Watchers};
 
 // Re-export LL types for convenience
 pub use structfs_ll_store::{LLError, LLPath, LLReader, LLStore, LLWriter};
@@ -57,12 +95,18 @@ mod async_traits;
 #[cfg(feature = "async")]
 mod async_bridge;
 
+#[cfg(feature = "async")]
+pub mod async_watch;
+
 #[cfg(feature = "async")]
 pub use async_traits::{AsyncReader, AsyncStore, AsyncWriter, SyncToAsync};
 
 #[cfg(feature = "async")]
 pub use async_bridge::{AsyncCoreToLL, AsyncLLToCore};
 
+#[cfg(feature = "async")]
+pub use async_watch::{Debounce, StreamWatchExt, Watch};
+
 // Re-export async LL types when async feature is enabled
 #[cfg(feature = "async")]
 pub use structfs_ll_store::{AsyncLLReader, AsyncLLStore, AsyncLLWriter, SyncToAsyncLL};
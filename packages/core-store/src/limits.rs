@@ -0,0 +1,422 @@
+//! Size and shape limits for untrusted records.
+//!
+//! Payloads arriving from the network or from guest code can be arbitrarily
+//! large or deeply nested. `RecordLimits` bounds them when a record is turned
+//! into a `Value`, either through [`Record::into_value_limited`] or by wrapping
+//! a codec in [`LimitedCodec`]. HTTP stores apply them to response bodies,
+//! and Featherweight to guest writes, with [`RecordLimits::untrusted`] unless
+//! configured otherwise.
+//!
+//! [`Record::into_value_limited`]: crate::Record::into_value_limited
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{Codec, Error, Format, Value};
+
+/// Which limit a record exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Encoded size, or approximate size for parsed values.
+    Bytes,
+    /// Number of keys in a single map.
+    MapKeys,
+    /// Nesting depth of maps and arrays.
+    Depth,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Bytes => write!(f, "bytes"),
+            Limit::MapKeys => write!(f, "map keys"),
+            Limit::Depth => write!(f, "nesting depth"),
+        }
+    }
+}
+
+/// Limits on records decoded from untrusted sources.
+///
+/// Every limit is optional; the default allows anything.
+///
+/// Appears under `options.limits` in an HTTP mount config; fields left out
+/// are unlimited:
+/// ```json
+/// {"type": "http", "url": "https://api.example.com",
+///  "options": {"limits": {"max_bytes": 1048576, "max_depth": 32}}}
+/// ```
+///
+/// ```rust
+/// use structfs_core_store::{limits::RecordLimits, Value};
+///
+/// let limits = RecordLimits {
+///     max_depth: Some(2),
+///     ..Default::default()
+/// };
+/// let nested = Value::Array(vec![Value::Array(vec![Value::Array(vec![])])]);
+/// assert!(limits.check(&nested).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordLimits {
+    /// Maximum size in bytes. Raw records are measured before decoding;
+    /// parsed values by the approximate size of their contents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    /// Maximum number of keys in any one map.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_map_keys: Option<usize>,
+    /// Maximum nesting depth. Scalars have depth 0; each enclosing map or
+    /// array adds one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+}
+
+impl RecordLimits {
+    /// Limits that allow anything.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Limits for sources nothing is known about: 16 MiB, 65,536 keys per
+    /// map and 128 levels of nesting.
+    ///
+    /// Generous enough for ordinary API responses and guest state, while a
+    /// hostile payload cannot exhaust memory or the stack.
+    pub fn untrusted() -> Self {
+        Self {
+            max_bytes: Some(16 * 1024 * 1024),
+            max_map_keys: Some(65_536),
+            max_depth: Some(128),
+        }
+    }
+
+    /// Convert to a Value map, omitting unset limits.
+    pub fn to_value(&self) -> Value {
+        let map = [
+            ("max_bytes", self.max_bytes),
+            ("max_map_keys", self.max_map_keys),
+            ("max_depth", self.max_depth),
+        ]
+        .into_iter()
+        .filter_map(|(key, max)| Some((key.to_string(), Value::Integer(max? as i64))))
+        .collect::<BTreeMap<_, _>>();
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map. Missing limits are unset.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(Error::decode(Format::VALUE, "record limits must be a map")),
+        };
+
+        let mut limits = Self::default();
+        for (key, field) in [
+            ("max_bytes", &mut limits.max_bytes),
+            ("max_map_keys", &mut limits.max_map_keys),
+            ("max_depth", &mut limits.max_depth),
+        ] {
+            match map.get(key) {
+                None | Some(Value::Null) => {}
+                Some(Value::Integer(i)) if *i >= 0 => *field = Some(*i as usize),
+                Some(_) => {
+                    return Err(Error::decode(
+                        Format::VALUE,
+                        format!("record limit '{}' must be a non-negative integer", key),
+                    ))
+                }
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Check the size of an encoded payload.
+    pub fn check_bytes(&self, len: usize) -> Result<(), Error> {
+        exceeds(Limit::Bytes, self.max_bytes, len)
+    }
+
+    /// Check a parsed value against every limit.
+    pub fn check(&self, value: &Value) -> Result<(), Error> {
+        self.walk(value, self.max_bytes.is_some())
+    }
+
+    /// Check the shape of a value, leaving size to [`check_bytes`](Self::check_bytes).
+    pub(crate) fn check_shape(&self, value: &Value) -> Result<(), Error> {
+        self.walk(value, false)
+    }
+
+    /// Iterative walk, so hostile nesting cannot overflow the stack.
+    fn walk(&self, value: &Value, count_bytes: bool) -> Result<(), Error> {
+        let mut stack = vec![(value, 0usize)];
+        let mut bytes = 0usize;
+
+        while let Some((value, depth)) = stack.pop() {
+            match value {
+                Value::Map(map) => {
+                    exceeds(Limit::Depth, self.max_depth, depth + 1)?;
+                    exceeds(Limit::MapKeys, self.max_map_keys, map.len())?;
+                    for (key, child) in map {
+                        bytes += key.len();
+                        stack.push((child, depth + 1));
+                    }
+                }
+                Value::Array(arr) => {
+                    exceeds(Limit::Depth, self.max_depth, depth + 1)?;
+                    stack.extend(arr.iter().map(|child| (child, depth + 1)));
+                }
+                Value::String(s) => bytes += s.len(),
+                Value::Bytes(b) => bytes += b.len(),
+                Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Float(_) => bytes += 8,
+            }
+
+            if count_bytes {
+                exceeds(Limit::Bytes, self.max_bytes, bytes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn exceeds(limit: Limit, max: Option<usize>, actual: usize) -> Result<(), Error> {
+    match max {
+        Some(max) if actual > max => Err(Error::LimitExceeded { limit, max, actual }),
+        _ => Ok(()),
+    }
+}
+
+/// A codec that enforces [`RecordLimits`] on everything it decodes.
+///
+/// The byte limit is checked before decoding; map keys and depth after.
+pub struct LimitedCodec<C> {
+    inner: C,
+    limits: RecordLimits,
+}
+
+impl<C: Codec> LimitedCodec<C> {
+    /// Wrap `inner` with `limits`.
+    pub fn new(inner: C, limits: RecordLimits) -> Self {
+        Self { inner, limits }
+    }
+
+    /// The limits this codec enforces.
+    pub fn limits(&self) -> &RecordLimits {
+        &self.limits
+    }
+}
+
+impl<C: Codec> Codec for LimitedCodec<C> {
+    fn decode(&self, bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+        self.limits.check_bytes(bytes.len())?;
+        let value = self.inner.decode(bytes, format)?;
+        self.limits.check_shape(&value)?;
+        Ok(value)
+    }
+
+    fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+        self.inner.encode(value, format)
+    }
+
+    fn supports(&self, format: &Format) -> bool {
+        self.inner.supports(format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoCodec, Record};
+    use std::collections::BTreeMap;
+
+    /// Decodes any bytes into a string value.
+    struct TextCodec;
+
+    impl Codec for TextCodec {
+        fn decode(&self, bytes: &Bytes, _format: &Format) -> Result<Value, Error> {
+            Ok(Value::String(String::from_utf8_lossy(bytes).into_owned()))
+        }
+
+        fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+            match value {
+                Value::String(s) => Ok(Bytes::from(s.clone())),
+                _ => Err(Error::encode(format.clone(), "not a string")),
+            }
+        }
+
+        fn supports(&self, _format: &Format) -> bool {
+            true
+        }
+    }
+
+    fn nested(depth: usize) -> Value {
+        (0..depth).fold(Value::Null, |inner, _| Value::Array(vec![inner]))
+    }
+
+    fn wide_map(keys: usize) -> Value {
+        Value::Map(
+            (0..keys)
+                .map(|i| (format!("k{}", i), Value::Null))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn unlimited_accepts_anything() {
+        let limits = RecordLimits::unlimited();
+        assert!(limits.check(&nested(1000)).is_ok());
+        assert!(limits.check(&wide_map(1000)).is_ok());
+        assert!(limits.check_bytes(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let limits = RecordLimits {
+            max_depth: Some(3),
+            ..Default::default()
+        };
+        assert!(limits.check(&nested(3)).is_ok());
+        let err = limits.check(&nested(4)).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::LimitExceeded {
+                limit: Limit::Depth,
+                max: 3,
+                actual: 4
+            }
+        ));
+    }
+
+    #[test]
+    fn deep_value_does_not_overflow_stack() {
+        let limits = RecordLimits {
+            max_depth: Some(10),
+            ..Default::default()
+        };
+        let value = nested(100_000);
+        assert!(limits.check(&value).is_err());
+        // Avoid a recursive drop of the deep value
+        std::mem::forget(value);
+    }
+
+    #[test]
+    fn map_keys_limit() {
+        let limits = RecordLimits {
+            max_map_keys: Some(2),
+            ..Default::default()
+        };
+        assert!(limits.check(&wide_map(2)).is_ok());
+        let inner = Value::Array(vec![wide_map(3)]);
+        assert!(matches!(
+            limits.check(&inner),
+            Err(Error::LimitExceeded {
+                limit: Limit::MapKeys,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn bytes_limit_on_parsed_value() {
+        let limits = RecordLimits {
+            max_bytes: Some(10),
+            ..Default::default()
+        };
+        assert!(limits.check(&Value::from("short")).is_ok());
+        assert!(limits.check(&Value::from("far too long")).is_err());
+    }
+
+    #[test]
+    fn bytes_limit_checked_before_decode() {
+        let limits = RecordLimits {
+            max_bytes: Some(4),
+            ..Default::default()
+        };
+        let record = Record::raw(Bytes::from_static(b"hello"), Format::JSON);
+        let err = record.into_value_limited(&TextCodec, &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::LimitExceeded {
+                limit: Limit::Bytes,
+                max: 4,
+                actual: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn into_value_limited_parsed() {
+        let limits = RecordLimits {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert!(Record::parsed(nested(1))
+            .into_value_limited(&NoCodec, &limits)
+            .is_ok());
+        assert!(Record::parsed(nested(2))
+            .into_value_limited(&NoCodec, &limits)
+            .is_err());
+    }
+
+    #[test]
+    fn limited_codec() {
+        let codec = LimitedCodec::new(
+            TextCodec,
+            RecordLimits {
+                max_bytes: Some(3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(codec.limits().max_bytes, Some(3));
+        assert!(codec
+            .decode(&Bytes::from_static(b"abc"), &Format::JSON)
+            .is_ok());
+        assert!(codec
+            .decode(&Bytes::from_static(b"abcd"), &Format::JSON)
+            .is_err());
+        // Encoding is not limited
+        assert!(codec.encode(&Value::from("abcd"), &Format::JSON).is_ok());
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let limits = RecordLimits {
+            max_bytes: Some(1024),
+            max_depth: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            RecordLimits::from_value(&limits.to_value()).unwrap(),
+            limits
+        );
+        assert_eq!(
+            RecordLimits::from_value(&Value::Map(BTreeMap::new())).unwrap(),
+            RecordLimits::unlimited()
+        );
+        assert!(RecordLimits::from_value(&Value::from("big")).is_err());
+        let negative = Value::Map(BTreeMap::from([(
+            "max_depth".to_string(),
+            Value::Integer(-1),
+        )]));
+        assert!(RecordLimits::from_value(&negative).is_err());
+    }
+
+    #[test]
+    fn untrusted_rejects_hostile_nesting() {
+        let limits = RecordLimits::untrusted();
+        assert!(limits.check(&nested(128)).is_ok());
+        assert!(limits.check(&nested(129)).is_err());
+    }
+
+    #[test]
+    fn limit_error_display() {
+        let err = Error::LimitExceeded {
+            limit: Limit::MapKeys,
+            max: 2,
+            actual: 3,
+        };
+        assert_eq!(err.to_string(), "record exceeds map keys limit: 3 > 2");
+    }
+}
@@ -3,7 +3,7 @@
 //! This store exposes mount management through the StructFS interface itself:
 //! - Read `/ctx/mounts` to list all mounts
 //! - Write to `/ctx/mounts/<name>` to create a mount at `/<name>`
-//! - Write `null` to `/ctx/mounts/<name>` to unmount
+//! - Write `null` to `/ctx/mounts/<name>`, or delete it, to unmount
 //!
 //! Mount configurations are JSON objects like:
 //! ```json
@@ -12,14 +12,19 @@
 //! {"type": "http", "url": "https://api.example.com"}
 //! {"type": "structfs", "url": "https://structfs.example.com"}
 //! ```
+//!
+//! Any other `type` becomes a [`MountConfig::Plugin`], which the
+//! [`StoreFactory`] may create or reject.
 
 use collection_literals::btree;
 use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
+use url::{Host, Url};
 
 use crate::overlay_store::{OverlayStore, RedirectMode, StoreBox};
-use crate::{path, Error, Path, Reader, Record, Value, Writer};
+use crate::traits::children_of;
+use crate::{path, Deleter, Error, Lister, Path, Reader, Record, RecordLimits, Value, Writer};
 
 /// Configuration for a mount point
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -30,11 +35,21 @@ pub enum MountConfig {
     /// Local filesystem JSON store
     Local { path: String },
     /// HTTP client store (for making HTTP requests to a base URL)
-    Http { url: String },
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "HttpOptions::is_default")]
+        options: HttpOptions,
+    },
     /// HTTP broker store - write HttpRequest, read from handle to execute (sync)
-    HttpBroker,
+    HttpBroker {
+        #[serde(default, skip_serializing_if = "HttpOptions::is_default")]
+        options: HttpOptions,
+    },
     /// Async HTTP broker - executes requests in background threads
-    AsyncHttpBroker,
+    AsyncHttpBroker {
+        #[serde(default, skip_serializing_if = "HttpOptions::is_default")]
+        options: HttpOptions,
+    },
     /// Remote StructFS store over HTTP
     Structfs { url: String },
     /// Help/documentation store (read-only)
@@ -45,6 +60,765 @@ pub enum MountConfig {
     Repl,
     /// Register storage (session-local named values)
     Registers,
+    /// A mount type not built into StructFS, handled by a factory that
+    /// recognizes `kind`. `config` is the full mount config map, including
+    /// its `type` field. Plugin mounts are only configured through `Value`s,
+    /// not serde.
+    #[serde(skip)]
+    Plugin { kind: String, config: Value },
+}
+
+/// Connection tuning for HTTP mounts.
+///
+/// Every field is optional; unset fields keep the HTTP client's defaults.
+/// In a mount config these appear under an `options` map:
+/// ```json
+/// {"type": "http", "url": "https://api.example.com", "options": {"http2_prior_knowledge": true}}
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpOptions {
+    /// Request timeout in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Speak HTTP/2 without ALPN negotiation or an upgrade.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub http2_prior_knowledge: bool,
+    /// Maximum idle connections kept per host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<u64>,
+    /// How long idle pooled connections are kept, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_ms: Option<u64>,
+    /// TCP keep-alive interval in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_ms: Option<u64>,
+    /// Set `TCP_NODELAY` on connections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_nodelay: Option<bool>,
+    /// Sign every request with the given scheme.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+    /// Resolve hostnames with static overrides or DNS-over-HTTPS instead of
+    /// the system resolver.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolver: Option<HttpResolver>,
+    /// Stop sending requests for a while when too many of them fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// File name of a JSON OpenAPI spec to route and check operations
+    /// against.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openapi: Option<String>,
+    /// Root CAs, client certificates and server name checks for HTTPS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<HttpTls>,
+    /// Hosts and schemes requests may be sent to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<HttpNetwork>,
+    /// Limits on response bodies. Unset means
+    /// [`RecordLimits::untrusted`](crate::RecordLimits::untrusted).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<RecordLimits>,
+}
+
+impl HttpOptions {
+    /// True if no option is set.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Convert to a Value map, omitting unset fields.
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        let mut put = |key: &str, value: Option<u64>| {
+            if let Some(v) = value {
+                map.insert(key.to_string(), Value::Integer(v as i64));
+            }
+        };
+        put("timeout_ms", self.timeout_ms);
+        put("pool_max_idle_per_host", self.pool_max_idle_per_host);
+        put("pool_idle_timeout_ms", self.pool_idle_timeout_ms);
+        put("tcp_keepalive_ms", self.tcp_keepalive_ms);
+        if self.http2_prior_knowledge {
+            map.insert("http2_prior_knowledge".to_string(), Value::Bool(true));
+        }
+        if let Some(nodelay) = self.tcp_nodelay {
+            map.insert("tcp_nodelay".to_string(), Value::Bool(nodelay));
+        }
+        if let Some(auth) = &self.auth {
+            map.insert("auth".to_string(), auth.to_value());
+        }
+        if let Some(resolver) = &self.resolver {
+            map.insert("resolver".to_string(), resolver.to_value());
+        }
+        if let Some(breaker) = &self.circuit_breaker {
+            map.insert("circuit_breaker".to_string(), breaker.to_value());
+        }
+        if let Some(openapi) = &self.openapi {
+            map.insert("openapi".to_string(), Value::String(openapi.clone()));
+        }
+        if let Some(tls) = &self.tls {
+            map.insert("tls".to_string(), tls.to_value());
+        }
+        if let Some(network) = &self.network {
+            map.insert("network".to_string(), network.to_value());
+        }
+        if let Some(limits) = &self.limits {
+            map.insert("limits".to_string(), limits.to_value());
+        }
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map. Null or a missing map yields the defaults.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let map = match value {
+            Value::Null => return Ok(Self::default()),
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::decode(
+                    crate::Format::VALUE,
+                    "HTTP options must be a map",
+                ))
+            }
+        };
+
+        let integer = |key: &str| -> Result<Option<u64>, Error> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::Integer(i)) if *i >= 0 => Ok(Some(*i as u64)),
+                Some(_) => Err(Error::decode(
+                    crate::Format::VALUE,
+                    format!("HTTP option '{}' must be a non-negative integer", key),
+                )),
+            }
+        };
+        let boolean = |key: &str| -> Result<Option<bool>, Error> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::Bool(b)) => Ok(Some(*b)),
+                Some(_) => Err(Error::decode(
+                    crate::Format::VALUE,
+                    format!("HTTP option '{}' must be a boolean", key),
+                )),
+            }
+        };
+
+        Ok(Self {
+            timeout_ms: integer("timeout_ms")?,
+            http2_prior_knowledge: boolean("http2_prior_knowledge")?.unwrap_or(false),
+            pool_max_idle_per_host: integer("pool_max_idle_per_host")?,
+            pool_idle_timeout_ms: integer("pool_idle_timeout_ms")?,
+            tcp_keepalive_ms: integer("tcp_keepalive_ms")?,
+            tcp_nodelay: boolean("tcp_nodelay")?,
+            auth: match map.get("auth") {
+                None | Some(Value::Null) => None,
+                Some(auth) => Some(HttpAuth::from_value(auth)?),
+            },
+            resolver: match map.get("resolver") {
+                None | Some(Value::Null) => None,
+                Some(resolver) => Some(HttpResolver::from_value(resolver)?),
+            },
+            circuit_breaker: match map.get("circuit_breaker") {
+                None | Some(Value::Null) => None,
+                Some(breaker) => Some(CircuitBreakerConfig::from_value(breaker)?),
+            },
+            openapi: match map.get("openapi") {
+                None | Some(Value::Null) => None,
+                Some(Value::String(file)) => Some(file.clone()),
+                Some(_) => {
+                    return Err(Error::decode(
+                        crate::Format::VALUE,
+                        "HTTP option 'openapi' must be a file name",
+                    ))
+                }
+            },
+            tls: match map.get("tls") {
+                None | Some(Value::Null) => None,
+                Some(tls) => Some(HttpTls::from_value(tls)?),
+            },
+            network: match map.get("network") {
+                None | Some(Value::Null) => None,
+                Some(network) => Some(HttpNetwork::from_value(network)?),
+            },
+            limits: match map.get("limits") {
+                None | Some(Value::Null) => None,
+                Some(limits) => Some(RecordLimits::from_value(limits)?),
+            },
+        })
+    }
+}
+
+/// TLS settings for HTTP mounts.
+///
+/// Appears under `options.tls` in a mount config. Files are PEM and are read
+/// when the store is created:
+/// ```json
+/// {"type": "http", "url": "https://billing.internal",
+///  "options": {"tls": {"ca_certs": ["certs/internal-ca.pem"],
+///                      "client_cert": "certs/client.pem",
+///                      "client_key": "certs/client.key"}}}
+/// ```
+///
+/// `ca_certs` are trusted in addition to the system roots, or instead of
+/// them with `only_ca_certs`. `client_cert` and `client_key` (a PKCS#8
+/// key) are presented to servers that require mutual TLS. With
+/// `server_name` set, HTTPS requests still connect to the URL's host but
+/// send that name for SNI, check the server's certificate against it and
+/// use it as the `Host` header. `danger_accept_invalid_certs` accepts any
+/// certificate and is meant only for development against self-signed
+/// servers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpTls {
+    /// Extra root CA files, each holding one or more certificates.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ca_certs: Vec<String>,
+    /// Trust only `ca_certs`, not the system roots.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub only_ca_certs: bool,
+    /// Client certificate chain file for mutual TLS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    /// PKCS#8 private key file for `client_cert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+    /// Name to send for SNI and verify the certificate against instead of
+    /// the URL's host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    /// Accept invalid and self-signed certificates. Development only.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl HttpTls {
+    /// Convert to a Value map, omitting unset fields.
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        if !self.ca_certs.is_empty() {
+            let files = self.ca_certs.iter().cloned().map(Value::String).collect();
+            map.insert("ca_certs".to_string(), Value::Array(files));
+        }
+        let mut put = |key: &str, value: &Option<String>| {
+            if let Some(v) = value {
+                map.insert(key.to_string(), Value::String(v.clone()));
+            }
+        };
+        put("client_cert", &self.client_cert);
+        put("client_key", &self.client_key);
+        put("server_name", &self.server_name);
+        for (key, set) in [
+            ("only_ca_certs", self.only_ca_certs),
+            (
+                "danger_accept_invalid_certs",
+                self.danger_accept_invalid_certs,
+            ),
+        ] {
+            if set {
+                map.insert(key.to_string(), Value::Bool(true));
+            }
+        }
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let invalid = |message: String| Error::decode(crate::Format::VALUE, message);
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(invalid("HTTP TLS options must be a map".to_string())),
+        };
+
+        let string = |key: &str| match map.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(invalid(format!("HTTP TLS '{}' must be a string", key))),
+        };
+        let boolean = |key: &str| match map.get(key) {
+            None | Some(Value::Null) => Ok(false),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(_) => Err(invalid(format!("HTTP TLS '{}' must be a boolean", key))),
+        };
+        let ca_certs = match map.get("ca_certs") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(file)) => vec![file.clone()],
+            Some(Value::Array(files)) => files
+                .iter()
+                .map(|file| match file {
+                    Value::String(file) => Ok(file.clone()),
+                    _ => Err(invalid(
+                        "HTTP TLS 'ca_certs' must be file names".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(invalid(
+                    "HTTP TLS 'ca_certs' must be file names".to_string(),
+                ))
+            }
+        };
+
+        let tls = Self {
+            ca_certs,
+            only_ca_certs: boolean("only_ca_certs")?,
+            client_cert: string("client_cert")?,
+            client_key: string("client_key")?,
+            server_name: string("server_name")?,
+            danger_accept_invalid_certs: boolean("danger_accept_invalid_certs")?,
+        };
+        if tls.client_cert.is_some() != tls.client_key.is_some() {
+            return Err(invalid(
+                "HTTP TLS 'client_cert' and 'client_key' must be set together".to_string(),
+            ));
+        }
+        Ok(tls)
+    }
+}
+
+/// Outbound network restrictions for HTTP mounts.
+///
+/// Appears under `options.network` in a mount config:
+/// ```json
+/// {"type": "httpbroker",
+///  "options": {"network": {"allow_hosts": ["api.example.com", "*.internal"],
+///                          "deny_hosts": ["admin.internal"],
+///                          "schemes": ["https"]}}}
+/// ```
+///
+/// A request is sent only if its URL's scheme is in `schemes` (any of
+/// `http` and `https` when empty), its host matches no `deny_hosts` entry,
+/// and it matches an `allow_hosts` entry (any host when empty). An entry is
+/// a host name or IP address, or `*.` followed by a domain to match every
+/// subdomain of it. Hosts are compared as the URL parser reads them, so
+/// `http://0x7f.1/` is checked as `127.0.0.1`. Redirects are checked the
+/// same way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpNetwork {
+    /// Hosts requests may go to. Empty allows every host not denied.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow_hosts: Vec<String>,
+    /// Hosts requests may never go to, even if allowed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deny_hosts: Vec<String>,
+    /// URL schemes requests may use. Empty allows `http` and `https`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schemes: Vec<String>,
+}
+
+impl HttpNetwork {
+    /// Check that a request to `url` is permitted, returning why not.
+    ///
+    /// Returns the URL as parsed for the check. Send the request to that
+    /// URL, not `url`, so the host checked is the host connected to.
+    pub fn check(&self, url: &str) -> Result<Url, String> {
+        let parsed = Url::parse(url).map_err(|e| match e {
+            url::ParseError::RelativeUrlWithoutBase => {
+                format!("network policy: {:?} is not an absolute URL", url)
+            }
+            e => format!("network policy: {:?} is not a valid URL: {}", url, e),
+        })?;
+        self.check_url(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Check that a request to a parsed URL is permitted, returning why not.
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        let scheme = url.scheme();
+        let permitted = if self.schemes.is_empty() {
+            scheme == "http" || scheme == "https"
+        } else {
+            self.schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme))
+        };
+        if !permitted {
+            return Err(format!("network policy: scheme {} is not allowed", scheme));
+        }
+
+        let host = match url.host() {
+            Some(host) => normalize_host(host),
+            None => String::new(),
+        };
+        if host.is_empty() {
+            return Err(format!("network policy: {:?} has no host", url.as_str()));
+        }
+        if self.deny_hosts.iter().any(|p| host_matches(p, &host)) {
+            return Err(format!("network policy: host {} is denied", host));
+        }
+        if !self.allow_hosts.is_empty() && !self.allow_hosts.iter().any(|p| host_matches(p, &host))
+        {
+            return Err(format!("network policy: host {} is not allowed", host));
+        }
+        Ok(())
+    }
+
+    /// Convert to a Value map, omitting empty lists.
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        for (key, list) in [
+            ("allow_hosts", &self.allow_hosts),
+            ("deny_hosts", &self.deny_hosts),
+            ("schemes", &self.schemes),
+        ] {
+            if !list.is_empty() {
+                let items = list.iter().cloned().map(Value::String).collect();
+                map.insert(key.to_string(), Value::Array(items));
+            }
+        }
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let invalid = |message: String| Error::decode(crate::Format::VALUE, message);
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(invalid("HTTP network options must be a map".to_string())),
+        };
+
+        let list = |key: &str| match map.get(key) {
+            None | Some(Value::Null) => Ok(Vec::new()),
+            Some(Value::String(s)) => Ok(vec![s.clone()]),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => Err(invalid(format!("HTTP network '{}' must be strings", key))),
+                })
+                .collect(),
+            Some(_) => Err(invalid(format!("HTTP network '{}' must be strings", key))),
+        };
+
+        Ok(Self {
+            allow_hosts: list("allow_hosts")?,
+            deny_hosts: list("deny_hosts")?,
+            schemes: list("schemes")?,
+        })
+    }
+}
+
+/// A parsed host as compared against policy entries: domains lowercased
+/// without a trailing dot, IP addresses in canonical form without brackets.
+fn normalize_host(host: Host<&str>) -> String {
+    match host {
+        Host::Domain(domain) => domain.trim_end_matches('.').to_ascii_lowercase(),
+        Host::Ipv4(addr) => addr.to_string(),
+        Host::Ipv6(addr) => addr.to_string(),
+    }
+}
+
+/// A policy entry normalized the way URL hosts are, so `0x7f.1` or `[::1]`
+/// match the addresses they name.
+fn normalize_pattern(pattern: &str) -> String {
+    let pattern = pattern.trim_end_matches('.');
+    match Host::parse(pattern) {
+        Ok(host) => normalize_host(match &host {
+            Host::Domain(domain) => Host::Domain(domain.as_str()),
+            Host::Ipv4(addr) => Host::Ipv4(*addr),
+            Host::Ipv6(addr) => Host::Ipv6(*addr),
+        }),
+        Err(_) => pattern.to_ascii_lowercase(),
+    }
+}
+
+/// Whether `host` matches a host pattern: the host itself, or `*.domain`
+/// for any subdomain of `domain`.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            let domain = normalize_pattern(domain);
+            host.strip_suffix(domain.as_str())
+                .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.'))
+        }
+        None => normalize_pattern(pattern) == host,
+    }
+}
+
+/// Circuit breaker settings for HTTP mounts.
+///
+/// Appears under `options.circuit_breaker` in a mount config. Every field
+/// has a default, so an empty map enables the breaker:
+/// ```json
+/// {"type": "http", "url": "https://api.example.com",
+///  "options": {"circuit_breaker": {"failure_percent": 50, "cooldown_ms": 10000}}}
+/// ```
+///
+/// The breaker opens when at least `failure_percent` of the last `window`
+/// requests failed, once `min_requests` have been made. While open, requests
+/// fail immediately. After `cooldown_ms` it lets requests through again, and
+/// closes once `half_open_requests` of them in a row succeed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Percentage of failed requests in the window that opens the breaker.
+    pub failure_percent: u64,
+    /// Requests needed in the window before the breaker can open.
+    pub min_requests: u64,
+    /// Number of recent requests considered.
+    pub window: u64,
+    /// How long the breaker stays open, in milliseconds.
+    pub cooldown_ms: u64,
+    /// Consecutive successes needed to close the breaker after the cooldown.
+    pub half_open_requests: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_percent: 50,
+            min_requests: 10,
+            window: 20,
+            cooldown_ms: 30_000,
+            half_open_requests: 1,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Convert to a Value map.
+    pub fn to_value(&self) -> Value {
+        Value::Map(btree! {
+            "failure_percent".to_string() => Value::Integer(self.failure_percent as i64),
+            "min_requests".to_string() => Value::Integer(self.min_requests as i64),
+            "window".to_string() => Value::Integer(self.window as i64),
+            "cooldown_ms".to_string() => Value::Integer(self.cooldown_ms as i64),
+            "half_open_requests".to_string() => Value::Integer(self.half_open_requests as i64),
+        })
+    }
+
+    /// Parse from a Value map. Missing fields keep their defaults.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::decode(
+                    crate::Format::VALUE,
+                    "circuit breaker config must be a map",
+                ))
+            }
+        };
+
+        let mut config = Self::default();
+        for (key, field) in [
+            ("failure_percent", &mut config.failure_percent),
+            ("min_requests", &mut config.min_requests),
+            ("window", &mut config.window),
+            ("cooldown_ms", &mut config.cooldown_ms),
+            ("half_open_requests", &mut config.half_open_requests),
+        ] {
+            match map.get(key) {
+                None | Some(Value::Null) => {}
+                Some(Value::Integer(i)) if *i >= 0 => *field = *i as u64,
+                Some(_) => {
+                    return Err(Error::decode(
+                        crate::Format::VALUE,
+                        format!("circuit breaker '{}' must be a non-negative integer", key),
+                    ))
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Hostname resolution for HTTP mounts.
+///
+/// Appears under `options.resolver` in a mount config:
+/// ```json
+/// {"type": "http", "url": "https://api.internal",
+///  "options": {"resolver": {"hosts": {"api.internal": ["10.0.0.5"]},
+///                           "doh_url": "https://1.1.1.1/dns-query"}}}
+/// ```
+///
+/// Hosts listed in `hosts` resolve to the given addresses, each an IP
+/// address or `ip:port`. Other hosts are looked up through the DNS-over-HTTPS
+/// endpoint if `doh_url` is set, and through the system resolver otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpResolver {
+    /// Fixed addresses by hostname.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, Vec<String>>,
+    /// DNS-over-HTTPS endpoint speaking the JSON API (`application/dns-json`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doh_url: Option<String>,
+}
+
+impl HttpResolver {
+    /// Convert to a Value map, omitting unset fields.
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        if !self.hosts.is_empty() {
+            let hosts = self
+                .hosts
+                .iter()
+                .map(|(host, addrs)| {
+                    let addrs = addrs.iter().cloned().map(Value::String).collect();
+                    (host.clone(), Value::Array(addrs))
+                })
+                .collect();
+            map.insert("hosts".to_string(), Value::Map(hosts));
+        }
+        if let Some(url) = &self.doh_url {
+            map.insert("doh_url".to_string(), Value::String(url.clone()));
+        }
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let invalid = |message: &str| Error::decode(crate::Format::VALUE, message);
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(invalid("HTTP resolver must be a map")),
+        };
+
+        let mut hosts = BTreeMap::new();
+        match map.get("hosts") {
+            None | Some(Value::Null) => {}
+            Some(Value::Map(entries)) => {
+                for (host, addrs) in entries {
+                    let addrs = match addrs {
+                        Value::String(addr) => vec![addr.clone()],
+                        Value::Array(addrs) => addrs
+                            .iter()
+                            .map(|addr| match addr {
+                                Value::String(addr) => Ok(addr.clone()),
+                                _ => Err(invalid("HTTP resolver addresses must be strings")),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        _ => return Err(invalid("HTTP resolver addresses must be strings")),
+                    };
+                    hosts.insert(host.clone(), addrs);
+                }
+            }
+            Some(_) => return Err(invalid("HTTP resolver 'hosts' must be a map")),
+        }
+
+        let doh_url = match map.get("doh_url") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(url)) => Some(url.clone()),
+            Some(_) => return Err(invalid("HTTP resolver 'doh_url' must be a string")),
+        };
+
+        Ok(Self { hosts, doh_url })
+    }
+}
+
+/// Request signing scheme for HTTP mounts.
+///
+/// Appears under `options.auth` in a mount config:
+/// ```json
+/// {"type": "http", "url": "https://s3.us-east-1.amazonaws.com",
+///  "options": {"auth": {"type": "sigv4", "region": "us-east-1", "service": "s3"}}}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HttpAuth {
+    /// AWS Signature Version 4.
+    ///
+    /// Credentials left unset are read from `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN` when the store is created.
+    Sigv4 {
+        region: String,
+        service: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        access_key_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        secret_access_key: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_token: Option<String>,
+    },
+    /// HMAC-SHA256 over the method, URL, timestamp and body with a shared secret.
+    Hmac {
+        secret: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_id: Option<String>,
+    },
+}
+
+impl HttpAuth {
+    /// Convert to a Value map, omitting unset fields.
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        let mut put = |key: &str, value: Option<&String>| {
+            if let Some(v) = value {
+                map.insert(key.to_string(), Value::String(v.clone()));
+            }
+        };
+        match self {
+            HttpAuth::Sigv4 {
+                region,
+                service,
+                access_key_id,
+                secret_access_key,
+                session_token,
+            } => {
+                put("type", Some(&"sigv4".to_string()));
+                put("region", Some(region));
+                put("service", Some(service));
+                put("access_key_id", access_key_id.as_ref());
+                put("secret_access_key", secret_access_key.as_ref());
+                put("session_token", session_token.as_ref());
+            }
+            HttpAuth::Hmac { secret, key_id } => {
+                put("type", Some(&"hmac".to_string()));
+                put("secret", Some(secret));
+                put("key_id", key_id.as_ref());
+            }
+        }
+        Value::Map(map)
+    }
+
+    /// Parse from a Value map with a `type` field.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::decode(
+                    crate::Format::VALUE,
+                    "HTTP auth must be a map",
+                ))
+            }
+        };
+
+        let optional = |key: &str| -> Result<Option<String>, Error> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(Error::decode(
+                    crate::Format::VALUE,
+                    format!("HTTP auth field '{}' must be a string", key),
+                )),
+            }
+        };
+        let required = |key: &str| -> Result<String, Error> {
+            optional(key)?.ok_or_else(|| {
+                Error::decode(
+                    crate::Format::VALUE,
+                    format!("HTTP auth requires a '{}' field", key),
+                )
+            })
+        };
+
+        match required("type")?.as_str() {
+            "sigv4" => Ok(HttpAuth::Sigv4 {
+                region: required("region")?,
+                service: required("service")?,
+                access_key_id: optional("access_key_id")?,
+                secret_access_key: optional("secret_access_key")?,
+                session_token: optional("session_token")?,
+            }),
+            "hmac" => Ok(HttpAuth::Hmac {
+                secret: required("secret")?,
+                key_id: optional("key_id")?,
+            }),
+            other => Err(Error::decode(
+                crate::Format::VALUE,
+                format!("Unknown HTTP auth type: {}", other),
+            )),
+        }
+    }
 }
 
 /// Information about a mount point
@@ -229,16 +1003,25 @@ fn config_to_value(config: &MountConfig) -> Value {
             "type".to_string() => Value::String("local".to_string()),
             "path".to_string() => Value::String(path.clone()),
         },
-        MountConfig::Http { url } => btree! {
-            "type".to_string() => Value::String("http".to_string()),
-            "url".to_string() => Value::String(url.clone()),
-        },
-        MountConfig::HttpBroker => btree! {
-            "type".to_string() => Value::String("httpbroker".to_string()),
-        },
-        MountConfig::AsyncHttpBroker => btree! {
-            "type".to_string() => Value::String("asynchttpbroker".to_string()),
-        },
+        MountConfig::Http { url, options } => with_http_options(
+            btree! {
+                "type".to_string() => Value::String("http".to_string()),
+                "url".to_string() => Value::String(url.clone()),
+            },
+            options,
+        ),
+        MountConfig::HttpBroker { options } => with_http_options(
+            btree! {
+                "type".to_string() => Value::String("httpbroker".to_string()),
+            },
+            options,
+        ),
+        MountConfig::AsyncHttpBroker { options } => with_http_options(
+            btree! {
+                "type".to_string() => Value::String("asynchttpbroker".to_string()),
+            },
+            options,
+        ),
         MountConfig::Structfs { url } => btree! {
             "type".to_string() => Value::String("structfs".to_string()),
             "url".to_string() => Value::String(url.clone()),
@@ -255,9 +1038,28 @@ fn config_to_value(config: &MountConfig) -> Value {
         MountConfig::Registers => btree! {
             "type".to_string() => Value::String("registers".to_string()),
         },
+        MountConfig::Plugin { kind, config } => {
+            let mut map = match config {
+                Value::Map(map) => map.clone(),
+                _ => BTreeMap::new(),
+            };
+            map.insert("type".to_string(), Value::String(kind.clone()));
+            map
+        }
     })
 }
 
+/// Add an `options` entry to a config map unless the options are all defaults.
+fn with_http_options(
+    mut map: BTreeMap<String, Value>,
+    options: &HttpOptions,
+) -> BTreeMap<String, Value> {
+    if !options.is_default() {
+        map.insert("options".to_string(), options.to_value());
+    }
+    map
+}
+
 /// Try to parse a Value as MountConfig
 fn value_to_config(value: &Value) -> Result<MountConfig, Error> {
     match value {
@@ -271,6 +1073,8 @@ fn value_to_config(value: &Value) -> Result<MountConfig, Error> {
                 .ok_or_else(|| {
                     Error::decode(crate::Format::VALUE, "Missing 'type' field in mount config")
                 })?;
+            let http_options =
+                || HttpOptions::from_value(map.get("options").unwrap_or(&Value::Null));
 
             match type_str {
                 "memory" => Ok(MountConfig::Memory),
@@ -302,10 +1106,17 @@ fn value_to_config(value: &Value) -> Result<MountConfig, Error> {
                                 "Missing 'url' field for http mount",
                             )
                         })?;
-                    Ok(MountConfig::Http { url })
+                    Ok(MountConfig::Http {
+                        url,
+                        options: http_options()?,
+                    })
                 }
-                "httpbroker" => Ok(MountConfig::HttpBroker),
-                "asynchttpbroker" => Ok(MountConfig::AsyncHttpBroker),
+                "httpbroker" => Ok(MountConfig::HttpBroker {
+                    options: http_options()?,
+                }),
+                "asynchttpbroker" => Ok(MountConfig::AsyncHttpBroker {
+                    options: http_options()?,
+                }),
                 "structfs" => {
                     let url = map
                         .get("url")
@@ -325,10 +1136,10 @@ fn value_to_config(value: &Value) -> Result<MountConfig, Error> {
                 "sys" => Ok(MountConfig::Sys),
                 "repl" => Ok(MountConfig::Repl),
                 "registers" => Ok(MountConfig::Registers),
-                other => Err(Error::decode(
-                    crate::Format::VALUE,
-                    format!("Unknown mount type: {}", other),
-                )),
+                other => Ok(MountConfig::Plugin {
+                    kind: other.to_string(),
+                    config: value.clone(),
+                }),
             }
         }
         _ => Err(Error::decode(
@@ -360,6 +1171,27 @@ impl<F: StoreFactory> Reader for MountStore<F> {
         // Delegate to overlay
         self.overlay.read(from)
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+}
+
+/// `/ctx/mounts` lists the mount names; other paths list through the
+/// overlay.
+impl<F: StoreFactory> Lister for MountStore<F> {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        if Self::is_mounts_path(path) {
+            return match Self::get_mount_name(path) {
+                None => Ok(Some(self.mounts.keys().cloned().collect())),
+                Some(name) => Ok(self
+                    .mounts
+                    .get(&name)
+                    .map(|config| children_of(&Self::config_to_value(config)))),
+            };
+        }
+        Lister::list(&mut self.overlay, path)
+    }
 }
 
 impl<F: StoreFactory> Writer for MountStore<F> {
@@ -391,6 +1223,23 @@ impl<F: StoreFactory> Writer for MountStore<F> {
         // Delegate to overlay
         self.overlay.write(destination, data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Deleting `/ctx/mounts/<name>` unmounts; other deletes go to the overlay.
+impl<F: StoreFactory> Deleter for MountStore<F> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if Self::is_mounts_path(path) {
+            return match Self::get_mount_name(path) {
+                Some(name) if !self.mounts.contains_key(&name) => Ok(()),
+                _ => self.write(path, Record::parsed(Value::Null)).map(|_| ()),
+            };
+        }
+        Deleter::delete(&mut self.overlay, path)
+    }
 }
 
 #[cfg(test)]
@@ -510,6 +1359,44 @@ mod tests {
         assert_eq!(store.list_mounts().len(), 0);
     }
 
+    #[test]
+    fn unmount_via_delete() {
+        let mut store = MountStore::new(TestFactory);
+        store.mount("data", MountConfig::Memory).unwrap();
+
+        Deleter::delete(&mut store, &path!("ctx/mounts/data")).unwrap();
+        assert_eq!(store.list_mounts().len(), 0);
+        // Already unmounted
+        Deleter::delete(&mut store, &path!("ctx/mounts/data")).unwrap();
+    }
+
+    #[test]
+    fn list_mount_names() {
+        let mut store = MountStore::new(TestFactory);
+        store.mount("data", MountConfig::Memory).unwrap();
+        store.mount("cache", MountConfig::Memory).unwrap();
+
+        assert_eq!(
+            Lister::list(&mut store, &path!("ctx/mounts"))
+                .unwrap()
+                .unwrap(),
+            ["cache", "data"]
+        );
+        assert_eq!(
+            Lister::list(&mut store, &path!("ctx/mounts/data"))
+                .unwrap()
+                .unwrap(),
+            ["type"]
+        );
+        assert_eq!(
+            Lister::list(&mut store, &path!("ctx/mounts/gone")).unwrap(),
+            None
+        );
+        let mut root = Lister::list(&mut store, &path!("")).unwrap().unwrap();
+        root.sort();
+        assert_eq!(root, ["cache", "data"]);
+    }
+
     #[test]
     fn config_conversion_roundtrip() {
         let configs = vec![
@@ -519,9 +1406,97 @@ mod tests {
             },
             MountConfig::Http {
                 url: "https://api.example.com".to_string(),
+                options: HttpOptions::default(),
+            },
+            MountConfig::Http {
+                url: "https://api.example.com".to_string(),
+                options: HttpOptions {
+                    timeout_ms: Some(5000),
+                    http2_prior_knowledge: true,
+                    pool_max_idle_per_host: Some(64),
+                    pool_idle_timeout_ms: Some(90_000),
+                    tcp_keepalive_ms: Some(60_000),
+                    tcp_nodelay: Some(true),
+                    auth: None,
+                    resolver: None,
+                    circuit_breaker: None,
+                    openapi: None,
+                    tls: None,
+                    network: None,
+                    limits: Some(RecordLimits {
+                        max_bytes: Some(1 << 20),
+                        ..RecordLimits::unlimited()
+                    }),
+                },
+            },
+            MountConfig::Http {
+                url: "https://s3.us-east-1.amazonaws.com".to_string(),
+                options: HttpOptions {
+                    auth: Some(HttpAuth::Sigv4 {
+                        region: "us-east-1".to_string(),
+                        service: "s3".to_string(),
+                        access_key_id: Some("AKIDEXAMPLE".to_string()),
+                        secret_access_key: None,
+                        session_token: None,
+                    }),
+                    ..Default::default()
+                },
+            },
+            MountConfig::HttpBroker {
+                options: HttpOptions {
+                    auth: Some(HttpAuth::Hmac {
+                        secret: "shh".to_string(),
+                        key_id: Some("client-1".to_string()),
+                    }),
+                    ..Default::default()
+                },
+            },
+            MountConfig::HttpBroker {
+                options: HttpOptions::default(),
+            },
+            MountConfig::AsyncHttpBroker {
+                options: HttpOptions {
+                    tcp_nodelay: Some(false),
+                    ..Default::default()
+                },
+            },
+            MountConfig::Http {
+                url: "https://api.internal".to_string(),
+                options: HttpOptions {
+                    resolver: Some(HttpResolver {
+                        hosts: btree! {
+                            "api.internal".to_string() => vec!["10.0.0.5".to_string(), "10.0.0.6:8443".to_string()],
+                        },
+                        doh_url: Some("https://1.1.1.1/dns-query".to_string()),
+                    }),
+                    ..Default::default()
+                },
+            },
+            MountConfig::Http {
+                url: "https://api.example.com".to_string(),
+                options: HttpOptions {
+                    circuit_breaker: Some(CircuitBreakerConfig {
+                        cooldown_ms: 5_000,
+                        ..Default::default()
+                    }),
+                    openapi: Some("specs/api.json".to_string()),
+                    ..Default::default()
+                },
+            },
+            MountConfig::Http {
+                url: "https://billing.internal".to_string(),
+                options: HttpOptions {
+                    tls: Some(HttpTls {
+                        ca_certs: vec!["certs/ca.pem".to_string()],
+                        only_ca_certs: true,
+                        client_cert: Some("certs/client.pem".to_string()),
+                        client_key: Some("certs/client.key".to_string()),
+                        server_name: Some("billing.example.com".to_string()),
+                        danger_accept_invalid_certs: false,
+                    }),
+                    ..Default::default()
+                },
             },
-            MountConfig::HttpBroker,
-            MountConfig::AsyncHttpBroker,
             MountConfig::Structfs {
                 url: "https://fs.example.com".to_string(),
             },
@@ -529,6 +1504,13 @@ mod tests {
             MountConfig::Sys,
             MountConfig::Repl,
             MountConfig::Registers,
+            MountConfig::Plugin {
+                kind: "redis".to_string(),
+                config: Value::Map(btree! {
+                    "type".to_string() => Value::String("redis".to_string()),
+                    "url".to_string() => Value::String("redis://localhost".to_string()),
+                }),
+            },
         ];
 
         for config in configs {
@@ -538,6 +1520,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn http_options_omitted_when_default() {
+        let value = config_to_value(&MountConfig::HttpBroker {
+            options: HttpOptions::default(),
+        });
+        match value {
+            Value::Map(map) => assert!(!map.contains_key("options")),
+            _ => panic!("Expected map"),
+        }
+    }
+
+    #[test]
+    fn http_options_from_value_errors() {
+        assert!(HttpOptions::from_value(&Value::from("fast")).is_err());
+
+        let bad_integer = Value::Map(btree! {
+            "pool_max_idle_per_host".to_string() => Value::Integer(-1),
+        });
+        assert!(HttpOptions::from_value(&bad_integer).is_err());
+
+        let bad_bool = Value::Map(btree! {
+            "tcp_nodelay".to_string() => Value::from("yes"),
+        });
+        assert!(HttpOptions::from_value(&bad_bool).is_err());
+
+        let config = Value::Map(btree! {
+            "type".to_string() => Value::from("httpbroker"),
+            "options".to_string() => bad_bool,
+        });
+        assert!(value_to_config(&config).is_err());
+
+        // A client certificate is useless without its key
+        let half_identity = Value::Map(btree! {
+            "tls".to_string() => Value::Map(btree! {
+                "client_cert".to_string() => Value::from("client.pem"),
+            }),
+        });
+        assert!(HttpOptions::from_value(&half_identity).is_err());
+    }
+
+    #[test]
+    fn http_network_checks_hosts_and_schemes() {
+        let network = HttpNetwork {
+            allow_hosts: vec!["api.example.com".into(), "*.internal".into()],
+            deny_hosts: vec!["admin.internal".into()],
+            schemes: vec!["https".into()],
+        };
+        assert!(network.check("https://api.example.com/v1?q=1").is_ok());
+        assert!(network.check("https://user@API.Example.com:8443").is_ok());
+        assert!(network.check("https://billing.eu.internal/").is_ok());
+
+        let denied = |url: &str| network.check(url).unwrap_err();
+        assert!(denied("http://api.example.com/").contains("scheme http"));
+        assert!(denied("https://admin.internal/").contains("denied"));
+        assert!(denied("https://internal/").contains("not allowed"));
+        assert!(denied("https://evil.com/?api.example.com").contains("not allowed"));
+        assert!(denied("https://api.example.com@evil.com/").contains("not allowed"));
+        assert!(denied("/relative").contains("absolute"));
+
+        // The host checked is the one the URL parser, and so the client,
+        // connects to
+        assert!(denied("http://evil.com\\@api.example.com/x").contains("scheme http"));
+        let http = HttpNetwork {
+            allow_hosts: vec!["api.example.com".into()],
+            deny_hosts: vec!["127.0.0.1".into(), "[::1]".into()],
+            schemes: Vec::new(),
+        };
+        let err = http
+            .check("http://evil.com\\@api.example.com/x")
+            .unwrap_err();
+        assert!(err.contains("host evil.com is not allowed"), "{}", err);
+        let err = http
+            .check("http://api.example.com:pw@evil.com/")
+            .unwrap_err();
+        assert!(err.contains("host evil.com is not allowed"), "{}", err);
+        for url in ["http://0x7f.1/", "http://127.1/", "http://2130706433/"] {
+            let err = http.check(url).unwrap_err();
+            assert!(err.contains("host 127.0.0.1 is denied"), "{}: {}", url, err);
+        }
+        let err = http.check("http://[0:0::1]/").unwrap_err();
+        assert!(err.contains("host ::1 is denied"), "{}", err);
+        let parsed = http.check("http://user@API.example.com./v1").unwrap();
+        assert_eq!(parsed.host_str(), Some("api.example.com."));
+
+        // Without lists, any http or https host is allowed
+        let open = HttpNetwork::default();
+        assert!(open.check("http://[::1]:8080/").is_ok());
+        assert!(open.check("file:///etc/passwd").is_err());
+
+        let options = HttpOptions {
+            network: Some(network),
+            ..Default::default()
+        };
+        assert_eq!(
+            HttpOptions::from_value(&options.to_value()).unwrap(),
+            options
+        );
+        let bad = Value::Map(btree! {
+            "network".to_string() => Value::Map(btree! {
+                "allow_hosts".to_string() => Value::Integer(1),
+            }),
+        });
+        assert!(HttpOptions::from_value(&bad).is_err());
+    }
+
+    #[test]
+    fn http_auth_from_value_errors() {
+        let missing_region = Value::Map(btree! {
+            "type".to_string() => Value::from("sigv4"),
+            "service".to_string() => Value::from("s3"),
+        });
+        assert!(HttpAuth::from_value(&missing_region).is_err());
+
+        let unknown = Value::Map(btree! {
+            "type".to_string() => Value::from("kerberos"),
+        });
+        assert!(HttpAuth::from_value(&unknown).is_err());
+
+        let options = Value::Map(btree! {
+            "auth".to_string() => Value::from("sigv4"),
+        });
+        assert!(HttpOptions::from_value(&options).is_err());
+    }
+
+    #[test]
+    fn http_options_serde() {
+        let config: MountConfig = serde_json::from_str(r#"{"type": "asynchttpbroker"}"#).unwrap();
+        assert_eq!(
+            config,
+            MountConfig::AsyncHttpBroker {
+                options: HttpOptions::default()
+            }
+        );
+
+        let config: MountConfig = serde_json::from_str(
+            r#"{"type": "http", "url": "https://x.test", "options": {"http2_prior_knowledge": true}}"#,
+        )
+        .unwrap();
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["options"]["http2_prior_knowledge"], true);
+        assert!(json["options"].get("timeout_ms").is_none());
+
+        let config: MountConfig = serde_json::from_str(
+            r#"{"type": "http", "url": "https://x.test",
+                "options": {"auth": {"type": "sigv4", "region": "eu-west-1", "service": "sts"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            MountConfig::Http {
+                url: "https://x.test".to_string(),
+                options: HttpOptions {
+                    auth: Some(HttpAuth::Sigv4 {
+                        region: "eu-west-1".to_string(),
+                        service: "sts".to_string(),
+                        access_key_id: None,
+                        secret_access_key: None,
+                        session_token: None,
+                    }),
+                    ..Default::default()
+                },
+            }
+        );
+    }
+
     #[test]
     fn mount_store_directly() {
         let mut store = MountStore::new(TestFactory);
@@ -618,15 +1765,18 @@ mod tests {
     }
 
     #[test]
-    fn value_to_config_unknown_type_fails() {
+    fn value_to_config_unknown_type_is_plugin() {
         let mut map = BTreeMap::new();
-        map.insert("type".to_string(), Value::String("unknown".to_string()));
-        let result = value_to_config(&Value::Map(map));
-        assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("Unknown mount type"));
+        map.insert("type".to_string(), Value::String("redis".to_string()));
+        map.insert("url".to_string(), Value::String("redis://x".to_string()));
+        let config = Value::Map(map);
+        assert_eq!(
+            value_to_config(&config).unwrap(),
+            MountConfig::Plugin {
+                kind: "redis".to_string(),
+                config: config.clone(),
+            }
+        );
     }
 
     #[test]
@@ -720,6 +1870,7 @@ mod tests {
         // Test Debug and Clone on MountConfig
         let config = MountConfig::Http {
             url: "https://test.com".to_string(),
+            options: HttpOptions::default(),
         };
         let debug = format!("{:?}", config);
         assert!(debug.contains("https://test.com"));
@@ -856,4 +2007,30 @@ mod tests {
         let result = store.read(&path!("data/other")).unwrap();
         assert!(result.is_some());
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        struct MapFactory;
+        impl StoreFactory for MapFactory {
+            fn create(&self, _config: &MountConfig) -> Result<StoreBox, Error> {
+                Ok(Box::new(MapStore::default()))
+            }
+        }
+
+        let mut store = MountStore::new(MapFactory);
+        store.mount("data", MountConfig::Memory).unwrap();
+        conformance::assert_missing(&mut store, &path!("ctx/mounts/missing"));
+        conformance::assert_found(&mut store, &path!("ctx/mounts/data"));
+        conformance::assert_missing(&mut store, &path!("data/missing"));
+        conformance::assert_round_trip(&mut store, &path!("data/a"), Value::from(1));
+        conformance::assert_deletes(&mut store, &path!("data/a"), Value::from(1));
+
+        // Deleting a mount unmounts it, and deleting it again succeeds
+        for _ in 0..2 {
+            crate::Store::delete(&mut store, &path!("ctx/mounts/data")).unwrap();
+            conformance::assert_missing(&mut store, &path!("ctx/mounts/data"));
+        }
+    }
 }
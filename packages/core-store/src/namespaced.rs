@@ -0,0 +1,222 @@
+//! Per-tenant views of a shared store.
+
+use crate::traits::delete_in;
+use crate::{Deleter, Error, OpContext, Path, Reader, Record, Writer};
+
+/// Default subtree holding each tenant's data.
+pub const DEFAULT_TENANTS_PREFIX: &str = "tenants";
+
+/// A store wrapper that confines every operation to the current tenant.
+///
+/// The tenant comes from the ambient [`OpContext`]: inside
+/// `OpContext::new().with_tenant("acme").scope(..)`, reading `orders/1`
+/// reads `tenants/acme/orders/1` from the inner store, and a write returns
+/// the path it wrote without the tenant prefix. One backing store can then
+/// serve many Blocks or tenants, each seeing only its own subtree.
+///
+/// The wrapper fails closed:
+///
+/// - An operation with no tenant in its context is an error.
+/// - A tenant id must be a single valid path component, so it cannot
+///   reach into another tenant's subtree.
+/// - Every component of the path is validated again, since a `Path` built
+///   by pushing onto its components may hold `..` or `/`.
+/// - A write whose inner store returns a path outside the tenant's subtree
+///   is reported as an error rather than handed back.
+///
+/// Nested scopes keep the outer tenant (see [`OpContext::scope`]), so code
+/// running for one tenant cannot switch to another. Values are passed
+/// through as they are; references inside them are not rewritten.
+pub struct NamespacedStore<S> {
+    inner: S,
+    prefix: Path,
+}
+
+impl<S> NamespacedStore<S> {
+    /// Keep each tenant's data at `tenants/{tenant}/` in `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            prefix: Path::parse(DEFAULT_TENANTS_PREFIX).unwrap(),
+        }
+    }
+
+    /// Keep each tenant's data at `{prefix}/{tenant}/` instead.
+    pub fn with_prefix(mut self, prefix: Path) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap the inner store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// The current tenant's subtree in the inner store.
+    pub fn tenant_root(&self, operation: &'static str) -> Result<Path, Error> {
+        let tenant = OpContext::current_tenant().ok_or_else(|| {
+            Error::store(
+                "namespaced",
+                operation,
+                "No tenant in the operation context",
+            )
+        })?;
+        let tenant = Path::try_from_components(vec![tenant.clone()]).map_err(|_| {
+            Error::store(
+                "namespaced",
+                operation,
+                format!("Invalid tenant id '{}'", tenant),
+            )
+        })?;
+        Ok(self.prefix.join(&tenant))
+    }
+
+    /// `path` inside the current tenant's subtree, and that subtree.
+    fn scoped(&self, path: &Path, operation: &'static str) -> Result<(Path, Path), Error> {
+        let root = self.tenant_root(operation)?;
+        let path = Path::try_from_components(path.components.clone())?;
+        Ok((root.join(&path), root))
+    }
+}
+
+impl<S: Reader> Reader for NamespacedStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let (path, _) = self.scoped(from, "read")?;
+        self.inner.read(&path)
+    }
+}
+
+impl<S: Writer> Writer for NamespacedStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let (path, root) = self.scoped(to, "write")?;
+        let written = self.inner.write(&path, data)?;
+        written.strip_prefix(&root).ok_or_else(|| {
+            Error::store(
+                "namespaced",
+                "write",
+                format!("Store wrote '{}', outside the tenant's subtree", written),
+            )
+        })
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl<S: Writer> Deleter for NamespacedStore<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        let (path, _) = self.scoped(path, "delete")?;
+        delete_in(&mut self.inner, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, NoCodec, Value};
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MapStore(BTreeMap<Path, Value>);
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.get(from).cloned().map(Record::parsed))
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data.into_value(&NoCodec)?);
+            Ok(to.clone())
+        }
+    }
+
+    fn as_tenant<R>(tenant: &str, f: impl FnOnce() -> R) -> R {
+        OpContext::new().with_tenant(tenant).scope(f)
+    }
+
+    fn read(store: &mut impl Reader, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn tenants_see_only_their_subtree() {
+        let mut store = NamespacedStore::new(MapStore::default());
+        let written = as_tenant("acme", || {
+            store.write(&path!("orders/1"), Record::parsed(Value::Integer(1)))
+        })
+        .unwrap();
+        assert_eq!(written, path!("orders/1"));
+        assert!(store
+            .inner()
+            .0
+            .contains_key(&path!("tenants/acme/orders/1")));
+
+        assert_eq!(
+            as_tenant("acme", || read(&mut store, &path!("orders/1"))),
+            Some(Value::Integer(1))
+        );
+        assert_eq!(
+            as_tenant("globex", || read(&mut store, &path!("orders/1"))),
+            None
+        );
+    }
+
+    #[test]
+    fn fails_closed() {
+        let mut store = NamespacedStore::new(MapStore::default()).with_prefix(path!("t"));
+        assert!(store.read(&path!("orders")).is_err());
+        assert!(as_tenant("a/b", || store.read(&path!("orders"))).is_err());
+
+        let mut escape = path!("orders");
+        escape.components.push("..".to_string());
+        assert!(as_tenant("acme", || store.read(&escape)).is_err());
+
+        // A nested scope cannot switch tenants
+        let tenant = as_tenant("acme", || {
+            OpContext::new()
+                .with_tenant("globex")
+                .scope(OpContext::current_tenant)
+        });
+        assert_eq!(tenant.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn written_paths_outside_the_tenant_are_errors() {
+        struct Elsewhere;
+
+        impl Writer for Elsewhere {
+            fn write(&mut self, _to: &Path, _data: Record) -> Result<Path, Error> {
+                Ok(path!("tenants/globex/x"))
+            }
+        }
+
+        let mut store = NamespacedStore::new(Elsewhere);
+        let result = as_tenant("acme", || {
+            store.write(&path!("x"), Record::parsed(Value::Null))
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut store = NamespacedStore::new(MapStore::default());
+        as_tenant("acme", || {
+            conformance::assert_missing(&mut store, &path!("orders/1"));
+            conformance::assert_round_trip(&mut store, &path!("orders/1"), Value::Integer(1));
+            conformance::assert_deletes(&mut store, &path!("orders/1"), Value::Integer(1));
+        });
+    }
+}
@@ -0,0 +1,91 @@
+//! Three-way result of a read.
+
+use crate::{Error, Record};
+
+/// The result of [`Reader::read`](crate::Reader::read), with "nothing here"
+/// spelled out.
+///
+/// `Result<Option<Record>, Error>` already separates the three cases, but it
+/// is easy to flatten `Ok(None)` and `Err` together with `?` or `ok()`.
+/// Matching on a `ReadOutcome` makes callers handle each one.
+///
+/// ```rust
+/// use structfs_core_store::{ReadOutcome, Reader, Path};
+/// # fn example(store: &mut dyn Reader) {
+/// match store.read_outcome(&Path::parse("users/1").unwrap()) {
+///     ReadOutcome::Found(record) => { /* use the record */ }
+///     ReadOutcome::NotFound => { /* nothing at the path */ }
+///     ReadOutcome::Error(e) => { /* the store failed */ }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum ReadOutcome {
+    /// Data exists at the path.
+    Found(Record),
+    /// Nothing exists at the path.
+    NotFound,
+    /// The store could not tell whether anything exists at the path.
+    Error(Error),
+}
+
+impl ReadOutcome {
+    /// Whether data was found.
+    pub fn is_found(&self) -> bool {
+        matches!(self, ReadOutcome::Found(_))
+    }
+
+    /// Whether nothing exists at the path.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ReadOutcome::NotFound)
+    }
+
+    /// Whether the read failed.
+    pub fn is_error(&self) -> bool {
+        matches!(self, ReadOutcome::Error(_))
+    }
+
+    /// Convert back to the [`Reader::read`](crate::Reader::read) result.
+    pub fn into_result(self) -> Result<Option<Record>, Error> {
+        match self {
+            ReadOutcome::Found(record) => Ok(Some(record)),
+            ReadOutcome::NotFound => Ok(None),
+            ReadOutcome::Error(e) => Err(e),
+        }
+    }
+}
+
+impl From<Result<Option<Record>, Error>> for ReadOutcome {
+    fn from(result: Result<Option<Record>, Error>) -> Self {
+        match result {
+            Ok(Some(record)) => ReadOutcome::Found(record),
+            Ok(None) => ReadOutcome::NotFound,
+            Err(e) => ReadOutcome::Error(e),
+        }
+    }
+}
+
+impl From<ReadOutcome> for Result<Option<Record>, Error> {
+    fn from(outcome: ReadOutcome) -> Self {
+        outcome.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    #[test]
+    fn converts_from_read_result() {
+        let found = ReadOutcome::from(Ok(Some(Record::parsed(Value::Integer(1)))));
+        assert!(found.is_found());
+
+        let missing = ReadOutcome::from(Ok(None));
+        assert!(missing.is_not_found());
+
+        let failed = ReadOutcome::from(Err(Error::store("test", "read", "boom")));
+        assert!(failed.is_error());
+        assert!(failed.into_result().is_err());
+    }
+}
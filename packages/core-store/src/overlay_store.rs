@@ -4,18 +4,29 @@
 //! semantics - the deepest matching prefix handles the request.
 //!
 //! Supports both direct store mounts and redirects (path aliases) with cycle detection.
+//!
+//! Stores mounted with [`OverlayStore::mount_described`] are also listed
+//! under the overlay's own `meta/` tree; see [`describe`](crate::describe).
+//!
+//! Interceptors added with [`OverlayStore::add_interceptor`] run around
+//! every operation; see [`interceptor`](crate::interceptor).
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-use std::collections::HashSet;
+use collection_literals::btree;
 
+use crate::describe::{SelfDescribing, META_PREFIX};
+use crate::interceptor::{intercept_read, intercept_write, Interceptor};
 use crate::path_trie::PathTrie;
-use crate::{Error, Path, PathError, Reader, Record, Writer};
+use crate::stats::{subtree_stats, DEFAULT_LARGEST};
+use crate::traits::{children_of, delete_in, list_children};
+use crate::{Deleter, Error, Lister, Path, PathError, Reader, Record, Reference, Value, Writer};
 
 /// A boxed store that is Send + Sync.
 pub type StoreBox = Box<dyn Store + Send + Sync>;
 
-/// Combined read/write trait for stores.
-pub trait Store: Reader + Writer {}
-impl<T: Reader + Writer> Store for T {}
+pub use crate::Store;
 
 /// Access control for redirects.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -101,6 +112,10 @@ impl<W: Writer> Writer for OnlyWritable<W> {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         self.inner.write(to, data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        self.inner.as_deleter()
+    }
 }
 
 /// Views into a sub-path of a store.
@@ -120,12 +135,32 @@ impl<S: Reader> Reader for SubStoreView<S> {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         self.inner.read(&self.prefix.join(from))
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+}
+
+impl<S: Reader> Lister for SubStoreView<S> {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        list_children(&mut self.inner, &self.prefix.join(path))
+    }
 }
 
 impl<S: Writer> Writer for SubStoreView<S> {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         self.inner.write(&self.prefix.join(to), data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl<S: Writer> Deleter for SubStoreView<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        delete_in(&mut self.inner, &self.prefix.join(path))
+    }
 }
 
 /// Route reads and writes to different stores based on path prefixes.
@@ -153,8 +188,36 @@ impl<S: Writer> Writer for SubStoreView<S> {
 /// // Reads to /users/alice go to user_store with path "alice"
 /// // Reads to /config/theme go to config_store with path "theme"
 /// ```
+///
+/// # Descriptions
+///
+/// Once any store is mounted with [`mount_described`](Self::mount_described),
+/// the top-level `meta` path belongs to the overlay. Reading `meta` returns
+/// a map from each described mount to a reference to its description, and
+/// `meta/{mount}/rest...` reads `{mount}/meta/rest...`. A store described
+/// at the root appears inline in that map under `/`; its own `meta/` paths
+/// are read through as usual.
+///
+/// # Statistics
+///
+/// Reading `meta/{prefix}/stats` walks everything at or below `{prefix}`
+/// and returns its [`SubtreeStats`](crate::stats::SubtreeStats): how many
+/// records there are, their total size, how many sit at each depth and the
+/// largest of them. `meta/stats` covers the whole overlay.
+///
+/// Reading `meta/memory` returns `{total_bytes, mounts: {mount: bytes}}`,
+/// the [`memory_usage`](Reader::memory_usage) of each mount that reports
+/// it, with the root mount under `/`. Unlike the statistics this reads no
+/// data, so it is cheap enough to poll.
+///
+/// These paths are answered by the overlay even where a store is mounted
+/// under `meta`.
 pub struct OverlayStore {
     trie: PathTrie<RouteTarget>,
+    /// Mounts whose store implements [`SelfDescribing`]
+    described: BTreeSet<Path>,
+    /// Run around every operation, in the order they were added
+    interceptors: Vec<Box<dyn Interceptor>>,
 }
 
 impl Default for OverlayStore {
@@ -168,6 +231,8 @@ impl OverlayStore {
     pub fn new() -> Self {
         Self {
             trie: PathTrie::new(),
+            described: BTreeSet::new(),
+            interceptors: Vec::new(),
         }
     }
 
@@ -179,16 +244,27 @@ impl OverlayStore {
         path: Path,
         store: S,
     ) -> Option<StoreBox> {
-        match self.trie.insert(&path, RouteTarget::Store(Box::new(store))) {
-            Some(RouteTarget::Store(s)) => Some(s),
-            _ => None,
-        }
+        self.mount_boxed(path, Box::new(store))
+    }
+
+    /// Mount a store that describes itself, listing it under `meta/`.
+    ///
+    /// Returns the previous store at that exact path if any.
+    pub fn mount_described<S: Store + SelfDescribing + Send + Sync + 'static>(
+        &mut self,
+        path: Path,
+        store: S,
+    ) -> Option<StoreBox> {
+        let previous = self.mount_boxed(path.clone(), Box::new(store));
+        self.described.insert(path);
+        previous
     }
 
     /// Mount a boxed store at the given path prefix.
     ///
     /// Returns the previous store at that exact path if any.
     pub fn mount_boxed(&mut self, path: Path, store: StoreBox) -> Option<StoreBox> {
+        self.described.remove(&path);
         match self.trie.insert(&path, RouteTarget::Store(store)) {
             Some(RouteTarget::Store(s)) => Some(s),
             _ => None,
@@ -199,17 +275,55 @@ impl OverlayStore {
     ///
     /// Returns the removed store if found.
     pub fn unmount(&mut self, path: &Path) -> Option<StoreBox> {
+        self.described.remove(path);
         match self.trie.remove(path) {
             Some(RouteTarget::Store(s)) => Some(s),
             _ => None,
         }
     }
 
+    /// Add an interceptor to run around every read and write.
+    ///
+    /// Interceptors run in the order they were added, and see paths as the
+    /// caller gave them, before redirects and routing.
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Number of interceptors added.
+    pub fn interceptor_count(&self) -> usize {
+        self.interceptors.len()
+    }
+
     /// Unmount entire subtree at path, returning it as a new OverlayStore.
+    ///
+    /// The subtree keeps no interceptors; they stay with this overlay.
     pub fn unmount_subtree(&mut self, path: &Path) -> Option<OverlayStore> {
-        self.trie
-            .remove_subtree(path)
-            .map(|trie| OverlayStore { trie })
+        let trie = self.trie.remove_subtree(path)?;
+        let moved: Vec<Path> = self
+            .described
+            .iter()
+            .filter(|m| m.has_prefix(path))
+            .cloned()
+            .collect();
+        let described = moved
+            .into_iter()
+            .filter_map(|m| {
+                self.described.remove(&m);
+                m.strip_prefix(path)
+            })
+            .collect();
+        Some(OverlayStore {
+            trie,
+            described,
+            interceptors: Vec::new(),
+        })
+    }
+
+    /// Mount points of stores mounted with
+    /// [`mount_described`](Self::mount_described).
+    pub fn described_mounts(&self) -> impl Iterator<Item = &Path> {
+        self.described.iter()
     }
 
     /// Add a redirect from one path to another.
@@ -266,7 +380,7 @@ impl OverlayStore {
 
     /// Check if any store would handle this path (fallthrough).
     pub fn has_route(&self, path: &Path) -> bool {
-        self.trie.find_ancestor(path).is_some()
+        self.trie.find_ancestor_depth(path).is_some()
     }
 
     /// Number of mounted routes (stores + redirects).
@@ -344,104 +458,384 @@ impl OverlayStore {
 struct ResolvedRoute<'a> {
     store: &'a mut StoreBox,
     suffix: Path,
-    /// The prefix that led to this store (for reconstructing full paths).
-    prefix: Path,
+    /// The path after redirects, whose first `depth` components are the
+    /// store's mount point.
+    path: &'a Path,
+    depth: usize,
+}
+
+impl ResolvedRoute<'_> {
+    /// A path relative to the store as a full overlay path.
+    fn mounted(&self, suffix: &Path) -> Path {
+        Path {
+            components: self.path.components[..self.depth]
+                .iter()
+                .chain(&suffix.components)
+                .cloned()
+                .collect(),
+        }
+    }
 }
 
 impl OverlayStore {
-    /// Resolve a path for reading, following redirects with cycle detection.
-    fn resolve_for_read(&mut self, path: &Path) -> Result<Option<ResolvedRoute<'_>>, Error> {
-        let mut visited = HashSet::new();
-        self.resolve_with_tracking(path, &mut visited, false)
+    /// Resolve a path for reading, following redirects with cycle detection,
+    /// and pass the route to `f`.
+    fn resolve_for_read<T>(
+        &mut self,
+        path: &Path,
+        f: impl FnOnce(ResolvedRoute<'_>) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        self.resolve(path, false, f)
     }
 
-    /// Resolve a path for writing, following redirects with cycle detection.
-    fn resolve_for_write(&mut self, path: &Path) -> Result<Option<ResolvedRoute<'_>>, Error> {
-        let mut visited = HashSet::new();
-        self.resolve_with_tracking(path, &mut visited, true)
+    /// Resolve a path for writing, following redirects with cycle detection,
+    /// and pass the route to `f`.
+    fn resolve_for_write<T>(
+        &mut self,
+        path: &Path,
+        f: impl FnOnce(ResolvedRoute<'_>) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        self.resolve(path, true, f)
     }
 
-    fn resolve_with_tracking(
+    /// Walk the trie to the deepest route, following redirects until a store
+    /// is found, and pass that store to `f`.
+    ///
+    /// Each step is one walk costing O(path length) regardless of how many
+    /// routes are mounted; the walk that finds the store hands it over
+    /// directly. The route is passed to `f` rather than returned so that the
+    /// store's borrow ends with the step that found it.
+    fn resolve<T>(
         &mut self,
         path: &Path,
-        visited: &mut HashSet<Path>,
         is_write: bool,
-    ) -> Result<Option<ResolvedRoute<'_>>, Error> {
-        // Cycle detection - use the path prefix that matched, not the full path
-        if !visited.insert(path.clone()) {
-            return Err(Error::store(
-                "overlay",
-                if is_write { "write" } else { "read" },
-                "redirect cycle detected",
-            ));
+        f: impl FnOnce(ResolvedRoute<'_>) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        let mut current = Cow::Borrowed(path);
+        // Redirect sources already followed; only allocates once one is
+        let mut visited: HashSet<Path> = HashSet::new();
+
+        loop {
+            let (target, depth) = match self.trie.find_ancestor_depth_mut(&current) {
+                Some((RouteTarget::Store(store), depth)) => {
+                    return f(ResolvedRoute {
+                        store,
+                        suffix: current.slice(depth, current.len()),
+                        path: &current,
+                        depth,
+                    })
+                    .map(Some);
+                }
+                Some((RouteTarget::Redirect { target, mode, .. }, depth)) => {
+                    // Check access mode
+                    let allowed = !matches!(
+                        (is_write, *mode),
+                        (false, RedirectMode::WriteOnly) | (true, RedirectMode::ReadOnly)
+                    );
+                    if !allowed {
+                        return Ok(None);
+                    }
+                    (target.clone(), depth)
+                }
+                None => return Ok(None),
+            };
+
+            // Cycle detection, keyed on the redirect that matched so that
+            // redirects into their own subtree are caught too
+            if !visited.insert(current.slice(0, depth)) {
+                return Err(Error::store(
+                    "overlay",
+                    if is_write { "write" } else { "read" },
+                    "redirect cycle detected",
+                ));
+            }
+            current = Cow::Owned(target.join(&current.slice(depth, current.len())));
         }
+    }
+}
 
-        // Find the ancestor route
-        let ancestor = self.trie.find_ancestor(path);
-        let (prefix_len, suffix, is_redirect, redirect_info) = match ancestor {
-            Some((target, suffix)) => {
-                let prefix_len = path.len() - suffix.len();
-                match target {
-                    RouteTarget::Store(_) => (prefix_len, suffix, false, None),
-                    RouteTarget::Redirect { target, mode, .. } => {
-                        // Check access mode
-                        let allowed = !matches!(
-                            (is_write, mode),
-                            (false, RedirectMode::WriteOnly) | (true, RedirectMode::ReadOnly)
-                        );
-                        if !allowed {
-                            return Ok(None);
+/// Last path component of the `meta/{prefix}/stats` lens.
+const STATS: &str = "stats";
+
+/// Last path component of the `meta/memory` lens.
+const MEMORY: &str = "memory";
+
+impl OverlayStore {
+    /// Serve reads of the aggregated `meta/` tree, or `None` to route
+    /// `from` as usual.
+    fn read_meta(&mut self, from: &Path) -> Option<Result<Option<Record>, Error>> {
+        if self.described.is_empty() || from.iter().next()? != META_PREFIX {
+            return None;
+        }
+        let rest = from.slice(1, from.len());
+        if rest.is_empty() {
+            return Some(self.meta_index());
+        }
+
+        // The root mount, if described, serves its own meta/ paths directly
+        let mount = self
+            .described
+            .iter()
+            .filter(|m| !m.is_empty() && rest.has_prefix(m))
+            .max_by_key(|m| m.len())?
+            .clone();
+        let meta = Path::from_components(vec![META_PREFIX.to_string()]);
+        let inner = mount.join(&meta).join(&rest.slice(mount.len(), rest.len()));
+        Some(
+            match self.resolve_for_read(&inner, |resolved| resolved.store.read(&resolved.suffix)) {
+                Ok(Some(result)) => Ok(result),
+                Ok(None) => Err(Error::NoRoute { path: from.clone() }),
+                Err(e) => Err(e),
+            },
+        )
+    }
+
+    /// `{mount: reference}` for every described mount.
+    fn meta_index(&mut self) -> Result<Option<Record>, Error> {
+        let mut index = BTreeMap::new();
+        for mount in self.described.clone() {
+            if mount.is_empty() {
+                let meta = Path::from_components(vec![META_PREFIX.to_string()]);
+                let overview = self
+                    .resolve_for_read(&meta, |resolved| resolved.store.read(&resolved.suffix))?
+                    .flatten();
+                let overview = match overview {
+                    Some(record) => record.into_value(&crate::NoCodec)?,
+                    None => Value::Null,
+                };
+                index.insert("/".to_string(), overview);
+            } else {
+                let reference = Reference::with_type(format!("{}/{}", META_PREFIX, mount), "meta");
+                index.insert(mount.to_string(), reference.to_value());
+            }
+        }
+        Ok(Some(Record::parsed(Value::Map(index))))
+    }
+}
+
+impl OverlayStore {
+    fn read_routed(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if let Some(result) = self.read_stats(from) {
+            return result;
+        }
+        if from.len() == 2 && from[0] == META_PREFIX && from[1] == MEMORY {
+            return Ok(Some(Record::parsed(self.memory_report())));
+        }
+        self.read_data(from)
+    }
+
+    /// `{total_bytes, mounts: {mount: bytes}}` for the mounts that report
+    /// their memory usage.
+    fn memory_report(&self) -> Value {
+        let mounts: BTreeMap<String, Value> = self
+            .mounts()
+            .filter_map(|(mount, store)| {
+                let name = if mount.is_empty() {
+                    "/".to_string()
+                } else {
+                    mount.to_string()
+                };
+                Some((name, Value::Integer(store.memory_usage()? as i64)))
+            })
+            .collect();
+        Value::Map(btree! {
+            "total_bytes".into() => Value::Integer(self.memory_usage().unwrap_or(0) as i64),
+            "mounts".into() => Value::Map(mounts),
+        })
+    }
+
+    /// Serve `meta/{prefix}/stats`, or `None` for any other path.
+    fn read_stats(&mut self, from: &Path) -> Option<Result<Option<Record>, Error>> {
+        if from.len() < 2 || from[0] != META_PREFIX || from[from.len() - 1] != STATS {
+            return None;
+        }
+
+        /// Reads the overlay without the stats lens, so a walk that reaches
+        /// `meta/` does not compute statistics of its own. A path with no
+        /// route of its own but mounts below it reads as a map of the next
+        /// component of each, so the walk finds them.
+        struct Data<'a>(&'a mut OverlayStore);
+
+        impl Reader for Data<'_> {
+            fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+                match self.0.read_data(from) {
+                    Err(Error::NoRoute { path }) => {
+                        let below: BTreeMap<String, Value> = self
+                            .0
+                            .mounts()
+                            .filter(|(mount, _)| mount.len() > from.len() && mount.has_prefix(from))
+                            .map(|(mount, _)| (mount[from.len()].clone(), Value::Null))
+                            .collect();
+                        if below.is_empty() {
+                            return Err(Error::NoRoute { path });
                         }
-                        (prefix_len, suffix, true, Some(target.clone()))
+                        Ok(Some(Record::parsed(Value::Map(below))))
                     }
+                    result => result,
                 }
             }
-            None => return Ok(None),
-        };
+        }
 
-        // If it's a redirect, follow it recursively
-        if is_redirect {
-            if let Some(redirect_target) = redirect_info {
-                let new_path = redirect_target.join(&suffix);
-                return self.resolve_with_tracking(&new_path, visited, is_write);
+        let prefix = from.slice(1, from.len() - 1);
+        Some(
+            subtree_stats(&mut Data(self), &prefix, DEFAULT_LARGEST)
+                .map(|stats| Some(Record::parsed(stats.to_value()))),
+        )
+    }
+
+    fn read_data(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if let Some(result) = self.read_meta(from) {
+            return result;
+        }
+        self.resolve_for_read(from, |resolved| resolved.store.read(&resolved.suffix))?
+            .ok_or_else(|| Error::NoRoute { path: from.clone() })
+    }
+
+    fn write_routed(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        self.resolve_for_write(to, |resolved| {
+            let result_suffix = resolved.store.write(&resolved.suffix, data)?;
+            Ok(resolved.mounted(&result_suffix))
+        })?
+        .ok_or_else(|| Error::NoRoute { path: to.clone() })
+    }
+
+    /// List `path` in the store it routes to, then add the next component
+    /// of each mount below it.
+    fn list_routed(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        if !self.described.is_empty() && path.iter().next().map(String::as_str) == Some(META_PREFIX)
+        {
+            return match self.read_routed(path)? {
+                Some(record) => Ok(Some(children_of(&record.into_value(&crate::NoCodec)?))),
+                None => Ok(None),
+            };
+        }
+
+        let (routed, listed) =
+            match self.resolve_for_read(path, |resolved| resolved.store.list(&resolved.suffix))? {
+                Some(listed) => (true, listed),
+                None => (false, None),
+            };
+        let mut below = Vec::new();
+        for (mount, _) in self.mounts() {
+            if mount.len() > path.len()
+                && mount.has_prefix(path)
+                && !below.contains(&mount[path.len()])
+            {
+                below.push(mount[path.len()].clone());
             }
         }
 
-        // It's a store - get mutable reference
-        match self.trie.find_ancestor_mut(path) {
-            Some((RouteTarget::Store(store), suffix)) => {
-                let prefix = Path {
-                    components: path.components[..prefix_len].to_vec(),
-                };
-                Ok(Some(ResolvedRoute {
-                    store,
-                    suffix,
-                    prefix,
-                }))
+        match listed {
+            Some(mut names) => {
+                for name in below {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                Ok(Some(names))
             }
-            _ => Ok(None),
+            None if !below.is_empty() => Ok(Some(below)),
+            None if routed => Ok(None),
+            None => Err(Error::NoRoute { path: path.clone() }),
         }
     }
+
+    fn delete_routed(&mut self, path: &Path) -> Result<Path, Error> {
+        self.resolve_for_write(path, |resolved| {
+            resolved.store.delete(&resolved.suffix)?;
+            Ok(resolved.mounted(&resolved.suffix))
+        })?
+        .ok_or_else(|| Error::NoRoute { path: path.clone() })
+    }
 }
 
 impl Reader for OverlayStore {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
-        match self.resolve_for_read(from)? {
-            Some(resolved) => resolved.store.read(&resolved.suffix),
-            None => Err(Error::NoRoute { path: from.clone() }),
+        if self.interceptors.is_empty() {
+            return self.read_routed(from);
         }
+        let mut interceptors = std::mem::take(&mut self.interceptors);
+        let result = intercept_read(&mut interceptors, from, |path| self.read_routed(path));
+        self.interceptors = interceptors;
+        result
+    }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+
+    /// The total of the mounts that report their usage, or `None` if none
+    /// do.
+    fn memory_usage(&self) -> Option<usize> {
+        self.mounts()
+            .filter_map(|(_, store)| store.memory_usage())
+            .reduce(|a, b| a + b)
+    }
+}
+
+/// Lists the routed store's entries merged with the mount points below the
+/// path, so a path with no store of its own but mounts below it lists
+/// those.
+///
+/// Interceptors see a listing as a read of the path, so they can refuse or
+/// redirect it; the result of that read is ignored.
+impl Lister for OverlayStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        if self.interceptors.is_empty() {
+            return self.list_routed(path);
+        }
+        let mut interceptors = std::mem::take(&mut self.interceptors);
+        let mut listed = None;
+        let result = intercept_read(&mut interceptors, path, |path| {
+            listed = Some(self.list_routed(path));
+            Ok(None)
+        });
+        self.interceptors = interceptors;
+        result?;
+        listed.unwrap_or(Ok(None))
     }
 }
 
 impl Writer for OverlayStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
-        match self.resolve_for_write(to)? {
-            Some(resolved) => {
-                let result_suffix = resolved.store.write(&resolved.suffix, data)?;
-                Ok(resolved.prefix.join(&result_suffix))
-            }
-            None => Err(Error::NoRoute { path: to.clone() }),
+        if self.interceptors.is_empty() {
+            return self.write_routed(to, data);
         }
+        let mut interceptors = std::mem::take(&mut self.interceptors);
+        let result = intercept_write(&mut interceptors, to, data, |path, data| {
+            self.write_routed(path, data)
+        });
+        self.interceptors = interceptors;
+        result
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Routes deletes like writes, to the layer's [`Store::delete`].
+///
+/// Interceptors see a delete as a write of null, so they can refuse or
+/// redirect it. If one replaces the null with other data, that data is
+/// written instead.
+impl Deleter for OverlayStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if self.interceptors.is_empty() {
+            return self.delete_routed(path).map(|_| ());
+        }
+        let mut interceptors = std::mem::take(&mut self.interceptors);
+        let result = intercept_write(
+            &mut interceptors,
+            path,
+            Record::parsed(Value::Null),
+            |path, data| match data.as_value() {
+                Some(Value::Null) => self.delete_routed(path),
+                _ => self.write_routed(path, data),
+            },
+        );
+        self.interceptors = interceptors;
+        result.map(|_| ())
     }
 }
 
@@ -468,6 +862,10 @@ mod tests {
         fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
             Ok(self.data.get(from).cloned())
         }
+
+        fn memory_usage(&self) -> Option<usize> {
+            Some(self.data.values().map(Record::estimated_size).sum())
+        }
     }
 
     impl Writer for TestStore {
@@ -475,6 +873,74 @@ mod tests {
             self.data.insert(to.clone(), data);
             Ok(to.clone())
         }
+
+        fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+            Some(self)
+        }
+    }
+
+    impl Deleter for TestStore {
+        fn delete(&mut self, path: &Path) -> Result<(), Error> {
+            self.data.remove(path);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn delete_routes_to_layer() {
+        let mut users = TestStore::new("users");
+        users
+            .write(&path!("alice"), Record::parsed(Value::from("Alice")))
+            .unwrap();
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!("users"), users);
+        overlay.add_redirect(
+            path!("people"),
+            path!("users"),
+            RedirectMode::ReadWrite,
+            None,
+        );
+
+        let store: &mut dyn Store = &mut overlay;
+        store.delete(&path!("people/alice")).unwrap();
+        assert!(store.read(&path!("users/alice")).unwrap().is_none());
+        // Deleting again is fine; deleting where nothing is mounted is not
+        store.delete(&path!("users/alice")).unwrap();
+        assert!(matches!(
+            store.delete(&path!("config/theme")),
+            Err(Error::NoRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn list_merges_mount_points() {
+        let mut root = TestStore::new("root");
+        root.write(
+            &path!(""),
+            Record::parsed(Value::Map(BTreeMap::from([(
+                "config".to_string(),
+                Value::Null,
+            )]))),
+        )
+        .unwrap();
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!(""), root);
+        overlay.mount(path!("data/users"), TestStore::new("users"));
+        overlay.mount(path!("data/teams"), TestStore::new("teams"));
+
+        let store: &mut dyn Store = &mut overlay;
+        assert_eq!(store.list(&path!("")).unwrap().unwrap(), ["config", "data"]);
+        // Nothing at data in the root store, but mounts below it
+        let mut data = store.list(&path!("data")).unwrap().unwrap();
+        data.sort();
+        assert_eq!(data, ["teams", "users"]);
+        assert_eq!(store.list(&path!("missing")).unwrap(), None);
+
+        let mut unrouted = OverlayStore::new();
+        assert!(matches!(
+            Lister::list(&mut unrouted, &path!("x")),
+            Err(Error::NoRoute { .. })
+        ));
     }
 
     #[test]
@@ -1054,6 +1520,41 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("cycle"));
     }
 
+    #[test]
+    fn redirect_into_own_subtree_detected() {
+        let mut overlay = OverlayStore::new();
+
+        // /a -> /a/x never revisits the same path, only the same redirect
+        overlay.add_redirect(path!("a"), path!("a/x"), RedirectMode::ReadWrite, None);
+
+        let result = overlay.read(&path!("a/key"));
+        assert!(result.unwrap_err().to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn resolution_with_many_mounts() {
+        let mut overlay = OverlayStore::new();
+        for i in 0..500 {
+            let mut store = TestStore::new("svc");
+            store
+                .write(&path!("key"), Record::parsed(Value::Integer(i)))
+                .unwrap();
+            overlay.mount(
+                Path::parse(&format!("svc{}/v{}", i / 4, i % 4)).unwrap(),
+                store,
+            );
+        }
+
+        let value = overlay
+            .read(&path!("svc77/v2/key"))
+            .unwrap()
+            .unwrap()
+            .into_value(&crate::NoCodec)
+            .unwrap();
+        assert_eq!(value, Value::Integer(310));
+        assert!(!overlay.has_route(&path!("svc77/v9/key")));
+    }
+
     #[test]
     fn redirect_chain() {
         let mut overlay = OverlayStore::new();
@@ -1135,4 +1636,346 @@ mod tests {
         // store_count only counts stores
         assert_eq!(overlay.store_count(), 1);
     }
+
+    /// Describes itself as `{name, path}` for any path under `meta/`.
+    struct NamedStore(&'static str);
+
+    impl Reader for NamedStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            match crate::describe::read_lens(self, from) {
+                Some(result) => result,
+                None => Ok(Some(Record::parsed(Value::from(self.0)))),
+            }
+        }
+    }
+
+    impl Writer for NamedStore {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+            Ok(to.clone())
+        }
+    }
+
+    impl SelfDescribing for NamedStore {
+        fn describe(&self, path: &Path) -> Result<Option<Value>, Error> {
+            Ok(Some(Value::Map(
+                [
+                    ("name".to_string(), Value::from(self.0)),
+                    ("path".to_string(), Value::from(path.to_string())),
+                ]
+                .into(),
+            )))
+        }
+    }
+
+    fn read_value(overlay: &mut OverlayStore, path: &Path) -> Value {
+        overlay
+            .read(path)
+            .unwrap()
+            .unwrap()
+            .into_value(&crate::NoCodec)
+            .unwrap()
+    }
+
+    #[test]
+    fn described_mounts_are_aggregated_under_meta() {
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!("plain"), TestStore::new("plain"));
+        overlay.mount_described(path!("fs"), NamedStore("fs"));
+        overlay.mount_described(path!("net/http"), NamedStore("http"));
+
+        let Value::Map(index) = read_value(&mut overlay, &path!("meta")) else {
+            panic!("expected a map");
+        };
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index["net/http"],
+            Reference::with_type("meta/net/http", "meta").to_value()
+        );
+
+        let Value::Map(desc) = read_value(&mut overlay, &path!("meta/net/http/outstanding/1"))
+        else {
+            panic!("expected a map");
+        };
+        assert_eq!(desc["name"], Value::from("http"));
+        assert_eq!(desc["path"], Value::from("outstanding/1"));
+
+        // The mount's own meta/ lens is the same tree
+        assert_eq!(
+            read_value(&mut overlay, &path!("fs/meta/open")),
+            read_value(&mut overlay, &path!("meta/fs/open"))
+        );
+        assert!(matches!(
+            overlay.read(&path!("meta/plain")),
+            Err(Error::NoRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn described_root_is_inlined() {
+        let mut overlay = OverlayStore::new();
+        overlay.mount_described(path!(""), NamedStore("root"));
+        overlay.mount_described(path!("sys"), NamedStore("sys"));
+
+        let Value::Map(index) = read_value(&mut overlay, &path!("meta")) else {
+            panic!("expected a map");
+        };
+        let Value::Map(root) = &index["/"] else {
+            panic!("expected the root description inline");
+        };
+        assert_eq!(root["name"], Value::from("root"));
+
+        let Value::Map(desc) = read_value(&mut overlay, &path!("meta/users")) else {
+            panic!("expected a map");
+        };
+        assert_eq!(desc["name"], Value::from("root"));
+    }
+
+    #[test]
+    fn unmounting_forgets_descriptions() {
+        let mut overlay = OverlayStore::new();
+        overlay.mount_described(path!("a"), NamedStore("a"));
+        overlay.mount_described(path!("b/c"), NamedStore("c"));
+
+        // Replacing with an undescribed store drops it from meta/
+        overlay.mount(path!("a"), TestStore::new("a"));
+        let sub = overlay.unmount_subtree(&path!("b")).unwrap();
+        assert_eq!(overlay.described_mounts().count(), 0);
+        assert_eq!(
+            sub.described_mounts().cloned().collect::<Vec<_>>(),
+            vec![path!("c")]
+        );
+
+        // With nothing described, meta routes like any other path
+        overlay.mount(path!("meta"), TestStore::new("meta"));
+        assert!(overlay.read(&path!("meta")).unwrap().is_none());
+    }
+
+    #[test]
+    fn stats_walk_mounts_below_a_prefix() {
+        let mut overlay = OverlayStore::new();
+        let mut users = TestStore::new("users");
+        let alice = Value::Map(BTreeMap::from([
+            ("name".to_string(), Value::from("Alice")),
+            ("age".to_string(), Value::Integer(30)),
+        ]));
+        users
+            .write(
+                &path!(""),
+                Record::parsed(Value::Map(BTreeMap::from([("alice".to_string(), alice)]))),
+            )
+            .unwrap();
+        overlay.mount(path!("users"), users);
+        let mut app = TestStore::new("app");
+        app.write(&path!(""), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        overlay.mount(path!("config/app"), app);
+
+        let stats = read_value(&mut overlay, &path!("meta/stats"));
+        assert_eq!(stats.get(&path!("entries")), Some(&Value::Integer(3)));
+        assert_eq!(
+            stats.get(&path!("depths")),
+            Some(&Value::Array(vec![
+                Value::Integer(0),
+                Value::Integer(0),
+                Value::Integer(1),
+                Value::Integer(2),
+            ]))
+        );
+
+        let stats = read_value(&mut overlay, &path!("meta/users/stats"));
+        assert_eq!(stats.get(&path!("entries")), Some(&Value::Integer(2)));
+        assert_eq!(
+            stats.get(&path!("largest/0/path")),
+            Some(&Value::from("users/alice/name"))
+        );
+
+        assert!(matches!(
+            overlay.read(&path!("meta/nothing/stats")),
+            Err(Error::NoRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn memory_usage_totals_reporting_mounts() {
+        let mut overlay = OverlayStore::new();
+        assert_eq!(overlay.memory_usage(), None);
+
+        let mut users = TestStore::new("users");
+        users
+            .write(
+                &path!("alice"),
+                Record::parsed(Value::from("x".repeat(100))),
+            )
+            .unwrap();
+        let users_bytes = users.memory_usage().unwrap();
+        overlay.mount(path!("users"), users);
+        let mut root = TestStore::new("root");
+        root.write(&path!("motd"), Record::parsed(Value::from("hi")))
+            .unwrap();
+        let root_bytes = root.memory_usage().unwrap();
+        overlay.mount(path!(""), root);
+        // Does not report, so it counts for nothing
+        overlay.mount(path!("named"), NamedStore("named"));
+
+        assert_eq!(overlay.memory_usage(), Some(users_bytes + root_bytes));
+
+        let report = read_value(&mut overlay, &path!("meta/memory"));
+        assert_eq!(
+            report.get(&path!("total_bytes")),
+            Some(&Value::Integer((users_bytes + root_bytes) as i64))
+        );
+        assert_eq!(
+            report.get(&path!("mounts")),
+            Some(&Value::Map(BTreeMap::from([
+                ("/".to_string(), Value::Integer(root_bytes as i64)),
+                ("users".to_string(), Value::Integer(users_bytes as i64)),
+            ])))
+        );
+
+        // Nested overlays report their total
+        let mut outer = OverlayStore::new();
+        outer.mount(path!("inner"), overlay);
+        assert_eq!(outer.memory_usage(), Some(users_bytes + root_bytes));
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!("data"), MapStore::default());
+        conformance::assert_missing(&mut overlay, &path!("data/missing"));
+        conformance::assert_round_trip(&mut overlay, &path!("data/a"), Value::from(1));
+        conformance::assert_deletes(&mut overlay, &path!("data/a"), Value::from(1));
+
+        // Through a redirect, too
+        overlay.add_redirect(path!("alias"), path!("data"), RedirectMode::ReadWrite, None);
+        conformance::assert_missing(&mut overlay, &path!("alias/missing"));
+        conformance::assert_deletes(&mut overlay, &path!("alias/b"), Value::from(2));
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::Value;
+    use proptest::prelude::*;
+
+    /// Store that answers every read with its id and the path it was asked
+    /// for, so tests can see where a request was routed.
+    struct EchoStore(usize);
+
+    impl Reader for EchoStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(Some(Record::parsed(Value::Array(vec![
+                Value::Integer(self.0 as i64),
+                Value::from(from.to_string()),
+            ]))))
+        }
+    }
+
+    impl Writer for EchoStore {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+            Ok(to.clone())
+        }
+    }
+
+    /// A small alphabet, so generated mounts and queries share prefixes
+    /// often. Includes Unicode and numeric components.
+    fn component() -> impl Strategy<Value = String> {
+        prop::sample::select(vec!["a", "b", "_c", "é", "ß", "日本", "0", "1"])
+            .prop_map(String::from)
+    }
+
+    fn path(max_len: usize) -> impl Strategy<Value = Path> {
+        prop::collection::vec(component(), 0..max_len).prop_map(Path::from_components)
+    }
+
+    /// The mount a read should reach: the longest mounted prefix.
+    fn expected_route(mounts: &[Path], query: &Path) -> Option<usize> {
+        mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, mount)| query.has_prefix(mount))
+            .max_by_key(|(_, mount)| mount.len())
+            .map(|(id, _)| id)
+    }
+
+    proptest! {
+        /// Reads and writes go to the store at the longest matching prefix,
+        /// with that prefix stripped.
+        #[test]
+        fn prop_routes_to_longest_prefix(
+            mounts in prop::collection::btree_set(path(4), 1..8),
+            queries in prop::collection::vec(path(6), 1..16),
+        ) {
+            let mounts: Vec<Path> = mounts.into_iter().collect();
+            let mut overlay = OverlayStore::new();
+            for (id, mount) in mounts.iter().enumerate() {
+                overlay.mount(mount.clone(), EchoStore(id));
+            }
+            prop_assert_eq!(overlay.store_count(), mounts.len());
+
+            for query in &queries {
+                let expected = expected_route(&mounts, query);
+                prop_assert_eq!(overlay.has_route(query), expected.is_some());
+
+                match expected {
+                    Some(id) => {
+                        let suffix = query.strip_prefix(&mounts[id]).unwrap();
+                        let record = overlay.read(query).unwrap().unwrap();
+                        let value = record.into_value(&crate::NoCodec).unwrap();
+                        prop_assert_eq!(
+                            value,
+                            Value::Array(vec![
+                                Value::Integer(id as i64),
+                                Value::from(suffix.to_string()),
+                            ])
+                        );
+
+                        let written = overlay.write(query, Record::parsed(Value::Null)).unwrap();
+                        prop_assert_eq!(&written, query);
+                    }
+                    None => {
+                        let is_no_route = matches!(
+                            overlay.read(query),
+                            Err(Error::NoRoute { .. })
+                        );
+                        prop_assert!(is_no_route);
+                    }
+                }
+            }
+        }
+
+        /// Unmounting a store routes its paths to the next longest prefix.
+        #[test]
+        fn prop_unmount_falls_back(
+            mounts in prop::collection::btree_set(path(4), 2..8),
+            query in path(6),
+            remove in any::<prop::sample::Index>(),
+        ) {
+            let mut mounts: Vec<Path> = mounts.into_iter().collect();
+            let mut overlay = OverlayStore::new();
+            for (id, mount) in mounts.iter().enumerate() {
+                overlay.mount(mount.clone(), EchoStore(id));
+            }
+
+            let removed = remove.index(mounts.len());
+            prop_assert!(overlay.unmount(&mounts[removed]).is_some());
+            // Keep ids stable by replacing the removed mount with one that
+            // can never match
+            mounts[removed] = Path::from_components(vec!["unmatched".into(); 7]);
+
+            let expected = expected_route(&mounts, &query);
+            prop_assert_eq!(overlay.has_route(&query), expected.is_some());
+            if let Some(id) = expected {
+                let record = overlay.read(&query).unwrap().unwrap();
+                let value = record.into_value(&crate::NoCodec).unwrap();
+                let Value::Array(items) = value else {
+                    panic!("echo store returned a non-array");
+                };
+                prop_assert_eq!(&items[0], &Value::Integer(id as i64));
+            }
+        }
+    }
 }
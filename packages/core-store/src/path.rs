@@ -56,6 +56,8 @@ impl Path {
     /// - Components are separated by `/`
     /// - Empty components are ignored (normalizes `//` and trailing `/`)
     /// - Each component must be a valid identifier or numeric string
+    /// - `.` and `..` are rejected; [`normalize`](Self::normalize) and
+    ///   [`resolve`](Self::resolve) accept them
     ///
     /// # Examples
     ///
@@ -120,6 +122,15 @@ impl Path {
             });
         }
 
+        if component == "." || component == ".." {
+            return Err(PathError::InvalidComponent {
+                component: component.to_string(),
+                position,
+                message: "relative segment; resolve it with Path::normalize or Path::resolve"
+                    .to_string(),
+            });
+        }
+
         // Allow pure numeric strings (for array indexing)
         if component.chars().all(|c| c.is_ascii_digit()) {
             return Ok(());
@@ -160,6 +171,90 @@ impl Path {
         Ok(())
     }
 
+    /// Parse a path string, resolving `.` and `..` segments.
+    ///
+    /// `.` is dropped and `..` removes the component before it. A `..`
+    /// that would go above the root is an error.
+    ///
+    /// ```rust
+    /// use structfs_core_store::{path, Path};
+    ///
+    /// assert_eq!(Path::normalize("users/./alice/../bob").unwrap(), path!("users/bob"));
+    /// assert!(Path::normalize("users/../..").is_err());
+    /// ```
+    pub fn normalize(s: &str) -> Result<Self, PathError> {
+        Path {
+            components: Vec::new(),
+        }
+        .resolve(s)
+    }
+
+    /// Resolve a path string against this one, as a shell resolves `cd`
+    /// against the working directory.
+    ///
+    /// The string is relative to this path unless it starts with `/`, in
+    /// which case it is relative to the root. `.` and `..` are resolved as
+    /// in [`normalize`](Self::normalize).
+    ///
+    /// ```rust
+    /// use structfs_core_store::path;
+    ///
+    /// let cwd = path!("users/alice");
+    /// assert_eq!(cwd.resolve("..").unwrap(), path!("users"));
+    /// assert_eq!(cwd.resolve("../bob/email").unwrap(), path!("users/bob/email"));
+    /// assert_eq!(cwd.resolve("/config").unwrap(), path!("config"));
+    /// ```
+    pub fn resolve(&self, s: &str) -> Result<Self, PathError> {
+        let mut components = if s.starts_with('/') {
+            Vec::new()
+        } else {
+            self.components.clone()
+        };
+        for (position, segment) in s.split('/').filter(|c| !c.is_empty()).enumerate() {
+            match segment {
+                "." => {}
+                ".." => {
+                    if components.pop().is_none() {
+                        return Err(PathError::InvalidPath {
+                            message: format!("'{}' goes above the root", s),
+                        });
+                    }
+                }
+                _ => {
+                    Self::validate_component(segment, position)?;
+                    components.push(segment.to_string());
+                }
+            }
+        }
+        Ok(Path { components })
+    }
+
+    /// The relative path string leading from `base` to this path, so that
+    /// `base.resolve(&path.relative_to(&base))` gives this path back.
+    ///
+    /// ```rust
+    /// use structfs_core_store::path;
+    ///
+    /// assert_eq!(path!("users/bob").relative_to(&path!("users/alice")), "../bob");
+    /// assert_eq!(path!("users/alice").relative_to(&path!("users/alice")), ".");
+    /// ```
+    pub fn relative_to(&self, base: &Path) -> String {
+        let common = self
+            .components
+            .iter()
+            .zip(&base.components)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let segments: Vec<&str> = std::iter::repeat_n("..", base.len() - common)
+            .chain(self.components[common..].iter().map(String::as_str))
+            .collect();
+        if segments.is_empty() {
+            ".".to_string()
+        } else {
+            segments.join("/")
+        }
+    }
+
     /// Check if this path is empty (root path).
     pub fn is_empty(&self) -> bool {
         self.components.is_empty()
@@ -210,6 +305,13 @@ impl Path {
         }
     }
 
+    /// Whether this path matches a glob pattern such as `users/*/email`.
+    ///
+    /// See [`PathPattern`](crate::PathPattern).
+    pub fn matches(&self, pattern: &crate::PathPattern) -> bool {
+        pattern.matches(self)
+    }
+
     /// Convert to LL path (byte components).
     pub fn to_ll_path(&self) -> structfs_ll_store::LLPath {
         self.components
@@ -317,6 +419,38 @@ mod tests {
         assert!(Path::parse("foo/123abc").is_err()); // starts with digit but not pure numeric
     }
 
+    #[test]
+    fn relative_segments() {
+        match Path::parse("users/../bob") {
+            Err(PathError::InvalidComponent {
+                position, message, ..
+            }) => {
+                assert_eq!(position, 1);
+                assert!(message.contains("relative segment"), "{}", message);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(Path::normalize("./a/b/./../c/").unwrap(), path!("a/c"));
+        assert_eq!(Path::normalize("a/..").unwrap(), path!(""));
+        assert!(matches!(
+            Path::normalize(".."),
+            Err(PathError::InvalidPath { .. })
+        ));
+        assert!(Path::normalize("a/../b-c").is_err());
+
+        let cwd = path!("a/b/c");
+        assert_eq!(cwd.resolve("").unwrap(), cwd);
+        assert_eq!(cwd.resolve("../../x/./y").unwrap(), path!("a/x/y"));
+        assert_eq!(cwd.resolve("/").unwrap(), path!(""));
+        assert_eq!(cwd.resolve("/x/../y").unwrap(), path!("y"));
+        assert!(cwd.resolve("../../../..").is_err());
+
+        assert_eq!(path!("a/x/y").relative_to(&cwd), "../../x/y");
+        assert_eq!(path!("a/b/c/d").relative_to(&cwd), "d");
+        assert_eq!(path!("").relative_to(&cwd), "../../..");
+        assert_eq!(cwd.relative_to(&path!("")), "a/b/c");
+    }
+
     #[test]
     fn has_prefix_works() {
         let p = path!("foo/bar/baz");
@@ -535,3 +669,112 @@ mod tests {
         assert!(debug.contains("bar"));
     }
 }
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Components the validator accepts: ASCII and Unicode identifiers,
+    /// underscore-prefixed names and array indices.
+    fn valid_component() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-zA-Z][a-zA-Z0-9_]{0,8}",
+            "_[a-zA-Z0-9][a-zA-Z0-9_]{0,4}",
+            "[0-9]{1,6}",
+            "\\p{XID_Start}\\p{XID_Continue}{0,4}",
+        ]
+        .prop_filter("rejected by the validator", |c| {
+            Path::try_from_components(vec![c.clone()]).is_ok()
+        })
+    }
+
+    fn valid_path(max_len: usize) -> impl Strategy<Value = Path> {
+        prop::collection::vec(valid_component(), 0..max_len).prop_map(Path::from_components)
+    }
+
+    /// Strings likely to trip up parsing: separators, whitespace, control
+    /// characters, combining marks and other non-identifier characters.
+    fn adversarial_string() -> impl Strategy<Value = String> {
+        prop_oneof![
+            ".*",
+            "[/_a0 .\\-\\u{0}\\u{301}\\u{200d}é]{0,12}",
+            "(/{0,3}[a-z_0-9]{0,3}){0,6}",
+        ]
+    }
+
+    proptest! {
+        /// Parsing never panics, and a successful parse has only valid,
+        /// non-empty components.
+        #[test]
+        fn prop_parse_total(s in adversarial_string()) {
+            if let Ok(path) = Path::parse(&s) {
+                for component in path.iter() {
+                    prop_assert!(!component.is_empty());
+                    prop_assert!(!component.contains('/'));
+                }
+                prop_assert!(Path::try_from_components(path.components.clone()).is_ok());
+            }
+        }
+
+        /// Display and parse are inverses.
+        #[test]
+        fn prop_display_roundtrip(path in valid_path(12)) {
+            prop_assert_eq!(Path::parse(&path.to_string()).unwrap(), path);
+        }
+
+        /// Parse agrees with the component validator: a single segment parses
+        /// exactly when it is a valid component.
+        #[test]
+        fn prop_parse_matches_validator(s in adversarial_string()) {
+            if !s.is_empty() && !s.contains('/') {
+                let parsed = Path::parse(&s).ok();
+                let built = Path::try_from_components(vec![s.clone()]).ok();
+                prop_assert_eq!(parsed, built);
+            }
+        }
+
+        /// Leading, trailing and repeated separators are normalized away.
+        #[test]
+        fn prop_separators_normalized(
+            path in valid_path(8),
+            seps in prop::collection::vec(1usize..4, 9),
+            lead in 0usize..3,
+        ) {
+            let mut s = "/".repeat(lead);
+            for (component, n) in path.iter().zip(&seps) {
+                s.push_str(component);
+                s.push_str(&"/".repeat(*n));
+            }
+            prop_assert_eq!(Path::parse(&s).unwrap(), path);
+        }
+
+        /// Join, slice and prefix operations agree with each other.
+        #[test]
+        fn prop_join_slice_prefix(a in valid_path(8), b in valid_path(8)) {
+            let joined = a.join(&b);
+            prop_assert_eq!(joined.len(), a.len() + b.len());
+            prop_assert_eq!(joined.slice(0, a.len()), a.clone());
+            prop_assert_eq!(joined.slice(a.len(), joined.len()), b.clone());
+            prop_assert!(joined.has_prefix(&a));
+            prop_assert_eq!(joined.strip_prefix(&a), Some(b));
+            prop_assert_eq!(Path::parse(&joined.to_string()).unwrap(), joined);
+        }
+
+        /// Resolving a path's relative form against its base gives it back.
+        #[test]
+        fn prop_relative_roundtrip(path in valid_path(8), base in valid_path(8)) {
+            let relative = path.relative_to(&base);
+            prop_assert_eq!(base.resolve(&relative).unwrap(), path);
+        }
+
+        /// Very deep paths survive the same operations.
+        #[test]
+        fn prop_deep_paths(path in valid_path(512)) {
+            let half = path.len() / 2;
+            let rejoined = path.slice(0, half).join(&path.slice(half, path.len()));
+            prop_assert_eq!(&rejoined, &path);
+            prop_assert_eq!(Path::parse(&path.to_string()).unwrap(), path);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Glob patterns over paths.
+//!
+//! A [`PathPattern`] is a path whose segments may be wildcards:
+//!
+//! | Segment | Matches |
+//! |---------|---------|
+//! | `users` | exactly that component |
+//! | `*` | any one component |
+//! | `**` | any number of components, including none |
+//! | `{alice,bob}` | any one of the listed components |
+//!
+//! ```rust
+//! use structfs_core_store::{path, PathPattern};
+//!
+//! let pattern = PathPattern::parse("users/*/{email,phone}").unwrap();
+//! assert!(path!("users/alice/email").matches(&pattern));
+//! assert!(!path!("users/alice/name").matches(&pattern));
+//!
+//! let logs = PathPattern::parse("services/**/logs").unwrap();
+//! assert!(path!("services/logs").matches(&logs));
+//! assert!(path!("services/api/v2/logs").matches(&logs));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Path, PathError};
+
+/// One segment of a [`PathPattern`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `*`
+    Any,
+    /// `**`
+    AnyDepth,
+    /// `{a,b}`
+    OneOf(Vec<String>),
+}
+
+impl Segment {
+    fn accepts(&self, component: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => literal == component,
+            Segment::Any | Segment::AnyDepth => true,
+            Segment::OneOf(options) => options.iter().any(|o| o == component),
+        }
+    }
+}
+
+/// A path with wildcard segments, matched with [`Path::matches`].
+///
+/// See the [module documentation](crate::path_pattern) for the syntax.
+/// Literal components follow the same rules as [`Path`] components.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+impl PathPattern {
+    /// Parse a pattern such as `users/*/{email,phone}`.
+    ///
+    /// As with [`Path::parse`], empty segments are ignored, so the empty
+    /// pattern matches only the root.
+    pub fn parse(pattern: &str) -> Result<Self, PathError> {
+        let segments = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .enumerate()
+            .map(|(position, segment)| parse_segment(segment, position))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { segments })
+    }
+
+    /// Whether `path` matches the whole pattern.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.states_after(path).last().copied().unwrap_or_default()
+    }
+
+    /// Whether `path` or some path below it could match.
+    ///
+    /// Walks over a tree can skip the subtrees this rules out.
+    pub fn may_match_below(&self, path: &Path) -> bool {
+        self.states_after(path).contains(&true)
+    }
+
+    /// The literal segments the pattern starts with: the deepest path every
+    /// match lies at or below.
+    pub fn literal_prefix(&self) -> Path {
+        let components = self
+            .segments
+            .iter()
+            .map_while(|segment| match segment {
+                Segment::Literal(literal) => Some(literal.clone()),
+                _ => None,
+            })
+            .collect();
+        Path::from_components(components)
+    }
+
+    /// Whether the pattern has no wildcards, and so matches one path.
+    pub fn is_literal(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|segment| matches!(segment, Segment::Literal(_)))
+    }
+
+    /// Which prefixes of the pattern `path` can have consumed: entry `i`
+    /// is true when the first `i` segments can match the whole path.
+    fn states_after(&self, path: &Path) -> Vec<bool> {
+        let mut states = vec![false; self.segments.len() + 1];
+        states[0] = true;
+        self.close(&mut states);
+        for component in path.iter() {
+            let mut next = vec![false; states.len()];
+            for (i, segment) in self.segments.iter().enumerate() {
+                if states[i] && segment.accepts(component) {
+                    // `**` can go on consuming; everything else moves on.
+                    let to = if *segment == Segment::AnyDepth {
+                        i
+                    } else {
+                        i + 1
+                    };
+                    next[to] = true;
+                }
+            }
+            self.close(&mut next);
+            if !next.contains(&true) {
+                return next;
+            }
+            states = next;
+        }
+        states
+    }
+
+    /// `**` can also match nothing: skip over it.
+    fn close(&self, states: &mut [bool]) {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if states[i] && *segment == Segment::AnyDepth {
+                states[i + 1] = true;
+            }
+        }
+    }
+}
+
+fn parse_segment(segment: &str, position: usize) -> Result<Segment, PathError> {
+    let invalid = |message: &str| PathError::InvalidComponent {
+        component: segment.to_string(),
+        position,
+        message: message.to_string(),
+    };
+    let literal = |component: &str| {
+        Path::try_from_components(vec![component.to_string()])
+            .map(|_| component.to_string())
+            .map_err(|_| invalid("must be a valid path component, '*', '**' or '{a,b}'"))
+    };
+    match segment {
+        "*" => Ok(Segment::Any),
+        "**" => Ok(Segment::AnyDepth),
+        _ if segment.starts_with('{') => {
+            let inner = segment
+                .strip_prefix('{')
+                .and_then(|s| s.strip_suffix('}'))
+                .ok_or_else(|| invalid("unclosed '{'"))?;
+            let options = inner
+                .split(',')
+                .map(literal)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Segment::OneOf(options))
+        }
+        _ => literal(segment).map(Segment::Literal),
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: Vec<String> = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(literal) => literal.clone(),
+                Segment::Any => "*".to_string(),
+                Segment::AnyDepth => "**".to_string(),
+                Segment::OneOf(options) => format!("{{{}}}", options.join(",")),
+            })
+            .collect();
+        write!(f, "{}", segments.join("/"))
+    }
+}
+
+impl From<&Path> for PathPattern {
+    /// The pattern matching exactly `path`.
+    fn from(path: &Path) -> Self {
+        Self {
+            segments: path.iter().cloned().map(Segment::Literal).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+
+    fn pattern(s: &str) -> PathPattern {
+        PathPattern::parse(s).unwrap()
+    }
+
+    #[test]
+    fn single_segment_wildcards() {
+        let p = pattern("users/*/email");
+        assert!(path!("users/alice/email").matches(&p));
+        assert!(path!("users/0/email").matches(&p));
+        assert!(!path!("users/email").matches(&p));
+        assert!(!path!("users/alice/bob/email").matches(&p));
+
+        let p = pattern("{users,admins}/{0,1}");
+        assert!(path!("admins/1").matches(&p));
+        assert!(!path!("guests/1").matches(&p));
+        assert!(!path!("users/2").matches(&p));
+    }
+
+    #[test]
+    fn any_depth() {
+        let p = pattern("a/**/z");
+        assert!(path!("a/z").matches(&p));
+        assert!(path!("a/b/z").matches(&p));
+        assert!(path!("a/b/c/z").matches(&p));
+        assert!(!path!("a/b/c").matches(&p));
+        assert!(!path!("b/z").matches(&p));
+
+        let everything = pattern("**");
+        assert!(path!("").matches(&everything));
+        assert!(path!("x/y").matches(&everything));
+
+        let p = pattern("**/id/**/id");
+        assert!(path!("id/id").matches(&p));
+        assert!(path!("a/id/b/c/id").matches(&p));
+        assert!(!path!("a/id/b").matches(&p));
+    }
+
+    #[test]
+    fn pruning_and_prefixes() {
+        let p = pattern("users/*/email");
+        assert!(p.may_match_below(&path!("")));
+        assert!(p.may_match_below(&path!("users/alice")));
+        assert!(!p.may_match_below(&path!("orders")));
+        assert!(!p.may_match_below(&path!("users/alice/email/extra")));
+        assert_eq!(p.literal_prefix(), path!("users"));
+        assert!(!p.is_literal());
+
+        let exact = PathPattern::from(&path!("a/b"));
+        assert!(exact.is_literal());
+        assert_eq!(exact.literal_prefix(), path!("a/b"));
+        assert!(path!("a/b").matches(&exact));
+    }
+
+    #[test]
+    fn parse_errors_and_display() {
+        for bad in ["users/{a,b", "users/a-b", "{a,}", "x/***"] {
+            assert!(PathPattern::parse(bad).is_err(), "{} should not parse", bad);
+        }
+        match PathPattern::parse("users/a-b") {
+            Err(PathError::InvalidComponent { position, .. }) => assert_eq!(position, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+        let text = "users/**/{email,phone}/*";
+        assert_eq!(pattern(text).to_string(), text);
+        assert_eq!(
+            "/users//*/".parse::<PathPattern>().unwrap(),
+            pattern("users/*")
+        );
+    }
+}
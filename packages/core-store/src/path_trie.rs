@@ -69,8 +69,13 @@ impl<T> PathTrie<T> {
 
     /// Navigate to node if it exists (mutable).
     fn get_node_mut(&mut self, path: &Path) -> Option<&mut PathTrie<T>> {
+        self.get_node_mut_prefix(&path.components)
+    }
+
+    /// Navigate to the node for a slice of components (mutable).
+    fn get_node_mut_prefix(&mut self, components: &[String]) -> Option<&mut PathTrie<T>> {
         let mut current = self;
-        for component in &path.components {
+        for component in components {
             current = current.children.get_mut(component)?;
         }
         Some(current)
@@ -146,66 +151,64 @@ impl<T> PathTrie<T> {
     /// Find deepest ancestor with a value.
     /// Returns (value_ref, remaining_suffix).
     pub fn find_ancestor(&self, path: &Path) -> Option<(&T, Path)> {
+        self.find_ancestor_depth(path).map(|(v, depth)| {
+            let suffix = Path {
+                components: path.components[depth..].to_vec(),
+            };
+            (v, suffix)
+        })
+    }
+
+    /// Find deepest ancestor with a value without allocating.
+    /// Returns (value_ref, number of path components the ancestor covers).
+    pub fn find_ancestor_depth(&self, path: &Path) -> Option<(&T, usize)> {
         let mut current = self;
-        let mut last_value: Option<&T> = self.value.as_ref();
-        let mut last_depth: usize = 0;
+        let mut last: Option<(&T, usize)> = self.value.as_ref().map(|v| (v, 0));
 
         for (depth, component) in path.components.iter().enumerate() {
             match current.children.get(component) {
                 Some(child) => {
                     current = child;
-                    if child.value.is_some() {
-                        last_value = child.value.as_ref();
-                        last_depth = depth + 1;
+                    if let Some(value) = &child.value {
+                        last = Some((value, depth + 1));
                     }
                 }
                 None => break,
             }
         }
 
-        last_value.map(|v| {
-            let suffix = Path {
-                components: path.components[last_depth..].to_vec(),
-            };
-            (v, suffix)
-        })
+        last
     }
 
-    /// Mutable version of find_ancestor.
-    /// Due to borrow checker constraints, this uses a two-pass approach.
-    pub fn find_ancestor_mut(&mut self, path: &Path) -> Option<(&mut T, Path)> {
-        // First pass: find the depth
-        let depth = {
-            let mut current = &*self;
-            let mut last_depth: usize = if self.value.is_some() { 0 } else { usize::MAX };
-
-            for (d, component) in path.components.iter().enumerate() {
-                match current.children.get(component) {
-                    Some(child) => {
-                        current = child;
-                        if child.value.is_some() {
-                            last_depth = d + 1;
-                        }
-                    }
-                    None => break,
-                }
-            }
+    /// Mutable version of find_ancestor_depth, in a single walk.
+    pub fn find_ancestor_depth_mut(&mut self, path: &Path) -> Option<(&mut T, usize)> {
+        let mut current = self;
+        let mut last: Option<(&mut T, usize)> = None;
 
-            if last_depth == usize::MAX {
-                return None;
+        for depth in 0..=path.len() {
+            let PathTrie { value, children } = current;
+            if let Some(value) = value {
+                last = Some((value, depth));
             }
-            last_depth
-        };
+            match path.components.get(depth).and_then(|c| children.get_mut(c)) {
+                Some(child) => current = child,
+                None => break,
+            }
+        }
 
-        // Second pass: get mutable reference
-        let target_path = Path {
-            components: path.components[..depth].to_vec(),
-        };
+        last
+    }
+
+    /// Mutable version of find_ancestor.
+    pub fn find_ancestor_mut(&mut self, path: &Path) -> Option<(&mut T, Path)> {
+        let (_, depth) = self.find_ancestor_depth(path)?;
         let suffix = Path {
             components: path.components[depth..].to_vec(),
         };
-
-        self.get_mut(&target_path).map(|v| (v, suffix))
+        self.get_node_mut_prefix(&path.components[..depth])?
+            .value
+            .as_mut()
+            .map(|v| (v, suffix))
     }
 
     /// Iterate over all (path, value) pairs.
@@ -439,6 +442,38 @@ mod tests {
         assert!(suffix.is_empty());
     }
 
+    #[test]
+    fn find_ancestor_depth() {
+        let mut trie: PathTrie<i32> = PathTrie::new();
+        trie.insert(&path!("a"), 1);
+        trie.insert(&path!("a/b/c"), 2);
+
+        assert_eq!(trie.find_ancestor_depth(&path!("a/b/c/d")), Some((&2, 3)));
+        assert_eq!(trie.find_ancestor_depth(&path!("a/b/x")), Some((&1, 1)));
+        assert_eq!(trie.find_ancestor_depth(&path!("z")), None);
+    }
+
+    #[test]
+    fn find_ancestor_depth_mut() {
+        let mut trie: PathTrie<i32> = PathTrie::new();
+        trie.insert(&path!(""), 0);
+        trie.insert(&path!("a/b"), 2);
+
+        let (value, depth) = trie.find_ancestor_depth_mut(&path!("a/b/c")).unwrap();
+        assert_eq!((*value, depth), (2, 2));
+        *value = 42;
+        assert_eq!(trie.get(&path!("a/b")), Some(&42));
+
+        assert_eq!(
+            trie.find_ancestor_depth_mut(&path!("a/x")),
+            Some((&mut 0, 0))
+        );
+        assert_eq!(
+            trie.find_ancestor_depth_mut(&path!("a/b")),
+            Some((&mut 42, 2))
+        );
+    }
+
     #[test]
     fn find_ancestor_mut() {
         let mut trie: PathTrie<i32> = PathTrie::new();
@@ -470,7 +505,7 @@ mod tests {
         trie.insert(&path!("a/c"), 3);
 
         let mut items: Vec<_> = trie.iter().collect();
-        items.sort_by(|a, b| a.0.to_string().cmp(&b.0.to_string()));
+        items.sort_by_key(|a| a.0.to_string());
 
         assert_eq!(items.len(), 3);
         assert_eq!(items[0], (path!("a"), &1));
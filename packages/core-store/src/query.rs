@@ -0,0 +1,486 @@
+//! Filtered reads of the entries below a path.
+//!
+//! [`Store::read_query`] returns the entries directly below a prefix whose
+//! values pass a [`Filter`]: field comparisons and string prefix matches,
+//! all of which must hold, then an offset and a limit.
+//!
+//! ```rust,ignore
+//! let filter = Filter::new()
+//!     .equals("status", "open")
+//!     .greater_than("priority", 2)
+//!     .starts_with("owner", "team-")
+//!     .with_limit(20);
+//! for (name, record) in store.read_query(&path!("tickets"), &filter)?.unwrap_or_default() {
+//!     // name is e.g. "1042", record the ticket
+//! }
+//! ```
+//!
+//! Stores that can run a filter themselves, such as a SQL table, a Redis
+//! index or an S3 listing, implement [`Querier`] and return it from
+//! [`Reader::as_querier`]. For every other store, and for filters a
+//! querier does not [support](Querier::supports), the query is evaluated
+//! by [`evaluate`]: each entry is listed and read, and the filter is
+//! applied to its value.
+//!
+//! A field is a path into an entry's value; the empty path is the value
+//! itself. A condition on a field an entry does not have fails, whatever
+//! its comparison, as `NULL` does in SQL.
+
+use std::cmp::Ordering;
+
+use crate::traits::list_children;
+use crate::{Error, NoCodec, Path, Reader, Record, Value};
+
+/// How a field is compared with a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// Equal. Integers and floats compare by value, so `1` equals `1.0`.
+    Eq,
+    /// Not equal.
+    Ne,
+    /// Less than.
+    Lt,
+    /// Less than or equal.
+    Le,
+    /// Greater than.
+    Gt,
+    /// Greater than or equal.
+    Ge,
+}
+
+/// One test an entry's value must pass.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// The field compares with `value` as `op` says.
+    ///
+    /// Ordering comparisons hold only between two numbers or two strings,
+    /// which compare by code point.
+    Compare {
+        /// The field, relative to the entry.
+        field: Path,
+        /// The comparison.
+        op: Comparison,
+        /// The value compared with.
+        value: Value,
+    },
+    /// The field is a string starting with `prefix`.
+    Prefix {
+        /// The field, relative to the entry.
+        field: Path,
+        /// The prefix.
+        prefix: String,
+    },
+}
+
+impl Condition {
+    /// Whether `entry` passes this condition.
+    pub fn matches(&self, entry: &Value) -> bool {
+        match self {
+            Condition::Compare { field, op, value } => match entry.get(field) {
+                Some(found) => compare(found, *op, value),
+                None => false,
+            },
+            Condition::Prefix { field, prefix } => {
+                matches!(entry.get(field), Some(Value::String(s)) if s.starts_with(prefix.as_str()))
+            }
+        }
+    }
+}
+
+fn compare(found: &Value, op: Comparison, value: &Value) -> bool {
+    let ordering = match (found, value) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match (op, ordering) {
+        (Comparison::Eq, Some(o)) => o == Ordering::Equal,
+        (Comparison::Eq, None) => found == value,
+        (Comparison::Ne, Some(o)) => o != Ordering::Equal,
+        (Comparison::Ne, None) => found != value,
+        (Comparison::Lt, Some(o)) => o == Ordering::Less,
+        (Comparison::Le, Some(o)) => o != Ordering::Greater,
+        (Comparison::Gt, Some(o)) => o == Ordering::Greater,
+        (Comparison::Ge, Some(o)) => o != Ordering::Less,
+        (_, None) => false,
+    }
+}
+
+/// Which entries below a prefix a query returns.
+///
+/// Entries pass if they meet every condition; of those, the first
+/// `offset` are skipped and at most `limit` returned, in the store's
+/// listing order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    conditions: Vec<Condition>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl Filter {
+    /// A filter every entry passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a condition.
+    pub fn with(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Add a comparison of `field`, a path such as `address/city`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a valid path; use [`with`](Self::with) for
+    /// fields built at runtime.
+    pub fn compare(self, field: &str, op: Comparison, value: impl Into<Value>) -> Self {
+        self.with(Condition::Compare {
+            field: field_path(field),
+            op,
+            value: value.into(),
+        })
+    }
+
+    /// `field` equals `value`.
+    pub fn equals(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Eq, value)
+    }
+
+    /// `field` does not equal `value`.
+    pub fn not_equals(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Ne, value)
+    }
+
+    /// `field` is less than `value`.
+    pub fn less_than(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Lt, value)
+    }
+
+    /// `field` is at most `value`.
+    pub fn at_most(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Le, value)
+    }
+
+    /// `field` is greater than `value`.
+    pub fn greater_than(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Gt, value)
+    }
+
+    /// `field` is at least `value`.
+    pub fn at_least(self, field: &str, value: impl Into<Value>) -> Self {
+        self.compare(field, Comparison::Ge, value)
+    }
+
+    /// `field` is a string starting with `prefix`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a valid path.
+    pub fn starts_with(self, field: &str, prefix: impl Into<String>) -> Self {
+        self.with(Condition::Prefix {
+            field: field_path(field),
+            prefix: prefix.into(),
+        })
+    }
+
+    /// Return at most `limit` entries.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` entries that pass.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// The conditions, all of which an entry must meet.
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// The most entries to return, if limited.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// How many passing entries to skip.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Whether `entry` meets every condition. Offset and limit are not
+    /// considered.
+    pub fn matches(&self, entry: &Value) -> bool {
+        self.conditions.iter().all(|c| c.matches(entry))
+    }
+}
+
+fn field_path(field: &str) -> Path {
+    Path::parse(field).unwrap_or_else(|e| panic!("invalid filter field '{}': {}", field, e))
+}
+
+/// Stores that run filters natively.
+///
+/// Implementors should also override [`Reader::as_querier`] so that
+/// [`Store::read_query`](crate::Store::read_query) finds the native
+/// support behind `dyn Store`.
+///
+/// # Object Safety
+///
+/// This trait is object-safe: you can use `Box<dyn Querier>`.
+pub trait Querier: Send + Sync {
+    /// Whether [`query`](Self::query) can run `filter`. Filters it cannot
+    /// are [`evaluate`]d instead.
+    fn supports(&self, filter: &Filter) -> bool {
+        let _ = filter;
+        true
+    }
+
+    /// The entries directly below `prefix` that pass `filter`, by name, as
+    /// [`evaluate`] would return them.
+    fn query(
+        &mut self,
+        prefix: &Path,
+        filter: &Filter,
+    ) -> Result<Option<Vec<(String, Record)>>, Error>;
+}
+
+/// Run a query by listing and reading each entry below `prefix`.
+///
+/// Returns `Ok(None)` where `prefix` does not exist, like
+/// [`Reader::read`]. Entries are read one at a time, and reading stops
+/// once `limit` entries have passed. Entries must read as parsed values;
+/// a raw record fails with a codec error, as this layer has no codecs.
+pub fn evaluate<R: Reader + ?Sized>(
+    reader: &mut R,
+    prefix: &Path,
+    filter: &Filter,
+) -> Result<Option<Vec<(String, Record)>>, Error> {
+    let Some(names) = list_children(reader, prefix)? else {
+        return Ok(None);
+    };
+    let mut found = Vec::new();
+    let mut skip = filter.offset;
+    for name in names {
+        if filter.limit.is_some_and(|limit| found.len() >= limit) {
+            break;
+        }
+        let path = prefix.join(&Path::from_components(vec![name.clone()]));
+        let Some(record) = reader.read(&path)? else {
+            continue;
+        };
+        let value = record.into_value(&NoCodec)?;
+        if !filter.matches(&value) {
+            continue;
+        }
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        found.push((name, Record::parsed(value)));
+    }
+    Ok(Some(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Store, Writer};
+    use std::collections::BTreeMap;
+
+    /// Serves reads from a fixed value, counting them.
+    struct ValueStore {
+        data: Value,
+        reads: usize,
+    }
+
+    impl Reader for ValueStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            self.reads += 1;
+            Ok(self.data.get(from).cloned().map(Record::parsed))
+        }
+    }
+
+    impl Writer for ValueStore {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+            Ok(to.clone())
+        }
+    }
+
+    fn ticket(status: &str, priority: i64, owner: &str) -> Value {
+        Value::Map(BTreeMap::from([
+            ("status".to_string(), Value::from(status)),
+            ("priority".to_string(), Value::Integer(priority)),
+            ("owner".to_string(), Value::from(owner)),
+        ]))
+    }
+
+    fn tickets() -> ValueStore {
+        let tickets = BTreeMap::from([
+            ("1".to_string(), ticket("open", 1, "team-a")),
+            ("2".to_string(), ticket("open", 3, "team-b")),
+            ("3".to_string(), ticket("closed", 5, "team-a")),
+            ("4".to_string(), ticket("open", 4, "ops")),
+            ("5".to_string(), ticket("open", 5, "team-c")),
+            ("6".to_string(), Value::from("not a ticket")),
+        ]);
+        ValueStore {
+            data: Value::Map(BTreeMap::from([(
+                "tickets".to_string(),
+                Value::Map(tickets),
+            )])),
+            reads: 0,
+        }
+    }
+
+    fn names(found: Option<Vec<(String, Record)>>) -> Vec<String> {
+        found.unwrap().into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn comparisons() {
+        let entry = ticket("open", 3, "team-a");
+        let passes = |filter: Filter| filter.matches(&entry);
+
+        assert!(passes(Filter::new()));
+        assert!(passes(Filter::new().equals("status", "open")));
+        assert!(passes(Filter::new().equals("priority", 3.0)));
+        assert!(!passes(Filter::new().not_equals("priority", 3)));
+        assert!(passes(Filter::new().less_than("priority", 4)));
+        assert!(passes(Filter::new().at_most("priority", 3)));
+        assert!(passes(Filter::new().greater_than("priority", 2.5)));
+        assert!(passes(Filter::new().at_least("owner", "team")));
+        assert!(passes(Filter::new().starts_with("owner", "team-")));
+        assert!(!passes(Filter::new().starts_with("priority", "3")));
+
+        // Mismatched types and missing fields never pass an ordering
+        assert!(!passes(Filter::new().greater_than("status", 1)));
+        assert!(!passes(Filter::new().not_equals("missing", 1)));
+        assert!(passes(Filter::new().not_equals("status", 1)));
+
+        // Every condition must hold
+        assert!(!passes(
+            Filter::new()
+                .equals("status", "open")
+                .equals("owner", "ops")
+        ));
+
+        // The empty field is the entry itself
+        let scalar = Value::Integer(7);
+        assert!(Filter::new().greater_than("", 5).matches(&scalar));
+    }
+
+    #[test]
+    fn evaluates_by_reading_entries() {
+        let mut store = tickets();
+        let open = Filter::new().equals("status", "open");
+        assert_eq!(
+            names(store.read_query(&path!("tickets"), &open).unwrap()),
+            ["1", "2", "4", "5"]
+        );
+
+        let page = open
+            .clone()
+            .greater_than("priority", 1)
+            .with_offset(1)
+            .with_limit(1);
+        let found = store.read_query(&path!("tickets"), &page).unwrap().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "4");
+        assert_eq!(
+            found[0].1.clone().into_value(&NoCodec).unwrap(),
+            ticket("open", 4, "ops")
+        );
+
+        // Reading stops once the limit is reached
+        store.reads = 0;
+        let first = store
+            .read_query(&path!("tickets"), &open.with_limit(1))
+            .unwrap();
+        assert_eq!(names(first), ["1"]);
+        assert_eq!(store.reads, 2);
+
+        assert!(store
+            .read_query(&path!("missing"), &Filter::new())
+            .unwrap()
+            .is_none());
+    }
+
+    /// Runs filters with no conditions natively, leaving the rest to
+    /// evaluation.
+    struct Paged {
+        inner: ValueStore,
+        native: usize,
+    }
+
+    impl Reader for Paged {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            self.inner.read(from)
+        }
+
+        fn as_querier(&mut self) -> Option<&mut dyn Querier> {
+            Some(self)
+        }
+    }
+
+    impl Writer for Paged {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.inner.write(to, data)
+        }
+    }
+
+    impl Querier for Paged {
+        fn supports(&self, filter: &Filter) -> bool {
+            filter.conditions().is_empty()
+        }
+
+        fn query(
+            &mut self,
+            prefix: &Path,
+            filter: &Filter,
+        ) -> Result<Option<Vec<(String, Record)>>, Error> {
+            self.native += 1;
+            evaluate(&mut self.inner, prefix, filter)
+        }
+    }
+
+    #[test]
+    fn pushes_down_supported_filters() {
+        let mut store = Paged {
+            inner: tickets(),
+            native: 0,
+        };
+        let page = Filter::new().with_offset(4);
+        assert_eq!(
+            names(store.read_query(&path!("tickets"), &page).unwrap()),
+            ["5", "6"]
+        );
+        assert_eq!(store.native, 1);
+
+        let open = Filter::new().equals("status", "open");
+        assert_eq!(
+            names(store.read_query(&path!("tickets"), &open).unwrap()),
+            ["1", "2", "4", "5"]
+        );
+        assert_eq!(store.native, 1);
+
+        // Found behind dyn Store too
+        let boxed: &mut dyn Store = &mut store;
+        boxed.read_query(&path!("tickets"), &page).unwrap();
+        assert_eq!(store.native, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid filter field")]
+    fn builder_rejects_invalid_fields() {
+        let _ = Filter::new().equals("room-1", 1);
+    }
+}
@@ -2,6 +2,7 @@
 
 use bytes::Bytes;
 
+use crate::limits::RecordLimits;
 use crate::{Codec, Error, Format, Value};
 
 /// A record that can be forwarded without parsing or parsed for inspection.
@@ -112,6 +113,17 @@ impl Record {
         }
     }
 
+    /// Rough number of bytes this record holds in memory.
+    ///
+    /// Raw records count their bytes plus a word, parsed records their
+    /// value's [`estimated_size`](Value::estimated_size).
+    pub fn estimated_size(&self) -> usize {
+        match self {
+            Record::Raw { bytes, .. } => std::mem::size_of::<usize>() + bytes.len(),
+            Record::Parsed(value) => value.estimated_size(),
+        }
+    }
+
     // === Conversion (potentially costly) ===
 
     /// Parse into a Value.
@@ -127,6 +139,30 @@ impl Record {
         }
     }
 
+    /// Parse into a Value, enforcing `limits`.
+    ///
+    /// Raw records have their size checked before the codec runs, and the
+    /// decoded value is then checked for map keys and depth. Parsed records
+    /// are checked against every limit.
+    pub fn into_value_limited(
+        self,
+        codec: &dyn Codec,
+        limits: &RecordLimits,
+    ) -> Result<Value, Error> {
+        match self {
+            Record::Parsed(v) => {
+                limits.check(&v)?;
+                Ok(v)
+            }
+            Record::Raw { bytes, format } => {
+                limits.check_bytes(bytes.len())?;
+                let value = codec.decode(&bytes, &format)?;
+                limits.check_shape(&value)?;
+                Ok(value)
+            }
+        }
+    }
+
     /// Serialize into bytes.
     ///
     /// - For `Raw` records with matching format: returns the bytes (no cost).
@@ -514,4 +550,17 @@ mod tests {
         let result = record.into_value(&codec);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn estimated_size_of_raw_and_parsed() {
+        const WORD: usize = std::mem::size_of::<usize>();
+        let raw = Record::raw(Bytes::from_static(b"{\"a\":1}"), Format::JSON);
+        assert_eq!(raw.estimated_size(), WORD + 7);
+
+        let value = Value::from("hello");
+        assert_eq!(
+            Record::parsed(value.clone()).estimated_size(),
+            value.estimated_size()
+        );
+    }
 }
@@ -7,7 +7,7 @@
 
 use std::collections::BTreeMap;
 
-use crate::Value;
+use crate::{Path, PathError, Value};
 
 /// Type information for a referenced value.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -192,6 +192,23 @@ impl Reference {
         Some(Self { path, type_info })
     }
 
+    /// The path this reference points to.
+    ///
+    /// Reference paths are from the root of the store they were read from,
+    /// except that ones starting with `./` or `../` are relative to `base`,
+    /// such as the mount or collection the reference was read from.
+    pub fn resolve(&self, base: &Path) -> Result<Path, PathError> {
+        let relative = self.path == "."
+            || self.path == ".."
+            || self.path.starts_with("./")
+            || self.path.starts_with("../");
+        if relative {
+            base.resolve(&self.path)
+        } else {
+            Path::normalize(&self.path)
+        }
+    }
+
     /// Check if a Value is a reference.
     ///
     /// A value is a reference if it's a map containing a `path` key whose
@@ -207,6 +224,25 @@ impl Reference {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::path;
+
+    #[test]
+    fn reference_resolve() {
+        let base = path!("mounts/users");
+        assert_eq!(
+            Reference::new("handles/0").resolve(&base).unwrap(),
+            path!("handles/0")
+        );
+        assert_eq!(
+            Reference::new("./alice").resolve(&base).unwrap(),
+            path!("mounts/users/alice")
+        );
+        assert_eq!(
+            Reference::new("../orders/1").resolve(&base).unwrap(),
+            path!("mounts/orders/1")
+        );
+        assert!(Reference::new("../../../x").resolve(&base).is_err());
+    }
 
     #[test]
     fn reference_new() {
@@ -0,0 +1,339 @@
+//! Read-your-writes consistency over eventually consistent stores.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::traits::delete_in;
+use crate::{Deleter, Error, Path, Reader, Record, Value, Writer};
+
+/// How long a write is overlaid on reads by default.
+pub const DEFAULT_MAX_PENDING_AGE: Duration = Duration::from_secs(60);
+
+/// A write the backend has not confirmed yet.
+struct Pending {
+    /// What was written; `None` for a null write, which deletes.
+    record: Option<Record>,
+    written_at: Instant,
+}
+
+/// A store wrapper that lets a session read its own writes.
+///
+/// Backends such as S3 may keep returning old data for a while after a
+/// write succeeds. `SessionStore` remembers each successful write and
+/// answers reads of the same path with the written record until a read from
+/// the backend returns it too, at which point the backend takes over again.
+///
+/// ```rust,ignore
+/// let mut session = SessionStore::new(s3_store);
+/// session.write(&path!("config/app"), Record::parsed(config))?;
+///
+/// // Sees the new config even if S3 still serves the old one
+/// let current = session.read(&path!("config/app"))?;
+/// ```
+///
+/// Each `SessionStore` is one session: writes made through other handles
+/// to the backend are not overlaid. Only exact paths are overlaid; a read
+/// of a parent path goes straight to the backend. A pending write is
+/// dropped after [`max_pending_age`](Self::with_max_pending_age) even if
+/// the backend never confirms it, so a session cannot hide other writers'
+/// changes forever. Expired writes are also dropped on each write, so a
+/// session that never reads its paths back does not grow without bound.
+///
+/// A write is confirmed when the backend returns an equal record: equal
+/// values, or equal bytes in the same format. If the two cannot be
+/// compared, such as raw bytes against a parsed value, any record from the
+/// backend counts as confirmation. A null write is confirmed when the
+/// backend returns nothing.
+pub struct SessionStore<S> {
+    inner: S,
+    pending: BTreeMap<Path, Pending>,
+    max_pending_age: Duration,
+}
+
+impl<S> SessionStore<S> {
+    /// Start a session over `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            pending: BTreeMap::new(),
+            max_pending_age: DEFAULT_MAX_PENDING_AGE,
+        }
+    }
+
+    /// Stop overlaying a write once it is older than `age`.
+    pub fn with_max_pending_age(mut self, age: Duration) -> Self {
+        self.max_pending_age = age;
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Paths with writes the backend has not confirmed yet.
+    pub fn pending_paths(&self) -> impl Iterator<Item = &Path> {
+        self.pending.keys()
+    }
+
+    /// End the session, forgetting all unconfirmed writes.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Unwrap the inner store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Forget writes older than `max_pending_age`.
+    fn expire(&mut self) {
+        let max_age = self.max_pending_age;
+        self.pending
+            .retain(|_, pending| pending.written_at.elapsed() < max_age);
+    }
+}
+
+/// Whether the backend's answer reflects the pending write.
+fn confirms(pending: &Option<Record>, backend: &Option<Record>) -> bool {
+    match (pending, backend) {
+        (None, None) => true,
+        (None, Some(_)) | (Some(_), None) => false,
+        (Some(written), Some(read)) => match (written, read) {
+            (Record::Parsed(a), Record::Parsed(b)) => a == b,
+            (
+                Record::Raw {
+                    bytes: a,
+                    format: fa,
+                },
+                Record::Raw {
+                    bytes: b,
+                    format: fb,
+                },
+            ) => a == b && fa == fb,
+            _ => true,
+        },
+    }
+}
+
+impl<S: Reader> Reader for SessionStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let backend = self.inner.read(from)?;
+
+        let Some(pending) = self.pending.get(from) else {
+            return Ok(backend);
+        };
+        if pending.written_at.elapsed() >= self.max_pending_age
+            || confirms(&pending.record, &backend)
+        {
+            self.pending.remove(from);
+            return Ok(backend);
+        }
+        Ok(pending.record.clone())
+    }
+}
+
+impl<S: Writer> Writer for SessionStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let record = match &data {
+            Record::Parsed(Value::Null) => None,
+            _ => Some(data.clone()),
+        };
+        let written = self.inner.write(to, data)?;
+        self.expire();
+
+        // Writes to a broker return a handle rather than the path written, so
+        // there is nothing to read back
+        if &written == to {
+            self.pending.insert(
+                written.clone(),
+                Pending {
+                    record,
+                    written_at: Instant::now(),
+                },
+            );
+        }
+        Ok(written)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Deletes in the inner store, then reads of the path, and of pending
+/// writes below it, return `None` until the delete is visible there.
+impl<S: Writer> Deleter for SessionStore<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        delete_in(&mut self.inner, path)?;
+        self.expire();
+        for (pending_path, pending) in self.pending.iter_mut() {
+            if pending_path.has_prefix(path) {
+                pending.record = None;
+                pending.written_at = Instant::now();
+            }
+        }
+        self.pending.insert(
+            path.clone(),
+            Pending {
+                record: None,
+                written_at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Format, NoCodec};
+    use std::sync::{Arc, Mutex};
+
+    /// Store whose writes become visible only when `publish` is called.
+    #[derive(Clone, Default)]
+    struct Lagging {
+        visible: Arc<Mutex<BTreeMap<Path, Record>>>,
+        queued: Arc<Mutex<Vec<(Path, Record)>>>,
+    }
+
+    impl Lagging {
+        fn publish(&self) {
+            let mut visible = self.visible.lock().unwrap();
+            for (path, record) in self.queued.lock().unwrap().drain(..) {
+                match record {
+                    Record::Parsed(Value::Null) => visible.remove(&path),
+                    record => visible.insert(path, record),
+                };
+            }
+        }
+    }
+
+    impl Reader for Lagging {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.visible.lock().unwrap().get(from).cloned())
+        }
+    }
+
+    impl Writer for Lagging {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.queued.lock().unwrap().push((to.clone(), data));
+            Ok(to.clone())
+        }
+    }
+
+    fn value(record: Option<Record>) -> Option<Value> {
+        record.map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn reads_own_write_until_confirmed() {
+        let backend = Lagging::default();
+        let mut session = SessionStore::new(backend.clone());
+        let path = path!("users/1");
+
+        session
+            .write(&path, Record::parsed(Value::Integer(1)))
+            .unwrap();
+        assert_eq!(value(session.read(&path).unwrap()), Some(Value::Integer(1)));
+        assert_eq!(session.pending_paths().count(), 1);
+
+        backend.publish();
+        assert_eq!(value(session.read(&path).unwrap()), Some(Value::Integer(1)));
+        assert_eq!(session.pending_paths().count(), 0);
+    }
+
+    #[test]
+    fn null_write_reads_as_missing() {
+        let backend = Lagging::default();
+        backend
+            .visible
+            .lock()
+            .unwrap()
+            .insert(path!("users/1"), Record::parsed(Value::Integer(1)));
+        let mut session = SessionStore::new(backend.clone());
+
+        session
+            .write(&path!("users/1"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(session.read(&path!("users/1")).unwrap().is_none());
+
+        backend.publish();
+        assert!(session.read(&path!("users/1")).unwrap().is_none());
+        assert_eq!(session.pending_paths().count(), 0);
+    }
+
+    #[test]
+    fn raw_records_compare_bytes() {
+        let backend = Lagging::default();
+        let mut session = SessionStore::new(backend.clone());
+        let path = path!("blob");
+
+        session
+            .write(&path, Record::raw(&b"old"[..], Format::OCTET_STREAM))
+            .unwrap();
+        backend.publish();
+        session
+            .write(&path, Record::raw(&b"new"[..], Format::OCTET_STREAM))
+            .unwrap();
+
+        // The backend still has the old bytes
+        let read = session.read(&path).unwrap().unwrap();
+        assert_eq!(read.as_bytes().unwrap().as_ref(), b"new");
+    }
+
+    #[test]
+    fn pending_write_expires() {
+        let backend = Lagging::default();
+        let mut session = SessionStore::new(backend).with_max_pending_age(Duration::ZERO);
+
+        session
+            .write(&path!("users/1"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        assert!(session.read(&path!("users/1")).unwrap().is_none());
+        assert_eq!(session.pending_paths().count(), 0);
+    }
+
+    #[test]
+    fn writes_drop_expired_paths() {
+        let backend = Lagging::default();
+        let mut session =
+            SessionStore::new(backend).with_max_pending_age(Duration::from_millis(200));
+
+        for i in 0..100 {
+            session
+                .write(
+                    &path!("logs").join(&Path::parse(&i.to_string()).unwrap()),
+                    Record::parsed(Value::Integer(i)),
+                )
+                .unwrap();
+        }
+        assert_eq!(session.pending_paths().count(), 100);
+
+        // None of those paths are read again; the next write drops them
+        std::thread::sleep(Duration::from_millis(250));
+        session
+            .write(&path!("logs/last"), Record::parsed(Value::Integer(0)))
+            .unwrap();
+        let pending: Vec<_> = session.pending_paths().cloned().collect();
+        assert_eq!(pending, vec![path!("logs/last")]);
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut session = SessionStore::new(MapStore::default());
+        conformance::assert_missing(&mut session, &path!("config/app"));
+        conformance::assert_round_trip(&mut session, &path!("config/app"), Value::from("v1"));
+        conformance::assert_deletes(&mut session, &path!("config/app"), Value::from("v1"));
+
+        // A delete the backend has not caught up with still reads as missing
+        let backend = Lagging::default();
+        let mut session = SessionStore::new(backend.clone());
+        conformance::assert_round_trip(&mut session, &path!("config/app"), Value::from("v1"));
+        backend.publish();
+        crate::Store::delete(&mut session, &path!("config/app")).unwrap();
+        conformance::assert_missing(&mut session, &path!("config/app"));
+    }
+}
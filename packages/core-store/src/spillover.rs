@@ -0,0 +1,338 @@
+//! Moving large values out of a primary store.
+
+use crate::path_trie::PathTrie;
+use crate::traits::delete_in;
+use crate::{Deleter, Error, NoCodec, Path, Reader, Record, Reference, Value, Writer};
+
+/// Type name of the stub left in the primary store for a spilled value.
+pub const SPILLED_TYPE: &str = "spilled";
+
+/// Default size above which values are spilled, in bytes.
+pub const DEFAULT_SPILL_THRESHOLD: usize = 64 * 1024;
+
+/// A store that keeps large values in a separate blob store.
+///
+/// A write bigger than the threshold goes to the blob store at the same
+/// path, and the primary store gets a reference stub in its place:
+///
+/// ```json
+/// {"path": "reports/2024", "type": {"name": "spilled"}}
+/// ```
+///
+/// Reads put the value back together: a read of a spilled path, or of a
+/// path inside a spilled value, is served from the blob store, and stubs
+/// inside a larger value read from the primary store are replaced by the
+/// values they stand for. Writes inside a spilled value update it in the
+/// blob store. The primary store, often an in-memory overlay, only ever
+/// holds the small stubs.
+///
+/// Sizes are estimated: raw records count their bytes, parsed values
+/// roughly what they take as JSON. A spilled value later overwritten with a
+/// small one stays in the blob store until overwritten there.
+///
+/// Spilled paths are remembered to route reads below them. A stub found in
+/// the primary store, such as one written before a restart, is remembered
+/// when first read.
+pub struct SpilloverStore<S, B> {
+    primary: S,
+    blobs: B,
+    threshold: usize,
+    spilled: PathTrie<()>,
+}
+
+impl<S, B> SpilloverStore<S, B> {
+    /// Keep values over [`DEFAULT_SPILL_THRESHOLD`] bytes from `primary`
+    /// in `blobs`.
+    pub fn new(primary: S, blobs: B) -> Self {
+        Self {
+            primary,
+            blobs,
+            threshold: DEFAULT_SPILL_THRESHOLD,
+            spilled: PathTrie::new(),
+        }
+    }
+
+    /// Spill values larger than `threshold` bytes.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The primary store.
+    pub fn primary(&self) -> &S {
+        &self.primary
+    }
+
+    /// The blob store.
+    pub fn blobs(&self) -> &B {
+        &self.blobs
+    }
+
+    /// Unwrap the two stores.
+    pub fn into_inner(self) -> (S, B) {
+        (self.primary, self.blobs)
+    }
+
+    /// Whether `path` is spilled or lies inside a spilled value.
+    pub fn is_spilled(&self, path: &Path) -> bool {
+        self.spilled.find_ancestor_depth(path).is_some()
+    }
+}
+
+/// The stub for a value spilled at `path`.
+fn stub(path: &Path) -> Value {
+    Reference::with_type(path.to_string(), SPILLED_TYPE).to_value()
+}
+
+/// The path a stub stands for, if `value` is one.
+fn stub_target(value: &Value) -> Option<Path> {
+    let reference = Reference::from_value(value)?;
+    if reference.type_info?.name != SPILLED_TYPE {
+        return None;
+    }
+    Path::parse(&reference.path).ok()
+}
+
+/// Rough encoded size of a record, in bytes.
+fn record_size(record: &Record) -> usize {
+    match record {
+        Record::Raw { bytes, .. } => bytes.len(),
+        Record::Parsed(value) => value_size(value),
+    }
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) => 5,
+        Value::Integer(_) | Value::Float(_) => 8,
+        Value::String(s) => s.len() + 2,
+        Value::Bytes(b) => b.len(),
+        Value::Array(items) => items.iter().map(|v| value_size(v) + 1).sum::<usize>() + 2,
+        Value::Map(map) => {
+            map.iter()
+                .map(|(k, v)| k.len() + 4 + value_size(v))
+                .sum::<usize>()
+                + 2
+        }
+    }
+}
+
+impl<S: Reader, B: Reader> SpilloverStore<S, B> {
+    /// Replace every stub inside `value` with the value it stands for.
+    fn inline_stubs(&mut self, value: &mut Value) -> Result<(), Error> {
+        if let Some(target) = stub_target(value) {
+            self.spilled.insert(&target, ());
+            *value = match self.blobs.read(&target)? {
+                Some(record) => record.into_value(&NoCodec)?,
+                None => Value::Null,
+            };
+            return Ok(());
+        }
+        match value {
+            Value::Array(items) => items.iter_mut().try_for_each(|v| self.inline_stubs(v)),
+            Value::Map(map) => map.values_mut().try_for_each(|v| self.inline_stubs(v)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S: Reader, B: Reader> Reader for SpilloverStore<S, B> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if self.is_spilled(from) {
+            return self.blobs.read(from);
+        }
+        match self.primary.read(from)? {
+            Some(Record::Parsed(value)) if stub_target(&value).as_ref() == Some(from) => {
+                self.spilled.insert(from, ());
+                self.blobs.read(from)
+            }
+            Some(Record::Parsed(mut value)) => {
+                self.inline_stubs(&mut value)?;
+                Ok(Some(Record::Parsed(value)))
+            }
+            other => Ok(other),
+        }
+    }
+}
+
+impl<S: Writer, B: Writer> Writer for SpilloverStore<S, B> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if let Some((_, depth)) = self.spilled.find_ancestor_depth(to) {
+            if depth < to.len() {
+                return self.blobs.write(to, data);
+            }
+        }
+
+        if record_size(&data) <= self.threshold {
+            let written = self.primary.write(to, data)?;
+            self.spilled.remove_subtree(to);
+            return Ok(written);
+        }
+
+        self.blobs.write(to, data)?;
+        let written = self.primary.write(to, Record::parsed(stub(to)))?;
+        self.spilled.remove_subtree(to);
+        self.spilled.insert(to, ());
+        Ok(written)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Deletes from the primary store, and from the blob store any value
+/// spilled at or below the path. A delete inside a spilled value goes to
+/// the blob store only.
+impl<S: Writer, B: Writer> Deleter for SpilloverStore<S, B> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if let Some((_, depth)) = self.spilled.find_ancestor_depth(path) {
+            if depth < path.len() {
+                return delete_in(&mut self.blobs, path);
+            }
+        }
+
+        delete_in(&mut self.primary, path)?;
+        if self
+            .spilled
+            .get_subtrie(path)
+            .is_some_and(|t| !t.is_empty())
+        {
+            delete_in(&mut self.blobs, path)?;
+        }
+        self.spilled.remove_subtree(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+
+    struct MapStore(std::collections::BTreeMap<Path, Value>);
+
+    impl MapStore {
+        fn new() -> Self {
+            Self(Default::default())
+        }
+    }
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            if let Some(value) = self.0.get(from) {
+                return Ok(Some(Record::parsed(value.clone())));
+            }
+            // Children of `from`, assembled into a map
+            let children: std::collections::BTreeMap<String, Value> = self
+                .0
+                .iter()
+                .filter(|(p, _)| p.len() == from.len() + 1 && p.has_prefix(from))
+                .map(|(p, v)| (p[from.len()].clone(), v.clone()))
+                .collect();
+            Ok((!children.is_empty()).then(|| Record::parsed(Value::Map(children))))
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data.into_value(&NoCodec)?);
+            Ok(to.clone())
+        }
+    }
+
+    fn big() -> Value {
+        Value::Map(
+            [
+                ("title".to_string(), Value::String("report".into())),
+                ("body".to_string(), Value::String("x".repeat(100))),
+            ]
+            .into(),
+        )
+    }
+
+    fn read(store: &mut impl Reader, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn large_values_spill_and_reassemble() {
+        let mut store = SpilloverStore::new(MapStore::new(), MapStore::new()).with_threshold(50);
+        store
+            .write(&path!("docs/big"), Record::parsed(big()))
+            .unwrap();
+        store
+            .write(&path!("docs/small"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+
+        assert_eq!(
+            store.primary().0.get(&path!("docs/big")),
+            Some(&stub(&path!("docs/big")))
+        );
+        assert_eq!(store.blobs().0.get(&path!("docs/big")), Some(&big()));
+        assert!(!store.blobs().0.contains_key(&path!("docs/small")));
+
+        assert_eq!(read(&mut store, &path!("docs/big")), Some(big()));
+        let Some(Value::Map(docs)) = read(&mut store, &path!("docs")) else {
+            panic!("expected a map");
+        };
+        assert_eq!(docs["big"], big());
+        assert_eq!(docs["small"], Value::Integer(1));
+    }
+
+    #[test]
+    fn paths_inside_spilled_values_use_blobs() {
+        let mut store = SpilloverStore::new(MapStore::new(), MapStore::new()).with_threshold(50);
+
+        store.write(&path!("big"), Record::parsed(big())).unwrap();
+        assert!(store.is_spilled(&path!("big/title")));
+        store
+            .write(&path!("big/title"), Record::parsed(Value::from("renamed")))
+            .unwrap();
+        assert!(store.blobs().0.contains_key(&path!("big/title")));
+        assert!(!store.primary().0.contains_key(&path!("big/title")));
+        assert_eq!(
+            read(&mut store, &path!("big/title")),
+            Some(Value::from("renamed"))
+        );
+
+        // Overwriting with a small value moves it back to the primary store
+        store
+            .write(&path!("big"), Record::parsed(Value::Integer(2)))
+            .unwrap();
+        assert!(!store.is_spilled(&path!("big/title")));
+        assert_eq!(read(&mut store, &path!("big")), Some(Value::Integer(2)));
+    }
+
+    #[test]
+    fn stubs_from_before_a_restart_are_followed() {
+        let mut store = SpilloverStore::new(MapStore::new(), MapStore::new()).with_threshold(50);
+        store.write(&path!("big"), Record::parsed(big())).unwrap();
+
+        let (primary, blobs) = store.into_inner();
+        let mut store = SpilloverStore::new(primary, blobs).with_threshold(50);
+        assert!(!store.is_spilled(&path!("big")));
+        assert_eq!(read(&mut store, &path!("big")), Some(big()));
+        assert!(store.is_spilled(&path!("big/body")));
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut store =
+            SpilloverStore::new(MapStore::default(), MapStore::default()).with_threshold(16);
+        let big = Value::from("x".repeat(64));
+        conformance::assert_missing(&mut store, &path!("reports/1"));
+        conformance::assert_round_trip(&mut store, &path!("reports/1"), Value::Integer(1));
+        conformance::assert_deletes(&mut store, &path!("reports/1"), Value::Integer(1));
+
+        // Spilled values are deleted from the blob store too
+        conformance::assert_deletes(&mut store, &path!("reports/2"), big);
+        assert!(store.blobs().0.is_empty());
+        assert!(!store.is_spilled(&path!("reports/2")));
+    }
+}
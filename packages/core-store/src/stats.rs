@@ -0,0 +1,202 @@
+//! Summary statistics for a subtree, for finding what makes a store large.
+//!
+//! [`subtree_stats`] walks every record below a prefix the way
+//! [`export_subtree`](crate::dump::export_subtree) does and totals them up:
+//!
+//! ```rust
+//! use structfs_core_store::stats::{subtree_stats, DEFAULT_LARGEST};
+//! # use structfs_core_store::{path, Error, Reader};
+//! # fn report(store: &mut impl Reader) -> Result<(), Error> {
+//! let stats = subtree_stats(store, &path!("sessions"), DEFAULT_LARGEST)?;
+//! println!("{} records, {} bytes", stats.entries, stats.total_bytes);
+//! for (path, bytes) in &stats.largest {
+//!     println!("  {} {}", path, bytes);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A record's size is the size of its bytes as they would be dumped: raw
+//! records count the bytes they were read with, and parsed records count
+//! their CBOR encoding.
+//!
+//! An [`OverlayStore`](crate::overlay_store::OverlayStore) serves the same
+//! statistics at `meta/{prefix}/stats`.
+
+use std::collections::BTreeMap;
+
+use collection_literals::btree;
+
+use crate::dump::{walk_subtree, DumpEntry};
+use crate::{Error, Path, Reader, Record, Value};
+
+/// How many of the largest records [`SubtreeStats`] keeps, by default.
+pub const DEFAULT_LARGEST: usize = 10;
+
+/// Totals for the records in a subtree.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubtreeStats {
+    /// How many records there are.
+    pub entries: usize,
+    /// The size of all records together, in bytes.
+    pub total_bytes: u64,
+    /// How many records there are at each depth below the prefix; a
+    /// record at the prefix itself is at depth 0.
+    pub depths: Vec<usize>,
+    /// The largest records and their sizes, largest first.
+    pub largest: Vec<(Path, u64)>,
+}
+
+impl SubtreeStats {
+    /// Convert to a Value::Map representation:
+    /// `{entries, total_bytes, depths: [count...], largest: [{path, bytes}...]}`.
+    pub fn to_value(&self) -> Value {
+        let depths = self
+            .depths
+            .iter()
+            .map(|&count| Value::Integer(count as i64))
+            .collect();
+        let largest = self
+            .largest
+            .iter()
+            .map(|(path, bytes)| {
+                Value::Map(btree! {
+                    "path".into() => Value::String(path.to_string()),
+                    "bytes".into() => Value::Integer(*bytes as i64),
+                })
+            })
+            .collect();
+        let mut map = BTreeMap::new();
+        map.insert("entries".to_string(), Value::Integer(self.entries as i64));
+        map.insert(
+            "total_bytes".to_string(),
+            Value::Integer(self.total_bytes as i64),
+        );
+        map.insert("depths".to_string(), Value::Array(depths));
+        map.insert("largest".to_string(), Value::Array(largest));
+        Value::Map(map)
+    }
+
+    fn add(&mut self, depth: usize, path: Path, bytes: u64, keep: usize) {
+        self.entries += 1;
+        self.total_bytes += bytes;
+        if self.depths.len() <= depth {
+            self.depths.resize(depth + 1, 0);
+        }
+        self.depths[depth] += 1;
+
+        // Ties keep the record seen first ahead
+        let at = self.largest.partition_point(|(_, b)| *b >= bytes);
+        if at < keep {
+            self.largest.insert(at, (path, bytes));
+            self.largest.truncate(keep);
+        }
+    }
+}
+
+/// Totals for every record at or below `prefix` in `store`, keeping the
+/// `largest` biggest records.
+///
+/// A missing prefix has no records.
+pub fn subtree_stats<S: Reader + ?Sized>(
+    store: &mut S,
+    prefix: &Path,
+    largest: usize,
+) -> Result<SubtreeStats, Error> {
+    let mut stats = SubtreeStats::default();
+    walk_subtree(store, prefix, &mut |path, record| {
+        let bytes = record_size(&path, record)?;
+        stats.add(path.len() - prefix.len(), path, bytes, largest);
+        Ok(())
+    })?;
+    Ok(stats)
+}
+
+fn record_size(path: &Path, record: Record) -> Result<u64, Error> {
+    let bytes = match record {
+        Record::Raw { bytes, .. } => bytes,
+        record => DumpEntry::from_record(path.clone(), record)?.bytes,
+    };
+    Ok(bytes.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Format, Writer};
+    use bytes::Bytes;
+
+    /// Keeps each record at its own path, and answers reads of a prefix
+    /// with a map of what is below it.
+    #[derive(Default)]
+    struct FlatStore(BTreeMap<Path, Record>);
+
+    impl Reader for FlatStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            if let Some(record) = self.0.get(from) {
+                return Ok(Some(record.clone()));
+            }
+            let keys: BTreeMap<String, Value> = self
+                .0
+                .keys()
+                .filter(|p| p.len() > from.len() && p.has_prefix(from))
+                .map(|p| (p[from.len()].clone(), Value::Null))
+                .collect();
+            Ok((!keys.is_empty()).then(|| Record::parsed(Value::Map(keys))))
+        }
+    }
+
+    impl Writer for FlatStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data);
+            Ok(to.clone())
+        }
+    }
+
+    fn raw(len: usize) -> Record {
+        Record::raw(Bytes::from(vec![b'x'; len]), Format::OCTET_STREAM)
+    }
+
+    #[test]
+    fn totals_sizes_depths_and_largest_records() {
+        let mut store = FlatStore::default();
+        store.write(&path!("logs/a"), raw(100)).unwrap();
+        store.write(&path!("logs/old/b"), raw(5000)).unwrap();
+        store.write(&path!("logs/old/c"), raw(20)).unwrap();
+        store
+            .write(&path!("logs/n"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        store.write(&path!("other"), raw(9999)).unwrap();
+
+        let stats = subtree_stats(&mut store, &path!("logs"), 2).unwrap();
+        assert_eq!(stats.entries, 4);
+        // A small integer is one byte of CBOR
+        assert_eq!(stats.total_bytes, 5121);
+        assert_eq!(stats.depths, [0, 2, 2]);
+        assert_eq!(
+            stats.largest,
+            [(path!("logs/old/b"), 5000), (path!("logs/a"), 100)]
+        );
+
+        let value = stats.to_value();
+        assert_eq!(
+            value.get(&path!("largest/0/path")),
+            Some(&Value::String("logs/old/b".into()))
+        );
+        assert_eq!(value.get(&path!("depths/2")), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn a_missing_prefix_is_empty_and_a_record_is_depth_zero() {
+        let mut store = FlatStore::default();
+        assert_eq!(
+            subtree_stats(&mut store, &path!("nothing"), DEFAULT_LARGEST).unwrap(),
+            SubtreeStats::default()
+        );
+
+        store.write(&path!("one"), raw(3)).unwrap();
+        let stats = subtree_stats(&mut store, &path!("one"), DEFAULT_LARGEST).unwrap();
+        assert_eq!(stats.depths, [1]);
+        assert_eq!(stats.largest, [(path!("one"), 3)]);
+    }
+}
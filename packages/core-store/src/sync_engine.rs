@@ -0,0 +1,763 @@
+//! Keeping a subtree of one store in step with another.
+//!
+//! A [`SyncEngine`] reconciles a prefix of a source store into a prefix of
+//! a destination store, one pass at a time. Each pass walks both subtrees
+//! the way [`export_subtree`](crate::dump::export_subtree) does and
+//! compares their records by the bytes they would be dumped as.
+//!
+//! One way, the destination is made a copy of the source: records that
+//! differ are copied over and records only in the destination are deleted
+//! by writing null. Both ways, the engine remembers what each path held
+//! after the last pass, so it can tell which side changed: a change on one
+//! side is copied to the other, and a change on both is a conflict, settled
+//! by a [`ConflictPolicy`].
+//!
+//! [`start`](SyncEngine::start) runs passes on a background thread and
+//! returns a [`SyncStore`] for watching and steering it:
+//!
+//! ```rust,ignore
+//! let sync = SyncEngine::new(local, path!("notes"), remote, path!("backup/notes"))
+//!     .bidirectional(ConflictPolicy::Manual)
+//!     .with_interval(Duration::from_secs(30))
+//!     .start();
+//! overlay.mount(path!("sync"), sync);
+//! ```
+//!
+//! Passes are periodic. Where a store reports changes at a `watch/` path,
+//! or anything else that changes whenever its subtree does, naming it with
+//! [`watch_source`](SyncEngine::watch_source) or
+//! [`watch_destination`](SyncEngine::watch_destination) lets a pass read
+//! just that path and skip the walk when nothing changed.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+use bytes::Bytes;
+use collection_literals::btree;
+
+use crate::dump::{walk_subtree, DumpEntry};
+use crate::{Error, Format, NoCodec, Path, Reader, Record, Store, Value, Writer};
+
+/// Default time between passes.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Which way records are copied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Make the destination a copy of the source.
+    #[default]
+    OneWay,
+    /// Copy changes on either side to the other.
+    Bidirectional,
+}
+
+/// What a bidirectional sync does with a path changed on both sides.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave both sides alone and list the path under `conflicts` until it
+    /// is resolved.
+    #[default]
+    Manual,
+    /// Copy the source's record over the destination's.
+    SourceWins,
+    /// Copy the destination's record over the source's.
+    DestinationWins,
+}
+
+/// A record compared by its format and bytes as dumped.
+type Key = (Format, Bytes);
+
+fn key(record: &Record) -> Result<Key, Error> {
+    let entry = DumpEntry::from_record(Path::from_components(vec![]), record.clone())?;
+    Ok((entry.format, entry.bytes))
+}
+
+/// One end of a sync.
+struct Side {
+    store: Box<dyn Store + Send>,
+    prefix: Path,
+    watch: Option<Path>,
+    /// What `watch` held at the last pass; `None` before the first
+    last_watch: Option<Option<Key>>,
+}
+
+impl Side {
+    fn new(store: Box<dyn Store + Send>, prefix: Path) -> Self {
+        Self {
+            store,
+            prefix,
+            watch: None,
+            last_watch: None,
+        }
+    }
+
+    /// Read the watch path, returning whether it changed since the last
+    /// pass. A side without one may always have changed.
+    fn watch_changed(&mut self) -> Result<bool, Error> {
+        let Some(watch) = &self.watch else {
+            return Ok(true);
+        };
+        let seen = self.store.read(watch)?.as_ref().map(key).transpose()?;
+        let changed = self.last_watch.as_ref() != Some(&seen);
+        self.last_watch = Some(seen);
+        Ok(changed)
+    }
+
+    /// Every record in the subtree, by path relative to the prefix.
+    fn snapshot(&mut self) -> Result<BTreeMap<Path, Record>, Error> {
+        let mut records = BTreeMap::new();
+        let skip = self.prefix.len();
+        walk_subtree(&mut *self.store, &self.prefix, &mut |path, record| {
+            records.insert(path.slice(skip, path.len()), record);
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    fn read(&mut self, path: &Path) -> Result<Option<Record>, Error> {
+        self.store.read(&self.prefix.join(path))
+    }
+
+    /// Write `record` at `path`, or delete it for `None`.
+    fn put(&mut self, path: &Path, record: Option<Record>) -> Result<(), Error> {
+        let record = record.unwrap_or(Record::parsed(Value::Null));
+        self.store.write(&self.prefix.join(path), record)?;
+        Ok(())
+    }
+}
+
+/// Progress and conflicts of a sync, as served at `progress` and
+/// `conflicts`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncStatus {
+    /// Passes that walked both subtrees.
+    pub passes: u64,
+    /// Passes skipped because no watch path changed.
+    pub skipped: u64,
+    /// Records written, on either side.
+    pub copied: u64,
+    /// Records deleted, on either side.
+    pub deleted: u64,
+    /// Why the last pass failed, if it did.
+    pub last_error: Option<String>,
+    /// Paths changed on both sides and not yet resolved, relative to the
+    /// prefixes, with `{source, destination}` as each side holds it.
+    pub conflicts: BTreeMap<Path, Value>,
+}
+
+impl SyncStatus {
+    fn progress_value(&self) -> Value {
+        Value::Map(btree! {
+            "passes".into() => Value::Integer(self.passes as i64),
+            "skipped".into() => Value::Integer(self.skipped as i64),
+            "copied".into() => Value::Integer(self.copied as i64),
+            "deleted".into() => Value::Integer(self.deleted as i64),
+            "last_error".into() => self.last_error.clone().map_or(Value::Null, Value::String),
+        })
+    }
+
+    fn conflicts_value(&self) -> Value {
+        Value::Map(
+            self.conflicts
+                .iter()
+                .map(|(path, conflict)| (path.to_string(), conflict.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// A record as shown in a conflict: its value if parsed, its bytes if raw.
+fn shown(record: &Option<Record>) -> Value {
+    match record {
+        None => Value::Null,
+        Some(Record::Parsed(value)) => value.clone(),
+        Some(Record::Raw { bytes, .. }) => Value::Bytes(bytes.to_vec()),
+    }
+}
+
+/// Reconciles a prefix of one store into a prefix of another.
+///
+/// Build it with the stores and options, then either call
+/// [`sync_once`](Self::sync_once) for each pass or [`start`](Self::start)
+/// it in the background.
+pub struct SyncEngine {
+    source: Side,
+    destination: Side,
+    mode: SyncMode,
+    policy: ConflictPolicy,
+    interval: Duration,
+    /// What each path held on both sides after it was last in step
+    base: BTreeMap<Path, Key>,
+    status: Arc<Mutex<SyncStatus>>,
+}
+
+impl SyncEngine {
+    /// Copy `source_prefix` of `source` one way to `destination_prefix` of
+    /// `destination`, every [`DEFAULT_INTERVAL`].
+    pub fn new<S, D>(
+        source: S,
+        source_prefix: Path,
+        destination: D,
+        destination_prefix: Path,
+    ) -> Self
+    where
+        S: Store + Send + 'static,
+        D: Store + Send + 'static,
+    {
+        Self {
+            source: Side::new(Box::new(source), source_prefix),
+            destination: Side::new(Box::new(destination), destination_prefix),
+            mode: SyncMode::OneWay,
+            policy: ConflictPolicy::default(),
+            interval: DEFAULT_INTERVAL,
+            base: BTreeMap::new(),
+            status: Arc::default(),
+        }
+    }
+
+    /// Copy changes both ways, settling conflicts by `policy`.
+    pub fn bidirectional(mut self, policy: ConflictPolicy) -> Self {
+        self.mode = SyncMode::Bidirectional;
+        self.policy = policy;
+        self
+    }
+
+    /// Run a pass every `interval` once started.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Read `path` in the source store before each pass, and leave the
+    /// source unwalked while it holds what it did last time.
+    pub fn watch_source(mut self, path: Path) -> Self {
+        self.source.watch = Some(path);
+        self
+    }
+
+    /// Read `path` in the destination store before each pass, and leave
+    /// the destination unwalked while it holds what it did last time.
+    pub fn watch_destination(mut self, path: Path) -> Self {
+        self.destination.watch = Some(path);
+        self
+    }
+
+    /// Which way records are copied.
+    pub fn mode(&self) -> SyncMode {
+        self.mode
+    }
+
+    /// The sync's progress and conflicts so far.
+    pub fn status(&self) -> SyncStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Run one pass, bringing the two subtrees into step as far as
+    /// conflicts allow.
+    ///
+    /// A failed read or write ends the pass and is kept as `last_error`;
+    /// records copied before it stay copied.
+    pub fn sync_once(&mut self) -> Result<(), Error> {
+        let result = self.pass();
+        let mut status = self.status.lock().unwrap();
+        status.last_error = result.as_ref().err().map(|e| e.to_string());
+        result
+    }
+
+    fn pass(&mut self) -> Result<(), Error> {
+        // Read both watch paths, so each remembers what it last held
+        let source_changed = self.source.watch_changed()?;
+        let destination_changed = self.destination.watch_changed()?;
+        let changed = match self.mode {
+            SyncMode::OneWay => source_changed,
+            SyncMode::Bidirectional => source_changed || destination_changed,
+        };
+        if !changed {
+            self.status.lock().unwrap().skipped += 1;
+            return Ok(());
+        }
+
+        let source = self.source.snapshot()?;
+        let destination = self.destination.snapshot()?;
+        let paths: BTreeSet<&Path> = source.keys().chain(destination.keys()).collect();
+        for path in paths {
+            let s = source.get(path);
+            let d = destination.get(path);
+            let s_key = s.map(key).transpose()?;
+            let d_key = d.map(key).transpose()?;
+            if s_key == d_key {
+                self.settle(path, s_key);
+                continue;
+            }
+
+            let to_destination = match self.mode {
+                SyncMode::OneWay => true,
+                SyncMode::Bidirectional => {
+                    let base = self.base.get(path);
+                    let source_changed = s_key.as_ref() != base;
+                    let destination_changed = d_key.as_ref() != base;
+                    match (source_changed, destination_changed, self.policy) {
+                        (true, false, _) | (_, _, ConflictPolicy::SourceWins) => true,
+                        (false, true, _) | (_, _, ConflictPolicy::DestinationWins) => false,
+                        (_, _, ConflictPolicy::Manual) => {
+                            let conflict = Value::Map(btree! {
+                                "source".into() => shown(&s.cloned()),
+                                "destination".into() => shown(&d.cloned()),
+                            });
+                            let mut status = self.status.lock().unwrap();
+                            status.conflicts.insert(path.clone(), conflict);
+                            continue;
+                        }
+                    }
+                }
+            };
+            if to_destination {
+                self.copy(path, s.cloned(), true, s_key)?;
+            } else {
+                self.copy(path, d.cloned(), false, d_key)?;
+            }
+        }
+
+        self.status.lock().unwrap().passes += 1;
+        Ok(())
+    }
+
+    /// Copy `record` to the destination, or to the source if not
+    /// `to_destination`, and note that `path` is in step.
+    fn copy(
+        &mut self,
+        path: &Path,
+        record: Option<Record>,
+        to_destination: bool,
+        record_key: Option<Key>,
+    ) -> Result<(), Error> {
+        let deleted = record.is_none();
+        if to_destination {
+            self.destination.put(path, record)?;
+        } else {
+            self.source.put(path, record)?;
+        }
+        let mut status = self.status.lock().unwrap();
+        if deleted {
+            status.deleted += 1;
+        } else {
+            status.copied += 1;
+        }
+        drop(status);
+        self.settle(path, record_key);
+        Ok(())
+    }
+
+    /// Note that both sides of `path` hold `record_key`.
+    fn settle(&mut self, path: &Path, record_key: Option<Key>) {
+        match record_key {
+            Some(record_key) => self.base.insert(path.clone(), record_key),
+            None => self.base.remove(path),
+        };
+        self.status.lock().unwrap().conflicts.remove(path);
+    }
+
+    /// Settle a conflict at `path` by copying the side named by `keep`,
+    /// `"source"` or `"destination"`, over the other.
+    pub fn resolve(&mut self, path: &Path, keep: &str) -> Result<(), Error> {
+        if !self.status.lock().unwrap().conflicts.contains_key(path) {
+            return Err(Error::store(
+                "sync",
+                "resolve",
+                format!("No conflict at '{}'", path),
+            ));
+        }
+        let to_destination = match keep {
+            "source" => true,
+            "destination" => false,
+            other => {
+                return Err(Error::store(
+                    "sync",
+                    "resolve",
+                    format!("keep must be 'source' or 'destination', not '{}'", other),
+                ))
+            }
+        };
+        let record = if to_destination {
+            self.source.read(path)?
+        } else {
+            self.destination.read(path)?
+        };
+        let record_key = record.as_ref().map(key).transpose()?;
+        self.copy(path, record, to_destination, record_key)
+    }
+
+    /// Run passes every interval on a background thread, returning the
+    /// store that controls it.
+    ///
+    /// The thread stops when `stop` is written or every clone of the
+    /// returned store has been dropped.
+    pub fn start(self) -> SyncStore {
+        let interval = self.interval;
+        let store = SyncStore {
+            status: self.status.clone(),
+            control: Arc::new(Control {
+                engine: Mutex::new(self),
+                stopped: Mutex::new(false),
+                wake: Condvar::new(),
+            }),
+        };
+        let control = Arc::downgrade(&store.control);
+        std::thread::spawn(move || run(control, interval));
+        store
+    }
+}
+
+struct Control {
+    engine: Mutex<SyncEngine>,
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+/// Run a pass every `interval` until stopped or forgotten.
+fn run(control: Weak<Control>, interval: Duration) {
+    loop {
+        let Some(control) = control.upgrade() else {
+            return;
+        };
+        // Errors are kept in the status for `progress` to show
+        let _ = control.engine.lock().unwrap().sync_once();
+
+        let stopped = control.stopped.lock().unwrap();
+        let (stopped, _) = control
+            .wake
+            .wait_timeout_while(stopped, interval, |stopped| !*stopped)
+            .unwrap();
+        if *stopped {
+            return;
+        }
+    }
+}
+
+/// Control subtree of a running [`SyncEngine`], usually mounted at `sync/`.
+///
+/// | Path | Operation | Result |
+/// |------|-----------|--------|
+/// | `read` | Everything below | `{progress, conflicts}` |
+/// | `read progress` | Counts so far | `{passes, skipped, copied, deleted, last_error}` |
+/// | `read conflicts` | Unresolved conflicts | `{path: {source, destination}}` |
+/// | `write run null` | Run a pass now | The path of `progress` |
+/// | `write resolve {"path": p, "keep": "source"}` | Settle a conflict | The path of `conflicts` |
+/// | `write stop null` | Stop the background thread | |
+///
+/// `keep` is `"source"` or `"destination"`. A pass run by writing `run`
+/// finishes before the write returns; its error, if any, is the write's.
+#[derive(Clone)]
+pub struct SyncStore {
+    status: Arc<Mutex<SyncStatus>>,
+    control: Arc<Control>,
+}
+
+impl SyncStore {
+    /// The sync's progress and conflicts so far.
+    pub fn status(&self) -> SyncStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Stop the background thread once any pass in progress finishes.
+    pub fn stop(&self) {
+        *self.control.stopped.lock().unwrap() = true;
+        self.control.wake.notify_all();
+    }
+
+    fn invalid(path: &Path) -> Error {
+        Error::store(
+            "sync",
+            "write",
+            format!("Unknown path '{}': expected run, resolve or stop", path),
+        )
+    }
+}
+
+impl Reader for SyncStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let status = self.status.lock().unwrap();
+        let value = match from.components.as_slice() {
+            [] => Value::Map(btree! {
+                "progress".into() => status.progress_value(),
+                "conflicts".into() => status.conflicts_value(),
+            }),
+            [field] if field == "progress" => status.progress_value(),
+            [field] if field == "conflicts" => status.conflicts_value(),
+            _ => return Ok(None),
+        };
+        Ok(Some(Record::parsed(value)))
+    }
+}
+
+impl Writer for SyncStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let [action] = to.components.as_slice() else {
+            return Err(Self::invalid(to));
+        };
+        match action.as_str() {
+            "run" => {
+                self.control.engine.lock().unwrap().sync_once()?;
+                Ok(Path::parse("progress").unwrap())
+            }
+            "resolve" => {
+                let value = data.into_value(&NoCodec)?;
+                let (Some(Value::String(path)), Some(Value::String(keep))) = (
+                    value.get(&Path::parse("path").unwrap()),
+                    value.get(&Path::parse("keep").unwrap()),
+                ) else {
+                    return Err(Error::store(
+                        "sync",
+                        "resolve",
+                        "Expected {\"path\": string, \"keep\": \"source\" or \"destination\"}",
+                    ));
+                };
+                let path = Path::parse(path)
+                    .map_err(|e| Error::store("sync", "resolve", e.to_string()))?;
+                self.control.engine.lock().unwrap().resolve(&path, keep)?;
+                Ok(Path::parse("conflicts").unwrap())
+            }
+            "stop" => {
+                self.stop();
+                Ok(to.clone())
+            }
+            _ => Err(Self::invalid(to)),
+        }
+    }
+}
+
+impl std::fmt::Debug for SyncStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncStore")
+            .field("status", &self.status())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A map of records shared between clones, answering reads of a
+    /// prefix with a map of what is below it, and counting reads.
+    #[derive(Clone, Default)]
+    struct Shared {
+        records: Arc<Mutex<BTreeMap<Path, Record>>>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl Shared {
+        fn get(&self, path: &str) -> Option<Value> {
+            let records = self.records.lock().unwrap();
+            records
+                .get(&Path::parse(path).unwrap())
+                .map(|r| r.clone().into_value(&NoCodec).unwrap())
+        }
+
+        fn set(&self, path: &str, value: Value) {
+            self.clone()
+                .write(&Path::parse(path).unwrap(), Record::parsed(value))
+                .unwrap();
+        }
+    }
+
+    impl Reader for Shared {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            let records = self.records.lock().unwrap();
+            if let Some(record) = records.get(from) {
+                return Ok(Some(record.clone()));
+            }
+            let keys: BTreeMap<String, Value> = records
+                .keys()
+                .filter(|p| p.len() > from.len() && p.has_prefix(from))
+                .map(|p| (p[from.len()].clone(), Value::Null))
+                .collect();
+            Ok((!keys.is_empty()).then(|| Record::parsed(Value::Map(keys))))
+        }
+    }
+
+    impl Writer for Shared {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            let mut records = self.records.lock().unwrap();
+            if matches!(data, Record::Parsed(Value::Null)) {
+                records.remove(to);
+            } else {
+                records.insert(to.clone(), data);
+            }
+            Ok(to.clone())
+        }
+    }
+
+    #[test]
+    fn one_way_mirrors_the_source() {
+        let (source, destination) = (Shared::default(), Shared::default());
+        source.set("notes/a", Value::from("one"));
+        source.set("notes/sub/b", Value::Integer(2));
+        destination.set("copy/stale", Value::Bool(true));
+        destination.set("other", Value::Bool(true));
+
+        let mut engine = SyncEngine::new(
+            source.clone(),
+            path!("notes"),
+            destination.clone(),
+            path!("copy"),
+        );
+        engine.sync_once().unwrap();
+        assert_eq!(destination.get("copy/a"), Some(Value::from("one")));
+        assert_eq!(destination.get("copy/sub/b"), Some(Value::Integer(2)));
+        assert_eq!(destination.get("copy/stale"), None);
+        assert_eq!(destination.get("other"), Some(Value::Bool(true)));
+
+        // Changes in the destination are undone
+        destination.set("copy/a", Value::from("edited"));
+        engine.sync_once().unwrap();
+        assert_eq!(destination.get("copy/a"), Some(Value::from("one")));
+
+        let status = engine.status();
+        assert_eq!((status.passes, status.copied, status.deleted), (2, 3, 1));
+    }
+
+    #[test]
+    fn bidirectional_copies_each_side_and_flags_conflicts() {
+        let (left, right) = (Shared::default(), Shared::default());
+        left.set("a", Value::Integer(1));
+        right.set("b", Value::Integer(2));
+        let mut engine = SyncEngine::new(left.clone(), path!(""), right.clone(), path!(""))
+            .bidirectional(ConflictPolicy::Manual);
+        engine.sync_once().unwrap();
+        assert_eq!(right.get("a"), Some(Value::Integer(1)));
+        assert_eq!(left.get("b"), Some(Value::Integer(2)));
+
+        // One-sided changes and deletes travel; both-sided ones conflict
+        left.set("a", Value::Integer(10));
+        right
+            .clone()
+            .write(&path!("b"), Record::parsed(Value::Null))
+            .unwrap();
+        engine.sync_once().unwrap();
+        assert_eq!(right.get("a"), Some(Value::Integer(10)));
+        assert_eq!(left.get("b"), None);
+
+        left.set("a", Value::Integer(100));
+        right.set("a", Value::Integer(200));
+        engine.sync_once().unwrap();
+        let conflicts = engine.status().conflicts;
+        assert_eq!(
+            conflicts[&path!("a")],
+            Value::Map(btree! {
+                "source".into() => Value::Integer(100),
+                "destination".into() => Value::Integer(200),
+            })
+        );
+        assert_eq!(left.get("a"), Some(Value::Integer(100)));
+
+        assert!(engine.resolve(&path!("a"), "neither").is_err());
+        engine.resolve(&path!("a"), "destination").unwrap();
+        assert_eq!(left.get("a"), Some(Value::Integer(200)));
+        assert!(engine.status().conflicts.is_empty());
+        assert!(engine.resolve(&path!("a"), "source").is_err());
+
+        // A policy settles conflicts itself
+        let mut engine = SyncEngine::new(left.clone(), path!(""), right.clone(), path!(""))
+            .bidirectional(ConflictPolicy::SourceWins);
+        left.set("c", Value::from("left"));
+        right.set("c", Value::from("right"));
+        engine.sync_once().unwrap();
+        assert_eq!(right.get("c"), Some(Value::from("left")));
+    }
+
+    #[test]
+    fn unchanged_watch_paths_skip_the_walk() {
+        let (source, destination) = (Shared::default(), Shared::default());
+        source.set("data/a", Value::Integer(1));
+        source.set("version", Value::Integer(1));
+        let mut engine = SyncEngine::new(
+            source.clone(),
+            path!("data"),
+            destination.clone(),
+            path!("data"),
+        )
+        .watch_source(path!("version"));
+        engine.sync_once().unwrap();
+
+        source.set("data/a", Value::Integer(2));
+        let reads = source.reads.load(Ordering::SeqCst);
+        engine.sync_once().unwrap();
+        assert_eq!(source.reads.load(Ordering::SeqCst), reads + 1);
+        assert_eq!(destination.get("data/a"), Some(Value::Integer(1)));
+
+        source.set("version", Value::Integer(2));
+        engine.sync_once().unwrap();
+        assert_eq!(destination.get("data/a"), Some(Value::Integer(2)));
+        let status = engine.status();
+        assert_eq!((status.passes, status.skipped), (2, 1));
+    }
+
+    #[test]
+    fn the_control_store_runs_resolves_and_stops() {
+        let (left, right) = (Shared::default(), Shared::default());
+        left.set("x", Value::Integer(1));
+        right.set("x", Value::Integer(2));
+        let mut sync = SyncEngine::new(left.clone(), path!(""), right.clone(), path!(""))
+            .bidirectional(ConflictPolicy::Manual)
+            .with_interval(Duration::from_secs(3600))
+            .start();
+
+        assert_eq!(
+            sync.write(&path!("run"), Record::parsed(Value::Null))
+                .unwrap(),
+            path!("progress")
+        );
+        let overview = sync
+            .read(&path!(""))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(
+            overview.get(&path!("conflicts")).and_then(|c| match c {
+                Value::Map(m) => m.get("x").cloned(),
+                _ => None,
+            }),
+            Some(Value::Map(btree! {
+                "source".into() => Value::Integer(1),
+                "destination".into() => Value::Integer(2),
+            }))
+        );
+
+        let resolve = Value::Map(btree! {
+            "path".into() => Value::from("x"),
+            "keep".into() => Value::from("source"),
+        });
+        sync.write(&path!("resolve"), Record::parsed(resolve))
+            .unwrap();
+        assert_eq!(right.get("x"), Some(Value::Integer(1)));
+        assert!(sync.status().conflicts.is_empty());
+
+        assert!(sync
+            .write(&path!("nope"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(sync.read(&path!("nope")).unwrap().is_none());
+        sync.write(&path!("stop"), Record::parsed(Value::Null))
+            .unwrap();
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance;
+
+        let (source, destination) = (Shared::default(), Shared::default());
+        let mut store = SyncEngine::new(source, path!("notes"), destination, path!("notes"))
+            .with_interval(Duration::from_secs(3600))
+            .start();
+        conformance::assert_found(&mut store, &path!("progress"));
+        conformance::assert_found(&mut store, &path!("conflicts"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+        conformance::assert_missing(&mut store, &path!("progress/nope"));
+        store.stop();
+    }
+}
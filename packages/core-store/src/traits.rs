@@ -1,8 +1,14 @@
-//! Core traits: Reader, Writer, Codec.
+//! Core traits: Reader, Writer, Deleter, Lister, Codec.
+//!
+//! [`Watcher`](crate::Watcher) lives in [`crate::watch`] with its
+//! subscriptions.
 
 use bytes::Bytes;
 
-use crate::{Error, Format, Path, Record, Value};
+use crate::query::{Filter, Querier};
+use crate::transaction::{Transaction, Transactor};
+use crate::watch::{Subscription, Watcher};
+use crate::{Error, Format, OpContext, Path, ReadOutcome, Record, Value};
 
 /// Read records from paths.
 ///
@@ -56,7 +62,61 @@ pub trait Reader: Send + Sync {
     /// Returns `Ok(Some(record))` if data exists at the path,
     /// `Ok(None)` if the path doesn't exist,
     /// or `Err` if an error occurred.
+    ///
+    /// "Doesn't exist" includes handles that were never created or have been
+    /// deleted. `Err` means the store could not tell whether data exists,
+    /// for example because of an I/O failure or a malformed path; it is
+    /// never used for a plain miss. The checks in the `conformance` module
+    /// enforce this.
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error>;
+
+    /// Read a record as a [`ReadOutcome`].
+    fn read_outcome(&mut self, from: &Path) -> ReadOutcome {
+        self.read(from).into()
+    }
+
+    /// Read a record with a deadline, cancellation token or trace id.
+    ///
+    /// The context is current for the duration of the read, so stores that
+    /// honor it see it however deeply they are mounted. Those stores fail
+    /// with [`Error::DeadlineExceeded`] or [`Error::Cancelled`] instead of
+    /// starting work the caller no longer wants.
+    fn read_with_context(&mut self, ctx: &OpContext, from: &Path) -> Result<Option<Record>, Error> {
+        ctx.scope(|| self.read(from))
+    }
+
+    /// This reader as a [`Lister`], if it lists natively.
+    ///
+    /// See [`Writer::as_deleter`].
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        None
+    }
+
+    /// This reader as a [`Watcher`], if it can be watched.
+    ///
+    /// See [`Writer::as_deleter`].
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        None
+    }
+
+    /// This reader as a [`Querier`], if it runs filters natively.
+    ///
+    /// See [`Writer::as_deleter`].
+    fn as_querier(&mut self) -> Option<&mut dyn Querier> {
+        None
+    }
+
+    /// Roughly how many bytes of data this store holds in memory, if it
+    /// keeps track.
+    ///
+    /// Stores that hold their data in memory report the
+    /// [`estimated_size`](Value::estimated_size) of what they hold; stores
+    /// backed by disk or the network report `None`. Quotas, eviction and
+    /// per-Block memory budgets read this; an
+    /// [`OverlayStore`](crate::overlay_store::OverlayStore) totals its mounts.
+    fn memory_usage(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// Write records to paths.
@@ -76,11 +136,193 @@ pub trait Writer: Send + Sync {
     /// input path—for example, the HTTP broker returns a handle path like
     /// `/outstanding/0` after queuing a request to the root path.
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error>;
+
+    /// Write a record with a deadline, cancellation token or trace id.
+    ///
+    /// See [`Reader::read_with_context`].
+    fn write_with_context(
+        &mut self,
+        ctx: &OpContext,
+        to: &Path,
+        data: Record,
+    ) -> Result<Path, Error> {
+        ctx.scope(|| self.write(to, data))
+    }
+
+    /// This writer as a [`Deleter`], if it deletes natively.
+    ///
+    /// Stores that implement [`Deleter`] override this to return
+    /// `Some(self)` so that [`Store::delete`] reaches them through
+    /// `dyn Store` and the wrappers that forward it.
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        None
+    }
+
+    /// This writer as a [`Transactor`], if it applies batches atomically.
+    ///
+    /// See [`Writer::as_deleter`].
+    fn as_transactor(&mut self) -> Option<&mut dyn Transactor> {
+        None
+    }
+}
+
+/// Remove data at paths.
+///
+/// After a successful delete, reading the path returns `Ok(None)`. Deleting
+/// a path that holds nothing is not an error, so deletes can be retried.
+/// Deleting a path removes everything below it.
+///
+/// Implementors should also override [`Writer::as_deleter`] so that the
+/// delete is found behind `dyn Store`.
+///
+/// # Object Safety
+///
+/// This trait is object-safe: you can use `Box<dyn Deleter>`.
+pub trait Deleter: Send + Sync {
+    /// Remove the data at a path and everything below it.
+    fn delete(&mut self, path: &Path) -> Result<(), Error>;
+
+    /// Delete with a deadline, cancellation token or trace id.
+    ///
+    /// See [`Reader::read_with_context`].
+    fn delete_with_context(&mut self, ctx: &OpContext, path: &Path) -> Result<(), Error> {
+        ctx.scope(|| self.delete(path))
+    }
+}
+
+/// List the entries directly below paths.
+///
+/// Returns `Ok(None)` where nothing exists, like [`Reader::read`], and an
+/// empty list for a path that holds a value with nothing below it. Names are
+/// single path components in the store's own order.
+///
+/// Implementors should also override [`Reader::as_lister`] so that the
+/// listing is found behind `dyn Store`.
+///
+/// # Object Safety
+///
+/// This trait is object-safe: you can use `Box<dyn Lister>`.
+pub trait Lister: Send + Sync {
+    /// Names of the entries directly below a path.
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error>;
+}
+
+/// Names of the entries directly below `value`: the keys of a map, the
+/// indices of an array, or the last component of each reference in an
+/// `{items: [...]}` listing such as [`HandleBroker::listing`].
+///
+/// [`HandleBroker::listing`]: crate::HandleBroker::listing
+pub(crate) fn children_of(value: &Value) -> Vec<String> {
+    match value {
+        Value::Map(map) => match map.get("items") {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(crate::Reference::from_value)
+                .filter_map(|r| Path::parse(&r.path).ok())
+                .filter_map(|p| p.components.last().cloned())
+                .collect(),
+            _ => map.keys().cloned().collect(),
+        },
+        Value::Array(items) => (0..items.len()).map(|i| i.to_string()).collect(),
+        _ => Vec::new(),
+    }
 }
 
 /// Combined read/write at the Core level.
-pub trait Store: Reader + Writer {}
-impl<T: Reader + Writer> Store for T {}
+pub trait Store: Reader + Writer {
+    /// Remove the data at a path.
+    ///
+    /// Uses the store's [`Deleter`] if it has one, and otherwise writes
+    /// [`Value::Null`], which stores without a native delete treat as one.
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        match self.as_deleter() {
+            Some(deleter) => deleter.delete(path),
+            None => self.write(path, Record::parsed(Value::Null)).map(|_| ()),
+        }
+    }
+
+    /// Names of the entries directly below a path.
+    ///
+    /// Uses the store's [`Lister`] if it has one, and otherwise reads the
+    /// path and lists the keys of a map, the indices of an array, or the
+    /// references of an `{items: [...]}` listing.
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        list_children(self, path)
+    }
+
+    /// The entries directly below `prefix` whose values pass `filter`, by
+    /// name.
+    ///
+    /// Uses the store's [`Querier`] if it has one that supports the
+    /// filter, and otherwise lists and reads each entry; see
+    /// [`crate::query`]. Returns `Ok(None)` where `prefix` does not exist.
+    fn read_query(
+        &mut self,
+        prefix: &Path,
+        filter: &Filter,
+    ) -> Result<Option<Vec<(String, Record)>>, Error> {
+        if let Some(querier) = self.as_querier() {
+            if querier.supports(filter) {
+                return querier.query(prefix, filter);
+            }
+        }
+        crate::query::evaluate(self, prefix, filter)
+    }
+
+    /// Subscribe to the changes at and below a path.
+    ///
+    /// Uses the store's [`Watcher`], and fails for stores without one;
+    /// wrap those in a [`PollWatcher`](crate::PollWatcher).
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        match self.as_watcher() {
+            Some(watcher) => watcher.watch(path),
+            None => Err(Error::store(
+                "store",
+                "watch",
+                format!("Cannot watch '{}': the store does not report changes", path),
+            )),
+        }
+    }
+
+    /// Start a [`Transaction`]: stage changes and apply them all together
+    /// with [`Transaction::commit`].
+    ///
+    /// Uses the store's [`Transactor`] if it has one, and otherwise applies
+    /// the changes one by one and reverts them if one fails; see
+    /// [`crate::transaction`]. Behind `dyn Store`, use
+    /// [`Transaction::new`].
+    fn begin(&mut self) -> Transaction<'_, Self>
+    where
+        Self: Sized,
+    {
+        Transaction::new(self)
+    }
+}
+
+/// List `path` with the reader's [`Lister`], or by reading it.
+pub(crate) fn list_children<R: Reader + ?Sized>(
+    reader: &mut R,
+    path: &Path,
+) -> Result<Option<Vec<String>>, Error> {
+    if let Some(lister) = reader.as_lister() {
+        return lister.list(path);
+    }
+    match reader.read(path)? {
+        Some(record) => Ok(Some(children_of(&record.into_value(&NoCodec)?))),
+        None => Ok(None),
+    }
+}
+/// Delete `path` with the writer's [`Deleter`], or by writing null.
+///
+/// For wrappers whose inner store is only known to be a [`Writer`].
+pub(crate) fn delete_in<W: Writer + ?Sized>(writer: &mut W, path: &Path) -> Result<(), Error> {
+    match writer.as_deleter() {
+        Some(deleter) => deleter.delete(path),
+        None => writer.write(path, Record::parsed(Value::Null)).map(|_| ()),
+    }
+}
+
+impl<T: Reader + Writer + ?Sized> Store for T {}
 
 /// Codec for converting between Value and bytes.
 ///
@@ -155,24 +397,96 @@ impl<T: Reader + ?Sized> Reader for &mut T {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         (*self).read(from)
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        (**self).as_lister()
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        (**self).as_watcher()
+    }
+
+    fn as_querier(&mut self) -> Option<&mut dyn Querier> {
+        (**self).as_querier()
+    }
+
+    fn memory_usage(&self) -> Option<usize> {
+        (**self).memory_usage()
+    }
 }
 
 impl<T: Writer + ?Sized> Writer for &mut T {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         (*self).write(to, data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        (**self).as_deleter()
+    }
+
+    fn as_transactor(&mut self) -> Option<&mut dyn Transactor> {
+        (**self).as_transactor()
+    }
 }
 
 impl<T: Reader + ?Sized> Reader for Box<T> {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         self.as_mut().read(from)
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        self.as_mut().as_lister()
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        self.as_mut().as_watcher()
+    }
+
+    fn as_querier(&mut self) -> Option<&mut dyn Querier> {
+        self.as_mut().as_querier()
+    }
+
+    fn memory_usage(&self) -> Option<usize> {
+        self.as_ref().memory_usage()
+    }
 }
 
 impl<T: Writer + ?Sized> Writer for Box<T> {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         self.as_mut().write(to, data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        self.as_mut().as_deleter()
+    }
+
+    fn as_transactor(&mut self) -> Option<&mut dyn Transactor> {
+        self.as_mut().as_transactor()
+    }
+}
+
+impl<T: Deleter + ?Sized> Deleter for &mut T {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        (**self).delete(path)
+    }
+}
+
+impl<T: Deleter + ?Sized> Deleter for Box<T> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        self.as_mut().delete(path)
+    }
+}
+
+impl<T: Lister + ?Sized> Lister for &mut T {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        (**self).list(path)
+    }
+}
+
+impl<T: Lister + ?Sized> Lister for Box<T> {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        self.as_mut().list(path)
+    }
 }
 
 impl<T: Codec + ?Sized> Codec for Box<T> {
@@ -340,6 +654,45 @@ mod tests {
         assert!(read_result.is_some());
     }
 
+    #[test]
+    fn list_reads_without_a_lister() {
+        use crate::{path, Reference};
+        use std::collections::BTreeMap;
+
+        let mut store = TestStore::new();
+        let map = BTreeMap::from([
+            ("b".to_string(), Value::Null),
+            ("a".to_string(), Value::Null),
+        ]);
+        let listing = BTreeMap::from([(
+            "items".to_string(),
+            Value::Array(vec![Reference::new("handles/7").to_value()]),
+        )]);
+        store
+            .write(&path!("map"), Record::parsed(Value::Map(map)))
+            .unwrap();
+        store
+            .write(
+                &path!("array"),
+                Record::parsed(Value::Array(vec![Value::Null; 2])),
+            )
+            .unwrap();
+        store
+            .write(&path!("handles"), Record::parsed(Value::Map(listing)))
+            .unwrap();
+        store
+            .write(&path!("leaf"), Record::parsed(Value::from("x")))
+            .unwrap();
+
+        let store: &mut dyn Store = &mut store;
+        assert!(store.as_lister().is_none());
+        assert_eq!(store.list(&path!("map")).unwrap().unwrap(), ["a", "b"]);
+        assert_eq!(store.list(&path!("array")).unwrap().unwrap(), ["0", "1"]);
+        assert_eq!(store.list(&path!("handles")).unwrap().unwrap(), ["7"]);
+        assert_eq!(store.list(&path!("leaf")).unwrap(), Some(vec![]));
+        assert_eq!(store.list(&path!("missing")).unwrap(), None);
+    }
+
     #[test]
     fn boxed_codec_works() {
         // Create a simple test codec
@@ -0,0 +1,427 @@
+//! Several writes applied together or not at all.
+//!
+//! [`Store::begin`] starts a [`Transaction`]: writes and deletes are staged
+//! in it, reads through it see them, and nothing reaches the store until
+//! [`commit`](Transaction::commit). Dropping the transaction, or calling
+//! [`rollback`](Transaction::rollback), discards what was staged.
+//!
+//! ```rust,ignore
+//! let mut tx = store.begin();
+//! tx.stage(&path!("users/1"), Record::parsed(user));
+//! tx.stage(&by_email, Record::parsed(Reference::new("users/1").to_value()));
+//! tx.commit()?;
+//! ```
+//!
+//! Stores that can apply a batch atomically implement [`Transactor`] and
+//! return it from [`Writer::as_transactor`]. For every other store, commit
+//! falls back to [`apply_with_undo`]: the changes are made one by one, and
+//! if one fails, those already made are reverted by writing back what was
+//! there before. That restores the data but isn't isolated: other readers
+//! may see the partial batch while it is being applied, and stores whose
+//! writes don't simply replace the value at a path (handle brokers, queues)
+//! can't be reverted this way.
+
+use crate::{Error, Path, Reader, Record, Store, Value, Writer};
+
+/// A change staged in a [`Transaction`].
+#[derive(Clone, Debug)]
+pub enum StagedOp {
+    /// Write a record to a path.
+    Write { path: Path, record: Record },
+    /// Delete the data at a path and below it.
+    Delete { path: Path },
+}
+
+impl StagedOp {
+    /// The path the change is made at.
+    pub fn path(&self) -> &Path {
+        match self {
+            StagedOp::Write { path, .. } | StagedOp::Delete { path } => path,
+        }
+    }
+}
+
+/// Stores that apply a batch of changes atomically.
+///
+/// Implementors should also override [`Writer::as_transactor`] so that
+/// [`Transaction::commit`] finds the native support behind `dyn Store`.
+///
+/// # Object Safety
+///
+/// This trait is object-safe: you can use `Box<dyn Transactor>`.
+pub trait Transactor: Send + Sync {
+    /// Apply every change, in order, or none of them.
+    ///
+    /// Returns the path each change was made at.
+    fn apply(&mut self, ops: Vec<StagedOp>) -> Result<Vec<Path>, Error>;
+}
+
+impl<T: Transactor + ?Sized> Transactor for &mut T {
+    fn apply(&mut self, ops: Vec<StagedOp>) -> Result<Vec<Path>, Error> {
+        (**self).apply(ops)
+    }
+}
+
+impl<T: Transactor + ?Sized> Transactor for Box<T> {
+    fn apply(&mut self, ops: Vec<StagedOp>) -> Result<Vec<Path>, Error> {
+        self.as_mut().apply(ops)
+    }
+}
+
+/// Changes staged against a store, applied together on commit.
+///
+/// A transaction is itself a store: writing to it stages a write, deleting
+/// stages a delete, and reading overlays the staged changes on the store's
+/// data, so code written against [`Store`] can run inside one.
+pub struct Transaction<'a, S: Store + ?Sized> {
+    store: &'a mut S,
+    ops: Vec<StagedOp>,
+}
+
+impl<'a, S: Store + ?Sized> Transaction<'a, S> {
+    /// Start staging changes to `store`.
+    pub fn new(store: &'a mut S) -> Self {
+        Self {
+            store,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Stage a write of `record` to `path`.
+    pub fn stage(&mut self, path: &Path, record: Record) {
+        self.ops.push(StagedOp::Write {
+            path: path.clone(),
+            record,
+        });
+    }
+
+    /// Stage a delete of `path`.
+    pub fn stage_delete(&mut self, path: &Path) {
+        self.ops.push(StagedOp::Delete { path: path.clone() });
+    }
+
+    /// The changes staged so far, in order.
+    pub fn staged(&self) -> &[StagedOp] {
+        &self.ops
+    }
+
+    /// Whether nothing has been staged.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply the staged changes: natively if the store is a [`Transactor`],
+    /// otherwise with [`apply_with_undo`].
+    ///
+    /// Returns the path each change was made at.
+    pub fn commit(self) -> Result<Vec<Path>, Error> {
+        if self.ops.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self.store.as_transactor() {
+            Some(transactor) => transactor.apply(self.ops),
+            None => apply_with_undo(self.store, self.ops),
+        }
+    }
+
+    /// Discard the staged changes.
+    pub fn rollback(self) {}
+}
+
+impl<S: Store + ?Sized> Reader for Transaction<'_, S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        // The last change at or above `from` decides where the view starts;
+        // the changes below it after that are overlaid on it.
+        let covering = self.ops.iter().rposition(|op| from.has_prefix(op.path()));
+        let start = covering.map_or(0, |i| i + 1);
+        let below: Vec<(Path, &StagedOp)> = self.ops[start..]
+            .iter()
+            .filter_map(|op| Some((op.path().strip_prefix(from)?, op)))
+            .collect();
+
+        let base = match covering.map(|i| &self.ops[i]) {
+            None => self.store.read(from)?,
+            Some(StagedOp::Delete { .. }) => None,
+            Some(StagedOp::Write { path, record }) => match from.strip_prefix(path) {
+                Some(rest) if !rest.is_empty() => staged_value(path, record)?
+                    .get(&rest)
+                    .cloned()
+                    .map(Record::parsed),
+                _ => Some(record.clone()),
+            },
+        };
+        if below.is_empty() {
+            return Ok(base);
+        }
+
+        let mut view = match base {
+            Some(record) => Some(staged_value(from, &record)?.clone()),
+            None => None,
+        };
+        for (rest, op) in below {
+            match op {
+                StagedOp::Write { path, record } => {
+                    let value = staged_value(path, record)?.clone();
+                    view.get_or_insert_with(Value::map).set(&rest, value)?;
+                }
+                StagedOp::Delete { .. } => {
+                    if let Some(view) = &mut view {
+                        view.remove(&rest)?;
+                    }
+                }
+            }
+        }
+        Ok(view.map(Record::parsed))
+    }
+}
+
+impl<S: Store + ?Sized> Writer for Transaction<'_, S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        self.stage(to, data);
+        Ok(to.clone())
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn crate::Deleter> {
+        Some(self)
+    }
+}
+
+/// Deleting through a transaction stages the delete.
+impl<S: Store + ?Sized> crate::Deleter for Transaction<'_, S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        self.stage_delete(path);
+        Ok(())
+    }
+}
+
+/// The value of a staged record, which must be parsed to be combined with
+/// the changes around it.
+fn staged_value<'r>(path: &Path, record: &'r Record) -> Result<&'r Value, Error> {
+    record.as_value().ok_or_else(|| {
+        Error::store(
+            "transaction",
+            "read",
+            format!(
+                "Cannot combine the raw {} record staged at '{}' with other changes",
+                record.format(),
+                path
+            ),
+        )
+    })
+}
+
+/// Apply `ops` one at a time, reverting the ones already applied if one
+/// fails.
+///
+/// This is how [`Transaction::commit`] applies changes to stores that
+/// aren't a [`Transactor`]. Before each change, the data at its path is
+/// read, and on failure it is written back (or deleted, where there was
+/// none) in reverse order. If reverting fails too, the error says where.
+pub fn apply_with_undo<S: Store + ?Sized>(
+    store: &mut S,
+    ops: Vec<StagedOp>,
+) -> Result<Vec<Path>, Error> {
+    let mut undo: Vec<(Path, Option<Record>)> = Vec::with_capacity(ops.len());
+    let mut paths = Vec::with_capacity(ops.len());
+    for op in ops {
+        let path = op.path().clone();
+        let applied = store.read(&path).and_then(|before| {
+            let written = match op {
+                StagedOp::Write { path, record } => store.write(&path, record)?,
+                StagedOp::Delete { path } => {
+                    Store::delete(store, &path)?;
+                    path
+                }
+            };
+            Ok((before, written))
+        });
+        match applied {
+            Ok((before, written)) => {
+                undo.push((path, before));
+                paths.push(written);
+            }
+            Err(error) => return Err(revert(store, undo, error)),
+        }
+    }
+    Ok(paths)
+}
+
+/// Put back what was at each path, latest change first.
+fn revert<S: Store + ?Sized>(
+    store: &mut S,
+    undo: Vec<(Path, Option<Record>)>,
+    error: Error,
+) -> Error {
+    for (path, before) in undo.into_iter().rev() {
+        let reverted = match before {
+            Some(record) => store.write(&path, record).map(|_| ()),
+            None => Store::delete(store, &path),
+        };
+        if let Err(undo_error) = reverted {
+            return Error::store(
+                "transaction",
+                "commit",
+                format!(
+                    "{}; reverting '{}' also failed, so the store holds part of the batch: {}",
+                    error, path, undo_error
+                ),
+            );
+        }
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Deleter, NoCodec};
+    use std::collections::BTreeMap;
+
+    /// Stores whole values at exact paths, and refuses writes under `locked`.
+    #[derive(Default)]
+    struct MapStore(BTreeMap<Path, Value>);
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.get(from).cloned().map(Record::parsed))
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            if to.has_prefix(&path!("locked")) {
+                return Err(Error::store("map", "write", "locked"));
+            }
+            self.0.insert(to.clone(), data.into_value(&NoCodec)?);
+            Ok(to.clone())
+        }
+
+        fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+            Some(self)
+        }
+    }
+
+    impl Deleter for MapStore {
+        fn delete(&mut self, path: &Path) -> Result<(), Error> {
+            self.0.remove(path);
+            Ok(())
+        }
+    }
+
+    fn read(store: &mut impl Reader, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    fn int(i: i64) -> Record {
+        Record::parsed(Value::Integer(i))
+    }
+
+    #[test]
+    fn reads_see_staged_changes() {
+        let mut store = MapStore::default();
+        store
+            .write(&path!("users"), Record::parsed(Value::map()))
+            .unwrap();
+        store.write(&path!("count"), int(1)).unwrap();
+
+        let mut tx = store.begin();
+        tx.stage(&path!("users/alice"), int(1));
+        tx.stage(&path!("users/bob"), int(2));
+        tx.stage_delete(&path!("users/alice"));
+        tx.stage(&path!("count"), int(2));
+        assert_eq!(read(&mut tx, &path!("count")), Some(Value::Integer(2)));
+        assert_eq!(read(&mut tx, &path!("users/alice")), None);
+        assert_eq!(read(&mut tx, &path!("users/bob")), Some(Value::Integer(2)));
+        assert_eq!(
+            read(&mut tx, &path!("users")),
+            Some(Value::Map(BTreeMap::from([(
+                "bob".to_string(),
+                Value::Integer(2)
+            )])))
+        );
+        tx.rollback();
+        assert_eq!(read(&mut store, &path!("count")), Some(Value::Integer(1)));
+    }
+
+    #[test]
+    fn commit_applies_everything() {
+        let mut store = MapStore::default();
+        store.write(&path!("gone"), int(0)).unwrap();
+        let mut tx = store.begin();
+        tx.write(&path!("a"), int(1)).unwrap();
+        Store::delete(&mut tx, &path!("gone")).unwrap();
+        assert_eq!(tx.staged().len(), 2);
+        assert_eq!(tx.commit().unwrap(), vec![path!("a"), path!("gone")]);
+        assert_eq!(read(&mut store, &path!("a")), Some(Value::Integer(1)));
+        assert_eq!(read(&mut store, &path!("gone")), None);
+    }
+
+    #[test]
+    fn failed_commit_reverts_the_applied_changes() {
+        let mut store = MapStore::default();
+        store.write(&path!("a"), int(1)).unwrap();
+        let mut tx = store.begin();
+        tx.stage(&path!("a"), int(2));
+        tx.stage(&path!("b"), int(2));
+        tx.stage(&path!("locked/c"), int(2));
+        assert!(tx.commit().is_err());
+        assert_eq!(read(&mut store, &path!("a")), Some(Value::Integer(1)));
+        assert_eq!(read(&mut store, &path!("b")), None);
+    }
+
+    #[test]
+    fn commit_uses_a_native_transactor() {
+        #[derive(Default)]
+        struct Batched {
+            store: MapStore,
+            batches: usize,
+        }
+
+        impl Reader for Batched {
+            fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+                self.store.read(from)
+            }
+        }
+
+        impl Writer for Batched {
+            fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+                self.store.write(to, data)
+            }
+
+            fn as_transactor(&mut self) -> Option<&mut dyn Transactor> {
+                Some(self)
+            }
+        }
+
+        impl Transactor for Batched {
+            fn apply(&mut self, ops: Vec<StagedOp>) -> Result<Vec<Path>, Error> {
+                self.batches += 1;
+                apply_with_undo(&mut self.store, ops)
+            }
+        }
+
+        let mut store = Batched::default();
+        let boxed: &mut dyn Store = &mut store;
+        let mut tx = Transaction::new(boxed);
+        tx.stage(&path!("a"), int(1));
+        tx.commit().unwrap();
+        assert_eq!(store.batches, 1);
+        assert_eq!(read(&mut store, &path!("a")), Some(Value::Integer(1)));
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance;
+
+        let mut store = MapStore::default();
+        let mut tx = Transaction::new(&mut store);
+        conformance::assert_missing(&mut tx, &path!("a"));
+        conformance::assert_round_trip(&mut tx, &path!("a"), Value::Integer(1));
+        conformance::assert_deletes(&mut tx, &path!("a"), Value::Integer(1));
+        tx.commit().unwrap();
+        assert!(store.0.is_empty());
+    }
+}
@@ -0,0 +1,523 @@
+//! Soft deletes that can be undone.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::traits::delete_in;
+use crate::{Deleter, Error, NoCodec, Path, Reader, Record, Value, Writer};
+
+/// Default subtree where deleted records are kept.
+pub const DEFAULT_TRASH_PREFIX: &str = "trash";
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// A store wrapper that moves deleted records to a trash subtree.
+///
+/// Deletes go through [`Store::delete`](crate::Store::delete), or are
+/// writes of null for callers without it. Instead of losing the old value,
+/// a `TrashStore` first copies it into the inner store under
+/// `trash/{deleted_at}/{path}`, where `deleted_at` is the time of the
+/// delete in milliseconds since the Unix epoch. (`.trash` is not a valid
+/// path component, so the subtree is `trash/` by default.) From there it
+/// can be read, restored or purged for good:
+///
+/// | Path | Operation | Result |
+/// |------|-----------|--------|
+/// | `read trash` | List deleted records | `{deleted_at: {path...: value}}` |
+/// | `read trash/{deleted_at}/{path}` | One deleted record | The value as it was |
+/// | `write trash/restore "{deleted_at}/{path}"` | Restore a record | The path restored to |
+/// | `delete trash/{deleted_at}/{path}` | Purge one record | |
+/// | `delete trash` or `write trash/purge null` | Empty the trash | |
+/// | `write trash/purge {"before_ms": n}` | Purge deletes older than `n` | |
+///
+/// Deleting a path that holds nothing, or null, is passed straight
+/// through to the inner store's delete. Deletes inside the trash, and
+/// writes of null there, are permanent; other writes there are rejected.
+/// Each delete gets its own `deleted_at`, one millisecond after the last
+/// if two land in the same millisecond. Since the trash is kept in the
+/// inner store, it survives for as long as that store does.
+///
+/// Restoring overwrites whatever is at the path now. The root, and with
+/// [`with_prefix`](Self::with_prefix) any other path the trash is kept
+/// under, cannot be deleted, as that would take the trash with it.
+pub struct TrashStore<S> {
+    inner: S,
+    prefix: Path,
+    last_deleted_at: u64,
+}
+
+impl<S> TrashStore<S> {
+    /// Wrap `inner`, keeping deleted records at `trash/`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            prefix: Path::parse(DEFAULT_TRASH_PREFIX).unwrap(),
+            last_deleted_at: 0,
+        }
+    }
+
+    /// Keep deleted records at `prefix` instead of `trash/`.
+    ///
+    /// Deletes of `prefix`'s ancestors are then rejected: they would copy
+    /// the trash into itself and then delete it.
+    pub fn with_prefix(mut self, prefix: Path) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap the inner store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Where a record deleted from `path` at `deleted_at` is kept.
+    pub fn trash_path(&self, deleted_at: u64, path: &Path) -> Path {
+        self.prefix
+            .join(&Path::from_components(vec![deleted_at.to_string()]))
+            .join(path)
+    }
+
+    fn next_deleted_at(&mut self) -> u64 {
+        self.last_deleted_at = now_ms().max(self.last_deleted_at + 1);
+        self.last_deleted_at
+    }
+}
+
+fn is_null(record: &Record) -> bool {
+    matches!(record, Record::Parsed(Value::Null))
+}
+
+/// `value` without null map entries or the maps they leave empty. Purged
+/// records are nulled out in the inner store, so listings drop them here.
+fn prune_nulls(value: Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Map(map) => {
+            let map: std::collections::BTreeMap<String, Value> = map
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, prune_nulls(v)?)))
+                .collect();
+            (!map.is_empty()).then_some(Value::Map(map))
+        }
+        other => Some(other),
+    }
+}
+
+impl<S: Reader + Writer> TrashStore<S> {
+    /// Copy the record at `path` to the trash. Returns when it was
+    /// deleted, or `None` if there was nothing to keep.
+    fn move_to_trash(&mut self, path: &Path) -> Result<Option<u64>, Error> {
+        if path.is_empty() {
+            return Err(Error::store(
+                "trash",
+                "delete",
+                "Cannot delete the root; delete the paths below it",
+            ));
+        }
+        if self.prefix.has_prefix(path) {
+            return Err(Error::store(
+                "trash",
+                "delete",
+                format!(
+                    "Cannot delete '{}': the trash is kept under it at '{}'; delete the paths below it",
+                    path, self.prefix
+                ),
+            ));
+        }
+        let Some(record) = self.inner.read(path)?.filter(|r| !is_null(r)) else {
+            return Ok(None);
+        };
+        let deleted_at = self.next_deleted_at();
+        self.write_creating(&self.trash_path(deleted_at, path), record)?;
+        Ok(Some(deleted_at))
+    }
+
+    /// Write `record` to `to`, creating missing parents as maps if the
+    /// inner store needs them to exist, as the in-memory JSON store does.
+    fn write_creating(&mut self, to: &Path, record: Record) -> Result<(), Error> {
+        let error = match self.inner.write(to, record.clone()) {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        let Ok(mut value) = record.into_value(&NoCodec) else {
+            return Err(error);
+        };
+        for depth in (0..to.len()).rev() {
+            if self.inner.read(&to.slice(0, depth))?.is_none() {
+                continue;
+            }
+            for component in to.components[depth + 1..].iter().rev() {
+                value = Value::Map([(component.clone(), value)].into());
+            }
+            self.inner
+                .write(&to.slice(0, depth + 1), Record::parsed(value))?;
+            return Ok(());
+        }
+        Err(error)
+    }
+
+    /// Put the record deleted from `path` at `deleted_at` back, and take it
+    /// out of the trash. Returns the path restored to.
+    pub fn restore(&mut self, deleted_at: u64, path: &Path) -> Result<Path, Error> {
+        let trashed = self.trash_path(deleted_at, path);
+        let record = self
+            .inner
+            .read(&trashed)?
+            .filter(|r| !is_null(r))
+            .ok_or_else(|| {
+                Error::store(
+                    "trash",
+                    "restore",
+                    format!("Nothing deleted from '{}' at {}", path, deleted_at),
+                )
+            })?;
+        let restored = self.inner.write(path, record)?;
+        delete_in(&mut self.inner, &trashed)?;
+        Ok(restored)
+    }
+
+    /// The times of the deletes still in the trash, oldest first.
+    pub fn deleted_at(&mut self) -> Result<Vec<u64>, Error> {
+        let listing = match self.inner.read(&self.prefix)? {
+            Some(record) => prune_nulls(record.into_value(&NoCodec)?),
+            None => None,
+        };
+        let Some(Value::Map(map)) = listing else {
+            return Ok(Vec::new());
+        };
+        let mut times: Vec<u64> = map.keys().filter_map(|k| k.parse().ok()).collect();
+        times.sort_unstable();
+        Ok(times)
+    }
+
+    /// Purge the deletes made before `cutoff_ms`. Returns how many there
+    /// were.
+    pub fn purge_before(&mut self, cutoff_ms: u64) -> Result<usize, Error> {
+        let old: Vec<u64> = self
+            .deleted_at()?
+            .into_iter()
+            .filter(|t| *t < cutoff_ms)
+            .collect();
+        for deleted_at in &old {
+            let path = self
+                .prefix
+                .join(&Path::from_components(vec![deleted_at.to_string()]));
+            delete_in(&mut self.inner, &path)?;
+        }
+        Ok(old.len())
+    }
+
+    /// Empty the trash.
+    pub fn purge(&mut self) -> Result<(), Error> {
+        let prefix = self.prefix.clone();
+        delete_in(&mut self.inner, &prefix)
+    }
+
+    fn write_control(&mut self, to: &Path, rest: &Path, data: Record) -> Result<Path, Error> {
+        match rest.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["restore"] => {
+                let Value::String(target) = data.into_value(&NoCodec)? else {
+                    return Err(Error::store(
+                        "trash",
+                        "restore",
+                        "restore requires \"{deleted_at}/{path}\"",
+                    ));
+                };
+                let target = Path::parse(&target)?;
+                let deleted_at = target
+                    .components
+                    .first()
+                    .and_then(|t| t.parse().ok())
+                    .filter(|_| target.len() > 1)
+                    .ok_or_else(|| {
+                        Error::store(
+                            "trash",
+                            "restore",
+                            format!("Expected \"{{deleted_at}}/{{path}}\", got '{}'", target),
+                        )
+                    })?;
+                self.restore(deleted_at, &target.slice(1, target.len()))
+            }
+            ["purge"] => {
+                match data.into_value(&NoCodec)? {
+                    Value::Null => self.purge()?,
+                    Value::Map(map) => match map.get("before_ms") {
+                        Some(Value::Integer(n)) if *n >= 0 => {
+                            self.purge_before(*n as u64)?;
+                        }
+                        _ => {
+                            return Err(Error::store(
+                                "trash",
+                                "purge",
+                                "purge requires null or {\"before_ms\": n}",
+                            ))
+                        }
+                    },
+                    _ => {
+                        return Err(Error::store(
+                            "trash",
+                            "purge",
+                            "purge requires null or {\"before_ms\": n}",
+                        ))
+                    }
+                }
+                Ok(to.clone())
+            }
+            _ if is_null(&data) => {
+                delete_in(&mut self.inner, to)?;
+                Ok(to.clone())
+            }
+            _ => Err(Error::store(
+                "trash",
+                "write",
+                format!(
+                    "Cannot write to '{}'. Write null to purge it, or write \"{{deleted_at}}/{{path}}\" to {}/restore.",
+                    to, self.prefix
+                ),
+            )),
+        }
+    }
+}
+
+impl<S: Reader> Reader for TrashStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if from.strip_prefix(&self.prefix).is_none() {
+            return self.inner.read(from);
+        }
+        match self.inner.read(from)? {
+            Some(Record::Parsed(value)) => Ok(prune_nulls(value).map(Record::parsed)),
+            other => Ok(other),
+        }
+    }
+}
+
+impl<S: Reader + Writer> Writer for TrashStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if let Some(rest) = to.strip_prefix(&self.prefix) {
+            return self.write_control(to, &rest, data);
+        }
+        if is_null(&data) {
+            Deleter::delete(self, to)?;
+            return Ok(to.clone());
+        }
+        self.inner.write(to, data)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+/// Moves the record to the trash, then deletes it from the inner store.
+/// Deletes inside the trash are permanent.
+impl<S: Reader + Writer> Deleter for TrashStore<S> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if path.strip_prefix(&self.prefix).is_none() {
+            self.move_to_trash(path)?;
+        }
+        delete_in(&mut self.inner, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+    use std::collections::BTreeMap;
+
+    /// Store of nested maps, like the in-memory JSON store.
+    struct MapStore(Value);
+
+    impl MapStore {
+        fn new() -> Self {
+            Self(Value::Map(BTreeMap::new()))
+        }
+    }
+
+    impl Reader for MapStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            let mut node = &self.0;
+            for component in from.iter() {
+                match node {
+                    Value::Map(map) => match map.get(component) {
+                        Some(child) => node = child,
+                        None => return Ok(None),
+                    },
+                    _ => return Ok(None),
+                }
+            }
+            Ok(Some(Record::parsed(node.clone())))
+        }
+    }
+
+    impl Writer for MapStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            let mut node = &mut self.0;
+            for component in to.iter() {
+                if !matches!(node, Value::Map(_)) {
+                    *node = Value::Map(BTreeMap::new());
+                }
+                let Value::Map(map) = node else {
+                    unreachable!()
+                };
+                node = map.entry(component.clone()).or_insert(Value::Null);
+            }
+            *node = data.into_value(&NoCodec)?;
+            Ok(to.clone())
+        }
+    }
+
+    fn read(store: &mut impl Reader, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    fn store_with_users() -> TrashStore<MapStore> {
+        let mut store = TrashStore::new(MapStore::new());
+        for (name, age) in [("alice", 30), ("bob", 40)] {
+            let path = Path::parse(&format!("users/{}", name)).unwrap();
+            store
+                .write(&path, Record::parsed(Value::Integer(age)))
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn deletes_move_to_trash_and_restore() {
+        let mut store = store_with_users();
+        store
+            .write(&path!("users/alice"), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(read(&mut store, &path!("users/alice")), Some(Value::Null));
+
+        let times = store.deleted_at().unwrap();
+        assert_eq!(times.len(), 1);
+        let trashed = store.trash_path(times[0], &path!("users/alice"));
+        assert_eq!(read(&mut store, &trashed), Some(Value::Integer(30)));
+
+        let target = format!("{}/users/alice", times[0]);
+        let restored = store
+            .write(&path!("trash/restore"), Record::parsed(Value::from(target)))
+            .unwrap();
+        assert_eq!(restored, path!("users/alice"));
+        assert_eq!(
+            read(&mut store, &path!("users/alice")),
+            Some(Value::Integer(30))
+        );
+
+        // Restored records leave the trash
+        assert_eq!(read(&mut store, &path!("trash")), None);
+        assert!(store.restore(times[0], &path!("users/alice")).is_err());
+    }
+
+    #[test]
+    fn store_delete_goes_through_the_trash() {
+        let mut store = store_with_users();
+        assert!(store.as_deleter().is_some());
+        crate::Store::delete(&mut store, &path!("users/bob")).unwrap();
+
+        let times = store.deleted_at().unwrap();
+        let trashed = store.trash_path(times[0], &path!("users/bob"));
+        assert_eq!(read(&mut store, &trashed), Some(Value::Integer(40)));
+
+        // Deleting in the trash purges
+        crate::Store::delete(&mut store, &trashed).unwrap();
+        assert!(store.deleted_at().unwrap().is_empty());
+    }
+
+    #[test]
+    fn deletes_of_nothing_pass_through() {
+        let mut store = store_with_users();
+        store
+            .write(&path!("users/carol"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(store.deleted_at().unwrap().is_empty());
+        assert!(store
+            .write(&path!(""), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn ancestors_of_the_trash_cannot_be_deleted() {
+        let mut store = TrashStore::new(MapStore::new()).with_prefix(path!("data/trash"));
+        store
+            .write(
+                &path!("data/users/alice"),
+                Record::parsed(Value::Integer(30)),
+            )
+            .unwrap();
+        crate::Store::delete(&mut store, &path!("data/users/alice")).unwrap();
+        assert_eq!(store.deleted_at().unwrap().len(), 1);
+
+        let err = crate::Store::delete(&mut store, &path!("data")).unwrap_err();
+        assert!(
+            err.to_string().contains("trash is kept under it"),
+            "{}",
+            err
+        );
+        assert!(store
+            .write(&path!("data"), Record::parsed(Value::Null))
+            .is_err());
+        assert_eq!(store.deleted_at().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn purge_one_old_or_all() {
+        let mut store = store_with_users();
+        for name in ["alice", "bob"] {
+            let path = Path::parse(&format!("users/{}", name)).unwrap();
+            store.write(&path, Record::parsed(Value::Null)).unwrap();
+        }
+        let times = store.deleted_at().unwrap();
+        assert_eq!(times.len(), 2);
+        assert!(times[0] < times[1]);
+
+        // Deleting inside the trash purges for good
+        let bob = store.trash_path(times[1], &path!("users/bob"));
+        store.write(&bob, Record::parsed(Value::Null)).unwrap();
+        assert_eq!(store.deleted_at().unwrap(), vec![times[0]]);
+        assert!(store
+            .write(&bob, Record::parsed(Value::Integer(1)))
+            .is_err());
+
+        store
+            .write(&path!("users/bob"), Record::parsed(Value::Integer(41)))
+            .unwrap();
+        store
+            .write(&path!("users/bob"), Record::parsed(Value::Null))
+            .unwrap();
+        let cutoff =
+            Value::Map([("before_ms".to_string(), Value::Integer(times[1] as i64))].into());
+        store
+            .write(&path!("trash/purge"), Record::parsed(cutoff))
+            .unwrap();
+        assert_eq!(store.deleted_at().unwrap().len(), 1);
+
+        store
+            .write(&path!("trash/purge"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(store.deleted_at().unwrap().is_empty());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut store = TrashStore::new(MapStore::default());
+        conformance::assert_missing(&mut store, &path!("users/carol"));
+        conformance::assert_missing(&mut store, &path!("trash"));
+        conformance::assert_round_trip(&mut store, &path!("users/carol"), Value::Integer(1));
+        conformance::assert_deletes(&mut store, &path!("users/carol"), Value::Integer(1));
+    }
+}
@@ -5,6 +5,8 @@
 
 use std::collections::BTreeMap;
 
+use sha2::{Digest, Sha256};
+
 use crate::{Error, Path, PathError};
 
 /// A tree-shaped value that can be read from or written to a Store.
@@ -225,6 +227,107 @@ impl Value {
             _ => Ok(None),
         }
     }
+
+    /// Deterministic binary encoding of this value.
+    ///
+    /// Equal values always produce the same bytes, regardless of how they
+    /// were built or which format they were decoded from. Each value is a
+    /// one-byte type tag followed by its payload: integers and floats as
+    /// 8 big-endian bytes, strings and bytes as a big-endian `u64` length and
+    /// their contents, arrays and maps as a `u64` count and their elements.
+    /// Map entries are written in key order. Floats are canonicalized so that
+    /// `-0.0` encodes as `0.0` and every NaN encodes the same way.
+    ///
+    /// Integers and floats are distinct: `1` and `1.0` encode differently.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_canonical(&mut out);
+        out
+    }
+
+    /// SHA-256 of [`canonical_bytes`](Self::canonical_bytes).
+    ///
+    /// Suitable as a content address or cache key.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut out = Vec::new();
+        self.write_canonical(&mut out);
+        Sha256::digest(&out).into()
+    }
+
+    /// Rough number of bytes this value holds in memory: the length of its
+    /// strings, bytes and map keys, plus a word for each value.
+    ///
+    /// This ignores allocator overhead and spare capacity, so it undercounts,
+    /// but it is cheap and stable, which is what quotas, eviction and memory
+    /// budgets need to compare values with each other.
+    pub fn estimated_size(&self) -> usize {
+        const WORD: usize = std::mem::size_of::<usize>();
+        match self {
+            Value::String(s) => WORD + s.len(),
+            Value::Bytes(b) => WORD + b.len(),
+            Value::Array(arr) => WORD + arr.iter().map(Value::estimated_size).sum::<usize>(),
+            Value::Map(map) => {
+                WORD + map
+                    .iter()
+                    .map(|(k, v)| k.len() + v.estimated_size())
+                    .sum::<usize>()
+            }
+            _ => WORD,
+        }
+    }
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        fn write_len(out: &mut Vec<u8>, len: usize) {
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+
+        match self {
+            Value::Null => out.push(0x00),
+            Value::Bool(false) => out.push(0x01),
+            Value::Bool(true) => out.push(0x02),
+            Value::Integer(i) => {
+                out.push(0x03);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+            Value::Float(f) => {
+                let f = if f.is_nan() {
+                    f64::NAN
+                } else if *f == 0.0 {
+                    0.0
+                } else {
+                    *f
+                };
+                out.push(0x04);
+                out.extend_from_slice(&f.to_bits().to_be_bytes());
+            }
+            Value::String(s) => {
+                out.push(0x05);
+                write_len(out, s.len());
+                out.extend_from_slice(s.as_bytes());
+            }
+            Value::Bytes(b) => {
+                out.push(0x06);
+                write_len(out, b.len());
+                out.extend_from_slice(b);
+            }
+            Value::Array(arr) => {
+                out.push(0x07);
+                write_len(out, arr.len());
+                for item in arr {
+                    item.write_canonical(out);
+                }
+            }
+            Value::Map(map) => {
+                out.push(0x08);
+                write_len(out, map.len());
+                for (key, value) in map {
+                    write_len(out, key.len());
+                    out.extend_from_slice(key.as_bytes());
+                    value.write_canonical(out);
+                }
+            }
+        }
+    }
 }
 
 // Conversion from common types
@@ -583,6 +686,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn canonical_bytes_ignore_insertion_order() {
+        let mut a = Value::map();
+        a.set(&path!("x"), Value::from(1)).unwrap();
+        a.set(&path!("y"), Value::from("two")).unwrap();
+        let mut b = Value::map();
+        b.set(&path!("y"), Value::from("two")).unwrap();
+        b.set(&path!("x"), Value::from(1)).unwrap();
+
+        assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+        assert_eq!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn canonical_bytes_layout() {
+        assert_eq!(Value::Null.canonical_bytes(), vec![0x00]);
+        assert_eq!(Value::Bool(true).canonical_bytes(), vec![0x02]);
+        assert_eq!(
+            Value::from(1).canonical_bytes(),
+            vec![0x03, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+        assert_eq!(
+            Value::from("hi").canonical_bytes(),
+            vec![0x05, 0, 0, 0, 0, 0, 0, 0, 2, b'h', b'i']
+        );
+    }
+
+    #[test]
+    fn canonical_floats() {
+        assert_eq!(
+            Value::Float(-0.0).canonical_bytes(),
+            Value::Float(0.0).canonical_bytes()
+        );
+        let other_nan = f64::from_bits(f64::NAN.to_bits() | 1);
+        assert_eq!(
+            Value::Float(other_nan).canonical_bytes(),
+            Value::Float(f64::NAN).canonical_bytes()
+        );
+        assert_ne!(
+            Value::Float(1.0).canonical_bytes(),
+            Value::Integer(1).canonical_bytes()
+        );
+    }
+
+    #[test]
+    fn canonical_bytes_distinguish_structure() {
+        // Length prefixes keep adjacent strings from running together
+        let a = Value::from(vec!["ab", "c"]);
+        let b = Value::from(vec!["a", "bc"]);
+        assert_ne!(a.canonical_bytes(), b.canonical_bytes());
+        assert_ne!(
+            Value::from("x").canonical_bytes(),
+            Value::Bytes(b"x".to_vec()).canonical_bytes()
+        );
+        assert_ne!(Value::array().content_hash(), Value::map().content_hash());
+    }
+
+    #[test]
+    fn content_hash_of_null() {
+        // SHA-256 of the single byte 0x00
+        assert_eq!(Value::Null.content_hash()[..4], [0x6e, 0x34, 0x0b, 0x9c]);
+    }
+
     #[test]
     fn value_equality() {
         assert_eq!(Value::Null, Value::Null);
@@ -618,4 +784,33 @@ mod tests {
         assert!(debug.contains("String"));
         assert!(debug.contains("test"));
     }
+
+    #[test]
+    fn estimated_size_counts_contents_and_values() {
+        const WORD: usize = std::mem::size_of::<usize>();
+        assert_eq!(Value::Null.estimated_size(), WORD);
+        assert_eq!(Value::Integer(1 << 40).estimated_size(), WORD);
+        assert_eq!(Value::from("hello").estimated_size(), WORD + 5);
+        assert_eq!(Value::Bytes(vec![0; 100]).estimated_size(), WORD + 100);
+
+        let mut map = BTreeMap::new();
+        map.insert("name".to_string(), Value::from("Alice"));
+        map.insert(
+            "tags".to_string(),
+            Value::Array(vec![Value::Bool(true), Value::Null]),
+        );
+        let value = Value::Map(map);
+        // map + "name" + string + "tags" + array of two
+        assert_eq!(
+            value.estimated_size(),
+            WORD + (4 + WORD + 5) + (4 + WORD + 2 * WORD)
+        );
+
+        // Growing a value grows its estimate
+        let mut bigger = value.clone();
+        bigger
+            .set(&crate::path!("bio"), Value::from("x".repeat(1000)))
+            .unwrap();
+        assert!(bigger.estimated_size() > value.estimated_size() + 1000);
+    }
 }
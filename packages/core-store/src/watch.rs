@@ -0,0 +1,529 @@
+//! Subscribing to changes in a store.
+//!
+//! A [`Watcher`] hands out [`Subscription`]s to the changes at and below a
+//! path. Each [`Change`] carries the path that changed with its record
+//! before and after, so a subscriber can react without reading the store
+//! again:
+//!
+//! ```rust,ignore
+//! let changes = store.watch(&path!("config"))?;
+//! store.write(&path!("config/level"), Record::parsed(Value::from("debug")))?;
+//! let change = changes.try_next().unwrap();
+//! assert_eq!(change.path, path!("config/level"));
+//! ```
+//!
+//! Stores that see every write, like `InMemoryStore`, implement [`Watcher`]
+//! with a [`Watchers`] registry and report each write as it happens. Any
+//! other [`Reader`] can be watched by wrapping it in a [`PollWatcher`],
+//! which re-reads the watched paths on [`poll`](PollWatcher::poll) and
+//! reports what differs.
+//!
+//! Subscriptions are queues: changes wait in them until taken, and dropping
+//! a subscription ends it. For async code, `async_watch::Watch` (with the
+//! `async` feature) streams the changes to one path from a subscription.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::traits::delete_in;
+use crate::{Deleter, Error, Path, Reader, Record, Writer};
+
+/// A change to the record at one path.
+#[derive(Clone)]
+pub struct Change {
+    /// The path whose record changed.
+    pub path: Path,
+    /// The record before the change, `None` if there was none.
+    pub old: Option<Record>,
+    /// The record after the change, `None` if it was removed.
+    pub new: Option<Record>,
+}
+
+impl std::fmt::Debug for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let describe = |record: &Option<Record>| match record {
+            None => "none".to_string(),
+            Some(Record::Parsed(value)) => format!("{:?}", value),
+            Some(Record::Raw { bytes, format }) => format!("{} bytes of {}", bytes.len(), format),
+        };
+        f.debug_struct("Change")
+            .field("path", &self.path)
+            .field("old", &describe(&self.old))
+            .field("new", &describe(&self.new))
+            .finish()
+    }
+}
+
+/// Whether two reads found the same thing.
+fn same(a: &Option<Record>, b: &Option<Record>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(Record::Parsed(a)), Some(Record::Parsed(b))) => a == b,
+        (
+            Some(Record::Raw { bytes, format }),
+            Some(Record::Raw {
+                bytes: other_bytes,
+                format: other_format,
+            }),
+        ) => format == other_format && bytes == other_bytes,
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    changes: Mutex<VecDeque<Change>>,
+    ready: Condvar,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Queue {
+    fn push(&self, change: Change) {
+        self.changes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_back(change);
+        self.ready.notify_all();
+        let waker = self.waker.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The changes at and below one path, in the order they happened.
+///
+/// Dropping the subscription unsubscribes.
+pub struct Subscription {
+    path: Path,
+    queue: Arc<Queue>,
+}
+
+impl Subscription {
+    /// The watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The oldest change not yet taken, without waiting.
+    pub fn try_next(&self) -> Option<Change> {
+        self.changes().pop_front()
+    }
+
+    /// The oldest change not yet taken, waiting up to `timeout` for one.
+    pub fn next_timeout(&self, timeout: Duration) -> Option<Change> {
+        let deadline = Instant::now() + timeout;
+        let mut changes = self.changes();
+        loop {
+            if let Some(change) = changes.pop_front() {
+                return Some(change);
+            }
+            let left = deadline.checked_duration_since(Instant::now())?;
+            changes = self
+                .queue
+                .ready
+                .wait_timeout(changes, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// The oldest change not yet taken, or [`Poll::Pending`] with the task
+    /// woken when the next change arrives.
+    ///
+    /// Only the task that polled last is woken.
+    pub fn poll_next(&self, cx: &mut Context<'_>) -> Poll<Change> {
+        let mut changes = self.changes();
+        if let Some(change) = changes.pop_front() {
+            return Poll::Ready(change);
+        }
+        // Registered under the changes lock so a push can't slip in between
+        *self.queue.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Every change not yet taken.
+    pub fn drain(&self) -> Vec<Change> {
+        self.changes().drain(..).collect()
+    }
+
+    fn changes(&self) -> std::sync::MutexGuard<'_, VecDeque<Change>> {
+        self.queue.changes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Subscribe to the changes in a store.
+///
+/// Implementors should also override [`Reader::as_watcher`] so that the
+/// watch is found behind `dyn Store`.
+///
+/// # Object Safety
+///
+/// This trait is object-safe: you can use `Box<dyn Watcher>`.
+pub trait Watcher: Send + Sync {
+    /// Subscribe to the changes at and below `path`.
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error>;
+}
+
+impl<T: Watcher + ?Sized> Watcher for &mut T {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        (**self).watch(path)
+    }
+}
+
+impl<T: Watcher + ?Sized> Watcher for Box<T> {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        self.as_mut().watch(path)
+    }
+}
+
+/// The subscriptions of a store that reports its own writes.
+///
+/// A store keeps one of these, hands out [`subscribe`](Self::subscribe)
+/// from its [`Watcher`] impl, and brackets each write with
+/// [`before`](Self::before) and [`PendingChanges::send`]:
+///
+/// ```rust,ignore
+/// let pending = self.watchers.before(to, |path| self.get(path));
+/// self.set(to, value)?;
+/// pending.send(|path| self.get(path));
+/// ```
+///
+/// A write is reported to each subscription whose subtree it touches,
+/// once, at the deeper of the written and the watched path: writing
+/// `users` shows a subscriber of `users/alice` the change to `users/alice`,
+/// and writing `users/alice/email` shows a subscriber of `users` the change
+/// to `users/alice/email`. Writes that leave the record the same are not
+/// reported.
+#[derive(Default)]
+pub struct Watchers {
+    subscribers: Vec<(Path, Weak<Queue>)>,
+}
+
+impl Watchers {
+    /// Create a registry with no subscriptions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to the changes at and below `path`.
+    pub fn subscribe(&mut self, path: &Path) -> Subscription {
+        let queue = Arc::new(Queue::default());
+        self.subscribers
+            .push((path.clone(), Arc::downgrade(&queue)));
+        Subscription {
+            path: path.clone(),
+            queue,
+        }
+    }
+
+    /// Whether every subscription has been dropped.
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.iter().all(|(_, q)| q.strong_count() == 0)
+    }
+
+    /// Take note of what a write to `to` may change, with `read` giving
+    /// the records as they are now.
+    ///
+    /// Only the paths some subscription would see are read, so a store
+    /// with no subscribers pays nothing.
+    pub fn before(
+        &mut self,
+        to: &Path,
+        mut read: impl FnMut(&Path) -> Option<Record>,
+    ) -> PendingChanges {
+        self.subscribers.retain(|(_, q)| q.strong_count() > 0);
+        let mut old = BTreeMap::new();
+        let mut subscribers = Vec::new();
+        for (path, queue) in &self.subscribers {
+            let at = if to.has_prefix(path) {
+                to
+            } else if path.has_prefix(to) {
+                path
+            } else {
+                continue;
+            };
+            old.entry(at.clone()).or_insert_with(|| read(at));
+            subscribers.push((at.clone(), queue.clone()));
+        }
+        PendingChanges { old, subscribers }
+    }
+}
+
+/// The records a write may change, from [`Watchers::before`].
+#[must_use = "changes are only reported by `send`"]
+pub struct PendingChanges {
+    old: BTreeMap<Path, Option<Record>>,
+    subscribers: Vec<(Path, Weak<Queue>)>,
+}
+
+impl PendingChanges {
+    /// Report what changed, with `read` giving the records after the write.
+    pub fn send(self, mut read: impl FnMut(&Path) -> Option<Record>) {
+        let mut changes = BTreeMap::new();
+        for (path, old) in self.old {
+            let new = read(&path);
+            if !same(&old, &new) {
+                changes.insert(path.clone(), Change { path, old, new });
+            }
+        }
+        for (at, queue) in self.subscribers {
+            if let (Some(change), Some(queue)) = (changes.get(&at), queue.upgrade()) {
+                queue.push(change.clone());
+            }
+        }
+    }
+}
+
+/// Watches any store by reading the watched paths again.
+///
+/// [`poll`](Self::poll) reads each watched path and reports a change, at
+/// the watched path, wherever the record differs from the last one seen.
+/// Writes made through the adapter are reported straight away; writes made
+/// around it show up at the next poll.
+///
+/// ```rust,ignore
+/// let mut store = PollWatcher::new(remote);
+/// let changes = store.watch(&path!("status"))?;
+/// loop {
+///     store.poll()?;
+///     while let Some(change) = changes.try_next() { /* ... */ }
+///     std::thread::sleep(interval);
+/// }
+/// ```
+pub struct PollWatcher<R> {
+    inner: R,
+    watched: Vec<Polled>,
+}
+
+struct Polled {
+    path: Path,
+    last: Option<Record>,
+    queue: Weak<Queue>,
+}
+
+impl<R: Reader> PollWatcher<R> {
+    /// Watch `inner` by polling it.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            watched: Vec::new(),
+        }
+    }
+
+    /// Read every watched path and report the ones that changed. Returns
+    /// how many did.
+    pub fn poll(&mut self) -> Result<usize, Error> {
+        self.poll_where(|_| true)
+    }
+
+    fn poll_where(&mut self, mut affected: impl FnMut(&Path) -> bool) -> Result<usize, Error> {
+        self.watched.retain(|w| w.queue.strong_count() > 0);
+        let mut changed = 0;
+        for watched in &mut self.watched {
+            if !affected(&watched.path) {
+                continue;
+            }
+            let new = self.inner.read(&watched.path)?;
+            if same(&watched.last, &new) {
+                continue;
+            }
+            let old = std::mem::replace(&mut watched.last, new.clone());
+            if let Some(queue) = watched.queue.upgrade() {
+                queue.push(Change {
+                    path: watched.path.clone(),
+                    old,
+                    new,
+                });
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// The wrapped store. Writes made through it are seen at the next poll.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the store, ending every subscription's updates.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Reader> Watcher for PollWatcher<R> {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        let last = self.inner.read(path)?;
+        let queue = Arc::new(Queue::default());
+        self.watched.push(Polled {
+            path: path.clone(),
+            last,
+            queue: Arc::downgrade(&queue),
+        });
+        Ok(Subscription {
+            path: path.clone(),
+            queue,
+        })
+    }
+}
+
+impl<R: Reader> Reader for PollWatcher<R> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        self.inner.read(from)
+    }
+
+    fn as_lister(&mut self) -> Option<&mut dyn crate::Lister> {
+        self.inner.as_lister()
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        Some(self)
+    }
+}
+
+impl<R: Reader + Writer> Writer for PollWatcher<R> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let written = self.inner.write(to, data)?;
+        self.poll_where(|path| to.has_prefix(path) || path.has_prefix(to))?;
+        Ok(written)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl<R: Reader + Writer> Deleter for PollWatcher<R> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        delete_in(&mut self.inner, path)?;
+        self.poll_where(|watched| path.has_prefix(watched) || watched.has_prefix(path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path, Value};
+
+    /// A map of paths to values with no watching of its own.
+    #[derive(Default)]
+    struct Flat(BTreeMap<Path, Value>);
+
+    impl Reader for Flat {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.get(from).cloned().map(Record::parsed))
+        }
+    }
+
+    impl Writer for Flat {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.0.insert(to.clone(), data.into_value(&crate::NoCodec)?);
+            Ok(to.clone())
+        }
+    }
+
+    fn value(record: &Option<Record>) -> Option<Value> {
+        record.as_ref().map(|r| r.as_value().unwrap().clone())
+    }
+
+    #[test]
+    fn watchers_report_at_the_deeper_path() {
+        let mut tree = Flat::default();
+        tree.0.insert(path!("users/alice"), Value::Integer(1));
+
+        let mut watchers = Watchers::new();
+        let all = watchers.subscribe(&path!("users"));
+        let alice = watchers.subscribe(&path!("users/alice"));
+        let other = watchers.subscribe(&path!("groups"));
+
+        let pending = watchers.before(&path!("users/alice"), |p| tree.read(p).unwrap());
+        tree.0.insert(path!("users/alice"), Value::Integer(2));
+        pending.send(|p| tree.read(p).unwrap());
+
+        for subscription in [&all, &alice] {
+            let change = subscription.try_next().unwrap();
+            assert_eq!(change.path, path!("users/alice"));
+            assert_eq!(value(&change.old), Some(Value::Integer(1)));
+            assert_eq!(value(&change.new), Some(Value::Integer(2)));
+            assert!(subscription.try_next().is_none());
+        }
+        assert!(other.try_next().is_none());
+
+        // Unchanged records are not reported
+        let pending = watchers.before(&path!("users/alice"), |p| tree.read(p).unwrap());
+        pending.send(|p| tree.read(p).unwrap());
+        assert!(all.drain().is_empty());
+
+        drop((all, alice, other));
+        assert!(watchers.is_empty());
+    }
+
+    #[test]
+    fn poll_watcher_reports_writes_and_outside_changes() {
+        let mut store = PollWatcher::new(Flat::default());
+        let changes = store.watch(&path!("status")).unwrap();
+
+        store
+            .write(&path!("status"), Record::parsed(Value::from("up")))
+            .unwrap();
+        let change = changes.try_next().unwrap();
+        assert_eq!(value(&change.old), None);
+        assert_eq!(value(&change.new), Some(Value::from("up")));
+
+        store
+            .inner_mut()
+            .0
+            .insert(path!("status"), Value::from("down"));
+        assert!(changes.try_next().is_none());
+        assert_eq!(store.poll().unwrap(), 1);
+        assert_eq!(
+            value(&changes.next_timeout(Duration::ZERO).unwrap().new),
+            Some(Value::from("down"))
+        );
+        assert_eq!(store.poll().unwrap(), 0);
+
+        // Behind dyn Store
+        let dyn_store: &mut dyn crate::Store = &mut store;
+        let again = dyn_store.watch(&path!("status")).unwrap();
+        assert!(again.try_next().is_none());
+    }
+
+    #[test]
+    fn next_timeout_waits_for_another_thread() {
+        let mut watchers = Watchers::new();
+        let changes = watchers.subscribe(&path!("x"));
+        let handle = std::thread::spawn(move || {
+            watchers
+                .before(&path!("x"), |_| None)
+                .send(|_| Some(Record::parsed(Value::Integer(1))));
+        });
+        let change = changes.next_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(value(&change.new), Some(Value::Integer(1)));
+        handle.join().unwrap();
+        assert!(changes.next_timeout(Duration::from_millis(1)).is_none());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::conformance::{self, tests::MapStore};
+
+        let mut store = PollWatcher::new(MapStore::default());
+        let changes = store.watch(&path!("a")).unwrap();
+        conformance::assert_missing(&mut store, &path!("a"));
+        conformance::assert_round_trip(&mut store, &path!("a"), Value::Integer(1));
+        conformance::assert_deletes(&mut store, &path!("a"), Value::Integer(1));
+
+        // The delete is reported like any other change
+        let last = std::iter::from_fn(|| changes.try_next()).last();
+        assert!(last.unwrap().new.is_none());
+    }
+}
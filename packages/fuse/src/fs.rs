@@ -0,0 +1,718 @@
+//! The filesystem view of a store.
+//!
+//! [`StoreFs`] answers filesystem operations on inode numbers and file
+//! handles, the way the kernel asks them, without knowing anything about
+//! the kernel. Maps and arrays are directories, every other value is a file
+//! holding its encoding, and a file handle buffers the bytes it is given
+//! until it is flushed, when they are decoded and written back.
+
+use std::collections::HashMap;
+
+use structfs_core_store::{Codec, Error, Format, Path, Record, Store, Value};
+use structfs_serde_store::JsonCodec;
+
+/// Inode number of the mount's root directory.
+pub const ROOT_INO: u64 = 1;
+
+/// Errors from filesystem operations.
+///
+/// Each maps onto the errno the kernel expects, see [`FsError::errno`].
+#[derive(thiserror::Error, Debug)]
+pub enum FsError {
+    #[error("no such file or directory")]
+    NotFound,
+
+    #[error("not a directory")]
+    NotDirectory,
+
+    #[error("is a directory")]
+    IsDirectory,
+
+    #[error("directory not empty")]
+    NotEmpty,
+
+    #[error("read-only filesystem")]
+    ReadOnly,
+
+    #[error("invalid file name '{0}'")]
+    InvalidName(String),
+
+    #[error("bad file handle {0}")]
+    BadHandle(u64),
+
+    #[error("store error: {0}")]
+    Store(#[from] Error),
+}
+
+impl FsError {
+    /// The errno reported to the kernel for this error.
+    #[cfg(target_os = "linux")]
+    pub fn errno(&self) -> i32 {
+        match self {
+            FsError::NotFound => libc::ENOENT,
+            FsError::NotDirectory => libc::ENOTDIR,
+            FsError::IsDirectory => libc::EISDIR,
+            FsError::NotEmpty => libc::ENOTEMPTY,
+            FsError::ReadOnly => libc::EROFS,
+            FsError::InvalidName(_) => libc::EINVAL,
+            FsError::BadHandle(_) => libc::EBADF,
+            FsError::Store(_) => libc::EIO,
+        }
+    }
+}
+
+/// Whether an inode is a directory or a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    /// A map or an array, or a path the store lists children for.
+    Directory,
+    /// Any other value, holding its encoding.
+    File,
+}
+
+/// The attributes of an inode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attr {
+    pub ino: u64,
+    pub kind: FileKind,
+    /// Length of a file's content; zero for directories.
+    pub size: u64,
+}
+
+/// One entry of a directory listing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub ino: u64,
+    pub name: String,
+    pub kind: FileKind,
+}
+
+/// What a path holds, as far as the filesystem is concerned.
+enum Node {
+    Directory,
+    /// The file's content, and the format of a raw record to write it back
+    /// in when that isn't the filesystem's own.
+    File(Vec<u8>, Option<Format>),
+}
+
+/// Two-way map between paths and the inode numbers handed to the kernel.
+///
+/// Numbers are never reused, so the kernel can't confuse a path that was
+/// deleted and written again with what was there before.
+struct Inodes {
+    paths: HashMap<u64, Path>,
+    inos: HashMap<Path, u64>,
+    next: u64,
+}
+
+impl Inodes {
+    fn new() -> Self {
+        let mut inodes = Self {
+            paths: HashMap::new(),
+            inos: HashMap::new(),
+            next: ROOT_INO + 1,
+        };
+        inodes.paths.insert(ROOT_INO, Path::parse("").unwrap());
+        inodes.inos.insert(Path::parse("").unwrap(), ROOT_INO);
+        inodes
+    }
+
+    fn path(&self, ino: u64) -> Result<&Path, FsError> {
+        self.paths.get(&ino).ok_or(FsError::NotFound)
+    }
+
+    fn ino(&mut self, path: &Path) -> u64 {
+        if let Some(&ino) = self.inos.get(path) {
+            return ino;
+        }
+        let ino = self.next;
+        self.next += 1;
+        self.paths.insert(ino, path.clone());
+        self.inos.insert(path.clone(), ino);
+        ino
+    }
+
+    /// Move `from` and everything below it to `to`, dropping whatever was
+    /// numbered at and below `to` before.
+    fn rename(&mut self, from: &Path, to: &Path) {
+        self.inos.retain(|path, _| !path.has_prefix(to));
+        self.paths.retain(|_, path| !path.has_prefix(to));
+        let moved: Vec<(u64, Path)> = self
+            .paths
+            .iter()
+            .filter_map(|(&ino, path)| Some((ino, to.join(&path.strip_prefix(from)?))))
+            .collect();
+        for (ino, path) in moved {
+            let old = self.paths.insert(ino, path.clone());
+            if let Some(old) = old {
+                self.inos.remove(&old);
+            }
+            self.inos.insert(path, ino);
+        }
+    }
+}
+
+/// An open file: its content, buffered until it is flushed.
+struct Handle {
+    path: Path,
+    content: Vec<u8>,
+    format: Option<Format>,
+    dirty: bool,
+}
+
+/// A [`Store`] seen as a filesystem.
+///
+/// Directories are maps and arrays (or anything the store lists children
+/// for), and files are the other values, encoded with the filesystem's
+/// codec: JSON unless [`with_codec`](Self::with_codec) chooses another.
+/// Raw records are served as their own bytes.
+///
+/// File names that aren't valid path components, such as an editor's
+/// `.notes.swp`, are stored under their [namecode](namecode) encoding and
+/// listed decoded, so tools that make such files keep working.
+///
+/// Writes to an open file are buffered and reach the store when the file is
+/// flushed or closed. An empty file is `null`, and content the codec can't
+/// decode fails the flush with `EIO`, leaving the store as it was.
+pub struct StoreFs<S> {
+    store: S,
+    codec: Box<dyn Codec>,
+    format: Format,
+    read_only: bool,
+    inodes: Inodes,
+    handles: HashMap<u64, Handle>,
+    next_handle: u64,
+}
+
+impl<S> StoreFs<S> {
+    /// The store being served.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The store being served, mutably.
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Stop serving and return the store.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+}
+
+impl<S: Store> StoreFs<S> {
+    /// Serve `store` with JSON files.
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            codec: Box::new(JsonCodec),
+            format: Format::JSON,
+            read_only: false,
+            inodes: Inodes::new(),
+            handles: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    /// Encode files with `codec` in `format` instead of JSON.
+    pub fn with_codec(mut self, codec: impl Codec + 'static, format: Format) -> Self {
+        self.codec = Box::new(codec);
+        self.format = format;
+        self
+    }
+
+    /// Refuse every change with `EROFS`.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Whether changes are refused.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The store path behind an inode number.
+    pub fn path(&self, ino: u64) -> Option<&Path> {
+        self.inodes.paths.get(&ino)
+    }
+
+    /// The inode number of a store path, numbering it if it's new.
+    pub(crate) fn ino_of(&mut self, path: &Path) -> u64 {
+        self.inodes.ino(path)
+    }
+
+    /// Attributes of the entry `name` in the directory `parent`.
+    pub fn lookup(&mut self, parent: u64, name: &str) -> Result<Attr, FsError> {
+        let path = self.child(parent, name).map_err(|_| FsError::NotFound)?;
+        self.attr_of(&path)
+    }
+
+    /// Attributes of an inode.
+    pub fn getattr(&mut self, ino: u64) -> Result<Attr, FsError> {
+        let path = self.inodes.path(ino)?.clone();
+        self.attr_of(&path)
+    }
+
+    /// The entries of a directory, without `.` and `..`.
+    pub fn readdir(&mut self, ino: u64) -> Result<Vec<DirEntry>, FsError> {
+        let path = self.inodes.path(ino)?.clone();
+        let names = match Store::list(&mut self.store, &path)? {
+            Some(names) => names,
+            None if path.is_empty() => Vec::new(),
+            None => return Err(FsError::NotFound),
+        };
+        if let Node::File(..) = self.node(&path)? {
+            return Err(FsError::NotDirectory);
+        }
+        let mut entries = Vec::with_capacity(names.len());
+        for key in names {
+            let Ok(child) = Path::try_from_components(vec![key.clone()]) else {
+                continue;
+            };
+            let child = path.join(&child);
+            // An entry can vanish between listing and reading it.
+            let Ok(attr) = self.attr_of(&child) else {
+                continue;
+            };
+            entries.push(DirEntry {
+                ino: attr.ino,
+                name: namecode::decode(&key).unwrap_or(key),
+                kind: attr.kind,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Open a file, reading its content into a new handle.
+    ///
+    /// With `truncate` the handle starts empty and the file is written as
+    /// empty when it is flushed, even if nothing else is written to it.
+    pub fn open(&mut self, ino: u64, truncate: bool) -> Result<u64, FsError> {
+        let path = self.inodes.path(ino)?.clone();
+        let (content, format) = match self.node(&path)? {
+            Node::Directory => return Err(FsError::IsDirectory),
+            Node::File(content, format) => (content, format),
+        };
+        if truncate && self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let content = if truncate { Vec::new() } else { content };
+        Ok(self.insert_handle(Handle {
+            path,
+            content,
+            format,
+            dirty: truncate,
+        }))
+    }
+
+    /// Up to `size` bytes of an open file from `offset`.
+    pub fn read(&self, fh: u64, offset: u64, size: u32) -> Result<&[u8], FsError> {
+        let handle = self.handles.get(&fh).ok_or(FsError::BadHandle(fh))?;
+        let start = (offset as usize).min(handle.content.len());
+        let end = start
+            .saturating_add(size as usize)
+            .min(handle.content.len());
+        Ok(&handle.content[start..end])
+    }
+
+    /// Write into an open file's buffer, returning how much was written.
+    pub fn write(&mut self, fh: u64, offset: u64, data: &[u8]) -> Result<u32, FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let handle = self.handles.get_mut(&fh).ok_or(FsError::BadHandle(fh))?;
+        let offset = offset as usize;
+        let end = offset + data.len();
+        if handle.content.len() < end {
+            handle.content.resize(end, 0);
+        }
+        handle.content[offset..end].copy_from_slice(data);
+        handle.dirty = true;
+        Ok(data.len() as u32)
+    }
+
+    /// Truncate or extend a file to `size` bytes.
+    ///
+    /// Through a handle the change waits for the flush like any write.
+    /// Without one it applies to the file's open handles, since the kernel
+    /// truncates files opened with `O_TRUNC` that way, or, when the file
+    /// isn't open, the file is read, resized and written back at once.
+    pub fn set_size(&mut self, ino: u64, fh: Option<u64>, size: u64) -> Result<Attr, FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let path = self.inodes.path(ino)?.clone();
+        let fhs: Vec<u64> = match fh {
+            Some(fh) => vec![fh],
+            None => self
+                .handles
+                .iter()
+                .filter(|(_, handle)| handle.path == path)
+                .map(|(&fh, _)| fh)
+                .collect(),
+        };
+        if fhs.is_empty() {
+            let fh = self.open(ino, size == 0)?;
+            let result = self.set_size(ino, Some(fh), size);
+            let released = self.release(fh);
+            result?;
+            released?;
+        }
+        for fh in fhs {
+            let handle = self.handles.get_mut(&fh).ok_or(FsError::BadHandle(fh))?;
+            handle.content.resize(size as usize, 0);
+            handle.dirty = true;
+        }
+        self.getattr(ino)
+    }
+
+    /// Write an open file's buffer back to the store if it changed.
+    pub fn flush(&mut self, fh: u64) -> Result<(), FsError> {
+        let handle = self.handles.get(&fh).ok_or(FsError::BadHandle(fh))?;
+        if !handle.dirty {
+            return Ok(());
+        }
+        let record = match &handle.format {
+            Some(format) => Record::raw(handle.content.clone(), format.clone()),
+            None if handle.content.iter().all(u8::is_ascii_whitespace) => {
+                Record::parsed(Value::Null)
+            }
+            None => Record::parsed(
+                self.codec
+                    .decode(&handle.content.clone().into(), &self.format)?,
+            ),
+        };
+        let path = handle.path.clone();
+        self.store.write(&path, record)?;
+        if let Some(handle) = self.handles.get_mut(&fh) {
+            handle.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Flush and close an open file.
+    pub fn release(&mut self, fh: u64) -> Result<(), FsError> {
+        let flushed = self.flush(fh);
+        self.handles.remove(&fh);
+        flushed
+    }
+
+    /// Create an empty (`null`) file and open it.
+    pub fn create(&mut self, parent: u64, name: &str) -> Result<(Attr, u64), FsError> {
+        let path = self.new_child(parent, name)?;
+        self.store.write(&path, Record::parsed(Value::Null))?;
+        let attr = self.attr_of(&path)?;
+        let fh = self.insert_handle(Handle {
+            path,
+            content: Vec::new(),
+            format: None,
+            dirty: false,
+        });
+        Ok((attr, fh))
+    }
+
+    /// Create an empty map.
+    pub fn mkdir(&mut self, parent: u64, name: &str) -> Result<Attr, FsError> {
+        let path = self.new_child(parent, name)?;
+        self.store
+            .write(&path, Record::parsed(Value::Map(Default::default())))?;
+        self.attr_of(&path)
+    }
+
+    /// Delete a file.
+    pub fn unlink(&mut self, parent: u64, name: &str) -> Result<(), FsError> {
+        let path = self.existing_child(parent, name)?;
+        if let Node::Directory = self.node(&path)? {
+            return Err(FsError::IsDirectory);
+        }
+        Store::delete(&mut self.store, &path)?;
+        Ok(())
+    }
+
+    /// Delete an empty directory.
+    pub fn rmdir(&mut self, parent: u64, name: &str) -> Result<(), FsError> {
+        let path = self.existing_child(parent, name)?;
+        if let Node::File(..) = self.node(&path)? {
+            return Err(FsError::NotDirectory);
+        }
+        let ino = self.inodes.ino(&path);
+        if !self.readdir(ino)?.is_empty() {
+            return Err(FsError::NotEmpty);
+        }
+        Store::delete(&mut self.store, &path)?;
+        Ok(())
+    }
+
+    /// Move an entry, replacing whatever is at the destination.
+    ///
+    /// The store has no move, so this reads the entry, writes it at the
+    /// destination and deletes the original.
+    pub fn rename(
+        &mut self,
+        parent: u64,
+        name: &str,
+        new_parent: u64,
+        new_name: &str,
+    ) -> Result<(), FsError> {
+        let from = self.existing_child(parent, name)?;
+        let to = self.new_child(new_parent, new_name)?;
+        if from == to {
+            return Ok(());
+        }
+        let record = self.store.read(&from)?.ok_or(FsError::NotFound)?;
+        self.store.write(&to, record)?;
+        Store::delete(&mut self.store, &from)?;
+        self.inodes.rename(&from, &to);
+        for handle in self.handles.values_mut() {
+            if let Some(rest) = handle.path.strip_prefix(&from) {
+                handle.path = to.join(&rest);
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_handle(&mut self, handle: Handle) -> u64 {
+        let fh = self.next_handle;
+        self.next_handle += 1;
+        self.handles.insert(fh, handle);
+        fh
+    }
+
+    /// The path of `name` in the directory `parent`.
+    fn child(&self, parent: u64, name: &str) -> Result<Path, FsError> {
+        let parent = self.inodes.path(parent)?;
+        let key = match Path::try_from_components(vec![name.to_string()]) {
+            Ok(key) => key,
+            Err(_) => Path::try_from_components(vec![namecode::encode(name)])
+                .map_err(|_| FsError::InvalidName(name.to_string()))?,
+        };
+        Ok(parent.join(&key))
+    }
+
+    fn existing_child(&mut self, parent: u64, name: &str) -> Result<Path, FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let path = self.child(parent, name).map_err(|_| FsError::NotFound)?;
+        self.node(&path)?;
+        Ok(path)
+    }
+
+    fn new_child(&mut self, parent: u64, name: &str) -> Result<Path, FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let parent_path = self.inodes.path(parent)?.clone();
+        if let Node::File(..) = self.node(&parent_path)? {
+            return Err(FsError::NotDirectory);
+        }
+        self.child(parent, name)
+    }
+
+    fn attr_of(&mut self, path: &Path) -> Result<Attr, FsError> {
+        let ino = self.inodes.ino(path);
+        let attr = match self.node(path)? {
+            Node::Directory => Attr {
+                ino,
+                kind: FileKind::Directory,
+                size: 0,
+            },
+            Node::File(content, _) => {
+                // A file being written shows what has been written so far.
+                let open = self
+                    .handles
+                    .values()
+                    .find(|handle| handle.dirty && &handle.path == path);
+                Attr {
+                    ino,
+                    kind: FileKind::File,
+                    size: open.map_or(content.len(), |handle| handle.content.len()) as u64,
+                }
+            }
+        };
+        Ok(attr)
+    }
+
+    fn node(&mut self, path: &Path) -> Result<Node, FsError> {
+        let record = match self.store.read(path) {
+            Ok(record) => record,
+            // Some stores only list the paths above their values.
+            Err(error) => match Store::list(&mut self.store, path) {
+                Ok(Some(_)) => return Ok(Node::Directory),
+                _ => return Err(error.into()),
+            },
+        };
+        let value = match record {
+            Some(Record::Parsed(value)) => value,
+            Some(Record::Raw { bytes, format }) => {
+                if format == self.format {
+                    match self.codec.decode(&bytes, &format) {
+                        Ok(value) => value,
+                        Err(_) => return Ok(Node::File(bytes.to_vec(), Some(format))),
+                    }
+                } else {
+                    return Ok(Node::File(bytes.to_vec(), Some(format)));
+                }
+            }
+            None if path.is_empty() => return Ok(Node::Directory),
+            None => {
+                return match Store::list(&mut self.store, path)? {
+                    Some(_) => Ok(Node::Directory),
+                    None => Err(FsError::NotFound),
+                }
+            }
+        };
+        match value {
+            Value::Map(_) | Value::Array(_) => Ok(Node::Directory),
+            value => {
+                let bytes = self.codec.encode(&value, &self.format)?;
+                Ok(Node::File(bytes.to_vec(), None))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, Reader, Writer};
+    use structfs_json_store::InMemoryStore;
+
+    fn value(store: &mut impl Reader, path: &structfs_core_store::Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.as_value().unwrap().clone())
+    }
+
+    fn fs() -> StoreFs<InMemoryStore> {
+        let mut store = InMemoryStore::new();
+        store
+            .write(
+                &path!(""),
+                Record::parsed(Value::Map(
+                    [
+                        ("name".to_string(), Value::String("Alice".into())),
+                        (
+                            "tags".to_string(),
+                            Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )),
+            )
+            .unwrap();
+        StoreFs::new(store)
+    }
+
+    fn names(fs: &mut StoreFs<InMemoryStore>, ino: u64) -> Vec<String> {
+        let mut names: Vec<_> = fs
+            .readdir(ino)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn maps_are_directories_and_values_are_files() {
+        let mut fs = fs();
+        assert_eq!(names(&mut fs, ROOT_INO), vec!["name", "tags"]);
+
+        let tags = fs.lookup(ROOT_INO, "tags").unwrap();
+        assert_eq!(tags.kind, FileKind::Directory);
+        assert_eq!(names(&mut fs, tags.ino), vec!["0", "1"]);
+
+        let name = fs.lookup(ROOT_INO, "name").unwrap();
+        assert_eq!(name.kind, FileKind::File);
+        assert_eq!(name.size, 7);
+        let fh = fs.open(name.ino, false).unwrap();
+        assert_eq!(fs.read(fh, 0, 100).unwrap(), b"\"Alice\"");
+        assert_eq!(fs.read(fh, 1, 3).unwrap(), b"Ali");
+        fs.release(fh).unwrap();
+
+        assert!(matches!(
+            fs.lookup(ROOT_INO, "missing"),
+            Err(FsError::NotFound)
+        ));
+        assert!(matches!(
+            fs.open(tags.ino, false),
+            Err(FsError::IsDirectory)
+        ));
+        assert!(matches!(fs.readdir(name.ino), Err(FsError::NotDirectory)));
+    }
+
+    #[test]
+    fn writes_reach_the_store_on_flush() {
+        let mut fs = fs();
+        let name = fs.lookup(ROOT_INO, "name").unwrap();
+        let fh = fs.open(name.ino, true).unwrap();
+        fs.write(fh, 0, b"\"Bob\"\n").unwrap();
+        assert_eq!(fs.getattr(name.ino).unwrap().size, 6);
+        fs.release(fh).unwrap();
+        assert_eq!(
+            value(fs.store_mut(), &path!("name")),
+            Some(Value::String("Bob".into()))
+        );
+
+        let fh = fs.open(name.ino, true).unwrap();
+        fs.write(fh, 0, b"{not json").unwrap();
+        assert!(matches!(fs.release(fh), Err(FsError::Store(_))));
+        assert_eq!(
+            value(fs.store_mut(), &path!("name")),
+            Some(Value::String("Bob".into()))
+        );
+
+        fs.set_size(name.ino, None, 0).unwrap();
+        assert_eq!(value(fs.store_mut(), &path!("name")), Some(Value::Null));
+    }
+
+    #[test]
+    fn create_mkdir_rename_and_delete() {
+        let mut fs = fs();
+        let dir = fs.mkdir(ROOT_INO, "notes").unwrap();
+        let (file, fh) = fs.create(dir.ino, ".todo.swp").unwrap();
+        fs.write(fh, 0, br#"{"done": false}"#).unwrap();
+        fs.release(fh).unwrap();
+        assert_eq!(names(&mut fs, dir.ino), vec![".todo.swp"]);
+        assert_eq!(fs.getattr(file.ino).unwrap().kind, FileKind::Directory);
+
+        fs.rename(dir.ino, ".todo.swp", dir.ino, "todo").unwrap();
+        assert_eq!(names(&mut fs, dir.ino), vec!["todo"]);
+        assert_eq!(fs.path(file.ino), Some(&path!("notes/todo")));
+        assert!(matches!(
+            fs.unlink(dir.ino, "todo"),
+            Err(FsError::IsDirectory)
+        ));
+        assert!(matches!(
+            fs.rmdir(ROOT_INO, "notes"),
+            Err(FsError::NotEmpty)
+        ));
+
+        fs.rmdir(dir.ino, "todo").unwrap_err();
+        let todo = fs.lookup(dir.ino, "todo").unwrap();
+        fs.unlink(todo.ino, "done").unwrap();
+        fs.rmdir(dir.ino, "todo").unwrap();
+        fs.rmdir(ROOT_INO, "notes").unwrap();
+        assert_eq!(names(&mut fs, ROOT_INO), vec!["name", "tags"]);
+    }
+
+    #[test]
+    fn read_only_refuses_changes() {
+        let mut fs = fs().read_only();
+        let name = fs.lookup(ROOT_INO, "name").unwrap();
+        assert!(matches!(fs.open(name.ino, true), Err(FsError::ReadOnly)));
+        let fh = fs.open(name.ino, false).unwrap();
+        assert!(matches!(fs.write(fh, 0, b"1"), Err(FsError::ReadOnly)));
+        assert!(matches!(fs.mkdir(ROOT_INO, "x"), Err(FsError::ReadOnly)));
+        assert!(matches!(
+            fs.unlink(ROOT_INO, "name"),
+            Err(FsError::ReadOnly)
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+//! Answering the kernel through [`fuser`].
+//!
+//! [`Kernel`] implements [`fuser::Filesystem`] by calling into a
+//! [`StoreFs`], which it shares with whoever mounted it so the store can be
+//! handed back after unmounting. fuser speaks the protocol; only the
+//! operations a store can answer are implemented, and fuser answers the
+//! rest with `ENOSYS`, which the kernel remembers and stops sending.
+
+use std::ffi::OsStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use fuser::{
+    AccessFlags, BsdFileFlags, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags,
+    Generation, INodeNo, LockOwner, OpenFlags, RenameFlags, ReplyAttr, ReplyCreate, ReplyData,
+    ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyStatfs, ReplyWrite, Request, TimeOrNow,
+    WriteFlags,
+};
+use structfs_core_store::Store;
+
+use crate::fs::{Attr, FileKind, FsError, StoreFs, ROOT_INO};
+
+/// How long the kernel may cache entries and attributes: not at all, so
+/// changes made through the store show up immediately.
+const TTL: Duration = Duration::ZERO;
+
+/// A [`StoreFs`] shared between a session and its mount.
+pub(crate) type Shared<S> = Arc<Mutex<StoreFs<S>>>;
+
+/// The user and group shown as the owner of every inode.
+pub(crate) struct Owner {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Answers kernel requests from a [`StoreFs`].
+pub(crate) struct Kernel<S> {
+    fs: Shared<S>,
+    owner: Owner,
+    /// Every inode shows the mount time, so editors don't see a file they
+    /// have open as changed by someone else.
+    time: SystemTime,
+}
+
+impl<S: Store> Kernel<S> {
+    pub(crate) fn new(fs: Shared<S>, owner: Owner) -> Self {
+        Self {
+            fs,
+            owner,
+            time: SystemTime::now(),
+        }
+    }
+
+    fn fs(&self) -> MutexGuard<'_, StoreFs<S>> {
+        // A request that panicked leaves the filesystem as consistent as
+        // any failed one.
+        self.fs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The entries of a directory from `offset`, with `.` and `..` first.
+    ///
+    /// Each entry's offset is the position of the next, so the kernel can
+    /// continue where a full reply stopped.
+    fn entries(&self, ino: u64, offset: u64) -> Result<Vec<(u64, String, FileKind)>, FsError> {
+        let mut fs = self.fs();
+        let parent = match fs.path(ino) {
+            Some(path) if !path.is_empty() => {
+                let parent = path.slice(0, path.len() - 1);
+                fs.ino_of(&parent)
+            }
+            _ => ROOT_INO,
+        };
+        let mut entries = vec![
+            (ino, ".".to_string(), FileKind::Directory),
+            (parent, "..".to_string(), FileKind::Directory),
+        ];
+        let listed = fs.readdir(ino)?;
+        entries.extend(listed.into_iter().map(|e| (e.ino, e.name, e.kind)));
+        Ok(entries.into_iter().skip(offset as usize).collect())
+    }
+
+    /// The attributes fuser reports for `attr`.
+    fn file_attr(&self, attr: &Attr, read_only: bool) -> FileAttr {
+        let write = if read_only { 0 } else { 0o200 };
+        let (kind, perm, nlink) = match attr.kind {
+            FileKind::Directory => (FileType::Directory, 0o555 | write, 2),
+            FileKind::File => (FileType::RegularFile, 0o444 | write, 1),
+        };
+        FileAttr {
+            ino: INodeNo(attr.ino),
+            size: attr.size,
+            blocks: attr.size.div_ceil(512),
+            atime: self.time,
+            mtime: self.time,
+            ctime: self.time,
+            crtime: self.time,
+            kind,
+            perm,
+            nlink,
+            uid: self.owner.uid,
+            gid: self.owner.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+
+    fn reply_entry(&self, attr: Result<Attr, FsError>, reply: ReplyEntry) {
+        let read_only = self.fs().is_read_only();
+        match attr {
+            Ok(attr) => reply.entry(&TTL, &self.file_attr(&attr, read_only), Generation(0)),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn reply_attr(&self, attr: Result<Attr, FsError>, reply: ReplyAttr) {
+        let read_only = self.fs().is_read_only();
+        match attr {
+            Ok(attr) => reply.attr(&TTL, &self.file_attr(&attr, read_only)),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+}
+
+/// The kernel's error for `e`.
+fn errno(e: FsError) -> Errno {
+    Errno::from_i32(e.errno())
+}
+
+fn reply_empty(result: Result<(), FsError>, reply: ReplyEmpty) {
+    match result {
+        Ok(()) => reply.ok(),
+        Err(e) => reply.error(errno(e)),
+    }
+}
+
+/// File names the store can't hold are never there.
+fn name(name: &OsStr) -> Result<&str, FsError> {
+    name.to_str().ok_or(FsError::NotFound)
+}
+
+impl<S: Store + 'static> Filesystem for Kernel<S> {
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        let attr = self::name(name).and_then(|name| self.fs().lookup(parent.0, name));
+        self.reply_entry(attr, reply);
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        let attr = self.fs().getattr(ino.0);
+        self.reply_attr(attr, reply);
+    }
+
+    fn setattr(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<FileHandle>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<BsdFileFlags>,
+        reply: ReplyAttr,
+    ) {
+        // Modes, owners and times aren't stored and are accepted only so
+        // that `touch` and `cp -p` succeed.
+        let attr = match size {
+            Some(size) => self.fs().set_size(ino.0, fh.map(|fh| fh.0), size),
+            None => self.fs().getattr(ino.0),
+        };
+        self.reply_attr(attr, reply);
+    }
+
+    fn mkdir(
+        &self,
+        _req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let attr = self::name(name).and_then(|name| self.fs().mkdir(parent.0, name));
+        self.reply_entry(attr, reply);
+    }
+
+    fn unlink(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        reply_empty(
+            self::name(name).and_then(|name| self.fs().unlink(parent.0, name)),
+            reply,
+        );
+    }
+
+    fn rmdir(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        reply_empty(
+            self::name(name).and_then(|name| self.fs().rmdir(parent.0, name)),
+            reply,
+        );
+    }
+
+    fn rename(
+        &self,
+        _req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        newparent: INodeNo,
+        newname: &OsStr,
+        flags: RenameFlags,
+        reply: ReplyEmpty,
+    ) {
+        if !(flags - RenameFlags::RENAME_NOREPLACE).is_empty() {
+            return reply.error(Errno::EINVAL);
+        }
+        let (Ok(name), Ok(newname)) = (self::name(name), self::name(newname)) else {
+            return reply.error(Errno::ENOENT);
+        };
+        let mut fs = self.fs();
+        if flags.contains(RenameFlags::RENAME_NOREPLACE) && fs.lookup(newparent.0, newname).is_ok()
+        {
+            return reply.error(Errno::EEXIST);
+        }
+        reply_empty(fs.rename(parent.0, name, newparent.0, newname), reply);
+    }
+
+    /// Reads and writes bypass the page cache, because a file's content is
+    /// made fresh from the store on every open.
+    fn open(&self, _req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+        match self.fs().open(ino.0, flags.0 & libc::O_TRUNC != 0) {
+            Ok(fh) => reply.opened(FileHandle(fh), FopenFlags::FOPEN_DIRECT_IO),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn read(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        match self.fs().read(fh.0, offset, size) {
+            Ok(data) => reply.data(data),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn write(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        offset: u64,
+        data: &[u8],
+        _write_flags: WriteFlags,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyWrite,
+    ) {
+        match self.fs().write(fh.0, offset, data) {
+            Ok(written) => reply.written(written),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn flush(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        _lock_owner: LockOwner,
+        reply: ReplyEmpty,
+    ) {
+        reply_empty(self.fs().flush(fh.0), reply);
+    }
+
+    fn fsync(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        _datasync: bool,
+        reply: ReplyEmpty,
+    ) {
+        reply_empty(self.fs().flush(fh.0), reply);
+    }
+
+    fn release(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        // The close has already returned by the time the kernel releases a
+        // file, so there's nobody to report to.
+        let _ = self.fs().release(fh.0);
+        reply.ok();
+    }
+
+    fn create(
+        &self,
+        _req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let mut fs = self.fs();
+        let read_only = fs.is_read_only();
+        match self::name(name).and_then(|name| fs.create(parent.0, name)) {
+            Ok((attr, fh)) => reply.created(
+                &TTL,
+                &self.file_attr(&attr, read_only),
+                Generation(0),
+                FileHandle(fh),
+                FopenFlags::FOPEN_DIRECT_IO,
+            ),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn opendir(&self, _req: &Request, ino: INodeNo, _flags: OpenFlags, reply: ReplyOpen) {
+        match self.fs().getattr(ino.0) {
+            Ok(Attr {
+                kind: FileKind::Directory,
+                ..
+            }) => reply.opened(FileHandle(0), FopenFlags::empty()),
+            Ok(_) => reply.error(errno(FsError::NotDirectory)),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn readdir(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        mut reply: ReplyDirectory,
+    ) {
+        let entries = match self.entries(ino.0, offset) {
+            Ok(entries) => entries,
+            Err(e) => return reply.error(errno(e)),
+        };
+        for (next, (ino, name, kind)) in (offset + 1..).zip(entries) {
+            let kind = match kind {
+                FileKind::Directory => FileType::Directory,
+                FileKind::File => FileType::RegularFile,
+            };
+            if reply.add(INodeNo(ino), next, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn fsyncdir(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        _fh: FileHandle,
+        _datasync: bool,
+        reply: ReplyEmpty,
+    ) {
+        reply.ok();
+    }
+
+    fn statfs(&self, _req: &Request, _ino: INodeNo, reply: ReplyStatfs) {
+        reply.statfs(0, 0, 0, 0, 0, 4096, 255, 4096);
+    }
+
+    fn access(&self, _req: &Request, _ino: INodeNo, _mask: AccessFlags, reply: ReplyEmpty) {
+        reply.ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, Record, Value, Writer};
+    use structfs_json_store::InMemoryStore;
+
+    fn kernel(fs: StoreFs<InMemoryStore>) -> Kernel<InMemoryStore> {
+        Kernel::new(Arc::new(Mutex::new(fs)), Owner { uid: 1, gid: 2 })
+    }
+
+    fn store() -> InMemoryStore {
+        let mut store = InMemoryStore::new();
+        store
+            .write(
+                &path!("greeting"),
+                Record::parsed(Value::String("hi".into())),
+            )
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn attributes_show_the_owner_and_mode() {
+        let k = kernel(StoreFs::new(store()));
+        let attr = k.fs().lookup(ROOT_INO, "greeting").unwrap();
+        let file = k.file_attr(&attr, false);
+        assert_eq!(file.kind, FileType::RegularFile);
+        assert_eq!(file.size, 4); // "hi" in quotes
+        assert_eq!((file.perm, file.nlink), (0o644, 1));
+        assert_eq!((file.uid, file.gid), (1, 2));
+        assert_eq!(file.mtime, k.time);
+        assert_eq!(k.file_attr(&attr, true).perm, 0o444);
+
+        let root = k.fs().getattr(ROOT_INO).unwrap();
+        let root = k.file_attr(&root, false);
+        assert_eq!(
+            (root.kind, root.perm, root.nlink),
+            (FileType::Directory, 0o755, 2)
+        );
+    }
+
+    #[test]
+    fn directory_entries_continue_from_an_offset() {
+        let k = kernel(StoreFs::new(store()));
+        let names = |offset| -> Vec<String> {
+            k.entries(ROOT_INO, offset)
+                .unwrap()
+                .into_iter()
+                .map(|(_, name, _)| name)
+                .collect()
+        };
+        assert_eq!(names(0), [".", "..", "greeting"]);
+        assert_eq!(names(2), ["greeting"]);
+        assert!(names(3).is_empty());
+
+        let greeting = k.fs().lookup(ROOT_INO, "greeting").unwrap();
+        assert!(matches!(
+            k.entries(greeting.ino, 0),
+            Err(FsError::NotDirectory)
+        ));
+    }
+
+    #[test]
+    fn errors_map_to_their_errno() {
+        assert_eq!(errno(FsError::NotFound), Errno::ENOENT);
+        assert_eq!(errno(FsError::ReadOnly), Errno::EROFS);
+        assert!(name(OsStr::new("greeting")).is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+//! # structfs-fuse
+//!
+//! Mount any StructFS [`Store`](structfs_core_store::Store) as a filesystem,
+//! so `ls`, `cat`, `grep` and editors can inspect and change a live store.
+//!
+//! ## Layout
+//!
+//! ```text
+//! /mnt/store/          # the store's root
+//!     config/          # a map is a directory of its keys
+//!       port           # a leaf value is a file holding its encoding: 8080
+//!       hosts/         # an array is a directory of its indices
+//!         0            # "a.example.com"
+//! ```
+//!
+//! Files are JSON by default; [`StoreFs::with_codec`] picks another codec.
+//! Writing a file decodes its content when it is closed and writes the
+//! value to the store, `mkdir` writes an empty map, and `rm` and `rmdir`
+//! delete. Nothing is cached, so changes made through the store show up
+//! immediately.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use structfs_fuse::{mount, StoreFs};
+//! use structfs_json_store::InMemoryStore;
+//!
+//! let mount = mount(StoreFs::new(InMemoryStore::new()).read_only(), "/mnt/store")?;
+//! ```
+//!
+//! [`StoreFs`] is the filesystem model and works anywhere; mounting it
+//! talks to the Linux FUSE driver and is only available on Linux.
+
+mod fs;
+#[cfg(target_os = "linux")]
+mod kernel;
+#[cfg(target_os = "linux")]
+mod session;
+
+pub use fs::{Attr, DirEntry, FileKind, FsError, StoreFs, ROOT_INO};
+#[cfg(target_os = "linux")]
+pub use session::{mount, serve, Mount};
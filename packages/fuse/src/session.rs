@@ -0,0 +1,200 @@
+//! Mounting a [`StoreFs`] and serving the kernel's requests.
+//!
+//! [`fuser`] mounts, directly as root and through the setuid `fusermount3`
+//! (or `fusermount`) helper for everyone else, and reads the requests that
+//! [`Kernel`] answers.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use fuser::{BackgroundSession, Config, MountOption, Session};
+use structfs_core_store::Store;
+
+use crate::fs::StoreFs;
+use crate::kernel::{Kernel, Owner, Shared};
+
+/// A mounted store, served from a background thread.
+///
+/// Dropping it unmounts the filesystem, lazily, so files still open keep
+/// working until they're closed, and waits for the session to end. Use
+/// [`unmount`](Self::unmount) to see errors and get the store back.
+pub struct Mount<S> {
+    mountpoint: PathBuf,
+    /// The session and the filesystem it serves, until unmounted.
+    session: Option<(BackgroundSession, Shared<S>)>,
+}
+
+impl<S> Mount<S> {
+    /// The directory the store is mounted on.
+    pub fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    /// Unmount the filesystem and return the store.
+    pub fn unmount(mut self) -> io::Result<S> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> io::Result<S> {
+        let Some((session, fs)) = self.session.take() else {
+            return Err(io::Error::other("already unmounted"));
+        };
+        session.umount_and_join()?;
+        into_store(fs)
+    }
+}
+
+impl<S> Drop for Mount<S> {
+    fn drop(&mut self) {
+        if self.session.is_some() {
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Mount `fs` on `mountpoint` and serve it from a background thread.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use structfs_fuse::{mount, StoreFs};
+/// use structfs_json_store::InMemoryStore;
+///
+/// let mount = mount(StoreFs::new(InMemoryStore::new()), "/mnt/store")?;
+/// // `ls /mnt/store`, `cat`, `vim` ...
+/// let store = mount.unmount()?;
+/// ```
+pub fn mount<S: Store + 'static>(
+    fs: StoreFs<S>,
+    mountpoint: impl AsRef<Path>,
+) -> io::Result<Mount<S>> {
+    let mountpoint = mountpoint.as_ref().to_path_buf();
+    let config = config(fs.is_read_only());
+    let fs = Arc::new(Mutex::new(fs));
+    let session = Session::new(Kernel::new(fs.clone(), owner()), &mountpoint, &config)?.spawn()?;
+    Ok(Mount {
+        mountpoint,
+        session: Some((session, fs)),
+    })
+}
+
+/// Mount `fs` on `mountpoint` and serve it on this thread until it is
+/// unmounted, with `umount` or `fusermount3 -u`, then return the store.
+pub fn serve<S: Store + 'static>(fs: StoreFs<S>, mountpoint: impl AsRef<Path>) -> io::Result<S> {
+    let config = config(fs.is_read_only());
+    let fs = Arc::new(Mutex::new(fs));
+    Session::new(Kernel::new(fs.clone(), owner()), mountpoint, &config)?.run()?;
+    into_store(fs)
+}
+
+/// Mount options: named after us, with the kernel checking permissions
+/// against the modes [`Kernel`] reports.
+fn config(read_only: bool) -> Config {
+    let mut options = vec![
+        MountOption::FSName("structfs".to_string()),
+        MountOption::Subtype("structfs".to_string()),
+        MountOption::DefaultPermissions,
+        MountOption::NoSuid,
+        MountOption::NoDev,
+    ];
+    if read_only {
+        options.push(MountOption::RO);
+    }
+    let mut config = Config::default();
+    config.mount_options = options;
+    config
+}
+
+/// The store back from a session that has ended.
+fn into_store<S>(fs: Shared<S>) -> io::Result<S> {
+    let fs =
+        Arc::try_unwrap(fs).map_err(|_| io::Error::other("the FUSE session is still running"))?;
+    let fs = fs.into_inner().unwrap_or_else(PoisonError::into_inner);
+    Ok(fs.into_store())
+}
+
+fn owner() -> Owner {
+    // SAFETY: getuid and getgid always succeed.
+    unsafe {
+        Owner {
+            uid: libc::getuid(),
+            gid: libc::getgid(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use structfs_core_store::{path, Reader, Record, Value, Writer};
+    use structfs_json_store::InMemoryStore;
+
+    fn value(store: &mut impl Reader, path: &structfs_core_store::Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.as_value().unwrap().clone())
+    }
+
+    #[test]
+    fn mounted_store_works_with_std_fs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = InMemoryStore::new();
+        store
+            .write(
+                &path!("config"),
+                Record::parsed(Value::Map(
+                    [("port".to_string(), Value::Integer(80))]
+                        .into_iter()
+                        .collect(),
+                )),
+            )
+            .unwrap();
+        let mount = match mount(StoreFs::new(store), dir.path()) {
+            Ok(mount) => mount,
+            Err(e) => {
+                // FUSE needs /dev/fuse and the right to mount; many
+                // sandboxes and CI runners have neither.
+                eprintln!("skipping: cannot mount FUSE here: {}", e);
+                return;
+            }
+        };
+        let root = mount.mountpoint().to_path_buf();
+
+        assert_eq!(
+            std::fs::read_to_string(root.join("config/port")).unwrap(),
+            "80"
+        );
+        assert!(root.join("config").is_dir());
+        std::fs::write(root.join("config/port"), "8080\n").unwrap();
+        std::fs::write(root.join("config/host"), "\"localhost\"").unwrap();
+        std::fs::create_dir(root.join("logs")).unwrap();
+        std::fs::rename(root.join("config/host"), root.join("logs/host")).unwrap();
+        // Closing ignores errors, so see the failed decode through fsync.
+        let mut bad = File::create(root.join("config/port")).unwrap();
+        bad.write_all(b"{oops").unwrap();
+        assert!(bad.sync_all().is_err());
+        drop(bad);
+
+        let mut names: Vec<_> = std::fs::read_dir(&root)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["config", "logs"]);
+
+        let mut store = mount.unmount().unwrap();
+        assert_eq!(
+            value(&mut store, &path!("config/port")),
+            Some(Value::Integer(8080))
+        );
+        assert_eq!(
+            value(&mut store, &path!("logs/host")),
+            Some(Value::String("localhost".into()))
+        );
+        assert_eq!(value(&mut store, &path!("config/host")), None);
+    }
+}
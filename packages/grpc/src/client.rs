@@ -0,0 +1,164 @@
+//! A store backed by a remote [`GrpcServer`](crate::GrpcServer).
+
+use std::future::Future;
+use std::time::Duration;
+
+use structfs_core_store::{
+    Deleter, Error, Lister, Path, Reader, Record, Subscription, Watcher, Watchers, Writer,
+};
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Status;
+
+use crate::convert::{record_from_proto, record_to_proto, status_to_error};
+use crate::proto;
+use crate::proto::store_service_client::StoreServiceClient;
+
+/// How often a watch with no changes checks whether its subscription has
+/// been dropped, so the stream can be closed.
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+/// A [`Store`](structfs_core_store::Store) whose reads, writes, deletes,
+/// listings and watches are served by a remote [`GrpcServer`](crate::GrpcServer).
+///
+/// The store is blocking: it runs its own tokio runtime and waits on each
+/// call, so it must not be used from inside another tokio runtime. Watches
+/// stream changes in the background and deliver them to the returned
+/// [`Subscription`].
+pub struct GrpcClientStore {
+    runtime: Runtime,
+    client: StoreServiceClient<Channel>,
+}
+
+impl GrpcClientStore {
+    /// Connect to a server, e.g. `http://127.0.0.1:50051`.
+    pub fn connect(endpoint: impl Into<String>) -> Result<Self, Error> {
+        let endpoint = Endpoint::from_shared(endpoint.into())
+            .map_err(|e| Error::store("grpc", "connect", e.to_string()))?;
+        Self::connect_to(endpoint)
+    }
+
+    /// Connect to a configured endpoint, with its own timeouts and TLS.
+    pub fn connect_to(endpoint: Endpoint) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(Error::Io)?;
+        let channel = runtime
+            .block_on(endpoint.connect())
+            .map_err(|e| Error::store("grpc", "connect", e.to_string()))?;
+        Ok(Self {
+            runtime,
+            client: StoreServiceClient::new(channel),
+        })
+    }
+
+    /// Run a call on the runtime, waiting for its reply.
+    fn call<T, F>(
+        &mut self,
+        operation: &'static str,
+        call: impl FnOnce(StoreServiceClient<Channel>) -> F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<tonic::Response<T>, Status>>,
+    {
+        // Clients share one channel, so a clone per call is cheap.
+        self.runtime
+            .block_on(call(self.client.clone()))
+            .map(tonic::Response::into_inner)
+            .map_err(|status| status_to_error(operation, status))
+    }
+}
+
+impl Reader for GrpcClientStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let request = proto::ReadRequest {
+            path: from.to_string(),
+        };
+        let response = self.call("read", |mut c| async move { c.read(request).await })?;
+        Ok(response.record.map(record_from_proto))
+    }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        Some(self)
+    }
+}
+
+impl Writer for GrpcClientStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let request = proto::WriteRequest {
+            path: to.to_string(),
+            record: Some(record_to_proto(data)),
+        };
+        let response = self.call("write", |mut c| async move { c.write(request).await })?;
+        Path::parse(&response.path).map_err(Error::Path)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl Deleter for GrpcClientStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        let request = proto::DeleteRequest {
+            path: path.to_string(),
+        };
+        self.call("delete", |mut c| async move { c.delete(request).await })?;
+        Ok(())
+    }
+}
+
+impl Lister for GrpcClientStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        let request = proto::ListRequest {
+            path: path.to_string(),
+        };
+        let response = self.call("list", |mut c| async move { c.list(request).await })?;
+        Ok(response.found.then_some(response.names))
+    }
+}
+
+impl Watcher for GrpcClientStore {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        let request = proto::WatchRequest {
+            path: path.to_string(),
+        };
+        let mut stream = self.call("watch", |mut c| async move { c.watch(request).await })?;
+
+        // The server already reports each change at the path this
+        // subscription sees it, so a registry with just this subscription
+        // passes it through unchanged.
+        let mut watchers = Watchers::new();
+        let subscription = watchers.subscribe(path);
+        self.runtime.spawn(async move {
+            let mut poll = tokio::time::interval(WATCH_POLL);
+            loop {
+                let message = tokio::select! {
+                    message = stream.message() => message,
+                    _ = poll.tick() => {
+                        if watchers.is_empty() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+                let Ok(Some(change)) = message else {
+                    return;
+                };
+                let Ok(at) = Path::parse(&change.path) else {
+                    continue;
+                };
+                let mut old = change.old.map(record_from_proto);
+                let mut new = change.new.map(record_from_proto);
+                watchers.before(&at, |_| old.take()).send(|_| new.take());
+            }
+        });
+        Ok(subscription)
+    }
+}
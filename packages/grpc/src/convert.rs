@@ -0,0 +1,140 @@
+//! Conversions between StructFS types and their protobuf messages.
+
+use std::collections::BTreeMap;
+
+use structfs_core_store::{Error, Format, Path, Record, Value};
+use tonic::{Code, Status};
+
+use crate::proto;
+use crate::proto::{record, value};
+
+pub(crate) fn value_to_proto(value: Value) -> proto::Value {
+    let kind = match value {
+        Value::Null => value::Kind::Null(proto::Null {}),
+        Value::Bool(b) => value::Kind::Bool(b),
+        Value::Integer(i) => value::Kind::Integer(i),
+        Value::Float(f) => value::Kind::Float(f),
+        Value::String(s) => value::Kind::String(s),
+        Value::Bytes(b) => value::Kind::Bytes(b),
+        Value::Array(items) => value::Kind::Array(proto::Array {
+            items: items.into_iter().map(value_to_proto).collect(),
+        }),
+        Value::Map(map) => value::Kind::Map(proto::Map {
+            entries: map
+                .into_iter()
+                .map(|(k, v)| (k, value_to_proto(v)))
+                .collect(),
+        }),
+    };
+    proto::Value { kind: Some(kind) }
+}
+
+/// An unset value is null, as protobuf leaves the default unsent.
+pub(crate) fn value_from_proto(value: proto::Value) -> Value {
+    match value.kind {
+        None | Some(value::Kind::Null(_)) => Value::Null,
+        Some(value::Kind::Bool(b)) => Value::Bool(b),
+        Some(value::Kind::Integer(i)) => Value::Integer(i),
+        Some(value::Kind::Float(f)) => Value::Float(f),
+        Some(value::Kind::String(s)) => Value::String(s),
+        Some(value::Kind::Bytes(b)) => Value::Bytes(b),
+        Some(value::Kind::Array(array)) => {
+            Value::Array(array.items.into_iter().map(value_from_proto).collect())
+        }
+        Some(value::Kind::Map(map)) => Value::Map(
+            map.entries
+                .into_iter()
+                .map(|(k, v)| (k, value_from_proto(v)))
+                .collect::<BTreeMap<_, _>>(),
+        ),
+    }
+}
+
+/// Raw records keep their bytes and format, so they cross unparsed.
+pub(crate) fn record_to_proto(record: Record) -> proto::Record {
+    let kind = match record {
+        Record::Raw { bytes, format } => record::Kind::Raw(proto::Raw {
+            bytes: bytes.to_vec(),
+            format: format.0.into_owned(),
+        }),
+        Record::Parsed(value) => record::Kind::Parsed(value_to_proto(value)),
+    };
+    proto::Record { kind: Some(kind) }
+}
+
+pub(crate) fn record_from_proto(record: proto::Record) -> Record {
+    match record.kind {
+        Some(record::Kind::Raw(raw)) => Record::Raw {
+            bytes: raw.bytes.into(),
+            format: Format(raw.format.into()),
+        },
+        Some(record::Kind::Parsed(value)) => Record::Parsed(value_from_proto(value)),
+        None => Record::Parsed(Value::Null),
+    }
+}
+
+/// Parse a path sent by the other side.
+pub(crate) fn parse_path(path: &str) -> Result<Path, Status> {
+    Path::parse(path).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// The status a server sends for a store error.
+pub(crate) fn error_to_status(error: Error) -> Status {
+    let code = match &error {
+        Error::Path(_) => Code::InvalidArgument,
+        Error::NoRoute { .. } => Code::NotFound,
+        Error::Codec { .. } | Error::UnsupportedFormat(_) => Code::InvalidArgument,
+        Error::LimitExceeded { .. } => Code::OutOfRange,
+        Error::RateLimited { .. } => Code::ResourceExhausted,
+        Error::DeadlineExceeded { .. } => Code::DeadlineExceeded,
+        Error::Cancelled { .. } => Code::Cancelled,
+        _ => Code::Internal,
+    };
+    Status::new(code, error.to_string())
+}
+
+/// The error a client reports for a failed call.
+pub(crate) fn status_to_error(operation: &'static str, status: Status) -> Error {
+    match status.code() {
+        Code::DeadlineExceeded => Error::DeadlineExceeded { store: "grpc" },
+        Code::Cancelled => Error::Cancelled { store: "grpc" },
+        _ => Error::store("grpc", operation, status.message()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::NoCodec;
+
+    #[test]
+    fn values_round_trip() {
+        let value = Value::Map(BTreeMap::from([
+            ("null".to_string(), Value::Null),
+            ("flag".to_string(), Value::Bool(true)),
+            ("count".to_string(), Value::Integer(-42)),
+            ("ratio".to_string(), Value::Float(0.5)),
+            ("name".to_string(), Value::String("alice".into())),
+            ("blob".to_string(), Value::Bytes(vec![0, 255])),
+            (
+                "list".to_string(),
+                Value::Array(vec![Value::Integer(1), Value::Map(BTreeMap::new())]),
+            ),
+        ]));
+        assert_eq!(value_from_proto(value_to_proto(value.clone())), value);
+
+        let raw = Record::Raw {
+            bytes: b"{\"a\":1}".to_vec().into(),
+            format: Format::JSON,
+        };
+        match record_from_proto(record_to_proto(raw)) {
+            Record::Raw { bytes, format } => {
+                assert_eq!(&bytes[..], b"{\"a\":1}");
+                assert_eq!(format, Format::JSON);
+            }
+            other => panic!("expected a raw record, got {:?}", other),
+        }
+        let parsed = record_from_proto(proto::Record::default());
+        assert_eq!(parsed.into_value(&NoCodec).unwrap(), Value::Null);
+    }
+}
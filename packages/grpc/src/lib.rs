@@ -0,0 +1,164 @@
+//! # structfs-grpc
+//!
+//! A gRPC transport for StructFS stores: [`GrpcServer`] serves any
+//! [`Store`](structfs_core_store::Store), and [`GrpcClientStore`] is a store
+//! whose operations are served by a remote one.
+//!
+//! Compared with mounting a store over HTTP/JSON, records cross as binary
+//! protobuf (raw records keep their bytes and format unparsed), calls share
+//! one HTTP/2 connection, and watches stream each change as it happens
+//! instead of being polled. It suits links between runtimes that both
+//! speak StructFS.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use structfs_grpc::{GrpcClientStore, GrpcServer};
+//! use structfs_json_store::InMemoryStore;
+//!
+//! // One process serves a store...
+//! GrpcServer::new(InMemoryStore::new())
+//!     .serve("127.0.0.1:50051".parse()?)
+//!     .await?;
+//!
+//! // ...and another reads, writes and watches it.
+//! let mut remote = GrpcClientStore::connect("http://127.0.0.1:50051")?;
+//! let changes = remote.watch(&path!("config"))?;
+//! remote.write(&path!("config/port"), Record::parsed(Value::Integer(8080)))?;
+//! ```
+//!
+//! The service is defined in `proto/structfs.proto`, so peers in other
+//! languages can generate their own client or server from it.
+
+mod client;
+mod convert;
+mod server;
+
+/// The messages and service generated from `proto/structfs.proto`.
+pub mod proto {
+    #![allow(missing_docs)]
+    tonic::include_proto!("structfs.v1");
+}
+
+pub use client::GrpcClientStore;
+pub use server::GrpcServer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+    use structfs_core_store::{path, NoCodec, Reader, Record, Store, Value, Writer};
+    use structfs_json_store::InMemoryStore;
+    use tokio::runtime::Runtime;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    /// Serve `store` on a local port, returning the runtime that runs the
+    /// server and the client's endpoint.
+    fn serve(store: InMemoryStore) -> (Runtime, String) {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(
+            tonic::transport::Server::builder()
+                .add_service(GrpcServer::new(store).into_service())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        (runtime, format!("http://{}", addr))
+    }
+
+    fn value(store: &mut impl Reader, path: &structfs_core_store::Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn operations_reach_the_remote_store() {
+        let (_server, endpoint) = serve(InMemoryStore::new());
+        let mut remote = GrpcClientStore::connect(endpoint).unwrap();
+
+        let config = Value::Map(BTreeMap::from([
+            ("port".to_string(), Value::Integer(8080)),
+            ("name".to_string(), Value::String("api".into())),
+        ]));
+        let written = remote
+            .write(&path!("config"), Record::parsed(config.clone()))
+            .unwrap();
+        assert_eq!(written, path!("config"));
+        assert_eq!(value(&mut remote, &path!("config")), Some(config));
+        assert_eq!(
+            value(&mut remote, &path!("config/port")),
+            Some(Value::Integer(8080))
+        );
+        assert_eq!(value(&mut remote, &path!("missing")), None);
+
+        let mut names = remote.list(&path!("config")).unwrap().unwrap();
+        names.sort();
+        assert_eq!(names, ["name", "port"]);
+        assert_eq!(remote.list(&path!("missing")).unwrap(), None);
+
+        remote.delete(&path!("config/name")).unwrap();
+        assert_eq!(value(&mut remote, &path!("config/name")), None);
+    }
+
+    #[test]
+    fn errors_come_back_as_store_errors() {
+        let (_server, endpoint) = serve(InMemoryStore::new());
+        let mut remote = GrpcClientStore::connect(endpoint).unwrap();
+        let err = remote
+            .write(&path!("a/b"), Record::parsed(Value::Integer(1)))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("grpc::write"), "{}", err);
+
+        assert!(GrpcClientStore::connect("not a url").is_err());
+    }
+
+    #[test]
+    fn watches_stream_changes() {
+        let (_server, endpoint) = serve(InMemoryStore::new());
+        let mut remote = GrpcClientStore::connect(endpoint).unwrap();
+        remote
+            .write(&path!("users"), Record::parsed(Value::Map(BTreeMap::new())))
+            .unwrap();
+
+        let changes = remote.watch(&path!("users")).unwrap();
+        remote
+            .write(&path!("users/alice"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        remote
+            .write(&path!("other"), Record::parsed(Value::Integer(2)))
+            .unwrap();
+        remote
+            .write(&path!("users/alice"), Record::parsed(Value::Integer(3)))
+            .unwrap();
+
+        let first = changes.next_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first.path, path!("users/alice"));
+        assert!(first.old.is_none());
+        assert_eq!(
+            first.new.unwrap().into_value(&NoCodec).unwrap(),
+            Value::Integer(1)
+        );
+        let second = changes.next_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            second.old.unwrap().into_value(&NoCodec).unwrap(),
+            Value::Integer(1)
+        );
+        assert!(changes.next_timeout(Duration::from_millis(200)).is_none());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let (_server, endpoint) = serve(InMemoryStore::new());
+        let mut remote = GrpcClientStore::connect(endpoint).unwrap();
+        conformance::assert_missing(&mut remote, &path!("missing"));
+        conformance::assert_round_trip(&mut remote, &path!("count"), Value::Integer(1));
+        conformance::assert_missing(&mut remote, &path!("count/child"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Serving a store over gRPC.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use structfs_core_store::{Error, Store, Subscription};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::convert::{error_to_status, parse_path, record_from_proto, record_to_proto};
+use crate::proto;
+use crate::proto::store_service_server::{StoreService, StoreServiceServer};
+
+/// How long a watch waits for a change before checking that its client is
+/// still listening.
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+/// Changes buffered per watch before the store side waits for the client.
+const WATCH_BUFFER: usize = 64;
+
+/// Serves a [`Store`] to [`GrpcClientStore`](crate::GrpcClientStore)s.
+///
+/// Calls are served one at a time against the store, on tokio's blocking
+/// threads, so a slow store does not hold up the runtime.
+pub struct GrpcServer<S> {
+    store: Arc<Mutex<S>>,
+}
+
+impl<S: Store + Send + 'static> GrpcServer<S> {
+    /// Serve `store`.
+    pub fn new(store: S) -> Self {
+        Self::shared(Arc::new(Mutex::new(store)))
+    }
+
+    /// Serve a store that is also used elsewhere in the process.
+    pub fn shared(store: Arc<Mutex<S>>) -> Self {
+        Self { store }
+    }
+
+    /// The tonic service, for adding to a
+    /// [`Server`](tonic::transport::Server) alongside other services.
+    pub fn into_service(self) -> StoreServiceServer<Self> {
+        StoreServiceServer::new(self)
+    }
+
+    /// Listen on `addr` and serve until the listener fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_service())
+            .serve(addr)
+            .await
+    }
+
+    /// Run `op` against the store on a blocking thread.
+    async fn with_store<T: Send + 'static>(
+        &self,
+        op: impl FnOnce(&mut S) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Status> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || op(&mut lock(&store)))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(error_to_status)
+    }
+}
+
+fn lock<S>(store: &Mutex<S>) -> MutexGuard<'_, S> {
+    store.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[tonic::async_trait]
+impl<S: Store + Send + 'static> StoreService for GrpcServer<S> {
+    async fn read(
+        &self,
+        request: Request<proto::ReadRequest>,
+    ) -> Result<Response<proto::ReadResponse>, Status> {
+        let path = parse_path(&request.into_inner().path)?;
+        let record = self.with_store(move |store| store.read(&path)).await?;
+        Ok(Response::new(proto::ReadResponse {
+            record: record.map(record_to_proto),
+        }))
+    }
+
+    async fn write(
+        &self,
+        request: Request<proto::WriteRequest>,
+    ) -> Result<Response<proto::WriteResponse>, Status> {
+        let request = request.into_inner();
+        let path = parse_path(&request.path)?;
+        let record = request
+            .record
+            .ok_or_else(|| Status::invalid_argument("write without a record"))?;
+        let record = record_from_proto(record);
+        let written = self
+            .with_store(move |store| store.write(&path, record))
+            .await?;
+        Ok(Response::new(proto::WriteResponse {
+            path: written.to_string(),
+        }))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::DeleteRequest>,
+    ) -> Result<Response<proto::DeleteResponse>, Status> {
+        let path = parse_path(&request.into_inner().path)?;
+        self.with_store(move |store| store.delete(&path)).await?;
+        Ok(Response::new(proto::DeleteResponse {}))
+    }
+
+    async fn list(
+        &self,
+        request: Request<proto::ListRequest>,
+    ) -> Result<Response<proto::ListResponse>, Status> {
+        let path = parse_path(&request.into_inner().path)?;
+        let names = self.with_store(move |store| store.list(&path)).await?;
+        Ok(Response::new(proto::ListResponse {
+            found: names.is_some(),
+            names: names.unwrap_or_default(),
+        }))
+    }
+
+    type WatchStream = ReceiverStream<Result<proto::Change, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<proto::WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let path = parse_path(&request.into_inner().path)?;
+        // Subscribe before replying, so the client sees every write made
+        // after its call returns.
+        let subscription = self.with_store(move |store| store.watch(&path)).await?;
+        let (tx, rx) = mpsc::channel(WATCH_BUFFER);
+        tokio::task::spawn_blocking(move || forward(subscription, tx));
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Send a subscription's changes to a client until it goes away.
+fn forward(subscription: Subscription, tx: mpsc::Sender<Result<proto::Change, Status>>) {
+    while !tx.is_closed() {
+        let Some(change) = subscription.next_timeout(WATCH_POLL) else {
+            continue;
+        };
+        let change = proto::Change {
+            path: change.path.to_string(),
+            old: change.old.map(record_to_proto),
+            new: change.new.map(record_to_proto),
+        };
+        if tx.blocking_send(Ok(change)).is_err() {
+            return;
+        }
+    }
+}
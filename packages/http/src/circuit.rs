@@ -0,0 +1,543 @@
+//! Circuit breaker for HTTP mounts.
+//!
+//! [`CircuitBreakerStore`] wraps a store, usually an
+//! [`HttpClientStore`](crate::HttpClientStore), and stops calling it once too
+//! many requests fail. While the circuit is open every operation fails
+//! immediately with [`Error::RateLimited`], so a dead upstream does not tie up
+//! callers waiting for timeouts. After a cooldown a few requests are let
+//! through to probe the upstream; if they succeed the circuit closes again.
+//!
+//! ```text
+//!   closed --(failure rate over threshold)--> open
+//!   open --(cooldown elapsed)--> half_open
+//!   half_open --(probe succeeds)--> closed
+//!   half_open --(probe fails)--> open
+//! ```
+//!
+//! Only upstream failures count: transport errors and 5xx responses. A 4xx
+//! response or a missing resource is the caller's problem, not the
+//! upstream's.
+//!
+//! The breaker's state is readable under `circuit/`, which shadows any
+//! upstream path of the same name:
+//!
+//! | Path | Operation | Result |
+//! |------|-----------|--------|
+//! | `read /circuit` | Breaker state | `{state, requests, failures, retry_after_ms, config}` |
+//! | `read /circuit/state` | Breaker state name | `"closed"`, `"open"` or `"half_open"` |
+//! | `read /circuit/events` | Recent state changes | `[{from, to, at_ms, reason}, ...]` |
+//! | `write /circuit/reset null` | Close the breaker | Clears the failure window |
+//!
+//! State changes can also be written to a metrics store with
+//! [`CircuitBreakerStore::with_event_sink`].
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use collection_literals::btree;
+use structfs_core_store::mount_store::{CircuitBreakerConfig, HttpOptions};
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::{Error, Path, Reader, Record, Store, Value, Writer};
+
+/// Path prefix for the breaker's own state.
+pub const CIRCUIT_PATH: &str = "circuit";
+
+/// How many state changes `circuit/events` keeps.
+const EVENT_HISTORY: usize = 32;
+
+/// State of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests pass through.
+    Closed,
+    /// Requests fail immediately.
+    Open,
+    /// Requests pass through to probe whether the upstream has recovered.
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Name used in `circuit/` reads and events.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// A change of breaker state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitEvent {
+    /// State before the change.
+    pub from: CircuitState,
+    /// State after the change.
+    pub to: CircuitState,
+    /// When the change happened, in milliseconds since the Unix epoch.
+    pub at_ms: u64,
+    /// Why the state changed.
+    pub reason: String,
+}
+
+impl CircuitEvent {
+    /// Convert to a Value map.
+    pub fn to_value(&self) -> Value {
+        Value::Map(btree! {
+            "from".into() => Value::String(self.from.as_str().into()),
+            "to".into() => Value::String(self.to.as_str().into()),
+            "at_ms".into() => Value::Integer(self.at_ms as i64),
+            "reason".into() => Value::String(self.reason.clone()),
+        })
+    }
+}
+
+/// Whether an error from the wrapped store means the upstream is failing.
+///
+/// Non-2xx responses carry their status in the error detail; only 5xx
+/// counts. Errors without a status, such as connection failures, count too,
+/// except for deadlines and cancellations, which the caller chose.
+fn is_upstream_failure(error: &Error) -> bool {
+    match error {
+        Error::DeadlineExceeded { .. } | Error::Cancelled { .. } => false,
+        Error::Detailed {
+            detail: Value::Map(map),
+            ..
+        } => match map.get("status") {
+            Some(Value::Integer(status)) => *status >= 500,
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// A store that stops calling its inner store while the upstream is failing.
+pub struct CircuitBreakerStore<S> {
+    inner: S,
+    config: CircuitBreakerConfig,
+    state: CircuitState,
+    /// Outcomes of recent requests, `true` for a failure. Only kept while
+    /// closed.
+    window: VecDeque<bool>,
+    /// When the breaker last opened.
+    opened_at: Option<Instant>,
+    /// Consecutive successful probes while half-open.
+    probes_succeeded: u64,
+    events: VecDeque<CircuitEvent>,
+    event_sink: Option<(Box<dyn Writer>, Path)>,
+}
+
+impl<S: Store> CircuitBreakerStore<S> {
+    /// Wrap `inner` with a breaker using `config`.
+    pub fn new(inner: S, config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            config,
+            state: CircuitState::Closed,
+            window: VecDeque::new(),
+            opened_at: None,
+            probes_succeeded: 0,
+            events: VecDeque::new(),
+            event_sink: None,
+        }
+    }
+
+    /// Also write every state change to `path` in `sink`, such as a metrics
+    /// store. Failures to write are ignored.
+    pub fn with_event_sink(mut self, sink: Box<dyn Writer>, path: Path) -> Self {
+        self.event_sink = Some((sink, path));
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The current state, moving from open to half-open if the cooldown has
+    /// elapsed.
+    pub fn state(&mut self) -> CircuitState {
+        if self.state == CircuitState::Open && self.retry_after().is_zero() {
+            self.transition(CircuitState::HalfOpen, "cooldown elapsed".to_string());
+        }
+        self.state
+    }
+
+    /// Recent state changes, oldest first.
+    pub fn events(&self) -> impl Iterator<Item = &CircuitEvent> {
+        self.events.iter()
+    }
+
+    /// Close the breaker and forget past failures.
+    pub fn reset(&mut self) {
+        self.window.clear();
+        if self.state != CircuitState::Closed {
+            self.transition(CircuitState::Closed, "reset".to_string());
+        }
+    }
+
+    /// Time until an open breaker lets requests through again.
+    fn retry_after(&self) -> Duration {
+        match (self.state, self.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => {
+                Duration::from_millis(self.config.cooldown_ms).saturating_sub(opened_at.elapsed())
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Fail fast if the breaker is open.
+    fn admit(&mut self) -> Result<(), Error> {
+        match self.state() {
+            CircuitState::Open => Err(Error::RateLimited {
+                store: "circuit_breaker",
+                retry_after: self.retry_after(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the outcome of a request that reached the inner store.
+    fn record<T>(&mut self, result: &Result<T, Error>) {
+        let failed = result.as_ref().err().is_some_and(is_upstream_failure);
+
+        match self.state {
+            CircuitState::Closed => {
+                self.window.push_back(failed);
+                while self.window.len() as u64 > self.config.window.max(1) {
+                    self.window.pop_front();
+                }
+
+                let requests = self.window.len() as u64;
+                let failures = self.window.iter().filter(|f| **f).count() as u64;
+                if failed
+                    && requests >= self.config.min_requests
+                    && failures * 100 >= self.config.failure_percent * requests
+                {
+                    self.transition(
+                        CircuitState::Open,
+                        format!("{} of the last {} requests failed", failures, requests),
+                    );
+                }
+            }
+            CircuitState::HalfOpen if failed => {
+                self.transition(CircuitState::Open, "probe request failed".to_string());
+            }
+            CircuitState::HalfOpen => {
+                self.probes_succeeded += 1;
+                if self.probes_succeeded >= self.config.half_open_requests {
+                    self.transition(CircuitState::Closed, "probe requests succeeded".to_string());
+                }
+            }
+            // Requests are not let through while open
+            CircuitState::Open => {}
+        }
+    }
+
+    fn transition(&mut self, to: CircuitState, reason: String) {
+        let event = CircuitEvent {
+            from: self.state,
+            to,
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            reason,
+        };
+
+        self.state = to;
+        self.probes_succeeded = 0;
+        match to {
+            CircuitState::Open => self.opened_at = Some(Instant::now()),
+            CircuitState::Closed => {
+                self.opened_at = None;
+                self.window.clear();
+            }
+            CircuitState::HalfOpen => {}
+        }
+
+        if let Some((sink, path)) = &mut self.event_sink {
+            let _ = sink.write(path, Record::parsed(event.to_value()));
+        }
+        if self.events.len() == EVENT_HISTORY {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn status_value(&mut self) -> Value {
+        let state = self.state();
+        Value::Map(btree! {
+            "state".into() => Value::String(state.as_str().into()),
+            "requests".into() => Value::Integer(self.window.len() as i64),
+            "failures".into() => Value::Integer(self.window.iter().filter(|f| **f).count() as i64),
+            "retry_after_ms".into() => Value::Integer(self.retry_after().as_millis() as i64),
+            "config".into() => self.config.to_value(),
+        })
+    }
+
+    fn read_circuit(&mut self, rest: &Path) -> Result<Option<Record>, Error> {
+        let value = match rest.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [] => self.status_value(),
+            ["state"] => Value::String(self.state().as_str().into()),
+            ["events"] => Value::Array(self.events.iter().map(CircuitEvent::to_value).collect()),
+            _ => return Ok(None),
+        };
+        Ok(Some(Record::parsed(value)))
+    }
+}
+
+impl<S: Store + Send + Sync + 'static> CircuitBreakerStore<S> {
+    /// Box `store` for mounting, behind a breaker if `options` configure
+    /// one.
+    pub fn boxed(store: S, options: &HttpOptions) -> StoreBox {
+        match &options.circuit_breaker {
+            Some(config) => Box::new(Self::new(store, config.clone())),
+            None => Box::new(store),
+        }
+    }
+}
+
+impl<S: Store> Reader for CircuitBreakerStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if !from.is_empty() && from[0] == CIRCUIT_PATH {
+            return self.read_circuit(&from.slice(1, from.len()));
+        }
+
+        self.admit()?;
+        let result = self.inner.read(from);
+        self.record(&result);
+        result
+    }
+}
+
+impl<S: Store> Writer for CircuitBreakerStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if !to.is_empty() && to[0] == CIRCUIT_PATH {
+            if to.len() == 2 && to[1] == "reset" {
+                self.reset();
+                return Ok(to.clone());
+            }
+            return Err(Error::store(
+                "circuit_breaker",
+                "write",
+                format!(
+                    "Cannot write to '{}'. Write to circuit/reset to close the breaker.",
+                    to
+                ),
+            ));
+        }
+
+        self.admit()?;
+        let result = self.inner.write(to, data);
+        self.record(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::mock::MockExecutor;
+    use crate::HttpClientStore;
+    use std::sync::{Arc, Mutex};
+    use structfs_core_store::{path, NoCodec};
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_percent: 50,
+            min_requests: 4,
+            window: 4,
+            cooldown_ms: 60_000,
+            half_open_requests: 1,
+        }
+    }
+
+    fn breaker(
+        mock: &MockExecutor,
+        config: CircuitBreakerConfig,
+    ) -> CircuitBreakerStore<HttpClientStore<MockExecutor>> {
+        let client =
+            HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+        CircuitBreakerStore::new(client, config)
+    }
+
+    fn read_value(store: &mut impl Reader, path: &Path) -> Value {
+        store
+            .read(path)
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap()
+    }
+
+    /// Writer that collects everything written to it.
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<(Path, Value)>>>);
+
+    impl Writer for Sink {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            let value = data.into_value(&NoCodec)?;
+            self.0.lock().unwrap().push((to.clone(), value));
+            Ok(to.clone())
+        }
+    }
+
+    #[test]
+    fn opens_after_failures_and_fails_fast() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::error_response(503, "Unavailable"));
+        let sink = Sink::default();
+        let mut store = breaker(&mock, config())
+            .with_event_sink(Box::new(sink.clone()), path!("metrics/circuit/api"));
+
+        for _ in 0..4 {
+            assert!(store.read(&path!("users")).is_err());
+        }
+        assert_eq!(store.state(), CircuitState::Open);
+        assert_eq!(mock.recorded_requests().len(), 4);
+
+        // Open: no request reaches the upstream
+        let err = store.read(&path!("users")).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RateLimited {
+                store: "circuit_breaker",
+                ..
+            }
+        ));
+        assert_eq!(mock.recorded_requests().len(), 4);
+
+        assert_eq!(
+            read_value(&mut store, &path!("circuit/state")),
+            Value::from("open")
+        );
+        let status = read_value(&mut store, &path!("circuit"));
+        let Value::Map(status) = status else {
+            panic!("expected a map")
+        };
+        assert!(matches!(status["retry_after_ms"], Value::Integer(ms) if ms > 0));
+
+        let events = sink.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, path!("metrics/circuit/api"));
+        let Value::Map(event) = &events[0].1 else {
+            panic!("expected a map")
+        };
+        assert_eq!(event["to"], Value::from("open"));
+    }
+
+    #[test]
+    fn client_errors_do_not_open() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::error_response(400, "Bad Request"));
+        let mut store = breaker(&mock, config());
+
+        for _ in 0..8 {
+            assert!(store.read(&path!("users")).is_err());
+        }
+        assert_eq!(store.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens() {
+        let mock = MockExecutor::new().fail_with("connection refused");
+        let mut store = breaker(
+            &mock,
+            CircuitBreakerConfig {
+                cooldown_ms: 0,
+                ..config()
+            },
+        );
+        for _ in 0..4 {
+            store.read(&path!("users")).unwrap_err();
+        }
+
+        // A zero cooldown goes straight to half-open, and a failed probe
+        // opens the breaker again
+        assert_eq!(store.state(), CircuitState::HalfOpen);
+        store.read(&path!("users")).unwrap_err();
+        assert_eq!(
+            store.events().last().unwrap().reason,
+            "probe request failed"
+        );
+
+        // Once the upstream recovers, a probe closes it
+        let healthy = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::json!(1)));
+        let client = HttpClientStore::with_executor("https://api.example.com", healthy).unwrap();
+        store.inner = client;
+        assert!(store.read(&path!("users")).unwrap().is_some());
+        assert_eq!(store.state(), CircuitState::Closed);
+
+        let Value::Array(events) = read_value(&mut store, &path!("circuit/events")) else {
+            panic!("expected an array");
+        };
+        let to: Vec<Value> = events
+            .iter()
+            .map(|e| match e {
+                Value::Map(map) => map["to"].clone(),
+                _ => Value::Null,
+            })
+            .collect();
+        assert_eq!(
+            to,
+            ["open", "half_open", "open", "half_open", "closed"].map(Value::from)
+        );
+    }
+
+    #[test]
+    fn reset_closes_breaker() {
+        let mock = MockExecutor::new().fail_with("connection refused");
+        let mut store = breaker(&mock, config());
+        for _ in 0..4 {
+            store.read(&path!("users")).unwrap_err();
+        }
+        assert_eq!(store.state(), CircuitState::Open);
+
+        store
+            .write(&path!("circuit/reset"), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(store.state(), CircuitState::Closed);
+        assert!(store
+            .write(&path!("circuit/state"), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn boxed_wraps_only_when_configured() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::json!(1)));
+        let client =
+            || HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+
+        let mut plain = CircuitBreakerStore::boxed(client(), &HttpOptions::default());
+        plain.read(&path!("circuit/state")).unwrap();
+        assert_eq!(mock.recorded_requests().len(), 1);
+
+        let options = HttpOptions {
+            circuit_breaker: Some(config()),
+            ..Default::default()
+        };
+        let mut guarded = CircuitBreakerStore::boxed(client(), &options);
+        guarded.read(&path!("circuit/state")).unwrap();
+        assert_eq!(mock.recorded_requests().len(), 1);
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mock = MockExecutor::new()
+            .with_response(
+                "https://api.example.com/users/1",
+                MockExecutor::success_response(serde_json::json!({"id": 1})),
+            )
+            .with_default_response(MockExecutor::not_found());
+        let mut store = breaker(&mock, config());
+        conformance::assert_found(&mut store, &path!("users/1"));
+        conformance::assert_missing(&mut store, &path!("users/2"));
+        conformance::assert_found(&mut store, &path!("circuit/state"));
+        conformance::assert_missing(&mut store, &path!("circuit/nope"));
+        assert_eq!(store.state(), CircuitState::Closed);
+    }
+}
@@ -3,7 +3,8 @@
 //! This module provides implementations using the new three-layer architecture
 //! (ll-store, core-store, serde-store) instead of the legacy erased_serde approach.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -12,109 +13,299 @@ use collection_literals::btree;
 use reqwest::blocking::Client;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
-use structfs_core_store::{path, Error, NoCodec, Path, Reader, Record, Reference, Value, Writer};
+use structfs_core_store::describe::{read_lens, Action, Docs, Field, DOCS_PREFIX, META_PREFIX};
+use structfs_core_store::mount_store::HttpOptions;
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::{
+    Deleter, Error, HandleBroker, NoCodec, OpContext, Path, Reader, Record, Reference,
+    SelfDescribing, Value, Writer,
+};
 use structfs_serde_store::{from_value, to_value};
 
-use crate::executor::{HttpExecutor, ReqwestExecutor};
-use crate::handle::RequestStatus;
-
-use crate::types::{HttpRequest, HttpResponse};
+use crate::executor::{
+    apply_trace_id, client_builder, parse_url, read_body, request_timeout, HttpExecutor,
+    ReqwestExecutor, DEFAULT_TIMEOUT,
+};
+use crate::handle::{RequestState, RequestStatus};
+use crate::journal::{Journal, JournalEntry};
+use crate::signing::{signer_for, RequestSigner};
+use crate::tls::server_name_client;
+use crate::types::{HttpRequest, HttpResponse, Method};
 
 const OUTSTANDING_PREFIX: &str = "outstanding";
-const DOCS_PATH: &str = "docs";
-const META_PATH: &str = "meta";
+const BATCHES_PREFIX: &str = "batches";
+const BATCH_PATH: &str = "batch";
+
+/// Requests of one batch that run at once unless the batch says otherwise.
+const DEFAULT_BATCH_CONCURRENCY: usize = 8;
 
 type RequestId = u64;
 
-/// Generate documentation for the sync HTTP broker store.
-fn sync_broker_docs() -> Value {
+/// Handles for queued requests, at `outstanding/{id}`.
+fn request_handles<T>() -> HandleBroker<T> {
+    HandleBroker::new(OUTSTANDING_PREFIX, "request-handle")
+}
+
+/// `meta/`: what can be described.
+fn broker_meta_root() -> Value {
     Value::Map(btree! {
-        "title".into() => Value::String("Sync HTTP Broker".into()),
-        "description".into() => Value::String("Queue HTTP requests by writing, execute on read (blocks until complete).".into()),
-        "paths".into() => Value::Map(btree! {
-            "write /".into() => Value::String("Queue request, returns outstanding/{id}".into()),
-            "read /outstanding".into() => Value::String("List queued request IDs".into()),
-            "read /outstanding/{id}".into() => Value::String("Execute request (blocks) and return response".into()),
-            "read /outstanding/{id}/request".into() => Value::String("View the queued request".into()),
-            "read /outstanding/{id}/response/body".into() => Value::String("Navigate into response fields".into()),
-            "write /outstanding/{id} null".into() => Value::String("Delete the handle".into()),
-        }),
-        "example".into() => Value::Array(vec![
-            Value::String("write / {\"method\": \"GET\", \"path\": \"https://httpbin.org/json\"}".into()),
-            Value::String("# Returns: outstanding/0".into()),
-            Value::String("read /outstanding/0".into()),
-            Value::String("# Blocks until complete, returns response".into()),
-        ]),
+        "queue".into() => Reference::with_type("meta/queue", "action").to_value(),
+        "outstanding".into() => Reference::with_type("meta/outstanding", "collection").to_value(),
     })
 }
 
+/// `meta/queue`: queue a request by writing it to the root.
+fn queue_action() -> Value {
+    Action::write("")
+        .accepts(
+            "method",
+            Field::string()
+                .required()
+                .one_of(["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"]),
+        )
+        .accepts("path", Field::string().required())
+        .accepts("headers", Field::new("map").optional())
+        .accepts("body", Field::string().optional())
+        .returns_collection("request-handle", OUTSTANDING_PREFIX)
+        .into()
+}
+
+/// `meta/batch`: run many requests at once by writing them to `batch`.
+fn batch_action() -> Value {
+    Action::write(BATCH_PATH)
+        .accepts("requests", Field::new("array").required())
+        .accepts("concurrency", Field::new("integer").optional())
+        .returns_collection("batch", BATCHES_PREFIX)
+        .into()
+}
+
+/// `meta/outstanding/{id}/delete`: delete a handle by writing null to it.
+fn delete_action(id: RequestId) -> Value {
+    Action::write(format!("{}/{}", OUTSTANDING_PREFIX, id))
+        .accepts_only("null")
+        .returns(Value::String("void".into()))
+        .into()
+}
+
+/// Parse the id in `meta/outstanding/{id}/...`.
+fn meta_handle_id(store: &'static str, path: &Path) -> Result<RequestId, Error> {
+    path[1]
+        .parse()
+        .map_err(|_| Error::store(store, "read", format!("Invalid handle ID: {}", path[1])))
+}
+
+/// Error for a `meta/` path the broker does not describe.
+fn unknown_meta_path(store: &'static str, path: &Path) -> Error {
+    Error::store(
+        store,
+        "read",
+        format!("Unknown meta path: {}/{}", META_PREFIX, path),
+    )
+}
+
+/// Generate documentation for the sync HTTP broker store.
+fn sync_broker_docs() -> Value {
+    Docs::new(
+        "Sync HTTP Broker",
+        "Queue HTTP requests by writing, execute on read (blocks until complete).",
+    )
+    .path("write /", "Queue request, returns outstanding/{id}")
+    .path("read /outstanding", "List queued request IDs")
+    .path(
+        "read /outstanding/{id}",
+        "Execute request (blocks) and return response",
+    )
+    .path("read /outstanding/{id}/request", "View the queued request")
+    .path(
+        "read /outstanding/{id}/response/body",
+        "Navigate into response fields",
+    )
+    .path(
+        "read /outstanding/{id}/error",
+        "Structured error for a non-2xx or failed request (None on success)",
+    )
+    .path("write /outstanding/{id} null", "Delete the handle")
+    .example([
+        "write / {\"method\": \"GET\", \"path\": \"https://httpbin.org/json\"}",
+        "# Returns: outstanding/0",
+        "read /outstanding/0",
+        "# Blocks until complete, returns response",
+    ])
+    .into()
+}
+
 /// Generate documentation for the HTTP client store.
 fn http_client_docs() -> Value {
-    Value::Map(btree! {
-        "title".into() => Value::String("HTTP Client Store".into()),
-        "description".into() => Value::String("Direct HTTP client with a base URL. Read = GET, Write = POST.".into()),
-        "paths".into() => Value::Map(btree! {
-            "read /<path>".into() => Value::String("GET request to base_url/<path>".into()),
-            "write /<path> <json>".into() => Value::String("POST request to base_url/<path>".into()),
-            "write / <HttpRequest>".into() => Value::String("Execute arbitrary request".into()),
-        }),
-        "example".into() => Value::Array(vec![
-            Value::String("# Mount at /api with base URL".into()),
-            Value::String("write /ctx/mounts/api {\"type\": \"http\", \"url\": \"https://api.example.com\"}".into()),
-            Value::String("read /api/users  # GET https://api.example.com/users".into()),
-            Value::String("write /api/users {\"name\": \"Alice\"}  # POST with body".into()),
-        ]),
-    })
+    Docs::new(
+        "HTTP Client Store",
+        "Direct HTTP client with a base URL. Read = GET, Write = POST; other methods are named with a _method/{method} suffix.",
+    )
+    .path("read /<path>", "GET request to base_url/<path>")
+    .path("write /<path> <json>", "POST request to base_url/<path>")
+    .path(
+        "read /<path>/_method/{head,options}",
+        "HEAD or OPTIONS request, returns {status, headers}",
+    )
+    .path(
+        "write /<path>/_method/{put,patch,delete} <json>",
+        "PUT, PATCH or DELETE request; null sends no body",
+    )
+    .path("write / <HttpRequest>", "Execute arbitrary request")
+    .field(
+        "errors",
+        Value::String("Non-2xx responses fail with a detail of {status, status_text, body}; body is the parsed JSON when the response has one".into()),
+    )
+    .example([
+        "# Mount at /api with base URL",
+        "write /ctx/mounts/api {\"type\": \"http\", \"url\": \"https://api.example.com\"}",
+        "read /api/users  # GET https://api.example.com/users",
+        "write /api/users {\"name\": \"Alice\"}  # POST with body",
+        "write /api/users/1/_method/patch {\"name\": \"Al\"}  # PATCH",
+        "write /api/users/1/_method/delete null  # DELETE",
+    ])
+    .into()
 }
 
 /// Generate documentation for the async HTTP broker store.
 fn async_broker_docs() -> Value {
-    Value::Map(btree! {
-        "title".into() => Value::String("Async HTTP Broker".into()),
-        "description".into() => Value::String("Queue HTTP requests by writing, requests execute in background threads.".into()),
-        "paths".into() => Value::Map(btree! {
-            "write /".into() => Value::String("Queue request, returns outstanding/{id}".into()),
-            "read /outstanding".into() => Value::String("List queued request IDs".into()),
-            "read /outstanding/{id}".into() => Value::String("Get request status (pending/complete/failed)".into()),
-            "read /outstanding/{id}/request".into() => Value::String("View the queued request".into()),
-            "read /outstanding/{id}/response".into() => Value::String("Get response (None if still pending)".into()),
-            "read /outstanding/{id}/response/wait".into() => Value::String("Block until response ready".into()),
-            "write /outstanding/{id} null".into() => Value::String("Delete the handle".into()),
-        }),
-        "example".into() => Value::Array(vec![
-            Value::String("write / {\"method\": \"GET\", \"path\": \"https://httpbin.org/json\"}".into()),
-            Value::String("# Returns: outstanding/0 (request starts executing)".into()),
-            Value::String("read /outstanding/0".into()),
-            Value::String("# Returns status: {\"status\": \"pending\"} or {\"status\": \"complete\"}".into()),
-            Value::String("read /outstanding/0/response/wait".into()),
-            Value::String("# Blocks until complete, returns response".into()),
-        ]),
-    })
+    Docs::new(
+        "Async HTTP Broker",
+        "Queue HTTP requests by writing, requests execute in background threads.",
+    )
+    .path("write /", "Queue request, returns outstanding/{id}")
+    .path("read /outstanding", "List queued request IDs")
+    .path(
+        "read /outstanding/{id}",
+        "Get request status (pending/complete/failed)",
+    )
+    .path("read /outstanding/{id}/request", "View the queued request")
+    .path(
+        "read /outstanding/{id}/response",
+        "Get response (None if still pending)",
+    )
+    .path(
+        "read /outstanding/{id}/response/wait",
+        "Block until response ready",
+    )
+    .path(
+        "read /outstanding/{id}/error",
+        "Structured error for a non-2xx or failed request (None otherwise)",
+    )
+    .path("write /outstanding/{id} null", "Delete the handle")
+    .path(
+        "write /batch [requests] or {requests, concurrency?}",
+        "Run requests concurrently, returns batches/{id}",
+    )
+    .path("read /batches/{id}", "Aggregate status with per-item state")
+    .path("read /batches/{id}/wait", "Block until every item finishes")
+    .path(
+        "read /batches/{id}/responses",
+        "Responses in request order (None where not complete)",
+    )
+    .path(
+        "read /batches/{id}/items/{n}/response",
+        "Response of one item",
+    )
+    .path("write /batches/{id} null", "Delete the batch")
+    .example([
+        "write / {\"method\": \"GET\", \"path\": \"https://httpbin.org/json\"}",
+        "# Returns: outstanding/0 (request starts executing)",
+        "read /outstanding/0",
+        "# Returns status: {\"status\": \"pending\"} or {\"status\": \"complete\"}",
+        "read /outstanding/0/response/wait",
+        "# Blocks until complete, returns response",
+    ])
+    .into()
 }
 
 /// Navigate into a Value structure using path components.
 ///
-/// Given a Value and a path like ["headers", "content-type"], returns the nested value.
-/// Returns `Err(index)` if navigation fails at path component `index`.
-fn navigate_value(value: Value, path: &[&str]) -> Result<Value, usize> {
+/// Given a Value and a path like ["headers", "content-type"], returns the nested value,
+/// or `None` if nothing exists at that path.
+fn navigate_value(value: Value, path: &[&str]) -> Option<Value> {
     let mut current = value;
 
-    for (i, key) in path.iter().enumerate() {
+    for key in path {
         current = match current {
-            Value::Map(map) => map
-                .into_iter()
-                .find(|(k, _)| k == key)
-                .map(|(_, v)| v)
-                .ok_or(i)?,
+            Value::Map(map) => map.into_iter().find(|(k, _)| k == key).map(|(_, v)| v)?,
             Value::Array(arr) => {
-                let index: usize = key.parse().map_err(|_| i)?;
-                arr.into_iter().nth(index).ok_or(i)?
+                let index: usize = key.parse().ok()?;
+                arr.into_iter().nth(index)?
             }
-            _ => return Err(i), // Can't navigate into scalars
+            _ => return None, // Can't navigate into scalars
         };
     }
 
-    Ok(current)
+    Some(current)
+}
+
+/// Structured description of a non-2xx response.
+///
+/// The body is the parsed JSON when the response had one, otherwise the raw text.
+fn error_detail(response: &HttpResponse) -> Value {
+    let body = if response.body.is_null() {
+        response
+            .body_text
+            .clone()
+            .filter(|text| !text.is_empty())
+            .map(Value::String)
+            .unwrap_or(Value::Null)
+    } else {
+        structfs_serde_store::json_to_value(response.body.clone())
+    };
+
+    Value::Map(btree! {
+        "status".into() => Value::Integer(response.status as i64),
+        "status_text".into() => Value::String(response.status_text.clone()),
+        "body".into() => body,
+    })
+}
+
+/// The `state` of `meta/outstanding/{id}`: the handle's bookkeeping, its
+/// status, and what it requests.
+fn handle_state<T>(
+    handles: &HandleBroker<T>,
+    id: RequestId,
+    status: &str,
+    request: &HttpRequest,
+) -> Value {
+    let mut state = match handles.status(id) {
+        Some(Value::Map(state)) => state,
+        _ => BTreeMap::new(),
+    };
+    state.extend(btree! {
+        "status".into() => Value::String(status.to_string()),
+        "method".into() => Value::String(format!("{:?}", request.method)),
+        "url".into() => Value::String(request.path.clone()),
+    });
+    Value::Map(state)
+}
+
+/// Error for a write to a path that neither queues nor deletes a request.
+fn invalid_write(store: &'static str, to: &Path) -> Error {
+    Error::store(
+        store,
+        "write",
+        format!(
+            "Invalid write path '{}'. Write to root to queue a request, or write null to outstanding/{{id}} to delete.",
+            to
+        ),
+    )
+}
+
+/// Error for a non-2xx response, with the response as its structured detail.
+fn status_error(store: &'static str, operation: &'static str, response: &HttpResponse) -> Error {
+    Error::store_with_detail(
+        store,
+        operation,
+        format!(
+            "HTTP {} {}: {}",
+            response.status,
+            response.status_text,
+            response.body_text.as_deref().unwrap_or_default()
+        ),
+        error_detail(response),
+    )
 }
 
 /// State of a request handle in the sync broker.
@@ -141,6 +332,25 @@ impl SyncRequestHandle {
     fn is_executed(&self) -> bool {
         self.response.is_some() || self.error.is_some()
     }
+
+    /// Execute the request unless it already has been, caching the outcome.
+    ///
+    /// An expired or cancelled [`OpContext`] fails the read without executing
+    /// the request, and is not cached, so a later read can still run it.
+    fn ensure_executed(&mut self, executor: &impl HttpExecutor) -> Result<(), Error> {
+        if !self.is_executed() {
+            let ctx = OpContext::current();
+            ctx.check("http_broker")?;
+
+            let mut request = self.request.clone();
+            apply_trace_id(&mut request, &ctx);
+            match executor.execute(&request) {
+                Ok(response) => self.response = Some(response),
+                Err(e) => self.error = Some(e),
+            }
+        }
+        Ok(())
+    }
 }
 
 /// HTTP broker store for sync (blocking) requests (new architecture).
@@ -158,12 +368,12 @@ impl SyncRequestHandle {
 /// | `read /outstanding/{id}` | Execute (blocks) & return response | Returns cached response |
 /// | `read /outstanding/{id}/response` | Same as above | Returns cached response |
 /// | `read /outstanding/{id}/request` | View queued request | Returns original request |
+/// | `read /outstanding/{id}/error` | Execute (blocks) & return error | Structured error, or `None` on success |
 /// | `write /outstanding/{id} null` | Delete handle | Removes handle |
 ///
 /// Generic over the HTTP executor to allow mocking in tests.
 pub struct HttpBrokerStore<E: HttpExecutor = ReqwestExecutor> {
-    handles: BTreeMap<RequestId, SyncRequestHandle>,
-    next_request_id: RequestId,
+    handles: HandleBroker<SyncRequestHandle>,
     executor: E,
 }
 
@@ -173,16 +383,20 @@ impl HttpBrokerStore<ReqwestExecutor> {
         let executor =
             ReqwestExecutor::new(timeout).map_err(|e| crate::Error::InvalidUrl { message: e })?;
 
-        Ok(Self {
-            handles: BTreeMap::new(),
-            next_request_id: 0,
-            executor,
-        })
+        Ok(Self::with_executor(executor))
     }
 
     /// Create with default timeout of 30 seconds.
     pub fn with_default_timeout() -> Result<Self, crate::Error> {
-        Self::new(Duration::from_secs(30))
+        Self::new(DEFAULT_TIMEOUT)
+    }
+
+    /// Create a broker whose client uses the given connection options.
+    pub fn with_options(options: &HttpOptions) -> Result<Self, crate::Error> {
+        let executor = ReqwestExecutor::with_options(options)
+            .map_err(|e| crate::Error::InvalidUrl { message: e })?;
+
+        Ok(Self::with_executor(executor))
     }
 }
 
@@ -192,38 +406,21 @@ impl<E: HttpExecutor> HttpBrokerStore<E> {
     /// This is primarily useful for testing with mock executors.
     pub fn with_executor(executor: E) -> Self {
         Self {
-            handles: BTreeMap::new(),
-            next_request_id: 0,
+            handles: request_handles(),
             executor,
         }
     }
 
-    /// Parse request ID and optional sub-path from paths like:
-    /// - "outstanding" -> None (listing)
-    /// - "outstanding/123" -> Some((123, None))
-    /// - "outstanding/123/request" -> Some((123, Some("request")))
-    /// - "outstanding/123/response/status" -> Some((123, Some("response/status")))
-    fn parse_handle_path(path: &Path) -> Option<(RequestId, Option<String>)> {
-        if path.is_empty() || path[0] != OUTSTANDING_PREFIX {
-            return None;
-        }
-        if path.len() == 1 {
-            // Just "outstanding" - listing request
-            return None;
-        }
-        let id: RequestId = path[1].parse().ok()?;
-        let sub_path = if path.len() > 2 {
-            Some(path.components[2..].join("/"))
-        } else {
-            None
-        };
-        Some((id, sub_path))
+    /// Delete handles that go unused for `ttl`.
+    pub fn with_handle_ttl(mut self, ttl: Duration) -> Self {
+        self.handles.set_ttl(Some(ttl));
+        self
     }
 
     /// Check if a handle exists (for testing).
     #[cfg(test)]
     pub fn has_handle(&self, id: RequestId) -> bool {
-        self.handles.contains_key(&id)
+        self.handles.contains(id)
     }
 
     /// Get the number of handles (for testing).
@@ -231,58 +428,36 @@ impl<E: HttpExecutor> HttpBrokerStore<E> {
     pub fn handle_count(&self) -> usize {
         self.handles.len()
     }
+}
 
-    /// Read meta paths for action descriptors and handle inspection.
-    fn read_meta(&self, path: &Path) -> Result<Option<Record>, Error> {
-        // meta/ root - list available meta operations
-        if path.len() == 1 {
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "queue".into() => Reference::with_type("meta/queue", "action").to_value(),
-                "outstanding".into() => Reference::with_type("meta/outstanding", "collection").to_value(),
-            }))));
+impl<E: HttpExecutor> SelfDescribing for HttpBrokerStore<E> {
+    fn describe(&self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(broker_meta_root()));
         }
-
-        // meta/queue - action descriptor for queuing requests
-        if path.len() == 2 && path[1] == "queue" {
-            return Ok(Some(Record::parsed(Self::queue_action_descriptor())));
+        if path.len() == 1 && path[0] == "queue" {
+            return Ok(Some(queue_action()));
         }
 
         // meta/outstanding - list handles with meta references
-        if path.len() == 2 && path[1] == OUTSTANDING_PREFIX {
-            let items: Vec<Value> = self
+        if path.len() == 1 && path[0] == OUTSTANDING_PREFIX {
+            let items = self
                 .handles
-                .keys()
-                .map(|id| {
-                    Reference::with_type(format!("meta/outstanding/{}", id), "request-handle-meta")
-                        .to_value()
-                })
-                .collect();
-            return Ok(Some(Record::parsed(Value::Map(btree! {
+                .references("meta/outstanding", "request-handle-meta");
+            return Ok(Some(Value::Map(btree! {
                 "items".into() => Value::Array(items),
-            }))));
+            })));
         }
 
-        // meta/outstanding/{id} - handle state and navigation
-        if path.len() >= 3 && path[1] == OUTSTANDING_PREFIX {
-            let id: RequestId = path[2].parse().map_err(|_| {
-                Error::store(
-                    "http_broker",
-                    "read",
-                    format!("Invalid handle ID: {}", path[2]),
-                )
-            })?;
-
-            let handle = self.handles.get(&id).ok_or_else(|| {
-                Error::store(
-                    "http_broker",
-                    "read",
-                    format!("Request with ID {} not found", id),
-                )
-            })?;
+        if path.len() >= 2 && path[0] == OUTSTANDING_PREFIX {
+            let id = meta_handle_id("http_broker", path)?;
+            let Some(handle) = self.handles.get(id) else {
+                return Ok(None);
+            };
 
             // meta/outstanding/{id}/delete - delete action descriptor
-            if path.len() == 4 && path[3] == "delete" {
-                return Ok(Some(Record::parsed(Self::delete_action_descriptor(id))));
+            if path.len() == 3 && path[2] == "delete" {
+                return Ok(Some(delete_action(id)));
             }
 
             // meta/outstanding/{id} - handle state + navigation
@@ -296,74 +471,19 @@ impl<E: HttpExecutor> HttpBrokerStore<E> {
                 "pending"
             };
 
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "state".into() => Value::Map(btree! {
-                    "status".into() => Value::String(state.to_string()),
-                    "method".into() => Value::String(format!("{:?}", handle.request.method)),
-                    "url".into() => Value::String(handle.request.path.clone()),
-                }),
+            return Ok(Some(Value::Map(btree! {
+                "state".into() => handle_state(&self.handles, id, state, &handle.request),
                 "request".into() => Reference::with_type(format!("outstanding/{}/request", id), "http-request").to_value(),
                 "response".into() => Reference::with_type(format!("outstanding/{}/response", id), "http-response").to_value(),
                 "delete".into() => Reference::with_type(format!("meta/outstanding/{}/delete", id), "action").to_value(),
-            }))));
+            })));
         }
 
-        Err(Error::store(
-            "http_broker",
-            "read",
-            format!("Unknown meta path: {}", path),
-        ))
-    }
-
-    /// Generate action descriptor for queue operation.
-    fn queue_action_descriptor() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! { "name".into() => Value::String("action".into()) }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "method".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(true),
-                    "values".into() => Value::Array(vec![
-                        Value::String("GET".into()),
-                        Value::String("POST".into()),
-                        Value::String("PUT".into()),
-                        Value::String("PATCH".into()),
-                        Value::String("DELETE".into()),
-                        Value::String("HEAD".into()),
-                        Value::String("OPTIONS".into()),
-                    ]),
-                }),
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(true),
-                }),
-                "headers".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("map".into()) }),
-                    "required".into() => Value::Bool(false),
-                }),
-                "body".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(false),
-                }),
-            }),
-            "returns".into() => Value::Map(btree! {
-                "type".into() => Value::Map(btree! { "name".into() => Value::String("request-handle".into()) }),
-                "collection".into() => Reference::new("outstanding").to_value(),
-            }),
-        })
+        Err(unknown_meta_path("http_broker", path))
     }
 
-    /// Generate action descriptor for delete operation.
-    fn delete_action_descriptor(id: RequestId) -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! { "name".into() => Value::String("action".into()) }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new(format!("outstanding/{}", id)).to_value(),
-            "accepts".into() => Value::String("null".into()),
-            "returns".into() => Value::String("void".into()),
-        })
+    fn docs(&self) -> Option<Value> {
+        Some(sync_broker_docs())
     }
 }
 
@@ -379,32 +499,18 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
             }))));
         }
 
-        // Handle docs: read /docs or /docs/... -> documentation
-        if from[0] == DOCS_PATH {
-            return Ok(Some(Record::parsed(sync_broker_docs())));
-        }
-
-        // Handle meta paths
-        if from[0] == META_PATH {
-            return self.read_meta(from);
+        // Handle docs and meta paths
+        if let Some(result) = read_lens(self, from) {
+            return result;
         }
 
         // Handle listing: read /outstanding -> {items: [refs...]}
-        if from.len() == 1 && from[0] == OUTSTANDING_PREFIX {
-            let items: Vec<Value> = self
-                .handles
-                .keys()
-                .map(|id| {
-                    Reference::with_type(format!("outstanding/{}", id), "request-handle").to_value()
-                })
-                .collect();
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "items".into() => Value::Array(items),
-            }))));
+        if self.handles.is_listing(from) {
+            return Ok(Some(Record::parsed(self.handles.listing())));
         }
 
         // Parse /outstanding/{id} or /outstanding/{id}/request
-        let (request_id, sub_path) = Self::parse_handle_path(from).ok_or_else(|| {
+        let (request_id, sub_path) = self.handles.parse(from).ok_or_else(|| {
             Error::store(
                 "http_broker",
                 "read",
@@ -415,19 +521,14 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
             )
         })?;
 
-        let handle = self.handles.get_mut(&request_id).ok_or_else(|| {
-            Error::store(
-                "http_broker",
-                "read",
-                format!("Request with ID {} not found", request_id),
-            )
-        })?;
+        // A request that was never queued, or was deleted or expired,
+        // does not exist
+        let Some(handle) = self.handles.touch(request_id) else {
+            return Ok(None);
+        };
 
-        // Parse sub-path components for deep navigation
-        let sub_components: Vec<&str> = sub_path
-            .as_deref()
-            .map(|s| s.split('/').collect())
-            .unwrap_or_default();
+        // Sub-path components for deep navigation
+        let sub_components: Vec<&str> = sub_path.iter().map(String::as_str).collect();
 
         // Handle /outstanding/{id}/request[/...] - view queued request
         if sub_components.first() == Some(&"request") {
@@ -437,14 +538,7 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
             // Navigate into request if there's a deeper path
             if sub_components.len() > 1 {
                 let nav_path = &sub_components[1..];
-                return match navigate_value(value, nav_path) {
-                    Ok(v) => Ok(Some(Record::parsed(v))),
-                    Err(i) => Err(Error::store(
-                        "http_broker",
-                        "read",
-                        format!("Path not found at index {}: '{}'", i, nav_path[i]),
-                    )),
-                };
+                return Ok(navigate_value(value, nav_path).map(Record::parsed));
             }
             return Ok(Some(Record::parsed(value)));
         }
@@ -453,12 +547,7 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
         // Both paths have the same blocking behavior for symmetry with async broker
         if sub_components.is_empty() || sub_components.first() == Some(&"response") {
             // Execute on first read if not yet executed (idempotent)
-            if !handle.is_executed() {
-                match self.executor.execute(&handle.request) {
-                    Ok(response) => handle.response = Some(response),
-                    Err(e) => handle.error = Some(e),
-                }
-            }
+            handle.ensure_executed(&self.executor)?;
 
             // Return cached response or error
             if let Some(ref response) = handle.response {
@@ -468,14 +557,7 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
                 // Navigate into response if there's a deeper path
                 if sub_components.len() > 1 {
                     let nav_path = &sub_components[1..];
-                    return match navigate_value(value, nav_path) {
-                        Ok(v) => Ok(Some(Record::parsed(v))),
-                        Err(i) => Err(Error::store(
-                            "http_broker",
-                            "read",
-                            format!("Path not found at index {}: '{}'", i, nav_path[i]),
-                        )),
-                    };
+                    return Ok(navigate_value(value, nav_path).map(Record::parsed));
                 }
                 return Ok(Some(Record::parsed(value)));
             } else if let Some(ref error) = handle.error {
@@ -489,12 +571,31 @@ impl<E: HttpExecutor> Reader for HttpBrokerStore<E> {
             }
         }
 
+        // Handle /outstanding/{id}/error[/...] - structured error, None on success
+        if sub_components.first() == Some(&"error") {
+            handle.ensure_executed(&self.executor)?;
+
+            let value = match (&handle.response, &handle.error) {
+                (Some(response), _) if !response.is_success() => error_detail(response),
+                (_, Some(error)) => Value::Map(btree! {
+                    "message".into() => Value::String(error.clone()),
+                }),
+                _ => return Ok(None),
+            };
+
+            if sub_components.len() > 1 {
+                let nav_path = &sub_components[1..];
+                return Ok(navigate_value(value, nav_path).map(Record::parsed));
+            }
+            return Ok(Some(Record::parsed(value)));
+        }
+
         // Reject unknown sub-paths
         Err(Error::store(
             "http_broker",
             "read",
             format!(
-                "Unknown sub-path '{}'. Use 'request' or 'response'.",
+                "Unknown sub-path '{}'. Use 'request', 'response', or 'error'.",
                 sub_components.first().unwrap_or(&""),
             ),
         ))
@@ -506,9 +607,12 @@ impl<E: HttpExecutor> Writer for HttpBrokerStore<E> {
         let value = data.into_value(&NoCodec)?;
 
         // Delete handle: write null to /outstanding/{id}
-        if let Some((request_id, None)) = Self::parse_handle_path(to) {
+        if let Some((request_id, sub_path)) = self.handles.parse(to) {
+            if !sub_path.is_empty() {
+                return Err(invalid_write("http_broker", to));
+            }
             if value == Value::Null {
-                self.handles.remove(&request_id);
+                self.handles.remove(request_id);
                 return Ok(to.clone());
             }
             return Err(Error::store(
@@ -527,29 +631,49 @@ impl<E: HttpExecutor> Writer for HttpBrokerStore<E> {
                 )
             })?;
 
-            let request_id = self.next_request_id;
-            self.next_request_id += 1;
-
-            self.handles
-                .insert(request_id, SyncRequestHandle::new(request));
-
-            return Ok(path!(OUTSTANDING_PREFIX).join(&path!(&format!("{}", request_id))));
+            let request_id = self.handles.insert(SyncRequestHandle::new(request));
+            return Ok(self.handles.path(request_id));
         }
 
-        Err(Error::store(
-            "http_broker",
-            "write",
+        Err(invalid_write("http_broker", to))
+    }
+}
+
+/// Path segment naming the method of a request, as in
+/// `users/1/_method/patch`.
+const METHOD_SEGMENT: &str = "_method";
+
+/// Split `{path}/_method/{method}` into the resource path and the method,
+/// or `None` for a path without a method suffix.
+fn split_method(path: &Path) -> Result<Option<(Path, Method)>, Error> {
+    let Some(at) = path.components.iter().position(|c| c == METHOD_SEGMENT) else {
+        return Ok(None);
+    };
+    let method = match &path.components[at + 1..] {
+        [name] => Method::parse(name),
+        _ => None,
+    };
+    let method = method.ok_or_else(|| {
+        Error::store(
+            "http_client",
+            "method",
             format!(
-                "Invalid write path '{}'. Write to root to queue a request, or write null to outstanding/{{id}} to delete.",
-                to
+                "Expected {}/{{method}} at the end of '{}', where method is get, post, put, patch, delete, head or options",
+                METHOD_SEGMENT, path
             ),
-        ))
-    }
+        )
+    })?;
+    Ok(Some((path.slice(0, at), method)))
 }
 
 /// HTTP client store for direct requests (new architecture).
 ///
-/// Maps read/write operations to GET/POST requests.
+/// Maps reads to GET and writes to POST. Other methods are named by
+/// appending `_method/{method}` to the path: reads send HEAD and OPTIONS
+/// (and GET), returning `{status, headers}` for the first two, and writes
+/// send PUT, PATCH and DELETE (and POST), with no body when the value
+/// written is null. [`Deleter::delete`] sends DELETE to the path and
+/// treats 404 and 410 as already deleted.
 /// Generic over the HTTP executor to allow mocking in tests.
 pub struct HttpClientStore<E: HttpExecutor = ReqwestExecutor> {
     executor: E,
@@ -570,6 +694,14 @@ impl HttpClientStore<ReqwestExecutor> {
             default_headers: std::collections::HashMap::new(),
         })
     }
+
+    /// Create a client store whose client uses the given connection options.
+    pub fn with_options(base_url: &str, options: &HttpOptions) -> Result<Self, crate::Error> {
+        let executor = ReqwestExecutor::with_options(options)
+            .map_err(|e| crate::Error::InvalidUrl { message: e })?;
+
+        Self::with_executor(base_url, executor)
+    }
 }
 
 impl<E: HttpExecutor> HttpClientStore<E> {
@@ -598,6 +730,8 @@ impl<E: HttpExecutor> HttpClientStore<E> {
 
     /// Build a full request with base URL, default headers, etc.
     fn build_request(&self, mut request: HttpRequest) -> HttpRequest {
+        apply_trace_id(&mut request, &OpContext::current());
+
         // Resolve relative URLs against base URL
         if !request.path.starts_with("http://") && !request.path.starts_with("https://") {
             if let Ok(url) = self.base_url.join(&request.path) {
@@ -617,9 +751,20 @@ impl<E: HttpExecutor> HttpClientStore<E> {
 
     /// Perform a GET request and return the response
     pub fn get(&self, path: &Path) -> Result<HttpResponse, crate::Error> {
+        self.send(Method::GET, path, None)
+    }
+
+    /// Perform a request with any method and return the response.
+    pub fn send(
+        &self,
+        method: Method,
+        path: &Path,
+        body: Option<serde_json::Value>,
+    ) -> Result<HttpResponse, crate::Error> {
         let request = HttpRequest {
-            method: crate::types::Method::GET,
+            method,
             path: path.components.join("/"),
+            body,
             ..Default::default()
         };
         let full_request = self.build_request(request);
@@ -631,32 +776,53 @@ impl<E: HttpExecutor> HttpClientStore<E> {
     }
 }
 
+/// `{status, headers}` of a response, for methods whose response has no
+/// body worth returning.
+fn head_value(response: &HttpResponse) -> Value {
+    let headers = response
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect();
+    Value::Map(btree! {
+        "status".into() => Value::Integer(response.status as i64),
+        "headers".into() => Value::Map(headers),
+    })
+}
+
 impl<E: HttpExecutor> Reader for HttpClientStore<E> {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         // Handle docs: read /docs or /docs/... -> documentation
-        if !from.is_empty() && from[0] == DOCS_PATH {
+        if !from.is_empty() && from[0] == DOCS_PREFIX {
             return Ok(Some(Record::parsed(http_client_docs())));
         }
+        OpContext::check_current("http_client")?;
 
+        let (path, method) = split_method(from)?.unwrap_or((from.clone(), Method::GET));
+        if !method.is_safe() {
+            return Err(Error::store(
+                "http_client",
+                "read",
+                format!(
+                    "{:?} changes the resource; write to '{}' instead",
+                    method, from
+                ),
+            ));
+        }
         let response = self
-            .get(from)
+            .send(method.clone(), &path, None)
             .map_err(|e| Error::store("http_client", "read", e.to_string()))?;
 
-        if response.status == 404 {
+        // Missing resources are `None`; every other non-2xx status is an error
+        if matches!(response.status, 404 | 410) {
             return Ok(None);
         }
 
         if !response.is_success() {
-            return Err(Error::store(
-                "http_client",
-                "read",
-                format!(
-                    "HTTP {} {}: {}",
-                    response.status,
-                    response.status_text,
-                    response.body_text.unwrap_or_default()
-                ),
-            ));
+            return Err(status_error("http_client", "read", &response));
+        }
+        if method != Method::GET {
+            return Ok(Some(Record::parsed(head_value(&response))));
         }
 
         // Convert response body to Value
@@ -669,8 +835,30 @@ impl<E: HttpExecutor> Reader for HttpClientStore<E> {
 
 impl<E: HttpExecutor> Writer for HttpClientStore<E> {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        OpContext::check_current("http_client")?;
         let value = data.into_value(&NoCodec)?;
 
+        if let Some((path, method)) = split_method(to)? {
+            if matches!(method, Method::HEAD | Method::OPTIONS) {
+                return Err(Error::store(
+                    "http_client",
+                    "write",
+                    format!("{:?} returns headers; read '{}' instead", method, to),
+                ));
+            }
+            let body = match value {
+                Value::Null => None,
+                value => Some(structfs_serde_store::value_to_json(value)),
+            };
+            let response = self
+                .send(method, &path, body)
+                .map_err(|e| Error::store("http_client", "write", e.to_string()))?;
+            if !response.is_success() {
+                return Err(status_error("http_client", "write", &response));
+            }
+            return Ok(path);
+        }
+
         // Try to interpret as HttpRequest if writing to root
         let response = if to.is_empty() {
             if let Ok(request) = from_value::<HttpRequest>(value.clone()) {
@@ -709,20 +897,29 @@ impl<E: HttpExecutor> Writer for HttpClientStore<E> {
         };
 
         if !response.is_success() {
-            return Err(Error::store(
-                "http_client",
-                "write",
-                format!(
-                    "HTTP {} {}: {}",
-                    response.status,
-                    response.status_text,
-                    response.body_text.unwrap_or_default()
-                ),
-            ));
+            return Err(status_error("http_client", "write", &response));
         }
 
         Ok(to.clone())
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl<E: HttpExecutor> Deleter for HttpClientStore<E> {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        OpContext::check_current("http_client")?;
+        let response = self
+            .send(Method::DELETE, path, None)
+            .map_err(|e| Error::store("http_client", "delete", e.to_string()))?;
+        if response.is_success() || matches!(response.status, 404 | 410) {
+            Ok(())
+        } else {
+            Err(status_error("http_client", "delete", &response))
+        }
+    }
 }
 
 /// Internal state for an async request
@@ -732,6 +929,96 @@ struct AsyncRequestHandle {
     response: Option<HttpResponse>,
 }
 
+impl AsyncRequestHandle {
+    /// The handle for a journal entry kept under `id`.
+    fn from_entry(id: RequestId, entry: JournalEntry) -> Self {
+        let status = match entry.state {
+            RequestState::Pending => RequestStatus::pending(id.to_string()),
+            RequestState::Complete => RequestStatus::complete(id.to_string()),
+            RequestState::Failed => {
+                RequestStatus::failed(id.to_string(), entry.error.unwrap_or_default())
+            }
+        };
+        Self {
+            request: entry.request,
+            status,
+            response: entry.response,
+        }
+    }
+}
+
+/// One request of a batch.
+struct BatchItem {
+    request: HttpRequest,
+    state: RequestState,
+    error: Option<String>,
+    response: Option<HttpResponse>,
+}
+
+/// Internal state for a batch of requests run together.
+struct BatchHandle {
+    items: Vec<BatchItem>,
+    concurrency: usize,
+}
+
+impl BatchHandle {
+    fn count(&self, state: RequestState) -> usize {
+        self.items.iter().filter(|item| item.state == state).count()
+    }
+
+    fn is_done(&self) -> bool {
+        self.count(RequestState::Pending) == 0
+    }
+
+    /// `{state, total, pending, complete, failed, concurrency, items}`.
+    ///
+    /// The batch is complete once no item is pending, whether or not any
+    /// failed.
+    fn summary(&self, id: RequestId) -> Result<Value, Error> {
+        let items = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| self.item_summary(id, i, item))
+            .collect::<Result<Vec<_>, _>>()?;
+        let state = if self.is_done() {
+            "complete"
+        } else {
+            "pending"
+        };
+        Ok(Value::Map(btree! {
+            "state".into() => Value::String(state.into()),
+            "total".into() => Value::Integer(self.items.len() as i64),
+            "pending".into() => Value::Integer(self.count(RequestState::Pending) as i64),
+            "complete".into() => Value::Integer(self.count(RequestState::Complete) as i64),
+            "failed".into() => Value::Integer(self.count(RequestState::Failed) as i64),
+            "concurrency".into() => Value::Integer(self.concurrency as i64),
+            "items".into() => Value::Array(items),
+        }))
+    }
+
+    /// `{state, status?, error?, request, response?}` for item `i`.
+    fn item_summary(&self, id: RequestId, i: usize, item: &BatchItem) -> Result<Value, Error> {
+        let prefix = format!("{}/{}/items/{}", BATCHES_PREFIX, id, i);
+        let mut summary = btree! {
+            "state".into() => to_value(&item.state)
+                .map_err(|e| Error::encode(structfs_core_store::Format::JSON, e.to_string()))?,
+            "request".into() => Reference::with_type(format!("{}/request", prefix), "http-request").to_value(),
+        };
+        if let Some(response) = &item.response {
+            summary.insert("status".into(), Value::Integer(response.status.into()));
+            summary.insert(
+                "response".into(),
+                Reference::with_type(format!("{}/response", prefix), "http-response").to_value(),
+            );
+        }
+        if let Some(error) = &item.error {
+            summary.insert("error".into(), Value::String(error.clone()));
+        }
+        Ok(Value::Map(summary))
+    }
+}
+
 /// Async HTTP broker store (new architecture).
 ///
 /// Requests are executed in background threads. Write to queue a request,
@@ -747,34 +1034,156 @@ struct AsyncRequestHandle {
 /// | `read /outstanding/{id}/request` | View queued request | Returns original request |
 /// | `read /outstanding/{id}/response` | Get response (non-blocking) | Returns response or `None` if pending |
 /// | `read /outstanding/{id}/response/wait` | Get response (blocking) | Blocks until response ready |
+/// | `read /outstanding/{id}/error` | Get error (non-blocking) | Structured error, or `None` if pending or successful |
 /// | `write /outstanding/{id} null` | Delete handle | Removes handle |
+/// | `write /batch [requests]` | Run requests concurrently | Returns `batches/{id}` |
+/// | `read /batches/{id}` | Check batch | Aggregate status with per-item state |
+/// | `read /batches/{id}/wait` | Wait for batch (blocking) | Aggregate status once every item finished |
+/// | `read /batches/{id}/responses` | Get responses | One per request, `None` where not complete |
+/// | `read /batches/{id}/items/{n}/...` | Inspect one item | `request`, `response`, `error` as for handles |
+/// | `write /batches/{id} null` | Delete batch | Removes batch |
+///
+/// A batch runs at most [`with_batch_concurrency`](Self::with_batch_concurrency)
+/// requests at once, or the `concurrency` given with the batch if lower.
+///
+/// ## Persistence
+///
+/// By default handles live only in memory. With
+/// [`with_journal`](Self::with_journal) every queued request and its outcome
+/// is also written to a backing store. Opening a broker on the same store
+/// after a restart restores the handles under their old ids and runs again
+/// any request that had not finished, so each request is sent at least once.
+/// Batches are not journaled.
 pub struct AsyncHttpBrokerStore {
-    handles: Arc<Mutex<HashMap<RequestId, AsyncRequestHandle>>>,
-    next_request_id: RequestId,
+    handles: Arc<Mutex<HandleBroker<AsyncRequestHandle>>>,
+    batches: Arc<Mutex<HandleBroker<BatchHandle>>>,
+    batch_concurrency: usize,
+    journal: Option<Arc<Journal>>,
+    /// Shared by every request so connections are pooled and reused
+    client: Arc<Client>,
     timeout: Duration,
+    options: HttpOptions,
+    signer: Option<Arc<dyn RequestSigner>>,
 }
 
 impl AsyncHttpBrokerStore {
     /// Create a new async HTTP broker store with the given request timeout.
     pub fn new(timeout: Duration) -> Result<Self, crate::Error> {
+        Self::build(timeout, HttpOptions::default(), None)
+    }
+
+    fn build(
+        timeout: Duration,
+        options: HttpOptions,
+        signer: Option<Arc<dyn RequestSigner>>,
+    ) -> Result<Self, crate::Error> {
+        let client = client_builder(timeout, &options)
+            .and_then(|builder| builder.build().map_err(|e| e.to_string()))
+            .map_err(|message| crate::Error::InvalidUrl { message })?;
+
         Ok(Self {
-            handles: Arc::new(Mutex::new(HashMap::new())),
-            next_request_id: 0,
+            handles: Arc::new(Mutex::new(request_handles())),
+            batches: Arc::new(Mutex::new(HandleBroker::new(BATCHES_PREFIX, "batch"))),
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            journal: None,
+            client: Arc::new(client),
             timeout,
+            options,
+            signer,
         })
     }
 
     /// Create with default timeout of 30 seconds.
     pub fn with_default_timeout() -> Result<Self, crate::Error> {
-        Self::new(Duration::from_secs(30))
+        Self::new(DEFAULT_TIMEOUT)
+    }
+
+    /// Create a broker whose requests use the given connection options.
+    pub fn with_options(options: &HttpOptions) -> Result<Self, crate::Error> {
+        let signer = options
+            .auth
+            .as_ref()
+            .map(signer_for)
+            .transpose()
+            .map_err(|message| crate::Error::Other { message })?;
+
+        Self::build(DEFAULT_TIMEOUT, options.clone(), signer)
+    }
+
+    /// Delete handles that go unused for `ttl`.
+    ///
+    /// A request still running when its handle expires finishes, but its
+    /// result is discarded.
+    pub fn with_handle_ttl(self, ttl: Duration) -> Self {
+        if let Ok(mut handles) = self.handles.lock() {
+            handles.set_ttl(Some(ttl));
+        }
+        if let Ok(mut batches) = self.batches.lock() {
+            batches.set_ttl(Some(ttl));
+        }
+        self
+    }
+
+    /// Keep requests and responses in `store` so they survive restarts.
+    ///
+    /// Handles kept in `store` from an earlier run are restored, and requests
+    /// that had not finished are queued again. Requests that finished are
+    /// kept for `retention`, then dropped from the store and the broker.
+    pub fn with_journal(mut self, store: StoreBox, retention: Duration) -> Result<Self, Error> {
+        let journal = Journal::open(store, retention)?;
+        let entries = journal.entries()?;
+        let next_id = journal.next_id()?;
+        self.journal = Some(Arc::new(journal));
+
+        let mut unfinished = Vec::new();
+        {
+            let mut handles = self.lock_handles("journal")?;
+            handles.reserve_below(next_id);
+            for (id, entry) in entries {
+                if entry.state == RequestState::Pending {
+                    unfinished.push((id, entry.request.clone()));
+                }
+                handles.restore(id, AsyncRequestHandle::from_entry(id, entry));
+            }
+        }
+
+        let ctx = OpContext::current();
+        for (id, request) in unfinished {
+            self.spawn_request(id, request, ctx.clone());
+        }
+        Ok(self)
+    }
+
+    /// Run at most `limit` requests of a batch at once. Defaults to 8.
+    pub fn with_batch_concurrency(mut self, limit: usize) -> Self {
+        self.batch_concurrency = limit.max(1);
+        self
     }
 
     /// Execute an HTTP request and return the response.
-    fn execute_request(request: HttpRequest, timeout: Duration) -> Result<HttpResponse, String> {
-        let client = Client::builder()
-            .timeout(timeout)
-            .build()
-            .map_err(|e| e.to_string())?;
+    ///
+    /// Honors the current [`OpContext`] like [`ReqwestExecutor`] does.
+    fn execute_request(
+        mut request: HttpRequest,
+        client: &Client,
+        timeout: Duration,
+        options: &HttpOptions,
+        signer: Option<&dyn RequestSigner>,
+    ) -> Result<HttpResponse, String> {
+        let ctx = OpContext::current();
+        ctx.check("async_http_broker").map_err(|e| e.to_string())?;
+        let url = parse_url(&request.path, options)?;
+        apply_trace_id(&mut request, &ctx);
+
+        if let Some(signer) = signer {
+            signer.sign(&mut request, chrono::Utc::now())?;
+        }
+
+        let routed = server_name_client(
+            || client_builder(timeout, options),
+            options.tls.as_ref(),
+            &url,
+        )?;
 
         let method: http::Method = request.method.into();
 
@@ -785,8 +1194,14 @@ impl AsyncHttpBrokerStore {
             headers.insert(header_name, header_value);
         }
 
-        let mut req_builder = client.request(method, &request.path);
-        req_builder = req_builder.headers(headers);
+        let mut req_builder = match routed {
+            Some((client, routed)) => client.request(method, routed),
+            None => client.request(method, url),
+        };
+        req_builder = req_builder.headers(headers).timeout(request_timeout(
+            options.timeout_ms.map_or(timeout, Duration::from_millis),
+            &ctx,
+        ));
 
         if !request.query.is_empty() {
             req_builder = req_builder.query(&request.query);
@@ -812,8 +1227,7 @@ impl AsyncHttpBrokerStore {
             }
         }
 
-        let body_text = response.text().map_err(|e| e.to_string())?;
-        let body = serde_json::from_str(&body_text).unwrap_or(serde_json::Value::Null);
+        let (body, body_text) = read_body(response, options)?;
 
         Ok(HttpResponse {
             status,
@@ -824,21 +1238,319 @@ impl AsyncHttpBrokerStore {
         })
     }
 
-    /// Parse request ID and sub-path from a path like "outstanding/123" or "outstanding/123/response".
-    fn parse_handle_path(path: &Path) -> Option<(RequestId, Option<String>)> {
-        if path.is_empty() || path[0] != OUTSTANDING_PREFIX {
-            return None;
+    /// Run request `id` on a background thread and record its outcome.
+    fn spawn_request(&self, id: RequestId, request: HttpRequest, ctx: OpContext) {
+        let handles = Arc::clone(&self.handles);
+        let journal = self.journal.clone();
+        let client = Arc::clone(&self.client);
+        let timeout = self.timeout;
+        let options = self.options.clone();
+        let signer = self.signer.clone();
+        thread::spawn(move || {
+            let mut entry = JournalEntry::pending(request.clone());
+            entry.finish(ctx.scope(|| {
+                Self::execute_request(request, &client, timeout, &options, signer.as_deref())
+            }));
+
+            let Ok(mut handles) = handles.lock() else {
+                return;
+            };
+            // The handle was deleted or expired while the request ran
+            let Some(handle) = handles.get_mut(id) else {
+                return;
+            };
+            if let Some(journal) = &journal {
+                // The outcome stays available in memory even if it cannot
+                // be kept; the request is then sent again after a restart
+                let _ = journal.save(id, &entry, 0);
+            }
+            *handle = AsyncRequestHandle::from_entry(id, entry);
+        });
+    }
+
+    /// Drop requests that expired from memory or outlived the journal's
+    /// retention, in both places.
+    fn prune_journal(&self) -> Result<(), Error> {
+        let Some(journal) = &self.journal else {
+            return Ok(());
+        };
+        let mut handles = self.lock_handles("write")?;
+        for (id, _) in handles.sweep() {
+            journal.remove(id)?;
         }
-        if path.len() < 2 {
-            return None;
+        for id in journal.prune()? {
+            handles.remove(id);
         }
-        let id: RequestId = path[1].parse().ok()?;
-        let sub_path = if path.len() > 2 {
-            Some(path.components[2..].join("/"))
-        } else {
-            None
+        Ok(())
+    }
+
+    fn lock_handles(
+        &self,
+        operation: &'static str,
+    ) -> Result<std::sync::MutexGuard<'_, HandleBroker<AsyncRequestHandle>>, Error> {
+        self.handles
+            .lock()
+            .map_err(|e| Error::store("async_http_broker", operation, format!("Lock error: {}", e)))
+    }
+
+    fn lock_batches(
+        &self,
+        operation: &'static str,
+    ) -> Result<std::sync::MutexGuard<'_, HandleBroker<BatchHandle>>, Error> {
+        self.batches
+            .lock()
+            .map_err(|e| Error::store("async_http_broker", operation, format!("Lock error: {}", e)))
+    }
+
+    /// Queue a batch written to `batch`, returning `batches/{id}`.
+    fn queue_batch(&mut self, value: Value) -> Result<Path, Error> {
+        let (requests, concurrency) = match value {
+            Value::Array(requests) => (requests, None),
+            Value::Map(mut map) => {
+                let requests = match map.remove("requests") {
+                    Some(Value::Array(requests)) => requests,
+                    _ => {
+                        return Err(Error::store(
+                            "async_http_broker",
+                            "write",
+                            "A batch needs a 'requests' array",
+                        ))
+                    }
+                };
+                let concurrency = match map.remove("concurrency") {
+                    None | Some(Value::Null) => None,
+                    Some(Value::Integer(n)) if n > 0 => Some(n as usize),
+                    Some(other) => {
+                        return Err(Error::store(
+                            "async_http_broker",
+                            "write",
+                            format!("'concurrency' must be a positive integer, got {:?}", other),
+                        ))
+                    }
+                };
+                (requests, concurrency)
+            }
+            _ => {
+                return Err(Error::store(
+                    "async_http_broker",
+                    "write",
+                    "A batch is an array of requests or {requests, concurrency}",
+                ))
+            }
+        };
+
+        let requests = requests
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                from_value::<HttpRequest>(value).map_err(|e| {
+                    Error::decode(
+                        structfs_core_store::Format::JSON,
+                        format!("Batch item {} must be an HttpRequest: {}", i, e),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ctx = OpContext::current();
+        ctx.check("async_http_broker")?;
+
+        let concurrency =
+            concurrency.map_or(self.batch_concurrency, |n| n.min(self.batch_concurrency));
+        let batch_id = self.lock_batches("write")?.insert(BatchHandle {
+            items: requests
+                .iter()
+                .map(|request| BatchItem {
+                    request: request.clone(),
+                    state: RequestState::Pending,
+                    error: None,
+                    response: None,
+                })
+                .collect(),
+            concurrency,
+        });
+
+        let batches = Arc::clone(&self.batches);
+        let client = Arc::clone(&self.client);
+        let timeout = self.timeout;
+        let options = self.options.clone();
+        let signer = self.signer.clone();
+        thread::spawn(move || {
+            let next = AtomicUsize::new(0);
+            thread::scope(|scope| {
+                for _ in 0..concurrency.min(requests.len()) {
+                    scope.spawn(|| loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(request) = requests.get(i) else {
+                            break;
+                        };
+                        let result = ctx.scope(|| {
+                            Self::execute_request(
+                                request.clone(),
+                                &client,
+                                timeout,
+                                &options,
+                                signer.as_deref(),
+                            )
+                        });
+
+                        let Ok(mut batches) = batches.lock() else {
+                            break;
+                        };
+                        // The batch was deleted or expired: stop starting requests
+                        let Some(batch) = batches.get_mut(batch_id) else {
+                            break;
+                        };
+                        let item = &mut batch.items[i];
+                        match result {
+                            Ok(response) => {
+                                item.state = RequestState::Complete;
+                                item.response = Some(response);
+                            }
+                            Err(error) => {
+                                item.state = RequestState::Failed;
+                                item.error = Some(error);
+                            }
+                        }
+                    });
+                }
+            });
+        });
+
+        Ok(self.lock_batches("write")?.path(batch_id))
+    }
+
+    /// Read under `batches`.
+    fn read_batch(&self, from: &Path) -> Result<Option<Record>, Error> {
+        let mut batches = self.lock_batches("read")?;
+        if batches.is_listing(from) {
+            return Ok(Some(Record::parsed(batches.listing())));
+        }
+
+        let (batch_id, sub_path) = batches.parse(from).ok_or_else(|| {
+            Error::store(
+                "async_http_broker",
+                "read",
+                format!(
+                    "Invalid path '{}'. Expected: batches, batches/{{id}}, or batches/{{id}}/...",
+                    from
+                ),
+            )
+        })?;
+        let sub_components: Vec<&str> = sub_path.iter().map(String::as_str).collect();
+
+        if sub_components == ["wait"] {
+            drop(batches);
+            return self.blocking_read_batch(batch_id);
+        }
+
+        let Some(batch) = batches.touch(batch_id) else {
+            return Ok(None);
         };
-        Some((id, sub_path))
+
+        match sub_components.as_slice() {
+            [] => Ok(Some(Record::parsed(batch.summary(batch_id)?))),
+            ["responses", rest @ ..] => {
+                let responses = batch
+                    .items
+                    .iter()
+                    .map(|item| match &item.response {
+                        Some(response) => to_value(response).map_err(|e| {
+                            Error::encode(structfs_core_store::Format::JSON, e.to_string())
+                        }),
+                        None => Ok(Value::Null),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let value = Value::Array(responses);
+                if rest.is_empty() {
+                    return Ok(Some(Record::parsed(value)));
+                }
+                Ok(navigate_value(value, rest).map(Record::parsed))
+            }
+            ["items"] => {
+                let items = (0..batch.items.len())
+                    .map(|i| {
+                        Reference::with_type(
+                            format!("{}/{}/items/{}", BATCHES_PREFIX, batch_id, i),
+                            "batch-item",
+                        )
+                        .to_value()
+                    })
+                    .collect();
+                Ok(Some(Record::parsed(Value::Map(btree! {
+                    "items".into() => Value::Array(items),
+                }))))
+            }
+            ["items", index, rest @ ..] => {
+                let Some((i, item)) = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| batch.items.get(i).map(|item| (i, item)))
+                else {
+                    return Ok(None);
+                };
+                let value = match rest.first() {
+                    None => {
+                        return Ok(Some(Record::parsed(batch.item_summary(batch_id, i, item)?)));
+                    }
+                    Some(&"request") => to_value(&item.request).map_err(|e| {
+                        Error::encode(structfs_core_store::Format::JSON, e.to_string())
+                    })?,
+                    Some(&"response") => match &item.response {
+                        Some(response) => to_value(response).map_err(|e| {
+                            Error::encode(structfs_core_store::Format::JSON, e.to_string())
+                        })?,
+                        None => return Ok(None),
+                    },
+                    Some(&"error") => match (&item.response, &item.error) {
+                        (Some(response), _) if !response.is_success() => error_detail(response),
+                        (None, Some(error)) => Value::Map(btree! {
+                            "message".into() => Value::String(error.clone()),
+                        }),
+                        _ => return Ok(None),
+                    },
+                    Some(other) => {
+                        return Err(Error::store(
+                            "async_http_broker",
+                            "read",
+                            format!(
+                                "Unknown sub-path '{}'. Use 'request', 'response', or 'error'.",
+                                other
+                            ),
+                        ))
+                    }
+                };
+                if rest.len() > 1 {
+                    return Ok(navigate_value(value, &rest[1..]).map(Record::parsed));
+                }
+                Ok(Some(Record::parsed(value)))
+            }
+            [other, ..] => Err(Error::store(
+                "async_http_broker",
+                "read",
+                format!(
+                    "Unknown sub-path '{}'. Use 'wait', 'responses', or 'items'.",
+                    other
+                ),
+            )),
+        }
+    }
+
+    /// Blocking read of a batch - polls until no item is pending.
+    fn blocking_read_batch(&self, batch_id: RequestId) -> Result<Option<Record>, Error> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        loop {
+            let mut batches = self.lock_batches("read")?;
+            let Some(batch) = batches.touch(batch_id) else {
+                return Ok(None);
+            };
+            if batch.is_done() {
+                return Ok(Some(Record::parsed(batch.summary(batch_id)?)));
+            }
+            drop(batches);
+            thread::sleep(POLL_INTERVAL);
+        }
     }
 
     /// Blocking read of response - polls until complete or failed.
@@ -851,31 +1563,18 @@ impl AsyncHttpBrokerStore {
         const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
         loop {
-            let handles = self.handles.lock().map_err(|e| {
-                Error::store("async_http_broker", "read", format!("Lock error: {}", e))
-            })?;
+            let mut handles = self.lock_handles("read")?;
 
-            let handle = handles.get(&request_id).ok_or_else(|| {
-                Error::store(
-                    "async_http_broker",
-                    "read",
-                    format!("Request with ID {} not found", request_id),
-                )
-            })?;
+            let Some(handle) = handles.touch(request_id) else {
+                return Ok(None);
+            };
 
             if let Some(ref response) = handle.response {
                 let value = to_value(response)
                     .map_err(|e| Error::encode(structfs_core_store::Format::JSON, e.to_string()))?;
 
                 if !nav_path.is_empty() {
-                    return match navigate_value(value, nav_path) {
-                        Ok(v) => Ok(Some(Record::parsed(v))),
-                        Err(i) => Err(Error::store(
-                            "async_http_broker",
-                            "read",
-                            format!("Path not found at index {}: '{}'", i, nav_path[i]),
-                        )),
-                    };
+                    return Ok(navigate_value(value, nav_path).map(Record::parsed));
                 }
                 return Ok(Some(Record::parsed(value)));
             }
@@ -896,64 +1595,47 @@ impl AsyncHttpBrokerStore {
             thread::sleep(POLL_INTERVAL);
         }
     }
+}
 
-    /// Read meta paths for action descriptors and handle inspection.
-    fn read_meta(&self, path: &Path) -> Result<Option<Record>, Error> {
-        // meta/ root - list available meta operations
-        if path.len() == 1 {
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "queue".into() => Reference::with_type("meta/queue", "action").to_value(),
-                "outstanding".into() => Reference::with_type("meta/outstanding", "collection").to_value(),
-            }))));
+impl SelfDescribing for AsyncHttpBrokerStore {
+    fn describe(&self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            let Value::Map(mut root) = broker_meta_root() else {
+                unreachable!("the meta root is a map");
+            };
+            root.insert(
+                BATCH_PATH.into(),
+                Reference::with_type("meta/batch", "action").to_value(),
+            );
+            return Ok(Some(Value::Map(root)));
         }
-
-        // meta/queue - action descriptor for queuing requests
-        if path.len() == 2 && path[1] == "queue" {
-            return Ok(Some(Record::parsed(Self::queue_action_descriptor())));
+        if path.len() == 1 && path[0] == "queue" {
+            return Ok(Some(queue_action()));
+        }
+        if path.len() == 1 && path[0] == BATCH_PATH {
+            return Ok(Some(batch_action()));
         }
 
         // meta/outstanding - list handles with meta references
-        if path.len() == 2 && path[1] == OUTSTANDING_PREFIX {
-            let handles = self.handles.lock().map_err(|e| {
-                Error::store("async_http_broker", "read", format!("Lock error: {}", e))
-            })?;
-            let items: Vec<Value> = handles
-                .keys()
-                .map(|id| {
-                    Reference::with_type(format!("meta/outstanding/{}", id), "request-handle-meta")
-                        .to_value()
-                })
-                .collect();
-            return Ok(Some(Record::parsed(Value::Map(btree! {
+        if path.len() == 1 && path[0] == OUTSTANDING_PREFIX {
+            let items = self
+                .lock_handles("read")?
+                .references("meta/outstanding", "request-handle-meta");
+            return Ok(Some(Value::Map(btree! {
                 "items".into() => Value::Array(items),
-            }))));
+            })));
         }
 
-        // meta/outstanding/{id} - handle state and navigation
-        if path.len() >= 3 && path[1] == OUTSTANDING_PREFIX {
-            let id: RequestId = path[2].parse().map_err(|_| {
-                Error::store(
-                    "async_http_broker",
-                    "read",
-                    format!("Invalid handle ID: {}", path[2]),
-                )
-            })?;
-
-            let handles = self.handles.lock().map_err(|e| {
-                Error::store("async_http_broker", "read", format!("Lock error: {}", e))
-            })?;
-
-            let handle = handles.get(&id).ok_or_else(|| {
-                Error::store(
-                    "async_http_broker",
-                    "read",
-                    format!("Request with ID {} not found", id),
-                )
-            })?;
+        if path.len() >= 2 && path[0] == OUTSTANDING_PREFIX {
+            let id = meta_handle_id("async_http_broker", path)?;
+            let handles = self.lock_handles("read")?;
+            let Some(handle) = handles.get(id) else {
+                return Ok(None);
+            };
 
             // meta/outstanding/{id}/delete - delete action descriptor
-            if path.len() == 4 && path[3] == "delete" {
-                return Ok(Some(Record::parsed(Self::delete_action_descriptor(id))));
+            if path.len() == 3 && path[2] == "delete" {
+                return Ok(Some(delete_action(id)));
             }
 
             // meta/outstanding/{id} - handle state + navigation
@@ -965,75 +1647,20 @@ impl AsyncHttpBrokerStore {
                 "pending"
             };
 
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "state".into() => Value::Map(btree! {
-                    "status".into() => Value::String(state.to_string()),
-                    "method".into() => Value::String(format!("{:?}", handle.request.method)),
-                    "url".into() => Value::String(handle.request.path.clone()),
-                }),
+            return Ok(Some(Value::Map(btree! {
+                "state".into() => handle_state(&handles, id, state, &handle.request),
                 "request".into() => Reference::with_type(format!("outstanding/{}/request", id), "http-request").to_value(),
                 "response".into() => Reference::with_type(format!("outstanding/{}/response", id), "http-response").to_value(),
                 "wait".into() => Reference::with_type(format!("outstanding/{}/response/wait", id), "accessor").to_value(),
                 "delete".into() => Reference::with_type(format!("meta/outstanding/{}/delete", id), "action").to_value(),
-            }))));
+            })));
         }
 
-        Err(Error::store(
-            "async_http_broker",
-            "read",
-            format!("Unknown meta path: {}", path),
-        ))
+        Err(unknown_meta_path("async_http_broker", path))
     }
 
-    /// Generate action descriptor for queue operation.
-    fn queue_action_descriptor() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! { "name".into() => Value::String("action".into()) }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "method".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(true),
-                    "values".into() => Value::Array(vec![
-                        Value::String("GET".into()),
-                        Value::String("POST".into()),
-                        Value::String("PUT".into()),
-                        Value::String("PATCH".into()),
-                        Value::String("DELETE".into()),
-                        Value::String("HEAD".into()),
-                        Value::String("OPTIONS".into()),
-                    ]),
-                }),
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(true),
-                }),
-                "headers".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("map".into()) }),
-                    "required".into() => Value::Bool(false),
-                }),
-                "body".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! { "name".into() => Value::String("string".into()) }),
-                    "required".into() => Value::Bool(false),
-                }),
-            }),
-            "returns".into() => Value::Map(btree! {
-                "type".into() => Value::Map(btree! { "name".into() => Value::String("request-handle".into()) }),
-                "collection".into() => Reference::new("outstanding").to_value(),
-            }),
-        })
-    }
-
-    /// Generate action descriptor for delete operation.
-    fn delete_action_descriptor(id: RequestId) -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! { "name".into() => Value::String("action".into()) }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new(format!("outstanding/{}", id)).to_value(),
-            "accepts".into() => Value::String("null".into()),
-            "returns".into() => Value::String("void".into()),
-        })
+    fn docs(&self) -> Option<Value> {
+        Some(async_broker_docs())
     }
 }
 
@@ -1044,38 +1671,29 @@ impl Reader for AsyncHttpBrokerStore {
             return Ok(Some(Record::parsed(Value::Map(btree! {
                 "outstanding".into() => Reference::with_type("outstanding", "collection").to_value(),
                 "queue".into() => Reference::with_type("meta/queue", "action").to_value(),
+                "batches".into() => Reference::with_type("batches", "collection").to_value(),
+                "batch".into() => Reference::with_type("meta/batch", "action").to_value(),
                 "meta".into() => Reference::with_type("meta", "meta").to_value(),
                 "docs".into() => Reference::with_type("docs", "docs").to_value(),
             }))));
         }
 
-        // Handle docs: read /docs or /docs/... -> documentation
-        if from[0] == DOCS_PATH {
-            return Ok(Some(Record::parsed(async_broker_docs())));
+        // Handle docs and meta paths
+        if let Some(result) = read_lens(self, from) {
+            return result;
         }
 
-        // Handle meta paths
-        if from[0] == META_PATH {
-            return self.read_meta(from);
+        if from[0] == BATCHES_PREFIX {
+            return self.read_batch(from);
         }
 
         // Handle listing: read /outstanding -> {items: [refs...]}
-        if from.len() == 1 && from[0] == OUTSTANDING_PREFIX {
-            let handles = self.handles.lock().map_err(|e| {
-                Error::store("async_http_broker", "read", format!("Lock error: {}", e))
-            })?;
-            let items: Vec<Value> = handles
-                .keys()
-                .map(|id| {
-                    Reference::with_type(format!("outstanding/{}", id), "request-handle").to_value()
-                })
-                .collect();
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "items".into() => Value::Array(items),
-            }))));
+        let mut handles = self.lock_handles("read")?;
+        if handles.is_listing(from) {
+            return Ok(Some(Record::parsed(handles.listing())));
         }
 
-        let (request_id, sub_path) = Self::parse_handle_path(from).ok_or_else(|| {
+        let (request_id, sub_path) = handles.parse(from).ok_or_else(|| {
             Error::store(
                 "async_http_broker",
                 "read",
@@ -1086,32 +1704,21 @@ impl Reader for AsyncHttpBrokerStore {
             )
         })?;
 
-        // Parse sub-path components for deep navigation
-        let sub_components: Vec<&str> = sub_path
-            .as_deref()
-            .map(|s| s.split('/').collect())
-            .unwrap_or_default();
+        // Sub-path components for deep navigation
+        let sub_components: Vec<&str> = sub_path.iter().map(String::as_str).collect();
 
         // Handle blocking wait: /outstanding/{id}/response/wait[/...]
         if sub_components.len() >= 2
             && sub_components[0] == "response"
             && sub_components[1] == "wait"
         {
+            drop(handles);
             return self.blocking_read_response_with_path(request_id, &sub_components[2..]);
         }
 
-        let handles = self
-            .handles
-            .lock()
-            .map_err(|e| Error::store("async_http_broker", "read", format!("Lock error: {}", e)))?;
-
-        let handle = handles.get(&request_id).ok_or_else(|| {
-            Error::store(
-                "async_http_broker",
-                "read",
-                format!("Request with ID {} not found", request_id),
-            )
-        })?;
+        let Some(handle) = handles.touch(request_id) else {
+            return Ok(None);
+        };
 
         // Handle /outstanding/{id}/request[/...] - view queued request
         if sub_components.first() == Some(&"request") {
@@ -1120,14 +1727,7 @@ impl Reader for AsyncHttpBrokerStore {
 
             if sub_components.len() > 1 {
                 let nav_path = &sub_components[1..];
-                return match navigate_value(value, nav_path) {
-                    Ok(v) => Ok(Some(Record::parsed(v))),
-                    Err(i) => Err(Error::store(
-                        "async_http_broker",
-                        "read",
-                        format!("Path not found at index {}: '{}'", i, nav_path[i]),
-                    )),
-                };
+                return Ok(navigate_value(value, nav_path).map(Record::parsed));
             }
             return Ok(Some(Record::parsed(value)));
         }
@@ -1140,14 +1740,7 @@ impl Reader for AsyncHttpBrokerStore {
 
                 if sub_components.len() > 1 {
                     let nav_path = &sub_components[1..];
-                    return match navigate_value(value, nav_path) {
-                        Ok(v) => Ok(Some(Record::parsed(v))),
-                        Err(i) => Err(Error::store(
-                            "async_http_broker",
-                            "read",
-                            format!("Path not found at index {}: '{}'", i, nav_path[i]),
-                        )),
-                    };
+                    return Ok(navigate_value(value, nav_path).map(Record::parsed));
                 }
                 return Ok(Some(Record::parsed(value)));
             } else if handle.status.is_failed() {
@@ -1164,6 +1757,26 @@ impl Reader for AsyncHttpBrokerStore {
             }
         }
 
+        // Handle /outstanding/{id}/error[/...] - structured error, None if pending or successful
+        if sub_components.first() == Some(&"error") {
+            let value = match &handle.response {
+                Some(response) if response.is_success() => return Ok(None),
+                Some(response) => error_detail(response),
+                None if handle.status.is_failed() => Value::Map(btree! {
+                    "message".into() => Value::String(
+                        handle.status.error.clone().unwrap_or_else(|| "unknown error".into()),
+                    ),
+                }),
+                None => return Ok(None),
+            };
+
+            if sub_components.len() > 1 {
+                let nav_path = &sub_components[1..];
+                return Ok(navigate_value(value, nav_path).map(Record::parsed));
+            }
+            return Ok(Some(Record::parsed(value)));
+        }
+
         // Handle /outstanding/{id} - return status
         if sub_components.is_empty() {
             let value = to_value(&handle.status)
@@ -1176,7 +1789,7 @@ impl Reader for AsyncHttpBrokerStore {
             "async_http_broker",
             "read",
             format!(
-                "Unknown sub-path '{}'. Use 'request', 'response', or 'response/wait'.",
+                "Unknown sub-path '{}'. Use 'request', 'response', 'response/wait', or 'error'.",
                 sub_components.first().unwrap_or(&""),
             ),
         ))
@@ -1187,13 +1800,35 @@ impl Writer for AsyncHttpBrokerStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         let value = data.into_value(&NoCodec)?;
 
+        if to.len() == 1 && to[0] == BATCH_PATH {
+            return self.queue_batch(value);
+        }
+
+        // Delete batch: write null to /batches/{id}
+        let parsed = self.lock_batches("write")?.parse(to);
+        if let Some((batch_id, sub_path)) = parsed {
+            if !sub_path.is_empty() || value != Value::Null {
+                return Err(Error::store(
+                    "async_http_broker",
+                    "write",
+                    "Write null to batches/{id} to delete a batch.",
+                ));
+            }
+            self.lock_batches("write")?.remove(batch_id);
+            return Ok(to.clone());
+        }
+
         // Delete handle: write null to /outstanding/{id}
-        if let Some((request_id, None)) = Self::parse_handle_path(to) {
+        let parsed = self.lock_handles("write")?.parse(to);
+        if let Some((request_id, sub_path)) = parsed {
+            if !sub_path.is_empty() {
+                return Err(invalid_write("async_http_broker", to));
+            }
             if value == Value::Null {
-                let mut handles = self.handles.lock().map_err(|e| {
-                    Error::store("async_http_broker", "write", format!("Lock error: {}", e))
-                })?;
-                handles.remove(&request_id);
+                self.lock_handles("write")?.remove(request_id);
+                if let Some(journal) = &self.journal {
+                    journal.remove(request_id)?;
+                }
                 return Ok(to.clone());
             }
             return Err(Error::store(
@@ -1212,56 +1847,35 @@ impl Writer for AsyncHttpBrokerStore {
                 )
             })?;
 
-            let request_id = self.next_request_id;
-            self.next_request_id += 1;
+            // The request runs on another thread, so carry the caller's
+            // context over to it
+            let ctx = OpContext::current();
+            ctx.check("async_http_broker")?;
+
+            self.prune_journal()?;
 
             // Create initial pending status with the request stored
-            let handle = AsyncRequestHandle {
+            let mut handles = self.lock_handles("write")?;
+            let request_id = handles.insert_with(|id| AsyncRequestHandle {
                 request: request.clone(),
-                status: RequestStatus::pending(request_id.to_string()),
+                status: RequestStatus::pending(id.to_string()),
                 response: None,
-            };
-
-            {
-                let mut handles = self.handles.lock().map_err(|e| {
-                    Error::store("async_http_broker", "write", format!("Lock error: {}", e))
-                })?;
-                handles.insert(request_id, handle);
+            });
+            if let Some(journal) = &self.journal {
+                let entry = JournalEntry::pending(request.clone());
+                if let Err(e) = journal.save(request_id, &entry, handles.next_id()) {
+                    handles.remove(request_id);
+                    return Err(e);
+                }
             }
+            drop(handles);
 
-            // Spawn background thread to execute the request
-            let handles = Arc::clone(&self.handles);
-            let timeout = self.timeout;
-            thread::spawn(move || {
-                let result = Self::execute_request(request, timeout);
+            self.spawn_request(request_id, request, ctx);
 
-                if let Ok(mut handles) = handles.lock() {
-                    if let Some(handle) = handles.get_mut(&request_id) {
-                        match result {
-                            Ok(response) => {
-                                handle.status = RequestStatus::complete(request_id.to_string());
-                                handle.response = Some(response);
-                            }
-                            Err(error) => {
-                                handle.status =
-                                    RequestStatus::failed(request_id.to_string(), error);
-                            }
-                        }
-                    }
-                }
-            });
-
-            return Ok(path!(OUTSTANDING_PREFIX).join(&path!(&format!("{}", request_id))));
+            return Ok(self.lock_handles("write")?.path(request_id));
         }
 
-        Err(Error::store(
-            "async_http_broker",
-            "write",
-            format!(
-                "Invalid write path '{}'. Write to root to queue a request, or write null to outstanding/{{id}} to delete.",
-                to
-            ),
-        ))
+        Err(invalid_write("async_http_broker", to))
     }
 }
 
@@ -1269,50 +1883,41 @@ impl Writer for AsyncHttpBrokerStore {
 mod tests {
     use super::*;
     use crate::executor::mock::MockExecutor;
+    use std::collections::HashMap;
+    use structfs_core_store::{conformance, path};
 
     // ==================== HttpBrokerStore tests ====================
 
     #[test]
     fn test_parse_handle_path() {
+        let broker = HttpBrokerStore::with_executor(MockExecutor::new());
         // Just "outstanding" returns None (listing)
-        assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("outstanding")),
-            None
-        );
+        assert_eq!(broker.handles.parse(&path!("outstanding")), None);
         // Basic handle path
         assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("outstanding/0")),
-            Some((0, None))
+            broker.handles.parse(&path!("outstanding/0")),
+            Some((0, path!("")))
         );
         assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("outstanding/123")),
-            Some((123, None))
+            broker.handles.parse(&path!("outstanding/123")),
+            Some((123, path!("")))
         );
         // With sub-path
         assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("outstanding/0/request")),
-            Some((0, Some("request".to_string())))
+            broker.handles.parse(&path!("outstanding/0/request")),
+            Some((0, path!("request")))
         );
         // With deep sub-path
         assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!(
-                "outstanding/0/response/status"
-            )),
-            Some((0, Some("response/status".to_string())))
+            broker
+                .handles
+                .parse(&path!("outstanding/0/response/status")),
+            Some((0, path!("response/status")))
         );
         // Invalid paths
-        assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("other/123")),
-            None
-        );
-        assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("outstanding/abc")),
-            None
-        );
-        assert_eq!(
-            HttpBrokerStore::<ReqwestExecutor>::parse_handle_path(&path!("")),
-            None
-        );
+        assert_eq!(broker.handles.parse(&path!("other/123")), None);
+        assert_eq!(broker.handles.parse(&path!("outstanding/abc")), None);
+        assert_eq!(broker.handles.parse(&path!("")), None);
     }
 
     #[test]
@@ -1334,6 +1939,34 @@ mod tests {
         assert_eq!(broker.handle_count(), 1);
     }
 
+    #[test]
+    fn test_broker_handles_expire() {
+        let mut broker = HttpBrokerStore::with_executor(MockExecutor::new())
+            .with_handle_ttl(Duration::from_millis(20));
+        let request = to_value(&HttpRequest::get("https://example.com")).unwrap();
+        let handle = broker.write(&path!(""), Record::parsed(request)).unwrap();
+
+        let meta = broker
+            .read(&path!("meta/outstanding/0"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        let Value::Map(meta) = meta else {
+            panic!("expected a map");
+        };
+        let Value::Map(state) = &meta["state"] else {
+            panic!("expected a state map");
+        };
+        assert_eq!(state["id"], Value::Integer(0));
+        assert_eq!(state["status"], Value::String("pending".into()));
+        assert!(matches!(state["expires_in_ms"], Value::Integer(_)));
+
+        thread::sleep(Duration::from_millis(40));
+        conformance::assert_missing(&mut broker, &handle);
+        assert_eq!(broker.handle_count(), 0);
+    }
+
     #[test]
     fn test_broker_with_mock_executor() {
         let mock = MockExecutor::new().with_response(
@@ -1362,6 +1995,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_broker_expired_context_does_not_execute() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::json!({})));
+        let mut broker = HttpBrokerStore::with_executor(mock.clone());
+
+        let request = to_value(&HttpRequest::get("/slow")).unwrap();
+        let handle = broker.write(&path!(""), Record::parsed(request)).unwrap();
+
+        let expired = OpContext::new().with_deadline(std::time::Instant::now());
+        let err = broker.read_with_context(&expired, &handle).unwrap_err();
+        assert!(matches!(err, Error::DeadlineExceeded { .. }));
+        assert!(mock.recorded_requests().is_empty());
+
+        // The failure is not cached, so a later read still executes
+        let ctx = OpContext::new()
+            .with_timeout(Duration::from_secs(60))
+            .with_trace_id("trace-1");
+        assert!(broker.read_with_context(&ctx, &handle).unwrap().is_some());
+        let recorded = mock.recorded_requests();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(
+            recorded[0].headers.get(crate::executor::TRACE_ID_HEADER),
+            Some(&"trace-1".to_string())
+        );
+    }
+
     #[test]
     fn test_broker_multiple_requests() {
         let mock = MockExecutor::new()
@@ -1399,9 +2059,28 @@ mod tests {
         let v2: HttpResponse = from_value(r2.into_value(&NoCodec).unwrap()).unwrap();
         assert_eq!(v2.body, serde_json::json!({"id": "b"}));
 
-        let r1 = broker.read(&h1).unwrap().unwrap();
-        let v1: HttpResponse = from_value(r1.into_value(&NoCodec).unwrap()).unwrap();
-        assert_eq!(v1.body, serde_json::json!({"id": "a"}));
+        let r1 = broker.read(&h1).unwrap().unwrap();
+        let v1: HttpResponse = from_value(r1.into_value(&NoCodec).unwrap()).unwrap();
+        assert_eq!(v1.body, serde_json::json!({"id": "a"}));
+    }
+
+    #[test]
+    fn test_broker_conforms_to_store_contract() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::json!({"a": 1})));
+        let mut broker = HttpBrokerStore::with_executor(mock);
+
+        conformance::assert_missing(&mut broker, &path!("outstanding/7"));
+        conformance::assert_missing(&mut broker, &path!("meta/outstanding/7"));
+
+        let request = to_value(&HttpRequest::get("/a")).unwrap();
+        let handle = broker.write(&path!(""), Record::parsed(request)).unwrap();
+        conformance::assert_found(&mut broker, &handle);
+        conformance::assert_missing(&mut broker, &handle.join(&path!("response/body/b")));
+        conformance::assert_missing(&mut broker, &handle.join(&path!("error")));
+
+        broker.write(&handle, Record::parsed(Value::Null)).unwrap();
+        conformance::assert_missing(&mut broker, &handle);
     }
 
     #[test]
@@ -1410,8 +2089,7 @@ mod tests {
         let mut broker = HttpBrokerStore::with_executor(mock);
 
         let result = broker.read(&path!("outstanding/999"));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("not found"));
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
@@ -1444,6 +2122,72 @@ mod tests {
             .contains("Connection refused"));
     }
 
+    #[test]
+    fn test_broker_error_path() {
+        let mock = MockExecutor::new()
+            .with_response(
+                "https://api.example.com/bad",
+                MockExecutor::error_response(400, "Bad Request"),
+            )
+            .with_response(
+                "https://api.example.com/ok",
+                MockExecutor::success_response(serde_json::json!({"ok": true})),
+            );
+        let mut broker = HttpBrokerStore::with_executor(mock);
+
+        let bad = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get("https://api.example.com/bad")).unwrap()),
+            )
+            .unwrap();
+        let ok = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get("https://api.example.com/ok")).unwrap()),
+            )
+            .unwrap();
+
+        // Reading the error executes the request
+        let status = broker
+            .read(&bad.join(&path!("error/status")))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(status, Value::Integer(400));
+        let code = broker
+            .read(&bad.join(&path!("error/body/error")))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(code, Value::String("Bad Request".into()));
+
+        assert!(broker.read(&ok.join(&path!("error"))).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_broker_error_path_transport_failure() {
+        let mock = MockExecutor::new().fail_with("Connection refused");
+        let mut broker = HttpBrokerStore::with_executor(mock);
+
+        let handle = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get("https://example.com")).unwrap()),
+            )
+            .unwrap();
+
+        let message = broker
+            .read(&handle.join(&path!("error/message")))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(message, Value::String("Connection refused".into()));
+    }
+
     #[test]
     fn test_broker_invalid_request_data() {
         let mock = MockExecutor::new();
@@ -1628,10 +2372,8 @@ mod tests {
         // Handle should be gone
         assert!(!broker.has_handle(0));
 
-        // Reading should now fail
-        let result = broker.read(&handle);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("not found"));
+        // The handle no longer exists
+        assert!(broker.read(&handle).unwrap().is_none());
     }
 
     #[test]
@@ -1795,15 +2537,9 @@ mod tests {
             )
             .unwrap();
 
-        // Navigate to non-existent path returns error with failing component
+        // Nothing exists at a non-existent path inside the response
         let result = broker.read(&handle.join(&path!("response/body/nonexistent/path")));
-        assert!(result.is_err());
-        let err = result.unwrap_err().to_string();
-        assert!(
-            err.contains("nonexistent"),
-            "Error should mention failing component: {}",
-            err
-        );
+        assert!(result.unwrap().is_none());
     }
 
     // ==================== HttpClientStore tests ====================
@@ -1824,6 +2560,48 @@ mod tests {
         assert_eq!(value, expected);
     }
 
+    #[test]
+    fn test_client_store_honors_context() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::json!(1)));
+        let mut client =
+            HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+
+        let token = structfs_core_store::CancellationToken::new();
+        token.cancel();
+        let cancelled = OpContext::new().with_cancellation(token);
+        let err = client
+            .read_with_context(&cancelled, &path!("users/1"))
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled { .. }));
+        assert!(mock.recorded_requests().is_empty());
+
+        let traced = OpContext::new().with_trace_id("abc");
+        client
+            .read_with_context(&traced, &path!("users/1"))
+            .unwrap();
+        assert_eq!(
+            mock.recorded_requests()[0]
+                .headers
+                .get(crate::executor::TRACE_ID_HEADER),
+            Some(&"abc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_client_store_conforms_to_store_contract() {
+        let mock = MockExecutor::new()
+            .with_response(
+                "https://api.example.com/users/1",
+                MockExecutor::success_response(serde_json::json!({"id": 1})),
+            )
+            .with_default_response(MockExecutor::not_found());
+        let mut client = HttpClientStore::with_executor("https://api.example.com", mock).unwrap();
+
+        conformance::assert_found(&mut client, &path!("users/1"));
+        conformance::assert_missing(&mut client, &path!("users/2"));
+    }
+
     #[test]
     fn test_client_store_404_returns_none() {
         let mock = MockExecutor::new()
@@ -1835,6 +2613,18 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_client_store_410_returns_none() {
+        let mock = MockExecutor::new().with_response(
+            "https://api.example.com/gone",
+            MockExecutor::error_response(410, "Gone"),
+        );
+
+        let mut client = HttpClientStore::with_executor("https://api.example.com", mock).unwrap();
+
+        assert!(client.read(&path!("gone")).unwrap().is_none());
+    }
+
     #[test]
     fn test_client_store_server_error() {
         let mock = MockExecutor::new().with_response(
@@ -1849,6 +2639,50 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("500"));
     }
 
+    #[test]
+    fn test_client_store_error_detail() {
+        let mock = MockExecutor::new().with_default_response(HttpResponse {
+            status: 409,
+            status_text: "Conflict".to_string(),
+            headers: HashMap::new(),
+            body: serde_json::json!({"code": "BucketAlreadyExists"}),
+            body_text: Some(r#"{"code":"BucketAlreadyExists"}"#.to_string()),
+        });
+
+        let mut client = HttpClientStore::with_executor("https://api.example.com", mock).unwrap();
+
+        let err = client
+            .write(&path!("buckets"), Record::parsed(Value::Null))
+            .unwrap_err();
+        let detail = err.detail().expect("structured detail");
+        let Value::Map(detail) = detail else {
+            panic!("Expected map detail");
+        };
+        assert_eq!(detail["status"], Value::Integer(409));
+        assert_eq!(detail["status_text"], Value::String("Conflict".into()));
+        assert_eq!(
+            detail["body"],
+            Value::Map(btree! {
+                "code".into() => Value::String("BucketAlreadyExists".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_detail_text_body() {
+        let response = HttpResponse {
+            status: 502,
+            status_text: "Bad Gateway".to_string(),
+            headers: HashMap::new(),
+            body: serde_json::Value::Null,
+            body_text: Some("upstream down".to_string()),
+        };
+        let Value::Map(detail) = error_detail(&response) else {
+            panic!("Expected map detail");
+        };
+        assert_eq!(detail["body"], Value::String("upstream down".into()));
+    }
+
     #[test]
     fn test_client_store_write_post() {
         let mock = MockExecutor::new().with_default_response(MockExecutor::success_response(
@@ -1885,6 +2719,96 @@ mod tests {
         assert_eq!(requests[0].method, crate::types::Method::DELETE);
     }
 
+    #[test]
+    fn test_client_store_method_suffix() {
+        let mut response = MockExecutor::success_response(serde_json::json!({"ok": true}));
+        response.headers.insert("allow".into(), "GET, PATCH".into());
+        let mock = MockExecutor::new().with_default_response(response);
+
+        let mut client =
+            HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+
+        let data = serde_json::json!({"name": "Al"});
+        let written = client
+            .write(
+                &path!("users/1/_method/PATCH"),
+                Record::parsed(to_value(&data).unwrap()),
+            )
+            .unwrap();
+        assert_eq!(written, path!("users/1"));
+        client
+            .write(
+                &path!("users/1/_method/delete"),
+                Record::parsed(Value::Null),
+            )
+            .unwrap();
+
+        let head = client
+            .read(&path!("users/_method/options"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(head.get(&path!("status")), Some(&Value::Integer(200)));
+        assert_eq!(
+            head.get(&path!("headers/allow")),
+            Some(&Value::String("GET, PATCH".into()))
+        );
+
+        let requests = mock.recorded_requests();
+        assert_eq!(requests[0].method, crate::types::Method::PATCH);
+        assert_eq!(requests[0].path, "https://api.example.com/users/1");
+        assert_eq!(requests[0].body, Some(data));
+        assert_eq!(requests[1].method, crate::types::Method::DELETE);
+        assert_eq!(requests[1].body, None);
+        assert_eq!(requests[2].method, crate::types::Method::OPTIONS);
+        assert_eq!(requests[2].path, "https://api.example.com/users");
+    }
+
+    #[test]
+    fn test_client_store_delete() {
+        let mock = MockExecutor::new()
+            .with_response(
+                "https://api.example.com/users/2",
+                MockExecutor::error_response(404, "Not Found"),
+            )
+            .with_response(
+                "https://api.example.com/users/3",
+                MockExecutor::error_response(403, "Forbidden"),
+            )
+            .with_default_response(MockExecutor::success_response(serde_json::Value::Null));
+        let mut client =
+            HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+
+        let store: &mut dyn structfs_core_store::Store = &mut client;
+        store.delete(&path!("users/1")).unwrap();
+        // Already gone
+        store.delete(&path!("users/2")).unwrap();
+        assert!(store.delete(&path!("users/3")).is_err());
+
+        let requests = mock.recorded_requests();
+        assert_eq!(requests[0].method, crate::types::Method::DELETE);
+        assert_eq!(requests[0].path, "https://api.example.com/users/1");
+        assert_eq!(requests[0].body, None);
+    }
+
+    #[test]
+    fn test_client_store_method_suffix_errors() {
+        let mock = MockExecutor::new()
+            .with_default_response(MockExecutor::success_response(serde_json::Value::Null));
+        let mut client =
+            HttpClientStore::with_executor("https://api.example.com", mock.clone()).unwrap();
+
+        // Unknown methods, trailing components and the wrong operation
+        assert!(client.read(&path!("users/_method/fetch")).is_err());
+        assert!(client.read(&path!("users/_method/get/extra")).is_err());
+        assert!(client.read(&path!("users/1/_method/delete")).is_err());
+        assert!(client
+            .write(&path!("users/_method/head"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(mock.recorded_requests().is_empty());
+    }
+
     #[test]
     fn test_client_store_default_headers() {
         let mock = MockExecutor::new()
@@ -1968,30 +2892,20 @@ mod tests {
 
     #[test]
     fn test_async_broker_parse_handle_path() {
+        let broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
+        let handles = broker.handles.lock().unwrap();
+        assert_eq!(handles.parse(&path!("outstanding/0")), Some((0, path!(""))));
         assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("outstanding/0")),
-            Some((0, None))
-        );
-        assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("outstanding/123/response")),
-            Some((123, Some("response".to_string())))
-        );
-        assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("outstanding")),
-            None
-        );
-        assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("other/123")),
-            None
+            handles.parse(&path!("outstanding/123/response")),
+            Some((123, path!("response")))
         );
-        assert_eq!(AsyncHttpBrokerStore::parse_handle_path(&path!("")), None);
+        assert_eq!(handles.parse(&path!("outstanding")), None);
+        assert_eq!(handles.parse(&path!("other/123")), None);
+        assert_eq!(handles.parse(&path!("")), None);
+        assert_eq!(handles.parse(&path!("outstanding/abc")), None);
         assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("outstanding/abc")),
-            None
-        );
-        assert_eq!(
-            AsyncHttpBrokerStore::parse_handle_path(&path!("outstanding/0/foo/bar")),
-            Some((0, Some("foo/bar".to_string())))
+            handles.parse(&path!("outstanding/0/foo/bar")),
+            Some((0, path!("foo/bar")))
         );
     }
 
@@ -2024,13 +2938,20 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("Invalid path"));
     }
 
+    #[test]
+    fn test_async_broker_conforms_to_store_contract() {
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
+        conformance::assert_missing(&mut broker, &path!("outstanding/7"));
+        conformance::assert_missing(&mut broker, &path!("outstanding/7/response/wait"));
+        conformance::assert_missing(&mut broker, &path!("meta/outstanding/7"));
+    }
+
     #[test]
     fn test_async_broker_request_not_found() {
         let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
 
         let result = broker.read(&path!("outstanding/999"));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("not found"));
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
@@ -2065,6 +2986,26 @@ mod tests {
         let _ = result;
     }
 
+    #[test]
+    fn test_async_broker_error_path() {
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
+
+        // A URL without a scheme fails in the client without touching the network
+        let request_value = to_value(&HttpRequest::get("not a url")).unwrap();
+        let handle = broker
+            .write(&path!(""), Record::parsed(request_value))
+            .unwrap();
+
+        assert!(broker.read(&handle.join(&path!("response/wait"))).is_err());
+        let message = broker
+            .read(&handle.join(&path!("error/message")))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert!(matches!(message, Value::String(_)));
+    }
+
     #[test]
     fn test_async_broker_invalid_request_data() {
         let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
@@ -2082,6 +3023,51 @@ mod tests {
         assert_eq!(broker.timeout, Duration::from_secs(5));
     }
 
+    #[test]
+    fn test_with_options_constructors() {
+        let options = HttpOptions {
+            timeout_ms: Some(2_000),
+            http2_prior_knowledge: true,
+            pool_max_idle_per_host: Some(8),
+            pool_idle_timeout_ms: Some(30_000),
+            tcp_keepalive_ms: Some(15_000),
+            tcp_nodelay: Some(true),
+            auth: None,
+            resolver: None,
+            circuit_breaker: None,
+            openapi: None,
+            tls: None,
+            network: None,
+            limits: None,
+        };
+
+        assert!(HttpBrokerStore::with_options(&options).is_ok());
+        assert!(HttpClientStore::with_options("https://api.example.com", &options).is_ok());
+        assert!(HttpClientStore::with_options("not a url", &options).is_err());
+
+        let broker = AsyncHttpBrokerStore::with_options(&options).unwrap();
+        assert_eq!(broker.options, options);
+        assert_eq!(broker.timeout, DEFAULT_TIMEOUT);
+        assert!(broker.signer.is_none());
+    }
+
+    #[test]
+    fn test_with_options_auth() {
+        let options = HttpOptions {
+            auth: Some(crate::HttpAuth::Hmac {
+                secret: "secret".to_string(),
+                key_id: None,
+            }),
+            ..Default::default()
+        };
+
+        assert!(HttpBrokerStore::with_options(&options).is_ok());
+        assert!(HttpClientStore::with_options("https://api.example.com", &options).is_ok());
+
+        let broker = AsyncHttpBrokerStore::with_options(&options).unwrap();
+        assert!(broker.signer.is_some());
+    }
+
     #[test]
     fn test_async_broker_list_outstanding() {
         let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
@@ -2187,6 +3173,266 @@ mod tests {
         }
     }
 
+    /// Serve `count` requests on localhost, answering each with its path as
+    /// the JSON body.
+    fn serve_paths(count: usize) -> u16 {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split(' ').nth(1).unwrap_or_default();
+                let body = format!("\"{}\"", path);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_async_broker_batch() {
+        let port = serve_paths(2);
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout()
+            .unwrap()
+            .with_batch_concurrency(4);
+
+        let requests = vec![
+            to_value(&HttpRequest::get(format!("http://127.0.0.1:{}/a", port))).unwrap(),
+            to_value(&HttpRequest::get("not a url")).unwrap(),
+            to_value(&HttpRequest::get(format!("http://127.0.0.1:{}/b", port))).unwrap(),
+        ];
+        let batch = broker
+            .write(
+                &path!("batch"),
+                Record::parsed(Value::Map(btree! {
+                    "requests".into() => Value::Array(requests),
+                    "concurrency".into() => Value::Integer(2),
+                })),
+            )
+            .unwrap();
+        assert_eq!(batch, path!("batches/0"));
+
+        let read = |broker: &mut AsyncHttpBrokerStore, path: Path| {
+            broker
+                .read(&path)
+                .unwrap()
+                .map(|r| r.into_value(&NoCodec).unwrap())
+        };
+
+        let Some(Value::Map(summary)) = read(&mut broker, batch.join(&path!("wait"))) else {
+            panic!("Expected a batch summary");
+        };
+        assert_eq!(summary["state"], Value::String("complete".into()));
+        assert_eq!(summary["total"], Value::Integer(3));
+        assert_eq!(summary["complete"], Value::Integer(2));
+        assert_eq!(summary["failed"], Value::Integer(1));
+        assert_eq!(summary["concurrency"], Value::Integer(2));
+
+        let Some(Value::Array(responses)) = read(&mut broker, batch.join(&path!("responses")))
+        else {
+            panic!("Expected responses");
+        };
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[1], Value::Null);
+        assert_eq!(
+            read(&mut broker, batch.join(&path!("items/2/response/body"))),
+            Some(Value::String("/b".into()))
+        );
+        assert!(matches!(
+            read(&mut broker, batch.join(&path!("items/1/error/message"))),
+            Some(Value::String(_))
+        ));
+        assert_eq!(read(&mut broker, batch.join(&path!("items/0/error"))), None);
+        assert_eq!(read(&mut broker, batch.join(&path!("items/9"))), None);
+
+        broker.write(&batch, Record::parsed(Value::Null)).unwrap();
+        assert_eq!(read(&mut broker, batch.clone()), None);
+        conformance::assert_missing(&mut broker, &batch.join(&path!("wait")));
+    }
+
+    #[test]
+    fn test_async_broker_reuses_connections() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // Answers requests on each connection until the client closes it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = Arc::clone(&connections);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                        while line.len() > 2 {
+                            line.clear();
+                            reader.read_line(&mut line).unwrap();
+                        }
+                        line.clear();
+                        stream
+                            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
+        for _ in 0..2 {
+            let handle = broker
+                .write(
+                    &path!(""),
+                    Record::parsed(
+                        to_value(&HttpRequest::get(format!("http://127.0.0.1:{}/", port))).unwrap(),
+                    ),
+                )
+                .unwrap();
+            assert!(broker
+                .read(&handle.join(&path!("response/wait")))
+                .unwrap()
+                .is_some());
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    /// A store whose contents outlive the brokers opened on it.
+    #[derive(Clone, Default)]
+    struct SharedStore(Arc<Mutex<HashMap<Path, Record>>>);
+
+    impl Reader for SharedStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            Ok(self.0.lock().unwrap().get(from).cloned())
+        }
+    }
+
+    impl Writer for SharedStore {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            let mut map = self.0.lock().unwrap();
+            match data.into_value(&NoCodec)? {
+                Value::Null => map.remove(to),
+                value => map.insert(to.clone(), Record::parsed(value)),
+            };
+            Ok(to.clone())
+        }
+    }
+
+    #[test]
+    fn test_async_broker_journal_survives_restart() {
+        let port = serve_paths(2);
+        let url = |path: &str| format!("http://127.0.0.1:{}/{}", port, path);
+        let shared = SharedStore::default();
+        let retention = Duration::from_secs(60);
+
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout()
+            .unwrap()
+            .with_journal(Box::new(shared.clone()), retention)
+            .unwrap();
+        let done = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get(url("a"))).unwrap()),
+            )
+            .unwrap();
+        broker.read(&done.join(&path!("response/wait"))).unwrap();
+        let deleted = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get("not a url")).unwrap()),
+            )
+            .unwrap();
+        broker.write(&deleted, Record::parsed(Value::Null)).unwrap();
+        drop(broker);
+
+        // A request queued before a crash, never run
+        let journal = Journal::open(Box::new(shared.clone()), retention).unwrap();
+        journal
+            .save(5, &JournalEntry::pending(HttpRequest::get(url("c"))), 6)
+            .unwrap();
+        drop(journal);
+
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout()
+            .unwrap()
+            .with_journal(Box::new(shared.clone()), retention)
+            .unwrap();
+        let body = |broker: &mut AsyncHttpBrokerStore, path: Path| {
+            broker
+                .read(&path)
+                .unwrap()
+                .map(|r| r.into_value(&NoCodec).unwrap())
+        };
+        assert_eq!(
+            body(&mut broker, path!("outstanding/0/response/body")),
+            Some(Value::String("/a".into()))
+        );
+        assert_eq!(body(&mut broker, deleted.clone()), None);
+        assert_eq!(
+            body(&mut broker, path!("outstanding/5/response/wait/body")),
+            Some(Value::String("/c".into()))
+        );
+        let next = broker
+            .write(
+                &path!(""),
+                Record::parsed(to_value(&HttpRequest::get("not a url")).unwrap()),
+            )
+            .unwrap();
+        assert_eq!(next, path!("outstanding/6"));
+        drop(broker);
+
+        // Finished requests are dropped once the retention period passes
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout()
+            .unwrap()
+            .with_journal(Box::new(shared.clone()), Duration::ZERO)
+            .unwrap();
+        assert_eq!(body(&mut broker, path!("outstanding/0")), None);
+        assert_eq!(body(&mut broker, path!("outstanding/5")), None);
+    }
+
+    #[test]
+    fn test_async_broker_batch_rejects_bad_input() {
+        let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
+        let request = to_value(&HttpRequest::get("not a url")).unwrap();
+
+        let err = broker
+            .write(
+                &path!("batch"),
+                Record::parsed(Value::Array(vec![request.clone(), Value::from("x")])),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Batch item 1"));
+
+        assert!(broker
+            .write(&path!("batch"), Record::parsed(Value::Integer(5)))
+            .is_err());
+        assert!(broker
+            .write(
+                &path!("batch"),
+                Record::parsed(Value::Map(btree! {
+                    "requests".into() => Value::Array(vec![request]),
+                    "concurrency".into() => Value::Integer(0),
+                })),
+            )
+            .is_err());
+        assert_eq!(broker.lock_batches("read").unwrap().len(), 0);
+    }
+
     #[test]
     fn test_async_broker_cannot_overwrite() {
         let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
@@ -2512,8 +3758,7 @@ mod tests {
         let mut broker = HttpBrokerStore::with_executor(mock);
 
         let result = broker.read(&path!("meta/outstanding/999"));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("not found"));
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
@@ -2692,8 +3937,7 @@ mod tests {
         let mut broker = AsyncHttpBrokerStore::with_default_timeout().unwrap();
 
         let result = broker.read(&path!("meta/outstanding/999"));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("not found"));
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
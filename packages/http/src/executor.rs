@@ -3,13 +3,156 @@
 //! This module provides a trait for HTTP execution that can be mocked in tests,
 //! avoiding the need for actual network calls.
 
+use std::io::Read;
+use std::sync::Arc;
 use std::time::Duration;
 
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, ClientBuilder, Response};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-
+use reqwest::redirect::Policy;
+use structfs_core_store::mount_store::HttpOptions;
+use structfs_core_store::{Bytes, Codec, Error, Format, LimitedCodec, OpContext, RecordLimits};
+use structfs_serde_store::{value_to_json, JsonCodec};
+use url::Url;
+
+use crate::resolver::apply_resolver;
+use crate::signing::{signer_for, RequestSigner};
+use crate::tls::{apply_tls, server_name_client};
 use crate::types::{HttpRequest, HttpResponse};
 
+/// Request timeout used when none is configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed per request, as with reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// Header carrying the [`OpContext`] trace id on outgoing requests.
+pub const TRACE_ID_HEADER: &str = "X-Trace-Id";
+
+/// W3C header carrying the [`OpContext`] trace and span ids.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Add the context's trace id, and its `traceparent` when it has a span, to
+/// a request, unless the request already sets them.
+pub(crate) fn apply_trace_id(request: &mut HttpRequest, ctx: &OpContext) {
+    let headers = [
+        (TRACE_ID_HEADER, ctx.trace_id.clone()),
+        (TRACEPARENT_HEADER, ctx.traceparent()),
+    ];
+    for (header, value) in headers {
+        let Some(value) = value else {
+            continue;
+        };
+        let present = request
+            .headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case(header));
+        if !present {
+            request.headers.insert(header.to_string(), value);
+        }
+    }
+}
+
+/// The timeout for one request: `configured`, or less if the context's
+/// deadline is sooner.
+pub(crate) fn request_timeout(configured: Duration, ctx: &OpContext) -> Duration {
+    ctx.remaining()
+        .map_or(configured, |remaining| remaining.min(configured))
+}
+
+/// Build a blocking client builder with the given connection options.
+///
+/// `options.timeout_ms` takes precedence over `timeout` when set, and
+/// `options.network` is applied to redirects. Fails if the resolver
+/// configuration is invalid or a TLS file cannot be loaded.
+pub(crate) fn client_builder(
+    timeout: Duration,
+    options: &HttpOptions,
+) -> Result<ClientBuilder, String> {
+    let timeout = options.timeout_ms.map_or(timeout, Duration::from_millis);
+    let mut builder = Client::builder().timeout(timeout);
+
+    if options.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    if let Some(max) = options.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max as usize);
+    }
+    if let Some(ms) = options.pool_idle_timeout_ms {
+        builder = builder.pool_idle_timeout(Duration::from_millis(ms));
+    }
+    if let Some(ms) = options.tcp_keepalive_ms {
+        builder = builder.tcp_keepalive(Duration::from_millis(ms));
+    }
+    if let Some(nodelay) = options.tcp_nodelay {
+        builder = builder.tcp_nodelay(nodelay);
+    }
+    if let Some(resolver) = &options.resolver {
+        builder = apply_resolver(builder, resolver)?;
+    }
+    if let Some(tls) = &options.tls {
+        builder = apply_tls(builder, tls)?;
+    }
+    if let Some(network) = options.network.clone() {
+        // Redirects must not lead outside the policy either
+        builder = builder.redirect(Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            match network.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(message) => attempt.error(message),
+            }
+        }));
+    }
+
+    Ok(builder)
+}
+
+/// Parse a request URL, checking it against `options.network`.
+///
+/// The request must be sent to the returned URL, so that the host the
+/// policy checked is the host connected to.
+pub(crate) fn parse_url(url: &str, options: &HttpOptions) -> Result<Url, String> {
+    match &options.network {
+        Some(network) => network.check(url),
+        None => Url::parse(url).map_err(|e| format!("invalid URL '{}': {}", url, e)),
+    }
+}
+
+/// Read a response body as text and, if it is JSON, as a value.
+///
+/// The body is held to `options.limits`, or [`RecordLimits::untrusted`]:
+/// reading stops past the byte limit, and JSON is decoded through a
+/// [`LimitedCodec`]. A body over a limit fails the request; a body that is
+/// not JSON leaves the value null.
+pub(crate) fn read_body(
+    mut response: Response,
+    options: &HttpOptions,
+) -> Result<(serde_json::Value, String), String> {
+    let limits = options.limits.unwrap_or_else(RecordLimits::untrusted);
+    let mut bytes = Vec::new();
+    match limits.max_bytes {
+        Some(max) => (&mut response)
+            .take(max as u64 + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| e.to_string())?,
+        None => response
+            .read_to_end(&mut bytes)
+            .map_err(|e| e.to_string())?,
+    };
+    limits.check_bytes(bytes.len()).map_err(|e| e.to_string())?;
+
+    let body_text = String::from_utf8_lossy(&bytes).into_owned();
+    let body = match LimitedCodec::new(JsonCodec, limits).decode(&Bytes::from(bytes), &Format::JSON)
+    {
+        Ok(value) => value_to_json(value),
+        Err(e @ Error::LimitExceeded { .. }) => return Err(e.to_string()),
+        Err(_) => serde_json::Value::Null,
+    };
+    Ok((body, body_text))
+}
+
 /// Trait for executing HTTP requests.
 ///
 /// Implementations can use real HTTP clients or mock responses for testing.
@@ -21,8 +164,18 @@ pub trait HttpExecutor: Send + Sync {
 }
 
 /// Production HTTP executor using reqwest.
+///
+/// Requests honor the current [`OpContext`]: an expired or cancelled context
+/// fails the request before it is sent, and a deadline shortens the timeout.
+/// Requests to hosts or schemes outside `options.network` fail without being
+/// sent.
 pub struct ReqwestExecutor {
     client: Client,
+    timeout: Duration,
+    signer: Option<Arc<dyn RequestSigner>>,
+    /// Kept to build clients for requests whose TLS server name is
+    /// overridden
+    options: HttpOptions,
 }
 
 impl ReqwestExecutor {
@@ -33,17 +186,59 @@ impl ReqwestExecutor {
             .build()
             .map_err(|e| e.to_string())?;
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            timeout,
+            signer: None,
+            options: HttpOptions::default(),
+        })
     }
 
     /// Create with default timeout of 30 seconds.
     pub fn with_default_timeout() -> Result<Self, String> {
-        Self::new(Duration::from_secs(30))
+        Self::new(DEFAULT_TIMEOUT)
+    }
+
+    /// Create an executor with connection tuning options.
+    ///
+    /// If `options.auth` is set, every request is signed before it is sent.
+    /// `options.tls` sets root CAs, a client certificate for mutual TLS and
+    /// the server name; its files are read here.
+    pub fn with_options(options: &HttpOptions) -> Result<Self, String> {
+        let client = client_builder(DEFAULT_TIMEOUT, options)?
+            .build()
+            .map_err(|e| e.to_string())?;
+        let timeout = options
+            .timeout_ms
+            .map_or(DEFAULT_TIMEOUT, Duration::from_millis);
+        let signer = options.auth.as_ref().map(signer_for).transpose()?;
+
+        Ok(Self {
+            client,
+            timeout,
+            signer,
+            options: options.clone(),
+        })
     }
 }
 
 impl HttpExecutor for ReqwestExecutor {
     fn execute(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        let ctx = OpContext::current();
+        ctx.check("http").map_err(|e| e.to_string())?;
+        let url = parse_url(&request.path, &self.options)?;
+
+        let signed;
+        let request = match &self.signer {
+            Some(signer) => {
+                let mut copy = request.clone();
+                signer.sign(&mut copy, chrono::Utc::now())?;
+                signed = copy;
+                &signed
+            }
+            None => request,
+        };
+
         let method: http::Method = request.method.clone().into();
 
         let mut headers = HeaderMap::new();
@@ -53,8 +248,18 @@ impl HttpExecutor for ReqwestExecutor {
             headers.insert(header_name, header_value);
         }
 
-        let mut req_builder = self.client.request(method, &request.path);
-        req_builder = req_builder.headers(headers);
+        let routed = server_name_client(
+            || client_builder(self.timeout, &self.options),
+            self.options.tls.as_ref(),
+            &url,
+        )?;
+        let mut req_builder = match routed {
+            Some((client, routed)) => client.request(method, routed),
+            None => self.client.request(method, url),
+        };
+        req_builder = req_builder
+            .headers(headers)
+            .timeout(request_timeout(self.timeout, &ctx));
 
         if !request.query.is_empty() {
             req_builder = req_builder.query(&request.query);
@@ -80,8 +285,7 @@ impl HttpExecutor for ReqwestExecutor {
             }
         }
 
-        let body_text = response.text().map_err(|e| e.to_string())?;
-        let body = serde_json::from_str(&body_text).unwrap_or(serde_json::Value::Null);
+        let (body, body_text) = read_body(response, &self.options)?;
 
         Ok(HttpResponse {
             status,
@@ -220,6 +424,7 @@ mod tests {
     use super::*;
     use crate::types::Method;
     use std::collections::HashMap;
+    use structfs_core_store::mount_store::HttpNetwork;
 
     #[test]
     fn mock_executor_returns_configured_response() {
@@ -394,9 +599,137 @@ mod tests {
         assert!(executor.is_ok());
     }
 
+    #[test]
+    fn context_deadline_shortens_timeout() {
+        let configured = Duration::from_secs(30);
+        assert_eq!(request_timeout(configured, &OpContext::new()), configured);
+
+        let ctx = OpContext::new().with_timeout(Duration::from_secs(1));
+        assert!(request_timeout(configured, &ctx) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn trace_id_does_not_replace_existing_header() {
+        let ctx = OpContext::new().with_trace_id("from-context");
+        let mut request = HttpRequest::get("/").with_header("x-trace-id", "explicit");
+        apply_trace_id(&mut request, &ctx);
+        assert_eq!(request.headers.len(), 1);
+        assert_eq!(request.headers["x-trace-id"], "explicit");
+    }
+
+    #[test]
+    fn span_adds_traceparent() {
+        let ctx = OpContext::new()
+            .with_trace_id("4bf92f3577b34da6a3ce929d0e0e4736")
+            .with_span_id("00f067aa0ba902b7");
+        let mut request = HttpRequest::get("/");
+        apply_trace_id(&mut request, &ctx);
+        assert_eq!(
+            request.headers[TRACEPARENT_HEADER],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+        assert_eq!(request.headers.len(), 2);
+    }
+
+    #[test]
+    fn reqwest_executor_rejects_expired_context() {
+        let executor = ReqwestExecutor::with_default_timeout().unwrap();
+        let ctx = OpContext::new().with_deadline(std::time::Instant::now());
+        let err = ctx
+            .scope(|| executor.execute(&HttpRequest::get("http://127.0.0.1:9/")))
+            .unwrap_err();
+        assert!(err.contains("deadline exceeded"));
+    }
+
+    #[test]
+    fn reqwest_executor_enforces_network_policy() {
+        let executor = ReqwestExecutor::with_options(&HttpOptions {
+            network: Some(HttpNetwork {
+                allow_hosts: vec!["api.example.com".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        let err = executor
+            .execute(&HttpRequest::get("http://127.0.0.1:9/exfiltrate"))
+            .unwrap_err();
+        assert!(err.contains("host 127.0.0.1 is not allowed"));
+
+        // The policy sees the host reqwest would connect to
+        for url in [
+            "http://evil.com\\@api.example.com/x",
+            "http://api.example.com@evil.com/x",
+        ] {
+            let err = executor.execute(&HttpRequest::get(url)).unwrap_err();
+            assert!(err.contains("host evil.com is not allowed"), "{}", err);
+        }
+        let err = executor
+            .execute(&HttpRequest::get("http://0x7f.1:9/"))
+            .unwrap_err();
+        assert!(err.contains("host 127.0.0.1 is not allowed"), "{}", err);
+    }
+
     #[test]
     fn reqwest_executor_custom_timeout() {
         let executor = ReqwestExecutor::new(Duration::from_secs(10));
         assert!(executor.is_ok());
     }
+    /// Answer `count` requests on localhost with `body` as JSON.
+    fn serve_body(count: usize, body: &'static str) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                // The client may hang up once it has read enough
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn reqwest_executor_enforces_response_limits() {
+        let url = serve_body(3, r#"{"items":[[[1]]],"name":"structfs"}"#);
+
+        let executor = ReqwestExecutor::with_options(&HttpOptions::default()).unwrap();
+        let response = executor.execute(&HttpRequest::get(&url)).unwrap();
+        assert_eq!(response.body["name"], "structfs");
+
+        let limited = |limits: RecordLimits| {
+            ReqwestExecutor::with_options(&HttpOptions {
+                limits: Some(limits),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let err = limited(RecordLimits {
+            max_bytes: Some(16),
+            ..Default::default()
+        })
+        .execute(&HttpRequest::get(&url))
+        .unwrap_err();
+        assert!(err.contains("bytes limit"), "{}", err);
+
+        let err = limited(RecordLimits {
+            max_depth: Some(3),
+            ..Default::default()
+        })
+        .execute(&HttpRequest::get(&url))
+        .unwrap_err();
+        assert!(err.contains("nesting depth limit"), "{}", err);
+    }
 }
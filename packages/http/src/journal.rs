@@ -0,0 +1,204 @@
+//! Keeping the async broker's requests in a store across restarts.
+//!
+//! The journal lives in any [`Store`](structfs_core_store::Store), laid out as:
+//!
+//! | Path | Value |
+//! |------|-------|
+//! | `index` | `{next_id, ids}`: the next handle id, and when each kept request finished |
+//! | `requests/{id}` | The request, its state, and its response or error |
+//!
+//! An entry is written before the index that lists it, so a crash between
+//! the two writes leaves at worst an entry nothing refers to.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::{Error, NoCodec, Path, Record, Value};
+use structfs_serde_store::{from_value, to_value};
+
+use crate::handle::RequestState;
+use crate::types::{HttpRequest, HttpResponse};
+
+/// A request as kept in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct JournalEntry {
+    pub request: HttpRequest,
+    pub state: RequestState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<HttpResponse>,
+    /// When the request finished, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+}
+
+impl JournalEntry {
+    pub fn pending(request: HttpRequest) -> Self {
+        Self {
+            request,
+            state: RequestState::Pending,
+            error: None,
+            response: None,
+            finished_at_ms: None,
+        }
+    }
+
+    /// Record the outcome of running the request.
+    pub fn finish(&mut self, result: Result<HttpResponse, String>) {
+        match result {
+            Ok(response) => {
+                self.state = RequestState::Complete;
+                self.response = Some(response);
+            }
+            Err(error) => {
+                self.state = RequestState::Failed;
+                self.error = Some(error);
+            }
+        }
+        self.finished_at_ms = Some(now_ms());
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalIndex {
+    next_id: u64,
+    /// Kept ids, with when each finished (`None` while pending)
+    ids: BTreeMap<u64, Option<u64>>,
+}
+
+struct JournalState {
+    store: StoreBox,
+    index: JournalIndex,
+}
+
+/// The requests of an async broker, written through to a store.
+pub(crate) struct Journal {
+    state: Mutex<JournalState>,
+    retention: Duration,
+}
+
+impl Journal {
+    /// Open the journal kept in `store`, dropping requests that finished
+    /// longer than `retention` ago.
+    pub fn open(mut store: StoreBox, retention: Duration) -> Result<Self, Error> {
+        let index: JournalIndex = match store.read(&index_path())? {
+            Some(record) => from_value(record.into_value(&NoCodec)?)?,
+            None => JournalIndex::default(),
+        };
+        let journal = Journal {
+            state: Mutex::new(JournalState { store, index }),
+            retention,
+        };
+        journal.prune()?;
+        Ok(journal)
+    }
+
+    /// The kept entries, in id order.
+    pub fn entries(&self) -> Result<Vec<(u64, JournalEntry)>, Error> {
+        let mut state = self.lock()?;
+        let ids: Vec<u64> = state.index.ids.keys().copied().collect();
+        let mut entries = Vec::new();
+        let mut missing = false;
+        for id in ids {
+            match state.store.read(&entry_path(id))? {
+                Some(record) => entries.push((id, from_value(record.into_value(&NoCodec)?)?)),
+                None => missing |= state.index.ids.remove(&id).is_some(),
+            }
+        }
+        if missing {
+            write_index(&mut state)?;
+        }
+        Ok(entries)
+    }
+
+    /// The id to allocate next: above every id the journal has seen.
+    pub fn next_id(&self) -> Result<u64, Error> {
+        Ok(self.lock()?.index.next_id)
+    }
+
+    /// Write `entry` under `id`, remembering that ids below `next_id` are
+    /// taken.
+    pub fn save(&self, id: u64, entry: &JournalEntry, next_id: u64) -> Result<(), Error> {
+        let mut state = self.lock()?;
+        state
+            .store
+            .write(&entry_path(id), Record::parsed(to_value(entry)?))?;
+        let previous = state.index.ids.insert(id, entry.finished_at_ms);
+        if previous == Some(entry.finished_at_ms) && state.index.next_id >= next_id {
+            return Ok(());
+        }
+        state.index.next_id = state.index.next_id.max(next_id);
+        write_index(&mut state)
+    }
+
+    /// Forget the entry under `id`.
+    pub fn remove(&self, id: u64) -> Result<(), Error> {
+        let mut state = self.lock()?;
+        if state.index.ids.remove(&id).is_none() {
+            return Ok(());
+        }
+        write_index(&mut state)?;
+        state
+            .store
+            .write(&entry_path(id), Record::parsed(Value::Null))?;
+        Ok(())
+    }
+
+    /// Drop requests that finished longer than the retention period ago,
+    /// returning their ids.
+    pub fn prune(&self) -> Result<Vec<u64>, Error> {
+        let retention = self.retention.as_millis() as u64;
+        let now = now_ms();
+        let mut state = self.lock()?;
+        let expired: Vec<u64> = state
+            .index
+            .ids
+            .iter()
+            .filter(|(_, finished)| finished.is_some_and(|at| now.saturating_sub(at) >= retention))
+            .map(|(id, _)| *id)
+            .collect();
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        for id in &expired {
+            state.index.ids.remove(id);
+        }
+        write_index(&mut state)?;
+        for id in &expired {
+            state
+                .store
+                .write(&entry_path(*id), Record::parsed(Value::Null))?;
+        }
+        Ok(expired)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, JournalState>, Error> {
+        self.state
+            .lock()
+            .map_err(|e| Error::store("async_http_broker", "journal", format!("Lock error: {}", e)))
+    }
+}
+
+fn write_index(state: &mut JournalState) -> Result<(), Error> {
+    let value = to_value(&state.index)?;
+    state.store.write(&index_path(), Record::parsed(value))?;
+    Ok(())
+}
+
+fn index_path() -> Path {
+    Path::from_components(vec!["index".into()])
+}
+
+fn entry_path(id: u64) -> Path {
+    Path::from_components(vec!["requests".into(), id.to_string()])
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
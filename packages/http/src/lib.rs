@@ -57,20 +57,123 @@
 //!
 //! // POST request via write
 //! client.write(&Path::parse("users")?, data)?;
+//!
+//! // Other methods are named by a `_method/{method}` suffix
+//! client.write(&Path::parse("users/123/_method/patch")?, changes)?;
+//! client.write(&Path::parse("users/123/_method/delete")?, Record::parsed(Value::Null))?;
+//! let head = client.read(&Path::parse("users/123/_method/head")?)?; // {status, headers}
+//! ```
+//!
+//! ## Request Signing
+//!
+//! Setting `auth` in [`HttpOptions`] signs every request a store sends, so
+//! AWS-style APIs can be mounted directly:
+//!
+//! ```json
+//! {"type": "http", "url": "https://sts.us-east-1.amazonaws.com",
+//!  "options": {"auth": {"type": "sigv4", "region": "us-east-1", "service": "sts"}}}
 //! ```
+//!
+//! See the [`signing`] module for the supported schemes.
+//!
+//! ## Name Resolution
+//!
+//! Setting `resolver` in [`HttpOptions`] pins hostnames to fixed addresses or
+//! resolves them with DNS-over-HTTPS instead of the system resolver:
+//!
+//! ```json
+//! {"type": "http", "url": "https://api.internal",
+//!  "options": {"resolver": {"hosts": {"api.internal": ["10.0.0.5"]}}}}
+//! ```
+//!
+//! See the [`resolver`] module for details.
+//!
+//! ## TLS
+//!
+//! Setting `tls` in [`HttpOptions`] adds private root CAs, presents a client
+//! certificate to services that require mutual TLS, overrides the server
+//! name, or, for development only, accepts invalid certificates:
+//!
+//! ```json
+//! {"type": "http", "url": "https://billing.internal",
+//!  "options": {"tls": {"ca_certs": ["certs/internal-ca.pem"],
+//!                      "client_cert": "certs/client.pem", "client_key": "certs/client.key"}}}
+//! ```
+//!
+//! See the [`tls`] module for details.
+//!
+//! ## Network Policy
+//!
+//! Setting `network` in [`HttpOptions`] limits the hosts and schemes a mount
+//! may reach. Requests and redirects outside it fail without being sent:
+//!
+//! ```json
+//! {"type": "httpbroker",
+//!  "options": {"network": {"allow_hosts": ["*.example.com"], "schemes": ["https"]}}}
+//! ```
+//!
+//! See [`HttpNetwork`] for how hosts are matched.
+//!
+//! ## Circuit Breaking
+//!
+//! Setting `circuit_breaker` in [`HttpOptions`] wraps a mount in a
+//! [`CircuitBreakerStore`], which fails fast while the upstream keeps
+//! returning errors and exposes its state under `circuit/`:
+//!
+//! ```json
+//! {"type": "http", "url": "https://api.example.com",
+//!  "options": {"circuit_breaker": {"failure_percent": 50, "cooldown_ms": 30000}}}
+//! ```
+//!
+//! See the [`circuit`] module for details.
+//!
+//! ## OpenAPI Specs
+//!
+//! Setting `openapi` in [`HttpOptions`] to a JSON OpenAPI spec wraps a mount
+//! in an [`OpenApiStore`], which maps paths onto the spec's path templates,
+//! checks path parameters and lists the spec's operations under `docs`:
+//!
+//! ```json
+//! {"type": "http", "url": "https://petstore.example.com/v1",
+//!  "options": {"openapi": "specs/petstore.json"}}
+//! ```
+//!
+//! See the [`openapi`] module for details.
+//!
+//! ## Webhooks
+//!
+//! [`WebhookStore`] verifies inbound webhook deliveries (HMAC, GitHub or
+//! Stripe signatures) written to it by an HTTP server and logs them under
+//! `events/` for Blocks to read with `latest` and `next`. See the
+//! [`webhook`] module for details.
 
+pub mod circuit;
 pub mod error;
 pub mod executor;
 pub mod handle;
+pub mod openapi;
+pub mod resolver;
+pub mod signing;
+pub mod tls;
 pub mod types;
+pub mod webhook;
 
 mod core;
+mod journal;
 
 // Re-export main types
+pub use circuit::{CircuitBreakerStore, CircuitState};
 pub use error::Error;
-pub use executor::{HttpExecutor, ReqwestExecutor};
+pub use executor::{HttpExecutor, ReqwestExecutor, DEFAULT_TIMEOUT};
 pub use handle::{RequestState, RequestStatus};
+pub use openapi::{OpenApiSpec, OpenApiStore};
+pub use resolver::DohResolver;
+pub use signing::{HmacSigner, RequestSigner, SigV4Signer, SigningExecutor};
+pub use structfs_core_store::mount_store::{
+    CircuitBreakerConfig, HttpAuth, HttpNetwork, HttpOptions, HttpResolver, HttpTls,
+};
 pub use types::{HttpRequest, HttpResponse, Method};
+pub use webhook::{Verifier, WebhookStore};
 
 // Re-export stores
 pub use crate::core::{AsyncHttpBrokerStore, HttpBrokerStore, HttpClientStore};
@@ -0,0 +1,699 @@
+//! HTTP mounts described by an OpenAPI spec.
+//!
+//! [`OpenApiStore`] wraps a store, usually an
+//! [`HttpClientStore`](crate::HttpClientStore), and checks each operation
+//! against an OpenAPI 3 spec before passing it on. Store paths are matched
+//! against the spec's path templates, so `users/42` matches
+//! `/users/{id}`:
+//!
+//! - Reads send the template's `GET` operation.
+//! - Writes send its `POST`, `PUT` or `PATCH` operation, in that order of
+//!   preference, with the written value as the body.
+//! - Writing null sends its `DELETE` operation if it has one.
+//!
+//! Path parameters are checked against their schema's `type` and `enum`
+//! before anything is sent, and reading `docs` lists every operation in the
+//! spec with its summary and parameters. A path that matches a template but
+//! not one of its methods is an error. Paths that match no template, such as
+//! `circuit/` or a raw `HttpRequest` written to the root, are passed through
+//! unchanged.
+//!
+//! Setting `openapi` in [`HttpOptions`] to the file name of a JSON spec
+//! mounts an HTTP client this way in one step:
+//!
+//! ```json
+//! {"type": "http", "url": "https://petstore.example.com/v1",
+//!  "options": {"openapi": "specs/petstore.json"}}
+//! ```
+//!
+//! Only JSON specs are read, and only path parameters are checked. Path
+//! components must be identifiers or numbers, so template segments that
+//! are not, such as `/user-profiles`, cannot be reached. Parameters given by
+//! a `$ref` are resolved within `#/components/parameters`.
+
+use structfs_core_store::describe::{Docs, DOCS_PREFIX};
+use structfs_core_store::mount_store::HttpOptions;
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Store, Value, Writer};
+use structfs_serde_store::{to_value, value_to_json};
+
+use crate::executor::ReqwestExecutor;
+use crate::types::{HttpRequest, Method};
+use crate::HttpClientStore;
+
+/// The schema type of a path parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+impl ParameterType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            Self::String => true,
+            Self::Integer => value.parse::<i64>().is_ok(),
+            Self::Number => value.parse::<f64>().is_ok(),
+            Self::Boolean => value == "true" || value == "false",
+        }
+    }
+}
+
+/// A path parameter of an operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub kind: ParameterType,
+    /// Allowed values, from the schema's `enum`. Empty allows any.
+    pub allowed: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl Parameter {
+    /// Why `value` is not valid for this parameter, if it is not.
+    fn check(&self, value: &str) -> Option<String> {
+        if !self.kind.accepts(value) {
+            return Some(format!(
+                "parameter '{}' must be {} {}, got '{}'",
+                self.name,
+                if self.kind == ParameterType::Integer {
+                    "an"
+                } else {
+                    "a"
+                },
+                self.kind.name(),
+                value
+            ));
+        }
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|a| a == value) {
+            return Some(format!(
+                "parameter '{}' must be one of {}, got '{}'",
+                self.name,
+                self.allowed.join(", "),
+                value
+            ));
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Parameter(String),
+}
+
+/// One operation in a spec: a method on a path template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub method: Method,
+    /// The template as written in the spec, such as `/users/{id}`.
+    pub template: String,
+    pub operation_id: Option<String>,
+    pub summary: Option<String>,
+    pub parameters: Vec<Parameter>,
+    segments: Vec<Segment>,
+}
+
+impl Operation {
+    /// The path parameters `path` gives this operation, if it matches the
+    /// template.
+    fn bind<'a>(&self, path: &'a Path) -> Option<Vec<(&str, &'a str)>> {
+        if path.len() != self.segments.len() {
+            return None;
+        }
+        let mut bound = Vec::new();
+        for (segment, component) in self.segments.iter().zip(path.iter()) {
+            match segment {
+                Segment::Literal(literal) if literal != component => return None,
+                Segment::Literal(_) => {}
+                Segment::Parameter(name) => bound.push((name.as_str(), component.as_str())),
+            }
+        }
+        Some(bound)
+    }
+
+    /// Number of literal segments. More specific templates win.
+    fn literals(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Literal(_)))
+            .count()
+    }
+
+    fn validate(&self, path: &Path, operation: &'static str) -> Result<(), Error> {
+        for (name, value) in self.bind(path).unwrap_or_default() {
+            let problem = match self.parameters.iter().find(|p| p.name == name) {
+                Some(parameter) => parameter.check(value),
+                None => None,
+            };
+            if let Some(problem) = problem {
+                return Err(Error::store(
+                    "openapi",
+                    operation,
+                    format!(
+                        "{} {}: {}",
+                        method_name(&self.method),
+                        self.template,
+                        problem
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The store form of the template: `users/{id}`.
+    fn store_template(&self) -> String {
+        self.template.trim_matches('/').to_string()
+    }
+
+    fn docs_line(&self) -> String {
+        let mut line = self.summary.clone().unwrap_or_default();
+        if let Some(id) = &self.operation_id {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&format!("({})", id));
+        }
+        for parameter in &self.parameters {
+            let mut kind = parameter.kind.name().to_string();
+            if !parameter.allowed.is_empty() {
+                kind = parameter.allowed.join("|");
+            }
+            line.push_str(&format!("; {}: {}", parameter.name, kind));
+        }
+        line.trim_start_matches("; ").to_string()
+    }
+}
+
+fn method_name(method: &Method) -> &'static str {
+    match method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+    }
+}
+
+/// The parts of an OpenAPI 3 spec a mount uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiSpec {
+    pub title: String,
+    pub version: String,
+    pub description: Option<String>,
+    /// Server URLs, in the order the spec lists them.
+    pub servers: Vec<String>,
+    pub operations: Vec<Operation>,
+}
+
+fn spec_error(message: impl Into<String>) -> Error {
+    Error::store("openapi", "parse", message)
+}
+
+fn string_at(value: &serde_json::Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+impl OpenApiSpec {
+    /// Read a JSON spec from a file.
+    pub fn load(file: &str) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(file)
+            .map_err(|e| spec_error(format!("Cannot read OpenAPI spec '{}': {}", file, e)))?;
+        let json: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| spec_error(format!("OpenAPI spec '{}' is not JSON: {}", file, e)))?;
+        Self::from_json(&json)
+    }
+
+    /// Parse a spec already decoded from JSON.
+    pub fn from_json(spec: &serde_json::Value) -> Result<Self, Error> {
+        let info = spec
+            .get("info")
+            .ok_or_else(|| spec_error("OpenAPI spec has no 'info'"))?;
+        let paths = spec
+            .get("paths")
+            .and_then(|p| p.as_object())
+            .ok_or_else(|| spec_error("OpenAPI spec has no 'paths' map"))?;
+
+        let mut operations = Vec::new();
+        for (template, item) in paths {
+            let segments = parse_template(template)?;
+            let shared = parameters(spec, item.get("parameters"))?;
+            for (key, method) in [
+                ("get", Method::GET),
+                ("post", Method::POST),
+                ("put", Method::PUT),
+                ("patch", Method::PATCH),
+                ("delete", Method::DELETE),
+            ] {
+                let Some(op) = item.get(key) else {
+                    continue;
+                };
+                // Operation parameters override path item parameters
+                let mut params = shared.clone();
+                for param in parameters(spec, op.get("parameters"))? {
+                    params.retain(|p: &Parameter| p.name != param.name);
+                    params.push(param);
+                }
+                operations.push(Operation {
+                    method,
+                    template: template.clone(),
+                    operation_id: string_at(op, "operationId"),
+                    summary: string_at(op, "summary"),
+                    parameters: params,
+                    segments: segments.clone(),
+                });
+            }
+        }
+
+        Ok(Self {
+            title: string_at(info, "title").unwrap_or_else(|| "OpenAPI mount".to_string()),
+            version: string_at(info, "version").unwrap_or_default(),
+            description: string_at(info, "description"),
+            servers: spec
+                .get("servers")
+                .and_then(|s| s.as_array())
+                .map(|servers| servers.iter().filter_map(|s| string_at(s, "url")).collect())
+                .unwrap_or_default(),
+            operations,
+        })
+    }
+
+    /// The operations whose templates match `path`, most specific first.
+    fn matching(&self, path: &Path) -> Vec<&Operation> {
+        let mut matches: Vec<&Operation> = self
+            .operations
+            .iter()
+            .filter(|op| op.bind(path).is_some())
+            .collect();
+        let best = matches.iter().map(|op| op.literals()).max();
+        matches.retain(|op| Some(op.literals()) == best);
+        matches
+    }
+
+    /// Docs listing every operation.
+    pub fn docs(&self) -> Value {
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("HTTP API described by the {} OpenAPI spec.", self.title));
+        let mut docs = Docs::new(&self.title, &description)
+            .field("version", Value::String(self.version.clone()));
+        for op in &self.operations {
+            let verb = match op.method {
+                Method::GET => "read",
+                Method::DELETE => "write null",
+                _ => "write",
+            };
+            docs = docs.path(
+                &format!(
+                    "{} /{} ({})",
+                    verb,
+                    op.store_template(),
+                    method_name(&op.method)
+                ),
+                &op.docs_line(),
+            );
+        }
+        docs.into()
+    }
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, Error> {
+    template
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => Ok(Segment::Parameter(name.to_string())),
+                None if segment.contains(['{', '}']) => Err(spec_error(format!(
+                    "Unsupported path template segment '{}' in '{}'",
+                    segment, template
+                ))),
+                None => Ok(Segment::Literal(segment.to_string())),
+            },
+        )
+        .collect()
+}
+
+/// The path parameters in a `parameters` list.
+fn parameters(
+    spec: &serde_json::Value,
+    list: Option<&serde_json::Value>,
+) -> Result<Vec<Parameter>, Error> {
+    let Some(list) = list.and_then(|l| l.as_array()) else {
+        return Ok(Vec::new());
+    };
+    let mut params = Vec::new();
+    for param in list {
+        let param = match string_at(param, "$ref") {
+            Some(reference) => resolve(spec, &reference)?,
+            None => param,
+        };
+        if string_at(param, "in").as_deref() != Some("path") {
+            continue;
+        }
+        let name = string_at(param, "name").ok_or_else(|| spec_error("Parameter has no name"))?;
+        let schema = param.get("schema");
+        let kind = match schema.and_then(|s| string_at(s, "type")).as_deref() {
+            Some("integer") => ParameterType::Integer,
+            Some("number") => ParameterType::Number,
+            Some("boolean") => ParameterType::Boolean,
+            _ => ParameterType::String,
+        };
+        let allowed = schema
+            .and_then(|s| s.get("enum"))
+            .and_then(|e| e.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|v| match v {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        params.push(Parameter {
+            name,
+            kind,
+            allowed,
+            description: string_at(param, "description"),
+        });
+    }
+    Ok(params)
+}
+
+fn resolve<'a>(
+    spec: &'a serde_json::Value,
+    reference: &str,
+) -> Result<&'a serde_json::Value, Error> {
+    reference
+        .strip_prefix("#/components/parameters/")
+        .and_then(|name| spec.get("components")?.get("parameters")?.get(name))
+        .ok_or_else(|| {
+            spec_error(format!(
+                "Cannot resolve parameter reference '{}'",
+                reference
+            ))
+        })
+}
+
+/// A store that routes and checks operations against an OpenAPI spec.
+pub struct OpenApiStore<S> {
+    inner: S,
+    spec: OpenApiSpec,
+}
+
+impl<S> OpenApiStore<S> {
+    /// Check operations on `inner` against `spec`.
+    pub fn new(inner: S, spec: OpenApiSpec) -> Self {
+        Self { inner, spec }
+    }
+
+    /// The spec operations are checked against.
+    pub fn spec(&self) -> &OpenApiSpec {
+        &self.spec
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Store + Send + Sync + 'static> OpenApiStore<S> {
+    /// Box `store` for mounting, behind the spec named by `options` if
+    /// they name one.
+    pub fn boxed(store: S, options: &HttpOptions) -> Result<StoreBox, Error> {
+        match &options.openapi {
+            Some(file) => Ok(Box::new(Self::new(store, OpenApiSpec::load(file)?))),
+            None => Ok(Box::new(store)),
+        }
+    }
+}
+
+impl OpenApiStore<HttpClientStore<ReqwestExecutor>> {
+    /// An HTTP client for the spec's first server, checked against the
+    /// spec.
+    pub fn from_spec(spec: OpenApiSpec, options: &HttpOptions) -> Result<Self, crate::Error> {
+        let url = spec
+            .servers
+            .first()
+            .ok_or_else(|| crate::Error::InvalidUrl {
+                message: format!("OpenAPI spec '{}' lists no servers", spec.title),
+            })?;
+        let client = HttpClientStore::with_options(url, options)?;
+        Ok(Self::new(client, spec))
+    }
+}
+
+impl<S: Reader> Reader for OpenApiStore<S> {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if from.len() == 1 && from[0] == DOCS_PREFIX {
+            return Ok(Some(Record::parsed(self.spec.docs())));
+        }
+        let matches = self.spec.matching(from);
+        if matches.is_empty() {
+            return self.inner.read(from);
+        }
+        match matches.iter().find(|op| op.method == Method::GET) {
+            Some(op) => op.validate(from, "read")?,
+            None => return Err(not_allowed("read", from, &matches)),
+        }
+        self.inner.read(from)
+    }
+}
+
+impl<S: Writer> Writer for OpenApiStore<S> {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let matches = self.spec.matching(to);
+        if matches.is_empty() {
+            return self.inner.write(to, data);
+        }
+
+        let value = data.into_value(&NoCodec)?;
+        let delete = value == Value::Null;
+        let wanted: &[Method] = if delete {
+            &[Method::DELETE, Method::POST, Method::PUT, Method::PATCH]
+        } else {
+            &[Method::POST, Method::PUT, Method::PATCH]
+        };
+        let Some(op) = wanted
+            .iter()
+            .find_map(|method| matches.iter().find(|op| op.method == *method))
+        else {
+            return Err(not_allowed("write", to, &matches));
+        };
+        op.validate(to, "write")?;
+
+        if op.method == Method::POST {
+            return self.inner.write(to, Record::parsed(value));
+        }
+        // The client store only POSTs to paths; send other methods as a
+        // request written to its root
+        let request = HttpRequest {
+            method: op.method.clone(),
+            path: to.components.join("/"),
+            body: (op.method != Method::DELETE).then(|| value_to_json(value)),
+            ..Default::default()
+        };
+        let request =
+            to_value(&request).map_err(|e| Error::store("openapi", "write", e.to_string()))?;
+        self.inner
+            .write(&Path::parse("").unwrap(), Record::parsed(request))?;
+        Ok(to.clone())
+    }
+}
+
+fn not_allowed(operation: &'static str, path: &Path, matches: &[&Operation]) -> Error {
+    let methods: Vec<&str> = matches.iter().map(|op| method_name(&op.method)).collect();
+    Error::store(
+        "openapi",
+        operation,
+        format!(
+            "Cannot {} /{}: {} only allows {}",
+            operation,
+            path,
+            matches[0].template,
+            methods.join(", ")
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+    use structfs_serde_store::from_value;
+
+    fn spec() -> OpenApiSpec {
+        OpenApiSpec::from_json(&serde_json::json!({
+            "openapi": "3.0.0",
+            "info": {"title": "Pets", "version": "1.0"},
+            "servers": [{"url": "https://pets.example.com/v1"}],
+            "components": {"parameters": {"PetId": {
+                "name": "id", "in": "path", "required": true,
+                "schema": {"type": "integer"}
+            }}},
+            "paths": {
+                "/pets": {
+                    "get": {"summary": "List pets", "operationId": "listPets"},
+                    "post": {"summary": "Add a pet"}
+                },
+                "/pets/{id}": {
+                    "parameters": [{"$ref": "#/components/parameters/PetId"}],
+                    "get": {"summary": "Get a pet"},
+                    "put": {"summary": "Replace a pet"},
+                    "delete": {"summary": "Remove a pet"}
+                },
+                "/pets/mine": {"get": {"summary": "My pets"}},
+                "/pets/{id}/status/{status}": {
+                    "parameters": [
+                        {"$ref": "#/components/parameters/PetId"},
+                        {"name": "status", "in": "path",
+                         "schema": {"type": "string", "enum": ["sold", "available"]}}
+                    ],
+                    "get": {"summary": "Pets with a status"}
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Records what reaches it.
+    #[derive(Default)]
+    struct Recorder {
+        reads: Vec<Path>,
+        writes: Vec<(Path, Value)>,
+    }
+
+    impl Reader for Recorder {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+            self.reads.push(from.clone());
+            Ok(Some(Record::parsed(Value::Null)))
+        }
+    }
+
+    impl Writer for Recorder {
+        fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+            self.writes.push((to.clone(), data.into_value(&NoCodec)?));
+            Ok(to.clone())
+        }
+    }
+
+    #[test]
+    fn parses_operations_and_parameters() {
+        let spec = spec();
+        assert_eq!(spec.title, "Pets");
+        assert_eq!(spec.servers, ["https://pets.example.com/v1"]);
+        assert_eq!(spec.operations.len(), 7);
+
+        let get = &spec.matching(&path!("pets/7"))[0];
+        assert_eq!(get.parameters[0].name, "id");
+        assert_eq!(get.parameters[0].kind, ParameterType::Integer);
+
+        // A literal segment beats a parameter
+        let mine = spec.matching(&path!("pets/mine"));
+        assert_eq!(mine.len(), 1);
+        assert_eq!(mine[0].template, "/pets/mine");
+    }
+
+    #[test]
+    fn validates_path_parameters() {
+        let mut store = OpenApiStore::new(Recorder::default(), spec());
+        store.read(&path!("pets/7")).unwrap();
+        store.read(&path!("pets/7/status/sold")).unwrap();
+        assert_eq!(store.inner().reads.len(), 2);
+
+        let err = store.read(&path!("pets/rex/status/sold")).unwrap_err();
+        assert!(err.to_string().contains("'id' must be an integer"));
+        let err = store.read(&path!("pets/7/status/lost")).unwrap_err();
+        assert!(err.to_string().contains("one of sold, available"));
+        assert_eq!(store.inner().reads.len(), 2);
+    }
+
+    #[test]
+    fn writes_pick_the_spec_method() {
+        let mut store = OpenApiStore::new(Recorder::default(), spec());
+        store
+            .write(&path!("pets"), Record::parsed(Value::from("rex")))
+            .unwrap();
+        store
+            .write(&path!("pets/7"), Record::parsed(Value::from("rex")))
+            .unwrap();
+        store
+            .write(&path!("pets/7"), Record::parsed(Value::Null))
+            .unwrap();
+
+        let writes = &store.inner().writes;
+        assert_eq!(writes[0], (path!("pets"), Value::from("rex")));
+        let put: HttpRequest = from_value(writes[1].1.clone()).unwrap();
+        assert_eq!(writes[1].0, path!(""));
+        assert_eq!((put.method, put.path.as_str()), (Method::PUT, "pets/7"));
+        assert_eq!(put.body, Some(serde_json::json!("rex")));
+        let delete: HttpRequest = from_value(writes[2].1.clone()).unwrap();
+        assert_eq!(delete.method, Method::DELETE);
+        assert_eq!(delete.body, None);
+    }
+
+    #[test]
+    fn unmatched_paths_pass_through_and_wrong_methods_fail() {
+        let mut store = OpenApiStore::new(Recorder::default(), spec());
+        store.read(&path!("circuit/state")).unwrap();
+        assert_eq!(store.inner().reads, [path!("circuit/state")]);
+
+        let err = store
+            .write(&path!("pets/mine"), Record::parsed(Value::Null))
+            .unwrap_err();
+        assert!(err.to_string().contains("only allows GET"));
+
+        let Some(Record::Parsed(Value::Map(docs))) = store.read(&path!("docs")).unwrap() else {
+            panic!("expected docs");
+        };
+        assert_eq!(docs["title"], Value::from("Pets"));
+        let Value::Map(paths) = &docs["paths"] else {
+            panic!("expected paths");
+        };
+        assert_eq!(
+            paths["read /pets (GET)"],
+            Value::from("List pets (listPets)")
+        );
+        assert_eq!(
+            paths["write /pets/{id} (PUT)"],
+            Value::from("Replace a pet; id: integer")
+        );
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use crate::executor::mock::MockExecutor;
+        use structfs_core_store::conformance;
+
+        let mock = MockExecutor::new()
+            .with_response(
+                "https://pets.example.com/pets/1",
+                MockExecutor::success_response(serde_json::json!({"id": 1})),
+            )
+            .with_default_response(MockExecutor::not_found());
+        let client = HttpClientStore::with_executor("https://pets.example.com", mock).unwrap();
+        let mut store = OpenApiStore::new(client, spec());
+        conformance::assert_found(&mut store, &path!("pets/1"));
+        conformance::assert_missing(&mut store, &path!("pets/2"));
+        conformance::assert_missing(&mut store, &path!("unlisted"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Custom hostname resolution.
+//!
+//! By default requests resolve hostnames through the system resolver. An
+//! [`HttpResolver`] in a mount's `options.resolver` replaces that:
+//!
+//! - `hosts` pins hostnames to fixed addresses, for hermetic tests and for
+//!   deployments where system DNS gives the wrong answer
+//! - `doh_url` looks up every other hostname through a DNS-over-HTTPS
+//!   endpoint with [`DohResolver`]
+//!
+//! Static hosts take precedence over DNS-over-HTTPS, and also apply to
+//! requests to the DNS-over-HTTPS endpoint itself, so its hostname can be
+//! pinned too.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use structfs_core_store::mount_store::HttpResolver;
+
+/// DNS record type for IPv4 addresses.
+const TYPE_A: u64 = 1;
+/// DNS record type for IPv6 addresses.
+const TYPE_AAAA: u64 = 28;
+
+/// Parse the addresses configured for a host. Each is an IP address or an
+/// `ip:port`; the port is used only when the request URL has none.
+pub fn parse_host_addrs(host: &str, addrs: &[String]) -> Result<Vec<SocketAddr>, String> {
+    addrs
+        .iter()
+        .map(|addr| {
+            SocketAddr::from_str(addr)
+                .or_else(|_| IpAddr::from_str(addr).map(|ip| SocketAddr::new(ip, 0)))
+                .map_err(|_| format!("resolver: invalid address '{}' for host '{}'", addr, host))
+        })
+        .collect()
+}
+
+/// Configure a blocking client builder to resolve hostnames as `config` says.
+pub(crate) fn apply_resolver(
+    builder: reqwest::blocking::ClientBuilder,
+    config: &HttpResolver,
+) -> Result<reqwest::blocking::ClientBuilder, String> {
+    let hosts = parse_hosts(&config.hosts)?;
+    let mut builder = builder;
+    for (host, addrs) in &hosts {
+        builder = builder.resolve_to_addrs(host, addrs);
+    }
+    if let Some(url) = &config.doh_url {
+        builder = builder.dns_resolver(std::sync::Arc::new(DohResolver::with_hosts(url, &hosts)?));
+    }
+    Ok(builder)
+}
+
+fn parse_hosts(
+    hosts: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<(String, Vec<SocketAddr>)>, String> {
+    hosts
+        .iter()
+        .map(|(host, addrs)| Ok((host.clone(), parse_host_addrs(host, addrs)?)))
+        .collect()
+}
+
+/// Resolves hostnames with a DNS-over-HTTPS endpoint.
+///
+/// Speaks the JSON API (`application/dns-json`) offered by Cloudflare,
+/// Google and most other public resolvers:
+///
+/// ```text
+/// GET https://1.1.1.1/dns-query?name=example.com&type=A
+/// ```
+///
+/// Both A and AAAA records are requested; IPv4 addresses come first.
+#[derive(Clone)]
+pub struct DohResolver {
+    endpoint: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl DohResolver {
+    /// Create a resolver that queries `endpoint`.
+    pub fn new(endpoint: &str) -> Result<Self, String> {
+        Self::with_hosts(endpoint, &[])
+    }
+
+    fn with_hosts(endpoint: &str, hosts: &[(String, Vec<SocketAddr>)]) -> Result<Self, String> {
+        let endpoint = reqwest::Url::parse(endpoint)
+            .map_err(|e| format!("resolver: invalid DoH URL '{}': {}", endpoint, e))?;
+        let mut builder = reqwest::Client::builder();
+        for (host, addrs) in hosts {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self { endpoint, client })
+    }
+
+    /// Look up the addresses for `host`.
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let mut addrs = Vec::new();
+        for record_type in ["A", "AAAA"] {
+            let body: serde_json::Value = self
+                .client
+                .get(self.endpoint.clone())
+                .query(&[("name", host), ("type", record_type)])
+                .header("accept", "application/dns-json")
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| format!("DoH query for {} failed: {}", host, e))?
+                .json()
+                .await
+                .map_err(|e| format!("DoH response for {} is not JSON: {}", host, e))?;
+            addrs.extend(parse_answer(host, &body)?);
+        }
+
+        if addrs.is_empty() {
+            return Err(format!("DoH: no addresses found for {}", host));
+        }
+        Ok(addrs)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Extract the addresses from a JSON DoH answer.
+///
+/// Records other than A and AAAA, such as the CNAMEs leading to them, are
+/// skipped.
+fn parse_answer(host: &str, body: &serde_json::Value) -> Result<Vec<IpAddr>, String> {
+    match body.get("Status").and_then(|s| s.as_u64()) {
+        Some(0) => {}
+        // NXDOMAIN: the name does not exist
+        Some(3) => return Err(format!("DoH: {} does not exist", host)),
+        Some(status) => {
+            return Err(format!(
+                "DoH: lookup of {} failed with rcode {}",
+                host, status
+            ))
+        }
+        None => return Err(format!("DoH: response for {} has no status", host)),
+    }
+
+    let answers = body.get("Answer").and_then(|a| a.as_array());
+    Ok(answers
+        .into_iter()
+        .flatten()
+        .filter(|record| {
+            matches!(
+                record.get("type").and_then(|t| t.as_u64()),
+                Some(TYPE_A | TYPE_AAAA)
+            )
+        })
+        .filter_map(|record| record.get("data")?.as_str()?.parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{HttpExecutor, ReqwestExecutor};
+    use crate::types::HttpRequest;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use structfs_core_store::mount_store::HttpOptions;
+
+    /// Serve `count` requests on localhost, answering each with the JSON body
+    /// `respond` returns for the request line.
+    fn serve(count: usize, respond: fn(&str) -> String) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let body = respond(&request_line);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        port
+    }
+
+    fn options(resolver: HttpResolver) -> HttpOptions {
+        HttpOptions {
+            resolver: Some(resolver),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_addresses_with_and_without_ports() {
+        let addrs = parse_host_addrs(
+            "api.test",
+            &["10.0.0.1".to_string(), "[::1]:8443".to_string()],
+        )
+        .unwrap();
+        assert_eq!(addrs[0], "10.0.0.1:0".parse().unwrap());
+        assert_eq!(addrs[1], "[::1]:8443".parse().unwrap());
+
+        let err = parse_host_addrs("api.test", &["not-an-ip".to_string()]).unwrap_err();
+        assert!(err.contains("not-an-ip"));
+    }
+
+    #[test]
+    fn parse_answer_keeps_addresses() {
+        let body = serde_json::json!({
+            "Status": 0,
+            "Answer": [
+                {"name": "www.test", "type": 5, "data": "svc.test."},
+                {"name": "svc.test", "type": 1, "data": "192.0.2.1"},
+                {"name": "svc.test", "type": 28, "data": "2001:db8::1"},
+            ]
+        });
+        let addrs = parse_answer("www.test", &body).unwrap();
+        assert_eq!(
+            addrs,
+            vec![
+                "192.0.2.1".parse::<IpAddr>().unwrap(),
+                "2001:db8::1".parse::<IpAddr>().unwrap(),
+            ]
+        );
+
+        let nxdomain = serde_json::json!({"Status": 3});
+        assert!(parse_answer("gone.test", &nxdomain)
+            .unwrap_err()
+            .contains("does not exist"));
+    }
+
+    #[test]
+    fn static_hosts_override_dns() {
+        let port = serve(1, |_| r#"{"ok": true}"#.to_string());
+        let executor = ReqwestExecutor::with_options(&options(HttpResolver {
+            hosts: BTreeMap::from([(
+                "hermetic.invalid".to_string(),
+                vec!["127.0.0.1".to_string()],
+            )]),
+            doh_url: None,
+        }))
+        .unwrap();
+
+        let url = format!("http://hermetic.invalid:{}/ping", port);
+        let response = executor.execute(&HttpRequest::get(url)).unwrap();
+        assert_eq!(response.body, serde_json::json!({"ok": true}));
+    }
+
+    #[test]
+    fn doh_resolves_hostnames() {
+        // The same server answers the DoH query and then the request itself
+        let port = serve(3, |request_line| {
+            if request_line.contains("/dns-query") && request_line.contains("type=AAAA") {
+                r#"{"Status": 0}"#.to_string()
+            } else if request_line.contains("/dns-query") {
+                r#"{"Status": 0, "Answer": [{"name": "svc.invalid", "type": 1, "data": "127.0.0.1"}]}"#
+                    .to_string()
+            } else {
+                r#"{"resolved": true}"#.to_string()
+            }
+        });
+        let executor = ReqwestExecutor::with_options(&options(HttpResolver {
+            hosts: BTreeMap::new(),
+            doh_url: Some(format!("http://127.0.0.1:{}/dns-query", port)),
+        }))
+        .unwrap();
+
+        let url = format!("http://svc.invalid:{}/", port);
+        let response = executor.execute(&HttpRequest::get(url)).unwrap();
+        assert_eq!(response.body, serde_json::json!({"resolved": true}));
+    }
+
+    #[test]
+    fn invalid_resolver_config_fails() {
+        let result = ReqwestExecutor::with_options(&options(HttpResolver {
+            hosts: BTreeMap::new(),
+            doh_url: Some("not a url".to_string()),
+        }));
+        assert!(result.is_err());
+    }
+}
@@ -44,6 +44,28 @@ impl From<http::Method> for Method {
     }
 }
 
+impl Method {
+    /// Parse a method name, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "GET" => Some(Method::GET),
+            "POST" => Some(Method::POST),
+            "PUT" => Some(Method::PUT),
+            "DELETE" => Some(Method::DELETE),
+            "PATCH" => Some(Method::PATCH),
+            "HEAD" => Some(Method::HEAD),
+            "OPTIONS" => Some(Method::OPTIONS),
+            _ => None,
+        }
+    }
+
+    /// Whether requests with this method only read, so a store read may
+    /// send them.
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Method::GET | Method::HEAD | Method::OPTIONS)
+    }
+}
+
 /// A full HTTP request specification
 ///
 /// Write this struct to an HttpStore to execute the request.
@@ -0,0 +1,567 @@
+//! Inbound webhook deliveries.
+//!
+//! [`WebhookStore`] takes the POSTs a webhook sender makes, checks their
+//! signatures and keeps them in a log that Blocks read. The store does not
+//! listen on a socket itself: whatever HTTP server the application runs
+//! writes each inbound request to the store at its route, as
+//! `{"headers": {...}, "body": "<raw body>"}`. The body must be the exact
+//! bytes received, as a string or bytes, since signatures cover it.
+//!
+//! | Path | Operation | Result |
+//! |------|-----------|--------|
+//! | `write /{route} {headers, body}` | Deliver a request | `events/{route}/{seq}`, or an error if the signature does not verify |
+//! | `read /routes` | Configured routes | `{route: {scheme, received, rejected}}` |
+//! | `read /events/{route}` | Kept events | `{seq: event}` |
+//! | `read /events/{route}/{seq}` | One event | `{seq, route, received_at_ms, headers, body}` |
+//! | `read /events/{route}/latest` | Newest event, for watching | The event, or nothing |
+//! | `read /events/{route}/next` | Next event not yet read by this handle | The event, or nothing |
+//!
+//! Bodies that parse as JSON are kept parsed, others as strings. Header
+//! names are matched without regard to case and kept in lowercase.
+//!
+//! Clones share routes and events, but each keeps its own position for
+//! `next`, so every Block that mounts a clone sees every event once.
+//! Each route keeps its newest [`DEFAULT_WEBHOOK_CAPACITY`] events; `next`
+//! skips any dropped before it got to them.
+//!
+//! ## Signature schemes
+//!
+//! - [`Verifier::Hmac`]: hex HMAC-SHA256 of the body in a named header,
+//!   optionally prefixed `sha256=`
+//! - [`Verifier::GitHub`]: `X-Hub-Signature-256: sha256=<hex>`
+//! - [`Verifier::Stripe`]: `Stripe-Signature: t=<unix>,v1=<hex>` over
+//!   `"{t}.{body}"`, rejected once `t` is further than the tolerance from
+//!   now, in either direction
+//! - [`Verifier::None`]: accept every delivery
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use collection_literals::btree;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+use structfs_serde_store::json_to_value;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Events kept per route before the oldest are dropped.
+pub const DEFAULT_WEBHOOK_CAPACITY: usize = 1000;
+
+/// Default age after which Stripe signatures are rejected.
+pub const DEFAULT_STRIPE_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// How a route checks that a delivery came from its sender.
+#[derive(Debug, Clone)]
+pub enum Verifier {
+    /// Accept every delivery.
+    None,
+    /// Hex HMAC-SHA256 of the body in `header`.
+    Hmac { header: String, secret: String },
+    /// GitHub's `X-Hub-Signature-256`.
+    GitHub { secret: String },
+    /// Stripe's `Stripe-Signature`, with how far its timestamp may be from now.
+    Stripe { secret: String, tolerance: Duration },
+}
+
+impl Verifier {
+    /// Stripe's scheme with the default tolerance.
+    pub fn stripe(secret: impl Into<String>) -> Self {
+        Self::Stripe {
+            secret: secret.into(),
+            tolerance: DEFAULT_STRIPE_TOLERANCE,
+        }
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Hmac { .. } => "hmac",
+            Self::GitHub { .. } => "github",
+            Self::Stripe { .. } => "stripe",
+        }
+    }
+
+    /// Why the delivery does not verify, if it does not.
+    fn check(&self, headers: &HashMap<String, String>, body: &[u8]) -> Result<(), String> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .map(String::as_str)
+                .ok_or_else(|| format!("missing {} header", name))
+        };
+        match self {
+            Self::None => Ok(()),
+            Self::Hmac {
+                header: name,
+                secret,
+            } => {
+                let signature = header(&name.to_lowercase())?;
+                let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+                verify(secret, body, signature)
+            }
+            Self::GitHub { secret } => {
+                let signature = header("x-hub-signature-256")?;
+                let signature = signature
+                    .strip_prefix("sha256=")
+                    .ok_or("x-hub-signature-256 must start with sha256=")?;
+                verify(secret, body, signature)
+            }
+            Self::Stripe { secret, tolerance } => {
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for part in header("stripe-signature")?.split(',') {
+                    match part.trim().split_once('=') {
+                        Some(("t", t)) => timestamp = t.parse::<u64>().ok(),
+                        Some(("v1", v1)) => signatures.push(v1),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or("stripe-signature has no timestamp")?;
+                let signed_ms = timestamp
+                    .checked_mul(1000)
+                    .ok_or("stripe-signature timestamp is out of range")?;
+                let now = now_ms();
+                let tolerance = tolerance.as_millis() as u64;
+                if signed_ms > now.saturating_add(tolerance) {
+                    return Err(format!(
+                        "signature is {}s in the future",
+                        (signed_ms - now) / 1000
+                    ));
+                }
+                let age = now.saturating_sub(signed_ms);
+                if age > tolerance {
+                    return Err(format!("signature is {}s old", age / 1000));
+                }
+                let mut signed = format!("{}.", timestamp).into_bytes();
+                signed.extend_from_slice(body);
+                // Stripe sends one v1 per active secret while rolling them
+                if signatures
+                    .iter()
+                    .any(|s| verify(secret, &signed, s).is_ok())
+                {
+                    Ok(())
+                } else {
+                    Err("no v1 signature matches".to_string())
+                }
+            }
+        }
+    }
+}
+
+/// Check a hex HMAC-SHA256 signature in constant time.
+fn verify(secret: &str, data: &[u8], signature: &str) -> Result<(), String> {
+    let signature = hex::decode(signature).map_err(|_| "signature is not hex".to_string())?;
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.verify_slice(&signature)
+        .map_err(|_| "signature does not match".to_string())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+struct Route {
+    verifier: Verifier,
+    events: BTreeMap<u64, Value>,
+    next_seq: u64,
+    received: u64,
+    rejected: u64,
+}
+
+/// A store that verifies inbound webhooks and logs them for Blocks.
+#[derive(Clone)]
+pub struct WebhookStore {
+    routes: Arc<Mutex<BTreeMap<String, Route>>>,
+    capacity: usize,
+    /// Sequence number of the next event `next` returns, per route.
+    cursors: HashMap<String, u64>,
+}
+
+impl Default for WebhookStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookStore {
+    /// A store with no routes.
+    pub fn new() -> Self {
+        Self {
+            routes: Arc::new(Mutex::new(BTreeMap::new())),
+            capacity: DEFAULT_WEBHOOK_CAPACITY,
+            cursors: HashMap::new(),
+        }
+    }
+
+    /// Keep at most `capacity` events per route.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Accept deliveries at `route`, checked by `verifier`.
+    pub fn route(self, route: &str, verifier: Verifier) -> Result<Self, Error> {
+        let route = Path::parse(route)?;
+        if route.len() != 1 || route[0] == "routes" || route[0] == "events" {
+            return Err(Error::store(
+                "webhook",
+                "route",
+                format!(
+                    "Invalid route '{}': use one component other than routes or events",
+                    route
+                ),
+            ));
+        }
+        self.routes.lock().unwrap().insert(
+            route[0].clone(),
+            Route {
+                verifier,
+                events: BTreeMap::new(),
+                next_seq: 0,
+                received: 0,
+                rejected: 0,
+            },
+        );
+        Ok(self)
+    }
+
+    fn deliver(&mut self, route: &str, data: Record) -> Result<Path, Error> {
+        let (headers, body) = parse_delivery(data.into_value(&NoCodec)?)?;
+        let mut routes = self.routes.lock().unwrap();
+        let entry = routes.get_mut(route).ok_or_else(|| Error::NoRoute {
+            path: Path::from_components(vec![route.to_string()]),
+        })?;
+        if let Err(reason) = entry.verifier.check(&headers, &body) {
+            entry.rejected += 1;
+            return Err(Error::store(
+                "webhook",
+                "verify",
+                format!("Rejected delivery to {}: {}", route, reason),
+            ));
+        }
+
+        let seq = entry.next_seq;
+        entry.next_seq += 1;
+        entry.received += 1;
+        let body = match serde_json::from_slice(&body) {
+            Ok(json) => json_to_value(json),
+            Err(_) => Value::String(String::from_utf8_lossy(&body).into_owned()),
+        };
+        let headers = headers
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        entry.events.insert(
+            seq,
+            Value::Map(btree! {
+                "seq".into() => Value::Integer(seq as i64),
+                "route".into() => Value::String(route.to_string()),
+                "received_at_ms".into() => Value::Integer(now_ms() as i64),
+                "headers".into() => Value::Map(headers),
+                "body".into() => body,
+            }),
+        );
+        while entry.events.len() > self.capacity {
+            entry.events.pop_first();
+        }
+        Ok(Path::from_components(vec![
+            "events".to_string(),
+            route.to_string(),
+            seq.to_string(),
+        ]))
+    }
+
+    fn read_events(&mut self, route: &str, rest: &[String]) -> Option<Value> {
+        let routes = self.routes.lock().unwrap();
+        let entry = routes.get(route)?;
+        match rest {
+            [] => Some(Value::Map(
+                entry
+                    .events
+                    .iter()
+                    .map(|(seq, event)| (seq.to_string(), event.clone()))
+                    .collect(),
+            )),
+            [latest] if latest == "latest" => entry.events.values().next_back().cloned(),
+            [next] if next == "next" => {
+                let cursor = self.cursors.entry(route.to_string()).or_insert(0);
+                let (seq, event) = entry.events.range(*cursor..).next()?;
+                *cursor = seq + 1;
+                Some(event.clone())
+            }
+            [seq] => entry.events.get(&seq.parse().ok()?).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// The lowercased headers and raw body of a delivery.
+fn parse_delivery(value: Value) -> Result<(HashMap<String, String>, Vec<u8>), Error> {
+    let invalid = |message: &str| Error::store("webhook", "deliver", message);
+    let Value::Map(mut map) = value else {
+        return Err(invalid(
+            "A delivery must be {\"headers\": {...}, \"body\": raw body}",
+        ));
+    };
+    let headers = match map.remove("headers") {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(Value::Map(headers)) => headers
+            .into_iter()
+            .map(|(k, v)| match v {
+                Value::String(v) => Ok((k.to_lowercase(), v)),
+                _ => Err(invalid("Header values must be strings")),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(invalid("Delivery headers must be a map")),
+    };
+    let body = match map.remove("body") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(s)) => s.into_bytes(),
+        Some(Value::Bytes(b)) => b,
+        Some(_) => {
+            return Err(invalid(
+                "A delivery body must be the raw body, as a string or bytes",
+            ))
+        }
+    };
+    Ok((headers, body))
+}
+
+impl Reader for WebhookStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let value = match from.components.as_slice() {
+            [routes] if routes == "routes" => Some(Value::Map(
+                self.routes
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(name, route)| {
+                        let info = btree! {
+                            "scheme".into() => Value::String(route.verifier.scheme().into()),
+                            "received".into() => Value::Integer(route.received as i64),
+                            "rejected".into() => Value::Integer(route.rejected as i64),
+                        };
+                        (name.clone(), Value::Map(info))
+                    })
+                    .collect(),
+            )),
+            [events, route, rest @ ..] if events == "events" => self.read_events(route, rest),
+            _ => None,
+        };
+        Ok(value.map(Record::parsed))
+    }
+}
+
+impl Writer for WebhookStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        match to.components.as_slice() {
+            [route] if route != "routes" && route != "events" => self.deliver(route, data),
+            _ => Err(Error::store(
+                "webhook",
+                "write",
+                format!("Cannot write to '{}'. Deliver requests to /{{route}}.", to),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn sign(secret: &str, data: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(data);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn delivery(headers: &[(&str, String)], body: &str) -> Record {
+        let headers = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.clone())))
+            .collect();
+        Record::parsed(Value::Map(btree! {
+            "headers".into() => Value::Map(headers),
+            "body".into() => Value::from(body),
+        }))
+    }
+
+    fn read(store: &mut WebhookStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    fn store() -> WebhookStore {
+        WebhookStore::new()
+            .route(
+                "github",
+                Verifier::GitHub {
+                    secret: "gh".into(),
+                },
+            )
+            .unwrap()
+            .route("stripe", Verifier::stripe("st"))
+            .unwrap()
+            .route(
+                "custom",
+                Verifier::Hmac {
+                    header: "X-Signature".into(),
+                    secret: "c".into(),
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn verifies_each_scheme() {
+        let mut store = store();
+        let body = r#"{"action": "opened"}"#;
+
+        let good = format!("sha256={}", sign("gh", body.as_bytes()));
+        let written = store
+            .write(
+                &path!("github"),
+                delivery(&[("X-Hub-Signature-256", good)], body),
+            )
+            .unwrap();
+        assert_eq!(written, path!("events/github/0"));
+        let bad = format!("sha256={}", sign("wrong", body.as_bytes()));
+        let err = store
+            .write(
+                &path!("github"),
+                delivery(&[("X-Hub-Signature-256", bad)], body),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("signature does not match"));
+
+        let t = now_ms() / 1000;
+        let signed = sign("st", format!("{}.{}", t, body).as_bytes());
+        let header = format!("t={},v1=deadbeef,v1={}", t, signed);
+        store
+            .write(
+                &path!("stripe"),
+                delivery(&[("Stripe-Signature", header)], body),
+            )
+            .unwrap();
+        let stale = format!("t={},v1={}", t - 600, sign("st", body.as_bytes()));
+        assert!(store
+            .write(
+                &path!("stripe"),
+                delivery(&[("Stripe-Signature", stale)], body)
+            )
+            .is_err());
+
+        store
+            .write(
+                &path!("custom"),
+                delivery(&[("x-signature", sign("c", b"plain"))], "plain"),
+            )
+            .unwrap();
+        assert!(store
+            .write(&path!("custom"), delivery(&[], "plain"))
+            .is_err());
+
+        let Some(Value::Map(routes)) = read(&mut store, &path!("routes")) else {
+            panic!("expected routes");
+        };
+        assert_eq!(
+            routes["github"],
+            Value::Map(btree! {
+                "scheme".into() => Value::from("github"),
+                "received".into() => Value::Integer(1),
+                "rejected".into() => Value::Integer(1),
+            })
+        );
+        assert!(matches!(
+            store.write(&path!("unknown"), delivery(&[], "")),
+            Err(Error::NoRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn stripe_timestamps_out_of_range_are_rejected() {
+        let mut store = store();
+        let body = "{}";
+        let write = |store: &mut WebhookStore, t: u64| {
+            let signed = sign("st", format!("{}.{}", t, body).as_bytes());
+            let header = format!("t={},v1={}", t, signed);
+            store.write(
+                &path!("stripe"),
+                delivery(&[("Stripe-Signature", header)], body),
+            )
+        };
+
+        // Overflowing the conversion to milliseconds must not wrap
+        let err = write(&mut store, u64::MAX).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{}", err);
+
+        // Signed too far ahead of now
+        let t = now_ms() / 1000;
+        let err = write(&mut store, t + 600).unwrap_err();
+        assert!(err.to_string().contains("in the future"), "{}", err);
+        write(&mut store, t + 10).unwrap();
+    }
+
+    #[test]
+    fn events_are_logged_for_each_reader() {
+        let mut store = WebhookStore::new()
+            .with_capacity(2)
+            .route("hooks", Verifier::None)
+            .unwrap();
+        let mut other = store.clone();
+        for body in [r#"{"n": 1}"#, r#"{"n": 2}"#, "three"] {
+            store
+                .write(&path!("hooks"), delivery(&[("X-Id", "a".into())], body))
+                .unwrap();
+        }
+
+        // The first event was dropped to keep two
+        let Some(Value::Map(events)) = read(&mut store, &path!("events/hooks")) else {
+            panic!("expected events");
+        };
+        assert_eq!(events.keys().collect::<Vec<_>>(), ["1", "2"]);
+        let Some(Value::Map(event)) = read(&mut store, &path!("events/hooks/1")) else {
+            panic!("expected an event");
+        };
+        assert_eq!(
+            event["body"],
+            Value::Map(btree! { "n".into() => Value::Integer(2) })
+        );
+        let Value::Map(headers) = &event["headers"] else {
+            panic!("expected headers");
+        };
+        assert_eq!(headers["x-id"], Value::from("a"));
+
+        let latest = read(&mut store, &path!("events/hooks/latest")).unwrap();
+        assert!(matches!(&latest, Value::Map(m) if m["body"] == Value::from("three")));
+
+        // Each clone reads every event once
+        for reader in [&mut store, &mut other] {
+            let first = read(reader, &path!("events/hooks/next")).unwrap();
+            assert!(matches!(&first, Value::Map(m) if m["seq"] == Value::Integer(1)));
+            assert!(read(reader, &path!("events/hooks/next")).is_some());
+            assert_eq!(read(reader, &path!("events/hooks/next")), None);
+        }
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = store();
+        conformance::assert_found(&mut store, &path!("routes"));
+        conformance::assert_missing(&mut store, &path!("github"));
+        conformance::assert_missing(&mut store, &path!("events/nope"));
+        conformance::assert_missing(&mut store, &path!("events/github/0"));
+        conformance::assert_missing(&mut store, &path!("events/github/latest"));
+    }
+}
@@ -0,0 +1,405 @@
+//! Bounded capacity for an [`InMemoryStore`] used as a cache.
+//!
+//! An [`Eviction`] names the entries of the cache with a pattern, such as
+//! `sessions/*`, and caps how many there are or how many bytes they hold.
+//! When a write goes over, entries are removed in the order an
+//! [`EvictionPolicy`] gives until the store fits again:
+//!
+//! ```rust
+//! use structfs_core_store::{path, Reader, Record, Value, Writer};
+//! use structfs_json_store::eviction::{Eviction, Lru};
+//! use structfs_json_store::InMemoryStore;
+//!
+//! let eviction = Eviction::new("sessions/*", Lru)?.max_entries(2);
+//! let mut store = InMemoryStore::new().with_eviction(eviction)?;
+//! store.write(&path!("sessions"), Record::parsed(Value::Map(Default::default())))?;
+//! for id in ["a", "b", "c"] {
+//!     let path = structfs_core_store::Path::parse(&format!("sessions/{}", id))?;
+//!     store.write(&path, Record::parsed(Value::Integer(1)))?;
+//! }
+//! // The least recently used session made room for the third
+//! assert!(store.read(&path!("sessions/a"))?.is_none());
+//! assert_eq!(store.evictions(), 1);
+//! # Ok::<(), structfs_core_store::Error>(())
+//! ```
+//!
+//! Reading an entry, or anything below it, counts as a use. Writing one
+//! counts as a use and remeasures it; sizes are the approximate bytes of
+//! its strings, bytes and keys plus a word per value. Data outside the
+//! pattern is never evicted and does not count towards the limits.
+//!
+//! With [`log_to`](Eviction::log_to), each eviction is also written to
+//! another store, such as a log aggregator's, as
+//! `{path, reason, policy, size}`. Evictions are removals: watchers and
+//! indexes see them like deletes.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use im::{OrdMap, OrdSet};
+use structfs_core_store::{Error, Path, Record, Value, Writer};
+
+use crate::persistent::Node;
+use crate::shard::SplitPoint;
+
+/// How an entry of the cache has been used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    /// Logical time of the last read or write; larger is more recent.
+    pub last_used: u64,
+    /// Number of reads and writes.
+    pub uses: u64,
+    /// Approximate size in bytes.
+    pub size: usize,
+}
+
+/// The order entries are evicted in.
+///
+/// Entries with the lowest priority go first, and the least recently used
+/// of those first.
+pub trait EvictionPolicy: Send + Sync {
+    /// The name given in eviction log records.
+    fn name(&self) -> &str;
+
+    /// Eviction priority of an entry; lower is evicted sooner.
+    fn priority(&self, usage: &Usage) -> u64;
+}
+
+/// Evict the least recently used entry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lru;
+
+impl EvictionPolicy for Lru {
+    fn name(&self) -> &str {
+        "lru"
+    }
+
+    fn priority(&self, usage: &Usage) -> u64 {
+        usage.last_used
+    }
+}
+
+/// Evict the least frequently used entry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lfu;
+
+impl EvictionPolicy for Lfu {
+    fn name(&self) -> &str {
+        "lfu"
+    }
+
+    fn priority(&self, usage: &Usage) -> u64 {
+        usage.uses
+    }
+}
+
+/// Evict the largest entry, to free the most space per eviction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+impl EvictionPolicy for LargestFirst {
+    fn name(&self) -> &str {
+        "largest_first"
+    }
+
+    fn priority(&self, usage: &Usage) -> u64 {
+        u64::MAX - usage.size as u64
+    }
+}
+
+type Log = Arc<Mutex<Box<dyn Writer>>>;
+
+/// Which entries of an [`InMemoryStore`] are evicted, when, and in what
+/// order.
+///
+/// Without [`max_entries`](Self::max_entries) or
+/// [`max_bytes`](Self::max_bytes) nothing is evicted.
+///
+/// [`InMemoryStore`]: crate::InMemoryStore
+#[derive(Clone)]
+pub struct Eviction {
+    pattern: SplitPoint,
+    policy: Arc<dyn EvictionPolicy>,
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    log: Option<(Log, Path)>,
+}
+
+impl Eviction {
+    /// Evict entries matching `pattern`, written like a [`SplitPoint`], in
+    /// the order `policy` gives.
+    pub fn new(pattern: &str, policy: impl EvictionPolicy + 'static) -> Result<Self, Error> {
+        Ok(Self {
+            pattern: SplitPoint::parse(pattern)?,
+            policy: Arc::new(policy),
+            max_entries: None,
+            max_bytes: None,
+            log: None,
+        })
+    }
+
+    /// Keep at most `max` entries.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Keep the entries to at most `max` bytes in all.
+    pub fn max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    /// Write a record of each eviction to `path` in `log`.
+    ///
+    /// The eviction happens whether or not the log accepts the record.
+    pub fn log_to(mut self, log: impl Writer + 'static, path: Path) -> Self {
+        self.log = Some((Arc::new(Mutex::new(Box::new(log))), path));
+        self
+    }
+
+    /// The pattern naming the entries.
+    pub fn pattern(&self) -> &SplitPoint {
+        &self.pattern
+    }
+}
+
+/// An entry removed to make room.
+pub(crate) struct Evicted {
+    pub(crate) path: Path,
+    size: usize,
+    reason: &'static str,
+}
+
+/// The usage of a cache's entries, kept in eviction order.
+#[derive(Clone)]
+pub(crate) struct Cache {
+    eviction: Eviction,
+    usage: OrdMap<Path, Usage>,
+    /// `(priority, last_used, entry)`, next to evict first
+    order: OrdSet<(u64, u64, Path)>,
+    bytes: usize,
+    clock: u64,
+    evictions: u64,
+}
+
+impl Cache {
+    /// Track the entries already in `root`.
+    pub(crate) fn new(eviction: Eviction, root: &Node) -> Self {
+        let mut cache = Self {
+            eviction,
+            usage: OrdMap::new(),
+            order: OrdSet::new(),
+            bytes: 0,
+            clock: 0,
+            evictions: 0,
+        };
+        cache.written(root, &Path::from_components(vec![]));
+        cache
+    }
+
+    /// Number of entries evicted so far.
+    pub(crate) fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Count a read of `from`.
+    pub(crate) fn read(&mut self, from: &Path) {
+        let Some(entry) = self.eviction.pattern.shard_of(from) else {
+            return;
+        };
+        if let Some(usage) = self.untrack(&entry) {
+            self.track(entry, usage.uses + 1, usage.size);
+        }
+    }
+
+    /// Bring the usage up to date after the subtree at `to` was written.
+    pub(crate) fn written(&mut self, root: &Node, to: &Path) {
+        if let Some(entry) = self.eviction.pattern.shard_of(to) {
+            let uses = self.untrack(&entry).map_or(0, |usage| usage.uses);
+            if let Some(size) = size_at(root, &entry) {
+                self.track(entry, uses + 1, size);
+            }
+        } else if self.eviction.pattern.extends(to) {
+            let stale: Vec<Path> = self
+                .usage
+                .keys()
+                .filter(|entry| entry.has_prefix(to))
+                .cloned()
+                .collect();
+            for entry in &stale {
+                self.untrack(entry);
+            }
+            for entry in self.eviction.pattern.instances(root) {
+                if entry.has_prefix(to) {
+                    if let Some(size) = size_at(root, &entry) {
+                        self.track(entry, 1, size);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bring the usage up to date after the subtree at `at` was removed.
+    /// Removing an array element renumbers its siblings, so the parent is
+    /// rescanned.
+    pub(crate) fn deleted(&mut self, root: &Node, at: &Path) {
+        if self.eviction.pattern.shard_of(at).as_ref() == Some(at) {
+            self.untrack(at);
+            if !matches!(
+                root.get(&at.slice(0, at.len() - 1)),
+                Ok(Some(Node::Array(_)))
+            ) {
+                return;
+            }
+        }
+        self.written(root, &at.slice(0, at.len().saturating_sub(1)));
+    }
+
+    /// The next entry to evict, if the cache is over a limit.
+    pub(crate) fn victim(&self) -> Option<Evicted> {
+        let reason = if self
+            .eviction
+            .max_entries
+            .is_some_and(|max| self.usage.len() > max)
+        {
+            "max_entries"
+        } else if self.eviction.max_bytes.is_some_and(|max| self.bytes > max) {
+            "max_bytes"
+        } else {
+            return None;
+        };
+        let (_, _, path) = self.order.get_min()?;
+        Some(Evicted {
+            path: path.clone(),
+            size: self.usage.get(path).map_or(0, |usage| usage.size),
+            reason,
+        })
+    }
+
+    /// Count `evicted`, which the store has removed, and log it.
+    pub(crate) fn evicted(&mut self, evicted: &Evicted) {
+        self.evictions += 1;
+        let Some((log, path)) = &self.eviction.log else {
+            return;
+        };
+        let record = Value::Map(BTreeMap::from([
+            ("path".to_string(), Value::String(evicted.path.to_string())),
+            ("reason".to_string(), Value::from(evicted.reason)),
+            (
+                "policy".to_string(),
+                Value::from(self.eviction.policy.name()),
+            ),
+            ("size".to_string(), Value::Integer(evicted.size as i64)),
+        ]));
+        // A failing log must not keep the cache over its limits
+        let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = log.write(path, Record::parsed(record));
+    }
+
+    fn track(&mut self, entry: Path, uses: u64, size: usize) {
+        self.clock += 1;
+        let usage = Usage {
+            last_used: self.clock,
+            uses,
+            size,
+        };
+        self.order.insert((
+            self.eviction.policy.priority(&usage),
+            usage.last_used,
+            entry.clone(),
+        ));
+        self.bytes += size;
+        self.usage.insert(entry, usage);
+    }
+
+    fn untrack(&mut self, entry: &Path) -> Option<Usage> {
+        let usage = self.usage.remove(entry)?;
+        self.order.remove(&(
+            self.eviction.policy.priority(&usage),
+            usage.last_used,
+            entry.clone(),
+        ));
+        self.bytes -= usage.size;
+        Some(usage)
+    }
+}
+
+/// Size of the entry at `path`, or `None` if there is none. A null entry
+/// counts as none, since writing null is how entries are removed.
+fn size_at(root: &Node, path: &Path) -> Option<usize> {
+    match root.get(path) {
+        Ok(Some(Node::Leaf(value))) if value.is_null() => None,
+        Ok(Some(node)) => Some(node.approximate_size()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn root(entries: &[(&str, &str)]) -> Node {
+        let map = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::from(*v)))
+            .collect();
+        Node::from(Value::Map(BTreeMap::from([(
+            "c".to_string(),
+            Value::Map(map),
+        )])))
+    }
+
+    fn victim(cache: &Cache) -> Option<(String, &'static str)> {
+        cache.victim().map(|e| (e.path.to_string(), e.reason))
+    }
+
+    #[test]
+    fn policies_choose_victims() {
+        let data = root(&[("a", "x"), ("b", "xxxxxxxxxx"), ("c", "x")]);
+
+        let mut lru = Cache::new(Eviction::new("c/*", Lru).unwrap().max_entries(2), &data);
+        assert_eq!(victim(&lru), Some(("c/a".to_string(), "max_entries")));
+        lru.read(&path!("c/a"));
+        assert_eq!(victim(&lru), Some(("c/b".to_string(), "max_entries")));
+
+        let mut lfu = Cache::new(Eviction::new("c/*", Lfu).unwrap().max_entries(2), &data);
+        lfu.read(&path!("c/a"));
+        lfu.read(&path!("c/b"));
+        assert_eq!(victim(&lfu), Some(("c/c".to_string(), "max_entries")));
+
+        let sized = Eviction::new("c/*", LargestFirst).unwrap().max_bytes(30);
+        let largest = Cache::new(sized, &data);
+        assert_eq!(victim(&largest), Some(("c/b".to_string(), "max_bytes")));
+
+        let unbounded = Cache::new(Eviction::new("c/*", Lru).unwrap(), &data);
+        assert!(unbounded.victim().is_none());
+    }
+
+    #[test]
+    fn usage_follows_writes() {
+        let mut data = root(&[("a", "x")]);
+        let mut cache = Cache::new(Eviction::new("c/*", Lru).unwrap().max_entries(0), &data);
+        assert_eq!(cache.usage.len(), 1);
+
+        data.set(&path!("c/b"), Value::from("y").into()).unwrap();
+        cache.written(&data, &path!("c/b"));
+        data.set(&path!("c/a"), Value::Null.into()).unwrap();
+        cache.written(&data, &path!("c/a"));
+        assert_eq!(cache.usage.keys().collect::<Vec<_>>(), [&path!("c/b")]);
+
+        data = root(&[("d", "z")]);
+        cache.written(&data, &path!("c"));
+        assert_eq!(cache.usage.keys().collect::<Vec<_>>(), [&path!("c/d")]);
+        assert_eq!(
+            cache.bytes,
+            data.get(&path!("c/d")).unwrap().unwrap().approximate_size()
+        );
+
+        data.remove(&path!("c/d")).unwrap();
+        cache.deleted(&data, &path!("c/d"));
+        assert!(cache.usage.is_empty());
+        assert_eq!(cache.bytes, 0);
+    }
+}
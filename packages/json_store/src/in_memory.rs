@@ -2,14 +2,31 @@
 //!
 //! In-memory JSON store using Value type.
 
-use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+use structfs_core_store::{
+    Deleter, Error, Lister, NoCodec, Path, Reader, Record, StagedOp, Subscription, Transactor,
+    Value, Watcher, Watchers, Writer,
+};
 
-use crate::value_utils;
+use crate::eviction::{Cache, Eviction};
+use crate::index::{Index, Indexes};
+use crate::persistent::Node;
 
 /// An in-memory store using core_store::Value as the storage format.
 ///
 /// In-memory store that uses the core-store Value type.
 ///
+/// Data is held in persistent collections, so `clone()` is O(1) and gives an
+/// independent copy-on-write fork: writes to either store copy only the nodes
+/// along the written path and are never seen by the other. This makes it cheap
+/// to hand out many forks of one template store. Forks start with no
+/// [watchers](Watcher): subscribers see the writes of the store they
+/// subscribed to.
+///
+/// [`with_index`](Self::with_index) adds secondary indexes, served under
+/// `indexes/` (see [`crate::index`]), and
+/// [`with_eviction`](Self::with_eviction) bounds the store's size (see
+/// [`crate::eviction`]).
+///
 /// # Example
 ///
 /// ```rust
@@ -27,28 +44,155 @@ use crate::value_utils;
 /// assert_eq!(value, Value::String("Alice".to_string()));
 /// ```
 pub struct InMemoryStore {
-    root: Value,
+    root: Node,
+    indexes: Indexes,
+    cache: Option<Cache>,
+    watchers: Watchers,
 }
 
 impl InMemoryStore {
     /// Create a new empty in-memory store.
     pub fn new() -> Self {
-        Self { root: Value::Null }
+        Self {
+            root: Node::default(),
+            indexes: Indexes::default(),
+            cache: None,
+            watchers: Watchers::new(),
+        }
     }
 
     /// Create a store with initial data.
     pub fn with_data(root: Value) -> Self {
-        Self { root }
+        Self {
+            root: root.into(),
+            indexes: Indexes::default(),
+            cache: None,
+            watchers: Watchers::new(),
+        }
+    }
+
+    /// Maintain `index` on every write, starting from the current data.
+    ///
+    /// Fails if an index with the same name was already added.
+    pub fn with_index(mut self, index: Index) -> Result<Self, Error> {
+        self.indexes.add(index, &self.root)?;
+        Ok(self)
+    }
+
+    /// Evict entries as `eviction` says once the store is over its limits,
+    /// starting with the data already in it.
+    pub fn with_eviction(mut self, eviction: Eviction) -> Result<Self, Error> {
+        self.cache = Some(Cache::new(eviction, &self.root));
+        self.evict();
+        Ok(self)
+    }
+
+    /// Number of entries evicted so far.
+    pub fn evictions(&self) -> u64 {
+        self.cache.as_ref().map_or(0, Cache::evictions)
+    }
+
+    /// Remove entries until the cache fits its limits.
+    fn evict(&mut self) {
+        let Some(cache) = &mut self.cache else {
+            return;
+        };
+        while let Some(evicted) = cache.victim() {
+            let pending = self
+                .watchers
+                .before(&evicted.path, |at| record_at(&self.root, at));
+            // Entries come from the tree, so they can always be removed
+            let _ = self.root.remove(&evicted.path);
+            self.indexes.deleted(&self.root, &evicted.path);
+            cache.deleted(&self.root, &evicted.path);
+            pending.send(|at| record_at(&self.root, at));
+            cache.evicted(&evicted);
+        }
+    }
+
+    /// Materialize the whole tree as a `Value`.
+    ///
+    /// The tree is held as persistent nodes rather than one `Value`, so
+    /// this builds a copy on each call. Read the paths you need instead
+    /// where you can.
+    pub fn root(&self) -> Value {
+        self.root.to_value()
+    }
+
+    /// The whole tree as a mutable `Value`, written back as the store's
+    /// root by [`RootMut::commit`] or when the returned guard is dropped.
+    ///
+    /// Like [`root`](Self::root) this builds a copy of the tree, so prefer
+    /// writing the paths that change.
+    pub fn root_mut(&mut self) -> RootMut<'_> {
+        let value = self.root();
+        RootMut {
+            store: self,
+            value,
+            committed: false,
+        }
+    }
+}
+
+/// Mutable access to an [`InMemoryStore`]'s whole tree, from
+/// [`InMemoryStore::root_mut`].
+///
+/// [`commit`](Self::commit) writes the value back like any write of the
+/// root, so watchers, indexes and eviction see the change. Dropping the
+/// guard without committing does the same, ignoring errors.
+pub struct RootMut<'a> {
+    store: &'a mut InMemoryStore,
+    value: Value,
+    committed: bool,
+}
+
+impl RootMut<'_> {
+    /// Write the value back as the store's root.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.committed = true;
+        self.write_back()
+    }
+
+    fn write_back(&mut self) -> Result<(), Error> {
+        let value = std::mem::replace(&mut self.value, Value::Null);
+        self.store
+            .write(&Path::parse("").unwrap(), Record::parsed(value))
+            .map(drop)
+    }
+}
+
+impl std::ops::Deref for RootMut<'_> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.value
     }
+}
 
-    /// Get a reference to the root value.
-    pub fn root(&self) -> &Value {
-        &self.root
+impl std::ops::DerefMut for RootMut<'_> {
+    fn deref_mut(&mut self) -> &mut Value {
+        &mut self.value
     }
+}
 
-    /// Get a mutable reference to the root value.
-    pub fn root_mut(&mut self) -> &mut Value {
-        &mut self.root
+impl Drop for RootMut<'_> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        let result = self.write_back();
+        debug_assert!(result.is_ok(), "writing back the root failed: {:?}", result);
+    }
+}
+
+impl Clone for InMemoryStore {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            indexes: self.indexes.clone(),
+            cache: self.cache.clone(),
+            watchers: Watchers::new(),
+        }
     }
 }
 
@@ -60,29 +204,463 @@ impl Default for InMemoryStore {
 
 impl Reader for InMemoryStore {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
-        match value_utils::get_path(&self.root, from)? {
-            Some(value) => {
-                let cloned: Value = value.clone();
-                Ok(Some(Record::parsed(cloned)))
-            }
+        if self.indexes.serves(from) {
+            return Ok(self.indexes.read(from).map(Record::parsed));
+        }
+        if let Some(cache) = &mut self.cache {
+            cache.read(from);
+        }
+        match self.root.get(from)? {
+            Some(node) => Ok(Some(Record::parsed(node.to_value()))),
             None => Ok(None),
         }
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        Some(self)
+    }
+
+    /// The approximate size of the stored tree; indexes are not counted.
+    fn memory_usage(&self) -> Option<usize> {
+        Some(self.root.approximate_size())
+    }
+}
+
+/// Lists map keys and array indices without materializing the subtree.
+impl Lister for InMemoryStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        if self.indexes.serves(path) {
+            return Ok(self.indexes.list(path));
+        }
+        self.root.children(path)
+    }
 }
 
 impl Writer for InMemoryStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if self.indexes.serves(to) {
+            return Err(Indexes::read_only(to));
+        }
         let value = data.into_value(&NoCodec)?;
-        value_utils::set_path(&mut self.root, to, value)?;
+        let pending = self.watchers.before(to, |path| record_at(&self.root, path));
+        self.root.set(to, value.into())?;
+        self.indexes.written(&self.root, to);
+        if let Some(cache) = &mut self.cache {
+            cache.written(&self.root, to);
+        }
+        pending.send(|path| record_at(&self.root, path));
+        self.evict();
         Ok(to.clone())
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+
+    fn as_transactor(&mut self) -> Option<&mut dyn Transactor> {
+        Some(self)
+    }
+}
+
+/// Removes map keys and array elements; deleting the root empties the store.
+impl Deleter for InMemoryStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        if self.indexes.serves(path) {
+            return Err(Indexes::read_only(path));
+        }
+        let pending = self.watchers.before(path, |at| record_at(&self.root, at));
+        if self.root.remove(path)? {
+            self.indexes.deleted(&self.root, path);
+            if let Some(cache) = &mut self.cache {
+                cache.deleted(&self.root, path);
+            }
+        }
+        pending.send(|at| record_at(&self.root, at));
+        Ok(())
+    }
+}
+
+/// Applies the batch to a copy-on-write fork of the data and swaps it in
+/// once every change has succeeded, so readers, watchers and indexes only
+/// ever see the whole batch.
+impl Transactor for InMemoryStore {
+    fn apply(&mut self, ops: Vec<StagedOp>) -> Result<Vec<Path>, Error> {
+        let mut root = self.root.clone();
+        let mut paths: Vec<Path> = Vec::with_capacity(ops.len());
+        let mut deleted = Vec::with_capacity(ops.len());
+        for op in ops {
+            let path = op.path().clone();
+            if self.indexes.serves(&path) {
+                return Err(Indexes::read_only(&path));
+            }
+            match op {
+                StagedOp::Write { record, .. } => {
+                    root.set(&path, record.into_value(&NoCodec)?.into())?;
+                    deleted.push(false);
+                }
+                StagedOp::Delete { .. } => {
+                    root.remove(&path)?;
+                    deleted.push(true);
+                }
+            }
+            paths.push(path);
+        }
+
+        let mut pending = Vec::with_capacity(paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            // One report per path, with the batch's final value.
+            if !paths[..i].contains(path) {
+                pending.push(self.watchers.before(path, |at| record_at(&self.root, at)));
+            }
+        }
+        self.root = root;
+        for (path, deleted) in paths.iter().zip(deleted) {
+            if deleted {
+                self.indexes.deleted(&self.root, path);
+            } else {
+                self.indexes.written(&self.root, path);
+            }
+            if let Some(cache) = &mut self.cache {
+                if deleted {
+                    cache.deleted(&self.root, path);
+                } else {
+                    cache.written(&self.root, path);
+                }
+            }
+        }
+        for pending in pending {
+            pending.send(|at| record_at(&self.root, at));
+        }
+        self.evict();
+        Ok(paths)
+    }
+}
+
+/// Reports every write and delete as it happens, at the deeper of the
+/// written and the watched path.
+impl Watcher for InMemoryStore {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        Ok(self.watchers.subscribe(path))
+    }
+}
+
+fn record_at(root: &Node, path: &Path) -> Option<Record> {
+    root.get(path)
+        .ok()
+        .flatten()
+        .map(|node| Record::parsed(node.to_value()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::BTreeMap;
-    use structfs_core_store::path;
+    use structfs_core_store::{conformance, path};
+
+    #[test]
+    fn conforms_to_store_contract() {
+        let mut store = InMemoryStore::new();
+        conformance::assert_missing(&mut store, &path!("missing"));
+        conformance::assert_round_trip(&mut store, &path!("users"), Value::Integer(1));
+        conformance::assert_missing(&mut store, &path!("users/1"));
+        conformance::assert_missing(&mut store, &path!("missing/child"));
+        conformance::assert_deletes(&mut store, &path!("users"), Value::Integer(1));
+    }
+
+    #[test]
+    fn wrappers_forward_deletes() {
+        use structfs_core_store::overlay_store::OverlayStore;
+        use structfs_core_store::{
+            DeadLetterStore, NamespacedStore, OpContext, PollWatcher, SessionStore, SpilloverStore,
+            TrashStore,
+        };
+
+        // A store holding empty maps down `keys`, to write below
+        let nested = |keys: &[&str]| {
+            let value = keys
+                .iter()
+                .rev()
+                .fold(Value::Map(BTreeMap::new()), |inner, key| {
+                    Value::Map(BTreeMap::from([(key.to_string(), inner)]))
+                });
+            InMemoryStore::with_data(value)
+        };
+        let value = || Value::Integer(1);
+        let users = path!("users");
+        conformance::assert_deletes(
+            &mut SessionStore::new(InMemoryStore::new()),
+            &users,
+            value(),
+        );
+        conformance::assert_deletes(
+            &mut DeadLetterStore::new(InMemoryStore::new()),
+            &users,
+            value(),
+        );
+        conformance::assert_deletes(
+            &mut SpilloverStore::new(InMemoryStore::new(), InMemoryStore::new()),
+            &users,
+            value(),
+        );
+        conformance::assert_deletes(&mut PollWatcher::new(InMemoryStore::new()), &users, value());
+        OpContext::new().with_tenant("acme").scope(|| {
+            conformance::assert_deletes(
+                &mut NamespacedStore::new(nested(&["tenants", "acme"])),
+                &users,
+                value(),
+            )
+        });
+
+        let mut trash = TrashStore::new(InMemoryStore::new());
+        conformance::assert_deletes(&mut trash, &users, value());
+        assert_eq!(trash.deleted_at().unwrap().len(), 1);
+
+        struct Passthrough;
+        impl structfs_core_store::Interceptor for Passthrough {}
+        let mut overlay = OverlayStore::new();
+        overlay.mount(path!(""), InMemoryStore::new());
+        overlay.add_interceptor(Box::new(Passthrough));
+        conformance::assert_deletes(&mut overlay, &users, value());
+    }
+
+    #[test]
+    fn delete_removes_subtree() {
+        use structfs_core_store::Store;
+
+        let mut store = InMemoryStore::with_data(Value::Map(BTreeMap::from([(
+            "users".to_string(),
+            Value::Map(BTreeMap::from([
+                ("alice".to_string(), Value::Integer(1)),
+                ("bob".to_string(), Value::Integer(2)),
+            ])),
+        )])));
+
+        Store::delete(&mut store, &path!("users/alice")).unwrap();
+        assert!(store.read(&path!("users/alice")).unwrap().is_none());
+        assert!(store.read(&path!("users/bob")).unwrap().is_some());
+
+        // Through a trait object, which reaches the Deleter via as_deleter
+        let boxed: &mut dyn Store = &mut store;
+        boxed.delete(&path!("users")).unwrap();
+        assert_eq!(store.root(), Value::Map(BTreeMap::new()));
+    }
+
+    #[test]
+    fn list_children() {
+        let mut store = InMemoryStore::with_data(Value::Map(BTreeMap::from([
+            ("b".to_string(), Value::Array(vec![Value::Null])),
+            ("a".to_string(), Value::Integer(1)),
+        ])));
+        let store: &mut dyn structfs_core_store::Store = &mut store;
+        assert_eq!(store.list(&path!("")).unwrap().unwrap(), ["a", "b"]);
+        assert_eq!(store.list(&path!("b")).unwrap().unwrap(), ["0"]);
+        assert_eq!(store.list(&path!("c")).unwrap(), None);
+    }
+
+    #[test]
+    fn indexes_answer_lookups() {
+        use structfs_core_store::{Reference, Store};
+
+        let user =
+            |email: &str| Value::Map(BTreeMap::from([("email".to_string(), Value::from(email))]));
+        let index = Index::new("users_by_email", "users/*", "email").unwrap();
+        let query = index.query_path(&Value::from("a@x.org")).unwrap();
+        let mut store = InMemoryStore::with_data(Value::Map(BTreeMap::from([(
+            "users".to_string(),
+            Value::Map(BTreeMap::from([("alice".to_string(), user("a@x.org"))])),
+        )])))
+        .with_index(index)
+        .unwrap();
+
+        store
+            .write(&path!("users/bob"), Record::parsed(user("a@x.org")))
+            .unwrap();
+        let found = store.read(&query).unwrap().unwrap();
+        assert_eq!(
+            found.into_value(&NoCodec).unwrap(),
+            Value::Map(BTreeMap::from([(
+                "items".to_string(),
+                Value::Array(vec![
+                    Reference::new("users/alice").to_value(),
+                    Reference::new("users/bob").to_value(),
+                ]),
+            )]))
+        );
+        assert_eq!(
+            Store::list(&mut store, &path!("indexes")).unwrap().unwrap(),
+            ["users_by_email"]
+        );
+        assert_eq!(
+            Store::list(&mut store, &query).unwrap().unwrap(),
+            ["alice", "bob"]
+        );
+
+        // Forks keep their own index
+        let mut fork = store.clone();
+        Store::delete(&mut fork, &path!("users/alice")).unwrap();
+        assert_eq!(Store::list(&mut fork, &query).unwrap().unwrap(), ["bob"]);
+        assert_eq!(
+            Store::list(&mut store, &query).unwrap().unwrap(),
+            ["alice", "bob"]
+        );
+
+        assert!(store.write(&query, Record::parsed(Value::Null)).is_err());
+        assert!(Store::delete(&mut store, &path!("indexes")).is_err());
+    }
+
+    #[test]
+    fn watchers_see_writes_and_deletes() {
+        use structfs_core_store::Store;
+
+        let mut store = InMemoryStore::with_data(Value::Map(BTreeMap::from([(
+            "config".to_string(),
+            Value::Map(BTreeMap::from([("level".to_string(), Value::from("info"))])),
+        )])));
+        let config = Store::watch(&mut store, &path!("config")).unwrap();
+        let level = Store::watch(&mut store, &path!("config/level")).unwrap();
+        let mut fork = store.clone();
+
+        store
+            .write(&path!("config/level"), Record::parsed(Value::from("debug")))
+            .unwrap();
+        for subscription in [&config, &level] {
+            let change = subscription.try_next().unwrap();
+            assert_eq!(change.path, path!("config/level"));
+            assert_eq!(
+                change.old.unwrap().into_value(&NoCodec).unwrap(),
+                Value::from("info")
+            );
+            assert_eq!(
+                change.new.unwrap().into_value(&NoCodec).unwrap(),
+                Value::from("debug")
+            );
+        }
+
+        Store::delete(&mut store, &path!("config")).unwrap();
+        assert_eq!(config.try_next().unwrap().path, path!("config"));
+        let change = level.try_next().unwrap();
+        assert_eq!(change.path, path!("config/level"));
+        assert!(change.new.is_none());
+
+        // Neither forks nor unrelated writes reach the subscribers
+        fork.write(&path!("config/level"), Record::parsed(Value::Null))
+            .unwrap();
+        store
+            .write(&path!("other"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        assert!(config.drain().is_empty());
+        assert!(level.drain().is_empty());
+    }
+
+    #[test]
+    fn transactions_apply_all_or_nothing() {
+        use structfs_core_store::Store;
+
+        let mut store = InMemoryStore::with_data(Value::Map(BTreeMap::from([
+            ("users".to_string(), Value::map()),
+            ("by_email".to_string(), Value::map()),
+        ])));
+        let users = Store::watch(&mut store, &path!("users")).unwrap();
+
+        let mut tx = store.begin();
+        tx.stage(&path!("users/u1"), Record::parsed(Value::from("a@x.org")));
+        tx.stage(&path!("missing/parent"), Record::parsed(Value::Null));
+        assert!(tx.commit().is_err());
+        assert!(store.read(&path!("users/u1")).unwrap().is_none());
+        assert!(users.drain().is_empty());
+
+        let mut tx = store.begin();
+        tx.stage(&path!("users/u1"), Record::parsed(Value::from("a@x.org")));
+        tx.stage(&path!("by_email/a"), Record::parsed(Value::from("u1")));
+        tx.stage(&path!("users/u1"), Record::parsed(Value::from("b@x.org")));
+        let paths = tx.commit().unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(
+            store.root().get(&path!("by_email/a")),
+            Some(&Value::from("u1"))
+        );
+        let changes = users.drain();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0]
+                .new
+                .clone()
+                .unwrap()
+                .into_value(&NoCodec)
+                .unwrap(),
+            Value::from("b@x.org")
+        );
+    }
+
+    #[test]
+    fn eviction_removes_logs_and_notifies() {
+        use crate::eviction::Lru;
+        use std::sync::{Arc, Mutex};
+        use structfs_core_store::Store;
+
+        #[derive(Clone, Default)]
+        struct Log(Arc<Mutex<Vec<Value>>>);
+        impl Writer for Log {
+            fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+                self.0.lock().unwrap().push(data.into_value(&NoCodec)?);
+                Ok(to.clone())
+            }
+        }
+
+        let log = Log::default();
+        let eviction = Eviction::new("cache/*", Lru)
+            .unwrap()
+            .max_entries(2)
+            .log_to(log.clone(), path!("cache/evictions"));
+        let mut store = InMemoryStore::new()
+            .with_index(Index::new("by_owner", "cache/*", "owner").unwrap())
+            .unwrap()
+            .with_eviction(eviction)
+            .unwrap();
+        let entry = |owner: &str| {
+            Record::parsed(Value::Map(BTreeMap::from([(
+                "owner".to_string(),
+                Value::from(owner),
+            )])))
+        };
+        store
+            .write(&path!("cache"), Record::parsed(Value::Map(BTreeMap::new())))
+            .unwrap();
+        store.write(&path!("cache/a"), entry("ann")).unwrap();
+        store.write(&path!("cache/b"), entry("bob")).unwrap();
+        let changes = Store::watch(&mut store, &path!("cache/a")).unwrap();
+
+        // Reading a keeps it, so b is the least recently used
+        store.read(&path!("cache/a/owner")).unwrap();
+        store.write(&path!("cache/c"), entry("cy")).unwrap();
+        // Listing is not a use
+        assert_eq!(
+            Store::list(&mut store, &path!("cache")).unwrap().unwrap(),
+            ["a", "c"]
+        );
+        assert_eq!(store.evictions(), 1);
+        assert!(changes.try_next().is_none());
+
+        let by_bob = Path::parse("indexes/by_owner/bob").unwrap();
+        assert!(store.read(&by_bob).unwrap().is_none());
+        let logged = log.0.lock().unwrap().clone();
+        assert_eq!(
+            logged,
+            [Value::Map(BTreeMap::from([
+                ("path".to_string(), Value::from("cache/b")),
+                ("reason".to_string(), Value::from("max_entries")),
+                ("policy".to_string(), Value::from("lru")),
+                ("size".to_string(), Value::Integer(24)),
+            ]))]
+        );
+
+        store.write(&path!("cache/d"), entry("di")).unwrap();
+        assert_eq!(changes.try_next().unwrap().path, path!("cache/a"));
+    }
 
     #[test]
     fn basic_write_read() {
@@ -200,4 +778,88 @@ mod tests {
         let value = record.into_value(&NoCodec).unwrap();
         assert_eq!(value, Value::String("value".to_string()));
     }
+
+    #[test]
+    fn memory_usage_matches_the_stored_value() {
+        let mut data = BTreeMap::new();
+        data.insert("key".to_string(), Value::String("value".to_string()));
+        data.insert("list".to_string(), Value::from(vec![1, 2, 3]));
+        let data = Value::Map(data);
+
+        let mut store = InMemoryStore::with_data(data.clone());
+        assert_eq!(store.memory_usage(), Some(data.estimated_size()));
+
+        store
+            .write(&path!("blob"), Record::parsed(Value::Bytes(vec![0; 4096])))
+            .unwrap();
+        assert!(store.memory_usage().unwrap() > data.estimated_size() + 4096);
+        store.delete(&path!("blob")).unwrap();
+        assert_eq!(store.memory_usage(), Some(data.estimated_size()));
+    }
+
+    #[test]
+    fn clone_is_independent_fork() {
+        let mut template = InMemoryStore::new();
+        template
+            .write(&path!("config"), Record::parsed(Value::from("default")))
+            .unwrap();
+
+        let mut fork = template.clone();
+        fork.write(&path!("config"), Record::parsed(Value::from("custom")))
+            .unwrap();
+        template
+            .write(&path!("extra"), Record::parsed(Value::Bool(true)))
+            .unwrap();
+
+        let read = |store: &mut InMemoryStore, p: &Path| {
+            store
+                .read(p)
+                .unwrap()
+                .map(|r| r.into_value(&NoCodec).unwrap())
+        };
+        assert_eq!(
+            read(&mut template, &path!("config")),
+            Some(Value::from("default"))
+        );
+        assert_eq!(
+            read(&mut fork, &path!("config")),
+            Some(Value::from("custom"))
+        );
+        assert_eq!(read(&mut fork, &path!("extra")), None);
+    }
+
+    #[test]
+    fn root_mut_writes_back_when_dropped() {
+        use structfs_core_store::Store;
+
+        let mut store = InMemoryStore::with_data(Value::map());
+        let name = Store::watch(&mut store, &path!("name")).unwrap();
+        *store.root_mut() =
+            Value::Map(BTreeMap::from([("name".to_string(), Value::from("Alice"))]));
+        if let Value::Map(map) = &mut *store.root_mut() {
+            map.insert("age".to_string(), Value::Integer(30));
+        }
+
+        let record = store.read(&path!("name")).unwrap().unwrap();
+        assert_eq!(record.into_value(&NoCodec).unwrap(), Value::from("Alice"));
+        assert_eq!(store.root().get(&path!("age")), Some(&Value::Integer(30)));
+        assert_eq!(name.drain().len(), 1);
+    }
+
+    #[test]
+    fn root_mut_commit_writes_once() {
+        use structfs_core_store::Store;
+
+        let mut store = InMemoryStore::with_data(Value::map());
+        let changes = Store::watch(&mut store, &path!("")).unwrap();
+        let mut root = store.root_mut();
+        *root = Value::Map(BTreeMap::from([("name".to_string(), Value::from("Alice"))]));
+        root.commit().unwrap();
+
+        assert_eq!(
+            store.root().get(&path!("name")),
+            Some(&Value::from("Alice"))
+        );
+        assert_eq!(changes.drain().len(), 1);
+    }
 }
@@ -0,0 +1,404 @@
+//! Secondary indexes over map fields.
+//!
+//! An [`Index`] names a field of the entries matching a pattern, such as the
+//! `email` of each `users/*`. The store keeps a map from field values to the
+//! entries that have them and updates it on every write, so finding a user
+//! by email reads one path instead of scanning all of `users`:
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//! use structfs_core_store::{path, NoCodec, Reader, Record, Reference, Value, Writer};
+//! use structfs_json_store::{Index, InMemoryStore};
+//!
+//! let index = Index::new("users_by_email", "users/*", "email")?;
+//! let query = index.query_path(&Value::from("alice@example.com")).unwrap();
+//! let mut store = InMemoryStore::new().with_index(index)?;
+//!
+//! store.write(&path!("users"), Record::parsed(Value::Map(BTreeMap::new())))?;
+//! let alice = BTreeMap::from([("email".to_string(), Value::from("alice@example.com"))]);
+//! store.write(&path!("users/alice"), Record::parsed(Value::Map(alice)))?;
+//!
+//! // indexes/users_by_email/_N_aliceexamplecom__...
+//! let found = store.read(&query)?.unwrap().into_value(&NoCodec)?;
+//! let Value::Map(found) = found else { panic!() };
+//! assert_eq!(found["items"], Value::Array(vec![Reference::new("users/alice").to_value()]));
+//! # Ok::<(), structfs_core_store::Error>(())
+//! ```
+//!
+//! Field values are indexed as text, so the string `"1"` and the integer
+//! `1` share a key. Keys are the text [namecode]-encoded into a valid path
+//! component; [`Index::key`] computes one. Entries whose field is missing,
+//! null, or a map or array are not indexed.
+//!
+//! Indexes are read-only and live under [`INDEXES_PATH`], which shadows any
+//! data stored there:
+//!
+//! | Path | Value |
+//! |------|-------|
+//! | `indexes` | Every index, by name |
+//! | `indexes/{name}` | The index: a map from key to its entries |
+//! | `indexes/{name}/{key}` | `{items: [...]}`, references to the entries with that key |
+//!
+//! A key no entry has reads as missing.
+
+use im::{OrdMap, OrdSet};
+use structfs_core_store::{Error, Path, Reference, Value};
+
+use crate::persistent::Node;
+use crate::shard::SplitPoint;
+
+/// Path under which a store serves its indexes.
+pub const INDEXES_PATH: &str = "indexes";
+
+/// A field of the entries matching a pattern, kept indexed by value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    name: String,
+    pattern: SplitPoint,
+    field: Path,
+}
+
+impl Index {
+    /// Index the `field` of each entry matching `pattern`, such as `email`
+    /// of `users/*`. Patterns are written like [`SplitPoint`]s, and `field`
+    /// may be a path such as `address/city`.
+    ///
+    /// Fails if `name` is not a single path component, `pattern` is invalid
+    /// or the root, or `field` is invalid or empty.
+    pub fn new(name: &str, pattern: &str, field: &str) -> Result<Self, Error> {
+        if Path::parse(name)?.len() != 1 {
+            return Err(Error::store(
+                "json_store",
+                "index",
+                format!("Index name '{}' must be a single path component", name),
+            ));
+        }
+        if pattern.split('/').all(str::is_empty) {
+            return Err(Error::store(
+                "json_store",
+                "index",
+                "The root cannot be indexed",
+            ));
+        }
+        let field_path = Path::parse(field)?;
+        if field_path.is_empty() {
+            return Err(Error::store(
+                "json_store",
+                "index",
+                format!("Index '{}' needs a field", name),
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            pattern: SplitPoint::parse(pattern)?,
+            field: field_path,
+        })
+    }
+
+    /// The name the index is served under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The pattern of the indexed entries.
+    pub fn pattern(&self) -> &SplitPoint {
+        &self.pattern
+    }
+
+    /// The indexed field, relative to each entry.
+    pub fn field(&self) -> &Path {
+        &self.field
+    }
+
+    /// The key a field value is indexed under, or `None` if values like it
+    /// are not indexed.
+    pub fn key(value: &Value) -> Option<String> {
+        let text = match value {
+            Value::String(s) if !s.is_empty() => s.clone(),
+            Value::Integer(n) => n.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return None,
+        };
+        Some(namecode::encode(&text))
+    }
+
+    /// The path that lists the entries whose field is `value`.
+    pub fn query_path(&self, value: &Value) -> Option<Path> {
+        let key = Self::key(value)?;
+        Path::try_from_components(vec![INDEXES_PATH.to_string(), self.name.clone(), key]).ok()
+    }
+}
+
+/// An index and its current contents.
+#[derive(Clone)]
+struct Maintained {
+    index: Index,
+    entries: OrdMap<String, OrdSet<Path>>,
+    keys: OrdMap<Path, String>,
+}
+
+impl Maintained {
+    /// Bring the index up to date after the subtree at `to` changed.
+    fn refresh(&mut self, root: &Node, to: &Path) {
+        if let Some(entry) = self.index.pattern.shard_of(to) {
+            self.reindex(root, entry);
+        } else if self.index.pattern.extends(to) {
+            let stale: Vec<Path> = self
+                .keys
+                .keys()
+                .filter(|entry| entry.has_prefix(to))
+                .cloned()
+                .collect();
+            for entry in &stale {
+                self.unindex(entry);
+            }
+            for entry in self.index.pattern.instances(root) {
+                if entry.has_prefix(to) {
+                    self.reindex(root, entry);
+                }
+            }
+        }
+    }
+
+    fn reindex(&mut self, root: &Node, entry: Path) {
+        self.unindex(&entry);
+        let key = match root.get(&entry.join(&self.index.field)) {
+            Ok(Some(Node::Leaf(value))) => Index::key(value),
+            _ => None,
+        };
+        if let Some(key) = key {
+            self.entries
+                .entry(key.clone())
+                .or_default()
+                .insert(entry.clone());
+            self.keys.insert(entry, key);
+        }
+    }
+
+    fn unindex(&mut self, entry: &Path) {
+        let Some(key) = self.keys.remove(entry) else {
+            return;
+        };
+        if let Some(entries) = self.entries.get_mut(&key) {
+            entries.remove(entry);
+            if entries.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn items(entries: &OrdSet<Path>) -> Value {
+        let items = entries
+            .iter()
+            .map(|entry| Reference::new(entry.to_string()).to_value())
+            .collect();
+        Value::Map([("items".to_string(), Value::Array(items))].into())
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Map(
+            self.entries
+                .iter()
+                .map(|(key, entries)| (key.clone(), Self::items(entries)))
+                .collect(),
+        )
+    }
+}
+
+/// The indexes of a store. Like the tree they index, clones share their
+/// entries until one of them is written.
+#[derive(Clone, Default)]
+pub(crate) struct Indexes {
+    indexes: Vec<Maintained>,
+}
+
+impl Indexes {
+    /// Start maintaining `index`, building it from `root`.
+    pub(crate) fn add(&mut self, index: Index, root: &Node) -> Result<(), Error> {
+        if self.indexes.iter().any(|m| m.index.name == index.name) {
+            return Err(Error::store(
+                "json_store",
+                "index",
+                format!("Index '{}' is defined twice", index.name),
+            ));
+        }
+        let mut maintained = Maintained {
+            index,
+            entries: OrdMap::new(),
+            keys: OrdMap::new(),
+        };
+        maintained.refresh(root, &Path::from_components(vec![]));
+        self.indexes.push(maintained);
+        Ok(())
+    }
+
+    /// Whether `path` is served by the indexes rather than the tree.
+    pub(crate) fn serves(&self, path: &Path) -> bool {
+        !self.indexes.is_empty() && path.iter().next().is_some_and(|c| c == INDEXES_PATH)
+    }
+
+    /// Update every index after the subtree at `to` was written.
+    pub(crate) fn written(&mut self, root: &Node, to: &Path) {
+        for maintained in &mut self.indexes {
+            maintained.refresh(root, to);
+        }
+    }
+
+    /// Update every index after the subtree at `at` was removed. Removing
+    /// an array element renumbers its siblings, so the parent is rescanned.
+    pub(crate) fn deleted(&mut self, root: &Node, at: &Path) {
+        let parent = at.slice(0, at.len().saturating_sub(1));
+        self.written(root, &parent);
+    }
+
+    /// The value at `path`, which starts with [`INDEXES_PATH`].
+    pub(crate) fn read(&self, path: &Path) -> Option<Value> {
+        let find = |name: &String| self.indexes.iter().find(|m| &m.index.name == name);
+        match &path.components[1..] {
+            [] => Some(Value::Map(
+                self.indexes
+                    .iter()
+                    .map(|m| (m.index.name.clone(), m.to_value()))
+                    .collect(),
+            )),
+            [name] => find(name).map(Maintained::to_value),
+            [name, key] => find(name)?.entries.get(key).map(Maintained::items),
+            _ => None,
+        }
+    }
+
+    /// Names of the children of `path`, which starts with [`INDEXES_PATH`].
+    pub(crate) fn list(&self, path: &Path) -> Option<Vec<String>> {
+        let find = |name: &String| self.indexes.iter().find(|m| &m.index.name == name);
+        match &path.components[1..] {
+            [] => Some(self.indexes.iter().map(|m| m.index.name.clone()).collect()),
+            [name] => find(name).map(|m| m.entries.keys().cloned().collect()),
+            [name, key] => find(name)?.entries.get(key).map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|e| e.components.last().cloned())
+                    .collect()
+            }),
+            _ => None,
+        }
+    }
+
+    /// The error for writing to an index.
+    pub(crate) fn read_only(path: &Path) -> Error {
+        Error::store(
+            "json_store",
+            "write",
+            format!("Indexes are read-only: cannot write '{}'", path),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use structfs_core_store::path;
+
+    fn user(email: &str) -> Value {
+        Value::Map(BTreeMap::from([("email".to_string(), Value::from(email))]))
+    }
+
+    fn users(root: &mut Node, entries: &[(&str, &str)]) {
+        let map = entries
+            .iter()
+            .map(|(name, email)| (name.to_string(), user(email)))
+            .collect();
+        root.set(&path!("users"), Value::Map(map).into()).unwrap();
+    }
+
+    fn lookup(indexes: &Indexes, email: &str) -> Vec<String> {
+        let key = Index::key(&Value::from(email)).unwrap();
+        let path = Path::from_components(vec!["indexes".into(), "by_email".into(), key]);
+        indexes.list(&path).unwrap_or_default()
+    }
+
+    #[test]
+    fn definitions_are_validated() {
+        assert!(Index::new("by_email", "users/*", "email").is_ok());
+        assert!(Index::new("by/email", "users/*", "email").is_err());
+        assert!(Index::new("by_email", "", "email").is_err());
+        assert!(Index::new("by_email", "users/*", "").is_err());
+        assert!(Index::new("by_email", "users/a-b", "email").is_err());
+    }
+
+    #[test]
+    fn keys_are_text_encoded_as_components() {
+        assert_eq!(Index::key(&Value::from("alice")).as_deref(), Some("alice"));
+        assert_eq!(
+            Index::key(&Value::Integer(1)),
+            Index::key(&Value::from("1"))
+        );
+        assert_eq!(Index::key(&Value::from("")), None);
+        assert_eq!(Index::key(&Value::Null), None);
+        let key = Index::key(&Value::from("a@example.com")).unwrap();
+        assert!(Path::parse(&key).is_ok_and(|p| p.len() == 1));
+    }
+
+    #[test]
+    fn maintained_across_writes() {
+        let mut root = Node::default();
+        users(&mut root, &[("alice", "a@x.org")]);
+
+        let mut indexes = Indexes::default();
+        indexes
+            .add(Index::new("by_email", "users/*", "email").unwrap(), &root)
+            .unwrap();
+        assert_eq!(lookup(&indexes, "a@x.org"), ["alice"]);
+
+        // An entry's field
+        root.set(&path!("users/alice/email"), Value::from("b@x.org").into())
+            .unwrap();
+        indexes.written(&root, &path!("users/alice/email"));
+        assert!(lookup(&indexes, "a@x.org").is_empty());
+        assert_eq!(lookup(&indexes, "b@x.org"), ["alice"]);
+
+        // A whole entry
+        root.set(&path!("users/bob"), user("b@x.org").into())
+            .unwrap();
+        indexes.written(&root, &path!("users/bob"));
+        assert_eq!(lookup(&indexes, "b@x.org"), ["alice", "bob"]);
+
+        // The whole collection
+        users(&mut root, &[("carol", "c@x.org")]);
+        indexes.written(&root, &path!("users"));
+        assert!(lookup(&indexes, "b@x.org").is_empty());
+        assert_eq!(lookup(&indexes, "c@x.org"), ["carol"]);
+
+        // Unrelated writes leave it alone
+        root.set(&path!("other"), user("c@x.org").into()).unwrap();
+        indexes.written(&root, &path!("other"));
+        assert_eq!(lookup(&indexes, "c@x.org"), ["carol"]);
+
+        root.remove(&path!("users/carol")).unwrap();
+        indexes.deleted(&root, &path!("users/carol"));
+        assert!(lookup(&indexes, "c@x.org").is_empty());
+        assert_eq!(
+            indexes.read(&path!("indexes/by_email")),
+            Some(Value::Map(BTreeMap::new()))
+        );
+    }
+
+    #[test]
+    fn array_entries_are_renumbered_on_delete() {
+        let mut root = Node::from(Value::Map(BTreeMap::from([(
+            "users".to_string(),
+            Value::Array(vec![user("a@x.org"), user("b@x.org")]),
+        )])));
+        let mut indexes = Indexes::default();
+        indexes
+            .add(Index::new("by_email", "users/*", "email").unwrap(), &root)
+            .unwrap();
+        assert_eq!(lookup(&indexes, "b@x.org"), ["1"]);
+
+        root.remove(&path!("users/0")).unwrap();
+        indexes.deleted(&root, &path!("users/0"));
+        assert!(lookup(&indexes, "a@x.org").is_empty());
+        assert_eq!(lookup(&indexes, "b@x.org"), ["0"]);
+    }
+}
@@ -2,10 +2,22 @@
 //!
 //! JSON-based StructFS store implementations.
 //!
-//! This crate provides in-memory store implementations for StructFS.
+//! This crate provides in-memory store implementations for StructFS, and
+//! [`JSONLocalStore`], which persists a tree to a JSON file with optional
+//! write batching and per-subtree shard files. Both can keep secondary
+//! [`Index`]es over map fields, and an [`InMemoryStore`] can be bounded
+//! with an [`Eviction`](eviction::Eviction) policy to serve as a cache.
 
+pub mod eviction;
 pub mod in_memory;
+pub mod index;
+pub mod local;
+mod persistent;
+pub mod shard;
 pub mod value_utils;
 
 pub use in_memory::InMemoryStore;
+pub use index::{Index, INDEXES_PATH};
+pub use local::{JSONLocalStore, WriteBatching};
+pub use shard::SplitPoint;
 pub use structfs_core_store::{path, Error, Path, Reader, Record, Value, Writer};
@@ -0,0 +1,840 @@
+//! A JSON store persisted to a file on disk.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+use structfs_serde_store::{json_to_value, value_to_json};
+
+use crate::index::{Index, Indexes};
+use crate::persistent::Node;
+use crate::shard::{self, SplitPoint};
+
+/// Path that flushes pending writes when written and reports batching
+/// status when read.
+pub const FLUSH_PATH: &str = "_flush";
+
+/// When a [`JSONLocalStore`] writes buffered changes to disk.
+///
+/// Pending writes are flushed once there are `max_writes` of them, or once
+/// the oldest has waited `max_delay`, whichever comes first. The default
+/// flushes after every write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBatching {
+    /// Flush when this many writes are pending.
+    pub max_writes: usize,
+    /// Flush when the oldest pending write is this old. `None` waits for
+    /// `max_writes` or an explicit flush.
+    pub max_delay: Option<Duration>,
+}
+
+impl WriteBatching {
+    /// Flush after every write.
+    pub const IMMEDIATE: Self = Self {
+        max_writes: 1,
+        max_delay: None,
+    };
+
+    /// Flush every `max_writes` writes or every `max_delay`.
+    pub fn every(max_writes: usize, max_delay: Duration) -> Self {
+        Self {
+            max_writes: max_writes.max(1),
+            max_delay: Some(max_delay),
+        }
+    }
+}
+
+impl Default for WriteBatching {
+    fn default() -> Self {
+        Self::IMMEDIATE
+    }
+}
+
+struct State {
+    root: Node,
+    splits: Vec<SplitPoint>,
+    indexes: Indexes,
+    /// Files that no longer match the tree
+    dirty: Dirty,
+    /// Writes applied in memory but not yet on disk
+    pending: usize,
+    /// When the oldest pending write was made
+    dirty_since: Option<Instant>,
+    flushes: u64,
+    last_error: Option<String>,
+}
+
+impl State {
+    /// Record that the subtree at `to` was replaced.
+    fn mark(&mut self, to: &Path) {
+        if let Some(shard) = self.splits.iter().find_map(|s| s.shard_of(to)) {
+            self.dirty.shards.insert(shard);
+            return;
+        }
+        self.dirty.main = true;
+        if self.splits.iter().any(|s| s.extends(to)) {
+            self.dirty.rescans.push(to.clone());
+        }
+    }
+}
+
+/// Which files a flush rewrites.
+#[derive(Debug, Default)]
+struct Dirty {
+    main: bool,
+    shards: BTreeSet<Path>,
+    /// Subtrees written wholesale: every shard below them is rewritten
+    rescans: Vec<Path>,
+}
+
+impl Dirty {
+    fn merge(&mut self, other: Dirty) {
+        self.main |= other.main;
+        self.shards.extend(other.shards);
+        self.rescans.extend(other.rescans);
+    }
+}
+
+struct Shared {
+    file: PathBuf,
+    state: Mutex<State>,
+    /// Shard files on disk. Held while a snapshot is written, so flushes
+    /// land in order
+    io: Mutex<BTreeSet<Path>>,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn shard_dir(&self) -> PathBuf {
+        let mut dir = self.file.as_os_str().to_owned();
+        dir.push(".shards");
+        PathBuf::from(dir)
+    }
+
+    /// Graft the shard files on disk into the loaded main file's tree.
+    fn load_shards(&mut self) -> Result<(), Error> {
+        let entries = match fs::read_dir(self.shard_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut shards = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if let Some(path) = entry.file_name().to_str().and_then(shard::parse_file_name) {
+                shards.push((path, entry.path()));
+            }
+        }
+        // Parents first, in case the layout changed to nest them
+        shards.sort_by_key(|(path, _)| path.len());
+
+        let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        let on_disk = self.io.get_mut().unwrap_or_else(|e| e.into_inner());
+        for (path, file) in shards {
+            state.root.set(&path, read_json(&file)?.into())?;
+            on_disk.insert(path);
+        }
+        Ok(())
+    }
+
+    /// Write the current tree to disk if anything is pending.
+    ///
+    /// The tree is snapshotted with an O(1) clone, so writers are only
+    /// blocked while the snapshot is taken, not during serialize and fsync.
+    fn flush(&self) -> Result<(), Error> {
+        let mut on_disk = self.io.lock().unwrap_or_else(|e| e.into_inner());
+        let (snapshot, splits, dirty, pending) = {
+            let mut state = self.state();
+            if state.pending == 0 {
+                return Ok(());
+            }
+            (
+                state.root.clone(),
+                state.splits.clone(),
+                std::mem::take(&mut state.dirty),
+                state.pending,
+            )
+        };
+
+        let result = self.write_files(&snapshot, &splits, &dirty, &mut on_disk);
+        let mut state = self.state();
+        match result {
+            Ok(()) => {
+                // Writes made during the flush stay pending
+                state.pending -= pending;
+                if state.pending == 0 {
+                    state.dirty_since = None;
+                }
+                state.flushes += 1;
+                state.last_error = None;
+                Ok(())
+            }
+            Err(e) => {
+                state.dirty.merge(dirty);
+                state.last_error = Some(e.to_string());
+                Err(Error::store(
+                    "json_local",
+                    "flush",
+                    format!("Failed to write '{}': {}", self.file.display(), e),
+                ))
+            }
+        }
+    }
+
+    /// Rewrite the files `dirty` names from `root`.
+    ///
+    /// Shards are written before the main file, whose copy of each shard
+    /// is replaced by `null`. Shard files that no longer hold a shard are
+    /// removed and their contents written to the main file instead.
+    fn write_files(
+        &self,
+        root: &Node,
+        splits: &[SplitPoint],
+        dirty: &Dirty,
+        on_disk: &mut BTreeSet<Path>,
+    ) -> std::io::Result<()> {
+        let instances: BTreeSet<Path> = splits.iter().flat_map(|s| s.instances(root)).collect();
+        let mut shards = dirty.shards.clone();
+        for prefix in &dirty.rescans {
+            shards.extend(instances.iter().filter(|p| p.has_prefix(prefix)).cloned());
+            shards.extend(on_disk.iter().filter(|p| p.has_prefix(prefix)).cloned());
+        }
+        if dirty.main {
+            shards.extend(on_disk.difference(&instances).cloned());
+        }
+
+        let dir = self.shard_dir();
+        let mut main = dirty.main;
+        for path in &shards {
+            let file = dir.join(shard::file_name(path));
+            match root.get(path).ok().flatten() {
+                Some(node) if instances.contains(path) => {
+                    fs::create_dir_all(&dir)?;
+                    persist(&file, &node.to_value())?;
+                    on_disk.insert(path.clone());
+                }
+                _ => {
+                    match fs::remove_file(&file) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e),
+                    }
+                    on_disk.remove(path);
+                    main = true;
+                }
+            }
+        }
+
+        if main {
+            let mut rest = root.clone();
+            for path in &instances {
+                // Instances exist in the tree, so setting them cannot fail
+                let _ = rest.set(path, Node::default());
+            }
+            persist(&self.file, &rest.to_value())?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a JSON file, treating an empty file as `null`.
+fn read_json(file: &FsPath) -> Result<Value, Error> {
+    let bytes = fs::read(file)?;
+    if bytes.is_empty() {
+        return Ok(Value::Null);
+    }
+    let json = serde_json::from_slice(&bytes).map_err(|e| {
+        Error::store(
+            "json_local",
+            "open",
+            format!("'{}' is not valid JSON: {}", file.display(), e),
+        )
+    })?;
+    Ok(json_to_value(json))
+}
+
+/// Atomically replace `file` with the JSON encoding of `value`.
+fn persist(file: &FsPath, value: &Value) -> std::io::Result<()> {
+    let bytes = serde_json::to_vec(&value_to_json(value.clone()))?;
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut out = File::create(&tmp)?;
+    out.write_all(&bytes)?;
+    out.sync_all()?;
+    fs::rename(&tmp, file)
+}
+
+/// A JSON store that keeps its whole tree in one file.
+///
+/// Reads are served from memory. Writes apply in memory at once and reach
+/// disk according to the store's [`WriteBatching`]: by default every write
+/// rewrites and fsyncs the file, while a batched store combines many writes
+/// into one flush. Write-heavy Blocks should batch:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use structfs_json_store::{JSONLocalStore, WriteBatching};
+///
+/// // Flush every 100 writes or 50ms, whichever comes first
+/// let store = JSONLocalStore::open("state.json")?
+///     .with_batching(WriteBatching::every(100, Duration::from_millis(50)));
+/// # Ok::<(), structfs_core_store::Error>(())
+/// ```
+///
+/// Pending writes are flushed when the store is dropped, by
+/// [`flush`](Self::flush), or by writing to the [`_flush`](FLUSH_PATH)
+/// control path. Reading `_flush` returns `{pending_writes, flushes,
+/// last_error}`. A failed flush keeps its writes pending for the next
+/// attempt; if it was triggered by a write, that write returns the error.
+///
+/// The file is replaced atomically through a temporary file next to it, so
+/// a crash leaves either the old or the new tree, never a mix.
+///
+/// # Sharding
+///
+/// A flush rewrites the whole file, which gets slow once the tree is large.
+/// [Split points](SplitPoint) move subtrees into files of their own under
+/// a `<file>.shards` directory, so a write below a split point rewrites
+/// only that subtree's file:
+///
+/// ```rust,no_run
+/// use structfs_json_store::JSONLocalStore;
+///
+/// // Each user gets a file such as state.json.shards/users.alice.json
+/// let store = JSONLocalStore::open("state.json")?.with_split_points(["users/*"])?;
+/// # Ok::<(), structfs_core_store::Error>(())
+/// ```
+///
+/// Reads and writes are routed transparently. Shard files are loaded on
+/// open whatever the configured split points, and a store opened with
+/// different split points moves data between files as it is written;
+/// [`migrate`](Self::migrate) rewrites everything at once. Each file is
+/// replaced atomically, but a crash during a flush that touches several
+/// files can leave some of them updated and others not.
+///
+/// [`with_index`](Self::with_index) adds secondary indexes, served under
+/// `indexes/` (see [`crate::index`]).
+pub struct JSONLocalStore {
+    shared: Arc<Shared>,
+    batching: WriteBatching,
+    flush_path: Path,
+}
+
+impl JSONLocalStore {
+    /// Open the store at `file`, loading its contents if it exists.
+    pub fn open(file: impl Into<PathBuf>) -> Result<Self, Error> {
+        let file = file.into();
+        let root = match read_json(&file) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Value::Null,
+            result => result?,
+        };
+
+        let mut shared = Shared {
+            file,
+            state: Mutex::new(State {
+                root: root.into(),
+                splits: Vec::new(),
+                indexes: Indexes::default(),
+                dirty: Dirty::default(),
+                pending: 0,
+                dirty_since: None,
+                flushes: 0,
+                last_error: None,
+            }),
+            io: Mutex::new(BTreeSet::new()),
+        };
+        shared.load_shards()?;
+
+        Ok(Self {
+            shared: Arc::new(shared),
+            batching: WriteBatching::IMMEDIATE,
+            flush_path: Path::parse(FLUSH_PATH).unwrap(),
+        })
+    }
+
+    /// Store subtrees matching `patterns` in their own files.
+    ///
+    /// Fails if a pattern is invalid or two patterns could name nested
+    /// subtrees.
+    pub fn with_split_points<I>(self, patterns: I) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let splits = patterns
+            .into_iter()
+            .map(|p| SplitPoint::parse(p.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, a) in splits.iter().enumerate() {
+            if let Some(b) = splits[i + 1..].iter().find(|b| a.overlaps(b)) {
+                return Err(Error::store(
+                    "json_local",
+                    "split",
+                    format!("Split points '{}' and '{}' overlap", a, b),
+                ));
+            }
+        }
+        self.shared.state().splits = splits;
+        Ok(self)
+    }
+
+    /// Maintain `index` on every write, starting from the loaded data.
+    ///
+    /// Indexes are kept in memory only and rebuilt each time the store is
+    /// opened. Fails if an index with the same name was already added.
+    pub fn with_index(self, index: Index) -> Result<Self, Error> {
+        {
+            let mut state = self.shared.state();
+            let State { root, indexes, .. } = &mut *state;
+            indexes.add(index, root)?;
+        }
+        Ok(self)
+    }
+
+    /// Rewrite the store at `file` so its shard files match `patterns`.
+    ///
+    /// Converts a single-file store to a sharded one, moves data between
+    /// shards when split points change, and with no patterns folds every
+    /// shard back into the main file. Returns the number of shard files.
+    pub fn migrate<I>(file: impl Into<PathBuf>, patterns: I) -> Result<usize, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut store = Self::open(file)?.with_split_points(patterns)?;
+        {
+            let mut state = store.shared.state();
+            state.dirty.main = true;
+            state.dirty.rescans.push(Path::parse("").unwrap());
+            state.pending += 1;
+        }
+        store.flush()?;
+        let shards = store
+            .shared
+            .io
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len();
+        Ok(shards)
+    }
+
+    /// Combine writes into flushes according to `batching`.
+    ///
+    /// With a `max_delay`, a background thread flushes pending writes once
+    /// they are that old. It stops when the store is dropped.
+    pub fn with_batching(mut self, batching: WriteBatching) -> Self {
+        self.batching = WriteBatching {
+            max_writes: batching.max_writes.max(1),
+            ..batching
+        };
+        if let Some(max_delay) = batching.max_delay {
+            spawn_flusher(Arc::downgrade(&self.shared), max_delay);
+        }
+        self
+    }
+
+    /// The file backing this store.
+    pub fn file(&self) -> &FsPath {
+        &self.shared.file
+    }
+
+    /// The directory holding shard files, `<file>.shards`.
+    pub fn shard_dir(&self) -> PathBuf {
+        self.shared.shard_dir()
+    }
+
+    /// Number of writes not yet on disk.
+    pub fn pending_writes(&self) -> usize {
+        self.shared.state().pending
+    }
+
+    /// Write all pending changes to disk.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.shared.flush()
+    }
+
+    fn status(&self) -> Value {
+        let state = self.shared.state();
+        Value::Map(BTreeMap::from([
+            (
+                "pending_writes".to_string(),
+                Value::Integer(state.pending as i64),
+            ),
+            ("flushes".to_string(), Value::Integer(state.flushes as i64)),
+            (
+                "last_error".to_string(),
+                state.last_error.clone().map_or(Value::Null, Value::String),
+            ),
+        ]))
+    }
+}
+
+fn spawn_flusher(shared: Weak<Shared>, max_delay: Duration) {
+    thread::spawn(move || loop {
+        let wait = {
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            let dirty_since = shared.state().dirty_since;
+            match dirty_since {
+                Some(since) if since.elapsed() >= max_delay => {
+                    // Errors are kept in the status and retried next time
+                    let _ = shared.flush();
+                    max_delay
+                }
+                Some(since) => max_delay - since.elapsed(),
+                None => max_delay,
+            }
+        };
+        thread::sleep(wait.max(Duration::from_millis(1)));
+    });
+}
+
+impl Reader for JSONLocalStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        if *from == self.flush_path {
+            return Ok(Some(Record::parsed(self.status())));
+        }
+        let state = self.shared.state();
+        if state.indexes.serves(from) {
+            return Ok(state.indexes.read(from).map(Record::parsed));
+        }
+        Ok(state
+            .root
+            .get(from)?
+            .map(|node| Record::parsed(node.to_value())))
+    }
+}
+
+impl Writer for JSONLocalStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if *to == self.flush_path {
+            self.flush()?;
+            return Ok(to.clone());
+        }
+
+        let value = data.into_value(&NoCodec)?;
+        let due = {
+            let mut state = self.shared.state();
+            if state.indexes.serves(to) {
+                return Err(Indexes::read_only(to));
+            }
+            let State { root, indexes, .. } = &mut *state;
+            root.set(to, value.into())?;
+            indexes.written(root, to);
+            state.mark(to);
+            state.pending += 1;
+            state.dirty_since.get_or_insert_with(Instant::now);
+            state.pending >= self.batching.max_writes
+        };
+        if due {
+            self.flush()?;
+        }
+        Ok(to.clone())
+    }
+}
+
+impl Drop for JSONLocalStore {
+    fn drop(&mut self) {
+        let _ = self.shared.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn on_disk(file: &FsPath) -> Value {
+        json_to_value(serde_json::from_slice(&fs::read(file).unwrap()).unwrap())
+    }
+
+    fn int(n: i64) -> Record {
+        Record::parsed(Value::Integer(n))
+    }
+
+    #[test]
+    fn persists_and_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        store.write(&path!("count"), int(1)).unwrap();
+        assert_eq!(store.pending_writes(), 0);
+        assert_eq!(
+            on_disk(&file),
+            Value::Map(BTreeMap::from([("count".to_string(), Value::Integer(1))]))
+        );
+        drop(store);
+
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        let record = store.read(&path!("count")).unwrap().unwrap();
+        assert_eq!(record.into_value(&NoCodec).unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn batches_by_count_and_flush_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_batching(WriteBatching {
+                max_writes: 3,
+                max_delay: None,
+            });
+
+        store.write(&path!("a"), int(1)).unwrap();
+        store.write(&path!("b"), int(2)).unwrap();
+        assert!(!file.exists());
+        assert_eq!(store.pending_writes(), 2);
+        // Unflushed writes are still readable
+        assert!(store.read(&path!("b")).unwrap().is_some());
+
+        store.write(&path!("c"), int(3)).unwrap();
+        assert_eq!(store.pending_writes(), 0);
+        assert!(file.exists());
+
+        store.write(&path!("d"), int(4)).unwrap();
+        let status = store.read(&path!("_flush")).unwrap().unwrap();
+        let status = status.into_value(&NoCodec).unwrap();
+        assert!(matches!(&status, Value::Map(m) if m["pending_writes"] == Value::Integer(1)));
+
+        store
+            .write(&path!("_flush"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(matches!(on_disk(&file), Value::Map(m) if m.len() == 4));
+    }
+
+    #[test]
+    fn batches_by_delay() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_batching(WriteBatching::every(1000, Duration::from_millis(20)));
+
+        store.write(&path!("a"), int(1)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while store.pending_writes() > 0 {
+            assert!(Instant::now() < deadline, "background flush never ran");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(matches!(on_disk(&file), Value::Map(m) if m.len() == 1));
+    }
+
+    #[test]
+    fn drop_flushes_and_failures_stay_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_batching(WriteBatching {
+                max_writes: 100,
+                max_delay: None,
+            });
+        store.write(&path!("a"), int(1)).unwrap();
+        drop(store);
+        assert!(matches!(on_disk(&file), Value::Map(m) if m.len() == 1));
+
+        let mut store = JSONLocalStore::open(dir.path().join("missing/state.json")).unwrap();
+        assert!(store.write(&path!("a"), int(1)).is_err());
+        assert_eq!(store.pending_writes(), 1);
+        assert!(store.flush().is_err());
+    }
+
+    fn users() -> Value {
+        let user = |n| Value::Map(BTreeMap::from([("n".to_string(), Value::Integer(n))]));
+        Value::Map(BTreeMap::from([
+            ("alice".to_string(), user(1)),
+            ("bob".to_string(), user(2)),
+        ]))
+    }
+
+    fn read(store: &mut JSONLocalStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn shard_writes_rewrite_only_their_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_split_points(["users/*"])
+            .unwrap();
+        store
+            .write(&path!("users"), Record::parsed(users()))
+            .unwrap();
+        store.write(&path!("count"), int(2)).unwrap();
+
+        let shards = store.shard_dir();
+        assert_eq!(
+            on_disk(&shards.join("users.bob.json")),
+            Value::Map(BTreeMap::from([("n".to_string(), Value::Integer(2))]))
+        );
+        assert!(matches!(
+            on_disk(&file),
+            Value::Map(m) if m["users"] == Value::Map(BTreeMap::from([
+                ("alice".to_string(), Value::Null),
+                ("bob".to_string(), Value::Null),
+            ]))
+        ));
+
+        // Neither the main file nor other shards are touched
+        fs::remove_file(&file).unwrap();
+        fs::remove_file(shards.join("users.bob.json")).unwrap();
+        store.write(&path!("users/alice/n"), int(5)).unwrap();
+        store.write(&path!("users/carol"), int(3)).unwrap();
+        assert!(!file.exists());
+        assert!(!shards.join("users.bob.json").exists());
+        assert_eq!(on_disk(&shards.join("users.carol.json")), Value::Integer(3));
+    }
+
+    #[test]
+    fn shards_reload_and_follow_parent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_split_points(["users/*"])
+            .unwrap();
+        store
+            .write(&path!("users"), Record::parsed(users()))
+            .unwrap();
+        store.write(&path!("users/bob/n"), int(7)).unwrap();
+        drop(store);
+
+        // Shards are loaded even without configuring split points
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        assert_eq!(
+            read(&mut store, &path!("users/bob/n")),
+            Some(Value::Integer(7))
+        );
+        drop(store);
+
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_split_points(["users/*"])
+            .unwrap();
+        let mut only_alice = users();
+        if let Value::Map(m) = &mut only_alice {
+            m.remove("bob");
+        }
+        store
+            .write(&path!("users"), Record::parsed(only_alice))
+            .unwrap();
+        assert!(!store.shard_dir().join("users.bob.json").exists());
+        drop(store);
+
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        assert_eq!(read(&mut store, &path!("users/bob")), None);
+        assert_eq!(
+            read(&mut store, &path!("users/alice/n")),
+            Some(Value::Integer(1))
+        );
+    }
+
+    #[test]
+    fn migrate_between_layouts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        store
+            .write(&path!(""), Record::parsed(Value::Map(BTreeMap::new())))
+            .unwrap();
+        store
+            .write(&path!("users"), Record::parsed(users()))
+            .unwrap();
+        drop(store);
+        let before = on_disk(&file);
+
+        assert_eq!(JSONLocalStore::migrate(&file, ["users/*"]).unwrap(), 2);
+        assert_ne!(on_disk(&file), before);
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        assert_eq!(read(&mut store, &path!("")), Some(before.clone()));
+        drop(store);
+
+        assert_eq!(JSONLocalStore::migrate(&file, [] as [&str; 0]).unwrap(), 0);
+        assert_eq!(on_disk(&file), before);
+        assert_eq!(
+            fs::read_dir(dir.path().join("state.json.shards"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn indexes_rebuild_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let by_n = || Index::new("by_n", "users/*", "n").unwrap();
+        let mut store = JSONLocalStore::open(&file).unwrap();
+        store
+            .write(&path!("users"), Record::parsed(users()))
+            .unwrap();
+        drop(store);
+
+        let mut store = JSONLocalStore::open(&file)
+            .unwrap()
+            .with_index(by_n())
+            .unwrap();
+        let query = by_n().query_path(&Value::Integer(2)).unwrap();
+        assert!(matches!(
+            read(&mut store, &query),
+            Some(Value::Map(m)) if m["items"] == Value::Array(vec![
+                structfs_core_store::Reference::new("users/bob").to_value(),
+            ])
+        ));
+
+        store.write(&path!("users/bob/n"), int(3)).unwrap();
+        assert_eq!(read(&mut store, &query), None);
+        assert!(store.write(&query, int(1)).is_err());
+        assert!(JSONLocalStore::open(&file)
+            .unwrap()
+            .with_index(by_n())
+            .unwrap()
+            .with_index(by_n())
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_overlapping_split_points() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JSONLocalStore::open(dir.path().join("state.json")).unwrap();
+        assert!(store.with_split_points(["users", "users/*/posts"]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        fs::write(&file, "{not json").unwrap();
+        assert!(JSONLocalStore::open(&file).is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JSONLocalStore::open(dir.path().join("state.json")).unwrap();
+        conformance::assert_missing(&mut store, &path!("missing"));
+        conformance::assert_round_trip(&mut store, &path!("count"), Value::Integer(1));
+        conformance::assert_missing(&mut store, &path!("count/child"));
+        conformance::assert_found(&mut store, &path!("_flush"));
+    }
+}
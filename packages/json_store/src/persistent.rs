@@ -0,0 +1,330 @@
+//! A `Value` tree with structurally shared containers.
+//!
+//! Maps and arrays are persistent collections, so cloning a tree is O(1) and
+//! a write copies only the nodes along the written path. Leaves are shared
+//! behind an `Arc` so those copies never duplicate strings or bytes.
+
+use std::sync::Arc;
+
+use im::{OrdMap, Vector};
+use structfs_core_store::{Error, Path, PathError, Value};
+
+/// A node in a persistent value tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Any value that is not a map or an array.
+    Leaf(Arc<Value>),
+    /// Key-value map with string keys.
+    Map(OrdMap<String, Node>),
+    /// Ordered sequence of nodes.
+    Array(Vector<Node>),
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::Leaf(Arc::new(Value::Null))
+    }
+}
+
+impl From<Value> for Node {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Map(map) => {
+                Node::Map(map.into_iter().map(|(k, v)| (k, Node::from(v))).collect())
+            }
+            Value::Array(arr) => Node::Array(arr.into_iter().map(Node::from).collect()),
+            other => Node::Leaf(Arc::new(other)),
+        }
+    }
+}
+
+impl Node {
+    /// Materialize this subtree as a plain `Value`.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Node::Leaf(value) => (**value).clone(),
+            Node::Map(map) => {
+                Value::Map(map.iter().map(|(k, v)| (k.clone(), v.to_value())).collect())
+            }
+            Node::Array(arr) => Value::Array(arr.iter().map(Node::to_value).collect()),
+        }
+    }
+
+    /// Get the subtree at `path`, with the same rules as [`crate::value_utils::get_path`].
+    pub fn get(&self, path: &Path) -> Result<Option<&Node>, Error> {
+        let mut cursor = self;
+        for (i, component) in path.iter().enumerate() {
+            cursor = match cursor {
+                Node::Map(map) => match map.get(component.as_str()) {
+                    Some(next) => next,
+                    None => return Ok(None),
+                },
+                Node::Array(arr) => match arr.get(parse_index(component, i)?) {
+                    Some(next) => next,
+                    None => return Ok(None),
+                },
+                Node::Leaf(_) => return Ok(None),
+            };
+        }
+        Ok(Some(cursor))
+    }
+
+    /// Names of the children of the subtree at `path`: map keys in order,
+    /// or array indices.
+    pub fn children(&self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        Ok(self.get(path)?.map(|node| match node {
+            Node::Map(map) => map.keys().cloned().collect(),
+            Node::Array(arr) => (0..arr.len()).map(|i| i.to_string()).collect(),
+            Node::Leaf(_) => Vec::new(),
+        }))
+    }
+
+    /// Rough number of bytes the subtree holds, the same as the
+    /// [`estimated_size`](Value::estimated_size) of its value.
+    pub fn approximate_size(&self) -> usize {
+        const WORD: usize = std::mem::size_of::<usize>();
+        match self {
+            Node::Leaf(value) => value.estimated_size(),
+            Node::Map(map) => {
+                WORD + map
+                    .iter()
+                    .map(|(k, v)| k.len() + v.approximate_size())
+                    .sum::<usize>()
+            }
+            Node::Array(arr) => WORD + arr.iter().map(Node::approximate_size).sum::<usize>(),
+        }
+    }
+
+    /// Mutable access to the subtree at `path`, copying shared nodes on the way down.
+    fn get_mut(&mut self, path: &Path) -> Result<Option<&mut Node>, Error> {
+        let mut cursor = self;
+        for (i, component) in path.iter().enumerate() {
+            cursor = match cursor {
+                Node::Map(map) => match map.get_mut(component.as_str()) {
+                    Some(next) => next,
+                    None => return Ok(None),
+                },
+                Node::Array(arr) => match arr.get_mut(parse_index(component, i)?) {
+                    Some(next) => next,
+                    None => return Ok(None),
+                },
+                Node::Leaf(_) => return Ok(None),
+            };
+        }
+        Ok(Some(cursor))
+    }
+
+    /// Set the subtree at `path`, with the same rules as [`crate::value_utils::set_path`].
+    pub fn set(&mut self, path: &Path, node: Node) -> Result<(), Error> {
+        let Some(last) = path.len().checked_sub(1) else {
+            *self = node;
+            return Ok(());
+        };
+
+        let parent_path = path.slice(0, last);
+        let parent = self.get_mut(&parent_path)?.ok_or_else(|| {
+            Error::Path(PathError::InvalidPath {
+                message: format!("Parent path '{}' does not exist", parent_path),
+            })
+        })?;
+        parent.set_child(&path[last], node, last)
+    }
+
+    /// Remove the subtree at `path`, returning whether there was one.
+    ///
+    /// Removing an array element shifts the elements after it down. Removing
+    /// the root leaves an empty (null) tree.
+    pub fn remove(&mut self, path: &Path) -> Result<bool, Error> {
+        let Some(last) = path.len().checked_sub(1) else {
+            *self = Node::default();
+            return Ok(true);
+        };
+
+        let Some(parent) = self.get_mut(&path.slice(0, last))? else {
+            return Ok(false);
+        };
+        match parent {
+            Node::Map(map) => Ok(map.remove(path[last].as_str()).is_some()),
+            Node::Array(arr) => {
+                let index = parse_index(&path[last], last)?;
+                if index < arr.len() {
+                    arr.remove(index);
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Node::Leaf(_) => Ok(false),
+        }
+    }
+
+    fn set_child(&mut self, key: &str, node: Node, position: usize) -> Result<(), Error> {
+        match self {
+            Node::Map(map) => {
+                map.insert(key.to_string(), node);
+                Ok(())
+            }
+            Node::Array(arr) => {
+                let index = parse_index(key, position)?;
+                if index < arr.len() {
+                    arr.set(index, node);
+                } else if index == arr.len() {
+                    arr.push_back(node);
+                } else {
+                    return Err(Error::Path(PathError::InvalidComponent {
+                        component: key.to_string(),
+                        position,
+                        message: format!("Array index {} out of bounds (len={})", index, arr.len()),
+                    }));
+                }
+                Ok(())
+            }
+            Node::Leaf(value) if **value == Value::Null => {
+                // Auto-create a map for null values
+                *self = Node::Map(OrdMap::unit(key.to_string(), node));
+                Ok(())
+            }
+            Node::Leaf(_) => Err(Error::Path(PathError::InvalidPath {
+                message: format!("Cannot set child '{}' on primitive value", key),
+            })),
+        }
+    }
+}
+
+fn parse_index(component: &str, position: usize) -> Result<usize, Error> {
+    component.parse::<usize>().map_err(|e| {
+        Error::Path(PathError::InvalidComponent {
+            component: component.to_string(),
+            position,
+            message: format!("Expected array index, got: {}", e),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use structfs_core_store::path;
+
+    fn sample() -> Value {
+        Value::Map(BTreeMap::from([
+            ("name".to_string(), Value::from("Alice")),
+            (
+                "scores".to_string(),
+                Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn value_roundtrip() {
+        let value = sample();
+        assert_eq!(Node::from(value.clone()).to_value(), value);
+    }
+
+    #[test]
+    fn get_matches_value_utils() {
+        let value = sample();
+        let node = Node::from(value.clone());
+        for p in [
+            path!(""),
+            path!("name"),
+            path!("scores/1"),
+            path!("scores/5"),
+            path!("name/x"),
+        ] {
+            let expected = crate::value_utils::get_path(&value, &p).unwrap().cloned();
+            assert_eq!(node.get(&p).unwrap().map(Node::to_value), expected);
+        }
+        assert!(node.get(&path!("scores/x")).is_err());
+    }
+
+    #[test]
+    fn set_matches_value_utils() {
+        let cases = [
+            (path!("name"), Value::from("Bob")),
+            (path!("scores/1"), Value::Integer(9)),
+            (path!("scores/2"), Value::Integer(3)),
+            (path!("scores/4"), Value::Integer(3)),
+            (path!("missing/child"), Value::Integer(0)),
+            (path!("name/child"), Value::Integer(0)),
+            (path!(""), Value::Null),
+        ];
+        for (p, v) in cases {
+            let mut value = sample();
+            let mut node = Node::from(value.clone());
+            let expected = crate::value_utils::set_path(&mut value, &p, v.clone());
+            let actual = node.set(&p, v.into());
+            assert_eq!(actual.is_ok(), expected.is_ok(), "path {}", p);
+            assert_eq!(node.to_value(), value, "path {}", p);
+        }
+    }
+
+    #[test]
+    fn set_on_null_creates_map() {
+        let mut node = Node::default();
+        node.set(&path!("a"), Value::Integer(1).into()).unwrap();
+        assert_eq!(
+            node.to_value(),
+            Value::Map(BTreeMap::from([("a".to_string(), Value::Integer(1))]))
+        );
+    }
+
+    #[test]
+    fn remove_drops_keys_and_elements() {
+        let mut node = Node::from(sample());
+        assert!(node.remove(&path!("scores/0")).unwrap());
+        assert!(node.remove(&path!("name")).unwrap());
+        assert!(!node.remove(&path!("name")).unwrap());
+        assert!(!node.remove(&path!("missing/child")).unwrap());
+        assert!(!node.remove(&path!("scores/5")).unwrap());
+        assert!(node.remove(&path!("scores/x")).is_err());
+        assert_eq!(
+            node.to_value(),
+            Value::Map(BTreeMap::from([(
+                "scores".to_string(),
+                Value::Array(vec![Value::Integer(2)])
+            )]))
+        );
+    }
+
+    #[test]
+    fn children_lists_keys_and_indices() {
+        let node = Node::from(sample());
+        assert_eq!(
+            node.children(&path!("")).unwrap().unwrap(),
+            ["name", "scores"]
+        );
+        assert_eq!(
+            node.children(&path!("scores")).unwrap().unwrap(),
+            ["0", "1"]
+        );
+        assert_eq!(node.children(&path!("name")).unwrap(), Some(vec![]));
+        assert_eq!(node.children(&path!("missing")).unwrap(), None);
+    }
+
+    #[test]
+    fn clones_share_until_written() {
+        let original = Node::from(sample());
+        let mut fork = original.clone();
+        fork.set(&path!("name"), Value::from("Bob").into()).unwrap();
+
+        assert_eq!(
+            original.get(&path!("name")).unwrap().unwrap().to_value(),
+            Value::from("Alice")
+        );
+        assert_eq!(
+            fork.get(&path!("name")).unwrap().unwrap().to_value(),
+            Value::from("Bob")
+        );
+        // The untouched array is still the same shared node
+        match (
+            original.get(&path!("scores")).unwrap().unwrap(),
+            fork.get(&path!("scores")).unwrap().unwrap(),
+        ) {
+            (Node::Array(a), Node::Array(b)) => assert!(a.ptr_eq(b)),
+            _ => panic!("expected arrays"),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Split points that give subtrees of a [`JSONLocalStore`] their own files.
+//!
+//! [`JSONLocalStore`]: crate::JSONLocalStore
+
+use std::fmt;
+use std::str::FromStr;
+
+use structfs_core_store::{Error, Path, PathError};
+
+use crate::persistent::Node;
+
+/// A pattern naming subtrees that are stored in their own shard files.
+///
+/// Components are matched literally, except `*`, which matches any one
+/// map key or array index. `users/*` puts each user in its own file;
+/// `users` puts all of them in one file separate from the rest of the tree.
+///
+/// ```rust
+/// use structfs_core_store::path;
+/// use structfs_json_store::SplitPoint;
+///
+/// let split: SplitPoint = "users/*".parse()?;
+/// assert_eq!(split.shard_of(&path!("users/alice/email")), Some(path!("users/alice")));
+/// assert_eq!(split.shard_of(&path!("users")), None);
+/// # Ok::<(), structfs_core_store::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPoint {
+    /// `None` is a `*` wildcard
+    components: Vec<Option<String>>,
+}
+
+impl SplitPoint {
+    /// Parse a pattern such as `users/*`.
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        let components = pattern
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .enumerate()
+            .map(|(i, c)| match c {
+                "*" => Ok(None),
+                _ => Path::parse(c)
+                    .map(|_| Some(c.to_string()))
+                    .map_err(|e| match e {
+                        PathError::InvalidComponent {
+                            component, message, ..
+                        } => PathError::InvalidComponent {
+                            component,
+                            position: i,
+                            message,
+                        },
+                        other => other,
+                    }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if components.is_empty() {
+            return Err(Error::store(
+                "json_local",
+                "split",
+                "The root cannot be a split point",
+            ));
+        }
+        Ok(Self { components })
+    }
+
+    /// Number of components a shard path has.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Always false: the root is not a valid split point.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// The shard containing `path`, if `path` is at or below one.
+    pub fn shard_of(&self, path: &Path) -> Option<Path> {
+        (path.len() >= self.len() && self.matches_start(path)).then(|| path.slice(0, self.len()))
+    }
+
+    /// Whether shards of this pattern can lie strictly below `path`.
+    pub(crate) fn extends(&self, path: &Path) -> bool {
+        path.len() < self.len() && self.matches_start(path)
+    }
+
+    /// Whether one pattern's shards could contain the other's.
+    pub(crate) fn overlaps(&self, other: &SplitPoint) -> bool {
+        self.components
+            .iter()
+            .zip(&other.components)
+            .all(|(a, b)| a.is_none() || b.is_none() || a == b)
+    }
+
+    /// The shards of this pattern present in `root`.
+    pub(crate) fn instances(&self, root: &Node) -> Vec<Path> {
+        let mut found = Vec::new();
+        self.collect(root, &mut Vec::new(), &mut found);
+        found
+    }
+
+    fn collect(&self, node: &Node, prefix: &mut Vec<String>, found: &mut Vec<Path>) {
+        let Some(component) = self.components.get(prefix.len()) else {
+            found.push(Path::from_components(prefix.clone()));
+            return;
+        };
+        let children: Vec<(String, &Node)> = match (node, component) {
+            (Node::Map(map), Some(key)) => {
+                map.get(key).map(|n| (key.clone(), n)).into_iter().collect()
+            }
+            (Node::Map(map), None) => map.iter().map(|(k, n)| (k.clone(), n)).collect(),
+            (Node::Array(arr), Some(key)) => key
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| arr.get(i))
+                .map(|n| (key.clone(), n))
+                .into_iter()
+                .collect(),
+            (Node::Array(arr), None) => arr
+                .iter()
+                .enumerate()
+                .map(|(i, n)| (i.to_string(), n))
+                .collect(),
+            (Node::Leaf(_), _) => Vec::new(),
+        };
+        for (key, child) in children {
+            prefix.push(key);
+            self.collect(child, prefix, found);
+            prefix.pop();
+        }
+    }
+
+    fn matches_start(&self, path: &Path) -> bool {
+        self.components
+            .iter()
+            .zip(path.iter())
+            .all(|(pattern, component)| pattern.as_ref().is_none_or(|p| p == component))
+    }
+}
+
+impl FromStr for SplitPoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for SplitPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<&str> = self
+            .components
+            .iter()
+            .map(|c| c.as_deref().unwrap_or("*"))
+            .collect();
+        write!(f, "{}", parts.join("/"))
+    }
+}
+
+/// File name of the shard at `path`: its components joined with dots.
+///
+/// Path components never contain dots, so the name maps back uniquely.
+pub(crate) fn file_name(path: &Path) -> String {
+    let parts: Vec<&str> = path.iter().map(String::as_str).collect();
+    format!("{}.json", parts.join("."))
+}
+
+/// The shard path named by a shard file, or `None` for other files.
+pub(crate) fn parse_file_name(name: &str) -> Option<Path> {
+    let stem = name.strip_suffix(".json")?;
+    let components = stem.split('.').map(str::to_string).collect();
+    Path::try_from_components(components).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, Value};
+
+    #[test]
+    fn wildcards_match_one_component() {
+        let split = SplitPoint::parse("tenants/*/users").unwrap();
+        assert_eq!(split.to_string(), "tenants/*/users");
+        assert_eq!(
+            split.shard_of(&path!("tenants/acme/users/1")),
+            Some(path!("tenants/acme/users"))
+        );
+        assert_eq!(split.shard_of(&path!("tenants/acme/groups")), None);
+        assert!(split.extends(&path!("tenants/acme")));
+        assert!(!split.extends(&path!("tenants/acme/users")));
+
+        assert!(SplitPoint::parse("").is_err());
+        assert!(SplitPoint::parse("users/a-b").is_err());
+    }
+
+    #[test]
+    fn overlap_and_instances() {
+        let users = SplitPoint::parse("users/*").unwrap();
+        assert!(users.overlaps(&SplitPoint::parse("users").unwrap()));
+        assert!(users.overlaps(&SplitPoint::parse("*/x").unwrap()));
+        assert!(!users.overlaps(&SplitPoint::parse("groups/*").unwrap()));
+
+        let root = Node::from(Value::Map(
+            [
+                (
+                    "users".to_string(),
+                    Value::Map(
+                        [
+                            ("alice".to_string(), Value::Integer(1)),
+                            ("bob".to_string(), Value::Integer(2)),
+                        ]
+                        .into(),
+                    ),
+                ),
+                ("n".to_string(), Value::Integer(3)),
+            ]
+            .into(),
+        ));
+        assert_eq!(
+            users.instances(&root),
+            vec![path!("users/alice"), path!("users/bob")]
+        );
+    }
+
+    #[test]
+    fn file_names_round_trip() {
+        let path = path!("tenants/acme/users/0");
+        assert_eq!(file_name(&path), "tenants.acme.users.0.json");
+        assert_eq!(parse_file_name(&file_name(&path)), Some(path));
+        assert_eq!(parse_file_name("notes.txt"), None);
+        assert_eq!(parse_file_name("a..b.json"), None);
+    }
+}
@@ -0,0 +1,55 @@
+//! Runs the LL conformance checks against a store through the C ABI.
+//!
+//! An in-memory store is exported as an `FfiStore` table, wrapped back up
+//! as a `ForeignStore`, and checked, so every call crosses the ABI twice.
+//! A host can do the same with its own table in place of the exported one.
+//!
+//! ```sh
+//! cargo run -p structfs-ll-store --example c_abi_conformance
+//! ```
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use structfs_ll_store::ffi::{check_conformance, FfiStore, ForeignStore};
+use structfs_ll_store::{Bytes, LLError, LLPath, LLReader, LLWriter};
+
+#[derive(Default)]
+struct MemoryStore(HashMap<Vec<Vec<u8>>, Bytes>);
+
+impl LLReader for MemoryStore {
+    fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+        let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+        Ok(self.0.get(&key).cloned())
+    }
+}
+
+impl LLWriter for MemoryStore {
+    fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+        let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+        self.0.insert(key, data);
+        Ok(path.iter().map(|c| Bytes::copy_from_slice(c)).collect())
+    }
+}
+
+fn main() -> ExitCode {
+    let table = FfiStore::from_store(MemoryStore::default());
+    // Safety: the table was made by `FfiStore::from_store`
+    let mut store = match unsafe { ForeignStore::new(table) } {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("table refused: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match check_conformance(&mut store) {
+        Ok(()) => {
+            println!("ok");
+            ExitCode::SUCCESS
+        }
+        Err(failure) => {
+            eprintln!("conformance failure: {}", failure);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,63 @@
+/*
+ * StructFS LL store C ABI.
+ *
+ * A store is a table of function pointers with a context pointer. The same
+ * table is used whichever side implements the store. See the `ffi` module
+ * of structfs-ll-store for the full calling convention.
+ *
+ * Memory is never freed across the boundary: paths and data are borrowed
+ * for the duration of a call, and results are passed to a sink that copies
+ * them before returning.
+ */
+
+#ifndef STRUCTFS_LL_H
+#define STRUCTFS_LL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define STRUCTFS_ABI_VERSION 1
+
+/* Status codes returned by read and write. */
+#define STRUCTFS_OK 0
+#define STRUCTFS_NOT_FOUND 1           /* read only */
+#define STRUCTFS_TRANSPORT (-1)        /* err: UTF-8 message */
+#define STRUCTFS_NOT_SUPPORTED (-2)    /* err: none */
+#define STRUCTFS_RESOURCE_EXHAUSTED (-3) /* err: none */
+#define STRUCTFS_PROTOCOL (-4)         /* err: u32 LE code, then detail */
+#define STRUCTFS_CORRUPTED (-5)        /* err: u32 LE actual, then u32 LE expected if known */
+
+/* Bytes borrowed for the duration of a call. ptr may be NULL if len is 0. */
+typedef struct structfs_bytes {
+    const uint8_t *ptr;
+    size_t len;
+} structfs_bytes;
+
+/* Receives bytes; put copies them before returning. */
+typedef struct structfs_sink {
+    void *ctx;
+    void (*put)(void *ctx, structfs_bytes data);
+} structfs_sink;
+
+typedef struct structfs_store {
+    uint32_t abi_version; /* STRUCTFS_ABI_VERSION */
+    void *ctx;
+    /* Pass the data to out (pieces are joined), or return STRUCTFS_NOT_FOUND. */
+    int32_t (*read)(void *ctx, const structfs_bytes *path, size_t path_len,
+                    structfs_sink out, structfs_sink err);
+    /* Pass each component of the result path to out, in order. */
+    int32_t (*write)(void *ctx, const structfs_bytes *path, size_t path_len,
+                     structfs_bytes data, structfs_sink out, structfs_sink err);
+    /* Free ctx after the last call. May be NULL. */
+    void (*release)(void *ctx);
+} structfs_store;
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STRUCTFS_LL_H */
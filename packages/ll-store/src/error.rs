@@ -36,6 +36,19 @@ pub enum LLError {
         /// Optional detail bytes (error message, structured error, etc.)
         detail: Bytes,
     },
+
+    /// Data failed an integrity check, so it was damaged in storage or in
+    /// transit.
+    ///
+    /// `actual` is the checksum of the bytes as read, and `expected` the
+    /// checksum stored with them, or `None` if they were too short to
+    /// hold one. See [`IntegrityStore`](crate::IntegrityStore).
+    Corrupted {
+        /// The checksum stored with the data.
+        expected: Option<u32>,
+        /// The checksum of the data as read.
+        actual: u32,
+    },
 }
 
 impl std::fmt::Display for LLError {
@@ -55,6 +68,17 @@ impl std::fmt::Display for LLError {
                     }
                 }
             }
+            LLError::Corrupted {
+                expected: Some(expected),
+                actual,
+            } => write!(
+                f,
+                "data corrupted: checksum {:08x}, expected {:08x}",
+                actual, expected
+            ),
+            LLError::Corrupted { expected: None, .. } => {
+                write!(f, "data corrupted: too short to hold a checksum")
+            }
         }
     }
 }
@@ -119,6 +143,18 @@ mod tests {
         assert!(display.contains("protocol error"));
     }
 
+    #[test]
+    fn corrupted_display() {
+        let e = LLError::Corrupted {
+            expected: Some(0xdeadbeef),
+            actual: 0x12345678,
+        };
+        assert_eq!(
+            format!("{}", e),
+            "data corrupted: checksum 12345678, expected deadbeef"
+        );
+    }
+
     #[test]
     fn transport_error_display() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
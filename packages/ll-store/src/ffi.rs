@@ -0,0 +1,580 @@
+//! A C ABI for LL stores, for hosts written in other languages.
+//!
+//! An [`FfiStore`] is a table of function pointers with a context pointer,
+//! the same shape in both directions:
+//!
+//! - A host written in C, C++ or Zig fills one in for its own store and
+//!   hands it over; [`ForeignStore`] wraps it as an [`LLReader`] and
+//!   [`LLWriter`], which an `LLToCore` bridge can then hand to the
+//!   Featherweight runtime like any other store.
+//! - [`FfiStore::from_store`] exports a Rust store the same way, for a host
+//!   to call through the table, typically returned from a `#[no_mangle]
+//!   extern "C"` function in a `cdylib`.
+//!
+//! The C declarations are in `include/structfs_ll.h`.
+//!
+//! # Memory
+//!
+//! No memory is freed on the other side of the boundary. Paths and data
+//! are passed as borrowed [`FfiBytes`], valid only for the duration of the
+//! call. Results come back through an [`FfiSink`]: the callee passes
+//! borrowed bytes to the sink, which copies them before returning.
+//!
+//! # Calls
+//!
+//! `read` and `write` return a status: [`STATUS_OK`], [`STATUS_NOT_FOUND`]
+//! (`read` only), or a negative error matching an [`LLError`] variant.
+//!
+//! - `read` passes the data to `out`. If it calls `out` more than once, the
+//!   pieces are joined.
+//! - `write` passes each component of the result path to `out`, in order.
+//! - On error, either call may pass detail bytes to `err`:
+//!
+//! | Status | `err` bytes |
+//! |--------|-------------|
+//! | [`STATUS_TRANSPORT`] | A UTF-8 message |
+//! | [`STATUS_NOT_SUPPORTED`], [`STATUS_RESOURCE_EXHAUSTED`] | None |
+//! | [`STATUS_PROTOCOL`] | The code as a little-endian `u32`, then the detail |
+//! | [`STATUS_CORRUPTED`] | The actual checksum as a little-endian `u32`, then the expected one if known |
+//!
+//! Calls through one table must not overlap; the caller serialises them.
+//! Once the table's owner is done with it, it calls `release`, if set, and
+//! makes no further calls.
+
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{LLError, LLPath, LLReader, LLStore, LLWriter};
+
+/// Version of the table layout. Bumped on any incompatible change.
+pub const ABI_VERSION: u32 = 1;
+
+/// The call succeeded.
+pub const STATUS_OK: i32 = 0;
+/// `read` found nothing at the path.
+pub const STATUS_NOT_FOUND: i32 = 1;
+/// [`LLError::Transport`].
+pub const STATUS_TRANSPORT: i32 = -1;
+/// [`LLError::NotSupported`].
+pub const STATUS_NOT_SUPPORTED: i32 = -2;
+/// [`LLError::ResourceExhausted`].
+pub const STATUS_RESOURCE_EXHAUSTED: i32 = -3;
+/// [`LLError::Protocol`].
+pub const STATUS_PROTOCOL: i32 = -4;
+/// [`LLError::Corrupted`].
+pub const STATUS_CORRUPTED: i32 = -5;
+
+/// Bytes borrowed across the boundary for the duration of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FfiBytes {
+    /// Start of the bytes. May be null if `len` is 0.
+    pub ptr: *const u8,
+    /// Number of bytes.
+    pub len: usize,
+}
+
+impl FfiBytes {
+    /// Borrow `bytes`.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        Self {
+            ptr: bytes.as_ptr(),
+            len: bytes.len(),
+        }
+    }
+
+    /// The bytes as a slice.
+    ///
+    /// # Safety
+    ///
+    /// Unless `len` is 0, `ptr` must point to `len` readable bytes that
+    /// stay valid and unchanged for `'a`.
+    pub unsafe fn as_slice<'a>(self) -> &'a [u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(self.ptr, self.len)
+        }
+    }
+}
+
+/// Receives bytes from a callee, copying them before `put` returns.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FfiSink {
+    /// Passed back to `put`.
+    pub ctx: *mut c_void,
+    /// Take a copy of `data`.
+    pub put: unsafe extern "C" fn(ctx: *mut c_void, data: FfiBytes),
+}
+
+/// An LL store as a table of C function pointers.
+///
+/// See the [module documentation](self) for the calling convention.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiStore {
+    /// Must be [`ABI_VERSION`].
+    pub abi_version: u32,
+    /// Passed to every function in the table.
+    pub ctx: *mut c_void,
+    /// Read the `path_len` components at `path`.
+    pub read: unsafe extern "C" fn(
+        ctx: *mut c_void,
+        path: *const FfiBytes,
+        path_len: usize,
+        out: FfiSink,
+        err: FfiSink,
+    ) -> i32,
+    /// Write `data` to the `path_len` components at `path`.
+    pub write: unsafe extern "C" fn(
+        ctx: *mut c_void,
+        path: *const FfiBytes,
+        path_len: usize,
+        data: FfiBytes,
+        out: FfiSink,
+        err: FfiSink,
+    ) -> i32,
+    /// Free `ctx`. Called once, after the last call, if set.
+    pub release: Option<unsafe extern "C" fn(ctx: *mut c_void)>,
+}
+
+impl FfiStore {
+    /// Export `store` through the C ABI. The table owns the store until
+    /// `release` is called.
+    pub fn from_store<S: LLStore + 'static>(store: S) -> Self {
+        Self {
+            abi_version: ABI_VERSION,
+            ctx: Box::into_raw(Box::new(store)).cast(),
+            read: read_exported::<S>,
+            write: write_exported::<S>,
+            release: Some(release_exported::<S>),
+        }
+    }
+}
+
+/// A store provided by the host through an [`FfiStore`] table.
+///
+/// Dropping it calls the table's `release`.
+#[derive(Debug)]
+pub struct ForeignStore {
+    table: FfiStore,
+}
+
+// Safety: the contract of `ForeignStore::new` requires the table to be
+// callable from any thread, and `&mut self` keeps calls from overlapping.
+unsafe impl Send for ForeignStore {}
+unsafe impl Sync for ForeignStore {}
+
+impl ForeignStore {
+    /// Wrap a table from the host. Fails with [`LLError::NotSupported`] if
+    /// it is for another [`ABI_VERSION`].
+    ///
+    /// # Safety
+    ///
+    /// The functions in `table` must follow the calling convention in the
+    /// [module documentation](self), and accept calls from any thread as
+    /// long as they do not overlap. The store takes ownership of `ctx`,
+    /// except on failure: a table for another version is not released.
+    pub unsafe fn new(table: FfiStore) -> Result<Self, LLError> {
+        if table.abi_version != ABI_VERSION {
+            return Err(LLError::NotSupported);
+        }
+        Ok(Self { table })
+    }
+}
+
+impl Drop for ForeignStore {
+    fn drop(&mut self) {
+        if let Some(release) = self.table.release {
+            // Safety: guaranteed by the contract of `ForeignStore::new`
+            unsafe { release(self.table.ctx) }
+        }
+    }
+}
+
+impl LLReader for ForeignStore {
+    fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+        let path: Vec<FfiBytes> = path.iter().map(|c| FfiBytes::from_slice(c)).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        // Safety: guaranteed by the contract of `ForeignStore::new`
+        let status = unsafe {
+            (self.table.read)(
+                self.table.ctx,
+                path.as_ptr(),
+                path.len(),
+                collector(&mut out),
+                collector(&mut err),
+            )
+        };
+        match status {
+            STATUS_OK => Ok(Some(join(out))),
+            STATUS_NOT_FOUND => Ok(None),
+            status => Err(error_from_status(status, join(err))),
+        }
+    }
+}
+
+impl LLWriter for ForeignStore {
+    fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+        let path: Vec<FfiBytes> = path.iter().map(|c| FfiBytes::from_slice(c)).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        // Safety: guaranteed by the contract of `ForeignStore::new`
+        let status = unsafe {
+            (self.table.write)(
+                self.table.ctx,
+                path.as_ptr(),
+                path.len(),
+                FfiBytes::from_slice(&data),
+                collector(&mut out),
+                collector(&mut err),
+            )
+        };
+        match status {
+            STATUS_OK => Ok(out),
+            status => Err(error_from_status(status, join(err))),
+        }
+    }
+}
+
+/// A sink that appends a copy of each piece to `pieces`.
+fn collector(pieces: &mut Vec<Bytes>) -> FfiSink {
+    unsafe extern "C" fn put(ctx: *mut c_void, data: FfiBytes) {
+        let pieces = &mut *ctx.cast::<Vec<Bytes>>();
+        pieces.push(Bytes::copy_from_slice(data.as_slice()));
+    }
+
+    FfiSink {
+        ctx: (pieces as *mut Vec<Bytes>).cast(),
+        put,
+    }
+}
+
+fn join(mut pieces: Vec<Bytes>) -> Bytes {
+    if pieces.len() == 1 {
+        return pieces.pop().unwrap();
+    }
+    let mut joined = BytesMut::new();
+    for piece in pieces {
+        joined.extend_from_slice(&piece);
+    }
+    joined.freeze()
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The error for a status from the other side, with its `err` bytes.
+fn error_from_status(status: i32, detail: Bytes) -> LLError {
+    match status {
+        STATUS_NOT_SUPPORTED => LLError::NotSupported,
+        STATUS_RESOURCE_EXHAUSTED => LLError::ResourceExhausted,
+        STATUS_PROTOCOL if detail.len() >= 4 => LLError::Protocol {
+            code: u32_at(&detail, 0).unwrap(),
+            detail: detail.slice(4..),
+        },
+        STATUS_CORRUPTED if detail.len() >= 4 => LLError::Corrupted {
+            expected: u32_at(&detail, 4),
+            actual: u32_at(&detail, 0).unwrap(),
+        },
+        STATUS_TRANSPORT => {
+            LLError::Transport(String::from_utf8_lossy(&detail).into_owned().into())
+        }
+        status => LLError::Transport(format!("store returned bad status {}", status).into()),
+    }
+}
+
+/// The status and `err` bytes for `error`.
+fn status_for_error(error: &LLError) -> (i32, Vec<u8>) {
+    match error {
+        LLError::Transport(e) => (STATUS_TRANSPORT, e.to_string().into_bytes()),
+        LLError::NotSupported => (STATUS_NOT_SUPPORTED, Vec::new()),
+        LLError::ResourceExhausted => (STATUS_RESOURCE_EXHAUSTED, Vec::new()),
+        LLError::Protocol { code, detail } => {
+            let mut bytes = code.to_le_bytes().to_vec();
+            bytes.extend_from_slice(detail);
+            (STATUS_PROTOCOL, bytes)
+        }
+        LLError::Corrupted { expected, actual } => {
+            let mut bytes = actual.to_le_bytes().to_vec();
+            if let Some(expected) = expected {
+                bytes.extend_from_slice(&expected.to_le_bytes());
+            }
+            (STATUS_CORRUPTED, bytes)
+        }
+    }
+}
+
+/// Run `call` on the exported store, turning errors and panics into a
+/// status and `err` bytes.
+unsafe fn call_exported<S: LLStore, T>(
+    ctx: *mut c_void,
+    path: *const FfiBytes,
+    path_len: usize,
+    err: FfiSink,
+    call: impl FnOnce(&mut S, &[&[u8]]) -> Result<T, LLError>,
+    done: impl FnOnce(T) -> i32,
+) -> i32 {
+    let store = &mut *ctx.cast::<S>();
+    let path: Vec<&[u8]> = if path_len == 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(path, path_len)
+            .iter()
+            .map(|c| c.as_slice())
+            .collect()
+    };
+    let (status, detail) = match catch_unwind(AssertUnwindSafe(|| call(store, &path))) {
+        Ok(Ok(value)) => return done(value),
+        Ok(Err(error)) => status_for_error(&error),
+        Err(_) => (STATUS_TRANSPORT, b"store panicked".to_vec()),
+    };
+    if !detail.is_empty() {
+        (err.put)(err.ctx, FfiBytes::from_slice(&detail));
+    }
+    status
+}
+
+unsafe extern "C" fn read_exported<S: LLStore>(
+    ctx: *mut c_void,
+    path: *const FfiBytes,
+    path_len: usize,
+    out: FfiSink,
+    err: FfiSink,
+) -> i32 {
+    call_exported::<S, _>(
+        ctx,
+        path,
+        path_len,
+        err,
+        |store, path| store.ll_read(path),
+        |data| match data {
+            Some(data) => {
+                (out.put)(out.ctx, FfiBytes::from_slice(&data));
+                STATUS_OK
+            }
+            None => STATUS_NOT_FOUND,
+        },
+    )
+}
+
+unsafe extern "C" fn write_exported<S: LLStore>(
+    ctx: *mut c_void,
+    path: *const FfiBytes,
+    path_len: usize,
+    data: FfiBytes,
+    out: FfiSink,
+    err: FfiSink,
+) -> i32 {
+    let data = Bytes::copy_from_slice(data.as_slice());
+    call_exported::<S, _>(
+        ctx,
+        path,
+        path_len,
+        err,
+        |store, path| store.ll_write(path, data),
+        |written| {
+            for component in written {
+                (out.put)(out.ctx, FfiBytes::from_slice(&component));
+            }
+            STATUS_OK
+        },
+    )
+}
+
+unsafe extern "C" fn release_exported<S: LLStore>(ctx: *mut c_void) {
+    drop(Box::from_raw(ctx.cast::<S>()));
+}
+
+/// Check that `store` behaves as the LL traits require, returning the
+/// first failure. Useful for testing a host's store through a
+/// [`ForeignStore`].
+///
+/// The checks write under the `conformance` component.
+pub fn check_conformance(store: &mut dyn LLStore) -> Result<(), String> {
+    fn expect(ok: bool, what: &str) -> Result<(), String> {
+        ok.then_some(()).ok_or_else(|| what.to_string())
+    }
+    fn fail(what: &str) -> impl FnOnce(LLError) -> String + '_ {
+        move |e| format!("{}: {}", what, e)
+    }
+
+    let path: &[&[u8]] = &[b"conformance", b"value"];
+    store
+        .ll_write(path, Bytes::from_static(b"hello"))
+        .map_err(fail("write"))?;
+    let read = store.ll_read(path).map_err(fail("read"))?;
+    expect(
+        read.as_deref() == Some(b"hello".as_slice()),
+        "read returns what was written",
+    )?;
+
+    store
+        .ll_write(path, Bytes::from_static(b"again"))
+        .map_err(fail("overwrite"))?;
+    let read = store.ll_read(path).map_err(fail("read after overwrite"))?;
+    expect(
+        read.as_deref() == Some(b"again".as_slice()),
+        "read returns the latest write",
+    )?;
+
+    let missing = store
+        .ll_read(&[b"conformance", b"missing"])
+        .map_err(fail("read of a missing path"))?;
+    expect(missing.is_none(), "a missing path reads as nothing")?;
+
+    let empty: &[&[u8]] = &[b"conformance", b"empty"];
+    store
+        .ll_write(empty, Bytes::new())
+        .map_err(fail("write of empty data"))?;
+    let read = store.ll_read(empty).map_err(fail("read of empty data"))?;
+    expect(
+        read.as_deref() == Some([].as_slice()),
+        "empty data reads back as empty, not missing",
+    )?;
+
+    let binary: Vec<u8> = (0..=255).collect();
+    let odd: &[&[u8]] = &[b"conformance", &[0xFF, 0x00, b'/']];
+    store
+        .ll_write(odd, Bytes::from(binary.clone()))
+        .map_err(fail("write to a non-UTF-8 component"))?;
+    let read = store
+        .ll_read(odd)
+        .map_err(fail("read from a non-UTF-8 component"))?;
+    expect(
+        read.as_deref() == Some(binary.as_slice()),
+        "components and data are opaque bytes",
+    )?;
+
+    let large = Bytes::from(vec![7u8; 1 << 20]);
+    let large_path: &[&[u8]] = &[b"conformance", b"large"];
+    store
+        .ll_write(large_path, large.clone())
+        .map_err(fail("write of 1 MiB"))?;
+    let read = store.ll_read(large_path).map_err(fail("read of 1 MiB"))?;
+    expect(read == Some(large), "1 MiB reads back intact")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct TestLLStore {
+        data: HashMap<Vec<Vec<u8>>, Bytes>,
+        dropped: Option<Arc<AtomicBool>>,
+    }
+
+    impl LLReader for TestLLStore {
+        fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+            if path.first() == Some(&b"fail".as_slice()) {
+                return Err(LLError::Protocol {
+                    code: 7,
+                    detail: Bytes::from_static(b"no"),
+                });
+            }
+            let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+            Ok(self.data.get(&key).cloned())
+        }
+    }
+
+    impl LLWriter for TestLLStore {
+        fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+            if path.first() == Some(&b"panic".as_slice()) {
+                panic!("test panic");
+            }
+            let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+            self.data.insert(key, data);
+            Ok(path.iter().map(|c| Bytes::copy_from_slice(c)).collect())
+        }
+    }
+
+    impl Drop for TestLLStore {
+        fn drop(&mut self) {
+            if let Some(dropped) = &self.dropped {
+                dropped.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn round_trip(store: TestLLStore) -> ForeignStore {
+        unsafe { ForeignStore::new(FfiStore::from_store(store)).unwrap() }
+    }
+
+    #[test]
+    fn exported_stores_pass_conformance_through_the_abi() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let mut store = round_trip(TestLLStore {
+            data: HashMap::new(),
+            dropped: Some(Arc::clone(&dropped)),
+        });
+        check_conformance(&mut store).unwrap();
+
+        let written = store
+            .ll_write(&[b"a", b"b"], Bytes::from_static(b"x"))
+            .unwrap();
+        assert_eq!(
+            written,
+            vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")]
+        );
+
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(store);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn errors_and_panics_cross_the_abi() {
+        let mut store = round_trip(TestLLStore::default());
+        match store.ll_read(&[b"fail"]) {
+            Err(LLError::Protocol { code, detail }) => {
+                assert_eq!(code, 7);
+                assert_eq!(detail, Bytes::from_static(b"no"));
+            }
+            other => panic!("expected protocol error, got {:?}", other),
+        }
+
+        let err = store
+            .ll_write(&[b"panic"], Bytes::new())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("store panicked"), "{}", err);
+
+        for error in [
+            LLError::NotSupported,
+            LLError::Corrupted {
+                expected: Some(1),
+                actual: 2,
+            },
+            LLError::Corrupted {
+                expected: None,
+                actual: 3,
+            },
+        ] {
+            let (status, detail) = status_for_error(&error);
+            let back = error_from_status(status, detail.into());
+            assert_eq!(back.to_string(), error.to_string());
+        }
+    }
+
+    #[test]
+    fn other_abi_versions_are_refused() {
+        let mut table = FfiStore::from_store(TestLLStore::default());
+        table.abi_version = ABI_VERSION + 1;
+        let release = table.release.take().unwrap();
+        let ctx = table.ctx;
+        assert!(matches!(
+            unsafe { ForeignStore::new(table) },
+            Err(LLError::NotSupported)
+        ));
+        unsafe { release(ctx) };
+    }
+}
@@ -0,0 +1,232 @@
+//! Checksummed payloads for transports that can damage bytes.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{LLError, LLPath, LLReader, LLWriter};
+
+/// Length of the checksum trailer [`seal`] appends.
+pub const CHECKSUM_LEN: usize = 4;
+
+/// CRC-32 (IEEE 802.3) lookup table, one entry per byte value.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32 (IEEE 802.3, as used by zlib and Ethernet) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// `data` followed by its CRC-32, little-endian.
+pub fn seal(data: &[u8]) -> Bytes {
+    let mut sealed = BytesMut::with_capacity(data.len() + CHECKSUM_LEN);
+    sealed.put_slice(data);
+    sealed.put_u32_le(crc32(data));
+    sealed.freeze()
+}
+
+/// The payload of bytes made by [`seal`], if its checksum matches.
+///
+/// Returns [`LLError::Corrupted`] if it does not, or if `sealed` is too
+/// short to hold a checksum. The payload shares `sealed`'s buffer.
+pub fn unseal(sealed: Bytes) -> Result<Bytes, LLError> {
+    let Some(split) = sealed.len().checked_sub(CHECKSUM_LEN) else {
+        return Err(LLError::Corrupted {
+            expected: None,
+            actual: crc32(&sealed),
+        });
+    };
+    let mut trailer = [0u8; CHECKSUM_LEN];
+    trailer.copy_from_slice(&sealed[split..]);
+    let expected = u32::from_le_bytes(trailer);
+    let payload = sealed.slice(..split);
+    let actual = crc32(&payload);
+    if actual != expected {
+        return Err(LLError::Corrupted {
+            expected: Some(expected),
+            actual,
+        });
+    }
+    Ok(payload)
+}
+
+/// An LL store wrapper that checksums every payload.
+///
+/// `ll_write` stores each payload [sealed](seal) with a CRC-32 trailer,
+/// and `ll_read` checks and strips it, so damage anywhere between the two,
+/// such as in shared memory or on the wire, surfaces as
+/// [`LLError::Corrupted`] rather than as wrong data. Paths are not
+/// checksummed.
+///
+/// Both ends must agree to use it: the inner store holds sealed bytes, and
+/// reading bytes that were not sealed fails the check.
+///
+/// ```rust
+/// use structfs_ll_store::{IntegrityStore, LLError, LLReader, LLWriter};
+/// # use structfs_ll_store::{Bytes, LLPath};
+/// # use std::collections::HashMap;
+/// # #[derive(Default)]
+/// # struct Memory(HashMap<Vec<Vec<u8>>, Bytes>);
+/// # impl LLReader for Memory {
+/// #     fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+/// #         Ok(self.0.get(&path.iter().map(|c| c.to_vec()).collect::<Vec<_>>()).cloned())
+/// #     }
+/// # }
+/// # impl LLWriter for Memory {
+/// #     fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+/// #         self.0.insert(path.iter().map(|c| c.to_vec()).collect(), data);
+/// #         Ok(path.iter().map(|c| Bytes::copy_from_slice(c)).collect())
+/// #     }
+/// # }
+/// let mut store = IntegrityStore::new(Memory::default());
+/// store.ll_write(&[b"key"], Bytes::from_static(b"value"))?;
+/// assert_eq!(store.ll_read(&[b"key"])?, Some(Bytes::from_static(b"value")));
+/// # Ok::<(), LLError>(())
+/// ```
+pub struct IntegrityStore<S> {
+    inner: S,
+}
+
+impl<S> IntegrityStore<S> {
+    /// Checksum everything written to and read from `inner`.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The wrapped store, mutably.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: LLReader> LLReader for IntegrityStore<S> {
+    fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+        self.inner.ll_read(path)?.map(unseal).transpose()
+    }
+}
+
+impl<S: LLWriter> LLWriter for IntegrityStore<S> {
+    fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+        self.inner.ll_write(path, seal(&data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct TestLLStore {
+        data: HashMap<Vec<Vec<u8>>, Bytes>,
+    }
+
+    impl LLReader for TestLLStore {
+        fn ll_read(&mut self, path: &[&[u8]]) -> Result<Option<Bytes>, LLError> {
+            let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+            Ok(self.data.get(&key).cloned())
+        }
+    }
+
+    impl LLWriter for TestLLStore {
+        fn ll_write(&mut self, path: &[&[u8]], data: Bytes) -> Result<LLPath, LLError> {
+            let key: Vec<Vec<u8>> = path.iter().map(|c| c.to_vec()).collect();
+            self.data.insert(key, data);
+            Ok(path.iter().map(|c| Bytes::copy_from_slice(c)).collect())
+        }
+    }
+
+    #[test]
+    fn crc32_matches_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"hello world"), 0x0D4A_1185);
+    }
+
+    #[test]
+    fn round_trips_and_stores_checksum() {
+        let mut store = IntegrityStore::new(TestLLStore::default());
+        store
+            .ll_write(&[b"users", b"1"], Bytes::from_static(b"alice"))
+            .unwrap();
+        assert_eq!(
+            store.ll_read(&[b"users", b"1"]).unwrap(),
+            Some(Bytes::from_static(b"alice"))
+        );
+        assert_eq!(store.ll_read(&[b"missing"]).unwrap(), None);
+
+        let raw = store
+            .inner_mut()
+            .ll_read(&[b"users", b"1"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw.len(), 5 + CHECKSUM_LEN);
+        assert_eq!(&raw[5..], crc32(b"alice").to_le_bytes());
+    }
+
+    #[test]
+    fn damage_is_reported_as_corruption() {
+        let mut store = IntegrityStore::new(TestLLStore::default());
+        store
+            .ll_write(&[b"key"], Bytes::from_static(b"value"))
+            .unwrap();
+
+        // Flip a bit in the stored payload
+        let mut raw = store
+            .inner_mut()
+            .ll_read(&[b"key"])
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        raw[0] ^= 1;
+        store.inner_mut().ll_write(&[b"key"], raw.into()).unwrap();
+        match store.ll_read(&[b"key"]) {
+            Err(LLError::Corrupted {
+                expected: Some(expected),
+                actual,
+            }) => {
+                assert_eq!(expected, crc32(b"value"));
+                assert_ne!(actual, expected);
+            }
+            other => panic!("expected corruption, got {:?}", other),
+        }
+
+        // Unsealed bytes are too short, or fail the check
+        store
+            .inner_mut()
+            .ll_write(&[b"key"], Bytes::from_static(b"abc"))
+            .unwrap();
+        assert!(matches!(
+            store.ll_read(&[b"key"]),
+            Err(LLError::Corrupted { expected: None, .. })
+        ));
+    }
+}
@@ -9,6 +9,13 @@
 //! - Zero-copy forwarding proxies
 //! - Any transport that shouldn't pay parsing costs
 //!
+//! Stores are trusted to return the bytes they were given. Where a
+//! transport may damage them, wrap it in an [`IntegrityStore`] to checksum
+//! every payload.
+//!
+//! Hosts written in other languages provide and consume stores through the
+//! C ABI in [`ffi`].
+//!
 //! # Example
 //!
 //! ```rust
@@ -41,9 +48,12 @@
 pub use bytes::Bytes;
 
 mod error;
+pub mod ffi;
+pub mod integrity;
 mod traits;
 
 pub use error::LLError;
+pub use integrity::IntegrityStore;
 pub use traits::{LLPath, LLReader, LLStore, LLWriter};
 
 #[cfg(feature = "async")]
@@ -0,0 +1,33 @@
+//! # structfs-mqtt
+//!
+//! A store bridging an MQTT broker, so Blocks can consume data from devices
+//! that speak MQTT as ordinary store reads and watches.
+//!
+//! Topics map to paths level by level: writing `home/kitchen/light`
+//! publishes to that topic, and reading it gives the last message received
+//! there. Watching a path subscribes to every topic below it and reports
+//! each message as a [`Change`](structfs_core_store::Change).
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use structfs_mqtt::{MqttOptions, MqttStore};
+//!
+//! let mut store = MqttStore::connect(MqttOptions::new("block-1", "broker.local", 1883))?;
+//!
+//! let readings = store.watch(&path!("home"))?;
+//! while let Some(change) = readings.next_timeout(Duration::from_secs(60)) {
+//!     // change.path is e.g. home/kitchen/temp, change.new the reading
+//! }
+//!
+//! store.write(&path!("home/kitchen/light"), Record::parsed(Value::from("on")))?;
+//! ```
+//!
+//! See [`MqttStore`] for how payloads and topic names are mapped.
+
+mod store;
+mod topic;
+
+pub use store::MqttStore;
+
+pub use rumqttc::{MqttOptions, QoS};
@@ -0,0 +1,471 @@
+//! The MQTT bridge store.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS};
+use structfs_core_store::{
+    Deleter, Error, Lister, Path, Reader, Record, Subscription, Watcher, Watchers, Writer,
+};
+
+use crate::topic::{
+    path_to_topic, payload_to_record, record_to_payload, subtree_filter, topic_to_path,
+};
+
+/// Requests queued for the event loop before publishing waits.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// How long [`MqttStore::connect`] waits for the broker to accept.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before reconnecting after the connection drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// A store bridging an MQTT broker.
+///
+/// Writing a path publishes to its topic, and reading a path gives the last
+/// message received on its topic. Topics are only received once subscribed:
+/// [`subscribe`](Self::subscribe) or [`watch`](Watcher::watch) a path to
+/// receive the topics at and below it.
+///
+/// | Store | MQTT |
+/// |-------|------|
+/// | path `home/kitchen/temp` | topic `home/kitchen/temp` |
+/// | write | publish |
+/// | read | the last message received on the topic |
+/// | list | the next levels of the topics received below the path |
+/// | watch | subscribe to `<topic>/#` and report each message |
+/// | delete | publish an empty retained message, clearing the topic |
+///
+/// Payloads that parse as JSON are read as their value, other text as a
+/// string, and anything else as bytes; strings and bytes are published as
+/// they are and other values as JSON. Topic levels that are not valid path
+/// components, such as `kitchen-1`, are [namecode](namecode)-encoded.
+///
+/// A background thread runs the connection, reconnecting when it drops and
+/// renewing the subscriptions.
+pub struct MqttStore {
+    client: Client,
+    shared: Arc<Shared>,
+    qos: QoS,
+    retain: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    stopped: AtomicBool,
+}
+
+#[derive(Default)]
+struct State {
+    /// The last message received on each topic.
+    messages: BTreeMap<Path, Record>,
+    watchers: Watchers,
+    /// The subscribed subtrees, renewed after a reconnect.
+    subscribed: Vec<(Path, QoS)>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take in a message, reporting it to the watchers.
+    fn receive(&self, topic: &str, payload: &[u8]) {
+        let Ok(path) = topic_to_path(topic) else {
+            return;
+        };
+        let record = payload_to_record(payload);
+        let mut state = self.lock();
+        let State {
+            messages, watchers, ..
+        } = &mut *state;
+        let pending = watchers.before(&path, |p| messages.get(p).cloned());
+        match record {
+            Some(record) => messages.insert(path, record),
+            None => messages.remove(&path),
+        };
+        pending.send(|p| messages.get(p).cloned());
+    }
+}
+
+impl MqttStore {
+    /// Connect to a broker, waiting until it accepts the connection.
+    ///
+    /// Publishes and subscriptions use QoS 1 (at least once) and messages
+    /// are not retained; see [`with_qos`](Self::with_qos) and
+    /// [`retained`](Self::retained).
+    pub fn connect(options: MqttOptions) -> Result<Self, Error> {
+        let (client, connection) = Client::new(options, CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared::default());
+        let (ready, connected) = mpsc::channel();
+        let events = (client.clone(), shared.clone());
+        thread::Builder::new()
+            .name("structfs-mqtt".to_string())
+            .spawn(move || run(connection, events.0, &events.1, ready))
+            .map_err(Error::Io)?;
+
+        let store = Self {
+            client,
+            shared,
+            qos: QoS::AtLeastOnce,
+            retain: false,
+        };
+        match connected.recv_timeout(CONNECT_TIMEOUT) {
+            Ok(Ok(())) => Ok(store),
+            Ok(Err(message)) => Err(Error::store("mqtt", "connect", message)),
+            Err(_) => Err(Error::store(
+                "mqtt",
+                "connect",
+                "timed out waiting for the broker",
+            )),
+        }
+    }
+
+    /// Publish and subscribe with `qos`.
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Publish retained messages, which the broker keeps and sends to
+    /// later subscribers.
+    pub fn retained(mut self) -> Self {
+        self.retain = true;
+        self
+    }
+
+    /// Receive the topics at and below `path`.
+    ///
+    /// Paths already covered by a subscription are not subscribed again.
+    pub fn subscribe(&mut self, path: &Path) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+        if state.subscribed.iter().any(|(s, _)| path.has_prefix(s)) {
+            return Ok(());
+        }
+        self.client
+            .subscribe(subtree_filter(path), self.qos)
+            .map_err(|e| Error::store("mqtt", "subscribe", e.to_string()))?;
+        state.subscribed.push((path.clone(), self.qos));
+        Ok(())
+    }
+
+    fn publish(
+        &self,
+        operation: &'static str,
+        path: &Path,
+        retain: bool,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let topic = path_to_topic(path);
+        if topic.is_empty() || topic.contains(['+', '#']) {
+            return Err(Error::store(
+                "mqtt",
+                operation,
+                format!("'{}' is not a topic that can be published to", topic),
+            ));
+        }
+        self.client
+            .publish(topic, self.qos, retain, payload)
+            .map_err(|e| Error::store("mqtt", operation, e.to_string()))
+    }
+}
+
+impl Drop for MqttStore {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+        let _ = self.client.try_disconnect();
+    }
+}
+
+/// Run the connection until the store is dropped, reporting the first
+/// connection attempt's outcome on `ready`.
+fn run(
+    mut connection: Connection,
+    client: Client,
+    shared: &Shared,
+    ready: mpsc::Sender<Result<(), String>>,
+) {
+    let mut connected = false;
+    for event in connection.iter() {
+        if shared.stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) if !connected => {
+                connected = true;
+                let _ = ready.send(Ok(()));
+            }
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                // A new session starts without the old subscriptions.
+                for (path, qos) in shared.lock().subscribed.iter() {
+                    let _ = client.try_subscribe(subtree_filter(path), *qos);
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                shared.receive(&publish.topic, &publish.payload);
+            }
+            Ok(_) => {}
+            Err(e) if !connected => {
+                let _ = ready.send(Err(e.to_string()));
+                return;
+            }
+            Err(_) => thread::sleep(RECONNECT_DELAY),
+        }
+    }
+}
+
+impl Reader for MqttStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.shared.lock().messages.get(from).cloned())
+    }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+
+    fn as_watcher(&mut self) -> Option<&mut dyn Watcher> {
+        Some(self)
+    }
+}
+
+impl Writer for MqttStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        self.publish("write", to, self.retain, record_to_payload(data)?)?;
+        Ok(to.clone())
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl Deleter for MqttStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        self.publish("delete", path, true, Vec::new())
+    }
+}
+
+impl Lister for MqttStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        let state = self.shared.lock();
+        let mut found = path.is_empty();
+        let mut names: Vec<String> = Vec::new();
+        for topic in state.messages.keys().filter(|t| t.has_prefix(path)) {
+            found = true;
+            if let Some(name) = topic.iter().nth(path.len()) {
+                if names.last() != Some(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        Ok(found.then_some(names))
+    }
+}
+
+impl Watcher for MqttStore {
+    fn watch(&mut self, path: &Path) -> Result<Subscription, Error> {
+        // Subscribe after registering, so retained messages sent straight
+        // back are reported.
+        let subscription = self.shared.lock().watchers.subscribe(path);
+        self.subscribe(path)?;
+        Ok(subscription)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck, Publish, SubAck, SubscribeReasonCode};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use structfs_core_store::{path, NoCodec, Value};
+
+    const MAX_PACKET: usize = 1 << 20;
+
+    #[derive(Default)]
+    struct Broker {
+        sessions: Vec<(TcpStream, Vec<String>)>,
+        retained: BTreeMap<String, Vec<u8>>,
+    }
+
+    /// Start a broker good enough for these tests: it accepts every
+    /// connection and subscription, keeps retained messages, and forwards
+    /// publishes at QoS 0. Returns its port.
+    fn broker() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = Arc::new(Mutex::new(Broker::default()));
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                let stream = stream.unwrap();
+                let broker = broker.clone();
+                broker
+                    .lock()
+                    .unwrap()
+                    .sessions
+                    .push((stream.try_clone().unwrap(), Vec::new()));
+                thread::spawn(move || serve(id, stream, &broker));
+            }
+        });
+        port
+    }
+
+    fn send(mut stream: &TcpStream, packet: Packet) {
+        let mut buf = BytesMut::new();
+        packet.write(&mut buf, MAX_PACKET).unwrap();
+        let _ = stream.write_all(&buf);
+    }
+
+    fn serve(id: usize, mut stream: TcpStream, broker: &Mutex<Broker>) {
+        let mut buf = BytesMut::new();
+        let mut chunk = [0; 4096];
+        loop {
+            let packet = match Packet::read(&mut buf, MAX_PACKET) {
+                Ok(packet) => packet,
+                Err(_) => match stream.read(&mut chunk) {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => {
+                        buf.extend_from_slice(&chunk[..n]);
+                        continue;
+                    }
+                },
+            };
+            let mut broker = broker.lock().unwrap();
+            match packet {
+                Packet::Connect(_) => send(
+                    &stream,
+                    Packet::ConnAck(ConnAck::new(ConnectReturnCode::Success, false)),
+                ),
+                Packet::Subscribe(subscribe) => {
+                    let codes = subscribe
+                        .filters
+                        .iter()
+                        .map(|_| SubscribeReasonCode::Success(QoS::AtMostOnce))
+                        .collect();
+                    send(&stream, Packet::SubAck(SubAck::new(subscribe.pkid, codes)));
+                    for filter in subscribe.filters {
+                        for (topic, payload) in &broker.retained {
+                            if rumqttc::matches(topic, &filter.path) {
+                                let publish = Publish::new(topic, QoS::AtMostOnce, payload.clone());
+                                send(&stream, Packet::Publish(publish));
+                            }
+                        }
+                        broker.sessions[id].1.push(filter.path);
+                    }
+                }
+                Packet::Publish(publish) => {
+                    if publish.qos != QoS::AtMostOnce {
+                        send(&stream, Packet::PubAck(PubAck::new(publish.pkid)));
+                    }
+                    if publish.retain {
+                        if publish.payload.is_empty() {
+                            broker.retained.remove(&publish.topic);
+                        } else {
+                            broker
+                                .retained
+                                .insert(publish.topic.clone(), publish.payload.to_vec());
+                        }
+                    }
+                    for (session, filters) in &broker.sessions {
+                        if filters.iter().any(|f| rumqttc::matches(&publish.topic, f)) {
+                            let forward = Publish::new(
+                                &publish.topic,
+                                QoS::AtMostOnce,
+                                publish.payload.to_vec(),
+                            );
+                            send(session, Packet::Publish(forward));
+                        }
+                    }
+                }
+                Packet::PingReq => send(&stream, Packet::PingResp),
+                Packet::Disconnect => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn connect(id: &str, port: u16) -> MqttStore {
+        MqttStore::connect(MqttOptions::new(id, "127.0.0.1", port)).unwrap()
+    }
+
+    fn next_value(changes: &Subscription) -> (Path, Option<Value>) {
+        let change = changes.next_timeout(Duration::from_secs(5)).unwrap();
+        let new = change.new.map(|r| r.into_value(&NoCodec).unwrap());
+        (change.path, new)
+    }
+
+    #[test]
+    fn messages_become_reads_and_changes() {
+        let port = broker();
+        let mut device = connect("device", port).retained();
+        device
+            .write(
+                &path!("home/kitchen/temp"),
+                Record::parsed(Value::Float(21.5)),
+            )
+            .unwrap();
+
+        let mut block = connect("block", port);
+        let changes = block.watch(&path!("home")).unwrap();
+        // The retained message arrives once subscribed.
+        assert_eq!(
+            next_value(&changes),
+            (path!("home/kitchen/temp"), Some(Value::Float(21.5)))
+        );
+        let read = block.read(&path!("home/kitchen/temp")).unwrap().unwrap();
+        assert_eq!(read.into_value(&NoCodec).unwrap(), Value::Float(21.5));
+
+        device
+            .write(
+                &path!("home/kitchen/light"),
+                Record::parsed(Value::from("on")),
+            )
+            .unwrap();
+        device
+            .write(&path!("garden/temp"), Record::parsed(Value::Integer(12)))
+            .unwrap();
+        assert_eq!(
+            next_value(&changes),
+            (path!("home/kitchen/light"), Some(Value::from("on")))
+        );
+        assert_eq!(
+            block.list(&path!("home/kitchen")).unwrap(),
+            Some(vec!["light".to_string(), "temp".to_string()])
+        );
+        assert_eq!(block.list(&path!("garden")).unwrap(), None);
+
+        device.delete(&path!("home/kitchen/temp")).unwrap();
+        assert_eq!(next_value(&changes), (path!("home/kitchen/temp"), None));
+        assert!(block.read(&path!("home/kitchen/temp")).unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_topics_and_brokers_are_errors() {
+        let port = broker();
+        let mut store = connect("store", port);
+        assert!(store
+            .write(&path!(""), Record::parsed(Value::Integer(1)))
+            .is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        assert!(MqttStore::connect(MqttOptions::new("nobody", "127.0.0.1", port)).is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let port = broker();
+        let mut store = connect("store", port);
+        conformance::assert_missing(&mut store, &path!("home/kitchen/temp"));
+        conformance::assert_missing(&mut store, &path!(""));
+    }
+}
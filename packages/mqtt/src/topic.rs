@@ -0,0 +1,112 @@
+//! Topics as paths, and payloads as records.
+
+use bytes::Bytes;
+use structfs_core_store::{Codec, Error, Format, Path, PathError, Record, Value};
+use structfs_serde_store::JsonCodec;
+
+/// The path of a topic, one component per level.
+///
+/// Levels that are not valid path components, such as `kitchen-1` or
+/// `$SYS`, are [namecode](namecode)-encoded. Topics with empty levels
+/// (`a//b`, `/a`) have no path.
+pub(crate) fn topic_to_path(topic: &str) -> Result<Path, PathError> {
+    let components = topic
+        .split('/')
+        .map(
+            |level| match Path::try_from_components(vec![level.to_string()]) {
+                Ok(_) => level.to_string(),
+                Err(_) => namecode::encode(level),
+            },
+        )
+        .collect();
+    Path::try_from_components(components)
+}
+
+/// The topic of a path: its components, decoded, joined by `/`.
+pub(crate) fn path_to_topic(path: &Path) -> String {
+    path.iter()
+        .map(|component| namecode::decode(component).unwrap_or_else(|_| component.clone()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The filter for everything at and below `path`.
+pub(crate) fn subtree_filter(path: &Path) -> String {
+    if path.is_empty() {
+        "#".to_string()
+    } else {
+        format!("{}/#", path_to_topic(path))
+    }
+}
+
+/// The record a payload holds: its JSON value if it parses, otherwise its
+/// text, otherwise its bytes. An empty payload, which clears a retained
+/// message, holds nothing.
+pub(crate) fn payload_to_record(payload: &[u8]) -> Option<Record> {
+    if payload.is_empty() {
+        return None;
+    }
+    let bytes = Bytes::copy_from_slice(payload);
+    let value = match JsonCodec.decode(&bytes, &Format::JSON) {
+        Ok(value) => value,
+        Err(_) => match std::str::from_utf8(payload) {
+            Ok(text) => Value::String(text.to_string()),
+            Err(_) => Value::Bytes(payload.to_vec()),
+        },
+    };
+    Some(Record::parsed(value))
+}
+
+/// The payload to publish for a record.
+///
+/// Strings and bytes are sent as they are, so devices expecting plain
+/// text get it; other values are sent as JSON, and raw records as their
+/// bytes.
+pub(crate) fn record_to_payload(record: Record) -> Result<Vec<u8>, Error> {
+    match record {
+        Record::Raw { bytes, .. } => Ok(bytes.to_vec()),
+        Record::Parsed(Value::String(text)) => Ok(text.into_bytes()),
+        Record::Parsed(Value::Bytes(bytes)) => Ok(bytes),
+        Record::Parsed(value) => Ok(JsonCodec.encode(&value, &Format::JSON)?.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, NoCodec};
+
+    #[test]
+    fn topics_and_paths() {
+        let path = topic_to_path("home/kitchen-1/temp").unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path_to_topic(&path), "home/kitchen-1/temp");
+        assert_eq!(
+            topic_to_path("sensors/0/value").unwrap(),
+            path!("sensors/0/value")
+        );
+        assert_eq!(
+            path_to_topic(&topic_to_path("$SYS/uptime").unwrap()),
+            "$SYS/uptime"
+        );
+        assert!(topic_to_path("a//b").is_err());
+
+        assert_eq!(subtree_filter(&path!("")), "#");
+        assert_eq!(subtree_filter(&path!("home/kitchen")), "home/kitchen/#");
+    }
+
+    #[test]
+    fn payloads_and_records() {
+        let value =
+            |payload: &[u8]| payload_to_record(payload).map(|r| r.into_value(&NoCodec).unwrap());
+        assert_eq!(value(b"21.5"), Some(Value::Float(21.5)));
+        assert_eq!(value(b"on"), Some(Value::String("on".into())));
+        assert_eq!(value(b"\xff\x00"), Some(Value::Bytes(vec![0xff, 0])));
+        assert_eq!(value(b""), None);
+
+        let payload = |value: Value| record_to_payload(Record::parsed(value)).unwrap();
+        assert_eq!(payload(Value::String("off".into())), b"off");
+        assert_eq!(payload(Value::Integer(3)), b"3");
+        assert_eq!(payload(Value::Bytes(vec![1, 2])), [1, 2]);
+    }
+}
@@ -0,0 +1,360 @@
+//! REPL core with background command execution.
+//!
+//! [`AsyncReplCore`] runs each command on a [`CommandWorker`] and keeps
+//! talking to the host while it executes. A long-running store operation no
+//! longer freezes the prompt or swallows signals: Ctrl+C moves the running
+//! command to the background and returns to the prompt, and its output is
+//! shown once it finishes.
+
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use structfs_core_store::Path;
+
+use crate::commands::{self, CommandResult};
+use crate::io::{AsyncIoHost, ExitReason, IoError, IoHost, Output, PromptConfig, Signal};
+use crate::repl::{format_path, prompt_for_secret, BANNER};
+use crate::store_context::StoreContext;
+use crate::worker::{CommandWorker, Completion, JobId};
+
+/// How often the core checks on a running command.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A REPL core that runs commands off the input loop.
+pub struct AsyncReplCore {
+    worker: CommandWorker,
+    current_path: Path,
+    /// The command the user is waiting on, if any.
+    foreground: Option<JobId>,
+}
+
+impl AsyncReplCore {
+    /// Create a core with default stores.
+    pub fn new() -> Self {
+        Self::with_context(StoreContext::new())
+    }
+
+    /// Create a core that runs commands against `ctx`.
+    pub fn with_context(ctx: StoreContext) -> Self {
+        let current_path = ctx.current_path().clone();
+        Self {
+            worker: CommandWorker::new(ctx),
+            current_path,
+            foreground: None,
+        }
+    }
+
+    /// Number of commands still running or queued.
+    pub fn pending(&self) -> usize {
+        self.worker.pending()
+    }
+
+    /// Run the REPL loop on an asynchronous host.
+    pub async fn run(&mut self, io: &mut impl AsyncIoHost) -> Result<ExitReason, IoError> {
+        io.write_output(Output::banner(BANNER))?;
+
+        loop {
+            if let Some(reason) = self.wait_for_foreground(io).await? {
+                return Ok(reason);
+            }
+
+            self.update_prompt(io)?;
+            io.wait_for_input().await?;
+
+            if let Some(signal) = io.read_signal()? {
+                match signal {
+                    Signal::Eof => return Self::goodbye(io, ExitReason::Eof),
+                    Signal::Interrupt => {
+                        io.write_output(Output::info("^C (use 'exit' to quit)"))?;
+                        continue;
+                    }
+                }
+            }
+
+            // Show anything that finished in the background while the host
+            // was waiting for input
+            if let Some(reason) = self.drain_completions(io)? {
+                return Ok(reason);
+            }
+
+            if let Some(input) = io.read_input()? {
+                if let Some(line) = prompt_for_secret(io, input.line)? {
+                    self.foreground = Some(self.worker.submit(line));
+                }
+            }
+        }
+    }
+
+    /// Run the REPL loop on a blocking host.
+    pub fn run_blocking(&mut self, io: &mut impl IoHost) -> Result<ExitReason, IoError> {
+        block_on(self.run(io))
+    }
+
+    /// Wait for the foreground command, reporting completions as they come.
+    ///
+    /// Returns the exit reason if a command asked to exit.
+    async fn wait_for_foreground(
+        &mut self,
+        io: &mut impl AsyncIoHost,
+    ) -> Result<Option<ExitReason>, IoError> {
+        while self.foreground.is_some() {
+            if let Some(reason) = self.drain_completions(io)? {
+                return Ok(Some(reason));
+            }
+            let Some(job) = self.foreground else {
+                break;
+            };
+
+            match io.read_signal()? {
+                Some(Signal::Interrupt) => {
+                    io.write_output(Output::info(format!(
+                        "^C (job {} continues in the background)",
+                        job.0
+                    )))?;
+                    self.foreground = None;
+                }
+                Some(Signal::Eof) => return Self::goodbye(io, ExitReason::Eof).map(Some),
+                None => io.idle(POLL_INTERVAL).await?,
+            }
+            io.flush()?;
+        }
+        Ok(None)
+    }
+
+    /// Report every finished command.
+    fn drain_completions(
+        &mut self,
+        io: &mut impl AsyncIoHost,
+    ) -> Result<Option<ExitReason>, IoError> {
+        while let Some(completion) = self.worker.poll() {
+            if let Some(reason) = self.complete(io, completion)? {
+                return Ok(Some(reason));
+            }
+        }
+        Ok(None)
+    }
+
+    fn complete(
+        &mut self,
+        io: &mut impl AsyncIoHost,
+        completion: Completion,
+    ) -> Result<Option<ExitReason>, IoError> {
+        let Completion {
+            job,
+            line,
+            result,
+            current_path,
+        } = completion;
+        self.current_path = current_path;
+
+        let in_background = self.foreground != Some(job);
+        if !in_background {
+            self.foreground = None;
+        }
+        if in_background && !matches!(result, CommandResult::Exit) {
+            io.write_output(Output::info(format!(
+                "[job {}] {}",
+                job.0,
+                commands::redact_secret(line.trim())
+            )))?;
+        }
+
+        match result {
+            CommandResult::Ok { display: None, .. } => {}
+            CommandResult::Ok {
+                display: Some(output),
+                ..
+            } => io.write_output(Output::normal(output))?,
+            CommandResult::Error(msg) => io.write_output(Output::error(msg))?,
+            CommandResult::Exit => return Self::goodbye(io, ExitReason::UserExit).map(Some),
+        }
+        io.flush()?;
+        Ok(None)
+    }
+
+    fn update_prompt(&self, io: &mut impl AsyncIoHost) -> Result<(), IoError> {
+        io.write_prompt(PromptConfig {
+            mount_count: 4, // http + http_sync + sys + help
+            current_path: format_path(&self.current_path),
+        })
+    }
+
+    fn goodbye(io: &mut impl AsyncIoHost, reason: ExitReason) -> Result<ExitReason, IoError> {
+        io.write_output(Output::info("Goodbye!"))?;
+        io.flush()?;
+        Ok(reason)
+    }
+}
+
+impl Default for AsyncReplCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drive a future whose waits block the thread instead of returning
+/// `Pending`, as with the [`AsyncIoHost`] impl for [`IoHost`]s.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::strip_ansi_codes;
+    use crate::io::InputLine;
+    use crate::plugins::{PluginRegistry, StorePlugin};
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+    use structfs_core_store::mount_store::MountConfig;
+    use structfs_core_store::overlay_store::StoreBox;
+    use structfs_core_store::{Error as CoreError, Reader, Record, Value, Writer};
+
+    /// Store whose reads of `x` block until released.
+    struct GateStore(Arc<Mutex<Receiver<()>>>);
+
+    impl Reader for GateStore {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, CoreError> {
+            // Only `x` blocks, so probing the mount for docs does not
+            if from.to_string() != "x" {
+                return Ok(None);
+            }
+            self.0.lock().unwrap().recv().ok();
+            Ok(Some(Record::parsed(Value::from("released"))))
+        }
+    }
+
+    impl Writer for GateStore {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, CoreError> {
+            Ok(to.clone())
+        }
+    }
+
+    struct GatePlugin(Arc<Mutex<Receiver<()>>>);
+
+    impl StorePlugin for GatePlugin {
+        fn kind(&self) -> &str {
+            "gate"
+        }
+
+        fn create(&self, _config: &Value) -> Result<StoreBox, CoreError> {
+            Ok(Box::new(GateStore(self.0.clone())))
+        }
+    }
+
+    /// Host that interrupts the first command it idles on, and releases the
+    /// gate once it has idled `release_after` times.
+    struct ScriptedHost {
+        inputs: VecDeque<String>,
+        signals: VecDeque<Signal>,
+        outputs: Vec<Output>,
+        idles: usize,
+        release_after: usize,
+        release: Sender<()>,
+    }
+
+    #[async_trait(?Send)]
+    impl AsyncIoHost for ScriptedHost {
+        async fn wait_for_input(&mut self) -> Result<(), IoError> {
+            Ok(())
+        }
+
+        async fn idle(&mut self, _interval: Duration) -> Result<(), IoError> {
+            self.idles += 1;
+            if self.idles == 1 {
+                self.signals.push_back(Signal::Interrupt);
+            }
+            if self.idles == self.release_after {
+                self.release.send(()).unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            Ok(())
+        }
+
+        fn read_input(&mut self) -> Result<Option<InputLine>, IoError> {
+            Ok(self.inputs.pop_front().map(|line| InputLine { line }))
+        }
+
+        fn read_signal(&mut self) -> Result<Option<Signal>, IoError> {
+            Ok(self.signals.pop_front())
+        }
+
+        fn write_output(&mut self, output: Output) -> Result<(), IoError> {
+            self.outputs.push(output);
+            Ok(())
+        }
+
+        fn write_prompt(&mut self, _config: PromptConfig) -> Result<(), IoError> {
+            Ok(())
+        }
+    }
+
+    fn text(outputs: &[Output]) -> Vec<&str> {
+        outputs.iter().map(|o| o.text.as_str()).collect()
+    }
+
+    #[test]
+    fn interrupt_moves_command_to_background() {
+        let (release, gate) = mpsc::channel();
+        let plugins = PluginRegistry::new();
+        plugins.register(GatePlugin(Arc::new(Mutex::new(gate))));
+        let mut ctx = StoreContext::with_plugins(plugins);
+        ctx.mount(
+            "slow",
+            MountConfig::Plugin {
+                kind: "gate".to_string(),
+                config: Value::Null,
+            },
+        )
+        .unwrap();
+
+        let mut core = AsyncReplCore::with_context(ctx);
+        let mut host = ScriptedHost {
+            inputs: ["read /slow/x", "exit"].map(String::from).into(),
+            signals: VecDeque::new(),
+            outputs: Vec::new(),
+            idles: 0,
+            release_after: 3,
+            release,
+        };
+
+        let reason = block_on(core.run(&mut host)).unwrap();
+        assert_eq!(reason, ExitReason::UserExit);
+
+        let out = text(&host.outputs);
+        let interrupted = out
+            .iter()
+            .position(|t| t.contains("continues in the background"))
+            .unwrap();
+        let finished = out
+            .iter()
+            .position(|t| *t == "[job 0] read /slow/x")
+            .unwrap();
+        assert!(interrupted < finished);
+        assert_eq!(strip_ansi_codes(out[finished + 1]), "\"released\"");
+        assert_eq!(out.last(), Some(&"Goodbye!"));
+    }
+
+    #[test]
+    fn blocking_host_runs_commands() {
+        let mut core = AsyncReplCore::new();
+        let mut host = crate::io::TestHost::new();
+        host.queue_inputs(["cd /ctx", "pwd", "exit"]);
+
+        let reason = core.run_blocking(&mut host).unwrap();
+        assert_eq!(reason, ExitReason::UserExit);
+        assert!(host.output_text().contains("/ctx"));
+        assert_eq!(core.pending(), 0);
+    }
+}
@@ -0,0 +1,304 @@
+//! Background reads.
+//!
+//! `bg read <path>` starts a read on its own thread and returns a job id at
+//! once, so a slow read, such as an HTTP broker handle executing its
+//! request, does not hold up the session. `jobs` lists the jobs, `wait <id>`
+//! blocks until one finishes and `result <id>` shows its value if it has.
+//!
+//! Every mounted store is shared between the session and its jobs behind a
+//! lock. A job holds the lock of the mount it reads from while the read
+//! runs, so commands on that mount wait for it; commands on other mounts go
+//! ahead. Jobs read the mount directly, so redirects do not apply.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use structfs_core_store::{
+    mount_store::{MountConfig, StoreFactory},
+    overlay_store::StoreBox,
+    Deleter, Error as CoreError, Lister, NoCodec, Path, Reader, Record, Store, Value, Writer,
+};
+
+/// A mounted store shared between the session and background jobs.
+#[derive(Clone)]
+pub(crate) struct SharedStore(Arc<Mutex<StoreBox>>);
+
+impl SharedStore {
+    fn lock(&self) -> std::sync::MutexGuard<'_, StoreBox> {
+        // A job that panicked mid-read leaves the store usable
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Reader for SharedStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, CoreError> {
+        self.lock().read(from)
+    }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+}
+
+impl Writer for SharedStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, CoreError> {
+        self.lock().write(to, data)
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl Lister for SharedStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, CoreError> {
+        Store::list(&mut *self.lock(), path)
+    }
+}
+
+impl Deleter for SharedStore {
+    fn delete(&mut self, path: &Path) -> Result<(), CoreError> {
+        Store::delete(&mut *self.lock(), path)
+    }
+}
+
+/// Wraps each store a factory creates in a [`SharedStore`], keeping the
+/// last one so the context can record which mount it went to.
+pub(crate) struct SharingFactory<F> {
+    inner: F,
+    created: Arc<Mutex<Option<SharedStore>>>,
+}
+
+impl<F> SharingFactory<F> {
+    pub(crate) fn new(inner: F) -> (Self, Arc<Mutex<Option<SharedStore>>>) {
+        let created = Arc::new(Mutex::new(None));
+        let factory = Self {
+            inner,
+            created: Arc::clone(&created),
+        };
+        (factory, created)
+    }
+}
+
+impl<F: StoreFactory> StoreFactory for SharingFactory<F> {
+    fn create(&self, config: &MountConfig) -> Result<StoreBox, CoreError> {
+        let shared = SharedStore(Arc::new(Mutex::new(self.inner.create(config)?)));
+        *self.created.lock().unwrap() = Some(shared.clone());
+        Ok(Box::new(shared))
+    }
+}
+
+/// How a background job ended.
+pub type JobOutcome = Result<Option<Value>, String>;
+
+enum JobState {
+    Running(JoinHandle<JobOutcome>),
+    Done(JobOutcome),
+}
+
+/// A background read.
+pub struct BackgroundJob {
+    /// The command as typed.
+    pub line: String,
+    started: Instant,
+    finished: Option<Duration>,
+    state: JobState,
+}
+
+impl BackgroundJob {
+    /// Whether the read has finished.
+    pub fn is_done(&self) -> bool {
+        match &self.state {
+            JobState::Running(handle) => handle.is_finished(),
+            JobState::Done(_) => true,
+        }
+    }
+
+    /// How long the read ran, or has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+
+    /// The outcome, if the read has finished.
+    pub fn outcome(&mut self) -> Option<&JobOutcome> {
+        if !self.is_done() {
+            return None;
+        }
+        Some(self.wait())
+    }
+
+    /// Block until the read finishes, then return its outcome.
+    pub fn wait(&mut self) -> &JobOutcome {
+        if let JobState::Running(_) = self.state {
+            let JobState::Running(handle) =
+                std::mem::replace(&mut self.state, JobState::Done(Ok(None)))
+            else {
+                unreachable!();
+            };
+            let outcome = handle
+                .join()
+                .unwrap_or_else(|_| Err("background read panicked".to_string()));
+            self.finished = Some(self.started.elapsed());
+            self.state = JobState::Done(outcome);
+        }
+        match &self.state {
+            JobState::Done(outcome) => outcome,
+            JobState::Running(_) => unreachable!(),
+        }
+    }
+}
+
+/// The session's background jobs, by id. Ids start at 1.
+#[derive(Default)]
+pub struct BackgroundJobs {
+    jobs: BTreeMap<u64, BackgroundJob>,
+    next_id: u64,
+}
+
+impl BackgroundJobs {
+    /// Start reading `path` from `store` on a new thread.
+    pub(crate) fn spawn_read(&mut self, line: &str, mut store: SharedStore, path: Path) -> u64 {
+        let handle = thread::spawn(move || {
+            let record = store.read(&path).map_err(|e| e.to_string())?;
+            record
+                .map(|record| record.into_value(&NoCodec))
+                .transpose()
+                .map_err(|e| e.to_string())
+        });
+        self.next_id += 1;
+        self.jobs.insert(
+            self.next_id,
+            BackgroundJob {
+                line: line.to_string(),
+                started: Instant::now(),
+                finished: None,
+                state: JobState::Running(handle),
+            },
+        );
+        self.next_id
+    }
+
+    /// The job with `id`.
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut BackgroundJob> {
+        self.jobs.get_mut(&id)
+    }
+
+    /// Every job, oldest first.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u64, &mut BackgroundJob)> {
+        self.jobs.iter_mut().map(|(id, job)| (*id, job))
+    }
+
+    /// Whether there are no jobs.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_context::StoreContext;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+
+    /// Memory mounts block reads of their root and `report` until
+    /// released; other mounts answer `x` at once.
+    struct GateFactory(Arc<Mutex<Receiver<()>>>);
+
+    struct Gated(Arc<Mutex<Receiver<()>>>);
+    struct Quick;
+
+    impl Reader for Gated {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, CoreError> {
+            // Anything else, such as the docs probe on mount, finds nothing
+            if !from.is_empty() && from[0] != "report" {
+                return Ok(None);
+            }
+            self.0.lock().unwrap().recv().unwrap();
+            Ok(Some(Record::parsed(Value::from("slow"))))
+        }
+    }
+
+    impl Writer for Gated {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, CoreError> {
+            Ok(to.clone())
+        }
+    }
+
+    impl Reader for Quick {
+        fn read(&mut self, from: &Path) -> Result<Option<Record>, CoreError> {
+            Ok((from.len() == 1 && from[0] == "x").then(|| Record::parsed(Value::from("fast"))))
+        }
+    }
+
+    impl Writer for Quick {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, CoreError> {
+            Ok(to.clone())
+        }
+    }
+
+    impl StoreFactory for GateFactory {
+        fn create(&self, config: &MountConfig) -> Result<StoreBox, CoreError> {
+            Ok(match config {
+                MountConfig::Memory => Box::new(Gated(Arc::clone(&self.0))),
+                _ => Box::new(Quick),
+            })
+        }
+    }
+
+    fn context() -> (StoreContext<GateFactory>, Sender<()>) {
+        let (release, gate) = channel();
+        let mut ctx = StoreContext::with_factory(GateFactory(Arc::new(Mutex::new(gate))));
+        ctx.mount("slow", MountConfig::Memory).unwrap();
+        ctx.mount("fast", MountConfig::Sys).unwrap();
+        (ctx, release)
+    }
+
+    #[test]
+    fn background_reads_do_not_block_other_mounts() {
+        let (mut ctx, release) = context();
+        let id = ctx
+            .spawn_background_read("read /slow/report", &Path::parse("slow/report").unwrap())
+            .unwrap();
+        assert_eq!(id, 1);
+
+        // The session keeps working while the job waits on its store
+        let fast = ctx.read(&Path::parse("fast/x").unwrap()).unwrap();
+        assert_eq!(fast, Some(Value::from("fast")));
+        assert!(!ctx.jobs().get_mut(id).unwrap().is_done());
+        assert!(ctx.jobs().get_mut(id).unwrap().outcome().is_none());
+
+        release.send(()).unwrap();
+        let job = ctx.jobs().get_mut(id).unwrap();
+        assert_eq!(job.wait(), &Ok(Some(Value::from("slow"))));
+        assert!(job.is_done());
+        assert_eq!(job.line, "read /slow/report");
+    }
+
+    #[test]
+    fn mounts_written_through_the_context_are_shared() {
+        let (mut ctx, release) = context();
+        ctx.write(
+            &Path::parse("ctx/mounts/later").unwrap(),
+            Value::Map([("type".to_string(), Value::from("memory"))].into()),
+        )
+        .unwrap();
+        let id = ctx
+            .spawn_background_read("read /later", &Path::parse("later").unwrap())
+            .unwrap();
+        release.send(()).unwrap();
+        assert_eq!(
+            ctx.jobs().get_mut(id).unwrap().wait(),
+            &Ok(Some(Value::from("slow")))
+        );
+
+        // Unmounted paths have nothing to read from
+        ctx.unmount("later").unwrap();
+        assert!(ctx
+            .spawn_background_read("read /later", &Path::parse("later").unwrap())
+            .is_err());
+    }
+}
@@ -6,10 +6,18 @@
 use nu_ansi_term::{Color, Style};
 use serde_json::Value as JsonValue;
 
+use structfs_core_store::dump::{DumpFormat, DumpReader, DumpWriter};
+use structfs_core_store::stats::DEFAULT_LARGEST;
 use structfs_core_store::{Path, Value};
 use structfs_serde_store::{json_to_value, value_to_json};
 
-use crate::store_context::{is_register_path, StoreContext};
+use crate::confirm;
+use crate::diagnostics;
+use crate::docs_view;
+use crate::eval;
+use crate::fixtures::Fixture;
+use crate::pretty::{format_value, PrettyOptions};
+use crate::store_context::{is_register_path, StoreContext, MASK};
 
 /// Result of executing a command
 #[derive(Debug)]
@@ -65,6 +73,50 @@ pub fn execute(input: &str, ctx: &mut StoreContext) -> CommandResult {
     execute_command(input, ctx)
 }
 
+/// The path of a `write --secret <path>` line with no value, which the REPL
+/// core completes by prompting for the value with hidden input.
+pub fn secret_write_path(input: &str) -> Option<&str> {
+    let (command, flags, rest) = split_write_flags(input)?;
+    if !matches!(command.as_str(), "write" | "set" | "w") || !flags.contains(&"--secret") {
+        return None;
+    }
+    let [path] = rest[..] else {
+        return None;
+    };
+    Some(path)
+}
+
+/// Complete a `write --secret <path>` line with the value typed at the prompt.
+pub fn with_secret_value(input: &str, secret: &str) -> String {
+    format!("{} {}", input.trim(), JsonValue::String(secret.to_string()))
+}
+
+/// `input` as it may be shown back to the user, without the value of a
+/// `write --secret`.
+pub fn redact_secret(input: &str) -> String {
+    match split_write_flags(input) {
+        Some((command, flags, rest))
+            if matches!(command.as_str(), "write" | "set" | "w")
+                && flags.contains(&"--secret")
+                && rest.len() > 1 =>
+        {
+            let words: Vec<&str> = input.split_whitespace().collect();
+            format!("{} {} {}", words[..=flags.len()].join(" "), rest[0], MASK)
+        }
+        _ => input.to_string(),
+    }
+}
+
+/// The lowercased command of `input`, the `--` flags after it and the
+/// remaining words.
+fn split_write_flags(input: &str) -> Option<(String, Vec<&str>, Vec<&str>)> {
+    let mut words = input.split_whitespace();
+    let command = words.next()?.to_lowercase();
+    let words: Vec<&str> = words.collect();
+    let flags = words.iter().take_while(|w| w.starts_with("--")).count();
+    Some((command, words[..flags].to_vec(), words[flags..].to_vec()))
+}
+
 fn parse_register_capture(input: &str) -> Option<(String, &str)> {
     if !input.starts_with('@') {
         return None;
@@ -86,7 +138,21 @@ fn parse_register_capture(input: &str) -> Option<(String, &str)> {
     let first_word = remaining.split_whitespace().next()?;
     let is_command = matches!(
         first_word.to_lowercase().as_str(),
-        "read" | "get" | "r" | "write" | "set" | "w" | "cd" | "pwd" | "mounts" | "ls"
+        "read"
+            | "get"
+            | "r"
+            | "eval"
+            | "write"
+            | "set"
+            | "w"
+            | "cd"
+            | "pwd"
+            | "mounts"
+            | "ls"
+            | "bg"
+            | "jobs"
+            | "wait"
+            | "result"
     );
 
     if is_command {
@@ -144,7 +210,7 @@ fn execute_with_capture(register_name: &str, input: &str, ctx: &mut StoreContext
     }
 }
 
-fn strip_ansi_codes(s: &str) -> String {
+pub(crate) fn strip_ansi_codes(s: &str) -> String {
     let mut result = String::new();
     let mut chars = s.chars().peekable();
 
@@ -168,7 +234,10 @@ fn contains_dereference(path_str: &str) -> bool {
     path_str.contains("*@")
 }
 
-fn resolve_dereference(path_str: &str, ctx: &mut StoreContext) -> Result<String, String> {
+pub(crate) fn resolve_dereference(
+    path_str: &str,
+    ctx: &mut StoreContext,
+) -> Result<String, String> {
     if !contains_dereference(path_str) {
         return Ok(path_str.to_string());
     }
@@ -219,10 +288,19 @@ fn execute_command(input: &str, ctx: &mut StoreContext) -> CommandResult {
         "help" | "?" => cmd_help(args, ctx),
         "exit" | "quit" | "q" => CommandResult::Exit,
         "read" | "get" | "r" => cmd_read(args, ctx),
+        "eval" => cmd_eval(args, ctx),
         "write" | "set" | "w" => cmd_write(args, ctx),
         "cd" => cmd_cd(args, ctx),
         "pwd" => cmd_pwd(ctx),
         "registers" | "regs" => cmd_registers(ctx),
+        "bg" => cmd_bg(args, ctx),
+        "jobs" => cmd_jobs(ctx),
+        "wait" => cmd_job_result(args, ctx, true),
+        "result" => cmd_job_result(args, ctx, false),
+        "export" => cmd_export(args, ctx),
+        "import" => cmd_import(args, ctx),
+        "stats" => cmd_stats(args, ctx),
+        "test" => cmd_test(args),
         _ => CommandResult::Error(format!(
             "Unknown command: '{}'. Type 'help' for available commands.",
             command
@@ -248,21 +326,77 @@ pub fn format_help() -> String {
             "[path|@reg]",
             "Read Value from path or register (alias: get, r)",
         ),
+        (
+            "",
+            "[--depth N]",
+            "Expand N levels; --limit N shows N entries (0: all)",
+        ),
+        ("", "[--reveal]", "Show secrets instead of masking them"),
+        ("", "[--json]", "Show docs as JSON instead of help text"),
+        (
+            "eval",
+            "<expr>",
+            "Compute a value, e.g. eval (read /a) + 1, read /x | len",
+        ),
         (
             "write",
             "<path> <json|@reg>",
             "Write Value to path (alias: set, w)",
         ),
+        (
+            "",
+            "[--secret]",
+            "Prompt for the value with hidden input; read masks it",
+        ),
+        (
+            "",
+            "[--yes]",
+            "Replace many values or a mount point's data without confirming",
+        ),
         ("cd", "<path>", "Change current path"),
         ("pwd", "", "Print current path"),
         ("registers", "", "List all registers (alias: regs)"),
         ("", "", ""),
+        (
+            "bg",
+            "read <path>",
+            "Read in the background, printing a job id",
+        ),
+        ("jobs", "", "List background jobs"),
+        (
+            "wait",
+            "<id>",
+            "Wait for a background job and show its value",
+        ),
+        (
+            "result",
+            "<id>",
+            "Show a background job's value if it has finished",
+        ),
+        ("", "", ""),
+        (
+            "export",
+            "<path> <file>",
+            "Dump a subtree to a file (.cbor for CBOR, else JSONL)",
+        ),
+        ("import", "<file>", "Write a dump back where it came from"),
+        (
+            "stats",
+            "<path> [n]",
+            "Count records and bytes in a subtree, listing the n largest",
+        ),
+        (
+            "test",
+            "<file>",
+            "Run a fixture file's cases against fresh mounts",
+        ),
+        ("", "", ""),
         ("help", "[topic]", "Show help (try: help ctx/http)"),
         ("exit", "", "Exit the REPL (alias: quit, q)"),
     ];
 
     for (cmd, args, desc) in commands {
-        if cmd.is_empty() {
+        if cmd.is_empty() && args.is_empty() {
             help.push('\n');
         } else {
             help.push_str(&format!(
@@ -320,9 +454,40 @@ pub fn format_help() -> String {
 }
 
 fn cmd_read(args: &str, ctx: &mut StoreContext) -> CommandResult {
-    let path_str = if args.is_empty() { "." } else { args };
+    // `read <path> | f ...` pipes the value through expression functions
+    let (args, pipeline) = match args.find('|') {
+        Some(i) => (args[..i].trim(), Some(&args[i..])),
+        None => (args, None),
+    };
 
-    let path_str = match resolve_dereference(path_str, ctx) {
+    let (path_str, options, flags) = match parse_read_args(args) {
+        Ok(parsed) => parsed,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let reveal = flags.reveal;
+
+    if let Some(pipeline) = pipeline {
+        let holds_secret = ctx
+            .resolve_path(&path_str)
+            .is_ok_and(|path| ctx.holds_secret(&path));
+        if holds_secret && !reveal {
+            return CommandResult::Error(format!(
+                "{} holds a secret; pass --reveal to pipe it",
+                path_str
+            ));
+        }
+        let value = eval::evaluate(&format!("read {}", path_str), ctx)
+            .and_then(|value| eval::apply_pipeline(value, pipeline, ctx));
+        return match value {
+            Ok(value) => CommandResult::ok_with_capture(
+                format_value(&value_to_json(value.clone()), &options),
+                value,
+            ),
+            Err(e) => CommandResult::Error(e),
+        };
+    }
+
+    let path_str = match resolve_dereference(&path_str, ctx) {
         Ok(p) => p,
         Err(e) => return CommandResult::Error(e),
     };
@@ -331,7 +496,7 @@ fn cmd_read(args: &str, ctx: &mut StoreContext) -> CommandResult {
         match ctx.read_register(&path_str) {
             Ok(Some(value)) => {
                 let json = value_to_json(value.clone());
-                let mut output = format_json(&json);
+                let mut output = format_value(&json, &options);
 
                 if let Value::String(s) = &value {
                     if s.starts_with('/') || s.contains('/') {
@@ -363,24 +528,126 @@ fn cmd_read(args: &str, ctx: &mut StoreContext) -> CommandResult {
 
     match ctx.read(&path) {
         Ok(Some(value)) => {
+            // The capture is masked too, so a register cannot carry a secret
+            // somewhere that prints it
+            let value = if reveal {
+                value
+            } else {
+                ctx.mask_secrets(&path, value)
+            };
+            if matches!(&value, Value::String(s) if s == MASK) {
+                return CommandResult::ok_with_capture(MASK, value);
+            }
+            if !flags.json {
+                if let Some(docs) = docs_view::render_docs(&value) {
+                    return CommandResult::ok_with_capture(docs, value);
+                }
+            }
             let json = value_to_json(value.clone());
-            CommandResult::ok_with_capture(format_json(&json), value)
+            CommandResult::ok_with_capture(format_value(&json, &options), value)
         }
-        Ok(None) => CommandResult::ok_with_capture(
-            format!(
+        Ok(None) => {
+            let mut output = format!(
                 "{}",
                 Color::Yellow.paint("null (path does not exist or no store mounted)")
-            ),
-            Value::Null,
+            );
+            if let Some(hint) = diagnostics::did_you_mean(ctx, &path) {
+                output.push_str(&format!("\n{}", Color::Cyan.dimmed().paint(hint)));
+            }
+            CommandResult::ok_with_capture(output, Value::Null)
+        }
+        Err(e) => CommandResult::Error(diagnostics::render_error("Read", &e, &path, ctx)),
+    }
+}
+
+fn cmd_eval(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Error("Usage: eval <expression>".to_string());
+    }
+
+    match eval::evaluate(args, ctx) {
+        Ok(value) => CommandResult::ok_with_capture(
+            format_value(&value_to_json(value.clone()), &PrettyOptions::default()),
+            value,
         ),
-        Err(e) => CommandResult::Error(format!("Read error: {}", e)),
+        Err(e) => CommandResult::Error(format!("Eval error: {}", e)),
+    }
+}
+
+/// Split `read` arguments into the path, the `--depth`/`--limit` flags and
+/// whether `--reveal` was given.
+///
+/// `--limit 0` shows every entry.
+/// Switches of `read` besides the printing limits.
+#[derive(Debug, Default, PartialEq)]
+struct ReadFlags {
+    /// `--reveal`: show secrets.
+    reveal: bool,
+    /// `--json`: show docs maps as JSON instead of help text.
+    json: bool,
+}
+
+fn parse_read_args(args: &str) -> Result<(String, PrettyOptions, ReadFlags), String> {
+    let mut options = PrettyOptions::default();
+    let mut path = None;
+    let mut flags = ReadFlags::default();
+    let mut words = args.split_whitespace();
+
+    while let Some(word) = words.next() {
+        match word {
+            "--reveal" => {
+                flags.reveal = true;
+                continue;
+            }
+            "--json" => {
+                flags.json = true;
+                continue;
+            }
+            _ => {}
+        }
+        let (flag, inline) = match word.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (word, None),
+        };
+        if flag != "--depth" && flag != "--limit" {
+            if path.replace(word).is_some() {
+                return Err(format!("Unexpected argument '{}'", word));
+            }
+            continue;
+        }
+
+        let n = inline
+            .or_else(|| words.next())
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(|| format!("{} expects a number", flag))?;
+        if flag == "--depth" {
+            options.depth = Some(n);
+        } else {
+            options.limit = (n > 0).then_some(n);
+        }
     }
+
+    Ok((path.unwrap_or(".").to_string(), options, flags))
 }
 
 fn cmd_write(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    let mut args = args;
+    let (mut secret, mut yes) = (false, false);
+    while let Some((flag, rest)) = split_flag(args, &["--secret", "--yes"]) {
+        match flag {
+            "--secret" => secret = true,
+            _ => yes = true,
+        }
+        args = rest;
+    }
+
     let (path_str, value_str) =
         match parse_write_args(args) {
             Some(parts) => parts,
+            None if secret => return CommandResult::Error(
+                "Usage: write --secret <path>\nThe REPL prompts for the value without echoing it"
+                    .to_string(),
+            ),
             None => return CommandResult::Error(
                 "Usage: write <path> <json|@register>\nExample: write /data {\"name\": \"Alice\"}"
                     .to_string(),
@@ -413,6 +680,9 @@ fn cmd_write(args: &str, ctx: &mut StoreContext) -> CommandResult {
 
     // Check if destination is a register
     if is_register_path(&path_str) {
+        if secret {
+            return CommandResult::Error("Secrets cannot be written to registers".to_string());
+        }
         match ctx.write_register(&path_str, value) {
             Ok(result_path) => {
                 let path_string = format_path(&result_path);
@@ -434,7 +704,18 @@ fn cmd_write(args: &str, ctx: &mut StoreContext) -> CommandResult {
         Err(e) => return CommandResult::Error(format!("Invalid path: {}", e)),
     };
 
-    match ctx.write(&path, value) {
+    if !yes {
+        if let Some(message) = write_confirmation(&path, &value, ctx) {
+            return CommandResult::Error(message);
+        }
+    }
+
+    let written = if secret {
+        ctx.write_secret(&path, value)
+    } else {
+        ctx.write(&path, value)
+    };
+    match written {
         Ok(result_path) => {
             // OverlayStore already returns the full path with mount prefix
             let path_string = format_path(&result_path);
@@ -451,10 +732,36 @@ fn cmd_write(args: &str, ctx: &mut StoreContext) -> CommandResult {
             };
             CommandResult::ok_with_capture(output, Value::String(path_string))
         }
-        Err(e) => CommandResult::Error(format!("Write error: {}", e)),
+        Err(e) => CommandResult::Error(diagnostics::render_error("Write", &e, &path, ctx)),
     }
 }
 
+/// `args` without a leading flag from `flags`, and the flag.
+fn split_flag<'a>(args: &'a str, flags: &[&'static str]) -> Option<(&'static str, &'a str)> {
+    flags
+        .iter()
+        .find_map(|&flag| match args.strip_prefix(flag) {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+                Some((flag, rest.trim_start()))
+            }
+            _ => None,
+        })
+}
+
+/// Why writing `value` to `path` needs `--yes`, if it does. A path that
+/// cannot be read has nothing to replace.
+fn write_confirmation(path: &Path, value: &Value, ctx: &mut StoreContext) -> Option<String> {
+    let old = ctx.read(path).ok().flatten()?;
+    let mount_point = ctx.mount_of(path).as_ref() == Some(path);
+    confirm::confirmation(
+        &format_path(path),
+        &old,
+        value,
+        ctx.confirm_threshold(),
+        mount_point,
+    )
+}
+
 fn cmd_cd(args: &str, ctx: &mut StoreContext) -> CommandResult {
     let path_str = if args.is_empty() { "/" } else { args };
 
@@ -477,6 +784,249 @@ fn cmd_pwd(ctx: &mut StoreContext) -> CommandResult {
     CommandResult::ok_with_capture(&path_str, Value::String(path_str.clone()))
 }
 
+fn cmd_bg(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    let mut parts = args.splitn(2, char::is_whitespace);
+    let command = parts.next().unwrap_or("");
+    let path_str = parts.next().unwrap_or("").trim();
+    if !matches!(command.to_lowercase().as_str(), "read" | "get" | "r") || path_str.is_empty() {
+        return CommandResult::Error("Usage: bg read <path>".to_string());
+    }
+
+    let path_str = match resolve_dereference(path_str, ctx) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let path = match ctx.resolve_path(&path_str) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(format!("Invalid path: {}", e)),
+    };
+
+    match ctx.spawn_background_read(args, &path) {
+        Ok(id) => CommandResult::ok_with_capture(
+            format!(
+                "{} {}",
+                Color::Cyan.paint(format!("[{}]", id)),
+                Color::White
+                    .dimmed()
+                    .paint(format!("read {}", format_path(&path)))
+            ),
+            Value::Integer(id as i64),
+        ),
+        Err(e) => {
+            CommandResult::Error(diagnostics::render_error("Background read", &e, &path, ctx))
+        }
+    }
+}
+
+fn cmd_jobs(ctx: &mut StoreContext) -> CommandResult {
+    let jobs = ctx.jobs();
+    if jobs.is_empty() {
+        return CommandResult::ok_display(format!(
+            "{}",
+            Color::Yellow.paint("No background jobs. Use 'bg read <path>' to start one.")
+        ));
+    }
+
+    let mut output = String::new();
+    let mut listing = Vec::new();
+    for (id, job) in jobs.iter_mut() {
+        let state = match job.outcome() {
+            None => "running",
+            Some(Ok(_)) => "done",
+            Some(Err(_)) => "failed",
+        };
+        let secs = job.elapsed().as_secs_f64();
+        output.push_str(&format!(
+            "  {} {:<8} {:>7.1}s  {}\n",
+            Color::Cyan.paint(format!("[{}]", id)),
+            state,
+            secs,
+            job.line
+        ));
+        listing.push(Value::Map(
+            [
+                ("id".to_string(), Value::Integer(id as i64)),
+                ("command".to_string(), Value::String(job.line.clone())),
+                ("state".to_string(), Value::String(state.to_string())),
+                ("elapsed_secs".to_string(), Value::Float(secs)),
+            ]
+            .into(),
+        ));
+    }
+    CommandResult::ok_with_capture(output.trim_end().to_string(), Value::Array(listing))
+}
+
+fn cmd_job_result(args: &str, ctx: &mut StoreContext, wait: bool) -> CommandResult {
+    let usage = if wait {
+        "Usage: wait <id>"
+    } else {
+        "Usage: result <id>"
+    };
+    let Ok(id) = args
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<u64>()
+    else {
+        return CommandResult::Error(usage.to_string());
+    };
+    let Some(job) = ctx.jobs().get_mut(id) else {
+        return CommandResult::Error(format!("No background job {}", id));
+    };
+
+    let outcome = if wait {
+        Some(job.wait())
+    } else {
+        job.outcome()
+    };
+    match outcome {
+        None => CommandResult::ok_display(format!(
+            "{}",
+            Color::Yellow.paint(format!("job {} is still running (use 'wait {}')", id, id))
+        )),
+        Some(Ok(Some(value))) => {
+            let value = value.clone();
+            let json = value_to_json(value.clone());
+            CommandResult::ok_with_capture(format_value(&json, &PrettyOptions::default()), value)
+        }
+        Some(Ok(None)) => CommandResult::ok_with_capture(
+            format!("{}", Color::Yellow.paint("null (path does not exist)")),
+            Value::Null,
+        ),
+        Some(Err(e)) => CommandResult::Error(format!("Background read error: {}", e)),
+    }
+}
+
+/// The dump encoding for `file`, by its extension.
+fn dump_format(file: &str) -> DumpFormat {
+    std::path::Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(DumpFormat::from_extension)
+        .unwrap_or_default()
+}
+
+fn cmd_export(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let [path_str, file] = parts[..] else {
+        return CommandResult::Error("Usage: export <path> <file>".to_string());
+    };
+
+    let path_str = match resolve_dereference(path_str, ctx) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let path = match ctx.resolve_path(&path_str) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(format!("Invalid path: {}", e)),
+    };
+    let output = match std::fs::File::create(file) {
+        Ok(f) => std::io::BufWriter::new(f),
+        Err(e) => return CommandResult::Error(format!("Cannot create '{}': {}", file, e)),
+    };
+
+    let mut dump = DumpWriter::new(output, dump_format(file));
+    match ctx.export(&path, &mut dump) {
+        Ok(count) => CommandResult::ok_with_capture(
+            format!(
+                "Exported {} record(s) from {} to {}",
+                count,
+                format_path(&path),
+                file
+            ),
+            Value::Integer(count as i64),
+        ),
+        Err(e) => CommandResult::Error(diagnostics::render_error("Export", &e, &path, ctx)),
+    }
+}
+
+fn cmd_import(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    let file = args.trim();
+    if file.is_empty() || file.contains(char::is_whitespace) {
+        return CommandResult::Error("Usage: import <file>".to_string());
+    }
+    let input = match std::fs::File::open(file) {
+        Ok(f) => f,
+        Err(e) => return CommandResult::Error(format!("Cannot open '{}': {}", file, e)),
+    };
+
+    let mut dump = DumpReader::new(input, dump_format(file));
+    match ctx.import(&mut dump) {
+        Ok(count) => CommandResult::ok_with_capture(
+            format!("Imported {} record(s) from {}", count, file),
+            Value::Integer(count as i64),
+        ),
+        Err(e) => CommandResult::Error(format!("Import error: {}", e)),
+    }
+}
+
+fn cmd_stats(args: &str, ctx: &mut StoreContext) -> CommandResult {
+    let usage = || CommandResult::Error("Usage: stats <path> [n]".to_string());
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let (path_str, largest) = match parts[..] {
+        [path] => (path, DEFAULT_LARGEST),
+        [path, n] => match n.parse() {
+            Ok(n) => (path, n),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    let path_str = match resolve_dereference(path_str, ctx) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let path = match ctx.resolve_path(&path_str) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(format!("Invalid path: {}", e)),
+    };
+
+    match ctx.stats(&path, largest) {
+        Ok(stats) => {
+            let value = stats.to_value();
+            let json = value_to_json(value.clone());
+            CommandResult::ok_with_capture(format_value(&json, &PrettyOptions::default()), value)
+        }
+        Err(e) => CommandResult::Error(diagnostics::render_error("Stats", &e, &path, ctx)),
+    }
+}
+
+fn cmd_test(args: &str) -> CommandResult {
+    let file = args.trim();
+    if file.is_empty() || file.contains(char::is_whitespace) {
+        return CommandResult::Error("Usage: test <file>".to_string());
+    }
+    let fixture = match Fixture::load(file) {
+        Ok(f) => f,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    let outcomes = fixture.run();
+    let failed = outcomes.iter().filter(|o| o.failure.is_some()).count();
+    let mut output = String::new();
+    for outcome in &outcomes {
+        match &outcome.failure {
+            None => output.push_str(&format!("{} {}\n", Color::Green.paint("✓"), outcome.name)),
+            Some(failure) => {
+                output.push_str(&format!("{} {}\n", Color::Red.paint("✗"), outcome.name));
+                for line in failure.lines() {
+                    output.push_str(&format!("    {}\n", line));
+                }
+            }
+        }
+    }
+    output.push_str(&format!(
+        "\n{} passed, {} failed",
+        outcomes.len() - failed,
+        failed
+    ));
+
+    if failed > 0 {
+        CommandResult::Error(output)
+    } else {
+        CommandResult::ok_with_capture(output, Value::Integer(outcomes.len() as i64))
+    }
+}
+
 fn cmd_registers(ctx: &mut StoreContext) -> CommandResult {
     let registers = ctx.list_registers();
     if registers.is_empty() {
@@ -725,15 +1275,7 @@ fn format_help_value_body(map: &std::collections::BTreeMap<String, Value>) -> St
     // Handle paths (from store docs)
     if let Some(Value::Map(paths)) = map.get("paths") {
         output.push_str(&format!("{}\n", Style::new().bold().paint("Paths")));
-        for (path, desc) in paths {
-            if let Value::String(d) = desc {
-                output.push_str(&format!(
-                    "  {}\n    {}\n",
-                    arg_style.paint(path),
-                    Color::White.dimmed().paint(d)
-                ));
-            }
-        }
+        output.push_str(&docs_view::table(paths));
         output.push('\n');
     }
 
@@ -1639,6 +2181,14 @@ mod tests {
         assert!(ctx.current_path().is_empty());
     }
 
+    #[test]
+    fn execute_cd_parent_at_root() {
+        let mut ctx = StoreContext::new();
+        let result = execute("cd ..", &mut ctx);
+        assert!(!matches!(result, CommandResult::Error(_)));
+        assert!(ctx.current_path().is_empty());
+    }
+
     #[test]
     fn execute_registers_empty() {
         let mut ctx = StoreContext::new();
@@ -1711,6 +2261,112 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_read_args_flags() {
+        assert_eq!(
+            parse_read_args("").unwrap(),
+            (
+                ".".to_string(),
+                PrettyOptions::default(),
+                ReadFlags::default()
+            )
+        );
+        assert_eq!(
+            parse_read_args("--depth 2 /data --limit=0 --reveal --json").unwrap(),
+            (
+                "/data".to_string(),
+                PrettyOptions {
+                    depth: Some(2),
+                    limit: None
+                },
+                ReadFlags {
+                    reveal: true,
+                    json: true
+                }
+            )
+        );
+        assert!(parse_read_args("/data --depth").is_err());
+        assert!(parse_read_args("/data --limit x").is_err());
+        assert!(parse_read_args("/a /b").is_err());
+    }
+
+    #[test]
+    fn execute_read_renders_docs() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute(
+            "write /test/docs {\"title\": \"Queue\", \"description\": \"Jobs\", \"paths\": {\"read /next\": \"Take a job\"}}",
+            &mut ctx,
+        );
+
+        let read = |cmd: &str, ctx: &mut StoreContext| match execute(cmd, ctx) {
+            CommandResult::Ok { display, capture } => {
+                assert!(matches!(capture, Some(Value::Map(_))));
+                strip_ansi_codes(&display.unwrap())
+            }
+            other => panic!("Expected Ok, got {:?}", other),
+        };
+        let text = read("read /test/docs", &mut ctx);
+        assert!(text.starts_with("Queue\n\nJobs\n\nPaths\n"), "{}", text);
+        assert!(text.contains("  read /next Take a job"), "{}", text);
+        assert!(read("read /test/docs --json", &mut ctx).contains("\"title\""));
+    }
+
+    #[test]
+    fn execute_read_truncates_display_not_capture() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        let items: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        execute(&format!("write /test/list [{}]", items.join(",")), &mut ctx);
+
+        match execute("read /test/list --limit 2", &mut ctx) {
+            CommandResult::Ok { display, capture } => {
+                let text = strip_ansi_codes(&display.unwrap());
+                assert!(text.contains("… 3 more"), "got {}", text);
+                assert!(!text.contains('4'));
+                assert!(matches!(capture, Some(Value::Array(a)) if a.len() == 5));
+            }
+            _ => panic!("Expected Ok"),
+        }
+    }
+
+    #[test]
+    fn execute_eval_and_read_pipe() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute("write /test/list [1, 2, 3]", &mut ctx);
+
+        match execute("read /test/list | len", &mut ctx) {
+            CommandResult::Ok { capture, .. } => assert_eq!(capture, Some(Value::Integer(3))),
+            _ => panic!("Expected Ok"),
+        }
+        match execute("@total eval sum(read /test/list) * 2", &mut ctx) {
+            CommandResult::Ok { .. } => {}
+            _ => panic!("Expected Ok"),
+        }
+        assert_eq!(
+            ctx.read_register("@total").unwrap(),
+            Some(Value::Integer(12))
+        );
+        assert!(matches!(
+            execute("eval (read /test/list) +", &mut ctx),
+            CommandResult::Error(_)
+        ));
+        assert!(matches!(execute("eval", &mut ctx), CommandResult::Error(_)));
+    }
+
     #[test]
     fn execute_read_register_not_found() {
         let mut ctx = StoreContext::new();
@@ -1742,6 +2398,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn execute_secrets_are_masked() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute("write /test/api {\"user\": \"alice\"}", &mut ctx);
+        let line = with_secret_value("write --secret /test/api/token", "hunter2");
+        assert_eq!(
+            secret_write_path("w --secret /test/api/token"),
+            Some("/test/api/token")
+        );
+        assert_eq!(secret_write_path(&line), None);
+        assert!(!redact_secret(&line).contains("hunter2"));
+        assert!(matches!(execute(&line, &mut ctx), CommandResult::Ok { .. }));
+
+        let read = |cmd: &str, ctx: &mut StoreContext| match execute(cmd, ctx) {
+            CommandResult::Ok { display, .. } => strip_ansi_codes(&display.unwrap()),
+            other => panic!("Expected Ok, got {:?}", other),
+        };
+        assert_eq!(read("read /test/api/token", &mut ctx), MASK);
+        let parent = read("read /test/api", &mut ctx);
+        assert!(
+            parent.contains("alice") && !parent.contains("hunter2"),
+            "{}",
+            parent
+        );
+        assert!(read("read /test/api/token --reveal", &mut ctx).contains("hunter2"));
+        assert!(matches!(
+            execute("read /test/api | keys", &mut ctx),
+            CommandResult::Error(_)
+        ));
+
+        // A plain write replaces the secret
+        execute("write /test/api/token \"public\"", &mut ctx);
+        assert!(read("read /test/api/token", &mut ctx).contains("public"));
+    }
+
     #[test]
     fn execute_write_json_object() {
         let mut ctx = StoreContext::new();
@@ -1754,6 +2450,168 @@ mod tests {
         assert!(matches!(result, CommandResult::Ok { .. }));
     }
 
+    #[test]
+    fn execute_read_suggests_near_misses() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute("write /test/users {\"alice\": 1}", &mut ctx);
+
+        match execute("read /test/usres", &mut ctx) {
+            CommandResult::Ok {
+                display: Some(output),
+                ..
+            } => assert!(strip_ansi_codes(&output).contains("did you mean /test/users?")),
+            _ => panic!("Expected Ok with display"),
+        }
+
+        match execute("write /tset/users 1", &mut ctx) {
+            CommandResult::Error(msg) => {
+                assert!(msg.contains("kind:  no-route"), "{}", msg);
+                assert!(msg.contains("did you mean /test/users?"), "{}", msg);
+            }
+            _ => panic!("Expected Error"),
+        }
+    }
+
+    #[test]
+    fn execute_export_and_import() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute(
+            "write /test/users {\"alice\": {\"age\": 30}, \"bob\": 2}",
+            &mut ctx,
+        );
+
+        for ext in ["jsonl", "cbor"] {
+            let file = std::env::temp_dir().join(format!(
+                "structfs-repl-export-{}.{}",
+                std::process::id(),
+                ext
+            ));
+            let file = file.to_str().unwrap();
+            match execute(&format!("export /test/users {}", file), &mut ctx) {
+                CommandResult::Ok { capture, .. } => assert_eq!(capture, Some(Value::Integer(2))),
+                other => panic!("Expected Ok, got {:?}", other),
+            }
+
+            execute("write /test/users/bob 3", &mut ctx);
+            assert!(matches!(
+                execute(&format!("import {}", file), &mut ctx),
+                CommandResult::Ok { .. }
+            ));
+            assert_eq!(
+                ctx.read(&Path::parse("test/users/bob").unwrap()).unwrap(),
+                Some(Value::Integer(2))
+            );
+            std::fs::remove_file(file).unwrap();
+        }
+
+        assert!(matches!(
+            execute("export /test/users", &mut ctx),
+            CommandResult::Error(_)
+        ));
+        assert!(matches!(
+            execute("import /nonexistent/dump.jsonl", &mut ctx),
+            CommandResult::Error(_)
+        ));
+    }
+
+    #[test]
+    fn execute_stats() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute(
+            "write /test/users {\"alice\": {\"age\": 30}, \"bob\": \"a long name\"}",
+            &mut ctx,
+        );
+
+        let CommandResult::Ok {
+            capture: Some(stats),
+            ..
+        } = execute("stats /test/users 1", &mut ctx)
+        else {
+            panic!("Expected stats");
+        };
+        assert_eq!(
+            stats.get(&Path::parse("entries").unwrap()),
+            Some(&Value::Integer(2))
+        );
+        assert_eq!(
+            stats.get(&Path::parse("largest/0/path").unwrap()),
+            Some(&Value::String("test/users/bob".into()))
+        );
+        assert!(stats.get(&Path::parse("largest/1").unwrap()).is_none());
+
+        assert!(matches!(
+            execute("stats /test/users many", &mut ctx),
+            CommandResult::Error(_)
+        ));
+    }
+
+    #[test]
+    fn execute_background_jobs() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        execute("write /test/report {\"rows\": 3}", &mut ctx);
+
+        match execute("bg read /test/report", &mut ctx) {
+            CommandResult::Ok { capture, .. } => assert_eq!(capture, Some(Value::Integer(1))),
+            _ => panic!("Expected Ok"),
+        }
+        match execute("wait 1", &mut ctx) {
+            CommandResult::Ok { capture, .. } => {
+                let Some(Value::Map(report)) = capture else {
+                    panic!("Expected map");
+                };
+                assert_eq!(report["rows"], Value::Integer(3));
+            }
+            _ => panic!("Expected Ok"),
+        }
+        assert!(matches!(
+            execute("result 1", &mut ctx),
+            CommandResult::Ok {
+                capture: Some(Value::Map(_)),
+                ..
+            }
+        ));
+        match execute("jobs", &mut ctx) {
+            CommandResult::Ok {
+                capture: Some(Value::Array(jobs)),
+                ..
+            } => assert_eq!(jobs.len(), 1),
+            _ => panic!("Expected Ok"),
+        }
+
+        assert!(matches!(
+            execute("result 9", &mut ctx),
+            CommandResult::Error(_)
+        ));
+        assert!(matches!(
+            execute("bg write /x 1", &mut ctx),
+            CommandResult::Error(_)
+        ));
+        assert!(matches!(
+            execute("wait soon", &mut ctx),
+            CommandResult::Error(_)
+        ));
+    }
+
     #[test]
     fn execute_write_invalid_json() {
         let mut ctx = StoreContext::new();
@@ -1768,6 +2626,52 @@ mod tests {
         assert!(matches!(result, CommandResult::Error(_)));
     }
 
+    #[test]
+    fn execute_write_confirms_large_replacements() {
+        let mut ctx = StoreContext::new();
+        ctx.mount(
+            "test",
+            structfs_core_store::mount_store::MountConfig::Memory,
+        )
+        .unwrap();
+        ctx.set_confirm_threshold(2);
+        execute("write /test/users {\"a\": 1, \"b\": 2, \"c\": 3}", &mut ctx);
+
+        // Changing one value goes ahead
+        let result = execute("write /test/users {\"a\": 1, \"b\": 2, \"c\": 4}", &mut ctx);
+        assert!(matches!(result, CommandResult::Ok { .. }));
+
+        match execute("write /test/users {}", &mut ctx) {
+            CommandResult::Error(message) => {
+                assert!(
+                    message.contains("would replace 3 existing values"),
+                    "{}",
+                    message
+                );
+                assert!(message.contains("  - a"), "{}", message);
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+        let result = execute("write --yes /test/users {}", &mut ctx);
+        assert!(matches!(result, CommandResult::Ok { .. }));
+
+        // Mount points always confirm
+        ctx.set_confirm_threshold(0);
+        execute("write --yes /test {\"x\": 1}", &mut ctx);
+        assert!(matches!(
+            execute("write /test {\"x\": 2}", &mut ctx),
+            CommandResult::Error(_)
+        ));
+        assert_eq!(
+            secret_write_path("write --yes --secret /test/token"),
+            Some("/test/token")
+        );
+        assert_eq!(
+            redact_secret("w --secret --yes /test/token \"hunter2\""),
+            format!("w --secret --yes /test/token {}", MASK)
+        );
+    }
+
     #[test]
     fn execute_register_capture() {
         let mut ctx = StoreContext::new();
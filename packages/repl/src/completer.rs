@@ -14,11 +14,20 @@ impl ReplCompleter {
                 "quit".to_string(),
                 "read".to_string(),
                 "write".to_string(),
+                "eval".to_string(),
                 "get".to_string(),
                 "set".to_string(),
                 "cd".to_string(),
                 "pwd".to_string(),
                 "mounts".to_string(),
+                "bg".to_string(),
+                "jobs".to_string(),
+                "wait".to_string(),
+                "result".to_string(),
+                "export".to_string(),
+                "import".to_string(),
+                "stats".to_string(),
+                "test".to_string(),
             ],
         }
     }
@@ -68,9 +77,18 @@ fn command_description(cmd: &str) -> String {
         "exit" | "quit" => "Exit the REPL".to_string(),
         "read" | "get" => "Read from path".to_string(),
         "write" | "set" => "Write to path".to_string(),
+        "eval" => "Compute a value from reads".to_string(),
         "cd" => "Change directory".to_string(),
         "pwd" => "Print working directory".to_string(),
         "mounts" => "List current mounts".to_string(),
+        "bg" => "Read in the background".to_string(),
+        "jobs" => "List background jobs".to_string(),
+        "wait" => "Wait for a background job".to_string(),
+        "result" => "Show a background job's value".to_string(),
+        "export" => "Dump a subtree to a file".to_string(),
+        "import" => "Write a dump back to the store".to_string(),
+        "stats" => "Summarize a subtree's size".to_string(),
+        "test" => "Run a fixture file".to_string(),
         _ => String::new(),
     }
 }
@@ -0,0 +1,192 @@
+//! Confirming writes that replace a lot of existing data.
+//!
+//! Before `write` replaces a value, the REPL reads what is there and diffs
+//! it against the new value leaf by leaf. When the write would remove or
+//! change at least the [confirmation threshold] of existing values, or
+//! would replace the data of a mount point, the write is refused with a
+//! summary of the diff and has to be run again with `--yes`:
+//!
+//! ```text
+//! write /data would replace 42 existing values (mount point)
+//!   - users/alice/age
+//!   - users/alice/name
+//!   - users/bob/age
+//!   - users/bob/name
+//!   - users/carol/age
+//!   ... and 36 more
+//!   ~ version
+//! Run again with --yes to write anyway
+//! ```
+//!
+//! [confirmation threshold]: crate::store_context::StoreContext::confirm_threshold
+
+use std::collections::BTreeMap;
+
+use structfs_core_store::{Path, Value};
+
+/// Confirmation threshold of a new context.
+pub const DEFAULT_CONFIRM_THRESHOLD: usize = 20;
+
+/// Most paths of each kind listed in a summary.
+const MAX_LISTED: usize = 5;
+
+/// How a write changes the leaves below its path.
+#[derive(Debug, Default, PartialEq)]
+pub struct WriteDiff {
+    /// Leaves that exist now and not after the write.
+    pub removed: Vec<Path>,
+    /// Leaves whose value the write changes.
+    pub changed: Vec<Path>,
+    /// Leaves the write creates.
+    pub added: Vec<Path>,
+}
+
+impl WriteDiff {
+    /// Diff the value at a path, `old`, against the `new` one written there.
+    pub fn new(old: &Value, new: &Value) -> Self {
+        let mut old_leaves = BTreeMap::new();
+        leaves(old, Path::from_components(vec![]), &mut old_leaves);
+        let mut new_leaves = BTreeMap::new();
+        leaves(new, Path::from_components(vec![]), &mut new_leaves);
+
+        let mut diff = Self::default();
+        for (path, value) in &old_leaves {
+            match new_leaves.get(path) {
+                None => diff.removed.push(path.clone()),
+                Some(new) if new != value => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.added = new_leaves
+            .into_keys()
+            .filter(|path| !old_leaves.contains_key(path))
+            .collect();
+        diff
+    }
+
+    /// How many existing values the write replaces.
+    pub fn replaced(&self) -> usize {
+        self.removed.len() + self.changed.len()
+    }
+
+    /// A few lines listing what the write removes (`-`), changes (`~`) and
+    /// adds (`+`).
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (mark, paths) in [
+            ("-", &self.removed),
+            ("~", &self.changed),
+            ("+", &self.added),
+        ] {
+            for path in paths.iter().take(MAX_LISTED) {
+                let shown = if path.is_empty() {
+                    "(value)".to_string()
+                } else {
+                    path.to_string()
+                };
+                lines.push(format!("  {} {}", mark, shown));
+            }
+            if paths.len() > MAX_LISTED {
+                lines.push(format!("  ... and {} more", paths.len() - MAX_LISTED));
+            }
+        }
+        lines
+    }
+}
+
+/// Collect the scalar values inside `value` by their path below `at`.
+/// Empty maps and arrays count as leaves so removing them shows up.
+fn leaves(value: &Value, at: Path, out: &mut BTreeMap<Path, Value>) {
+    match value {
+        Value::Map(map) if !map.is_empty() => {
+            for (key, value) in map {
+                leaves(
+                    value,
+                    at.join(&Path::from_components(vec![key.clone()])),
+                    out,
+                );
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, value) in items.iter().enumerate() {
+                leaves(
+                    value,
+                    at.join(&Path::from_components(vec![i.to_string()])),
+                    out,
+                );
+            }
+        }
+        Value::Null => {}
+        value => {
+            out.insert(at, value.clone());
+        }
+    }
+}
+
+/// The reason a write of `new` over `old` at `path` needs confirmation, or
+/// `None` if it can go ahead.
+///
+/// A `threshold` of 0 only confirms writes over mount points.
+pub fn confirmation(
+    path: &str,
+    old: &Value,
+    new: &Value,
+    threshold: usize,
+    mount_point: bool,
+) -> Option<String> {
+    let diff = WriteDiff::new(old, new);
+    let large = threshold > 0 && diff.replaced() >= threshold;
+    if diff.replaced() == 0 || !(large || mount_point) {
+        return None;
+    }
+
+    let mut lines = vec![format!(
+        "write {} would replace {} existing value{}{}",
+        path,
+        diff.replaced(),
+        if diff.replaced() == 1 { "" } else { "s" },
+        if mount_point { " (mount point)" } else { "" }
+    )];
+    lines.extend(diff.summary());
+    lines.push("Run again with --yes to write anyway".to_string());
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn json(s: &str) -> Value {
+        structfs_serde_store::json_to_value(serde_json::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn diff_lists_removed_changed_and_added_leaves() {
+        let old = json(r#"{"a": 1, "b": {"c": 2, "d": [3]}, "e": {}}"#);
+        let new = json(r#"{"a": 1, "b": {"c": 5}, "f": true}"#);
+        let diff = WriteDiff::new(&old, &new);
+        assert_eq!(diff.removed, [path!("b/d/0"), path!("e")]);
+        assert_eq!(diff.changed, [path!("b/c")]);
+        assert_eq!(diff.added, [path!("f")]);
+        assert_eq!(diff.replaced(), 3);
+        assert_eq!(diff.summary(), ["  - b/d/0", "  - e", "  ~ b/c", "  + f"]);
+    }
+
+    #[test]
+    fn only_large_writes_and_mount_points_need_confirmation() {
+        let old = json(r#"{"a": 1, "b": 2, "c": 3}"#);
+        let new = json(r#"{"a": 1}"#);
+        assert_eq!(confirmation("/data", &old, &new, 3, false), None);
+        assert_eq!(confirmation("/data", &old, &new, 0, false), None);
+
+        let message = confirmation("/data", &old, &new, 2, false).unwrap();
+        assert!(message.starts_with("write /data would replace 2 existing values\n"));
+        assert!(message.ends_with("--yes to write anyway"));
+
+        let message = confirmation("/data", &old, &new, 0, true).unwrap();
+        assert!(message.contains("(mount point)"), "{}", message);
+        // Writing over nothing replaces nothing
+        assert_eq!(confirmation("/data", &Value::Null, &new, 1, true), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Rendering failed commands.
+//!
+//! A store error is shown with its kind, the path it concerns and the mount
+//! serving that path, followed by "did you mean" suggestions when the path
+//! is a near miss of one that exists:
+//!
+//! ```text
+//! Read error: no route to dta/users
+//!   kind:  no-route
+//!   path:  /dta/users
+//!   mount: none
+//!   did you mean /data/users?
+//! ```
+//!
+//! Suggestions are found by listing each parent along the path, so they
+//! only cover entries that their stores list when read.
+
+use structfs_core_store::{Error as CoreError, Path};
+
+use crate::store_context::{ContextError, StoreContext};
+
+/// Most suggestions shown for one path.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Render `err`, raised by `action` ("Read", "Write", ...) on `path`.
+pub fn render_error(
+    action: &str,
+    err: &ContextError,
+    path: &Path,
+    ctx: &mut StoreContext,
+) -> String {
+    let mut lines = vec![
+        format!("{} error: {}", action, headline(err)),
+        format!("  kind:  {}", error_kind(err)),
+        format!("  path:  {}", display_path(path)),
+        format!(
+            "  mount: {}",
+            ctx.mount_of(path)
+                .map_or_else(|| "none".to_string(), |m| display_path(&m))
+        ),
+    ];
+    if let Some(store) = store_of(err) {
+        lines.push(format!("  store: {}", store));
+    }
+    if let Some(hint) = did_you_mean(ctx, path) {
+        lines.push(format!("  {}", hint));
+    }
+    lines.join("\n")
+}
+
+/// "did you mean ...?" for `path`, if anything close to it exists.
+pub fn did_you_mean(ctx: &mut StoreContext, path: &Path) -> Option<String> {
+    let suggestions = suggest(ctx, path);
+    if suggestions.is_empty() {
+        return None;
+    }
+    let shown: Vec<String> = suggestions.iter().map(display_path).collect();
+    Some(format!("did you mean {}?", shown.join(" or ")))
+}
+
+/// Existing paths that differ from `path` by small typos.
+///
+/// Walks `path` from the root. The first component that is not listed
+/// under its parent is replaced by each close enough listed name, best
+/// first, and the rest of the path is corrected the same way, keeping only
+/// the closest name. Returns nothing if `path` has no misspelled component
+/// or one has no close name.
+pub fn suggest(ctx: &mut StoreContext, path: &Path) -> Vec<Path> {
+    let Some((prefix, candidates)) = first_miss(ctx, path) else {
+        return Vec::new();
+    };
+    let mut suggestions = Vec::new();
+    for name in candidates {
+        let mut fixed = prefix.clone();
+        fixed.components.push(name);
+        let rest = Path::from_components(path.components[fixed.len()..].to_vec());
+        if let Some(fixed) = correct_rest(ctx, fixed, &rest) {
+            suggestions.push(fixed);
+        }
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    suggestions
+}
+
+/// The prefix before the first component of `path` that is not listed,
+/// with the listed names close to it, best first.
+fn first_miss(ctx: &mut StoreContext, path: &Path) -> Option<(Path, Vec<String>)> {
+    for i in 0..path.len() {
+        let parent = path.slice(0, i);
+        let children = ctx.children(&parent);
+        let wanted = &path[i];
+        if children.iter().any(|c| c == wanted) {
+            continue;
+        }
+        return Some((parent, close_names(wanted, &children)));
+    }
+    None
+}
+
+/// Append `rest` to `base`, swapping each unlisted component for the
+/// closest listed name. `None` if some component has no close name.
+fn correct_rest(ctx: &mut StoreContext, mut base: Path, rest: &Path) -> Option<Path> {
+    for wanted in &rest.components {
+        let children = ctx.children(&base);
+        let name = if children.iter().any(|c| c == wanted) {
+            wanted.clone()
+        } else {
+            close_names(wanted, &children).into_iter().next()?
+        };
+        base.components.push(name);
+    }
+    Some(base)
+}
+
+/// Names within typo distance of `wanted`, closest first.
+fn close_names(wanted: &str, names: &[String]) -> Vec<String> {
+    // Allow one edit for short names, about one per three characters after
+    let max = (wanted.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &String)> = names
+        .iter()
+        .map(|name| (edit_distance(wanted, name), name))
+        .filter(|(distance, _)| *distance <= max)
+        .collect();
+    close.sort();
+    close.into_iter().map(|(_, name)| name.clone()).collect()
+}
+
+/// Levenshtein distance, counting an adjacent swap as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+fn display_path(path: &Path) -> String {
+    format!("/{}", path.components.join("/"))
+}
+
+fn headline(err: &ContextError) -> String {
+    match err {
+        ContextError::Store(e) => e.to_string(),
+        ContextError::Http(e) => e.to_string(),
+        ContextError::InvalidPath(msg) => msg.clone(),
+    }
+}
+
+/// Short machine-friendly name for the kind of `err`.
+pub fn error_kind(err: &ContextError) -> &'static str {
+    let ContextError::Store(e) = err else {
+        return match err {
+            ContextError::Http(_) => "http",
+            _ => "invalid-path",
+        };
+    };
+    match e {
+        CoreError::Path(_) => "invalid-path",
+        CoreError::NoRoute { .. } => "no-route",
+        CoreError::Codec { .. } => "codec",
+        CoreError::UnsupportedFormat(_) => "unsupported-format",
+        CoreError::Ll(_) => "ll",
+        CoreError::Io(_) => "io",
+        CoreError::Store { .. } | CoreError::Detailed { .. } => "store",
+        CoreError::LimitExceeded { .. } => "limit-exceeded",
+        CoreError::RateLimited { .. } => "rate-limited",
+        CoreError::DeadlineExceeded { .. } => "deadline-exceeded",
+        CoreError::Cancelled { .. } => "cancelled",
+    }
+}
+
+/// The store that raised `err`, with the operation when known.
+fn store_of(err: &ContextError) -> Option<String> {
+    let ContextError::Store(e) = err else {
+        return None;
+    };
+    match e {
+        CoreError::Store {
+            store, operation, ..
+        }
+        | CoreError::Detailed {
+            store, operation, ..
+        } => Some(format!("{} ({})", store, operation)),
+        CoreError::RateLimited { store, .. }
+        | CoreError::DeadlineExceeded { store }
+        | CoreError::Cancelled { store } => Some(store.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{mount_store::MountConfig, Value};
+
+    fn context() -> StoreContext {
+        let mut ctx = StoreContext::new();
+        ctx.mount("data", MountConfig::Memory).unwrap();
+        let users = Value::Map(
+            [
+                ("alice".to_string(), Value::Bool(true)),
+                ("bob".to_string(), Value::Bool(true)),
+            ]
+            .into(),
+        );
+        ctx.write(&Path::parse("data/users").unwrap(), users)
+            .unwrap();
+        ctx
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("users", "users"), 0);
+        assert_eq!(edit_distance("usres", "users"), 1);
+        assert_eq!(edit_distance("user", "users"), 1);
+        assert_eq!(edit_distance("alice", "bob"), 5);
+    }
+
+    #[test]
+    fn suggests_near_misses_along_the_path() {
+        let mut ctx = context();
+        let path = Path::parse("dta/usres/alcie").unwrap();
+        assert_eq!(
+            suggest(&mut ctx, &path),
+            vec![Path::parse("data/users/alice").unwrap()]
+        );
+
+        // Existing and far-off paths get no suggestions
+        assert!(suggest(&mut ctx, &Path::parse("data/users/alice").unwrap()).is_empty());
+        assert!(suggest(&mut ctx, &Path::parse("data/zzzzzz").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn renders_kind_path_mount_and_suggestion() {
+        let mut ctx = context();
+        let path = Path::parse("data/usres").unwrap();
+        let err = ContextError::Store(CoreError::store("memory", "read", "boom"));
+        let rendered = render_error("Read", &err, &path, &mut ctx);
+        assert!(rendered.starts_with("Read error: "));
+        assert!(rendered.contains("kind:  store"));
+        assert!(rendered.contains("path:  /data/usres"));
+        assert!(rendered.contains("mount: /data"));
+        assert!(rendered.contains("store: memory (read)"));
+        assert!(rendered.contains("did you mean /data/users?"));
+
+        let err = ContextError::Store(CoreError::NoRoute { path: path.clone() });
+        assert_eq!(error_kind(&err), "no-route");
+    }
+}
@@ -0,0 +1,244 @@
+//! Rendering self-describing docs as help text.
+//!
+//! Stores describe themselves with maps like
+//! `{title, description, paths: {operation: description}, example: [...]}`
+//! (see `structfs_core_store::describe::Docs`). When `read` lands on one of
+//! these, [`render_docs`] shows it the way `help` would instead of as JSON:
+//!
+//! ```text
+//! Trash
+//!
+//! Deleted records kept for restoring.
+//!
+//! Paths
+//!   read /items               List deleted records
+//!   write /items/{id}/restore Put a record back where it was
+//!
+//! Example
+//!   read /items
+//! ```
+//!
+//! Descriptions may use `code` and **bold** spans. `read --json` shows the
+//! value as JSON.
+
+use std::collections::BTreeMap;
+
+use nu_ansi_term::{Color, Style};
+use structfs_core_store::Value;
+use structfs_serde_store::value_to_json;
+
+use crate::pretty::{format_value, PrettyOptions};
+
+/// Widest first column of a table; longer entries get a line of their own.
+const MAX_KEY_WIDTH: usize = 32;
+
+/// Whether `value` is a docs map: a string `title` with a string
+/// `description` or a `paths` map.
+pub fn is_docs(value: &Value) -> bool {
+    let Value::Map(map) = value else {
+        return false;
+    };
+    matches!(map.get("title"), Some(Value::String(_)))
+        && (matches!(map.get("description"), Some(Value::String(_)))
+            || matches!(map.get("paths"), Some(Value::Map(_))))
+}
+
+/// `value` rendered as help text, or `None` if it is not a docs map.
+pub fn render_docs(value: &Value) -> Option<String> {
+    if !is_docs(value) {
+        return None;
+    }
+    let Value::Map(map) = value else {
+        return None;
+    };
+
+    let mut out = String::new();
+    if let Some(Value::String(title)) = map.get("title") {
+        out.push_str(&format!("{}\n", Style::new().bold().paint(title)));
+    }
+    if let Some(Value::String(description)) = map.get("description") {
+        out.push_str(&format!("\n{}\n", inline_markdown(description)));
+    }
+    if let Some(Value::Map(paths)) = map.get("paths") {
+        section(&mut out, "paths");
+        out.push_str(&table(paths));
+    }
+    if let Some(value) = map.get("example") {
+        section(&mut out, "example");
+        out.push_str(&example(value));
+    }
+    for (key, value) in map {
+        if matches!(key.as_str(), "title" | "description" | "paths" | "example") {
+            continue;
+        }
+        section(&mut out, key);
+        out.push_str(&field(value));
+    }
+    Some(out.trim_end().to_string())
+}
+
+/// Rows of `key  description`, with the descriptions aligned.
+pub fn table(rows: &BTreeMap<String, Value>) -> String {
+    let width = rows
+        .keys()
+        .map(|key| key.chars().count())
+        .filter(|&len| len <= MAX_KEY_WIDTH)
+        .max()
+        .unwrap_or(0);
+    let key_style = Color::Yellow.normal();
+
+    let mut out = String::new();
+    for (key, value) in rows {
+        let text = match value {
+            Value::String(s) => inline_markdown(s),
+            other => compact(other),
+        };
+        let len = key.chars().count();
+        if len > MAX_KEY_WIDTH {
+            out.push_str(&format!(
+                "  {}\n  {:width$} {}\n",
+                key_style.paint(key),
+                "",
+                text
+            ));
+        } else {
+            out.push_str(&format!(
+                "  {}{:pad$} {}\n",
+                key_style.paint(key),
+                "",
+                text,
+                pad = width - len
+            ));
+        }
+    }
+    out
+}
+
+fn section(out: &mut String, key: &str) {
+    let mut heading = key.replace('_', " ");
+    if let Some(first) = heading.get(..1) {
+        heading.replace_range(..1, &first.to_uppercase());
+    }
+    out.push_str(&format!("\n{}\n", Style::new().bold().paint(heading)));
+}
+
+/// Example lines, with `#` comments dimmed.
+fn example(value: &Value) -> String {
+    let Value::Array(lines) = value else {
+        return field(value);
+    };
+    let mut out = String::new();
+    for line in lines {
+        match line {
+            Value::String(s) if s.starts_with('#') => {
+                out.push_str(&format!("  {}\n", Color::White.dimmed().paint(s)))
+            }
+            Value::String(s) => out.push_str(&format!("  {}\n", Color::Green.paint(s))),
+            other => out.push_str(&format!("  {}\n", compact(other))),
+        }
+    }
+    out
+}
+
+/// Any other field: strings as text, lists of strings as bullets, maps as
+/// tables and anything else as JSON.
+fn field(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("  {}\n", inline_markdown(s)),
+        Value::Array(items) if items.iter().all(|i| matches!(i, Value::String(_))) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => format!("  • {}\n", inline_markdown(s)),
+                _ => unreachable!(),
+            })
+            .collect(),
+        Value::Map(map) => table(map),
+        other => {
+            let json = format_value(&value_to_json(other.clone()), &PrettyOptions::default());
+            json.lines().map(|line| format!("  {}\n", line)).collect()
+        }
+    }
+}
+
+/// `value` as one line of JSON.
+fn compact(value: &Value) -> String {
+    value_to_json(value.clone()).to_string()
+}
+
+/// `text` with `code` spans in cyan and **bold** spans in bold.
+fn inline_markdown(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    loop {
+        let code = rest.find('`');
+        let bold = rest.find("**");
+        let (start, marker, style) = match (code, bold) {
+            (Some(c), Some(b)) if b < c => (b, "**", Style::new().bold()),
+            (Some(c), _) => (c, "`", Color::Cyan.normal()),
+            (None, Some(b)) => (b, "**", Style::new().bold()),
+            (None, None) => break,
+        };
+        let after = &rest[start + marker.len()..];
+        let Some(end) = after.find(marker) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&style.paint(&after[..end]).to_string());
+        rest = &after[end + marker.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::strip_ansi_codes;
+    use structfs_core_store::describe::Docs;
+
+    #[test]
+    fn docs_render_as_help_with_aligned_paths() {
+        let docs = Docs::new("Trash", "Deleted records kept for `restore`.")
+            .path("read /items", "List deleted records")
+            .path("write /items/{id}/restore", "Put a record back")
+            .example(["# Undo a delete", "write /items/1/restore null"])
+            .field("errors", Value::Array(vec![Value::String("Gone".into())]))
+            .to_value();
+
+        let text = strip_ansi_codes(&render_docs(&docs).unwrap());
+        assert_eq!(
+            text,
+            "Trash\n\
+             \n\
+             Deleted records kept for restore.\n\
+             \n\
+             Paths\n  \
+             read /items               List deleted records\n  \
+             write /items/{id}/restore Put a record back\n\
+             \n\
+             Example\n  \
+             # Undo a delete\n  \
+             write /items/1/restore null\n\
+             \n\
+             Errors\n  \
+             • Gone"
+        );
+    }
+
+    #[test]
+    fn other_values_are_not_docs() {
+        let mut map = BTreeMap::new();
+        map.insert("title".to_string(), Value::String("Untitled".into()));
+        assert!(render_docs(&Value::Map(map.clone())).is_none());
+        map.insert("description".to_string(), Value::Integer(1));
+        assert!(!is_docs(&Value::Map(map)));
+        assert!(!is_docs(&Value::String("title".into())));
+    }
+
+    #[test]
+    fn inline_markdown_styles_code_and_bold() {
+        let text = inline_markdown("use `read` for **all** reads, not `x");
+        assert_eq!(strip_ansi_codes(&text), "use read for all reads, not `x");
+        assert_ne!(text, strip_ansi_codes(&text));
+    }
+}
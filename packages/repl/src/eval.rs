@@ -0,0 +1,531 @@
+//! Inline expressions over store values.
+//!
+//! The `eval` command computes a value from reads, literals and a handful of
+//! functions, and `read` pipes its result through the same functions:
+//!
+//! ```text
+//! > eval (read /a/count) + (read /b/count)
+//! > eval len(read /users) * 2
+//! > read /users | len
+//! > read /users | pick name email
+//! > eval @totals | sum
+//! ```
+//!
+//! Operators are `+ - * / %` on numbers and `+` on strings, with the usual
+//! precedence. `x | f a b` is the same as `f(x, a, b)`; piped arguments are
+//! bare words, read as numbers where they parse as one. A `read <path>` or
+//! `@register` may appear anywhere a value can.
+//!
+//! | Function | Result |
+//! |----------|--------|
+//! | `len(x)` | Entries in an array or map, or characters in a string |
+//! | `keys(x)` | Keys of a map, or indices of an array |
+//! | `sum(x)` | Sum of the numbers in an array or map |
+//! | `pick(x, k...)` | Map with only keys `k...`; applied to each map in an array |
+
+use std::collections::BTreeMap;
+
+use structfs_core_store::Value;
+
+use crate::commands::resolve_dereference;
+use crate::store_context::{is_register_path, StoreContext};
+
+/// Evaluate an expression, reading through `ctx`.
+pub fn evaluate(expr: &str, ctx: &mut StoreContext) -> Result<Value, String> {
+    let mut parser = Parser {
+        src: expr,
+        pos: 0,
+        ctx,
+    };
+    let value = parser.pipeline()?;
+    parser.finish(value)
+}
+
+/// Pass `value` through `pipeline`, a list of `| f args` stages.
+pub fn apply_pipeline(
+    value: Value,
+    pipeline: &str,
+    ctx: &mut StoreContext,
+) -> Result<Value, String> {
+    let mut parser = Parser {
+        src: pipeline,
+        pos: 0,
+        ctx,
+    };
+    let value = parser.stages(value)?;
+    parser.finish(value)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    ctx: &'a mut StoreContext,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    /// Return `value` if the whole input was consumed.
+    fn finish(&mut self, value: Value) -> Result<Value, String> {
+        self.skip_space();
+        match self.peek() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected '{}' at column {}", c, self.pos + 1)),
+        }
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += self.peek().map_or(0, char::len_utf8);
+        }
+    }
+
+    /// Skip whitespace and consume `c` if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at column {}", c, self.pos + 1))
+        }
+    }
+
+    /// Consume characters while `pred` holds.
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.pos += self.peek().map_or(0, char::len_utf8);
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// A word that ends at whitespace or expression punctuation.
+    fn word(&mut self) -> String {
+        self.skip_space();
+        self.take_while(|c| !c.is_whitespace() && !matches!(c, '(' | ')' | ',' | '|'))
+            .to_string()
+    }
+
+    fn pipeline(&mut self) -> Result<Value, String> {
+        let value = self.additive()?;
+        self.stages(value)
+    }
+
+    fn stages(&mut self, mut value: Value) -> Result<Value, String> {
+        while self.eat('|') {
+            self.skip_space();
+            let name = self
+                .take_while(|c| c.is_alphanumeric() || c == '_')
+                .to_string();
+            if name.is_empty() {
+                return Err(format!(
+                    "Expected a function after '|' at column {}",
+                    self.pos + 1
+                ));
+            }
+
+            let mut args = vec![value];
+            if self.eat('(') {
+                args.extend(self.arguments()?);
+            } else {
+                loop {
+                    let word = self.word();
+                    if word.is_empty() {
+                        break;
+                    }
+                    args.push(bare_word(&word));
+                }
+            }
+            value = call(&name, args)?;
+        }
+        Ok(value)
+    }
+
+    fn additive(&mut self) -> Result<Value, String> {
+        let mut value = self.multiplicative()?;
+        loop {
+            let op = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                return Ok(value);
+            };
+            let rhs = self.multiplicative()?;
+            value = binary(op, value, rhs)?;
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            let op = match ['*', '/', '%'].into_iter().find(|&op| self.eat(op)) {
+                Some(op) => op,
+                None => return Ok(value),
+            };
+            let rhs = self.unary()?;
+            value = binary(op, value, rhs)?;
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat('-') {
+            let value = self.unary()?;
+            return binary('-', Value::Integer(0), value);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        self.skip_space();
+        let start = self.pos;
+        match self.peek() {
+            None => Err("Expected a value".to_string()),
+            Some('(') => {
+                self.pos += 1;
+                let value = self.pipeline()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some('"') => self.string(),
+            Some('@') => {
+                let register = self.word();
+                self.read(&register)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                parse_number(number).ok_or_else(|| format!("Invalid number '{}'", number))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self
+                    .take_while(|c| c.is_alphanumeric() || c == '_')
+                    .to_string();
+                match name.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    "read" | "get" | "r" => {
+                        let path = self.word();
+                        self.read(if path.is_empty() { "." } else { &path })
+                    }
+                    _ if self.eat('(') => call(&name, self.arguments()?),
+                    _ => Err(format!("Unknown name '{}'", name)),
+                }
+            }
+            Some(c) => Err(format!("Unexpected '{}' at column {}", c, start + 1)),
+        }
+    }
+
+    /// Comma-separated arguments after an opening parenthesis.
+    fn arguments(&mut self) -> Result<Vec<Value>, String> {
+        let mut args = Vec::new();
+        if self.eat(')') {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.pipeline()?);
+            if self.eat(')') {
+                return Ok(args);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn string(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut escaped = false;
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| format!("Unterminated string at column {}", start + 1))?;
+            self.pos += c.len_utf8();
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => break,
+                _ => escaped = false,
+            }
+        }
+        serde_json::from_str(&self.src[start..self.pos])
+            .map(Value::String)
+            .map_err(|e| format!("Invalid string: {}", e))
+    }
+
+    fn read(&mut self, path_str: &str) -> Result<Value, String> {
+        let path_str = resolve_dereference(path_str, self.ctx)?;
+        let value = if is_register_path(&path_str) {
+            self.ctx.read_register(&path_str)
+        } else {
+            let path = self
+                .ctx
+                .resolve_path(&path_str)
+                .map_err(|e| format!("Invalid path: {}", e))?;
+            self.ctx.read(&path)
+        };
+        value
+            .map(|v| v.unwrap_or(Value::Null))
+            .map_err(|e| format!("Read error: {}", e))
+    }
+}
+
+fn parse_number(s: &str) -> Option<Value> {
+    if s.contains('.') {
+        s.parse().ok().map(Value::Float)
+    } else {
+        s.parse().ok().map(Value::Integer)
+    }
+}
+
+/// A piped argument: a number if it parses as one, otherwise a string.
+fn bare_word(word: &str) -> Value {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    match parse_number(digits) {
+        Some(Value::Integer(i)) if negative => Value::Integer(-i),
+        Some(Value::Float(f)) if negative => Value::Float(-f),
+        Some(n) => n,
+        None => Value::String(word.to_string()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Integer(_) => "integer",
+        Value::Float(_) => "float",
+        Value::String(_) => "string",
+        Value::Bytes(_) => "bytes",
+        Value::Array(_) => "array",
+        Value::Map(_) => "map",
+    }
+}
+
+fn binary(op: char, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let overflow = || format!("Integer overflow in '{}'", op);
+    match (&lhs, &rhs) {
+        (Value::String(a), Value::String(b)) if op == '+' => Ok(Value::String(format!("{a}{b}"))),
+        (Value::Integer(a), Value::Integer(b)) => {
+            let (a, b) = (*a, *b);
+            match op {
+                '+' => a.checked_add(b).map(Value::Integer).ok_or_else(overflow),
+                '-' => a.checked_sub(b).map(Value::Integer).ok_or_else(overflow),
+                '*' => a.checked_mul(b).map(Value::Integer).ok_or_else(overflow),
+                _ if b == 0 => Err("Division by zero".to_string()),
+                '%' => Ok(Value::Integer(a % b)),
+                _ if a % b == 0 => Ok(Value::Integer(a / b)),
+                _ => Ok(Value::Float(a as f64 / b as f64)),
+            }
+        }
+        _ => match (as_float(&lhs), as_float(&rhs)) {
+            (Some(a), Some(b)) => Ok(Value::Float(match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                _ => a % b,
+            })),
+            _ => Err(format!(
+                "Cannot apply '{}' to {} and {}",
+                op,
+                type_name(&lhs),
+                type_name(&rhs)
+            )),
+        },
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let mut args = args.into_iter();
+    let arg = args
+        .next()
+        .ok_or_else(|| format!("{}() needs an argument", name))?;
+    let rest: Vec<Value> = args.collect();
+    let no_extra = |value: Value| {
+        if rest.is_empty() {
+            Ok(value)
+        } else {
+            Err(format!("{}() takes one argument", name))
+        }
+    };
+
+    match name {
+        "len" => {
+            let len = match &arg {
+                Value::Array(items) => items.len(),
+                Value::Map(map) => map.len(),
+                Value::String(s) => s.chars().count(),
+                Value::Bytes(b) => b.len(),
+                other => return Err(format!("len() of {}", type_name(other))),
+            };
+            no_extra(Value::Integer(len as i64))
+        }
+        "keys" => {
+            let keys = match &arg {
+                Value::Map(map) => map.keys().cloned().map(Value::String).collect(),
+                Value::Array(items) => (0..items.len() as i64).map(Value::Integer).collect(),
+                other => return Err(format!("keys() of {}", type_name(other))),
+            };
+            no_extra(Value::Array(keys))
+        }
+        "sum" => {
+            let items: Vec<Value> = match arg {
+                Value::Array(items) => items,
+                Value::Map(map) => map.into_values().collect(),
+                other => return Err(format!("sum() of {}", type_name(&other))),
+            };
+            let total = items
+                .into_iter()
+                .try_fold(Value::Integer(0), |acc, item| binary('+', acc, item))?;
+            no_extra(total)
+        }
+        "pick" => {
+            let keys = rest
+                .iter()
+                .map(|key| match key {
+                    Value::String(s) => Ok(s.clone()),
+                    Value::Integer(i) => Ok(i.to_string()),
+                    other => Err(format!(
+                        "pick() key must be a string, not {}",
+                        type_name(other)
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            pick(arg, &keys)
+        }
+        _ => Err(format!(
+            "Unknown function '{}'. Available: len, keys, sum, pick",
+            name
+        )),
+    }
+}
+
+fn pick(value: Value, keys: &[String]) -> Result<Value, String> {
+    match value {
+        Value::Map(mut map) => Ok(Value::Map(
+            keys.iter()
+                .filter_map(|key| map.remove_entry(key))
+                .collect::<BTreeMap<_, _>>(),
+        )),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| pick(item, keys))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        other => Err(format!("pick() of {}", type_name(&other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection_literals::btree;
+    use structfs_core_store::mount_store::MountConfig;
+
+    fn ctx_with_data() -> StoreContext {
+        let mut ctx = StoreContext::new();
+        ctx.mount("data", MountConfig::Memory).unwrap();
+        crate::commands::execute(
+            r#"write /data {"a": {"count": 3}, "b": {"count": 4}, "users": [{"name": "Alice", "age": 30}, {"name": "Bob", "age": 25}]}"#,
+            &mut ctx,
+        );
+        ctx
+    }
+
+    #[test]
+    fn arithmetic_over_reads() {
+        let mut ctx = ctx_with_data();
+        assert_eq!(
+            evaluate("(read /data/a/count) + (read /data/b/count)", &mut ctx),
+            Ok(Value::Integer(7))
+        );
+        assert_eq!(evaluate("1 + 2 * 3 - -1", &mut ctx), Ok(Value::Integer(8)));
+        assert_eq!(evaluate("(1 + 2) * 3 % 5", &mut ctx), Ok(Value::Integer(4)));
+        assert_eq!(evaluate("7 / 2", &mut ctx), Ok(Value::Float(3.5)));
+        assert_eq!(evaluate("1.5 + 1", &mut ctx), Ok(Value::Float(2.5)));
+        assert_eq!(
+            evaluate(r#""a" + "\"b""#, &mut ctx),
+            Ok(Value::String("a\"b".into()))
+        );
+        assert!(evaluate("1 / 0", &mut ctx).is_err());
+        assert!(evaluate("\"a\" * 2", &mut ctx).is_err());
+        assert!(evaluate("1 +", &mut ctx).is_err());
+        assert!(evaluate("1 2", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn functions_and_pipes() {
+        let mut ctx = ctx_with_data();
+        assert_eq!(
+            evaluate("read /data/users | len", &mut ctx),
+            Ok(Value::Integer(2))
+        );
+        assert_eq!(
+            evaluate("len(read /data/users) * 2", &mut ctx),
+            Ok(Value::Integer(4))
+        );
+        assert_eq!(
+            evaluate("read /data | keys", &mut ctx),
+            Ok(Value::Array(vec!["a".into(), "b".into(), "users".into()]))
+        );
+        assert_eq!(
+            evaluate("sum(keys(read /data/users)) + len(\"héllo\")", &mut ctx),
+            Ok(Value::Integer(6))
+        );
+        assert_eq!(
+            evaluate("read /data/users | pick name | len", &mut ctx),
+            Ok(Value::Integer(2))
+        );
+        assert_eq!(
+            evaluate("pick(read /data/users, \"age\")", &mut ctx),
+            Ok(Value::Array(vec![
+                Value::Map(btree! { "age".to_string() => Value::Integer(30) }),
+                Value::Map(btree! { "age".to_string() => Value::Integer(25) }),
+            ]))
+        );
+        assert!(evaluate("read /data | nope", &mut ctx).is_err());
+        assert!(evaluate("len(1)", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn registers_and_pipelines() {
+        let mut ctx = ctx_with_data();
+        ctx.set_register(
+            "nums",
+            Value::Array(vec![Value::Integer(1), Value::Float(2.5)]),
+        );
+        assert_eq!(evaluate("@nums | sum", &mut ctx), Ok(Value::Float(3.5)));
+        assert_eq!(
+            apply_pipeline(
+                Value::Map(btree! {
+                    "x".to_string() => Value::Integer(1),
+                    "y".to_string() => Value::Integer(2),
+                }),
+                "| pick y | sum",
+                &mut ctx
+            ),
+            Ok(Value::Integer(2))
+        );
+    }
+}
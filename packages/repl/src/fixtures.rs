@@ -0,0 +1,254 @@
+//! Regression tests for store layouts.
+//!
+//! `test <file>` runs a fixture file: a JSON document listing the mounts to
+//! create and a series of cases. Each case starts from a fresh context
+//! with empty in-memory stores at those mounts, makes its setup writes in
+//! order, runs one command and checks what it printed:
+//!
+//! ```json
+//! {
+//!   "mounts": ["data"],
+//!   "cases": [
+//!     {
+//!       "name": "reads a user's name",
+//!       "setup": [{"path": "/data/users/1", "value": {"name": "Alice"}}],
+//!       "command": "read /data/users/1/name",
+//!       "expect": "\"Alice\""
+//!     },
+//!     {
+//!       "name": "misspelt mounts are no-route errors",
+//!       "command": "read /dta/users",
+//!       "fails": true,
+//!       "matches": "*kind:  no-route*"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `expect` is compared with the output exactly, and `matches` is a
+//! pattern where `*` stands for any text and `?` for any one character.
+//! Both ignore colours and surrounding whitespace. A case with `fails`
+//! passes only if the command fails, and its output is the error message;
+//! otherwise the command must succeed. A case with neither `expect` nor
+//! `matches` checks only that.
+
+use serde::Deserialize;
+
+use structfs_core_store::mount_store::MountConfig;
+use structfs_serde_store::value_to_json;
+
+use crate::commands::{execute, strip_ansi_codes, CommandResult};
+use crate::store_context::StoreContext;
+
+/// A fixture file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    /// Names of the in-memory stores each case mounts.
+    #[serde(default)]
+    pub mounts: Vec<String>,
+    /// The cases, run in order.
+    pub cases: Vec<FixtureCase>,
+}
+
+/// One case in a fixture file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureCase {
+    /// Shown in the report.
+    pub name: String,
+    /// Writes made before the command, in order.
+    #[serde(default)]
+    pub setup: Vec<SetupWrite>,
+    /// The command under test.
+    pub command: String,
+    /// The exact output expected.
+    pub expect: Option<String>,
+    /// A pattern the output must match.
+    pub matches: Option<String>,
+    /// Whether the command should fail.
+    #[serde(default)]
+    pub fails: bool,
+}
+
+/// A write made before a case's command.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetupWrite {
+    /// Where to write.
+    pub path: String,
+    /// What to write.
+    pub value: serde_json::Value,
+}
+
+/// How one case went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseOutcome {
+    /// The case's name.
+    pub name: String,
+    /// Why it failed, or `None` if it passed.
+    pub failure: Option<String>,
+}
+
+impl Fixture {
+    /// Parse a fixture file's contents.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        serde_json::from_str(source).map_err(|e| format!("Invalid fixture file: {}", e))
+    }
+
+    /// Load a fixture file.
+    pub fn load(file: &str) -> Result<Self, String> {
+        let source =
+            std::fs::read_to_string(file).map_err(|e| format!("Cannot read '{}': {}", file, e))?;
+        Self::parse(&source)
+    }
+
+    /// Run every case.
+    pub fn run(&self) -> Vec<CaseOutcome> {
+        self.cases
+            .iter()
+            .map(|case| CaseOutcome {
+                name: case.name.clone(),
+                failure: self.run_case(case).err(),
+            })
+            .collect()
+    }
+
+    fn run_case(&self, case: &FixtureCase) -> Result<(), String> {
+        let mut ctx = StoreContext::new();
+        for mount in &self.mounts {
+            ctx.mount(mount, MountConfig::Memory)
+                .map_err(|e| format!("mount {}: {}", mount, e))?;
+        }
+        for write in &case.setup {
+            let path = ctx
+                .resolve_path(&write.path)
+                .map_err(|e| format!("setup write to {}: {}", write.path, e))?;
+            ctx.write_json(&path, &write.value)
+                .map_err(|e| format!("setup write to {}: {}", write.path, e))?;
+        }
+
+        let (failed, output) = match execute(&case.command, &mut ctx) {
+            CommandResult::Ok { display, capture } => {
+                let output = match (display, capture) {
+                    (Some(display), _) => display,
+                    (None, Some(value)) => {
+                        serde_json::to_string(&value_to_json(value)).unwrap_or_default()
+                    }
+                    (None, None) => String::new(),
+                };
+                (false, output)
+            }
+            CommandResult::Error(message) => (true, message),
+            CommandResult::Exit => (false, String::new()),
+        };
+        let output = strip_ansi_codes(&output).trim().to_string();
+
+        if failed != case.fails {
+            return Err(if failed {
+                format!("command failed: {}", output)
+            } else {
+                format!("expected the command to fail, got: {}", output)
+            });
+        }
+        if let Some(expected) = &case.expect {
+            if output != expected.trim() {
+                return Err(format!("expected:\n{}\ngot:\n{}", expected.trim(), output));
+            }
+        }
+        if let Some(pattern) = &case.matches {
+            if !glob_match(pattern.trim(), &output) {
+                return Err(format!("expected to match:\n{}\ngot:\n{}", pattern, output));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any text and `?`
+/// any one character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it is matching up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*c", "abbbc"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*kind:*no-route*", "error\n  kind:  no-route\n"));
+        assert!(!glob_match("a*c", "abd"));
+        assert!(!glob_match("abc", "abcd"));
+    }
+
+    #[test]
+    fn cases_run_against_fresh_mounts() {
+        let fixture = Fixture::parse(
+            r#"{
+                "mounts": ["data"],
+                "cases": [
+                    {
+                        "name": "setup is visible",
+                        "setup": [{"path": "/data/users", "value": {"alice": {"age": 30}}}],
+                        "command": "read /data/users/alice/age",
+                        "expect": "30"
+                    },
+                    {
+                        "name": "each case starts empty",
+                        "command": "read /data/users/alice/age",
+                        "expect": "30"
+                    },
+                    {
+                        "name": "errors can be expected",
+                        "command": "read /dta/users",
+                        "fails": true,
+                        "matches": "*no-route*"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let outcomes = fixture.run();
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0].failure, None);
+        assert!(outcomes[1].failure.is_some());
+        assert_eq!(outcomes[2].failure, None);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let err = Fixture::parse(r#"{"cases": [{"name": "x", "comand": "pwd"}]}"#).unwrap_err();
+        assert!(err.contains("comand"), "{}", err);
+    }
+}
@@ -763,4 +763,13 @@ mod tests {
             _ => panic!("Expected array"),
         }
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut help = HelpStore::new();
+        conformance::assert_found(&mut help, &path!(""));
+        conformance::assert_missing(&mut help, &path!("sys"));
+    }
 }
@@ -5,10 +5,13 @@
 //! - Tab completion
 //! - Syntax highlighting
 //! - Command history
+//! - Paging of output taller than the terminal
 
 use std::borrow::Cow;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use nu_ansi_term::{Color, Style};
 use reedline::{
@@ -21,6 +24,7 @@ use reedline::{
 use crate::completer::ReplCompleter;
 use crate::highlighter::ReplHighlighter;
 use crate::io::{InputLine, IoError, IoHost, Output, OutputStyle, PromptConfig, Signal};
+use crate::pager::{read_terminal_action, Pager};
 
 /// Terminal host using Reedline for interactive I/O.
 pub struct TerminalHost {
@@ -28,6 +32,8 @@ pub struct TerminalHost {
     pending_input: Option<InputLine>,
     pending_signal: Option<Signal>,
     current_prompt: PromptConfig,
+    /// Set by SIGINT outside of line editing, while a command runs
+    interrupted: Arc<AtomicBool>,
 }
 
 impl TerminalHost {
@@ -102,11 +108,17 @@ impl TerminalHost {
             }
         }
 
+        // Reedline reports Ctrl+C itself while editing a line. At other
+        // times it arrives as SIGINT, which would otherwise kill the REPL.
+        let interrupted = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGINT, interrupted.clone())?;
+
         Ok(Self {
             line_editor,
             pending_input: None,
             pending_signal: None,
             current_prompt: PromptConfig::default(),
+            interrupted,
         })
     }
 }
@@ -138,10 +150,24 @@ impl IoHost for TerminalHost {
     }
 
     fn read_signal(&mut self) -> Result<Option<Signal>, IoError> {
-        Ok(self.pending_signal.take())
+        if let Some(signal) = self.pending_signal.take() {
+            return Ok(Some(signal));
+        }
+        Ok(self
+            .interrupted
+            .swap(false, Ordering::SeqCst)
+            .then_some(Signal::Interrupt))
     }
 
     fn write_output(&mut self, output: Output) -> Result<(), IoError> {
+        if output.style == OutputStyle::Normal {
+            if let Some(pager) = Pager::for_terminal().filter(|p| p.needs_paging(&output.text)) {
+                return pager
+                    .page(&output.text, &mut io::stdout(), read_terminal_action)
+                    .map_err(|e| IoError::Io(e.to_string()));
+            }
+        }
+
         let styled = match output.style {
             OutputStyle::Normal => output.text,
             OutputStyle::Error => {
@@ -162,6 +188,46 @@ impl IoHost for TerminalHost {
     fn flush(&mut self) -> Result<(), IoError> {
         io::stdout().flush().map_err(|e| IoError::Io(e.to_string()))
     }
+
+    fn read_secret(&mut self, prompt: &str) -> Result<Option<String>, IoError> {
+        print!("{}", prompt);
+        self.flush()?;
+        let secret = read_hidden_line().map_err(|e| IoError::Io(e.to_string()));
+        println!();
+        secret
+    }
+}
+
+/// Read a line in raw mode without echoing it. Ctrl+C and Esc cancel.
+fn read_hidden_line() -> io::Result<Option<String>> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal;
+
+    terminal::enable_raw_mode()?;
+    let mut line = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(Event::Paste(text)) => {
+                line.push_str(&text);
+                continue;
+            }
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(None),
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Enter => break Ok(Some(std::mem::take(&mut line))),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Char(c) => line.push(c),
+            _ => {}
+        }
+    };
+    terminal::disable_raw_mode()?;
+    result
 }
 
 /// Prompt implementation for the terminal.
@@ -6,6 +6,10 @@
 
 pub mod types;
 
+use std::time::Duration;
+
+use async_trait::async_trait;
+
 #[cfg(test)]
 pub mod test_host;
 
@@ -58,4 +62,96 @@ pub trait IoHost {
     fn flush(&mut self) -> Result<(), IoError> {
         Ok(())
     }
+
+    /// Read one line without echoing it, for `write --secret`.
+    ///
+    /// Returns `None` if the user cancels. Hosts that cannot hide input
+    /// fail rather than echo the secret.
+    fn read_secret(&mut self, prompt: &str) -> Result<Option<String>, IoError> {
+        let _ = prompt;
+        Err(IoError::Io(
+            "This host cannot read hidden input".to_string(),
+        ))
+    }
+}
+
+/// Host interface for REPL cores that run commands in the background.
+///
+/// Like [`IoHost`], but waiting is asynchronous so hosts driven by an event
+/// loop, such as a browser, never block it. Every [`IoHost`] is also an
+/// `AsyncIoHost` whose waits block the calling thread.
+#[async_trait(?Send)]
+pub trait AsyncIoHost {
+    /// Wait for input or a signal to become available.
+    async fn wait_for_input(&mut self) -> Result<(), IoError>;
+
+    /// Wait while a command runs in the background.
+    ///
+    /// Should return after about `interval`, or sooner if a signal arrives,
+    /// so the core can check on the command and on `read_signal()`.
+    async fn idle(&mut self, interval: Duration) -> Result<(), IoError>;
+
+    /// Read the next input line, if available.
+    fn read_input(&mut self) -> Result<Option<InputLine>, IoError>;
+
+    /// Read any pending signal (Ctrl+C, Ctrl+D).
+    fn read_signal(&mut self) -> Result<Option<Signal>, IoError>;
+
+    /// Write output to the user.
+    fn write_output(&mut self, output: Output) -> Result<(), IoError>;
+
+    /// Update the prompt configuration.
+    fn write_prompt(&mut self, config: PromptConfig) -> Result<(), IoError>;
+
+    /// Flush any buffered output.
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Read one line without echoing it, for `write --secret`.
+    ///
+    /// Returns `None` if the user cancels. Hosts that cannot hide input
+    /// fail rather than echo the secret.
+    fn read_secret(&mut self, prompt: &str) -> Result<Option<String>, IoError> {
+        let _ = prompt;
+        Err(IoError::Io(
+            "This host cannot read hidden input".to_string(),
+        ))
+    }
+}
+
+#[async_trait(?Send)]
+impl<H: IoHost> AsyncIoHost for H {
+    async fn wait_for_input(&mut self) -> Result<(), IoError> {
+        IoHost::wait_for_input(self)
+    }
+
+    async fn idle(&mut self, interval: Duration) -> Result<(), IoError> {
+        std::thread::sleep(interval);
+        Ok(())
+    }
+
+    fn read_input(&mut self) -> Result<Option<InputLine>, IoError> {
+        IoHost::read_input(self)
+    }
+
+    fn read_signal(&mut self) -> Result<Option<Signal>, IoError> {
+        IoHost::read_signal(self)
+    }
+
+    fn read_secret(&mut self, prompt: &str) -> Result<Option<String>, IoError> {
+        IoHost::read_secret(self, prompt)
+    }
+
+    fn write_output(&mut self, output: Output) -> Result<(), IoError> {
+        IoHost::write_output(self, output)
+    }
+
+    fn write_prompt(&mut self, config: PromptConfig) -> Result<(), IoError> {
+        IoHost::write_prompt(self, config)
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        IoHost::flush(self)
+    }
 }
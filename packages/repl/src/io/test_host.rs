@@ -19,6 +19,8 @@ pub struct TestHost {
     input_queue: VecDeque<String>,
     /// Queue of signals to be returned by `read_signal()`.
     signal_queue: VecDeque<Signal>,
+    /// Queue of values to be returned by `read_secret()`.
+    secret_queue: VecDeque<String>,
     /// Buffer of all output written via `write_output()`.
     output_buffer: Vec<Output>,
     /// The most recent prompt configuration.
@@ -50,6 +52,11 @@ impl TestHost {
         self.signal_queue.push_back(signal);
     }
 
+    /// Queue a value to be returned by `read_secret()`.
+    pub fn queue_secret(&mut self, secret: impl Into<String>) {
+        self.secret_queue.push_back(secret.into());
+    }
+
     /// Get all output that was written.
     pub fn output(&self) -> &[Output] {
         &self.output_buffer
@@ -133,6 +140,10 @@ impl IoHost for TestHost {
         self.flush_count += 1;
         Ok(())
     }
+
+    fn read_secret(&mut self, _prompt: &str) -> Result<Option<String>, IoError> {
+        Ok(self.secret_queue.pop_front())
+    }
 }
 
 #[cfg(test)]
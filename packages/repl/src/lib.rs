@@ -10,6 +10,8 @@
 //! The REPL is split into platform-independent core and platform-specific host:
 //!
 //! - **`repl`**: The main REPL loop, interacts only through `IoHost` trait
+//! - **`async_repl`**: A REPL loop that runs commands on a background
+//!   [`worker`] and talks to hosts through `AsyncIoHost`
 //! - **`io`**: Types and traits for I/O abstraction
 //! - **`host`**: Platform-specific implementations (terminal, future: Wasm)
 //!
@@ -18,9 +20,21 @@
 //! - Connect to local JSON stores or remote HTTP endpoints
 //! - Read and write JSON data at any path
 //! - Tab completion for commands
+//! - Errors shown with their kind, path and mount, with "did you mean"
+//!   suggestions for near-miss paths ([`diagnostics`])
 //! - Syntax highlighting for JSON input
+//! - Depth and size limits on printed values, with long output paged
+//! - Inline expressions over values ([`eval`])
+//! - Slow reads run as [`background`] jobs with `bg read <path>`
 //! - Vi mode support (detected from EDITOR, .inputrc, or STRUCTFS_EDIT_MODE)
 //! - Command history
+//! - Third-party mount types through [`plugins`]
+//! - Regression tests for store layouts with `test <file>` ([`fixtures`])
+//! - Hidden input for `write --secret`, which `read` masks unless given `--reveal`
+//! - Docs maps read as formatted help text with aligned path tables
+//!   ([`docs_view`])
+//! - Writes that would replace many values or a mount point's data show a
+//!   diff and need `--yes` ([`confirm`])
 //!
 //! ## Usage
 //!
@@ -28,6 +42,9 @@
 //! # Run the REPL
 //! structfs
 //!
+//! # Start with mounts already in place
+//! structfs --mount data=memory --mount api=http:https://api.example.com
+//!
 //! # Inside the REPL:
 //! > read /ctx/mounts
 //! > write /ctx/mounts/data {"type": "memory"}
@@ -35,30 +52,61 @@
 //! > read /data/users/1
 //! ```
 
+pub mod async_repl;
+pub mod background;
 pub mod commands;
 pub mod completer;
+pub mod confirm;
+pub mod diagnostics;
+pub mod docs_view;
+pub mod eval;
+pub mod fixtures;
 pub mod help_store;
 pub mod highlighter;
 pub mod host;
 pub mod io;
+pub mod pager;
+pub mod plugins;
+pub mod pretty;
 pub mod repl;
 pub mod repl_docs_store;
 pub mod store_context;
+pub mod worker;
 
 // Re-exports
+pub use async_repl::AsyncReplCore;
 pub use host::TerminalHost;
-pub use io::{ExitReason, IoHost, Output, PromptConfig, Signal};
+pub use io::{AsyncIoHost, ExitReason, IoHost, Output, PromptConfig, Signal};
+pub use plugins::{PluginRegistry, StorePlugin};
 pub use repl::ReplCore;
-pub use store_context::StoreContext;
+pub use store_context::{MountSpec, StoreContext};
 
 /// Run the REPL with the terminal host.
 ///
 /// This is the main entry point for the CLI application.
 pub fn run() -> std::io::Result<()> {
-    let mut core = ReplCore::new();
+    run_core(ReplCore::new())
+}
+
+/// Run the REPL with the terminal host and extra store types.
+///
+/// Binaries that embed the REPL use this to make their own
+/// [`StorePlugin`]s mountable.
+pub fn run_with_plugins(plugins: PluginRegistry) -> std::io::Result<()> {
+    run_core(ReplCore::with_plugins(plugins))
+}
+
+/// Run a configured REPL core with the terminal host.
+///
+/// Use this to prepare the core's context, for example mounting stores,
+/// before the banner is shown. Commands run in the background through an
+/// [`AsyncReplCore`], so Ctrl+C returns to the prompt while a slow command
+/// finishes.
+pub fn run_core(core: ReplCore) -> std::io::Result<()> {
+    let mut core = AsyncReplCore::with_context(core.into_context());
     let mut host = TerminalHost::new()?;
 
-    match core.run(&mut host) {
+    match core.run_blocking(&mut host) {
         Ok(_) => Ok(()),
         Err(e) => {
             eprintln!("Error: {}", e);
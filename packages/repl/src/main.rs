@@ -1,4 +1,5 @@
 use clap::Parser;
+use structfs_repl::{MountSpec, ReplCore};
 
 /// StructFS - Interactive REPL for StructFS stores
 #[derive(Parser, Debug)]
@@ -12,6 +13,20 @@ struct Args {
     /// Force emacs editing mode
     #[arg(long)]
     emacs: bool,
+
+    /// Mount a store at startup, as name=type[:arg] or name={json} (repeatable)
+    #[arg(long = "mount", value_name = "SPEC")]
+    mounts: Vec<MountSpec>,
+
+    /// Existing values a write may replace before it needs --yes (0: only
+    /// confirm writes over mount points)
+    #[arg(long, value_name = "N")]
+    confirm_threshold: Option<usize>,
+
+    /// Load store plugins from a shared library (repeatable)
+    #[cfg(feature = "dynamic-plugins")]
+    #[arg(long = "plugin", value_name = "PATH")]
+    plugins: Vec<std::path::PathBuf>,
 }
 
 fn main() {
@@ -24,8 +39,29 @@ fn main() {
         std::env::set_var("STRUCTFS_EDIT_MODE", "emacs");
     }
 
+    let plugins = structfs_repl::PluginRegistry::new();
+    #[cfg(feature = "dynamic-plugins")]
+    for path in &args.plugins {
+        // SAFETY: the user asked for this library to be loaded
+        if let Err(e) = unsafe { plugins.load_library(path) } {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut core = ReplCore::with_plugins(plugins);
+    if let Some(threshold) = args.confirm_threshold {
+        core.context_mut().set_confirm_threshold(threshold);
+    }
+    for spec in &args.mounts {
+        if let Err(e) = core.context_mut().mount_spec(spec) {
+            eprintln!("Error: failed to mount {}: {}", spec.name, e);
+            std::process::exit(1);
+        }
+    }
+
     // Run the REPL
-    if let Err(e) = structfs_repl::run() {
+    if let Err(e) = structfs_repl::run_core(core) {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,205 @@
+//! An internal pager for output taller than the terminal.
+//!
+//! The terminal host sends long output through a [`Pager`], which shows one
+//! screen at a time and waits for a key between screens:
+//!
+//! | Key | Action |
+//! |-----|--------|
+//! | Space, `f`, PageDown | Next page |
+//! | Enter, `j`, Down | Next line |
+//! | `a`, `G` | Print the rest |
+//! | `q`, Esc, Ctrl+C | Stop |
+//!
+//! Set `STRUCTFS_PAGER=off` to print everything at once.
+
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use nu_ansi_term::Color;
+
+use crate::pretty::group_thousands;
+
+/// Moves to the start of the line and erases it.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// What to do after a page is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagerAction {
+    NextPage,
+    NextLine,
+    All,
+    Quit,
+}
+
+/// Shows text one screen at a time.
+#[derive(Debug, Clone, Copy)]
+pub struct Pager {
+    height: usize,
+}
+
+impl Pager {
+    /// A pager for a screen `height` lines tall, one of which is used for
+    /// the status line.
+    pub fn new(height: usize) -> Self {
+        Self {
+            height: height.max(2),
+        }
+    }
+
+    /// A pager sized to the terminal, or `None` if stdin or stdout is not a
+    /// terminal or paging is turned off with `STRUCTFS_PAGER=off`.
+    pub fn for_terminal() -> Option<Self> {
+        use std::io::IsTerminal;
+
+        if std::env::var("STRUCTFS_PAGER").is_ok_and(|v| v.eq_ignore_ascii_case("off")) {
+            return None;
+        }
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return None;
+        }
+        let (_, rows) = terminal::size().ok()?;
+        Some(Self::new(rows as usize))
+    }
+
+    /// Whether `text` is too tall to show at once.
+    pub fn needs_paging(&self, text: &str) -> bool {
+        text.lines().count() >= self.height
+    }
+
+    /// Write `text` to `out`, calling `next` for an action whenever the
+    /// screen fills.
+    pub fn page(
+        &self,
+        text: &str,
+        out: &mut impl Write,
+        mut next: impl FnMut() -> io::Result<PagerAction>,
+    ) -> io::Result<()> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut shown = 0;
+        let mut budget = self.height - 1;
+
+        while shown < lines.len() {
+            let end = (shown + budget).min(lines.len());
+            for line in &lines[shown..end] {
+                writeln!(out, "{}", line)?;
+            }
+            shown = end;
+            if shown == lines.len() {
+                break;
+            }
+
+            write!(
+                out,
+                "{}",
+                Color::Black.on(Color::White).paint(format!(
+                    " lines 1-{} of {} (space: page, enter: line, a: all, q: quit) ",
+                    group_thousands(shown),
+                    group_thousands(lines.len())
+                ))
+            )?;
+            out.flush()?;
+            let action = next()?;
+            // Replace the status line with the next output
+            write!(out, "{}", CLEAR_LINE)?;
+
+            budget = match action {
+                PagerAction::NextPage => self.height - 1,
+                PagerAction::NextLine => 1,
+                PagerAction::All => lines.len(),
+                PagerAction::Quit => return out.flush(),
+            };
+        }
+        out.flush()
+    }
+}
+
+/// Read a pager key from the terminal.
+pub fn read_terminal_action() -> io::Result<PagerAction> {
+    terminal::enable_raw_mode()?;
+    let action = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        let action = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                PagerAction::Quit
+            }
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => PagerAction::NextPage,
+            KeyCode::Enter | KeyCode::Char('j') | KeyCode::Down => PagerAction::NextLine,
+            KeyCode::Char('a' | 'G') => PagerAction::All,
+            KeyCode::Char('q') | KeyCode::Esc => PagerAction::Quit,
+            _ => continue,
+        };
+        break Ok(action);
+    };
+    terminal::disable_raw_mode()?;
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::strip_ansi_codes;
+
+    fn run(pager: Pager, text: &str, actions: &[PagerAction]) -> (String, usize) {
+        let mut out = Vec::new();
+        let mut actions = actions.iter().copied();
+        let mut prompts = 0;
+        pager
+            .page(text, &mut out, || {
+                prompts += 1;
+                Ok(actions.next().expect("pager asked for too many actions"))
+            })
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // Drop the status lines, each of which is cleared by the next write
+        let mut chunks: Vec<String> = out.split(CLEAR_LINE).map(strip_ansi_codes).collect();
+        let last = chunks.pop().unwrap();
+        let mut visible: String = chunks
+            .iter()
+            .map(|chunk| &chunk[..chunk.rfind('\n').map_or(0, |i| i + 1)])
+            .collect();
+        visible.push_str(&last);
+        (visible, prompts)
+    }
+
+    fn numbered(n: usize) -> String {
+        (1..=n).map(|i| format!("{}\n", i)).collect()
+    }
+
+    #[test]
+    fn short_output_is_not_paged() {
+        let pager = Pager::new(10);
+        assert!(!pager.needs_paging(&numbered(9)));
+        assert!(pager.needs_paging(&numbered(10)));
+
+        let (out, prompts) = run(pager, &numbered(3), &[]);
+        assert_eq!(out, numbered(3));
+        assert_eq!(prompts, 0);
+    }
+
+    #[test]
+    fn pages_lines_and_all() {
+        let (out, prompts) = run(
+            Pager::new(4),
+            &numbered(12),
+            &[
+                PagerAction::NextPage,
+                PagerAction::NextLine,
+                PagerAction::All,
+            ],
+        );
+        assert_eq!(out, numbered(12));
+        assert_eq!(prompts, 3);
+    }
+
+    #[test]
+    fn quit_stops_output() {
+        let (out, prompts) = run(Pager::new(4), &numbered(12), &[PagerAction::Quit]);
+        assert_eq!(out, numbered(3));
+        assert_eq!(prompts, 1);
+    }
+}
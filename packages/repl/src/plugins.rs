@@ -0,0 +1,193 @@
+//! Store type plugins for the REPL.
+//!
+//! The REPL's built-in mount types are created by
+//! [`CoreReplStoreFactory`](crate::store_context::CoreReplStoreFactory). Other
+//! crates can add mount types without changing it by implementing
+//! [`StorePlugin`] and registering the plugin in a [`PluginRegistry`]:
+//!
+//! ```rust
+//! use structfs_core_store::{overlay_store::StoreBox, Error, Value};
+//! use structfs_json_store::InMemoryStore;
+//! use structfs_repl::plugins::{PluginRegistry, StorePlugin};
+//! use structfs_repl::StoreContext;
+//!
+//! struct ScratchPlugin;
+//!
+//! impl StorePlugin for ScratchPlugin {
+//!     fn kind(&self) -> &str {
+//!         "scratch"
+//!     }
+//!
+//!     fn create(&self, _config: &Value) -> Result<StoreBox, Error> {
+//!         Ok(Box::new(InMemoryStore::new()))
+//!     }
+//! }
+//!
+//! let plugins = PluginRegistry::new();
+//! plugins.register(ScratchPlugin);
+//! let mut ctx = StoreContext::with_plugins(plugins);
+//! ```
+//!
+//! The new type is then mounted like any other:
+//!
+//! ```text
+//! > write /ctx/mounts/tmp {"type": "scratch"}
+//! ```
+//!
+//! With the `dynamic-plugins` feature, plugins can also be loaded from shared
+//! libraries at runtime with [`PluginRegistry::load_library`].
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use structfs_core_store::{overlay_store::StoreBox, Error as CoreError, Value};
+
+/// A mount type provided outside the REPL.
+pub trait StorePlugin: Send + Sync {
+    /// The mount type this plugin creates, matched against the `type` field
+    /// of mount configs. Built-in types always take precedence.
+    fn kind(&self) -> &str;
+
+    /// Create a store from a mount config.
+    ///
+    /// `config` is the full config map written to `/ctx/mounts/<name>`,
+    /// including its `type` field.
+    fn create(&self, config: &Value) -> Result<StoreBox, CoreError>;
+}
+
+#[derive(Default)]
+struct Registry {
+    plugins: BTreeMap<String, Arc<dyn StorePlugin>>,
+    /// Libraries that plugins were loaded from. They must outlive the
+    /// plugins, so they are never unloaded.
+    #[cfg(feature = "dynamic-plugins")]
+    libraries: Vec<libloading::Library>,
+}
+
+/// Registered store plugins, by mount type.
+///
+/// Clones share the same plugins, so a plugin registered after the registry
+/// was handed to a [`StoreContext`](crate::StoreContext) is still available
+/// to later mounts.
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    inner: Arc<RwLock<Registry>>,
+}
+
+impl PluginRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a plugin, replacing any plugin for the same mount type.
+    pub fn register(&self, plugin: impl StorePlugin + 'static) {
+        self.register_arc(Arc::new(plugin));
+    }
+
+    /// Register a shared plugin, replacing any plugin for the same mount type.
+    pub fn register_arc(&self, plugin: Arc<dyn StorePlugin>) {
+        let kind = plugin.kind().to_string();
+        self.inner.write().unwrap().plugins.insert(kind, plugin);
+    }
+
+    /// The plugin for a mount type.
+    pub fn get(&self, kind: &str) -> Option<Arc<dyn StorePlugin>> {
+        self.inner.read().unwrap().plugins.get(kind).cloned()
+    }
+
+    /// Registered mount types, sorted.
+    pub fn kinds(&self) -> Vec<String> {
+        self.inner.read().unwrap().plugins.keys().cloned().collect()
+    }
+
+    /// Create a store with the plugin for `kind`.
+    pub fn create(&self, kind: &str, config: &Value) -> Result<StoreBox, CoreError> {
+        match self.get(kind) {
+            Some(plugin) => plugin.create(config),
+            None => Err(CoreError::store(
+                "factory",
+                "create",
+                format!("Unknown mount type: {}", kind),
+            )),
+        }
+    }
+}
+
+/// Symbol a plugin library exports to register its plugins.
+///
+/// The library defines it as:
+///
+/// ```rust,ignore
+/// #[no_mangle]
+/// pub fn structfs_repl_register_plugins(registry: &PluginRegistry) {
+///     registry.register(MyPlugin);
+/// }
+/// ```
+#[cfg(feature = "dynamic-plugins")]
+pub const REGISTER_SYMBOL: &str = "structfs_repl_register_plugins";
+
+#[cfg(feature = "dynamic-plugins")]
+impl PluginRegistry {
+    /// Load a plugin library and let it register its plugins.
+    ///
+    /// The library is never unloaded. Rust has no stable ABI, so the library
+    /// must be built with the same compiler and the same version of this
+    /// crate as the REPL loading it.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initializers, and the registration symbol
+    /// is trusted to have the signature documented on [`REGISTER_SYMBOL`].
+    pub unsafe fn load_library(&self, path: impl AsRef<std::path::Path>) -> Result<(), CoreError> {
+        let path = path.as_ref();
+        let load_error = |e: libloading::Error| {
+            CoreError::store("plugins", "load", format!("{}: {}", path.display(), e))
+        };
+
+        let library = libloading::Library::new(path).map_err(load_error)?;
+        let register: libloading::Symbol<fn(&PluginRegistry)> = library
+            .get(REGISTER_SYMBOL.as_bytes())
+            .map_err(load_error)?;
+        register(self);
+        self.inner.write().unwrap().libraries.push(library);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_json_store::InMemoryStore;
+
+    struct ScratchPlugin;
+
+    impl StorePlugin for ScratchPlugin {
+        fn kind(&self) -> &str {
+            "scratch"
+        }
+
+        fn create(&self, _config: &Value) -> Result<StoreBox, CoreError> {
+            Ok(Box::new(InMemoryStore::new()))
+        }
+    }
+
+    #[test]
+    fn registry_creates_registered_kinds() {
+        let registry = PluginRegistry::new();
+        registry.register(ScratchPlugin);
+
+        assert_eq!(registry.kinds(), vec!["scratch".to_string()]);
+        assert!(registry.create("scratch", &Value::Null).is_ok());
+        let err = registry.create("redis", &Value::Null).err().unwrap();
+        assert!(err.to_string().contains("Unknown mount type: redis"));
+    }
+
+    #[test]
+    fn clones_share_plugins() {
+        let registry = PluginRegistry::new();
+        let clone = registry.clone();
+        clone.register(ScratchPlugin);
+        assert!(registry.get("scratch").is_some());
+    }
+}
@@ -0,0 +1,217 @@
+//! Colorized pretty printing with depth and size limits.
+//!
+//! Reading a large subtree would otherwise print every element. The
+//! printer shows at most [`limit`](PrettyOptions::limit) entries of each
+//! array or map, ending with a `… 4,312 more` marker, and collapses
+//! containers nested deeper than [`depth`](PrettyOptions::depth) into a
+//! one-line summary such as `{… 12 entries}`.
+
+use nu_ansi_term::{Color, Style};
+use serde_json::Value as JsonValue;
+
+/// Entries shown per array or map when no `--limit` is given.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// How much of a value to print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Levels of nesting to expand; deeper containers are summarized.
+    /// `None` expands everything.
+    pub depth: Option<usize>,
+    /// Entries shown per array or map. `None` shows all of them.
+    pub limit: Option<usize>,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            depth: None,
+            limit: Some(DEFAULT_LIMIT),
+        }
+    }
+}
+
+/// Format `value` as indented, colorized JSON within `options`' limits.
+pub fn format_value(value: &JsonValue, options: &PrettyOptions) -> String {
+    let mut out = String::new();
+    Printer {
+        options,
+        out: &mut out,
+    }
+    .value(value, 0);
+    out
+}
+
+/// Format `n` with comma thousands separators, e.g. `4,312`.
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+struct Printer<'a> {
+    options: &'a PrettyOptions,
+    out: &'a mut String,
+}
+
+impl Printer<'_> {
+    fn value(&mut self, value: &JsonValue, level: usize) {
+        match value {
+            JsonValue::Null => self.paint(Color::Yellow.normal(), "null"),
+            JsonValue::Bool(b) => self.paint(Color::Yellow.normal(), &b.to_string()),
+            JsonValue::Number(n) => self.paint(Color::Cyan.normal(), &n.to_string()),
+            JsonValue::String(s) => self.string(s),
+            JsonValue::Array(items) => self.container(
+                ('[', ']'),
+                items.len(),
+                level,
+                "item",
+                items.iter().map(|item| (None, item)),
+            ),
+            JsonValue::Object(map) => self.container(
+                ('{', '}'),
+                map.len(),
+                level,
+                "entry",
+                map.iter().map(|(key, value)| (Some(key.as_str()), value)),
+            ),
+        }
+    }
+
+    fn container<'v>(
+        &mut self,
+        (open, close): (char, char),
+        len: usize,
+        level: usize,
+        noun: &str,
+        entries: impl Iterator<Item = (Option<&'v str>, &'v JsonValue)>,
+    ) {
+        let bracket = Color::White.bold();
+        if len == 0 {
+            self.paint(bracket, &format!("{}{}", open, close));
+            return;
+        }
+        if self.options.depth.is_some_and(|depth| level >= depth) {
+            let plural = if noun == "entry" { "entries" } else { "items" };
+            self.paint(bracket, &open.to_string());
+            self.marker(&format!(
+                "… {} {}",
+                group_thousands(len),
+                if len == 1 { noun } else { plural }
+            ));
+            self.paint(bracket, &close.to_string());
+            return;
+        }
+
+        let shown = self.options.limit.map_or(len, |limit| limit.min(len));
+        let inner = "  ".repeat(level + 1);
+        self.paint(bracket, &open.to_string());
+        for (i, (key, value)) in entries.take(shown).enumerate() {
+            self.out.push('\n');
+            self.out.push_str(&inner);
+            if let Some(key) = key {
+                self.string(key);
+                self.paint(Color::White.normal(), ":");
+                self.out.push(' ');
+            }
+            self.value(value, level + 1);
+            if i + 1 < len {
+                self.paint(Color::White.normal(), ",");
+            }
+        }
+        if shown < len {
+            self.out.push('\n');
+            self.out.push_str(&inner);
+            self.marker(&format!("… {} more", group_thousands(len - shown)));
+        }
+        self.out.push('\n');
+        self.out.push_str(&"  ".repeat(level));
+        self.paint(bracket, &close.to_string());
+    }
+
+    fn string(&mut self, s: &str) {
+        let quoted = serde_json::to_string(s).unwrap_or_else(|_| format!("{:?}", s));
+        self.paint(Color::Green.normal(), &quoted);
+    }
+
+    fn marker(&mut self, text: &str) {
+        self.paint(Color::DarkGray.italic(), text);
+    }
+
+    fn paint(&mut self, style: Style, text: &str) {
+        self.out.push_str(&style.paint(text).to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::strip_ansi_codes;
+    use serde_json::json;
+
+    fn plain(value: &JsonValue, options: PrettyOptions) -> String {
+        strip_ansi_codes(&format_value(value, &options))
+    }
+
+    #[test]
+    fn matches_pretty_json_within_limits() {
+        let value = json!({"name": "a\"b", "tags": [1, 2.5, null], "empty": {}, "ok": true});
+        assert_eq!(
+            plain(&value, PrettyOptions::default()),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn limit_adds_more_marker() {
+        let value = JsonValue::Array((0..5000).map(|i| json!(i)).collect());
+        let output = plain(
+            &value,
+            PrettyOptions {
+                depth: None,
+                limit: Some(2),
+            },
+        );
+        assert_eq!(output, "[\n  0,\n  1,\n  … 4,998 more\n]");
+    }
+
+    #[test]
+    fn depth_summarizes_nested_containers() {
+        let value = json!({"users": {"1": {"name": "Alice"}}, "ids": [1], "n": 3});
+        let output = plain(
+            &value,
+            PrettyOptions {
+                depth: Some(1),
+                limit: None,
+            },
+        );
+        assert_eq!(
+            output,
+            "{\n  \"ids\": [… 1 item],\n  \"n\": 3,\n  \"users\": {… 1 entry}\n}"
+        );
+        assert_eq!(
+            plain(
+                &value,
+                PrettyOptions {
+                    depth: Some(0),
+                    limit: None
+                }
+            ),
+            "{… 3 entries}"
+        );
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(4312), "4,312");
+        assert_eq!(group_thousands(1234567), "1,234,567");
+    }
+}
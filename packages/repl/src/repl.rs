@@ -4,6 +4,7 @@
 
 use crate::commands::{self, CommandResult};
 use crate::io::{ExitReason, IoError, IoHost, Output, PromptConfig, Signal};
+use crate::plugins::PluginRegistry;
 use crate::store_context::StoreContext;
 
 /// The platform-independent REPL core.
@@ -19,6 +20,14 @@ impl ReplCore {
         }
     }
 
+    /// Create a REPL core whose `mount` also accepts the store types in
+    /// `plugins`.
+    pub fn with_plugins(plugins: PluginRegistry) -> Self {
+        Self {
+            ctx: StoreContext::with_plugins(plugins),
+        }
+    }
+
     /// Run the REPL loop, reading/writing through the provided I/O host.
     pub fn run(&mut self, io: &mut impl IoHost) -> Result<ExitReason, IoError> {
         self.write_banner(io)?;
@@ -46,7 +55,10 @@ impl ReplCore {
                 None => continue,
             };
 
-            let result = commands::execute(&input.line, &mut self.ctx);
+            let Some(line) = prompt_for_secret(io, input.line)? else {
+                continue;
+            };
+            let result = commands::execute(&line, &mut self.ctx);
 
             match result {
                 CommandResult::Ok { display: None, .. } => {}
@@ -80,6 +92,12 @@ impl ReplCore {
         &mut self.ctx
     }
 
+    /// Take the store context, for example to hand it to an
+    /// [`AsyncReplCore`](crate::AsyncReplCore).
+    pub fn into_context(self) -> StoreContext {
+        self.ctx
+    }
+
     fn write_banner(&self, io: &mut impl IoHost) -> Result<(), IoError> {
         io.write_output(Output::banner(BANNER))
     }
@@ -100,7 +118,30 @@ impl Default for ReplCore {
     }
 }
 
-fn format_path(path: &structfs_core_store::Path) -> String {
+/// Complete a `write --secret <path>` line with a value typed at a hidden
+/// prompt; other lines pass through. Returns `None` if the user cancels or
+/// the host cannot hide input.
+pub(crate) fn prompt_for_secret(
+    io: &mut impl crate::io::AsyncIoHost,
+    line: String,
+) -> Result<Option<String>, IoError> {
+    let Some(path) = commands::secret_write_path(&line) else {
+        return Ok(Some(line));
+    };
+    match io.read_secret(&format!("Value for {}: ", path)) {
+        Ok(Some(secret)) => Ok(Some(commands::with_secret_value(&line, &secret))),
+        Ok(None) => {
+            io.write_output(Output::info("Cancelled"))?;
+            Ok(None)
+        }
+        Err(e) => {
+            io.write_output(Output::error(e.to_string()))?;
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) fn format_path(path: &structfs_core_store::Path) -> String {
     if path.is_empty() {
         "/".to_string()
     } else {
@@ -108,7 +149,7 @@ fn format_path(path: &structfs_core_store::Path) -> String {
     }
 }
 
-const BANNER: &str = r#"
+pub(crate) const BANNER: &str = r#"
   _____ _                   _   _____ ____
  / ____| |                 | | |  ___/ ___|
 | (___ | |_ _ __ _   _  ___| |_| |_  \___ \
@@ -203,4 +244,35 @@ mod tests {
         // Should have output containing a timestamp
         assert!(host.outputs.iter().any(|o| o.text.contains("T")));
     }
+
+    #[test]
+    fn test_secret_write_prompts_and_masks() {
+        let mut core = ReplCore::new();
+        core.context_mut()
+            .mount(
+                "vault",
+                structfs_core_store::mount_store::MountConfig::Memory,
+            )
+            .unwrap();
+        let mut host = crate::io::TestHost::new();
+        host.queue_inputs([
+            "write --secret /vault/token",
+            "read /vault/token",
+            "read /vault/token --reveal",
+            "exit",
+        ]);
+        host.queue_secret("hunter2");
+
+        core.run(&mut host).unwrap();
+
+        let normal = host.output_with_style(crate::io::OutputStyle::Normal);
+        assert!(normal.contains(&crate::store_context::MASK));
+        assert_eq!(
+            normal
+                .iter()
+                .filter(|text| text.contains("hunter2"))
+                .count(),
+            1
+        );
+    }
 }
@@ -56,17 +56,42 @@ impl ReplDocsStore {
 
     fn commands_docs() -> Value {
         let commands = [
-            ("read", "read <path>", "Read value at path (alias: get, r)"),
+            (
+                "read",
+                "read <path> [--depth N] [--limit N] [--reveal] [--json]",
+                "Read value at path, showing N levels and N entries per container; secrets are masked unless --reveal, and docs show as help text unless --json (alias: get, r)",
+            ),
             (
                 "write",
-                "write <path> <json>",
-                "Write JSON value to path (alias: set, w)",
+                "write [--secret] [--yes] <path> <json>",
+                "Write JSON value to path; --secret prompts for it with hidden input, --yes skips confirming a write that replaces many values or a mount point's data (alias: set, w)",
+            ),
+            (
+                "eval",
+                "eval <expr>",
+                "Compute a value from reads, arithmetic and len, keys, sum, pick",
             ),
             ("ls", "ls [path]", "List children at path"),
             ("cd", "cd <path>", "Change current directory"),
             ("pwd", "pwd", "Print current directory"),
             ("mounts", "mounts", "List all mount points"),
             ("registers", "registers", "List all registers (alias: regs)"),
+            (
+                "bg",
+                "bg read <path>",
+                "Read in the background, printing a job id",
+            ),
+            ("jobs", "jobs", "List background jobs"),
+            (
+                "wait",
+                "wait <id>",
+                "Wait for a background job and show its value",
+            ),
+            (
+                "result",
+                "result <id>",
+                "Show a background job's value if it has finished",
+            ),
             ("help", "help [topic]", "Show help"),
             ("exit", "exit", "Exit the REPL (alias: quit, q)"),
         ];
@@ -399,4 +424,14 @@ mod tests {
         let store: ReplDocsStore = Default::default();
         assert!(!store.docs.is_empty());
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = ReplDocsStore::new();
+        conformance::assert_found(&mut store, &path!("docs"));
+        conformance::assert_missing(&mut store, &path!("docs/nope"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+    }
 }
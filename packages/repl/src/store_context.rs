@@ -3,21 +3,29 @@
 //! This module provides the store context that manages mounts and registers.
 
 use collection_literals::btree;
-use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, RwLock};
 
 use structfs_core_store::{
+    dump::{export_subtree, import_subtree, DumpReader, DumpWriter},
     mount_store::{MountConfig, MountStore, StoreFactory},
     overlay_store::StoreBox,
-    Error as CoreError, NoCodec, Path, Reader, Record, Value, Writer,
+    stats::{subtree_stats, SubtreeStats},
+    Error as CoreError, NoCodec, Path, Reader, Record, Store, Value, Writer,
 };
 
 use structfs_serde_store::{json_to_value, value_to_json};
 
 // Import store implementations
+use crate::background::{BackgroundJobs, SharedStore, SharingFactory};
+use crate::confirm::DEFAULT_CONFIRM_THRESHOLD;
 use crate::help_store::{HelpStore, HelpStoreHandle, HelpStoreState};
+use crate::plugins::PluginRegistry;
 use crate::repl_docs_store::ReplDocsStore;
-use structfs_http::{AsyncHttpBrokerStore, HttpBrokerStore};
+use structfs_http::{
+    AsyncHttpBrokerStore, CircuitBreakerStore, HttpBrokerStore, HttpClientStore, OpenApiStore,
+};
 use structfs_json_store::InMemoryStore;
 use structfs_sys::SysStore;
 
@@ -36,8 +44,29 @@ pub enum ContextError {
 /// Factory for creating stores from mount configurations.
 ///
 /// This is the default factory used by StoreContext. It creates stores for
-/// all standard mount configurations (memory, HTTP, sys, help, etc.).
-pub struct CoreReplStoreFactory;
+/// all standard mount configurations (memory, HTTP, sys, help, etc.), and
+/// hands other mount types to its [`PluginRegistry`].
+#[derive(Clone, Default)]
+pub struct CoreReplStoreFactory {
+    plugins: PluginRegistry,
+}
+
+impl CoreReplStoreFactory {
+    /// Create a factory with no plugins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a factory that also creates the mount types in `plugins`.
+    pub fn with_plugins(plugins: PluginRegistry) -> Self {
+        Self { plugins }
+    }
+
+    /// The plugins this factory uses.
+    pub fn plugins(&self) -> &PluginRegistry {
+        &self.plugins
+    }
+}
 
 impl StoreFactory for CoreReplStoreFactory {
     fn create(&self, config: &MountConfig) -> Result<StoreBox, CoreError> {
@@ -51,16 +80,18 @@ impl StoreFactory for CoreReplStoreFactory {
                     "Local disk store not yet available in new architecture",
                 ))
             }
-            MountConfig::Http { url: _ } => {
-                // HTTP client not using direct mode in REPL context
-                Err(CoreError::store(
-                    "factory",
-                    "create",
-                    "HTTP client store not yet available in new architecture",
-                ))
+            MountConfig::Http { url, options } => {
+                let store = HttpClientStore::with_options(url, options).map_err(|e| {
+                    CoreError::store(
+                        "factory",
+                        "create",
+                        format!("Failed to create HTTP client: {}", e),
+                    )
+                })?;
+                OpenApiStore::boxed(CircuitBreakerStore::boxed(store, options), options)
             }
-            MountConfig::HttpBroker => {
-                let store = HttpBrokerStore::with_default_timeout().map_err(|e| {
+            MountConfig::HttpBroker { options } => {
+                let store = HttpBrokerStore::with_options(options).map_err(|e| {
                     CoreError::store(
                         "factory",
                         "create",
@@ -69,8 +100,8 @@ impl StoreFactory for CoreReplStoreFactory {
                 })?;
                 Ok(Box::new(store))
             }
-            MountConfig::AsyncHttpBroker => {
-                let store = AsyncHttpBrokerStore::with_default_timeout().map_err(|e| {
+            MountConfig::AsyncHttpBroker { options } => {
+                let store = AsyncHttpBrokerStore::with_options(options).map_err(|e| {
                     CoreError::store(
                         "factory",
                         "create",
@@ -88,6 +119,7 @@ impl StoreFactory for CoreReplStoreFactory {
             MountConfig::Sys => Ok(Box::new(SysStore::new())),
             MountConfig::Repl => Ok(Box::new(ReplDocsStore::new())),
             MountConfig::Registers => Ok(Box::new(RegisterStore::new())),
+            MountConfig::Plugin { kind, config } => self.plugins.create(kind, config),
         }
     }
 }
@@ -235,10 +267,19 @@ impl Writer for RegisterStore {
 /// Registers are now mounted at `/ctx/registers/` rather than embedded.
 /// Use `@name` syntax as sugar for `/ctx/registers/name`.
 pub struct StoreContext<F: StoreFactory = CoreReplStoreFactory> {
-    store: MountStore<F>,
+    store: MountStore<SharingFactory<F>>,
     current_path: Path,
     /// Handle to HelpStore state for dynamic updates on mount/unmount
     help_state: Option<HelpStoreHandle>,
+    /// The store the factory created last, until it is recorded in `shared`
+    created: Arc<Mutex<Option<SharedStore>>>,
+    /// Mounted stores by mount path, for background jobs
+    shared: BTreeMap<Path, SharedStore>,
+    jobs: BackgroundJobs,
+    /// Paths written with `write --secret`, whose values `read` masks
+    secrets: BTreeSet<Path>,
+    /// Existing values a `write` may replace before it needs `--yes`
+    confirm_threshold: usize,
 }
 
 impl StoreContext<CoreReplStoreFactory> {
@@ -250,8 +291,31 @@ impl StoreContext<CoreReplStoreFactory> {
     /// - `/ctx/sys` - System utilities (time, env, proc, fs, random)
     /// - `/ctx/help` - Help system
     pub fn new() -> Self {
-        Self::with_factory_and_mounts(CoreReplStoreFactory, true)
+        Self::with_factory_and_mounts(CoreReplStoreFactory::new(), true)
     }
+
+    /// Create a context with the standard mounts that can also mount the
+    /// store types in `plugins`.
+    pub fn with_plugins(plugins: PluginRegistry) -> Self {
+        Self::with_factory_and_mounts(CoreReplStoreFactory::with_plugins(plugins), true)
+    }
+}
+
+/// What `read` shows in place of a secret.
+pub const MASK: &str = "•••";
+
+/// The value at `path` inside `value`, following map keys and list indices.
+fn value_at_mut<'a>(value: &'a mut Value, path: &Path) -> Option<&'a mut Value> {
+    path.components
+        .iter()
+        .try_fold(value, |value, component| match value {
+            Value::Map(map) => map.get_mut(component),
+            Value::Array(items) => component
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i)),
+            _ => None,
+        })
 }
 
 /// Check if a path string refers to a register (starts with @)
@@ -280,14 +344,95 @@ pub fn parse_register_path(path_str: &str) -> Option<(String, Path)> {
     }
 }
 
+/// A mount requested on the command line.
+///
+/// Parsed from `name=type`, `name=type:arg` or `name={json mount config}`:
+///
+/// - `data=memory`, `sys=sys`, `tmp=scratch` (any type without options)
+/// - `api=http:https://api.example.com`, `remote=structfs:https://...`
+/// - `disk=local:/var/data`
+/// - `cache={"type": "redis", "url": "redis://localhost"}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountSpec {
+    /// Mount path, without a leading slash.
+    pub name: String,
+    /// Mount config, as written to `/ctx/mounts/<name>`.
+    pub config: Value,
+}
+
+impl std::str::FromStr for MountSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (name, config) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=type, got '{}'", spec))?;
+        let name = name.trim_start_matches('/');
+        if name.is_empty() {
+            return Err(format!("missing mount name in '{}'", spec));
+        }
+        Path::parse(name).map_err(|e| format!("invalid mount name '{}': {}", name, e))?;
+
+        let config = if config.starts_with('{') {
+            let json: serde_json::Value = serde_json::from_str(config)
+                .map_err(|e| format!("invalid mount config for '{}': {}", name, e))?;
+            json_to_value(json)
+        } else {
+            let (kind, arg) = match config.split_once(':') {
+                Some((kind, arg)) => (kind, Some(arg)),
+                None => (config, None),
+            };
+            if kind.is_empty() {
+                return Err(format!("missing mount type for '{}'", name));
+            }
+            let mut map = btree! {
+                "type".to_string() => Value::String(kind.to_string()),
+            };
+            let arg_field = match kind {
+                "http" | "structfs" => Some("url"),
+                "local" => Some("path"),
+                _ => None,
+            };
+            match (arg_field, arg) {
+                (Some(field), Some(arg)) => {
+                    map.insert(field.to_string(), Value::String(arg.to_string()));
+                }
+                (Some(field), None) => {
+                    return Err(format!("{} mount '{}' needs a {}", kind, name, field));
+                }
+                (None, Some(_)) => {
+                    return Err(format!(
+                        "{} mount '{}' takes no argument; use name={{json}} for options",
+                        kind, name
+                    ));
+                }
+                (None, None) => {}
+            }
+            Value::Map(map)
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            config,
+        })
+    }
+}
+
 impl<F: StoreFactory> StoreContext<F> {
     /// Create a context with a custom factory and optionally mount defaults.
     ///
     /// If `mount_defaults` is true, the standard mounts (http, sys, help, repl) are added.
     /// If false, the context starts with no mounts.
     pub fn with_factory_and_mounts(factory: F, mount_defaults: bool) -> Self {
+        let (factory, created) = SharingFactory::new(factory);
         let mut store = MountStore::new(factory);
         let mut help_state: Option<HelpStoreHandle> = None;
+        let mut shared = BTreeMap::new();
+        let mut adopt = |path: &str| {
+            if let Some(store) = created.lock().unwrap().take() {
+                shared.insert(Path::parse(path).unwrap(), store);
+            }
+        };
 
         if mount_defaults {
             // Mount stores with docs FIRST (they create redirects)
@@ -295,26 +440,41 @@ impl<F: StoreFactory> StoreContext<F> {
             if let Err(e) = store.mount("ctx/repl", MountConfig::Repl) {
                 eprintln!("Warning: Failed to mount REPL docs store: {}", e);
             }
+            adopt("ctx/repl");
 
             // Mount async HTTP broker (background execution)
-            if let Err(e) = store.mount("ctx/http", MountConfig::AsyncHttpBroker) {
+            if let Err(e) = store.mount(
+                "ctx/http",
+                MountConfig::AsyncHttpBroker {
+                    options: Default::default(),
+                },
+            ) {
                 eprintln!("Warning: Failed to mount async HTTP broker: {}", e);
             }
+            adopt("ctx/http");
 
             // Mount sync HTTP broker (blocking execution)
-            if let Err(e) = store.mount("ctx/http_sync", MountConfig::HttpBroker) {
+            if let Err(e) = store.mount(
+                "ctx/http_sync",
+                MountConfig::HttpBroker {
+                    options: Default::default(),
+                },
+            ) {
                 eprintln!("Warning: Failed to mount HTTP broker: {}", e);
             }
+            adopt("ctx/http_sync");
 
             // Mount sys store
             if let Err(e) = store.mount("ctx/sys", MountConfig::Sys) {
                 eprintln!("Warning: Failed to mount sys store: {}", e);
             }
+            adopt("ctx/sys");
 
             // Mount register store (session-local named values)
             if let Err(e) = store.mount("ctx/registers", MountConfig::Registers) {
                 eprintln!("Warning: Failed to mount register store: {}", e);
             }
+            adopt("ctx/registers");
 
             // Create shared state for HelpStore
             let state = Arc::new(RwLock::new(HelpStoreState::new()));
@@ -334,11 +494,16 @@ impl<F: StoreFactory> StoreContext<F> {
             store,
             current_path: Path::parse("").unwrap(),
             help_state,
+            created,
+            shared,
+            jobs: BackgroundJobs::default(),
+            secrets: BTreeSet::new(),
+            confirm_threshold: DEFAULT_CONFIRM_THRESHOLD,
         }
     }
 
     /// Populate HelpStore state from existing redirects.
-    fn populate_help_state(store: &mut MountStore<F>, state: &HelpStoreHandle) {
+    fn populate_help_state(store: &mut MountStore<SharingFactory<F>>, state: &HelpStoreHandle) {
         use structfs_core_store::path;
 
         let help_prefix = path!("ctx/help");
@@ -419,6 +584,24 @@ impl<F: StoreFactory> StoreContext<F> {
     /// After mounting, the help index is refreshed to include any new docs.
     pub fn mount(&mut self, path: &str, config: MountConfig) -> Result<(), ContextError> {
         self.store.mount(path, config)?;
+        self.adopt_mount(Path::parse(path).map_err(CoreError::Path)?);
+        self.refresh_help_state();
+        Ok(())
+    }
+
+    /// Record the store just created for the mount at `path`.
+    fn adopt_mount(&mut self, path: Path) {
+        if let Some(store) = self.created.lock().unwrap().take() {
+            self.shared.insert(path, store);
+        }
+    }
+
+    /// Mount a store from a [`MountSpec`], as if its config were written to
+    /// `/ctx/mounts/<name>`.
+    pub fn mount_spec(&mut self, spec: &MountSpec) -> Result<(), ContextError> {
+        let name = Path::parse(&spec.name).map_err(CoreError::Path)?;
+        let mounts = Path::parse("ctx/mounts").map_err(CoreError::Path)?;
+        self.write(&mounts.join(&name), spec.config.clone())?;
         self.refresh_help_state();
         Ok(())
     }
@@ -428,6 +611,9 @@ impl<F: StoreFactory> StoreContext<F> {
     /// After unmounting, the help index is refreshed to remove the store's docs.
     pub fn unmount(&mut self, path: &str) -> Result<(), ContextError> {
         self.store.unmount(path)?;
+        if let Ok(path) = Path::parse(path) {
+            self.shared.remove(&path);
+        }
         self.refresh_help_state();
         Ok(())
     }
@@ -516,6 +702,43 @@ impl<F: StoreFactory> StoreContext<F> {
         }
     }
 
+    /// The mount serving `path`: the longest mounted prefix of it.
+    pub fn mount_of(&self, path: &Path) -> Option<Path> {
+        self.store
+            .list_mounts()
+            .into_iter()
+            .filter_map(|mount| Path::parse(&mount.path).ok())
+            .filter(|mount| path.has_prefix(mount))
+            .max_by_key(|mount| mount.len())
+    }
+
+    /// How many existing values a `write` may remove or change before it
+    /// needs `--yes`. Writes over a mount point always need it. See the
+    /// [`confirm`](crate::confirm) module.
+    pub fn confirm_threshold(&self) -> usize {
+        self.confirm_threshold
+    }
+
+    /// Set the [confirmation threshold](Self::confirm_threshold); 0 only
+    /// confirms writes over mount points.
+    pub fn set_confirm_threshold(&mut self, threshold: usize) {
+        self.confirm_threshold = threshold;
+    }
+
+    /// Names of the entries directly below `path`, sorted.
+    ///
+    /// Lists `path` with [`Store::list`], which includes the next component
+    /// of any mount below it. A failed listing lists nothing.
+    pub fn children(&mut self, path: &Path) -> Vec<String> {
+        let mut names = Store::list(&mut self.store, path)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        names.sort();
+        names.dedup();
+        names
+    }
+
     /// Get the current path
     pub fn current_path(&self) -> &Path {
         &self.current_path
@@ -527,39 +750,25 @@ impl<F: StoreFactory> StoreContext<F> {
     }
 
     /// Resolve a path relative to the current path
+    ///
+    /// `..` and `.` segments may appear anywhere, and a leading `/` starts
+    /// from the root; see [`Path::resolve`]. Unlike [`Path::resolve`], `..`
+    /// at the root stays there, as `cd ..` does in a shell.
     pub fn resolve_path(&self, path_str: &str) -> Result<Path, ContextError> {
-        if path_str.is_empty() || path_str == "." {
-            return Ok(self.current_path.clone());
-        }
-
-        if path_str == "/" {
-            return Ok(Path::parse("").unwrap());
-        }
-
-        if let Some(stripped) = path_str.strip_prefix('/') {
-            Path::parse(stripped).map_err(|e| ContextError::InvalidPath(format!("{}", e)))
-        } else if path_str == ".." {
-            let mut components = self.current_path.components.clone();
-            components.pop();
-            Ok(Path { components })
-        } else if path_str.starts_with("../") {
-            let mut components = self.current_path.components.clone();
-            let mut remaining = path_str;
-            while remaining.starts_with("../") {
-                components.pop();
-                remaining = &remaining[3..];
-            }
-            if !remaining.is_empty() {
-                let suffix = Path::parse(remaining)
-                    .map_err(|e| ContextError::InvalidPath(format!("{}", e)))?;
-                components.extend(suffix.components);
-            }
-            Ok(Path { components })
+        let mut path = if path_str.starts_with('/') {
+            Path::parse("").unwrap()
         } else {
-            let suffix =
-                Path::parse(path_str).map_err(|e| ContextError::InvalidPath(format!("{}", e)))?;
-            Ok(self.current_path.join(&suffix))
+            self.current_path.clone()
+        };
+        for segment in path_str.split('/').filter(|s| !s.is_empty()) {
+            if segment == ".." && path.is_empty() {
+                continue;
+            }
+            path = path
+                .resolve(segment)
+                .map_err(|e| ContextError::InvalidPath(format!("{}", e)))?;
         }
+        Ok(path)
     }
 
     /// Read Value from a path
@@ -573,7 +782,91 @@ impl<F: StoreFactory> StoreContext<F> {
 
     /// Write Value to a path
     pub fn write(&mut self, path: &Path, value: Value) -> Result<Path, ContextError> {
-        Ok(self.store.write(path, Record::parsed(value))?)
+        let written = self.store.write(path, Record::parsed(value))?;
+        self.secrets.remove(path);
+        // Writing `ctx/mounts/{name}` mounts or unmounts `name`
+        if path.len() == 3 && path.has_prefix(&Path::parse("ctx/mounts").unwrap()) {
+            let name = path.slice(2, 3);
+            self.shared.remove(&name);
+            self.adopt_mount(name);
+        }
+        Ok(written)
+    }
+
+    /// Write a secret to a path. Reads of it, or of anything containing it,
+    /// show it masked until it is overwritten with a plain `write`.
+    pub fn write_secret(&mut self, path: &Path, value: Value) -> Result<Path, ContextError> {
+        let written = self.write(path, value)?;
+        self.secrets.insert(path.clone());
+        Ok(written)
+    }
+
+    /// Whether the value at `path` is, or contains, a secret.
+    pub fn holds_secret(&self, path: &Path) -> bool {
+        self.secrets
+            .iter()
+            .any(|secret| path.has_prefix(secret) || secret.has_prefix(path))
+    }
+
+    /// Replace every secret in `value`, read from `path`, with [`MASK`].
+    pub fn mask_secrets(&self, path: &Path, mut value: Value) -> Value {
+        for secret in &self.secrets {
+            if path.has_prefix(secret) {
+                return Value::String(MASK.to_string());
+            }
+            if let Some(inner) = secret.strip_prefix(path) {
+                if let Some(slot) = value_at_mut(&mut value, &inner) {
+                    *slot = Value::String(MASK.to_string());
+                }
+            }
+        }
+        value
+    }
+
+    /// Start reading `path` in the background; `line` is the command shown
+    /// in job listings. Returns the job id.
+    ///
+    /// See [`background`](crate::background).
+    pub fn spawn_background_read(&mut self, line: &str, path: &Path) -> Result<u64, ContextError> {
+        let (mount, store) = self
+            .shared
+            .iter()
+            .filter(|(mount, _)| path.has_prefix(mount))
+            .max_by_key(|(mount, _)| mount.len())
+            .ok_or_else(|| CoreError::NoRoute { path: path.clone() })?;
+        let inner = path.slice(mount.len(), path.len());
+        Ok(self.jobs.spawn_read(line, store.clone(), inner))
+    }
+
+    /// The session's background jobs.
+    pub fn jobs(&mut self) -> &mut BackgroundJobs {
+        &mut self.jobs
+    }
+
+    /// Dump every record at or below `prefix` to `dump`. Returns how many
+    /// entries were written.
+    ///
+    /// See [`structfs_core_store::dump`].
+    pub fn export<W: Write>(
+        &mut self,
+        prefix: &Path,
+        dump: &mut DumpWriter<W>,
+    ) -> Result<usize, ContextError> {
+        Ok(export_subtree(&mut self.store, prefix, dump)?)
+    }
+
+    /// Totals for every record at or below `prefix`, keeping the `largest`
+    /// biggest records.
+    ///
+    /// See [`structfs_core_store::stats`].
+    pub fn stats(&mut self, prefix: &Path, largest: usize) -> Result<SubtreeStats, ContextError> {
+        Ok(subtree_stats(&mut self.store, prefix, largest)?)
+    }
+
+    /// Write every entry in `dump` back to the path it was exported from.
+    /// Returns how many records were written.
+    pub fn import<R: Read>(&mut self, dump: &mut DumpReader<R>) -> Result<usize, ContextError> {
+        Ok(import_subtree(&mut self.store, dump)?)
     }
 
     /// Read and convert to JsonValue for display compatibility
@@ -627,6 +920,16 @@ mod tests {
         assert_eq!(store.get("nonexistent"), None);
     }
 
+    #[test]
+    fn register_store_conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = RegisterStore::new();
+        conformance::assert_missing(&mut store, &path!("foo"));
+        conformance::assert_round_trip(&mut store, &path!("foo"), Value::Integer(1));
+        conformance::assert_missing(&mut store, &path!("foo/bar"));
+    }
+
     #[test]
     fn register_store_list() {
         let mut store = RegisterStore::new();
@@ -857,6 +1160,26 @@ mod tests {
         assert_eq!(path.to_string(), "foo/bar/qux");
     }
 
+    #[test]
+    fn test_resolve_inner_relative_segments() {
+        let mut ctx = StoreContext::new();
+        ctx.set_current_path(Path::parse("a/b").unwrap());
+        let path = ctx.resolve_path("c/../../d/./e").unwrap();
+        assert_eq!(path.to_string(), "a/d/e");
+        assert!(ctx.resolve_path("c/in valid").is_err());
+    }
+
+    #[test]
+    fn test_resolve_parent_at_root_stays_at_root() {
+        let mut ctx = StoreContext::new();
+        assert!(ctx.resolve_path("..").unwrap().is_empty());
+        assert!(ctx.resolve_path("/..").unwrap().is_empty());
+
+        ctx.set_current_path(Path::parse("a/b").unwrap());
+        assert!(ctx.resolve_path("../../..").unwrap().is_empty());
+        assert_eq!(ctx.resolve_path("../../../c").unwrap().to_string(), "c");
+    }
+
     #[test]
     fn test_resolve_multiple_parent_path() {
         let mut ctx = StoreContext::new();
@@ -1186,7 +1509,7 @@ mod tests {
 
     #[test]
     fn test_with_factory_no_mounts() {
-        let ctx = StoreContext::with_factory(CoreReplStoreFactory);
+        let ctx = StoreContext::with_factory(CoreReplStoreFactory::new());
         // Should not have default mounts
         let result = ctx.resolve_path("/ctx/sys").unwrap();
         assert_eq!(result.to_string(), "ctx/sys");
@@ -1194,7 +1517,7 @@ mod tests {
 
     #[test]
     fn test_with_factory_and_mounts_false() {
-        let ctx = StoreContext::with_factory_and_mounts(CoreReplStoreFactory, false);
+        let ctx = StoreContext::with_factory_and_mounts(CoreReplStoreFactory::new(), false);
         // No default mounts
         let result = ctx.resolve_path("/").unwrap();
         assert!(result.is_empty());
@@ -1202,7 +1525,7 @@ mod tests {
 
     #[test]
     fn test_mount() {
-        let mut ctx = StoreContext::with_factory(CoreReplStoreFactory);
+        let mut ctx = StoreContext::with_factory(CoreReplStoreFactory::new());
         ctx.mount("mystore", MountConfig::Memory).unwrap();
         ctx.write(&path!("mystore/key"), Value::Integer(123))
             .unwrap();
@@ -1225,7 +1548,7 @@ mod tests {
     // Factory error path tests
     #[test]
     fn factory_local_not_available() {
-        let factory = CoreReplStoreFactory;
+        let factory = CoreReplStoreFactory::new();
         let result = factory.create(&MountConfig::Local {
             path: "/tmp".to_string(),
         });
@@ -1236,20 +1559,18 @@ mod tests {
     }
 
     #[test]
-    fn factory_http_not_available() {
-        let factory = CoreReplStoreFactory;
+    fn factory_creates_http_client_store() {
+        let factory = CoreReplStoreFactory::new();
         let result = factory.create(&MountConfig::Http {
             url: "https://example.com".to_string(),
+            options: Default::default(),
         });
-        match result {
-            Err(e) => assert!(e.to_string().contains("not yet available")),
-            Ok(_) => panic!("Expected error for Http config"),
-        }
+        assert!(result.is_ok());
     }
 
     #[test]
     fn factory_structfs_not_available() {
-        let factory = CoreReplStoreFactory;
+        let factory = CoreReplStoreFactory::new();
         let result = factory.create(&MountConfig::Structfs {
             url: "https://example.com".to_string(),
         });
@@ -1261,18 +1582,110 @@ mod tests {
 
     #[test]
     fn factory_creates_memory_store() {
-        let factory = CoreReplStoreFactory;
+        let factory = CoreReplStoreFactory::new();
         let result = factory.create(&MountConfig::Memory);
         assert!(result.is_ok());
     }
 
     #[test]
     fn factory_creates_registers_store() {
-        let factory = CoreReplStoreFactory;
+        let factory = CoreReplStoreFactory::new();
         let result = factory.create(&MountConfig::Registers);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn plugin_mount_types() {
+        use crate::plugins::StorePlugin;
+        use structfs_core_store::overlay_store::StoreBox;
+
+        struct ScratchPlugin;
+        impl StorePlugin for ScratchPlugin {
+            fn kind(&self) -> &str {
+                "scratch"
+            }
+            fn create(&self, _config: &Value) -> Result<StoreBox, CoreError> {
+                Ok(Box::new(InMemoryStore::new()))
+            }
+        }
+
+        let plugins = PluginRegistry::new();
+        let mut ctx = StoreContext::with_plugins(plugins.clone());
+        let config = Value::Map(btree! {
+            "type".to_string() => Value::String("scratch".to_string()),
+        });
+        assert!(ctx.write(&path!("ctx/mounts/tmp"), config.clone()).is_err());
+
+        // Registering after the context was created still takes effect
+        plugins.register(ScratchPlugin);
+        ctx.write(&path!("ctx/mounts/tmp"), config).unwrap();
+        ctx.write(&path!("tmp/key"), Value::Integer(1)).unwrap();
+        assert_eq!(
+            ctx.read(&path!("tmp/key")).unwrap(),
+            Some(Value::Integer(1))
+        );
+    }
+
+    #[test]
+    fn mount_spec_parsing() {
+        let spec: MountSpec = "data=memory".parse().unwrap();
+        assert_eq!(spec.name, "data");
+        assert_eq!(
+            spec.config,
+            Value::Map(btree! {"type".to_string() => Value::String("memory".to_string())})
+        );
+
+        let spec: MountSpec = "/api=http:https://api.example.com".parse().unwrap();
+        assert_eq!(spec.name, "api");
+        assert_eq!(
+            spec.config,
+            Value::Map(btree! {
+                "type".to_string() => Value::String("http".to_string()),
+                "url".to_string() => Value::String("https://api.example.com".to_string()),
+            })
+        );
+
+        let spec: MountSpec = r#"cache={"type": "redis", "db": 2}"#.parse().unwrap();
+        assert_eq!(
+            spec.config,
+            Value::Map(btree! {
+                "type".to_string() => Value::String("redis".to_string()),
+                "db".to_string() => Value::Integer(2),
+            })
+        );
+
+        for bad in [
+            "data",
+            "=memory",
+            "data=",
+            "api=http",
+            "data=memory:x",
+            "a-b=memory",
+        ] {
+            assert!(
+                bad.parse::<MountSpec>().is_err(),
+                "{} should not parse",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn mount_spec_mounts() {
+        let mut ctx = StoreContext::with_factory(CoreReplStoreFactory::new());
+        let spec: MountSpec = "data/nested=memory".parse().unwrap();
+        ctx.mount_spec(&spec).unwrap();
+        ctx.write(&path!("data/nested/key"), Value::Integer(1))
+            .unwrap();
+        assert_eq!(
+            ctx.read(&path!("data/nested/key")).unwrap(),
+            Some(Value::Integer(1))
+        );
+
+        let spec: MountSpec = "bad=nope".parse().unwrap();
+        assert!(ctx.mount_spec(&spec).is_err());
+    }
+
     #[test]
     fn list_registers_empty() {
         let mut ctx = StoreContext::new();
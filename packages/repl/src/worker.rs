@@ -0,0 +1,198 @@
+//! Command execution off the REPL's input loop.
+//!
+//! A [`CommandWorker`] owns the [`StoreContext`] on its own thread and runs
+//! command lines in the order they are submitted. The REPL loop stays free
+//! to redraw the prompt and handle signals while a slow store operation,
+//! such as a blocking HTTP request, is in progress.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use structfs_core_store::Path;
+
+use crate::commands::{self, CommandResult};
+use crate::store_context::StoreContext;
+
+/// Identifies a submitted command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(pub u64);
+
+/// A finished command.
+#[derive(Debug)]
+pub struct Completion {
+    /// The command's job.
+    pub job: JobId,
+    /// The command line as submitted.
+    pub line: String,
+    /// What the command returned.
+    pub result: CommandResult,
+    /// The context's current path after the command ran.
+    pub current_path: Path,
+}
+
+/// Runs REPL commands on a background thread.
+///
+/// Dropping the worker does not wait: the thread finishes any queued
+/// commands in the background and then exits.
+pub struct CommandWorker {
+    jobs: Sender<(JobId, String)>,
+    done: Receiver<Completion>,
+    thread: JoinHandle<StoreContext>,
+    next_job: u64,
+    pending: usize,
+}
+
+impl CommandWorker {
+    /// Start a worker that owns `ctx`.
+    pub fn new(ctx: StoreContext) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<(JobId, String)>();
+        let (done_tx, done) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("structfs-repl-worker".to_string())
+            .spawn(move || {
+                let mut ctx = ctx;
+                for (job, line) in job_rx {
+                    let result = commands::execute(&line, &mut ctx);
+                    let completion = Completion {
+                        job,
+                        line,
+                        result,
+                        current_path: ctx.current_path().clone(),
+                    };
+                    if done_tx.send(completion).is_err() {
+                        break;
+                    }
+                }
+                ctx
+            })
+            .expect("failed to spawn REPL worker thread");
+
+        Self {
+            jobs,
+            done,
+            thread,
+            next_job: 0,
+            pending: 0,
+        }
+    }
+
+    /// Queue a command line. Commands run in submission order.
+    pub fn submit(&mut self, line: impl Into<String>) -> JobId {
+        let job = JobId(self.next_job);
+        self.next_job += 1;
+        self.pending += 1;
+        // A closed channel means the thread panicked; `poll` reports it
+        let _ = self.jobs.send((job, line.into()));
+        job
+    }
+
+    /// Number of submitted commands that have not completed.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Take a finished command, if any, without waiting.
+    pub fn poll(&mut self) -> Option<Completion> {
+        match self.done.try_recv() {
+            Ok(completion) => Some(self.finish(completion)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => self.worker_lost(),
+        }
+    }
+
+    /// Wait up to `timeout` for a command to finish.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Option<Completion> {
+        if self.pending == 0 {
+            return None;
+        }
+        match self.done.recv_timeout(timeout) {
+            Ok(completion) => Some(self.finish(completion)),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => self.worker_lost(),
+        }
+    }
+
+    /// Wait for every queued command, then return the context.
+    ///
+    /// Completions not yet taken are discarded.
+    pub fn into_context(self) -> Option<StoreContext> {
+        drop(self.jobs);
+        self.thread.join().ok()
+    }
+
+    fn finish(&mut self, completion: Completion) -> Completion {
+        self.pending = self.pending.saturating_sub(1);
+        completion
+    }
+
+    /// Fail every pending command after the worker thread died.
+    fn worker_lost(&mut self) -> Option<Completion> {
+        if self.pending == 0 {
+            return None;
+        }
+        // Commands finish in order, so this is the oldest pending job
+        let job = JobId(self.next_job - self.pending as u64);
+        self.pending -= 1;
+        Some(Completion {
+            job,
+            line: String::new(),
+            result: CommandResult::Error("command worker stopped unexpectedly".to_string()),
+            current_path: Path::parse("").unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_context::CoreReplStoreFactory;
+    use structfs_core_store::mount_store::MountConfig;
+
+    fn worker() -> CommandWorker {
+        let mut ctx = StoreContext::with_factory(CoreReplStoreFactory::new());
+        ctx.mount("data", MountConfig::Memory).unwrap();
+        CommandWorker::new(ctx)
+    }
+
+    fn wait(worker: &mut CommandWorker) -> Completion {
+        worker.wait_timeout(Duration::from_secs(10)).unwrap()
+    }
+
+    #[test]
+    fn runs_commands_in_order() {
+        let mut worker = worker();
+        let first = worker.submit("write /data/x 1");
+        let second = worker.submit("read /data/x");
+        let third = worker.submit("cd /data");
+        assert_eq!(worker.pending(), 3);
+
+        let done = wait(&mut worker);
+        assert_eq!(done.job, first);
+        assert!(matches!(done.result, CommandResult::Ok { .. }));
+
+        let done = wait(&mut worker);
+        assert_eq!(done.job, second);
+        assert_eq!(done.line, "read /data/x");
+        match done.result {
+            CommandResult::Ok { display, .. } => assert!(display.unwrap().contains('1')),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let done = wait(&mut worker);
+        assert_eq!(done.job, third);
+        assert_eq!(done.current_path.to_string(), "data");
+        assert_eq!(worker.pending(), 0);
+        assert!(worker.poll().is_none());
+    }
+
+    #[test]
+    fn into_context_returns_state() {
+        let mut worker = worker();
+        worker.submit("write /data/x 1");
+        let mut ctx = worker.into_context().unwrap();
+        let x = Path::parse("data/x").unwrap();
+        assert!(ctx.read(&x).unwrap().is_some());
+    }
+}
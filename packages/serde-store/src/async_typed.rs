@@ -15,7 +15,7 @@ use serde::Serialize;
 
 use structfs_core_store::{AsyncReader, AsyncWriter, Codec, Error, Path, Record};
 
-use crate::convert::{from_value, to_value};
+use crate::convert::{from_value_at, to_value};
 
 /// Async extension trait for typed reads.
 ///
@@ -58,7 +58,7 @@ pub trait AsyncTypedReader: AsyncReader {
         };
 
         let value = record.into_value(codec)?;
-        let typed = from_value(value)?;
+        let typed = from_value_at(value, from)?;
         Ok(Some(typed))
     }
 
@@ -1,9 +1,13 @@
-//! JSON codec implementation.
+//! JSON, CBOR, MessagePack, YAML and TOML codec implementations.
 
 use bytes::Bytes;
 use structfs_core_store::{Codec, Error, Format, Value};
 
-use crate::convert::{json_to_value, value_to_json};
+use crate::convert::{
+    cbor_to_value, json_to_value, msgpack_to_value, toml_to_value, value_to_cbor, value_to_json,
+    value_to_msgpack, value_to_toml, value_to_yaml, yaml_to_value,
+};
+use crate::sniff::{CborDetector, Detector, JsonDetector, MsgPackDetector, YamlDetector};
 
 /// A codec that handles JSON encoding/decoding.
 ///
@@ -57,17 +61,240 @@ impl Codec for JsonCodec {
     }
 }
 
+/// A codec that handles CBOR (RFC 8949) encoding/decoding.
+///
+/// CBOR keeps [`Value::Bytes`] as bytes, where JSON has to base64 them, so
+/// it suits binary payloads from WASM guests and wire protocols. Decoding
+/// drops tags, keeping the value they wrap, and turns integer map keys
+/// into strings.
+///
+/// # Example
+///
+/// ```rust
+/// use structfs_serde_store::CborCodec;
+/// use structfs_core_store::{Codec, Format, Value};
+///
+/// let codec = CborCodec;
+/// let value = Value::Bytes(vec![0xde, 0xad]);
+///
+/// let bytes = codec.encode(&value, &Format::CBOR).unwrap();
+/// assert_eq!(codec.decode(&bytes, &Format::CBOR).unwrap(), value);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+impl Codec for CborCodec {
+    fn decode(&self, bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let cbor: ciborium::Value = ciborium::from_reader(bytes.as_ref())
+            .map_err(|e| Error::decode(format.clone(), e.to_string()))?;
+
+        cbor_to_value(cbor)
+    }
+
+    fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&value_to_cbor(value.clone()), &mut bytes)
+            .map_err(|e| Error::encode(format.clone(), e.to_string()))?;
+
+        Ok(Bytes::from(bytes))
+    }
+
+    fn supports(&self, format: &Format) -> bool {
+        format == &Format::CBOR
+    }
+}
+
+/// A codec that handles MessagePack encoding/decoding.
+///
+/// Like CBOR, MessagePack is compact and keeps [`Value::Bytes`] as bytes.
+/// Integer map keys decode as strings; extension types, such as
+/// MessagePack's timestamps, are not supported.
+///
+/// # Example
+///
+/// ```rust
+/// use structfs_serde_store::MsgPackCodec;
+/// use structfs_core_store::{Codec, Format, Value};
+///
+/// let codec = MsgPackCodec;
+/// let value = Value::Array(vec![Value::Integer(1), Value::Bytes(vec![2])]);
+///
+/// let bytes = codec.encode(&value, &Format::MSGPACK).unwrap();
+/// assert_eq!(codec.decode(&bytes, &Format::MSGPACK).unwrap(), value);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackCodec;
+
+impl Codec for MsgPackCodec {
+    fn decode(&self, bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let mut reader = bytes.as_ref();
+        let msgpack = rmpv::decode::read_value(&mut reader)
+            .map_err(|e| Error::decode(format.clone(), e.to_string()))?;
+        if !reader.is_empty() {
+            return Err(Error::decode(
+                format.clone(),
+                format!("{} trailing bytes", reader.len()),
+            ));
+        }
+
+        msgpack_to_value(msgpack)
+    }
+
+    fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &value_to_msgpack(value.clone()))
+            .map_err(|e| Error::encode(format.clone(), e.to_string()))?;
+
+        Ok(Bytes::from(bytes))
+    }
+
+    fn supports(&self, format: &Format) -> bool {
+        format == &Format::MSGPACK
+    }
+}
+
+/// A codec that handles YAML encoding/decoding.
+///
+/// Meant for configuration files: anchors and merge keys are resolved,
+/// tags are dropped, and scalar map keys such as `8080:` decode as
+/// strings. Only single-document YAML is supported. Encoding base64s
+/// [`Value::Bytes`], as [`JsonCodec`] does.
+///
+/// # Example
+///
+/// ```rust
+/// use structfs_serde_store::YamlCodec;
+/// use structfs_core_store::{Codec, Format, Value};
+/// use bytes::Bytes;
+///
+/// let yaml = Bytes::from_static(b"server:\n  port: 8080\n  hosts: [a, b]\n");
+/// let value = YamlCodec.decode(&yaml, &Format::YAML).unwrap();
+///
+/// let port = value.get(&structfs_core_store::path!("server/port"));
+/// assert_eq!(port, Some(&Value::Integer(8080)));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YamlCodec;
+
+impl Codec for YamlCodec {
+    fn decode(&self, bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let yaml: serde_yaml::Value = serde_yaml::from_slice(bytes)
+            .map_err(|e| Error::decode(format.clone(), e.to_string()))?;
+
+        yaml_to_value(yaml)
+    }
+
+    fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let text = serde_yaml::to_string(&value_to_yaml(value.clone()))
+            .map_err(|e| Error::encode(format.clone(), e.to_string()))?;
+
+        Ok(Bytes::from(text))
+    }
+
+    fn supports(&self, format: &Format) -> bool {
+        format == &Format::YAML
+    }
+}
+
+/// A codec that handles TOML encoding/decoding.
+///
+/// A TOML document is a table, so only maps can be encoded. TOML has no
+/// null: null map entries are left out when encoding, and nulls anywhere
+/// else fail. Dates and times decode as their RFC 3339 strings, and
+/// [`Value::Bytes`] encode as base64, as with [`JsonCodec`].
+///
+/// # Example
+///
+/// ```rust
+/// use structfs_serde_store::TomlCodec;
+/// use structfs_core_store::{path, Codec, Format, Value};
+/// use bytes::Bytes;
+///
+/// let toml = Bytes::from_static(b"[server]\nport = 8080\nhosts = [\"a\", \"b\"]\n");
+/// let value = TomlCodec.decode(&toml, &Format::TOML).unwrap();
+///
+/// assert_eq!(value.get(&path!("server/port")), Some(&Value::Integer(8080)));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TomlCodec;
+
+impl Codec for TomlCodec {
+    fn decode(&self, bytes: &Bytes, format: &Format) -> Result<Value, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let table: toml::Table =
+            toml::from_slice(bytes).map_err(|e| Error::decode(format.clone(), e.to_string()))?;
+
+        Ok(toml_to_value(toml::Value::Table(table)))
+    }
+
+    fn encode(&self, value: &Value, format: &Format) -> Result<Bytes, Error> {
+        if !self.supports(format) {
+            return Err(Error::UnsupportedFormat(format.clone()));
+        }
+
+        let toml::Value::Table(table) = value_to_toml(value.clone())? else {
+            return Err(Error::encode(
+                format.clone(),
+                "a TOML document must be a map",
+            ));
+        };
+        let text =
+            toml::to_string(&table).map_err(|e| Error::encode(format.clone(), e.to_string()))?;
+
+        Ok(Bytes::from(text))
+    }
+
+    fn supports(&self, format: &Format) -> bool {
+        format == &Format::TOML
+    }
+}
+
 /// A codec that combines multiple codecs.
 ///
 /// Routes encode/decode to the appropriate codec based on format.
+///
+/// Bytes of unknown format, [`Format::OCTET_STREAM`], are decoded by
+/// asking each [`Detector`] in turn what they look like and decoding them
+/// as the first guess that a codec supports and that decodes cleanly.
 pub struct MultiCodec {
     codecs: Vec<Box<dyn Codec>>,
+    detectors: Vec<Box<dyn Detector>>,
 }
 
 impl MultiCodec {
     /// Create an empty multi-codec.
     pub fn new() -> Self {
-        Self { codecs: Vec::new() }
+        Self {
+            codecs: Vec::new(),
+            detectors: Vec::new(),
+        }
     }
 
     /// Add a codec.
@@ -75,17 +302,73 @@ impl MultiCodec {
         self.codecs.push(Box::new(codec));
     }
 
+    /// Add a detector, asked after the ones already added.
+    pub fn add_detector(&mut self, detector: impl Detector + 'static) {
+        self.detectors.push(Box::new(detector));
+    }
+
+    /// The format the detectors take `bytes` to be, among those a codec
+    /// supports. Unlike decoding, this does not check the guess.
+    pub fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        self.detectors
+            .iter()
+            .filter_map(|detector| detector.detect(bytes))
+            .find(|format| self.codec_for(format).is_some())
+    }
+
+    fn codec_for(&self, format: &Format) -> Option<&dyn Codec> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.supports(format))
+            .map(|codec| codec.as_ref())
+    }
+
+    /// Decode bytes of unknown format with the first detected format that
+    /// decodes, or fail with the last decode error.
+    fn decode_detected(&self, bytes: &Bytes) -> Result<Value, Error> {
+        let mut failure = None;
+        for format in self.detectors.iter().filter_map(|d| d.detect(bytes)) {
+            let Some(codec) = self.codec_for(&format) else {
+                continue;
+            };
+            match codec.decode(bytes, &format) {
+                Ok(value) => return Ok(value),
+                Err(e) => failure = Some(e),
+            }
+        }
+        Err(failure.unwrap_or(Error::UnsupportedFormat(Format::OCTET_STREAM)))
+    }
+
     /// Create a multi-codec with the JSON codec included.
     pub fn with_json() -> Self {
         let mut mc = Self::new();
         mc.add(JsonCodec);
         mc
     }
+
+    /// Create a multi-codec with every codec this crate provides: JSON,
+    /// CBOR, MessagePack, YAML and TOML, and detectors for all of them but
+    /// TOML.
+    ///
+    /// MessagePack is detected before CBOR, since the two share the leading
+    /// bytes of small maps and arrays.
+    pub fn standard() -> Self {
+        let mut mc = Self::with_json();
+        mc.add(CborCodec);
+        mc.add(MsgPackCodec);
+        mc.add(YamlCodec);
+        mc.add(TomlCodec);
+        mc.add_detector(JsonDetector);
+        mc.add_detector(MsgPackDetector);
+        mc.add_detector(CborDetector);
+        mc.add_detector(YamlDetector);
+        mc
+    }
 }
 
 impl Default for MultiCodec {
     fn default() -> Self {
-        Self::with_json()
+        Self::standard()
     }
 }
 
@@ -96,6 +379,9 @@ impl Codec for MultiCodec {
                 return codec.decode(bytes, format);
             }
         }
+        if format == &Format::OCTET_STREAM {
+            return self.decode_detected(bytes);
+        }
         Err(Error::UnsupportedFormat(format.clone()))
     }
 
@@ -110,6 +396,7 @@ impl Codec for MultiCodec {
 
     fn supports(&self, format: &Format) -> bool {
         self.codecs.iter().any(|c| c.supports(format))
+            || (format == &Format::OCTET_STREAM && !self.detectors.is_empty())
     }
 }
 
@@ -182,6 +469,342 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn cbor_codec_roundtrip() {
+        let codec = CborCodec;
+        let original = Value::Map(
+            [
+                ("name".to_string(), Value::from("Alice")),
+                ("age".to_string(), Value::Integer(-30)),
+                ("ratio".to_string(), Value::Float(0.25)),
+                ("avatar".to_string(), Value::Bytes(vec![0, 1, 255])),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::Null, Value::Bool(true)]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let bytes = codec.encode(&original, &Format::CBOR).unwrap();
+        assert_eq!(codec.decode(&bytes, &Format::CBOR).unwrap(), original);
+        assert!(matches!(
+            codec.decode(&bytes, &Format::JSON),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn cbor_codec_decodes_foreign_shapes() {
+        let codec = CborCodec;
+        let decode = |cbor: ciborium::Value| {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(&cbor, &mut bytes).unwrap();
+            codec.decode(&Bytes::from(bytes), &Format::CBOR)
+        };
+
+        // Integer keys become strings, tags are dropped
+        let keyed = ciborium::Value::Map(vec![(
+            ciborium::Value::Integer(1.into()),
+            ciborium::Value::Tag(1, Box::new(ciborium::Value::Integer(1700000000.into()))),
+        )]);
+        assert_eq!(
+            decode(keyed).unwrap(),
+            Value::Map([("1".to_string(), Value::Integer(1700000000))].into())
+        );
+
+        let huge = ciborium::Value::Integer(u64::MAX.into());
+        assert!(matches!(
+            decode(huge),
+            Err(Error::Codec {
+                operation: structfs_core_store::CodecOperation::Decode,
+                ..
+            })
+        ));
+        let bool_key =
+            ciborium::Value::Map(vec![(ciborium::Value::Bool(true), ciborium::Value::Null)]);
+        assert!(decode(bool_key).is_err());
+        assert!(codec
+            .decode(&Bytes::from_static(b"\xff\xff"), &Format::CBOR)
+            .is_err());
+    }
+
+    #[test]
+    fn msgpack_codec_roundtrip() {
+        let codec = MsgPackCodec;
+        let original = Value::Map(
+            [
+                ("name".to_string(), Value::from("Alice")),
+                ("age".to_string(), Value::Integer(-30)),
+                ("big".to_string(), Value::Integer(i64::MAX)),
+                ("ratio".to_string(), Value::Float(0.25)),
+                ("avatar".to_string(), Value::Bytes(vec![0, 1, 255])),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::Null, Value::Bool(true)]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let bytes = codec.encode(&original, &Format::MSGPACK).unwrap();
+        assert_eq!(codec.decode(&bytes, &Format::MSGPACK).unwrap(), original);
+        assert!(matches!(
+            codec.decode(&bytes, &Format::CBOR),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn msgpack_codec_decodes_foreign_shapes() {
+        let codec = MsgPackCodec;
+        let decode = |msgpack: rmpv::Value| {
+            let mut bytes = Vec::new();
+            rmpv::encode::write_value(&mut bytes, &msgpack).unwrap();
+            codec.decode(&Bytes::from(bytes), &Format::MSGPACK)
+        };
+
+        // Integer keys become strings, f32 widens
+        let keyed = rmpv::Value::Map(vec![(rmpv::Value::from(7), rmpv::Value::F32(1.5))]);
+        assert_eq!(
+            decode(keyed).unwrap(),
+            Value::Map([("7".to_string(), Value::Float(1.5))].into())
+        );
+
+        assert!(matches!(
+            decode(rmpv::Value::from(u64::MAX)),
+            Err(Error::Codec {
+                operation: structfs_core_store::CodecOperation::Decode,
+                ..
+            })
+        ));
+        assert!(decode(rmpv::Value::Ext(-1, vec![0; 4])).is_err());
+        let bool_key = rmpv::Value::Map(vec![(rmpv::Value::Boolean(true), rmpv::Value::Nil)]);
+        assert!(decode(bool_key).is_err());
+        // Trailing bytes, and a truncated array
+        assert!(codec
+            .decode(&Bytes::from_static(b"\x01\x02"), &Format::MSGPACK)
+            .is_err());
+        assert!(codec
+            .decode(&Bytes::from_static(b"\x92\x01"), &Format::MSGPACK)
+            .is_err());
+    }
+
+    #[test]
+    fn yaml_codec_roundtrip() {
+        let codec = YamlCodec;
+        let original = Value::Map(
+            [
+                ("name".to_string(), Value::from("Alice")),
+                ("age".to_string(), Value::Integer(-30)),
+                ("ratio".to_string(), Value::Float(0.25)),
+                ("note".to_string(), Value::from("yes")),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::Null, Value::Bool(true)]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let bytes = codec.encode(&original, &Format::YAML).unwrap();
+        assert_eq!(codec.decode(&bytes, &Format::YAML).unwrap(), original);
+        assert!(matches!(
+            codec.decode(&bytes, &Format::JSON),
+            Err(Error::UnsupportedFormat(_))
+        ));
+
+        // Bytes come back as their base64, as with JSON
+        let bytes = codec
+            .encode(&Value::Bytes(vec![1, 2, 3]), &Format::YAML)
+            .unwrap();
+        assert_eq!(
+            codec.decode(&bytes, &Format::YAML).unwrap(),
+            Value::from("AQID")
+        );
+    }
+
+    #[test]
+    fn yaml_codec_decodes_config_files() {
+        let codec = YamlCodec;
+        let decode =
+            |text: &'static str| codec.decode(&Bytes::from_static(text.as_bytes()), &Format::YAML);
+
+        let config = decode(
+            "defaults: &defaults\n  timeout: 30\n  retries: 3\n\
+             production:\n  <<: *defaults\n  retries: 5\n\
+             ports:\n  8080: http\n  true: on\n\
+             start: !!str 2024-01-01\n",
+        )
+        .unwrap();
+        let at = |p: &str| {
+            config
+                .get(&structfs_core_store::Path::parse(p).unwrap())
+                .cloned()
+        };
+        assert_eq!(at("production/timeout"), Some(Value::Integer(30)));
+        assert_eq!(at("production/retries"), Some(Value::Integer(5)));
+        assert_eq!(at("ports/8080"), Some(Value::from("http")));
+        assert_eq!(at("ports/true"), Some(Value::from("on")));
+        assert_eq!(at("start"), Some(Value::from("2024-01-01")));
+
+        assert!(decode("? [a, b]\n: complex key\n").is_err());
+        assert!(decode("a: [unclosed\n").is_err());
+        assert!(decode("a: 1\n---\nb: 2\n").is_err());
+    }
+
+    #[test]
+    fn toml_codec_roundtrip() {
+        let codec = TomlCodec;
+        let original = Value::Map(
+            [
+                ("name".to_string(), Value::from("api")),
+                ("port".to_string(), Value::Integer(8080)),
+                ("ratio".to_string(), Value::Float(0.25)),
+                (
+                    "tls".to_string(),
+                    Value::Map([("enabled".to_string(), Value::Bool(true))].into()),
+                ),
+                (
+                    "hosts".to_string(),
+                    Value::Array(vec![Value::from("a"), Value::from("b")]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let bytes = codec.encode(&original, &Format::TOML).unwrap();
+        assert_eq!(codec.decode(&bytes, &Format::TOML).unwrap(), original);
+        assert!(matches!(
+            codec.decode(&bytes, &Format::JSON),
+            Err(Error::UnsupportedFormat(_))
+        ));
+
+        // Null entries are left out; other nulls and non-maps can't be encoded
+        let with_null = Value::Map([("gone".to_string(), Value::Null)].into());
+        let bytes = codec.encode(&with_null, &Format::TOML).unwrap();
+        assert_eq!(codec.decode(&bytes, &Format::TOML).unwrap(), Value::map());
+        assert!(codec
+            .encode(&Value::Array(vec![Value::Null]), &Format::TOML)
+            .is_err());
+        assert!(codec.encode(&Value::Integer(1), &Format::TOML).is_err());
+    }
+
+    #[test]
+    fn toml_codec_decodes_config_files() {
+        let codec = TomlCodec;
+        let config = codec
+            .decode(
+                &Bytes::from_static(
+                    b"title = \"deploy\"\n\
+                      released = 2024-05-01T12:00:00Z\n\
+                      [[targets]]\nname = \"eu\"\n\
+                      [[targets]]\nname = \"us\"\n",
+                ),
+                &Format::TOML,
+            )
+            .unwrap();
+        let at = |p: &str| {
+            config
+                .get(&structfs_core_store::Path::parse(p).unwrap())
+                .cloned()
+        };
+        assert_eq!(at("released"), Some(Value::from("2024-05-01T12:00:00Z")));
+        assert_eq!(at("targets/1/name"), Some(Value::from("us")));
+
+        assert!(codec
+            .decode(&Bytes::from_static(b"a = "), &Format::TOML)
+            .is_err());
+    }
+
+    #[test]
+    fn multi_codec_standard_decodes_raw_binary_formats() {
+        use structfs_core_store::Record;
+
+        let codec = MultiCodec::default();
+        assert!(codec.supports(&Format::JSON));
+        assert!(codec.supports(&Format::CBOR));
+        assert!(codec.supports(&Format::MSGPACK));
+        assert!(codec.supports(&Format::YAML));
+        assert!(codec.supports(&Format::TOML));
+
+        let value = Value::Array(vec![Value::Integer(1), Value::Bytes(vec![2])]);
+        let record = Record::Raw {
+            bytes: CborCodec.encode(&value, &Format::CBOR).unwrap(),
+            format: Format::CBOR,
+        };
+        assert_eq!(record.into_value(&codec).unwrap(), value);
+
+        let record = Record::Raw {
+            bytes: MsgPackCodec.encode(&value, &Format::MSGPACK).unwrap(),
+            format: Format::MSGPACK,
+        };
+        assert_eq!(record.into_value(&codec).unwrap(), value);
+    }
+
+    #[test]
+    fn multi_codec_detects_unknown_formats() {
+        use structfs_core_store::Record;
+
+        let codec = MultiCodec::standard();
+        assert!(codec.supports(&Format::OCTET_STREAM));
+        let unknown = |bytes: Bytes| {
+            Record::raw(bytes, Format::OCTET_STREAM)
+                .into_value(&codec)
+                .unwrap()
+        };
+
+        let map = Value::Map([("a".to_string(), Value::Integer(1))].into());
+        let array = Value::Array(vec![Value::Integer(1), Value::Bytes(vec![2])]);
+        assert_eq!(unknown(Bytes::from_static(b" {\"a\": 1}")), map);
+        assert_eq!(unknown(Bytes::from_static(b"---\na: 1\n")), map);
+        for value in [&map, &array] {
+            let cbor = CborCodec.encode(value, &Format::CBOR).unwrap();
+            let msgpack = MsgPackCodec.encode(value, &Format::MSGPACK).unwrap();
+            assert_eq!(&unknown(cbor), value);
+            assert_eq!(&unknown(msgpack), value);
+        }
+
+        let cbor = CborCodec.encode(&array, &Format::CBOR).unwrap();
+        assert_eq!(codec.detect(&cbor), Some(Format::MSGPACK));
+        assert_eq!(codec.detect(b"plain text"), None);
+
+        let err = codec
+            .decode(&Bytes::from_static(b"plain text"), &Format::OCTET_STREAM)
+            .unwrap_err();
+        assert!(matches!(err, Error::UnsupportedFormat(_)));
+        assert!(codec
+            .decode(&Bytes::from_static(b"{not json"), &Format::OCTET_STREAM)
+            .is_err());
+        assert!(!MultiCodec::with_json().supports(&Format::OCTET_STREAM));
+    }
+
+    #[test]
+    fn multi_codec_custom_detector() {
+        let mut codec = MultiCodec::standard();
+        codec.add_detector(|bytes: &[u8]| bytes.starts_with(b"[").then_some(Format::TOML));
+
+        // JSON is guessed first and fails, so the TOML guess is tried
+        let value = codec
+            .decode(
+                &Bytes::from_static(b"[server]\nport = 8080\n"),
+                &Format::OCTET_STREAM,
+            )
+            .unwrap();
+        let server = Value::Map([("port".to_string(), Value::Integer(8080))].into());
+        assert_eq!(value, Value::Map([("server".to_string(), server)].into()));
+        assert_eq!(
+            codec
+                .decode(&Bytes::from_static(b"[1, 2]"), &Format::OCTET_STREAM)
+                .unwrap(),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
+    }
+
     #[test]
     fn multi_codec_decode_unsupported() {
         let codec = MultiCodec::new(); // Empty, no codecs
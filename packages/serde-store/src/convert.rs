@@ -2,14 +2,56 @@
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use structfs_core_store::{Error, Value};
+use structfs_core_store::{Error, Path, Value};
 
 /// Convert a Value to a Rust type via serde.
+///
+/// If the value does not fit `T`, the error names the offending field as a
+/// JSON pointer along with the type found and the type expected, e.g.
+/// `at /users/0/age: invalid type: string "x", expected u32`.
 pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    deserialize(value, None)
+}
+
+/// [`from_value`] for a value read from `at`, which errors also name.
+pub(crate) fn from_value_at<T: DeserializeOwned>(value: Value, at: &Path) -> Result<T, Error> {
+    deserialize(value, Some(at))
+}
+
+fn deserialize<T: DeserializeOwned>(value: Value, at: Option<&Path>) -> Result<T, Error> {
     // Convert Value to serde_json::Value first, then deserialize
     let json = value_to_json(value);
-    serde_json::from_value(json)
-        .map_err(|e| Error::decode(structfs_core_store::Format::VALUE, e.to_string()))
+    serde_path_to_error::deserialize(json).map_err(|e| {
+        let pointer = json_pointer(e.path());
+        let location = match (at, pointer.is_empty()) {
+            (Some(at), true) => format!("{}: ", at),
+            (Some(at), false) => format!("{} at {}: ", at, pointer),
+            (None, true) => String::new(),
+            (None, false) => format!("at {}: ", pointer),
+        };
+        Error::decode(
+            structfs_core_store::Format::VALUE,
+            format!("{}{}", location, e.inner()),
+        )
+    })
+}
+
+/// `path` as a JSON pointer (RFC 6901), or empty for the root.
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    let mut pointer = String::new();
+    for segment in path.iter() {
+        pointer.push('/');
+        match segment {
+            Segment::Seq { index } => pointer.push_str(&index.to_string()),
+            Segment::Map { key } | Segment::Enum { variant: key } => {
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"))
+            }
+            Segment::Unknown => pointer.push('?'),
+        }
+    }
+    pointer
 }
 
 /// Convert a Rust type to a Value via serde.
@@ -70,6 +112,253 @@ pub fn json_to_value(json: serde_json::Value) -> Value {
     }
 }
 
+/// Convert our Value to a CBOR value.
+pub(crate) fn value_to_cbor(value: Value) -> ciborium::Value {
+    match value {
+        Value::Null => ciborium::Value::Null,
+        Value::Bool(b) => ciborium::Value::Bool(b),
+        Value::Integer(i) => ciborium::Value::Integer(i.into()),
+        Value::Float(f) => ciborium::Value::Float(f),
+        Value::String(s) => ciborium::Value::Text(s),
+        Value::Bytes(b) => ciborium::Value::Bytes(b),
+        Value::Array(arr) => ciborium::Value::Array(arr.into_iter().map(value_to_cbor).collect()),
+        Value::Map(map) => ciborium::Value::Map(
+            map.into_iter()
+                .map(|(k, v)| (ciborium::Value::Text(k), value_to_cbor(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert a CBOR value to our Value.
+///
+/// Tags are dropped in favour of the value they wrap, and integer map keys,
+/// common in compact wire formats, become their decimal strings. Integers
+/// outside the `i64` range and other map keys are errors.
+pub(crate) fn cbor_to_value(cbor: ciborium::Value) -> Result<Value, Error> {
+    let invalid = |message: String| Error::decode(structfs_core_store::Format::CBOR, message);
+    Ok(match cbor {
+        ciborium::Value::Null => Value::Null,
+        ciborium::Value::Bool(b) => Value::Bool(b),
+        ciborium::Value::Integer(i) => Value::Integer(
+            i64::try_from(i).map_err(|_| invalid(format!("integer {:?} out of range", i)))?,
+        ),
+        ciborium::Value::Float(f) => Value::Float(f),
+        ciborium::Value::Text(s) => Value::String(s),
+        ciborium::Value::Bytes(b) => Value::Bytes(b),
+        ciborium::Value::Tag(_, inner) => cbor_to_value(*inner)?,
+        ciborium::Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .map(cbor_to_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        ciborium::Value::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        ciborium::Value::Text(k) => k,
+                        ciborium::Value::Integer(i) => i128::from(i).to_string(),
+                        other => {
+                            return Err(invalid(format!("map key {:?} is not a string", other)))
+                        }
+                    };
+                    Ok((key, cbor_to_value(v)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        other => return Err(invalid(format!("unsupported CBOR value {:?}", other))),
+    })
+}
+
+/// Convert our Value to a MessagePack value.
+pub(crate) fn value_to_msgpack(value: Value) -> rmpv::Value {
+    match value {
+        Value::Null => rmpv::Value::Nil,
+        Value::Bool(b) => rmpv::Value::Boolean(b),
+        Value::Integer(i) => rmpv::Value::from(i),
+        Value::Float(f) => rmpv::Value::F64(f),
+        Value::String(s) => rmpv::Value::from(s),
+        Value::Bytes(b) => rmpv::Value::Binary(b),
+        Value::Array(arr) => rmpv::Value::Array(arr.into_iter().map(value_to_msgpack).collect()),
+        Value::Map(map) => rmpv::Value::Map(
+            map.into_iter()
+                .map(|(k, v)| (rmpv::Value::from(k), value_to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert a MessagePack value to our Value.
+///
+/// As with CBOR, integer map keys become their decimal strings. Integers
+/// above `i64::MAX`, strings that are not UTF-8, other map keys and
+/// extension types are errors.
+pub(crate) fn msgpack_to_value(msgpack: rmpv::Value) -> Result<Value, Error> {
+    let invalid = |message: String| Error::decode(structfs_core_store::Format::MSGPACK, message);
+    Ok(match msgpack {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(i) => Value::Integer(
+            i.as_i64()
+                .ok_or_else(|| invalid(format!("integer {} out of range", i)))?,
+        ),
+        rmpv::Value::F32(f) => Value::Float(f.into()),
+        rmpv::Value::F64(f) => Value::Float(f),
+        rmpv::Value::String(s) => Value::String(
+            s.into_str()
+                .ok_or_else(|| invalid("string is not UTF-8".to_string()))?,
+        ),
+        rmpv::Value::Binary(b) => Value::Bytes(b),
+        rmpv::Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .map(msgpack_to_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        rmpv::Value::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        rmpv::Value::String(k) => k
+                            .into_str()
+                            .ok_or_else(|| invalid("map key is not UTF-8".to_string()))?,
+                        rmpv::Value::Integer(i) => i.to_string(),
+                        other => return Err(invalid(format!("map key {} is not a string", other))),
+                    };
+                    Ok((key, msgpack_to_value(v)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        rmpv::Value::Ext(kind, _) => {
+            return Err(invalid(format!("unsupported extension type {}", kind)))
+        }
+    })
+}
+
+/// Convert our Value to a YAML value.
+///
+/// YAML has no bytes, so bytes are base64 encoded as they are for JSON.
+pub(crate) fn value_to_yaml(value: Value) -> serde_yaml::Value {
+    match value {
+        Value::Null => serde_yaml::Value::Null,
+        Value::Bool(b) => serde_yaml::Value::Bool(b),
+        Value::Integer(i) => serde_yaml::Value::Number(i.into()),
+        Value::Float(f) => serde_yaml::Value::Number(f.into()),
+        Value::String(s) => serde_yaml::Value::String(s),
+        Value::Bytes(b) => {
+            use base64::Engine;
+            serde_yaml::Value::String(base64::engine::general_purpose::STANDARD.encode(&b))
+        }
+        Value::Array(arr) => {
+            serde_yaml::Value::Sequence(arr.into_iter().map(value_to_yaml).collect())
+        }
+        Value::Map(map) => serde_yaml::Value::Mapping(
+            map.into_iter()
+                .map(|(k, v)| (serde_yaml::Value::String(k), value_to_yaml(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert a YAML value to our Value.
+///
+/// Merge keys (`<<: *defaults`) are applied and tags are dropped in favour
+/// of the value they wrap. Scalar map keys such as `8080:` or `true:`
+/// become their string form; integers outside the `i64` range become
+/// floats, as they do for JSON. Sequences and maps as keys are errors.
+pub(crate) fn yaml_to_value(mut yaml: serde_yaml::Value) -> Result<Value, Error> {
+    yaml.apply_merge()
+        .map_err(|e| Error::decode(structfs_core_store::Format::YAML, e.to_string()))?;
+    yaml_to_value_merged(yaml)
+}
+
+fn yaml_to_value_merged(yaml: serde_yaml::Value) -> Result<Value, Error> {
+    let invalid = |message: String| Error::decode(structfs_core_store::Format::YAML, message);
+    Ok(match yaml {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(seq) => Value::Array(
+            seq.into_iter()
+                .map(yaml_to_value_merged)
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_yaml::Value::Mapping(mapping) => Value::Map(
+            mapping
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        serde_yaml::Value::String(k) => k,
+                        serde_yaml::Value::Number(n) => n.to_string(),
+                        serde_yaml::Value::Bool(b) => b.to_string(),
+                        serde_yaml::Value::Null => "null".to_string(),
+                        other => {
+                            return Err(invalid(format!("map key {:?} is not a scalar", other)))
+                        }
+                    };
+                    Ok((key, yaml_to_value_merged(v)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_value_merged(tagged.value)?,
+    })
+}
+
+/// Convert our Value to a TOML value.
+///
+/// TOML has no null: null map entries are left out, and a null anywhere
+/// else is an error. Bytes are base64 encoded as they are for JSON.
+pub(crate) fn value_to_toml(value: Value) -> Result<toml::Value, Error> {
+    let invalid = |message: &str| Error::encode(structfs_core_store::Format::TOML, message);
+    Ok(match value {
+        Value::Null => return Err(invalid("TOML has no null")),
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Integer(i) => toml::Value::Integer(i),
+        Value::Float(f) => toml::Value::Float(f),
+        Value::String(s) => toml::Value::String(s),
+        Value::Bytes(b) => {
+            use base64::Engine;
+            toml::Value::String(base64::engine::general_purpose::STANDARD.encode(&b))
+        }
+        Value::Array(arr) => toml::Value::Array(
+            arr.into_iter()
+                .map(value_to_toml)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Map(map) => toml::Value::Table(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok((k, value_to_toml(v)?)))
+                .collect::<Result<_, Error>>()?,
+        ),
+    })
+}
+
+/// Convert a TOML value to our Value.
+///
+/// Dates and times become their RFC 3339 strings.
+pub(crate) fn toml_to_value(toml: toml::Value) -> Value {
+    match toml {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(arr) => Value::Array(arr.into_iter().map(toml_to_value).collect()),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_value(v)))
+                .collect(),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,6 +385,41 @@ mod tests {
         assert_eq!(original, recovered);
     }
 
+    #[test]
+    fn from_value_errors_name_the_field() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Team {
+            members: Vec<TestStruct>,
+        }
+
+        let value = json_to_value(serde_json::json!({
+            "members": [
+                {"name": "Alice", "age": 30, "active": true},
+                {"name": "Bob", "age": "forty", "active": true}
+            ]
+        }));
+        let err = from_value::<Team>(value.clone()).unwrap_err().to_string();
+        assert!(
+            err.contains("at /members/1/age: invalid type: string \"forty\", expected u32"),
+            "{}",
+            err
+        );
+
+        let at = Path::parse("teams/blue").unwrap();
+        let err = from_value_at::<Team>(value, &at).unwrap_err().to_string();
+        assert!(err.contains("teams/blue at /members/1/age: "), "{}", err);
+
+        let err = from_value::<u32>(Value::Bool(true))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.ends_with(": invalid type: boolean `true`, expected u32"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn json_to_value_numbers() {
         let json = serde_json::json!({
@@ -3,8 +3,13 @@
 //! This layer provides typed access to StructFS stores via serde. It adds:
 //! - `TypedReader`: Read directly into Rust types
 //! - `TypedWriter`: Write Rust types directly
-//! - `JsonCodec`: A codec for JSON format
+//! - `JsonCodec`, `CborCodec`, `MsgPackCodec`, `YamlCodec` and `TomlCodec`:
+//!   Codecs for JSON, CBOR, MessagePack, YAML and TOML formats
+//! - `MultiCodec`: Routes formats to codecs, detecting the format of
+//!   `application/octet-stream` records from their leading bytes
 //! - Value <-> serde conversions
+//! - `ScalarValue`: Direct conversions for timestamps, UUIDs and decimals
+//!   (features `chrono`, `uuid` and `decimal`)
 //!
 //! # Example
 //!
@@ -39,10 +44,14 @@ pub use bytes::Bytes;
 
 mod codec;
 mod convert;
+mod scalar;
+mod sniff;
 mod typed;
 
-pub use codec::{JsonCodec, MultiCodec};
+pub use codec::{CborCodec, JsonCodec, MsgPackCodec, MultiCodec, TomlCodec, YamlCodec};
 pub use convert::{from_value, json_to_value, to_value, value_to_json};
+pub use scalar::ScalarValue;
+pub use sniff::{CborDetector, Detector, JsonDetector, MsgPackDetector, YamlDetector};
 pub use typed::{TypedReader, TypedWriter};
 
 // Re-export core types for convenience
@@ -0,0 +1,157 @@
+//! Conversions for common scalar types with no serde mapping of their own.
+//!
+//! [`to_value`](crate::to_value) goes through serde, where timestamps,
+//! UUIDs and decimals are all strings. [`ScalarValue`] converts them
+//! directly instead, picking the closest [`Value`] variant and accepting the
+//! other shapes these values commonly arrive in:
+//!
+//! | Type | Written as | Also read from |
+//! |------|------------|----------------|
+//! | `chrono::DateTime<Utc>` / `<FixedOffset>` | RFC 3339 string | Integer milliseconds since the Unix epoch |
+//! | `uuid::Uuid` | 16 bytes | Hyphenated or simple string |
+//! | `rust_decimal::Decimal` | Decimal string, exact | Integer, float |
+//!
+//! Each type is behind a feature of the same name (`chrono`, `uuid`,
+//! `decimal`).
+
+#[cfg(any(feature = "chrono", feature = "uuid", feature = "decimal"))]
+use structfs_core_store::Format;
+use structfs_core_store::{Error, Value};
+
+/// A type with a direct conversion to and from [`Value`].
+pub trait ScalarValue: Sized {
+    /// Convert to a Value.
+    fn to_value(&self) -> Value;
+
+    /// Convert from a Value, failing if it has the wrong shape.
+    fn from_value(value: Value) -> Result<Self, Error>;
+}
+
+#[cfg(any(feature = "chrono", feature = "uuid", feature = "decimal"))]
+fn mismatch(expected: &str, value: &Value) -> Error {
+    Error::decode(
+        Format::VALUE,
+        format!("Expected {}, got {:?}", expected, value),
+    )
+}
+
+#[cfg(feature = "chrono")]
+mod datetime {
+    use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+
+    use super::*;
+
+    impl ScalarValue for DateTime<Utc> {
+        fn to_value(&self) -> Value {
+            Value::String(self.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+
+        fn from_value(value: Value) -> Result<Self, Error> {
+            DateTime::<FixedOffset>::from_value(value).map(|t| t.with_timezone(&Utc))
+        }
+    }
+
+    impl ScalarValue for DateTime<FixedOffset> {
+        fn to_value(&self) -> Value {
+            Value::String(self.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+        }
+
+        fn from_value(value: Value) -> Result<Self, Error> {
+            match &value {
+                Value::String(s) => DateTime::parse_from_rfc3339(s)
+                    .map_err(|e| Error::decode(Format::VALUE, format!("'{}': {}", s, e))),
+                Value::Integer(ms) => DateTime::from_timestamp_millis(*ms)
+                    .map(|t| t.fixed_offset())
+                    .ok_or_else(|| mismatch("a timestamp in range", &value)),
+                _ => Err(mismatch("an RFC 3339 string or epoch milliseconds", &value)),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl ScalarValue for uuid::Uuid {
+    fn to_value(&self) -> Value {
+        Value::Bytes(self.as_bytes().to_vec())
+    }
+
+    fn from_value(value: Value) -> Result<Self, Error> {
+        match &value {
+            Value::Bytes(b) => {
+                uuid::Uuid::from_slice(b).map_err(|e| Error::decode(Format::VALUE, e.to_string()))
+            }
+            Value::String(s) => uuid::Uuid::parse_str(s)
+                .map_err(|e| Error::decode(Format::VALUE, format!("'{}': {}", s, e))),
+            _ => Err(mismatch("16 bytes or a UUID string", &value)),
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl ScalarValue for rust_decimal::Decimal {
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    fn from_value(value: Value) -> Result<Self, Error> {
+        use std::str::FromStr;
+
+        match &value {
+            Value::String(s) => rust_decimal::Decimal::from_str(s)
+                .map_err(|e| Error::decode(Format::VALUE, format!("'{}': {}", s, e))),
+            Value::Integer(i) => Ok((*i).into()),
+            Value::Float(f) => rust_decimal::Decimal::try_from(*f)
+                .map_err(|e| Error::decode(Format::VALUE, format!("{}: {}", f, e))),
+            _ => Err(mismatch("a decimal string or number", &value)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "chrono", feature = "uuid", feature = "decimal"))]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    #[test]
+    fn datetimes_round_trip_and_read_epoch_millis() {
+        let t = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        assert_eq!(t.to_value(), Value::from("2023-11-14T22:13:20.123Z"));
+        assert_eq!(DateTime::<Utc>::from_value(t.to_value()).unwrap(), t);
+        assert_eq!(
+            DateTime::<Utc>::from_value(Value::Integer(1_700_000_000_123)).unwrap(),
+            t
+        );
+
+        let local =
+            DateTime::<FixedOffset>::from_value(Value::from("2024-01-02T03:04:05+02:00")).unwrap();
+        assert_eq!(local.offset().local_minus_utc(), 7200);
+        assert_eq!(local.to_value(), Value::from("2024-01-02T03:04:05+02:00"));
+        assert!(DateTime::<Utc>::from_value(Value::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn uuids_are_bytes() {
+        let id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(id.to_value(), Value::Bytes(id.as_bytes().to_vec()));
+        assert_eq!(Uuid::from_value(id.to_value()).unwrap(), id);
+        assert_eq!(Uuid::from_value(Value::from(id.to_string())).unwrap(), id);
+        assert!(Uuid::from_value(Value::Bytes(vec![1, 2, 3])).is_err());
+    }
+
+    #[test]
+    fn decimals_are_exact() {
+        let d = Decimal::new(1999, 2);
+        assert_eq!(d.to_value(), Value::from("19.99"));
+        assert_eq!(Decimal::from_value(d.to_value()).unwrap(), d);
+        assert_eq!(
+            Decimal::from_value(Value::Integer(42)).unwrap(),
+            Decimal::from(42)
+        );
+        assert_eq!(
+            Decimal::from_value(Value::Float(0.5)).unwrap(),
+            Decimal::new(5, 1)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Format detection for records of unknown format.
+//!
+//! A [`Detector`] guesses the format of raw bytes from their first bytes.
+//! [`MultiCodec`](crate::MultiCodec) asks its detectors in turn when it is
+//! given [`Format::OCTET_STREAM`], and decodes with the first guess that it
+//! has a codec for and that decodes cleanly.
+
+use structfs_core_store::Format;
+
+/// Guesses the format of raw bytes.
+///
+/// Detectors only look at the bytes, so a guess may be wrong; a detector
+/// should claim every input its format can start with and leave it to the
+/// codec to reject the ones it cannot decode. Closures taking `&[u8]` are
+/// detectors too.
+pub trait Detector: Send + Sync {
+    /// The format `bytes` appear to be in, if this detector recognizes it.
+    fn detect(&self, bytes: &[u8]) -> Option<Format>;
+}
+
+impl<F> Detector for F
+where
+    F: Fn(&[u8]) -> Option<Format> + Send + Sync,
+{
+    fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        self(bytes)
+    }
+}
+
+/// The bytes after a UTF-8 byte order mark and leading whitespace.
+fn text_start(bytes: &[u8]) -> &[u8] {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Detects JSON objects and arrays, which start with `{` or `[`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonDetector;
+
+impl Detector for JsonDetector {
+    fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        matches!(text_start(bytes).first(), Some(b'{' | b'[')).then_some(Format::JSON)
+    }
+}
+
+/// Detects CBOR maps and arrays, tagged values and the self-described CBOR
+/// tag (RFC 8949 §3.4.6).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborDetector;
+
+impl Detector for CborDetector {
+    fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        match bytes.first()? {
+            // Arrays and maps
+            0x80..=0xbf => Some(Format::CBOR),
+            // Tags, including 55799 (0xd9 0xd9 0xf7) for self-described CBOR
+            0xc0..=0xdb => Some(Format::CBOR),
+            _ => None,
+        }
+    }
+}
+
+/// Detects MessagePack maps and arrays.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackDetector;
+
+impl Detector for MsgPackDetector {
+    fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        match bytes.first()? {
+            // fixmap and fixarray
+            0x80..=0x9f => Some(Format::MSGPACK),
+            // array 16/32 and map 16/32
+            0xdc..=0xdf => Some(Format::MSGPACK),
+            _ => None,
+        }
+    }
+}
+
+/// Detects YAML documents that open with a `---` marker or a `%YAML`
+/// directive.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YamlDetector;
+
+impl Detector for YamlDetector {
+    fn detect(&self, bytes: &[u8]) -> Option<Format> {
+        let start = text_start(bytes);
+        (start.starts_with(b"---") || start.starts_with(b"%YAML")).then_some(Format::YAML)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detectors_recognize_their_formats() {
+        assert_eq!(
+            JsonDetector.detect(b"\xef\xbb\xbf  {\"a\": 1}"),
+            Some(Format::JSON)
+        );
+        assert_eq!(JsonDetector.detect(b"\n[1, 2]"), Some(Format::JSON));
+        assert_eq!(JsonDetector.detect(b"name = 1"), None);
+
+        assert_eq!(
+            CborDetector.detect(&[0xa1, 0x61, 0x61, 0x01]),
+            Some(Format::CBOR)
+        );
+        assert_eq!(
+            CborDetector.detect(&[0xd9, 0xd9, 0xf7, 0xa0]),
+            Some(Format::CBOR)
+        );
+        assert_eq!(CborDetector.detect(b"{"), None);
+
+        assert_eq!(
+            MsgPackDetector.detect(&[0x81, 0xa1, 0x61, 0x01]),
+            Some(Format::MSGPACK)
+        );
+        assert_eq!(
+            MsgPackDetector.detect(&[0xde, 0x00, 0x00]),
+            Some(Format::MSGPACK)
+        );
+        assert_eq!(MsgPackDetector.detect(&[0xa0]), None);
+
+        assert_eq!(YamlDetector.detect(b"---\na: 1\n"), Some(Format::YAML));
+        assert_eq!(YamlDetector.detect(b"%YAML 1.2\n---\n"), Some(Format::YAML));
+        assert_eq!(YamlDetector.detect(b"a: 1\n"), None);
+
+        for detector in [
+            &JsonDetector as &dyn Detector,
+            &CborDetector,
+            &MsgPackDetector,
+        ] {
+            assert_eq!(detector.detect(b""), None);
+        }
+    }
+}
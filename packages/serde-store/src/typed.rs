@@ -5,7 +5,7 @@ use serde::Serialize;
 
 use structfs_core_store::{Codec, Error, Path, Reader, Record, Writer};
 
-use crate::convert::{from_value, to_value};
+use crate::convert::{from_value_at, to_value};
 
 /// Extension trait for typed reads.
 ///
@@ -37,6 +37,9 @@ pub trait TypedReader: Reader {
     /// 1. Reads the Record from the store
     /// 2. Parses it to a Value using the codec (if raw)
     /// 3. Deserializes the Value to the target type
+    ///
+    /// If the value does not fit `T`, the error names `from` and the
+    /// offending field, as in [`from_value`](crate::from_value).
     fn read_as<T: DeserializeOwned>(
         &mut self,
         from: &Path,
@@ -47,7 +50,7 @@ pub trait TypedReader: Reader {
         };
 
         let value = record.into_value(codec)?;
-        let typed = from_value(value)?;
+        let typed = from_value_at(value, from)?;
         Ok(Some(typed))
     }
 
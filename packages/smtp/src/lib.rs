@@ -0,0 +1,47 @@
+//! # structfs-smtp
+//!
+//! A store that sends email, so notification Blocks can write a mail to a
+//! path instead of shelling out to `sendmail` or `curl`.
+//!
+//! Mail written to `outbox` is checked, queued and sent through the
+//! configured SMTP server in the background; its delivery status is read
+//! back at `outbox/{id}/status`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use structfs_smtp::{SmtpOptions, SmtpStore};
+//!
+//! let mut store = SmtpStore::new(SmtpOptions {
+//!     host: "smtp.example.com".into(),
+//!     username: Some("alerts".into()),
+//!     password: Some(password),
+//!     from: Some("alerts@example.com".into()),
+//!     ..Default::default()
+//! })?;
+//!
+//! let mail = store.write(&path!("outbox"), Record::parsed(mail_value))?;
+//! // later: "queued", "sending", "sent" or "failed"
+//! let status = store.read(&mail.join(&path!("status")))?;
+//! ```
+//!
+//! ## Mail
+//!
+//! A mail is a map:
+//!
+//! | Field | |
+//! |-------|-|
+//! | `to`, `cc`, `bcc` | an address or an array of them; at least one recipient is required |
+//! | `from` | the sender, defaulting to [`SmtpOptions::from`] |
+//! | `reply_to` | an address |
+//! | `subject` | required |
+//! | `body` | the plain text body |
+//! | `html` | an HTML alternative to `body` |
+//! | `attachments` | an array of `{filename, content, content_type}`, with `content` a string or bytes |
+//!
+//! Addresses may include a display name: `"Ops <ops@example.com>"`.
+
+mod mail;
+mod store;
+
+pub use store::{Security, SmtpOptions, SmtpStore};
@@ -0,0 +1,233 @@
+//! Mail values as lettre messages.
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::Message;
+use structfs_core_store::{Error, Value};
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::store("smtp", "write", message)
+}
+
+fn field<'a>(mail: &'a Value, name: &str) -> Option<&'a Value> {
+    match mail {
+        Value::Map(map) => map.get(name).filter(|v| !matches!(v, Value::Null)),
+        _ => None,
+    }
+}
+
+fn text<'a>(mail: &'a Value, name: &str) -> Result<Option<&'a str>, Error> {
+    match field(mail, name) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(invalid(format!("{} must be a string", name))),
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, Error> {
+    address
+        .parse()
+        .map_err(|e| invalid(format!("invalid address {:?}: {}", address, e)))
+}
+
+/// The mailboxes in `name`, which may be one address or an array of them.
+fn mailboxes(mail: &Value, name: &str) -> Result<Vec<Mailbox>, Error> {
+    match field(mail, name) {
+        None => Ok(Vec::new()),
+        Some(Value::String(address)) => Ok(vec![mailbox(address)?]),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(address) => mailbox(address),
+                _ => Err(invalid(format!("{} must hold addresses", name))),
+            })
+            .collect(),
+        Some(_) => Err(invalid(format!(
+            "{} must be an address or an array of them",
+            name
+        ))),
+    }
+}
+
+fn attachment(value: &Value) -> Result<SinglePart, Error> {
+    let filename = text(value, "filename")?
+        .ok_or_else(|| invalid("attachment without a filename"))?
+        .to_string();
+    let content = match field(value, "content") {
+        Some(Value::String(s)) => s.as_bytes().to_vec(),
+        Some(Value::Bytes(b)) => b.clone(),
+        _ => return Err(invalid(format!("attachment {} without content", filename))),
+    };
+    let content_type = text(value, "content_type")?.unwrap_or("application/octet-stream");
+    let content_type = ContentType::parse(content_type)
+        .map_err(|e| invalid(format!("invalid content_type {:?}: {}", content_type, e)))?;
+    Ok(Attachment::new(filename).body(content, content_type))
+}
+
+/// Build the message a mail value describes.
+///
+/// The value is `{to, cc, bcc, from, reply_to, subject, body, html,
+/// attachments}`: `to`, `cc` and `bcc` are an address or an array of them,
+/// at least one of which is required; `from` defaults to `default_from`;
+/// `body` is plain text and `html` an optional alternative; and each
+/// attachment is `{filename, content, content_type}`, with `content` a
+/// string or bytes.
+pub(crate) fn build(mail: &Value, default_from: Option<&Mailbox>) -> Result<Message, Error> {
+    if !matches!(mail, Value::Map(_)) {
+        return Err(invalid("mail must be a map"));
+    }
+
+    let from = match text(mail, "from")? {
+        Some(address) => mailbox(address)?,
+        None => default_from
+            .cloned()
+            .ok_or_else(|| invalid("mail without from, and no default sender"))?,
+    };
+    let subject = text(mail, "subject")?.ok_or_else(|| invalid("mail without a subject"))?;
+
+    let mut builder = Message::builder().from(from).subject(subject);
+    let mut recipients = 0;
+    for mailbox in mailboxes(mail, "to")? {
+        builder = builder.to(mailbox);
+        recipients += 1;
+    }
+    for mailbox in mailboxes(mail, "cc")? {
+        builder = builder.cc(mailbox);
+        recipients += 1;
+    }
+    for mailbox in mailboxes(mail, "bcc")? {
+        builder = builder.bcc(mailbox);
+        recipients += 1;
+    }
+    if recipients == 0 {
+        return Err(invalid("mail without recipients"));
+    }
+    if let Some(reply_to) = text(mail, "reply_to")? {
+        builder = builder.reply_to(mailbox(reply_to)?);
+    }
+
+    let body = text(mail, "body")?.unwrap_or_default().to_string();
+    let html = text(mail, "html")?;
+    let attachments = match field(mail, "attachments") {
+        None => Vec::new(),
+        Some(Value::Array(items)) => items.iter().map(attachment).collect::<Result<_, _>>()?,
+        Some(_) => return Err(invalid("attachments must be an array")),
+    };
+
+    let message = match (html, attachments.is_empty()) {
+        // A lone plain body needs no multipart wrapper.
+        (None, true) => builder.singlepart(SinglePart::plain(body)),
+        (Some(html), true) => {
+            builder.multipart(MultiPart::alternative_plain_html(body, html.to_string()))
+        }
+        (html, false) => {
+            let mut mixed = match html {
+                Some(html) => MultiPart::mixed()
+                    .multipart(MultiPart::alternative_plain_html(body, html.to_string())),
+                None => MultiPart::mixed().singlepart(SinglePart::plain(body)),
+            };
+            for attachment in attachments {
+                mixed = mixed.singlepart(attachment);
+            }
+            builder.multipart(mixed)
+        }
+    };
+    message.map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn mail(fields: &[(&str, Value)]) -> Value {
+        Value::Map(
+            fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    fn formatted(message: Message) -> String {
+        String::from_utf8(message.formatted()).unwrap()
+    }
+
+    #[test]
+    fn builds_plain_and_multipart_messages() {
+        let from: Mailbox = "alerts@example.com".parse().unwrap();
+        let plain = build(
+            &mail(&[
+                ("to", Value::from("ops@example.com")),
+                ("subject", Value::from("Disk full")),
+                ("body", Value::from("/var is at 98%")),
+            ]),
+            Some(&from),
+        )
+        .unwrap();
+        let plain = formatted(plain);
+        assert!(plain.contains("From: alerts@example.com"));
+        assert!(plain.contains("To: ops@example.com"));
+        assert!(plain.contains("/var is at 98%"));
+        assert!(!plain.contains("multipart"));
+
+        let multipart = build(
+            &mail(&[
+                (
+                    "to",
+                    Value::Array(vec![
+                        Value::from("a@example.com"),
+                        Value::from("b@example.com"),
+                    ]),
+                ),
+                ("from", Value::from("me@example.com")),
+                ("subject", Value::from("Report")),
+                ("html", Value::from("<b>hi</b>")),
+                (
+                    "attachments",
+                    Value::Array(vec![mail(&[
+                        ("filename", Value::from("report.csv")),
+                        ("content", Value::from("a,b\n1,2\n")),
+                        ("content_type", Value::from("text/csv")),
+                    ])]),
+                ),
+            ]),
+            None,
+        )
+        .unwrap();
+        let multipart = formatted(multipart);
+        assert!(multipart.contains("From: me@example.com"));
+        assert!(multipart.contains("multipart/mixed"));
+        assert!(multipart.contains("text/html"));
+        assert!(multipart.contains("filename=\"report.csv\""));
+    }
+
+    #[test]
+    fn rejects_incomplete_mail() {
+        let from: Mailbox = "alerts@example.com".parse().unwrap();
+        let err =
+            |value: Value, from: Option<&Mailbox>| build(&value, from).unwrap_err().to_string();
+
+        assert!(err(Value::from("hi"), Some(&from)).contains("must be a map"));
+        assert!(err(mail(&[("subject", Value::from("s"))]), Some(&from)).contains("recipients"));
+        assert!(
+            err(mail(&[("to", Value::from("a@example.com"))]), Some(&from)).contains("subject")
+        );
+        assert!(err(
+            mail(&[
+                ("to", Value::from("a@example.com")),
+                ("subject", Value::from("s"))
+            ]),
+            None
+        )
+        .contains("default sender"));
+        assert!(err(
+            mail(&[
+                ("to", Value::from("not an address")),
+                ("subject", Value::from("s"))
+            ]),
+            Some(&from)
+        )
+        .contains("invalid address"));
+    }
+}
@@ -0,0 +1,409 @@
+//! The outbox store and its sending thread.
+
+use std::fmt::Display;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use structfs_core_store::{Deleter, Error, HandleBroker, Path, Reader, Record, Value, Writer};
+
+use crate::mail;
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// Plain text upgraded with `STARTTLS`, usually on port 587.
+    #[default]
+    StartTls,
+    /// No encryption, for local relays and test servers.
+    None,
+}
+
+/// Where and how to send mail.
+///
+/// In a config these appear as a map; every field but `host` is optional:
+/// ```json
+/// {"host": "smtp.example.com", "username": "alerts", "password": "...", "from": "alerts@example.com"}
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SmtpOptions {
+    /// The server's hostname.
+    pub host: String,
+    /// The server's port, if not the default for `security`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// The user to authenticate as; no authentication when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// The password for `username`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// How the connection is secured.
+    pub security: Security,
+    /// The sender of mail that does not name its own `from`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Connection timeout in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl SmtpOptions {
+    /// A transport for these options.
+    pub fn transport(&self) -> Result<SmtpTransport, Error> {
+        let builder = match self.security {
+            Security::Tls => SmtpTransport::relay(&self.host),
+            Security::StartTls => SmtpTransport::starttls_relay(&self.host),
+            Security::None => Ok(SmtpTransport::builder_dangerous(&self.host)),
+        }
+        .map_err(|e| Error::store("smtp", "connect", e.to_string()))?;
+        let mut builder = builder.timeout(self.timeout_secs.map(Duration::from_secs));
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                self.password.clone().unwrap_or_default(),
+            ));
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Where a queued mail is on its way out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Queued,
+    Sending,
+    Sent,
+    Failed,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Queued => "queued",
+            Status::Sending => "sending",
+            Status::Sent => "sent",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+/// A mail in the outbox.
+struct Entry {
+    mail: Value,
+    status: Status,
+    error: Option<String>,
+}
+
+impl Entry {
+    /// The mail as written, with its `status` and, once it has failed,
+    /// its `error`.
+    fn to_value(&self) -> Value {
+        let mut value = self.mail.clone();
+        if let Value::Map(map) = &mut value {
+            map.insert("status".into(), Value::String(self.status.as_str().into()));
+            map.insert(
+                "error".into(),
+                self.error.clone().map_or(Value::Null, Value::String),
+            );
+        }
+        value
+    }
+}
+
+type Outbox = Arc<Mutex<HandleBroker<Entry>>>;
+
+fn lock(outbox: &Outbox) -> MutexGuard<'_, HandleBroker<Entry>> {
+    outbox.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A store that sends mail written to its `outbox`.
+///
+/// Writing `{to, subject, body, attachments}` to `outbox` checks the mail,
+/// queues it and returns its path, `outbox/{id}`; a background thread then
+/// sends queued mail in order. Reading `outbox/{id}` gives the mail with its
+/// `status` (`queued`, `sending`, `sent` or `failed`) and `error`, and
+/// `outbox/{id}/status` just the status. Reading `outbox` lists the queue.
+/// Deleting `outbox/{id}` forgets a mail, and stops it being sent if it has
+/// not been yet.
+///
+/// See the [crate docs](crate) for the fields a mail may have.
+pub struct SmtpStore {
+    outbox: Outbox,
+    queue: Sender<(u64, Message)>,
+    from: Option<Mailbox>,
+}
+
+impl SmtpStore {
+    /// Send through the server `options` describe.
+    pub fn new(options: SmtpOptions) -> Result<Self, Error> {
+        let transport = options.transport()?;
+        Self::with_transport(transport, options.from.as_deref())
+    }
+
+    /// Send through `transport`, with `from` as the default sender.
+    ///
+    /// Any lettre transport works, such as a
+    /// [`StubTransport`](lettre::transport::stub::StubTransport) in tests.
+    pub fn with_transport<T>(transport: T, from: Option<&str>) -> Result<Self, Error>
+    where
+        T: Transport + Send + 'static,
+        T::Error: Display,
+    {
+        let from = from
+            .map(|address| {
+                address.parse().map_err(|e| {
+                    Error::store("smtp", "new", format!("invalid from {:?}: {}", address, e))
+                })
+            })
+            .transpose()?;
+        let outbox: Outbox = Arc::new(Mutex::new(HandleBroker::new("outbox", "mail")));
+        let (queue, queued) = mpsc::channel::<(u64, Message)>();
+
+        let sending = outbox.clone();
+        thread::spawn(move || {
+            // Ends when the store, and with it the queue, is dropped.
+            for (id, message) in queued {
+                match lock(&sending).get_mut(id) {
+                    Some(entry) => entry.status = Status::Sending,
+                    None => continue,
+                }
+                let result = transport.send(&message);
+                if let Some(entry) = lock(&sending).get_mut(id) {
+                    match result {
+                        Ok(_) => entry.status = Status::Sent,
+                        Err(e) => {
+                            entry.status = Status::Failed;
+                            entry.error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            outbox,
+            queue,
+            from,
+        })
+    }
+}
+
+impl Reader for SmtpStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        let mut outbox = lock(&self.outbox);
+        if outbox.is_listing(from) {
+            return Ok(Some(Record::parsed(outbox.listing())));
+        }
+        let Some((id, rest)) = outbox.parse(from) else {
+            return Ok(None);
+        };
+        let value = outbox
+            .get(id)
+            .map(Entry::to_value)
+            .and_then(|value| value.get(&rest).cloned());
+        Ok(value.map(Record::parsed))
+    }
+}
+
+impl Writer for SmtpStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let mut outbox = lock(&self.outbox);
+        if !outbox.is_listing(to) {
+            return Err(Error::store(
+                "smtp",
+                "write",
+                format!("mail is written to {}, not {}", outbox.prefix(), to),
+            ));
+        }
+        let mail = data.into_value(&structfs_serde_store::JsonCodec)?;
+        let message = mail::build(&mail, self.from.as_ref())?;
+        let id = outbox.insert(Entry {
+            mail,
+            status: Status::Queued,
+            error: None,
+        });
+        self.queue
+            .send((id, message))
+            .map_err(|_| Error::store("smtp", "write", "the sending thread has stopped"))?;
+        Ok(outbox.path(id))
+    }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl Deleter for SmtpStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        let mut outbox = lock(&self.outbox);
+        match outbox.parse(path) {
+            Some((id, rest)) if rest.is_empty() => {
+                outbox.remove(id);
+                Ok(())
+            }
+            _ => Err(Error::store(
+                "smtp",
+                "delete",
+                format!("only queued mail can be deleted, not {}", path),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lettre::transport::stub::StubTransport;
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+    use structfs_core_store::{path, NoCodec};
+
+    fn mail(to: &str) -> Record {
+        Record::parsed(Value::Map(BTreeMap::from([
+            ("to".to_string(), Value::from(to)),
+            ("subject".to_string(), Value::from("Build failed")),
+            ("body".to_string(), Value::from("see the log")),
+        ])))
+    }
+
+    fn value(store: &mut SmtpStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|r| r.into_value(&NoCodec).unwrap())
+    }
+
+    /// Wait for the mail at `path` to leave the queue, returning its status.
+    fn settled(store: &mut SmtpStore, path: &Path) -> Value {
+        let status = path.join(&path!("status"));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let current = value(store, &status).unwrap();
+            if !matches!(&current, Value::String(s) if s == "queued" || s == "sending")
+                || Instant::now() > deadline
+            {
+                return current;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn written_mail_is_sent() {
+        let transport = StubTransport::new_ok();
+        let mut store =
+            SmtpStore::with_transport(transport.clone(), Some("ci@example.com")).unwrap();
+
+        let at = store
+            .write(&path!("outbox"), mail("dev@example.com"))
+            .unwrap();
+        assert_eq!(at, path!("outbox/0"));
+        assert_eq!(settled(&mut store, &at), Value::from("sent"));
+        assert_eq!(
+            value(&mut store, &at.join(&path!("subject"))),
+            Some(Value::from("Build failed"))
+        );
+        assert_eq!(
+            value(&mut store, &at.join(&path!("error"))),
+            Some(Value::Null)
+        );
+
+        let sent = transport.messages();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].1.contains("From: ci@example.com"));
+        assert!(sent[0].1.contains("To: dev@example.com"));
+
+        let listing = value(&mut store, &path!("outbox")).unwrap();
+        assert_eq!(
+            listing.get(&path!("items/0/path")),
+            Some(&Value::from("outbox/0"))
+        );
+
+        store.delete(&at).unwrap();
+        assert_eq!(value(&mut store, &at), None);
+    }
+
+    #[test]
+    fn failed_delivery_is_reported() {
+        let mut store =
+            SmtpStore::with_transport(StubTransport::new_error(), Some("ci@example.com")).unwrap();
+        let at = store
+            .write(&path!("outbox"), mail("dev@example.com"))
+            .unwrap();
+        assert_eq!(settled(&mut store, &at), Value::from("failed"));
+        assert_eq!(
+            value(&mut store, &at.join(&path!("error"))),
+            Some(Value::from("stub error"))
+        );
+    }
+
+    #[test]
+    fn invalid_mail_is_rejected_when_written() {
+        let transport = StubTransport::new_ok();
+        let mut store = SmtpStore::with_transport(transport.clone(), None).unwrap();
+
+        let err = store
+            .write(&path!("outbox"), mail("dev@example.com"))
+            .unwrap_err();
+        assert!(err.to_string().contains("default sender"), "{}", err);
+        assert!(store
+            .write(&path!("elsewhere"), mail("dev@example.com"))
+            .is_err());
+        assert!(store.delete(&path!("outbox")).is_err());
+        assert!(SmtpStore::with_transport(transport, Some("nope")).is_err());
+    }
+
+    #[test]
+    fn options_deserialize_from_values() {
+        let config = Value::Map(BTreeMap::from([
+            ("host".to_string(), Value::from("smtp.example.com")),
+            ("port".to_string(), Value::Integer(2525)),
+            ("security".to_string(), Value::from("none")),
+            ("from".to_string(), Value::from("alerts@example.com")),
+        ]));
+        let options: SmtpOptions = structfs_serde_store::from_value(config).unwrap();
+        assert_eq!(options.host, "smtp.example.com");
+        assert_eq!(options.port, Some(2525));
+        assert_eq!(options.security, Security::None);
+        assert_eq!(options.username, None);
+
+        let options: SmtpOptions =
+            structfs_serde_store::from_value(Value::Map(BTreeMap::from([(
+                "host".to_string(),
+                Value::from("localhost"),
+            )])))
+            .unwrap();
+        assert_eq!(options.security, Security::StartTls);
+        assert!(SmtpStore::new(options).is_ok());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store =
+            SmtpStore::with_transport(StubTransport::new_ok(), Some("ci@example.com")).unwrap();
+        conformance::assert_found(&mut store, &path!("outbox"));
+        conformance::assert_missing(&mut store, &path!("outbox/7"));
+        conformance::assert_missing(&mut store, &path!("outbox/7/status"));
+
+        let at = store
+            .write(&path!("outbox"), mail("dev@example.com"))
+            .unwrap();
+        conformance::assert_found(&mut store, &at);
+        conformance::assert_missing(&mut store, &at.join(&path!("nope")));
+    }
+}
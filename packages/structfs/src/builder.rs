@@ -0,0 +1,313 @@
+//! Assemble a mount tree from code or configuration.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use structfs_core_store::mount_store::{MountConfig, MountStore, StoreFactory};
+use structfs_core_store::overlay_store::StoreBox;
+use structfs_core_store::{Error, Format, Value};
+use structfs_http::{
+    AsyncHttpBrokerStore, CircuitBreakerStore, HttpBrokerStore, HttpClientStore, OpenApiStore,
+};
+use structfs_json_store::{InMemoryStore, JSONLocalStore};
+use structfs_sys::SysStore;
+
+/// Factory for the stores that ship with StructFS.
+///
+/// Creates memory, local disk, sys, and HTTP stores. A local mount's `path`
+/// names the JSON file backing it. Mount types that need an embedding
+/// application (help, repl, registers) or that are not yet available (remote
+/// StructFS) return an error.
+pub struct DefaultStoreFactory;
+
+impl StoreFactory for DefaultStoreFactory {
+    fn create(&self, config: &MountConfig) -> Result<StoreBox, Error> {
+        match config {
+            MountConfig::Memory => Ok(Box::new(InMemoryStore::new())),
+            MountConfig::Local { path } => Ok(Box::new(JSONLocalStore::open(path)?)),
+            MountConfig::Sys => Ok(Box::new(SysStore::new())),
+            MountConfig::Http { url, options } => {
+                let store = HttpClientStore::with_options(url, options).map_err(|e| {
+                    Error::store("factory", "create", format!("HTTP client: {}", e))
+                })?;
+                OpenApiStore::boxed(CircuitBreakerStore::boxed(store, options), options)
+            }
+            MountConfig::HttpBroker { options } => {
+                let store = HttpBrokerStore::with_options(options).map_err(|e| {
+                    Error::store("factory", "create", format!("HTTP broker: {}", e))
+                })?;
+                Ok(Box::new(store))
+            }
+            MountConfig::AsyncHttpBroker { options } => {
+                let store = AsyncHttpBrokerStore::with_options(options).map_err(|e| {
+                    Error::store("factory", "create", format!("async HTTP broker: {}", e))
+                })?;
+                Ok(Box::new(store))
+            }
+            MountConfig::Plugin { kind, .. } => Err(Error::store(
+                "factory",
+                "create",
+                format!("Unknown mount type: {}", kind),
+            )),
+            other => Err(Error::store(
+                "factory",
+                "create",
+                format!("mount type not available: {:?}", other),
+            )),
+        }
+    }
+}
+
+/// Serialized form of a builder configuration.
+#[derive(Debug, Default, Deserialize)]
+struct BuilderConfig {
+    #[serde(default)]
+    mounts: BTreeMap<String, MountConfig>,
+}
+
+/// Builds a [`MountStore`] with a set of mounts.
+///
+/// Mounts are applied in the order they were added. The resulting store
+/// still accepts mount changes at runtime through `/ctx/mounts`.
+///
+/// # Example
+///
+/// ```rust
+/// use structfs::{MountConfig, StoreBuilder};
+///
+/// let store = StoreBuilder::new()
+///     .mount("data", MountConfig::Memory)
+///     .mount("ctx/sys", MountConfig::Sys)
+///     .build()
+///     .unwrap();
+/// assert_eq!(store.list_mounts().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StoreBuilder {
+    mounts: Vec<(String, MountConfig)>,
+}
+
+impl StoreBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build from a config `Value` of the form `{"mounts": {"<path>": <mount config>}}`.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let config: BuilderConfig = structfs_serde_store::from_value(value.clone())?;
+        Ok(Self::from_config(config))
+    }
+
+    /// Build from a TOML document with a `[mounts]` table.
+    pub fn from_toml_str(source: &str) -> Result<Self, Error> {
+        let config: BuilderConfig =
+            toml::from_str(source).map_err(|e| Error::decode(Format::TOML, e.to_string()))?;
+        Ok(Self::from_config(config))
+    }
+
+    /// Build from a TOML file on disk.
+    pub fn from_toml_file(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_toml_str(&source)
+    }
+
+    fn from_config(config: BuilderConfig) -> Self {
+        Self {
+            mounts: config.mounts.into_iter().collect(),
+        }
+    }
+
+    /// Add a mount at `path`.
+    pub fn mount(mut self, path: impl Into<String>, config: MountConfig) -> Self {
+        self.mounts.push((path.into(), config));
+        self
+    }
+
+    /// Mount an in-memory store at `path`.
+    pub fn memory(self, path: impl Into<String>) -> Self {
+        self.mount(path, MountConfig::Memory)
+    }
+
+    /// Mount the system store at `path`.
+    pub fn sys(self, path: impl Into<String>) -> Self {
+        self.mount(path, MountConfig::Sys)
+    }
+
+    /// Mount an HTTP client for `url` at `path`.
+    pub fn http(self, path: impl Into<String>, url: impl Into<String>) -> Self {
+        self.mount(
+            path,
+            MountConfig::Http {
+                url: url.into(),
+                options: Default::default(),
+            },
+        )
+    }
+
+    /// Build the store with the [`DefaultStoreFactory`].
+    pub fn build(self) -> Result<MountStore<DefaultStoreFactory>, Error> {
+        self.build_with_factory(DefaultStoreFactory)
+    }
+
+    /// Build the store with a custom factory.
+    pub fn build_with_factory<F: StoreFactory>(self, factory: F) -> Result<MountStore<F>, Error> {
+        let mut store = MountStore::new(factory);
+        for (path, config) in self.mounts {
+            store.mount(&path, config)?;
+        }
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection_literals::btree;
+    use structfs_core_store::{path, NoCodec, Reader, Record, Writer};
+
+    #[test]
+    fn builder_mounts_in_order() {
+        let store = StoreBuilder::new()
+            .memory("data")
+            .sys("ctx/sys")
+            .build()
+            .unwrap();
+        let paths: Vec<_> = store.list_mounts().into_iter().map(|m| m.path).collect();
+        assert_eq!(paths, vec!["ctx/sys", "data"]);
+    }
+
+    #[test]
+    fn builder_memory_roundtrip() {
+        let mut store = StoreBuilder::new().memory("data").build().unwrap();
+        store
+            .write(&path!("data/x"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        let value = store
+            .read(&path!("data/x"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(value, Value::Integer(1));
+    }
+
+    #[test]
+    fn builder_sys_mount_readable() {
+        let mut store = StoreBuilder::new().sys("sys").build().unwrap();
+        assert!(store.read(&path!("sys/time/now")).unwrap().is_some());
+    }
+
+    #[test]
+    fn builder_local_mount_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data.json");
+        let local = MountConfig::Local {
+            path: file.to_string_lossy().into_owned(),
+        };
+
+        let mut store = StoreBuilder::new()
+            .mount("data", local.clone())
+            .build()
+            .unwrap();
+        store
+            .write(&path!("data/x"), Record::parsed(Value::Integer(1)))
+            .unwrap();
+        drop(store);
+        assert!(file.exists());
+
+        let mut store = StoreBuilder::new().mount("data", local).build().unwrap();
+        let value = store
+            .read(&path!("data/x"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        assert_eq!(value, Value::Integer(1));
+    }
+
+    #[test]
+    fn from_value_config() {
+        let config = Value::Map(btree! {
+            "mounts".to_string() => Value::Map(btree! {
+                "data".to_string() => Value::Map(btree! {
+                    "type".to_string() => Value::String("memory".into()),
+                }),
+                "api".to_string() => Value::Map(btree! {
+                    "type".to_string() => Value::String("http".into()),
+                    "url".to_string() => Value::String("https://example.com".into()),
+                }),
+            }),
+        });
+        let builder = StoreBuilder::from_value(&config).unwrap();
+        assert_eq!(
+            builder.mounts,
+            vec![
+                (
+                    "api".to_string(),
+                    MountConfig::Http {
+                        url: "https://example.com".into(),
+                        options: Default::default(),
+                    }
+                ),
+                ("data".to_string(), MountConfig::Memory),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_value_rejects_unknown_type() {
+        let config = Value::Map(btree! {
+            "mounts".to_string() => Value::Map(btree! {
+                "data".to_string() => Value::Map(btree! {
+                    "type".to_string() => Value::String("bogus".into()),
+                }),
+            }),
+        });
+        assert!(StoreBuilder::from_value(&config).is_err());
+    }
+
+    #[test]
+    fn from_toml_str_config() {
+        let builder = StoreBuilder::from_toml_str(
+            r#"
+            [mounts.data]
+            type = "memory"
+
+            [mounts."ctx/sys"]
+            type = "sys"
+            "#,
+        )
+        .unwrap();
+        let store = builder.build().unwrap();
+        assert_eq!(store.list_mounts().len(), 2);
+    }
+
+    #[test]
+    fn from_toml_str_invalid() {
+        let result = StoreBuilder::from_toml_str("[mounts.data");
+        assert!(matches!(result, Err(Error::Codec { .. })));
+    }
+
+    #[test]
+    fn from_toml_file_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("structfs.toml");
+        std::fs::write(&file, "[mounts.data]\ntype = \"memory\"\n").unwrap();
+        let builder = StoreBuilder::from_toml_file(&file).unwrap();
+        assert_eq!(builder.mounts.len(), 1);
+    }
+
+    #[test]
+    fn from_toml_file_missing() {
+        let result = StoreBuilder::from_toml_file("/nonexistent/structfs.toml");
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn build_fails_for_unavailable_mount() {
+        let result = StoreBuilder::new().mount("help", MountConfig::Help).build();
+        assert!(result.is_err());
+    }
+}
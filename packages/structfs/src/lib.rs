@@ -3,3 +3,74 @@
 //! StructFS provides a "everything is a store" abstraction where all data access — including
 //! mount management, HTTP requests, and configuration — happens through the same read/write
 //! interface on paths.
+//!
+//! This crate is the batteries-included facade over the layered StructFS crates. It
+//! re-exports the core traits and types, the standard store implementations, and a
+//! [`StoreBuilder`] that assembles a mount tree from code or from configuration.
+//!
+//! # Example
+//!
+//! ```rust
+//! use structfs::prelude::*;
+//!
+//! let mut store = StoreBuilder::new()
+//!     .mount("data", MountConfig::Memory)
+//!     .mount("ctx/sys", MountConfig::Sys)
+//!     .build()
+//!     .unwrap();
+//!
+//! store
+//!     .write(&path!("data/greeting"), Record::parsed(Value::String("hi".into())))
+//!     .unwrap();
+//! let record = store.read(&path!("data/greeting")).unwrap().unwrap();
+//! assert_eq!(record.into_value(&NoCodec).unwrap(), Value::String("hi".into()));
+//! ```
+//!
+//! # Configuration
+//!
+//! The same mount tree can be described as a `Value` or a TOML file:
+//!
+//! ```toml
+//! [mounts.data]
+//! type = "memory"
+//!
+//! [mounts."ctx/sys"]
+//! type = "sys"
+//!
+//! [mounts.api]
+//! type = "http"
+//! url = "https://api.example.com"
+//! ```
+
+mod builder;
+
+pub use builder::{DefaultStoreFactory, StoreBuilder};
+
+// Layer crates, for anything not covered by the re-exports below
+pub use structfs_core_store as core_store;
+pub use structfs_http as http;
+pub use structfs_json_store as json_store;
+pub use structfs_serde_store as serde_store;
+pub use structfs_sys as sys;
+
+// Core traits and types
+pub use structfs_core_store::{
+    mount_store::{MountConfig, MountStore, StoreFactory},
+    overlay_store::{OverlayStore, StoreBox},
+    path, Codec, Error, Format, NoCodec, Path, PathError, Reader, Record, Store, Value, Writer,
+};
+pub use structfs_serde_store::{from_value, to_value, JsonCodec, TypedReader, TypedWriter};
+
+/// Commonly used items, for glob import.
+///
+/// ```rust
+/// use structfs::prelude::*;
+/// ```
+pub mod prelude {
+    pub use crate::builder::StoreBuilder;
+    pub use structfs_core_store::{
+        mount_store::MountConfig, path, Error, Format, NoCodec, Path, Reader, Record, Store, Value,
+        Writer,
+    };
+    pub use structfs_serde_store::{JsonCodec, TypedReader, TypedWriter};
+}
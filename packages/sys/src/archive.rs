@@ -0,0 +1,788 @@
+//! Archive (tar/zip) store.
+//!
+//! Archive jobs run on a background thread. Writing to `create` or `extract`
+//! starts a job and returns its handle path; reading the handle reports
+//! progress until the job completes.
+
+use collection_literals::btree;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Reference, Value, Writer};
+
+static ARCHIVE_HANDLE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tar" => Some(ArchiveFormat::Tar),
+            "zip" => Some(ArchiveFormat::Zip),
+            _ => None,
+        }
+    }
+
+    fn from_extension(path: &str) -> Option<Self> {
+        FsPath::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+}
+
+/// State of an archive job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Running,
+    Complete,
+    Failed,
+}
+
+impl JobState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Running => "running",
+            JobState::Complete => "complete",
+            JobState::Failed => "failed",
+        }
+    }
+}
+
+/// Progress shared between the store and the worker thread.
+#[derive(Debug)]
+struct Progress {
+    state: JobState,
+    /// Total entries, if known up front (tar extraction streams, so it isn't).
+    total_entries: Option<u64>,
+    processed_entries: u64,
+    bytes: u64,
+    current: Option<String>,
+    error: Option<String>,
+}
+
+impl Progress {
+    fn new() -> Self {
+        Self {
+            state: JobState::Running,
+            total_entries: None,
+            processed_entries: 0,
+            bytes: 0,
+            current: None,
+            error: None,
+        }
+    }
+}
+
+type SharedProgress = Arc<Mutex<Progress>>;
+
+/// A running or finished archive job.
+struct ArchiveJob {
+    operation: &'static str,
+    format: ArchiveFormat,
+    source: String,
+    output: String,
+    progress: SharedProgress,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// Store for creating and extracting tar and zip archives.
+pub struct ArchiveStore {
+    jobs: HashMap<u64, ArchiveJob>,
+}
+
+impl ArchiveStore {
+    pub fn new() -> Self {
+        Self {
+            jobs: HashMap::new(),
+        }
+    }
+
+    fn next_handle_id() -> u64 {
+        ARCHIVE_HANDLE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn read_value(&mut self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(Value::Map(btree! {
+                "create".into() => Value::String("Write {\"paths\": [...], \"output\": \"out.tar\", \"format\": \"tar\"|\"zip\"} to start a job".into()),
+                "extract".into() => Value::String("Write {\"path\": \"in.zip\", \"output\": \"dir\"} to start a job".into()),
+                "handles".into() => Reference::with_type("handles", "collection").to_value(),
+            })));
+        }
+
+        if path[0] != "handles" {
+            return Ok(None);
+        }
+
+        match path.len() {
+            1 => {
+                let mut ids: Vec<_> = self.jobs.keys().copied().collect();
+                ids.sort_unstable();
+                let items = ids
+                    .into_iter()
+                    .map(|id| Reference::with_type(format!("handles/{}", id), "handle").to_value())
+                    .collect();
+                Ok(Some(Value::Map(btree! {
+                    "items".into() => Value::Array(items),
+                })))
+            }
+            2 | 3 => {
+                let id: u64 = match path[1].parse() {
+                    Ok(id) => id,
+                    Err(_) => return Ok(None),
+                };
+                let job = match self.jobs.get_mut(&id) {
+                    Some(job) => job,
+                    None => return Ok(None),
+                };
+                if path.len() == 3 {
+                    if path[2] != "wait" {
+                        return Ok(None);
+                    }
+                    // Block until the worker finishes
+                    if let Some(worker) = job.worker.take() {
+                        let _ = worker.join();
+                    }
+                }
+                Ok(Some(Self::job_status(id, job)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn job_status(id: u64, job: &ArchiveJob) -> Value {
+        let progress = job.progress.lock().unwrap();
+        let optional = |s: &Option<String>| s.clone().map_or(Value::Null, Value::String);
+        Value::Map(btree! {
+            "id".into() => Value::Integer(id as i64),
+            "operation".into() => Value::String(job.operation.into()),
+            "format".into() => Value::String(job.format.as_str().into()),
+            "source".into() => Value::String(job.source.clone()),
+            "output".into() => Value::String(job.output.clone()),
+            "state".into() => Value::String(progress.state.as_str().into()),
+            "total_entries".into() => progress.total_entries.map_or(Value::Null, |n| Value::Integer(n as i64)),
+            "processed_entries".into() => Value::Integer(progress.processed_entries as i64),
+            "bytes".into() => Value::Integer(progress.bytes as i64),
+            "current".into() => optional(&progress.current),
+            "error".into() => optional(&progress.error),
+        })
+    }
+
+    fn string_field(value: &Value, name: &str) -> Option<String> {
+        match value {
+            Value::Map(map) => match map.get(name) {
+                Some(Value::String(s)) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parse_format(
+        value: &Value,
+        path: &str,
+        operation: &'static str,
+    ) -> Result<ArchiveFormat, Error> {
+        match Self::string_field(value, "format") {
+            Some(name) => ArchiveFormat::from_name(&name).ok_or_else(|| {
+                Error::store(
+                    "archive",
+                    operation,
+                    format!("Unknown archive format: {}", name),
+                )
+            }),
+            None => ArchiveFormat::from_extension(path).ok_or_else(|| {
+                Error::store(
+                    "archive",
+                    operation,
+                    "Cannot infer archive format; set 'format' to \"tar\" or \"zip\"",
+                )
+            }),
+        }
+    }
+
+    fn start<F>(
+        &mut self,
+        operation: &'static str,
+        format: ArchiveFormat,
+        source: String,
+        output: String,
+        work: F,
+    ) -> Path
+    where
+        F: FnOnce(&SharedProgress) -> io::Result<()> + Send + 'static,
+    {
+        let progress: SharedProgress = Arc::new(Mutex::new(Progress::new()));
+        let worker_progress = Arc::clone(&progress);
+        let worker = std::thread::spawn(move || {
+            let result = work(&worker_progress);
+            let mut progress = worker_progress.lock().unwrap();
+            progress.current = None;
+            match result {
+                Ok(()) => progress.state = JobState::Complete,
+                Err(e) => {
+                    progress.state = JobState::Failed;
+                    progress.error = Some(e.to_string());
+                }
+            }
+        });
+
+        let id = Self::next_handle_id();
+        self.jobs.insert(
+            id,
+            ArchiveJob {
+                operation,
+                format,
+                source,
+                output,
+                progress,
+                worker: Some(worker),
+            },
+        );
+        Path::parse(&format!("handles/{}", id)).unwrap()
+    }
+
+    fn write_create(&mut self, value: &Value) -> Result<Path, Error> {
+        let paths: Vec<String> = match value {
+            Value::Map(map) => match map.get("paths") {
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(s) => Ok(s.clone()),
+                        _ => Err(Error::store(
+                            "archive",
+                            "create",
+                            "'paths' must contain strings",
+                        )),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => {
+                    return Err(Error::store(
+                        "archive",
+                        "create",
+                        "create requires 'paths' array",
+                    ))
+                }
+            },
+            _ => {
+                return Err(Error::store(
+                    "archive",
+                    "create",
+                    "create requires a map with 'paths' and 'output'",
+                ))
+            }
+        };
+        let output = Self::string_field(value, "output")
+            .ok_or_else(|| Error::store("archive", "create", "create requires 'output' field"))?;
+        let format = Self::parse_format(value, &output, "create")?;
+
+        let source = paths.join(", ");
+        let out = output.clone();
+        Ok(
+            self.start("create", format, source, output, move |progress| {
+                create_archive(format, &paths, &out, progress)
+            }),
+        )
+    }
+
+    fn write_extract(&mut self, value: &Value) -> Result<Path, Error> {
+        let source = Self::string_field(value, "path")
+            .ok_or_else(|| Error::store("archive", "extract", "extract requires 'path' field"))?;
+        let output = Self::string_field(value, "output")
+            .ok_or_else(|| Error::store("archive", "extract", "extract requires 'output' field"))?;
+        let format = Self::parse_format(value, &source, "extract")?;
+
+        let src = source.clone();
+        let out = output.clone();
+        Ok(
+            self.start("extract", format, source, output, move |progress| {
+                extract_archive(format, &src, &out, progress)
+            }),
+        )
+    }
+}
+
+impl Default for ArchiveStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for ArchiveStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from)?.map(Record::parsed))
+    }
+}
+
+impl Writer for ArchiveStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let value = data.into_value(&NoCodec)?;
+
+        if to.len() == 2 && to[0] == "handles" {
+            // Writing null forgets a finished job
+            if value != Value::Null {
+                return Err(Error::store(
+                    "archive",
+                    "write",
+                    "Write null to remove a handle",
+                ));
+            }
+            let id: u64 = to[1]
+                .parse()
+                .map_err(|_| Error::store("archive", "write", "Invalid handle ID"))?;
+            let job = self.jobs.get(&id).ok_or_else(|| {
+                Error::store("archive", "write", format!("Handle {} not found", id))
+            })?;
+            if job.progress.lock().unwrap().state == JobState::Running {
+                return Err(Error::store(
+                    "archive",
+                    "write",
+                    format!("Handle {} is still running", id),
+                ));
+            }
+            self.jobs.remove(&id);
+            return Ok(to.clone());
+        }
+
+        if to.len() != 1 {
+            return Err(Error::store(
+                "archive",
+                "write",
+                format!("Invalid archive path: {}", to),
+            ));
+        }
+
+        match to[0].as_str() {
+            "create" => self.write_create(&value),
+            "extract" => self.write_extract(&value),
+            _ => Err(Error::store(
+                "archive",
+                "write",
+                format!("Unknown archive operation: {}", to[0]),
+            )),
+        }
+    }
+}
+
+/// Collect (filesystem path, archive name) pairs for the given inputs.
+///
+/// Directories are walked recursively and named relative to their parent,
+/// so archiving `/a/b` produces entries `b/...`.
+fn collect_entries(paths: &[String]) -> io::Result<Vec<(PathBuf, String)>> {
+    fn walk(path: &FsPath, name: String, out: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+        out.push((path.to_path_buf(), name.clone()));
+        if path.is_dir() {
+            let mut children: Vec<_> = fs::read_dir(path)?.collect::<Result<_, _>>()?;
+            children.sort_by_key(|entry| entry.file_name());
+            for child in children {
+                let child_name = format!("{}/{}", name, child.file_name().to_string_lossy());
+                walk(&child.path(), child_name, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let path = FsPath::new(path);
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Cannot archive path without a name: {}", path.display()),
+                )
+            })?;
+        walk(path, name, &mut entries)?;
+    }
+    Ok(entries)
+}
+
+fn record_entry(progress: &SharedProgress, name: &str, bytes: u64) {
+    let mut progress = progress.lock().unwrap();
+    progress.processed_entries += 1;
+    progress.bytes += bytes;
+    progress.current = Some(name.to_string());
+}
+
+fn create_archive(
+    format: ArchiveFormat,
+    paths: &[String],
+    output: &str,
+    progress: &SharedProgress,
+) -> io::Result<()> {
+    let entries = collect_entries(paths)?;
+    progress.lock().unwrap().total_entries = Some(entries.len() as u64);
+    let file = File::create(output)?;
+
+    match format {
+        ArchiveFormat::Tar => {
+            let mut builder = tar::Builder::new(file);
+            for (path, name) in &entries {
+                let size = if path.is_dir() {
+                    builder.append_dir(name, path)?;
+                    0
+                } else {
+                    builder.append_path_with_name(path, name)?;
+                    fs::metadata(path)?.len()
+                };
+                record_entry(progress, name, size);
+            }
+            builder.into_inner()?;
+        }
+        ArchiveFormat::Zip => {
+            let mut writer = zip::ZipWriter::new(file);
+            let options = zip::write::SimpleFileOptions::default();
+            for (path, name) in &entries {
+                let size = if path.is_dir() {
+                    writer.add_directory(name.as_str(), options)?;
+                    0
+                } else {
+                    writer.start_file(name.as_str(), options)?;
+                    io::copy(&mut File::open(path)?, &mut writer)?
+                };
+                record_entry(progress, name, size);
+            }
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_archive(
+    format: ArchiveFormat,
+    source: &str,
+    output: &str,
+    progress: &SharedProgress,
+) -> io::Result<()> {
+    let file = File::open(source)?;
+    fs::create_dir_all(output)?;
+
+    match format {
+        ArchiveFormat::Tar => {
+            let mut archive = tar::Archive::new(file);
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let name = entry.path()?.to_string_lossy().into_owned();
+                let size = entry.size();
+                // unpack_in refuses entries that would escape `output`
+                entry.unpack_in(output)?;
+                record_entry(progress, &name, size);
+            }
+        }
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(file)?;
+            progress.lock().unwrap().total_entries = Some(archive.len() as u64);
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index)?;
+                let relative = entry.enclosed_name().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unsafe path in archive: {}", entry.name()),
+                    )
+                })?;
+                let target = FsPath::new(output).join(relative);
+                let size = if entry.is_dir() {
+                    fs::create_dir_all(&target)?;
+                    0
+                } else {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    io::copy(&mut entry, &mut File::create(&target)?)?
+                };
+                record_entry(progress, entry.name(), size);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use structfs_core_store::path;
+    use tempfile::TempDir;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    fn wait(store: &mut ArchiveStore, handle: &Path) -> BTreeMap<String, Value> {
+        let record = store.read(&handle.join(&path!("wait"))).unwrap().unwrap();
+        match record.into_value(&NoCodec).unwrap() {
+            Value::Map(map) => map,
+            _ => panic!("Expected map"),
+        }
+    }
+
+    fn sample_tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("a.txt"), "alpha").unwrap();
+        fs::write(src.join("nested/b.txt"), "bravo").unwrap();
+        dir
+    }
+
+    fn roundtrip(format: &str) {
+        let dir = sample_tree();
+        let archive = dir.path().join(format!("out.{}", format));
+        let mut store = ArchiveStore::new();
+
+        let handle = store
+            .write(
+                &path!("create"),
+                Record::parsed(map(vec![
+                    (
+                        "paths",
+                        Value::Array(vec![Value::String(
+                            dir.path().join("src").to_string_lossy().into(),
+                        )]),
+                    ),
+                    ("output", Value::String(archive.to_string_lossy().into())),
+                ])),
+            )
+            .unwrap();
+        let status = wait(&mut store, &handle);
+        assert_eq!(status["state"], Value::String("complete".into()));
+        assert_eq!(status["format"], Value::String(format.into()));
+        assert_eq!(status["total_entries"], Value::Integer(4));
+        assert_eq!(status["processed_entries"], Value::Integer(4));
+        assert_eq!(status["bytes"], Value::Integer(10));
+
+        let out = dir.path().join("extracted");
+        let handle = store
+            .write(
+                &path!("extract"),
+                Record::parsed(map(vec![
+                    ("path", Value::String(archive.to_string_lossy().into())),
+                    ("output", Value::String(out.to_string_lossy().into())),
+                ])),
+            )
+            .unwrap();
+        let status = wait(&mut store, &handle);
+        assert_eq!(status["state"], Value::String("complete".into()));
+        assert_eq!(fs::read_to_string(out.join("src/a.txt")).unwrap(), "alpha");
+        assert_eq!(
+            fs::read_to_string(out.join("src/nested/b.txt")).unwrap(),
+            "bravo"
+        );
+    }
+
+    #[test]
+    fn tar_roundtrip() {
+        roundtrip("tar");
+    }
+
+    #[test]
+    fn zip_roundtrip() {
+        roundtrip("zip");
+    }
+
+    #[test]
+    fn explicit_format_overrides_extension() {
+        let dir = sample_tree();
+        let archive = dir.path().join("out.bin");
+        let mut store = ArchiveStore::new();
+        let handle = store
+            .write(
+                &path!("create"),
+                Record::parsed(map(vec![
+                    (
+                        "paths",
+                        Value::Array(vec![Value::String(
+                            dir.path().join("src/a.txt").to_string_lossy().into(),
+                        )]),
+                    ),
+                    ("output", Value::String(archive.to_string_lossy().into())),
+                    ("format", Value::String("zip".into())),
+                ])),
+            )
+            .unwrap();
+        let status = wait(&mut store, &handle);
+        assert_eq!(status["state"], Value::String("complete".into()));
+        assert_eq!(status["total_entries"], Value::Integer(1));
+    }
+
+    #[test]
+    fn create_missing_source_fails_job() {
+        let dir = TempDir::new().unwrap();
+        let mut store = ArchiveStore::new();
+        let handle = store
+            .write(
+                &path!("create"),
+                Record::parsed(map(vec![
+                    (
+                        "paths",
+                        Value::Array(vec![Value::String("/nonexistent/input".into())]),
+                    ),
+                    (
+                        "output",
+                        Value::String(dir.path().join("x.tar").to_string_lossy().into()),
+                    ),
+                ])),
+            )
+            .unwrap();
+        let status = wait(&mut store, &handle);
+        assert_eq!(status["state"], Value::String("failed".into()));
+        assert!(matches!(status["error"], Value::String(_)));
+    }
+
+    #[test]
+    fn create_validation_errors() {
+        let mut store = ArchiveStore::new();
+        let bad = vec![
+            Value::Null,
+            map(vec![("output", Value::String("x.tar".into()))]),
+            map(vec![
+                ("paths", Value::Array(vec![Value::Integer(1)])),
+                ("output", Value::String("x.tar".into())),
+            ]),
+            map(vec![("paths", Value::Array(vec![]))]),
+            map(vec![
+                ("paths", Value::Array(vec![])),
+                ("output", Value::String("x.rar".into())),
+            ]),
+            map(vec![
+                ("paths", Value::Array(vec![])),
+                ("output", Value::String("x.tar".into())),
+                ("format", Value::String("rar".into())),
+            ]),
+        ];
+        for value in bad {
+            assert!(store
+                .write(&path!("create"), Record::parsed(value))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn extract_validation_errors() {
+        let mut store = ArchiveStore::new();
+        assert!(store
+            .write(
+                &path!("extract"),
+                Record::parsed(map(vec![("output", Value::String("d".into()))]))
+            )
+            .is_err());
+        assert!(store
+            .write(
+                &path!("extract"),
+                Record::parsed(map(vec![("path", Value::String("x.zip".into()))]))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn handles_listing_and_removal() {
+        let dir = sample_tree();
+        let mut store = ArchiveStore::new();
+        let handle = store
+            .write(
+                &path!("create"),
+                Record::parsed(map(vec![
+                    (
+                        "paths",
+                        Value::Array(vec![Value::String(
+                            dir.path().join("src").to_string_lossy().into(),
+                        )]),
+                    ),
+                    (
+                        "output",
+                        Value::String(dir.path().join("o.tar").to_string_lossy().into()),
+                    ),
+                ])),
+            )
+            .unwrap();
+        wait(&mut store, &handle);
+
+        let listing = store
+            .read(&path!("handles"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        match listing {
+            Value::Map(map) => match &map["items"] {
+                Value::Array(items) => assert_eq!(items.len(), 1),
+                _ => panic!("Expected array"),
+            },
+            _ => panic!("Expected map"),
+        }
+
+        // Plain reads report status without blocking
+        assert!(store.read(&handle).unwrap().is_some());
+
+        assert!(store
+            .write(&handle, Record::parsed(Value::Integer(1)))
+            .is_err());
+        store.write(&handle, Record::parsed(Value::Null)).unwrap();
+        assert!(store.read(&handle).unwrap().is_none());
+        assert!(store.write(&handle, Record::parsed(Value::Null)).is_err());
+    }
+
+    #[test]
+    fn read_root_and_unknown() {
+        let mut store = ArchiveStore::default();
+        let root = store
+            .read(&path!(""))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        match root {
+            Value::Map(map) => {
+                assert!(map.contains_key("create"));
+                assert!(map.contains_key("extract"));
+                assert!(map.contains_key("handles"));
+            }
+            _ => panic!("Expected map"),
+        }
+        assert!(store.read(&path!("other")).unwrap().is_none());
+        assert!(store.read(&path!("handles/999999")).unwrap().is_none());
+        assert!(store.read(&path!("handles/0/bogus")).unwrap().is_none());
+        assert!(store
+            .write(&path!("other"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(store
+            .write(&path!("create/x"), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = ArchiveStore::new();
+        conformance::assert_found(&mut store, &path!("handles"));
+        conformance::assert_missing(&mut store, &path!("handles/7"));
+        conformance::assert_missing(&mut store, &path!("handles/7/wait"));
+        conformance::assert_missing(&mut store, &path!("handles/nope"));
+    }
+}
@@ -0,0 +1,462 @@
+//! Cron expression scheduling store.
+//!
+//! Writing `{"expr": "0 */5 * * * *"}` to the store root creates a schedule
+//! handle at `crons/{id}`. Reading its `next` returns the next fire time;
+//! reading its `wait` blocks until then, so a guest can loop on `wait`
+//! without parsing cron expressions itself.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDateTime, Timelike, Utc};
+use collection_literals::btree;
+use std::fmt;
+
+use structfs_core_store::{
+    Error, HandleBroker, NoCodec, Path, Reader, Record, Reference, Value, Writer,
+};
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead to look for a fire time before giving up, in days.
+///
+/// Long enough to reach the next 29 February from anywhere.
+const SEARCH_DAYS: i64 = 8 * 366;
+
+/// A parsed cron expression.
+///
+/// Six fields, separated by whitespace: second, minute, hour, day of month,
+/// month and day of week. Five-field expressions are accepted too and fire
+/// at second 0. Each field is `*`, a value, a range `a-b`, any of those with
+/// a step `/n`, or a comma-separated list of them. Months and weekdays may
+/// be given by their three-letter English names; Sunday is 0 or 7.
+///
+/// As in classic cron, when both the day of month and the day of week are
+/// restricted, a day matching either one fires.
+///
+/// All times are UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression.
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let fields = match fields.len() {
+            6 => fields,
+            5 => std::iter::once("0").chain(fields).collect(),
+            n => {
+                return Err(Error::store(
+                    "cron",
+                    "parse",
+                    format!("expected 6 fields, found {} in {:?}", n, expr),
+                ))
+            }
+        };
+
+        // Sunday may be written as 7; fold it onto 0
+        let mut weekdays = parse_field(fields[5], 0, 7, &WEEKDAY_NAMES)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expr: fields.join(" "),
+            seconds: parse_field(fields[0], 0, 59, &[])?,
+            minutes: parse_field(fields[1], 0, 59, &[])?,
+            hours: parse_field(fields[2], 0, 23, &[])?,
+            days: parse_field(fields[3], 1, 31, &[])?,
+            months: parse_field(fields[4], 1, 12, &MONTH_NAMES)?,
+            weekdays,
+            any_day: fields[3].starts_with('*'),
+            any_weekday: fields[5].starts_with('*'),
+        })
+    }
+
+    /// The expression, normalized to six fields.
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// The first fire time strictly after `after`.
+    ///
+    /// `None` if the schedule never fires, such as on 30 February.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc().with_nanosecond(0)? + ChronoDuration::seconds(1);
+        let limit = start + ChronoDuration::days(SEARCH_DAYS);
+        let mut t = start;
+
+        while t <= limit {
+            if !has(self.months, t.month()) {
+                t = start_of_next_month(t)?;
+            } else if !self.day_matches(t) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, t.hour()) {
+                t = t.with_minute(0)?.with_second(0)? + ChronoDuration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t = t.with_second(0)? + ChronoDuration::minutes(1);
+            } else if !has(self.seconds, t.second()) {
+                t += ChronoDuration::seconds(1);
+            } else {
+                return Some(t.and_utc());
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, t: NaiveDateTime) -> bool {
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, _) => weekday,
+            (_, true) => day,
+            _ => day || weekday,
+        }
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn start_of_next_month(t: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = if t.month() == 12 {
+        (t.year() + 1, 1)
+    } else {
+        (t.year(), t.month() + 1)
+    };
+    chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// Parse one field into a bit set of the values it matches.
+///
+/// `names[i]` stands for the value `min + i`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, Error> {
+    let invalid = |why: &str| {
+        Error::store(
+            "cron",
+            "parse",
+            format!("invalid field {:?}: {}", field, why),
+        )
+    };
+    let value = |s: &str| -> Result<u32, Error> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|name| *name == lower) {
+            Some(i) => min + i as u32,
+            None => s.parse().map_err(|_| invalid("not a number"))?,
+        };
+        if n < min || n > max {
+            return Err(invalid(&format!("{} is outside {}-{}", n, min, max)));
+        }
+        Ok(n)
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| invalid("bad step"))?;
+                if step == 0 {
+                    return Err(invalid("step must be positive"));
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((lo, hi)) => (value(lo)?, value(hi)?),
+                // `5/15` means every 15 starting at 5
+                None if step.is_some() => (value(range)?, max),
+                None => {
+                    let n = value(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if lo > hi {
+            return Err(invalid("range is backwards"));
+        }
+        for n in (lo..=hi).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << n;
+        }
+    }
+    Ok(set)
+}
+
+struct CronHandle {
+    schedule: CronSchedule,
+    /// The fire time the last `wait` returned.
+    fired: Option<DateTime<Utc>>,
+}
+
+impl CronHandle {
+    /// The next fire time not yet waited for.
+    fn next(&self) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        let after = self.fired.map_or(now, |fired| fired.max(now));
+        self.schedule.next_after(after)
+    }
+}
+
+/// Store for cron schedules.
+pub struct CronStore {
+    handles: HandleBroker<CronHandle>,
+}
+
+impl CronStore {
+    pub fn new() -> Self {
+        Self {
+            handles: HandleBroker::new("crons", "cron"),
+        }
+    }
+
+    fn read_value(&mut self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(Value::Map(btree! {
+                "create".into() => Value::String("Write {\"expr\": \"sec min hour day month weekday\"} here to get crons/{id}".into()),
+                "crons".into() => Reference::with_type("crons", "collection").to_value(),
+            })));
+        }
+        if self.handles.is_listing(path) {
+            return Ok(Some(self.handles.listing()));
+        }
+
+        let Some((id, rest)) = self.handles.parse(path) else {
+            return Ok(None);
+        };
+        let Some(handle) = self.handles.get_mut(id) else {
+            return Ok(None);
+        };
+        let next = handle.next();
+        let value = match rest.len() {
+            0 => Value::Map(btree! {
+                "id".into() => Value::Integer(id as i64),
+                "expr".into() => Value::String(handle.schedule.expr().into()),
+                "next".into() => timestamp(next),
+                "next_unix_ms".into() => next.map_or(Value::Null, |t| Value::Integer(t.timestamp_millis())),
+            }),
+            1 if rest[0] == "next" => timestamp(next),
+            1 if rest[0] == "wait" => {
+                let Some(next) = next else {
+                    return Err(Error::store("cron", "wait", "schedule never fires"));
+                };
+                if let Ok(delay) = (next - Utc::now()).to_std() {
+                    std::thread::sleep(delay);
+                }
+                handle.fired = Some(next);
+                timestamp(Some(next))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+}
+
+fn timestamp(at: Option<DateTime<Utc>>) -> Value {
+    at.map_or(Value::Null, |t| Value::String(t.to_rfc3339()))
+}
+
+impl Default for CronStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for CronStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from)?.map(Record::parsed))
+    }
+}
+
+impl Writer for CronStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let value = data.into_value(&NoCodec)?;
+
+        if to.is_empty() {
+            let expr = match &value {
+                Value::Map(map) => match map.get("expr") {
+                    Some(Value::String(expr)) => expr,
+                    _ => return Err(Error::store("cron", "create", "missing 'expr' field")),
+                },
+                Value::String(expr) => expr,
+                _ => {
+                    return Err(Error::store(
+                        "cron",
+                        "create",
+                        "expected {\"expr\": \"...\"}",
+                    ))
+                }
+            };
+            let schedule = CronSchedule::parse(expr)?;
+            let id = self.handles.insert(CronHandle {
+                schedule,
+                fired: None,
+            });
+            return Ok(self.handles.path(id));
+        }
+
+        // Writing null to crons/{id} removes the schedule
+        match self.handles.parse(to) {
+            Some((id, rest)) if rest.is_empty() && value == Value::Null => {
+                self.handles.remove(id);
+                Ok(to.clone())
+            }
+            _ => Err(Error::store(
+                "cron",
+                "write",
+                format!("Cannot write to cron/{}", to),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use structfs_core_store::path;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap()
+    }
+
+    #[test]
+    fn next_fire_times() {
+        let every_five = CronSchedule::parse("0 */5 * * * *").unwrap();
+        assert_eq!(
+            every_five.next_after(at(2024, 3, 1, 10, 3, 7)),
+            Some(at(2024, 3, 1, 10, 5, 0))
+        );
+        // Strictly after: a fire time is not returned again
+        assert_eq!(
+            every_five.next_after(at(2024, 3, 1, 10, 5, 0)),
+            Some(at(2024, 3, 1, 10, 10, 0))
+        );
+
+        let new_year = CronSchedule::parse("0 0 0 1 jan *").unwrap();
+        assert_eq!(
+            new_year.next_after(at(2024, 3, 1, 0, 0, 0)),
+            Some(at(2025, 1, 1, 0, 0, 0))
+        );
+
+        let leap_day = CronSchedule::parse("0 0 12 29 2 *").unwrap();
+        assert_eq!(
+            leap_day.next_after(at(2024, 3, 1, 0, 0, 0)),
+            Some(at(2028, 2, 29, 12, 0, 0))
+        );
+
+        let never = CronSchedule::parse("0 0 0 30 2 *").unwrap();
+        assert_eq!(never.next_after(at(2024, 1, 1, 0, 0, 0)), None);
+    }
+
+    #[test]
+    fn day_of_month_or_weekday() {
+        // The 13th, or any Friday
+        let schedule = CronSchedule::parse("0 0 9 13 * fri").unwrap();
+        // 2024-09-01 is a Sunday; the first Friday is the 6th
+        assert_eq!(
+            schedule.next_after(at(2024, 9, 1, 0, 0, 0)),
+            Some(at(2024, 9, 6, 9, 0, 0))
+        );
+        assert_eq!(
+            schedule.next_after(at(2024, 9, 12, 0, 0, 0)),
+            Some(at(2024, 9, 13, 9, 0, 0))
+        );
+
+        // Sunday as 7, and a five-field expression
+        let sundays = CronSchedule::parse("30 4 * * 7").unwrap();
+        assert_eq!(sundays.expr(), "0 30 4 * * 7");
+        assert_eq!(
+            sundays.next_after(at(2024, 9, 2, 0, 0, 0)),
+            Some(at(2024, 9, 8, 4, 30, 0))
+        );
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expr in [
+            "* * * *",
+            "60 * * * * *",
+            "* * * 0 * *",
+            "*/0 * * * * *",
+            "5-1 * * * * *",
+            "* * * * foo *",
+        ] {
+            assert!(CronSchedule::parse(expr).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn handles_report_and_wait_for_fire_times() {
+        let mut store = CronStore::new();
+        let handle = store
+            .write(
+                &path!(""),
+                Record::parsed(Value::Map(btree! {
+                    "expr".into() => Value::String("* * * * * *".into()),
+                })),
+            )
+            .unwrap();
+        assert_eq!(handle, path!("crons/0"));
+
+        let read = |store: &mut CronStore, path: &Path| {
+            store
+                .read(path)
+                .unwrap()
+                .unwrap()
+                .into_value(&NoCodec)
+                .unwrap()
+        };
+        let Value::String(next) = read(&mut store, &path!("crons/0/next")) else {
+            panic!("Expected string");
+        };
+        let next = DateTime::parse_from_rfc3339(&next).unwrap();
+        assert!(next > Utc::now());
+
+        // Successive waits return successive fire times
+        let first = read(&mut store, &path!("crons/0/wait"));
+        let second = read(&mut store, &path!("crons/0/wait"));
+        assert_ne!(first, second);
+
+        store
+            .write(&path!("crons/0"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(store.read(&path!("crons/0/next")).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_expression_is_rejected() {
+        let mut store = CronStore::new();
+        let result = store.write(&path!(""), Record::parsed(Value::String("bogus".into())));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = CronStore::new();
+        conformance::assert_found(&mut store, &path!("crons"));
+        conformance::assert_missing(&mut store, &path!("crons/7"));
+        conformance::assert_missing(&mut store, &path!("crons/7/next"));
+    }
+}
@@ -2,7 +2,9 @@
 
 use collection_literals::btree;
 
-use structfs_core_store::{Error, Path, Reader, Record, Value, Writer};
+use structfs_core_store::{Error, Path, Reader, Record, SelfDescribing, Value, Writer};
+
+use crate::FsStore;
 
 /// Documentation store for sys primitives.
 pub struct DocsStore;
@@ -23,6 +25,9 @@ impl DocsStore {
             "random" => Some(Self::random_docs()),
             "proc" => Some(Self::proc_docs()),
             "fs" => Some(Self::fs_docs()),
+            "url" => Some(Self::url_docs()),
+            "user" => Some(Self::user_docs()),
+            "hw" => Some(Self::hw_docs()),
             _ => None,
         }
     }
@@ -37,6 +42,9 @@ impl DocsStore {
                 "random".into() => Value::String("Random generation - integers, UUIDs, bytes".into()),
                 "proc".into() => Value::String("Process info - PID, CWD, args, environment".into()),
                 "fs".into() => Value::String("Filesystem - open, read, write, stat, mkdir, etc.".into()),
+                "url".into() => Value::String("URLs - parse into components, build from components".into()),
+                "user".into() => Value::String("Users and groups - current user, lookup by name or id".into()),
+                "hw".into() => Value::String("Hardware - CPU, GPUs, disks, network interfaces".into()),
             }),
             "examples".into() => Value::Array(vec![
                 Value::String("read env/HOME".into()),
@@ -44,6 +52,9 @@ impl DocsStore {
                 Value::String("read random/uuid".into()),
                 Value::String("read proc/self/pid".into()),
                 Value::String("write fs/open {\"path\": \"/tmp/test\", \"mode\": \"write\"}".into()),
+                Value::String("write url/parse \"https://example.com/a?b=c\"".into()),
+                Value::String("read user/current".into()),
+                Value::String("read hw/cpu/features".into()),
             ]),
             "see_also".into() => Value::Array(vec![
                 Value::String("docs/env".into()),
@@ -51,6 +62,9 @@ impl DocsStore {
                 Value::String("docs/random".into()),
                 Value::String("docs/proc".into()),
                 Value::String("docs/fs".into()),
+                Value::String("docs/url".into()),
+                Value::String("docs/user".into()),
+                Value::String("docs/hw".into()),
             ]),
         })
     }
@@ -58,14 +72,14 @@ impl DocsStore {
     fn env_docs() -> Value {
         Value::Map(btree! {
             "title".into() => Value::String("Environment Variables".into()),
-            "description".into() => Value::String("Read and write process environment variables.".into()),
+            "description".into() => Value::String("Read and write process environment variables. Read watch/{NAME} repeatedly to see whether a variable changed since the last read.".into()),
         })
     }
 
     fn time_docs() -> Value {
         Value::Map(btree! {
             "title".into() => Value::String("Time Operations".into()),
-            "description".into() => Value::String("Clocks, timestamps, and delays.".into()),
+            "description".into() => Value::String("Clocks, timestamps, and delays. Write {expr} to cron to get crons/{id}, a cron schedule: read its next for the next fire time, or its wait to block until then.".into()),
         })
     }
 
@@ -79,14 +93,32 @@ impl DocsStore {
     fn proc_docs() -> Value {
         Value::Map(btree! {
             "title".into() => Value::String("Process Information".into()),
-            "description".into() => Value::String("Information about the current process.".into()),
+            "description".into() => Value::String("Information about the current process. Write {code} to self/exit to exit after running the hooks registered at self/on_exit.".into()),
         })
     }
 
     fn fs_docs() -> Value {
+        FsStore::new().docs().unwrap_or(Value::Null)
+    }
+
+    fn url_docs() -> Value {
+        Value::Map(btree! {
+            "title".into() => Value::String("URL Operations".into()),
+            "description".into() => Value::String("Parse URLs into components and build URLs from components.".into()),
+        })
+    }
+
+    fn user_docs() -> Value {
         Value::Map(btree! {
-            "title".into() => Value::String("Filesystem Operations".into()),
-            "description".into() => Value::String("File and directory operations with handle-based I/O.".into()),
+            "title".into() => Value::String("Users and Groups".into()),
+            "description".into() => Value::String("The user running this process at current, and user and group entries at lookup/user/{name or uid} and lookup/group/{name or gid}. Unix only.".into()),
+        })
+    }
+
+    fn hw_docs() -> Value {
+        Value::Map(btree! {
+            "title".into() => Value::String("Hardware Inventory".into()),
+            "description".into() => Value::String("Read-only description of the host for placement decisions: cpu {model, vendor, arch, cores, threads, features}, and gpus, disks and nics keyed by device name. Fields the host does not report are null.".into()),
         })
     }
 }
@@ -201,6 +233,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn read_url_docs() {
+        let mut store = DocsStore::new();
+        let record = store.read(&path!("url")).unwrap().unwrap();
+        let value = record.into_value(&NoCodec).unwrap();
+        match value {
+            Value::Map(map) => {
+                assert!(map.contains_key("title"));
+                assert!(map.contains_key("description"));
+            }
+            _ => panic!("Expected map"),
+        }
+    }
+
     #[test]
     fn read_nonexistent_returns_none() {
         let mut store = DocsStore::new();
@@ -240,4 +286,13 @@ mod tests {
         let _store: DocsStore = Default::default();
         // Just verify default works
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = DocsStore::new();
+        conformance::assert_found(&mut store, &path!("env"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+    }
 }
@@ -1,17 +1,125 @@
 //! Environment variable store.
+//!
+//! ## Watching
+//!
+//! `watch/{NAME}` reports whether a variable has changed since it was last
+//! read there, so a configuration Block can poll it and reconfigure without
+//! a restart:
+//!
+//! ```text
+//! read env/watch/LOG_LEVEL
+//!   -> {"name": "LOG_LEVEL", "value": "info", "previous": "info",
+//!       "changed": false, "version": 0}
+//! write env/LOG_LEVEL "debug"
+//! read env/watch/LOG_LEVEL
+//!   -> {"name": "LOG_LEVEL", "value": "debug", "previous": "info",
+//!       "changed": true, "version": 1}
+//! ```
+//!
+//! The first read starts the watch. Each later read compares the variable
+//! with the value seen by the previous read; `version` counts the changes
+//! observed so far. Changes made by other code in the process, including
+//! between reads, are picked up the same way, but a variable changed and
+//! changed back between two reads is not reported. `read env/watch` lists
+//! every watched variable and deleting `env/watch/{NAME}` stops watching
+//! one.
+//!
+//! Deleting `env/{NAME}` unsets the variable. Writing null does the same,
+//! for callers without a [`Deleter`].
+//!
+//! `watch` is reserved, so a variable named `watch` cannot be read through
+//! this store.
 
 use std::collections::BTreeMap;
-use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+use structfs_core_store::{Deleter, Error, NoCodec, Path, Reader, Record, Value, Writer};
+
+/// Path prefix for watched variables.
+const WATCH: &str = "watch";
+
+/// Last observed state of a watched variable.
+struct EnvWatch {
+    value: Option<String>,
+    version: u64,
+}
 
 /// Store for environment variable access.
-pub struct EnvStore;
+#[derive(Default)]
+pub struct EnvStore {
+    watches: BTreeMap<String, EnvWatch>,
+}
+
+/// Read a variable, treating an unset variable as `None`.
+fn var(name: &str) -> Result<Option<String>, Error> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(Error::store(
+            "env",
+            "read",
+            "Environment variable contains invalid UTF-8",
+        )),
+    }
+}
+
+fn optional_string(value: Option<String>) -> Value {
+    value.map_or(Value::Null, Value::String)
+}
 
 impl EnvStore {
     pub fn new() -> Self {
-        Self
+        Self::default()
     }
 
-    fn read_value(&self, path: &Path) -> Result<Option<Value>, Error> {
+    /// Poll a watched variable, starting the watch if needed.
+    fn poll_watch(&mut self, name: &str) -> Result<Value, Error> {
+        let current = var(name)?;
+        let watch = self
+            .watches
+            .entry(name.to_string())
+            .or_insert_with(|| EnvWatch {
+                value: current.clone(),
+                version: 0,
+            });
+
+        let previous = std::mem::replace(&mut watch.value, current.clone());
+        let changed = previous != current;
+        if changed {
+            watch.version += 1;
+        }
+
+        Ok(Value::Map(BTreeMap::from([
+            ("name".to_string(), Value::String(name.to_string())),
+            ("value".to_string(), optional_string(current)),
+            ("previous".to_string(), optional_string(previous)),
+            ("changed".to_string(), Value::Bool(changed)),
+            ("version".to_string(), Value::Integer(watch.version as i64)),
+        ])))
+    }
+
+    fn list_watches(&self) -> Value {
+        Value::Map(
+            self.watches
+                .iter()
+                .map(|(name, watch)| {
+                    let summary = BTreeMap::from([
+                        ("value".to_string(), optional_string(watch.value.clone())),
+                        ("version".to_string(), Value::Integer(watch.version as i64)),
+                    ]);
+                    (name.clone(), Value::Map(summary))
+                })
+                .collect(),
+        )
+    }
+
+    fn read_value(&mut self, path: &Path) -> Result<Option<Value>, Error> {
+        if !path.is_empty() && path[0] == WATCH {
+            return match path.len() {
+                1 => Ok(Some(self.list_watches())),
+                2 => self.poll_watch(&path[1]).map(Some),
+                _ => Ok(None),
+            };
+        }
+
         if path.is_empty() {
             // Return all environment variables as a map
             let vars: BTreeMap<String, Value> = std::env::vars()
@@ -20,25 +128,24 @@ impl EnvStore {
             Ok(Some(Value::Map(vars)))
         } else if path.len() == 1 {
             // Return single variable
-            let name = &path[0];
-            match std::env::var(name) {
-                Ok(value) => Ok(Some(Value::String(value))),
-                Err(std::env::VarError::NotPresent) => Ok(None),
-                Err(std::env::VarError::NotUnicode(_)) => Err(Error::store(
-                    "env",
-                    "read",
-                    "Environment variable contains invalid UTF-8",
-                )),
-            }
+            Ok(var(&path[0])?.map(Value::String))
         } else {
             Ok(None)
         }
     }
 }
 
-impl Default for EnvStore {
-    fn default() -> Self {
-        Self::new()
+impl EnvStore {
+    fn write_watch(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if to.len() != 2 || data.into_value(&NoCodec)? != Value::Null {
+            return Err(Error::store(
+                "env",
+                "write",
+                "Write null to watch/{NAME} to stop watching a variable",
+            ));
+        }
+        self.watches.remove(&to[1]);
+        Ok(to.clone())
     }
 }
 
@@ -58,6 +165,10 @@ impl Writer for EnvStore {
             ));
         }
 
+        if to[0] == WATCH {
+            return self.write_watch(to, data);
+        }
+
         if to.len() != 1 {
             return Err(Error::store(
                 "env",
@@ -85,6 +196,30 @@ impl Writer for EnvStore {
             )),
         }
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        Some(self)
+    }
+}
+
+impl Deleter for EnvStore {
+    fn delete(&mut self, path: &Path) -> Result<(), Error> {
+        match path.len() {
+            2 if path[0] == WATCH => {
+                self.watches.remove(&path[1]);
+                Ok(())
+            }
+            1 if path[0] != WATCH => {
+                std::env::remove_var(&path[0]);
+                Ok(())
+            }
+            _ => Err(Error::store(
+                "env",
+                "delete",
+                "Delete a variable at {NAME} or a watch at watch/{NAME}",
+            )),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -169,6 +304,96 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("must be a string"));
     }
 
+    #[test]
+    fn watch_reports_changes() {
+        let mut store = EnvStore::new();
+        let watch = path!("watch/STRUCTFS_ENV_WATCH_TEST");
+        let poll = |store: &mut EnvStore| match store
+            .read(&watch)
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap()
+        {
+            Value::Map(map) => map,
+            other => panic!("expected a map, got {:?}", other),
+        };
+
+        // The first read is the baseline
+        let first = poll(&mut store);
+        assert_eq!(first["value"], Value::Null);
+        assert_eq!(first["changed"], Value::Bool(false));
+        assert_eq!(first["version"], Value::Integer(0));
+
+        store
+            .write(
+                &path!("STRUCTFS_ENV_WATCH_TEST"),
+                Record::parsed(Value::String("on".into())),
+            )
+            .unwrap();
+        let second = poll(&mut store);
+        assert_eq!(second["value"], Value::String("on".into()));
+        assert_eq!(second["previous"], Value::Null);
+        assert_eq!(second["changed"], Value::Bool(true));
+        assert_eq!(second["version"], Value::Integer(1));
+
+        // Nothing changed since the last read
+        let third = poll(&mut store);
+        assert_eq!(third["changed"], Value::Bool(false));
+        assert_eq!(third["version"], Value::Integer(1));
+
+        std::env::remove_var("STRUCTFS_ENV_WATCH_TEST");
+    }
+
+    #[test]
+    fn watch_list_and_stop() {
+        let mut store = EnvStore::new();
+        store.read(&path!("watch/STRUCTFS_ENV_WATCH_LIST")).unwrap();
+
+        let list = store.read(&path!("watch")).unwrap().unwrap();
+        let Value::Map(list) = list.into_value(&NoCodec).unwrap() else {
+            panic!("expected a map");
+        };
+        assert!(list.contains_key("STRUCTFS_ENV_WATCH_LIST"));
+
+        store
+            .write(
+                &path!("watch/STRUCTFS_ENV_WATCH_LIST"),
+                Record::parsed(Value::Null),
+            )
+            .unwrap();
+        let list = store.read(&path!("watch")).unwrap().unwrap();
+        assert_eq!(
+            list.into_value(&NoCodec).unwrap(),
+            Value::Map(BTreeMap::new())
+        );
+
+        let result = store.write(
+            &path!("watch/STRUCTFS_ENV_WATCH_LIST"),
+            Record::parsed(Value::String("x".into())),
+        );
+        assert!(result.unwrap_err().to_string().contains("stop watching"));
+    }
+
+    #[test]
+    fn delete_var_and_watch() {
+        let mut store = EnvStore::new();
+        structfs_core_store::conformance::assert_deletes(
+            &mut store,
+            &path!("STRUCTFS_ENV_DELETE_TEST"),
+            Value::String("x".into()),
+        );
+
+        store
+            .read(&path!("watch/STRUCTFS_ENV_DELETE_TEST"))
+            .unwrap();
+        Deleter::delete(&mut store, &path!("watch/STRUCTFS_ENV_DELETE_TEST")).unwrap();
+        assert!(store.watches.is_empty());
+
+        assert!(Deleter::delete(&mut store, &path!("")).is_err());
+        assert!(Deleter::delete(&mut store, &path!("FOO/BAR")).is_err());
+    }
+
     #[test]
     fn default_impl() {
         let _store: EnvStore = Default::default();
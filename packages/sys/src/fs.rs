@@ -1,14 +1,24 @@
 //! Filesystem operations store.
+//!
+//! ## Confinement
+//!
+//! [`FsStore::chrooted`] confines every path the store is given to one
+//! directory. Paths are resolved as if that directory were `/`: `/data/x`
+//! and `data/x` both name `{root}/data/x`, and `..` cannot climb above the
+//! root. Symlinks that lead out of the root are rejected. Paths reported
+//! back, such as a handle's `path`, are the ones the caller gave.
 
 use collection_literals::btree;
-use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read as IoRead, Seek, SeekFrom, Write as IoWrite};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::path::{Component, PathBuf};
+use std::time::Duration;
 
-use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Reference, Value, Writer};
-
-static FS_HANDLE_COUNTER: AtomicU64 = AtomicU64::new(0);
+use structfs_core_store::describe::{read_lens, type_value, Action, Docs, Field};
+use structfs_core_store::{
+    Error, HandleBroker, Lister, NoCodec, OpContext, Path, Reader, Record, Reference,
+    SelfDescribing, Value, Writer,
+};
 
 /// File open mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -96,18 +106,78 @@ fn parse_handle_operation(path: &Path) -> Option<(u64, HandleOperation)> {
 
 /// Store for filesystem operations.
 pub struct FsStore {
-    handles: HashMap<u64, FileHandle>,
+    handles: HandleBroker<FileHandle>,
+    /// Directory all paths are confined to, if any.
+    root: Option<PathBuf>,
 }
 
 impl FsStore {
     pub fn new() -> Self {
         Self {
-            handles: HashMap::new(),
+            handles: HandleBroker::new("handles", "handle"),
+            root: None,
         }
     }
 
-    fn next_handle_id() -> u64 {
-        FS_HANDLE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    /// Create a store confined to `root`, which must exist.
+    pub fn chrooted(root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into().canonicalize()?;
+        Ok(Self {
+            handles: HandleBroker::new("handles", "handle"),
+            root: Some(root),
+        })
+    }
+
+    /// Close handles that go unused for `ttl`.
+    pub fn with_handle_ttl(mut self, ttl: Duration) -> Self {
+        self.handles.set_ttl(Some(ttl));
+        self
+    }
+
+    /// The directory this store is confined to, if any.
+    pub fn root(&self) -> Option<&std::path::Path> {
+        self.root.as_deref()
+    }
+
+    /// Map a path from a request to the path on disk.
+    fn resolve(&self, path: &str) -> Result<PathBuf, Error> {
+        let Some(root) = &self.root else {
+            return Ok(PathBuf::from(path));
+        };
+        let escape = || {
+            Error::store(
+                "fs",
+                "resolve",
+                format!("Path '{}' is outside the store root", path),
+            )
+        };
+
+        let mut resolved = root.clone();
+        for component in std::path::Path::new(path).components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+                Component::ParentDir => {
+                    if resolved == *root {
+                        return Err(escape());
+                    }
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+            }
+        }
+
+        // A symlink inside the root can still point outside it. Whatever
+        // part of the path exists must really be under the root.
+        for existing in resolved.ancestors() {
+            if fs::symlink_metadata(existing).is_err() {
+                continue;
+            }
+            match existing.canonicalize() {
+                Ok(real) if real.starts_with(root) => break,
+                _ => return Err(escape()),
+            }
+        }
+        Ok(resolved)
     }
 
     fn read_value(&self, path: &Path) -> Result<Option<Value>, Error> {
@@ -120,6 +190,9 @@ impl FsStore {
                 "rmdir".into() => Reference::with_type("meta/rmdir", "action").to_value(),
                 "unlink".into() => Reference::with_type("meta/unlink", "action").to_value(),
                 "rename".into() => Reference::with_type("meta/rename", "action").to_value(),
+                "archive".into() => Reference::with_type("archive", "store").to_value(),
+                "tail".into() => Reference::with_type("tail", "store").to_value(),
+                "docs".into() => Reference::with_type("docs", "docs").to_value(),
                 "meta".into() => Reference::with_type("meta", "meta").to_value(),
             })));
         }
@@ -128,19 +201,8 @@ impl FsStore {
         Ok(None)
     }
 
-    fn read_handles_listing(&self) -> Value {
-        let items: Vec<Value> = self
-            .handles
-            .keys()
-            .map(|id| Reference::with_type(format!("handles/{}", id), "handle").to_value())
-            .collect();
-        Value::Map(btree! {
-            "items".into() => Value::Array(items),
-        })
-    }
-
     fn read_handle_meta(&self, handle: &FileHandle) -> Result<Value, Error> {
-        let metadata = fs::metadata(&handle.path)?;
+        let metadata = handle.file.metadata()?;
         Ok(Value::Map(btree! {
             "size".into() => Value::Integer(metadata.len() as i64),
             "is_file".into() => Value::Bool(metadata.is_file()),
@@ -174,7 +236,7 @@ impl FsStore {
     fn read_from_position(&mut self, handle_id: u64) -> Result<Value, Error> {
         let handle = self
             .handles
-            .get_mut(&handle_id)
+            .get_mut(handle_id)
             .ok_or_else(|| Error::store("fs", "read", format!("Handle {} not found", handle_id)))?;
 
         let mut buffer = Vec::new();
@@ -188,7 +250,7 @@ impl FsStore {
     fn read_at_offset(&mut self, handle_id: u64, offset: u64) -> Result<Value, Error> {
         let handle = self
             .handles
-            .get_mut(&handle_id)
+            .get_mut(handle_id)
             .ok_or_else(|| Error::store("fs", "read", format!("Handle {} not found", handle_id)))?;
 
         handle.file.seek(SeekFrom::Start(offset))?;
@@ -210,7 +272,7 @@ impl FsStore {
     ) -> Result<Value, Error> {
         let handle = self
             .handles
-            .get_mut(&handle_id)
+            .get_mut(handle_id)
             .ok_or_else(|| Error::store("fs", "read", format!("Handle {} not found", handle_id)))?;
 
         handle.file.seek(SeekFrom::Start(offset))?;
@@ -265,10 +327,11 @@ impl FsStore {
     fn write_handle(&mut self, path: &Path, value: &Value) -> Result<Path, Error> {
         let (handle_id, op) = parse_handle_operation(path)
             .ok_or_else(|| Error::store("fs", "write", format!("Invalid handle path: {}", path)))?;
+        self.handles.touch(handle_id);
 
         match op {
             HandleOperation::Close => {
-                self.handles.remove(&handle_id).ok_or_else(|| {
+                self.handles.remove(handle_id).ok_or_else(|| {
                     Error::store("fs", "write", format!("Handle {} not found", handle_id))
                 })?;
                 Ok(path.clone())
@@ -290,7 +353,7 @@ impl FsStore {
                     ));
                 };
 
-                let handle = self.handles.get_mut(&handle_id).ok_or_else(|| {
+                let handle = self.handles.get_mut(handle_id).ok_or_else(|| {
                     Error::store("fs", "write", format!("Handle {} not found", handle_id))
                 })?;
 
@@ -340,13 +403,13 @@ impl FsStore {
     fn write_content_at_current(&mut self, handle_id: u64, value: &Value) -> Result<(), Error> {
         let encoding = self
             .handles
-            .get(&handle_id)
+            .get(handle_id)
             .ok_or_else(|| Error::store("fs", "write", format!("Handle {} not found", handle_id)))?
             .encoding;
 
         let content = Self::decode_content(value, encoding)?;
 
-        let handle = self.handles.get_mut(&handle_id).unwrap();
+        let handle = self.handles.get_mut(handle_id).unwrap();
         handle.file.write_all(&content)?;
         handle.position = handle.file.stream_position()?;
         Ok(())
@@ -360,13 +423,13 @@ impl FsStore {
     ) -> Result<(), Error> {
         let encoding = self
             .handles
-            .get(&handle_id)
+            .get(handle_id)
             .ok_or_else(|| Error::store("fs", "write", format!("Handle {} not found", handle_id)))?
             .encoding;
 
         let content = Self::decode_content(value, encoding)?;
 
-        let handle = self.handles.get_mut(&handle_id).unwrap();
+        let handle = self.handles.get_mut(handle_id).unwrap();
         handle.file.seek(SeekFrom::Start(offset))?;
         handle.position = offset;
 
@@ -377,29 +440,74 @@ impl FsStore {
 }
 
 // Meta lens implementation
-impl FsStore {
-    fn read_meta(&self, path: &Path) -> Result<Option<Record>, Error> {
+impl SelfDescribing for FsStore {
+    fn describe(&self, path: &Path) -> Result<Option<Value>, Error> {
         if path.is_empty() {
-            return Ok(Some(Record::parsed(self.meta_root())));
+            return Ok(Some(self.meta_root()));
         }
 
-        match path[0].as_str() {
-            "open" => Ok(Some(Record::parsed(Self::meta_open()))),
-            "handles" => self.read_meta_handles(&path.slice(1, path.len())),
-            "stat" => Ok(Some(Record::parsed(Self::meta_stat()))),
-            "mkdir" => Ok(Some(Record::parsed(Self::meta_mkdir()))),
-            "rmdir" => Ok(Some(Record::parsed(Self::meta_rmdir()))),
-            "unlink" => Ok(Some(Record::parsed(Self::meta_unlink()))),
-            "rename" => Ok(Some(Record::parsed(Self::meta_rename()))),
-            _ => Ok(None),
-        }
+        let action = match path[0].as_str() {
+            "handles" => return self.describe_handles(&path.slice(1, path.len())),
+            "open" => Action::write("open")
+                .accepts("path", Field::string().required())
+                .accepts(
+                    "mode",
+                    Field::string().one_of(["read", "write", "append", "readwrite", "createnew"]),
+                )
+                .accepts(
+                    "encoding",
+                    Field::string().one_of(["base64", "utf8", "bytes"]),
+                )
+                .returns_collection("handle", "handles"),
+            "stat" => Action::write("stat")
+                .accepts("path", Field::string().required())
+                .returns_type("stat"),
+            "mkdir" => Action::write("mkdir")
+                .accepts("path", Field::string().required())
+                .accepts("recursive", Field::boolean()),
+            "rmdir" => Action::write("rmdir").accepts("path", Field::string().required()),
+            "unlink" => Action::write("unlink").accepts("path", Field::string().required()),
+            "rename" => Action::write("rename")
+                .accepts("from", Field::string().required())
+                .accepts("to", Field::string().required()),
+            _ => return Ok(None),
+        };
+        Ok(Some(action.into()))
+    }
+
+    fn docs(&self) -> Option<Value> {
+        Some(
+            Docs::new(
+                "Filesystem Operations",
+                "File and directory operations with handle-based I/O. Archives (tar/zip) \
+                 are created and extracted under fs/archive, and log files are followed \
+                 line by line under fs/tail. meta/ describes what each action accepts.",
+            )
+            .path("write /open {path, mode?, encoding?}", "Open a file, returns handles/{id}")
+            .path("read /handles/{id}", "Read from the current position")
+            .path("write /handles/{id}", "Write at the current position")
+            .path("read /handles/{id}/at/{offset}/len/{n}", "Read n bytes at offset")
+            .path("write /handles/{id}/close null", "Close the handle")
+            .path("write /stat {path}", "File metadata")
+            .path("write /mkdir {path, recursive?}", "Create a directory")
+            .path("write /rmdir {path}", "Remove an empty directory")
+            .path("write /unlink {path}", "Remove a file")
+            .path("write /rename {from, to}", "Move a file or directory")
+            .example([
+                "write /open {\"path\": \"/tmp/notes.txt\", \"mode\": \"write\", \"encoding\": \"utf8\"}",
+                "# Returns: handles/0",
+                "write /handles/0 \"hello\"",
+                "write /handles/0/close null",
+            ])
+            .into(),
+        )
     }
+}
 
+impl FsStore {
     fn meta_root(&self) -> Value {
         Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("store".into()),
-            }),
+            "type".into() => type_value("store"),
             "handles".into() => Reference::with_type("meta/handles", "collection").to_value(),
             "open".into() => Reference::with_type("meta/open", "action").to_value(),
             "stat".into() => Reference::with_type("meta/stat", "action").to_value(),
@@ -410,148 +518,14 @@ impl FsStore {
         })
     }
 
-    fn meta_open() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("open").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-                "mode".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "values".into() => Value::Array(vec![
-                        Value::String("read".into()),
-                        Value::String("write".into()),
-                        Value::String("append".into()),
-                        Value::String("readwrite".into()),
-                        Value::String("createnew".into()),
-                    ]),
-                }),
-                "encoding".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "values".into() => Value::Array(vec![
-                        Value::String("base64".into()),
-                        Value::String("utf8".into()),
-                        Value::String("bytes".into()),
-                    ]),
-                }),
-            }),
-            "returns".into() => Value::Map(btree! {
-                "type".into() => Value::Map(btree! {"name".into() => Value::String("handle".into())}),
-                "collection".into() => Reference::new("handles").to_value(),
-            }),
-        })
-    }
-
-    fn meta_stat() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("stat").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-            }),
-            "returns".into() => Value::Map(btree! {
-                "type".into() => Value::Map(btree! {"name".into() => Value::String("stat".into())}),
-            }),
-        })
-    }
-
-    fn meta_mkdir() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("mkdir").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-                "recursive".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("boolean".into())}),
-                }),
-            }),
-        })
-    }
-
-    fn meta_rmdir() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("rmdir").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-            }),
-        })
-    }
-
-    fn meta_unlink() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("unlink").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "path".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-            }),
-        })
-    }
-
-    fn meta_rename() -> Value {
-        Value::Map(btree! {
-            "type".into() => Value::Map(btree! {
-                "name".into() => Value::String("action".into()),
-            }),
-            "method".into() => Value::String("write".into()),
-            "target".into() => Reference::new("rename").to_value(),
-            "accepts".into() => Value::Map(btree! {
-                "from".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-                "to".into() => Value::Map(btree! {
-                    "type".into() => Value::Map(btree! {"name".into() => Value::String("string".into())}),
-                    "required".into() => Value::Bool(true),
-                }),
-            }),
-        })
-    }
-
-    fn read_meta_handles(&self, path: &Path) -> Result<Option<Record>, Error> {
+    fn describe_handles(&self, path: &Path) -> Result<Option<Value>, Error> {
         if path.is_empty() {
-            let items: Vec<Value> = self
-                .handles
-                .keys()
-                .map(|id| Reference::with_type(format!("meta/handles/{}", id), "handle").to_value())
-                .collect();
-
-            return Ok(Some(Record::parsed(Value::Map(btree! {
-                "type".into() => Value::Map(btree! {
-                    "name".into() => Value::String("collection".into()),
-                }),
+            let items = self.handles.references("meta/handles", "handle");
+
+            return Ok(Some(Value::Map(btree! {
+                "type".into() => type_value("collection"),
                 "items".into() => Value::Array(items),
-            }))));
+            })));
         }
 
         // Parse handle ID
@@ -559,36 +533,40 @@ impl FsStore {
             .parse()
             .map_err(|_| Error::store("fs", "meta", "Invalid handle ID"))?;
 
-        let handle = self
-            .handles
-            .get(&id)
-            .ok_or_else(|| Error::store("fs", "meta", format!("Handle {} not found", id)))?;
+        let Some(handle) = self.handles.get(id) else {
+            return Ok(None);
+        };
 
         if path.len() == 1 {
-            return Ok(Some(Record::parsed(self.meta_handle(id, handle))));
+            return Ok(Some(self.meta_handle(id, handle)));
         }
 
         // Sub-path meta
         match path[1].as_str() {
-            "position" => Ok(Some(Record::parsed(Self::meta_position(handle)))),
-            "meta" => Ok(Some(Record::parsed(Self::meta_handle_meta()))),
-            "at" => Ok(Some(Record::parsed(Self::meta_at()))),
-            "close" => Ok(Some(Record::parsed(Self::meta_close()))),
+            "position" => Ok(Some(Self::meta_position(handle))),
+            "meta" => Ok(Some(Self::meta_handle_meta())),
+            "at" => Ok(Some(Self::meta_at())),
+            "close" => Ok(Some(Self::meta_close())),
             _ => Ok(None),
         }
     }
-
     fn meta_handle(&self, id: u64, handle: &FileHandle) -> Value {
         let meta_prefix = format!("meta/handles/{}", id);
         let data_prefix = format!("handles/{}", id);
 
+        let mut state = match self.handles.status(id) {
+            Some(Value::Map(status)) => status,
+            _ => Default::default(),
+        };
+        state.extend(btree! {
+            "position".into() => Value::Integer(handle.position as i64),
+            "encoding".into() => Value::String(format!("{:?}", handle.encoding)),
+            "mode".into() => Value::String(format!("{:?}", handle.mode)),
+            "file".into() => Value::String(handle.path.clone()),
+        });
+
         Value::Map(btree! {
-            "state".into() => Value::Map(btree! {
-                "position".into() => Value::Integer(handle.position as i64),
-                "encoding".into() => Value::String(format!("{:?}", handle.encoding)),
-                "mode".into() => Value::String(format!("{:?}", handle.mode)),
-                "file".into() => Value::String(handle.path.clone()),
-            }),
+            "state".into() => Value::Map(state),
             "position".into() => Reference::with_type(format!("{}/position", meta_prefix), "integer").to_value(),
             "encoding".into() => Reference::with_type(format!("{}/encoding", meta_prefix), "string").to_value(),
             "close".into() => Reference::with_type(format!("{}/close", meta_prefix), "action").to_value(),
@@ -653,7 +631,7 @@ impl FsStore {
                     }
                 };
 
-                let handle = self.handles.get_mut(&id).ok_or_else(|| {
+                let handle = self.handles.get_mut(id).ok_or_else(|| {
                     Error::store("fs", "meta", format!("Handle {} not found", id))
                 })?;
 
@@ -680,15 +658,15 @@ impl Default for FsStore {
 
 impl Reader for FsStore {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
-        // Check for meta prefix
-        if !from.is_empty() && from[0] == "meta" {
-            let rest = from.slice(1, from.len());
-            return self.read_meta(&rest);
+        OpContext::check_current("fs")?;
+
+        if let Some(result) = read_lens(self, from) {
+            return result;
         }
 
         // Handle /handles listing
         if from.len() == 1 && from[0] == "handles" {
-            return Ok(Some(Record::parsed(self.read_handles_listing())));
+            return Ok(Some(Record::parsed(self.handles.listing())));
         }
 
         // Handle operations on specific handles
@@ -697,6 +675,11 @@ impl Reader for FsStore {
                 Error::store("fs", "read", format!("Invalid handle path: {}", from))
             })?;
 
+            // A closed or never-opened handle does not exist
+            if self.handles.touch(handle_id).is_none() {
+                return Ok(None);
+            }
+
             let value = match op {
                 HandleOperation::ReadToEnd => self.read_from_position(handle_id)?,
                 HandleOperation::AtOffset { offset } => self.read_at_offset(handle_id, offset)?,
@@ -704,13 +687,13 @@ impl Reader for FsStore {
                     self.read_at_offset_len(handle_id, offset, length)?
                 }
                 HandleOperation::Position => {
-                    let handle = self.handles.get(&handle_id).ok_or_else(|| {
+                    let handle = self.handles.get(handle_id).ok_or_else(|| {
                         Error::store("fs", "read", format!("Handle {} not found", handle_id))
                     })?;
                     self.read_handle_position(handle)
                 }
                 HandleOperation::Meta => {
-                    let handle = self.handles.get(&handle_id).ok_or_else(|| {
+                    let handle = self.handles.get(handle_id).ok_or_else(|| {
                         Error::store("fs", "read", format!("Handle {} not found", handle_id))
                     })?;
                     self.read_handle_meta(handle)?
@@ -729,10 +712,55 @@ impl Reader for FsStore {
 
         Ok(self.read_value(from)?.map(Record::parsed))
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        Some(self)
+    }
+}
+
+/// Lists the store's own tree: the entries at the root, the open handles,
+/// the operations on a handle, and the `meta/` descriptions. Listing never
+/// reads file content.
+impl Lister for FsStore {
+    fn list(&mut self, path: &Path) -> Result<Option<Vec<String>>, Error> {
+        OpContext::check_current("fs")?;
+
+        if path.is_empty() || path[0] == "meta" {
+            let value = match read_lens(self, path) {
+                Some(result) => result?.map(|r| r.into_value(&NoCodec)).transpose()?,
+                None => self.read_value(path)?,
+            };
+            return Ok(value.map(|value| match value {
+                Value::Map(map) => map.into_keys().collect(),
+                _ => Vec::new(),
+            }));
+        }
+        if path[0] != "handles" {
+            return Ok(None);
+        }
+
+        match path.len() {
+            1 => {
+                self.handles.sweep();
+                Ok(Some(self.handles.ids().map(|id| id.to_string()).collect()))
+            }
+            2 => match path[1].parse::<u64>() {
+                Ok(id) if self.handles.contains(id) => Ok(Some(
+                    ["at", "close", "meta", "position"]
+                        .map(String::from)
+                        .to_vec(),
+                )),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
 }
 
 impl Writer for FsStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        OpContext::check_current("fs")?;
+
         if to.is_empty() {
             return Err(Error::store("fs", "write", "Cannot write to fs root"));
         }
@@ -765,45 +793,42 @@ impl Writer for FsStore {
 
                 let mode = Self::parse_open_mode(&value);
                 let encoding = Self::parse_encoding(&value);
+                let disk_path = self.resolve(&file_path)?;
 
                 let file = match mode {
-                    OpenMode::Read => File::open(&file_path),
-                    OpenMode::Write => File::create(&file_path),
+                    OpenMode::Read => File::open(&disk_path),
+                    OpenMode::Write => File::create(&disk_path),
                     OpenMode::Append => OpenOptions::new()
                         .append(true)
                         .create(true)
-                        .open(&file_path),
+                        .open(&disk_path),
                     OpenMode::ReadWrite => OpenOptions::new()
                         .read(true)
                         .write(true)
                         .create(true)
                         .truncate(false)
-                        .open(&file_path),
+                        .open(&disk_path),
                     OpenMode::CreateNew => OpenOptions::new()
                         .write(true)
                         .create_new(true)
-                        .open(&file_path),
+                        .open(&disk_path),
                 }?;
 
-                let handle_id = Self::next_handle_id();
-                self.handles.insert(
-                    handle_id,
-                    FileHandle {
-                        file,
-                        path: file_path,
-                        mode,
-                        position: 0,
-                        encoding,
-                    },
-                );
-
-                Ok(Path::parse(&format!("handles/{}", handle_id)).unwrap())
+                let handle_id = self.handles.insert(FileHandle {
+                    file,
+                    path: file_path,
+                    mode,
+                    position: 0,
+                    encoding,
+                });
+
+                Ok(self.handles.path(handle_id))
             }
             "stat" => {
                 let file_path = Self::get_path_from_value(&value)
                     .ok_or_else(|| Error::store("fs", "stat", "stat requires 'path' field"))?;
 
-                let _metadata = fs::metadata(&file_path)?;
+                let _metadata = fs::metadata(self.resolve(&file_path)?)?;
 
                 Ok(to.clone())
             }
@@ -818,9 +843,9 @@ impl Writer for FsStore {
                 };
 
                 if recursive {
-                    fs::create_dir_all(&file_path)?;
+                    fs::create_dir_all(self.resolve(&file_path)?)?;
                 } else {
-                    fs::create_dir(&file_path)?;
+                    fs::create_dir(self.resolve(&file_path)?)?;
                 }
 
                 Ok(to.clone())
@@ -829,7 +854,7 @@ impl Writer for FsStore {
                 let file_path = Self::get_path_from_value(&value)
                     .ok_or_else(|| Error::store("fs", "rmdir", "rmdir requires 'path' field"))?;
 
-                fs::remove_dir(&file_path)?;
+                fs::remove_dir(self.resolve(&file_path)?)?;
 
                 Ok(to.clone())
             }
@@ -837,7 +862,7 @@ impl Writer for FsStore {
                 let file_path = Self::get_path_from_value(&value)
                     .ok_or_else(|| Error::store("fs", "unlink", "unlink requires 'path' field"))?;
 
-                fs::remove_file(&file_path)?;
+                fs::remove_file(self.resolve(&file_path)?)?;
 
                 Ok(to.clone())
             }
@@ -876,7 +901,7 @@ impl Writer for FsStore {
                     ));
                 };
 
-                fs::rename(&from_path, &to_path)?;
+                fs::rename(self.resolve(&from_path)?, self.resolve(&to_path)?)?;
 
                 Ok(to.clone())
             }
@@ -894,7 +919,7 @@ mod tests {
     use super::*;
     use std::collections::BTreeMap;
     use std::io::Write;
-    use structfs_core_store::path;
+    use structfs_core_store::{conformance, path};
     use tempfile::{NamedTempFile, TempDir};
 
     #[test]
@@ -947,6 +972,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unused_handles_expire() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut store = FsStore::new().with_handle_ttl(Duration::from_millis(20));
+        let mut open_map = BTreeMap::new();
+        open_map.insert(
+            "path".to_string(),
+            Value::String(temp.path().to_string_lossy().to_string()),
+        );
+        let handle_path = store
+            .write(&path!("open"), Record::parsed(Value::Map(open_map)))
+            .unwrap();
+        assert_eq!(handle_path, path!("handles/0"));
+        assert!(store.read(&path!("handles/0/position")).unwrap().is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(store.read(&path!("handles/0/position")).unwrap().is_none());
+    }
+
+    #[test]
+    fn expired_context_fails_before_io() {
+        let mut store = FsStore::new();
+        let ctx = OpContext::new().with_deadline(std::time::Instant::now());
+        let err = store
+            .read_with_context(&ctx, &path!("handles"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "fs: deadline exceeded");
+        assert!(store.read(&path!("handles")).unwrap().is_some());
+    }
+
     #[test]
     fn read_root() {
         let mut store = FsStore::new();
@@ -1239,10 +1294,62 @@ mod tests {
     }
 
     #[test]
-    fn handle_not_found_error() {
+    fn conforms_to_store_contract() {
+        let mut temp = NamedTempFile::new().unwrap();
+        writeln!(temp, "data").unwrap();
+        let mut store = FsStore::new();
+
+        conformance::assert_missing(&mut store, &path!("handles/7"));
+        conformance::assert_missing(&mut store, &path!("handles/7/position"));
+        conformance::assert_missing(&mut store, &path!("meta/handles/7"));
+
+        let open = Value::Map(BTreeMap::from([
+            (
+                "path".to_string(),
+                Value::String(temp.path().to_string_lossy().to_string()),
+            ),
+            ("mode".to_string(), Value::String("read".to_string())),
+        ]));
+        let handle = store.write(&path!("open"), Record::parsed(open)).unwrap();
+        conformance::assert_found(&mut store, &handle.join(&path!("position")));
+
+        store
+            .write(&handle.join(&path!("close")), Record::parsed(Value::Null))
+            .unwrap();
+        conformance::assert_missing(&mut store, &handle);
+    }
+
+    #[test]
+    fn list_root_and_handles() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut store = FsStore::new();
+        let open = Value::Map(BTreeMap::from([(
+            "path".to_string(),
+            Value::String(temp.path().to_string_lossy().to_string()),
+        )]));
+        let handle = store.write(&path!("open"), Record::parsed(open)).unwrap();
+
+        let store: &mut dyn structfs_core_store::Store = &mut store;
+        let root = store.list(&path!("")).unwrap().unwrap();
+        assert!(root.contains(&"handles".to_string()), "{:?}", root);
+        assert_eq!(
+            store.list(&path!("handles")).unwrap().unwrap(),
+            [handle[1].clone()]
+        );
+        assert_eq!(
+            store.list(&handle).unwrap().unwrap(),
+            ["at", "close", "meta", "position"]
+        );
+        assert!(!store.list(&path!("meta")).unwrap().unwrap().is_empty());
+        assert_eq!(store.list(&path!("handles/999")).unwrap(), None);
+        assert_eq!(store.list(&path!("nothing")).unwrap(), None);
+    }
+
+    #[test]
+    fn handle_not_found_is_none() {
         let mut store = FsStore::new();
         let result = store.read(&path!("handles/999999"));
-        assert!(result.is_err());
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
@@ -2291,10 +2398,10 @@ mod tests {
     }
 
     #[test]
-    fn meta_handle_not_found_error() {
+    fn meta_handle_not_found_is_none() {
         let mut store = FsStore::new();
         let result = store.read(&path!("meta/handles/999999"));
-        assert!(result.is_err());
+        assert!(result.unwrap().is_none());
     }
 
     #[test]
@@ -2415,4 +2522,65 @@ mod tests {
             _ => panic!("Expected map"),
         }
     }
+
+    fn open_request(path: &str, mode: &str) -> Record {
+        Record::parsed(Value::Map(btree! {
+            "path".into() => Value::String(path.into()),
+            "mode".into() => Value::String(mode.into()),
+            "encoding".into() => Value::String("utf8".into()),
+        }))
+    }
+
+    #[test]
+    fn chrooted_paths_resolve_under_root() {
+        let dir = TempDir::new().unwrap();
+        let mut store = FsStore::chrooted(dir.path()).unwrap();
+
+        let handle = store
+            .write(&path!("open"), open_request("/notes.txt", "write"))
+            .unwrap();
+        store
+            .write(&handle, Record::parsed(Value::String("kept".into())))
+            .unwrap();
+        store
+            .write(&handle.join(&path!("close")), Record::parsed(Value::Null))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "kept"
+        );
+
+        // The handle reports the path as given, not the path on disk
+        let handle = store
+            .write(&path!("open"), open_request("sub/../notes.txt", "read"))
+            .unwrap();
+        let meta = store.read(&handle.join(&path!("meta"))).unwrap().unwrap();
+        let Value::Map(meta) = meta.into_value(&NoCodec).unwrap() else {
+            panic!("expected map");
+        };
+        assert_eq!(meta["path"], Value::String("sub/../notes.txt".into()));
+    }
+
+    #[test]
+    fn chrooted_rejects_escapes() {
+        let outer = TempDir::new().unwrap();
+        let root = outer.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(outer.path().join("secret"), "hidden").unwrap();
+        let mut store = FsStore::chrooted(&root).unwrap();
+
+        let err = store
+            .write(&path!("open"), open_request("../secret", "read"))
+            .unwrap_err();
+        assert!(err.to_string().contains("outside the store root"));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outer.path(), root.join("link")).unwrap();
+            let err = store
+                .write(&path!("open"), open_request("link/secret", "read"))
+                .unwrap_err();
+            assert!(err.to_string().contains("outside the store root"));
+        }
+    }
 }
@@ -0,0 +1,454 @@
+//! Hardware inventory store (read-only).
+//!
+//! ```text
+//! read hw                       -> {cpu, gpus, disks, nics}
+//! read hw/cpu                   -> {model, vendor, arch, cores, threads, features}
+//! read hw/cpu/features          -> ["avx2", "sse4_2", ...]
+//! read hw/gpus                  -> {card0: {vendor, vendor_id, device_id, driver, memory_bytes}}
+//! read hw/disks                 -> {sda: {model, capacity_bytes, rotational, removable}}
+//! read hw/nics                  -> {eth0: {mac, mtu, speed_mbps, up, driver}}
+//! read hw/disks/sda/capacity_bytes -> 512110190592
+//! ```
+//!
+//! The inventory is read from `/proc/cpuinfo` and `/sys` each time, so it
+//! follows hot-plugged devices. Fields the host does not report, such as the
+//! speed of a virtual NIC or the memory of a GPU whose driver does not
+//! publish it, are `null`. On systems without `/proc` and `/sys` the CPU
+//! reports only its architecture and thread count, and the device maps are
+//! empty.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path as FsPath, PathBuf};
+
+use collection_literals::btree;
+
+use structfs_core_store::{Error, Path, Reader, Record, Value, Writer};
+
+/// Store describing the host's hardware.
+pub struct HwStore {
+    /// Where `proc` and `sys` are found; `/` except in tests.
+    root: PathBuf,
+}
+
+impl HwStore {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    /// Read the inventory from `{root}/proc` and `{root}/sys` instead.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn read_value(&self, path: &Path) -> Option<Value> {
+        if path.is_empty() {
+            return Some(Value::Map(btree! {
+                "cpu".into() => self.cpu(),
+                "gpus".into() => self.gpus(),
+                "disks".into() => self.disks(),
+                "nics".into() => self.nics(),
+            }));
+        }
+        let section = match path[0].as_str() {
+            "cpu" => self.cpu(),
+            "gpus" => self.gpus(),
+            "disks" => self.disks(),
+            "nics" => self.nics(),
+            _ => return None,
+        };
+        section.get(&path.slice(1, path.len())).cloned()
+    }
+
+    fn text(&self, path: impl AsRef<FsPath>) -> Option<String> {
+        let text = fs::read_to_string(self.root.join(path)).ok()?;
+        Some(text.trim().to_string())
+    }
+
+    fn integer(&self, path: impl AsRef<FsPath>) -> Value {
+        self.text(path)
+            .and_then(|text| text.parse::<i64>().ok())
+            .map_or(Value::Null, Value::Integer)
+    }
+
+    /// The entries of a `/sys` directory, sorted by name.
+    fn entries(&self, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.root.join(dir))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// The name of the file a `/sys` symlink points to, such as a driver.
+    fn link_name(&self, path: impl AsRef<FsPath>) -> Value {
+        fs::read_link(self.root.join(path))
+            .ok()
+            .and_then(|target| Some(target.file_name()?.to_string_lossy().into_owned()))
+            .map_or(Value::Null, Value::String)
+    }
+
+    fn cpu(&self) -> Value {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()) as i64;
+        let mut cpu = match self.text("proc/cpuinfo") {
+            Some(cpuinfo) => parse_cpuinfo(&cpuinfo),
+            None => btree! {
+                "model".into() => Value::Null,
+                "vendor".into() => Value::Null,
+                "cores".into() => Value::Integer(threads),
+                "threads".into() => Value::Integer(threads),
+                "features".into() => Value::Array(Vec::new()),
+            },
+        };
+        cpu.insert("arch".into(), Value::from(std::env::consts::ARCH));
+        Value::Map(cpu)
+    }
+
+    fn gpus(&self) -> Value {
+        let mut gpus = BTreeMap::new();
+        for card in self.entries("sys/class/drm") {
+            // card0 is a GPU; card0-HDMI-A-1 is one of its connectors
+            if !card.starts_with("card") || card.contains('-') {
+                continue;
+            }
+            let device = format!("sys/class/drm/{}/device", card);
+            let vendor_id = self.text(format!("{}/vendor", device));
+            let memory = match self.integer(format!("{}/mem_info_vram_total", device)) {
+                Value::Null => self.nvidia_memory(&device),
+                memory => memory,
+            };
+            gpus.insert(
+                card,
+                Value::Map(btree! {
+                    "vendor".into() => vendor_id
+                        .as_deref()
+                        .and_then(pci_vendor)
+                        .map_or(Value::Null, Value::from),
+                    "vendor_id".into() => vendor_id.map_or(Value::Null, Value::String),
+                    "device_id".into() => self
+                        .text(format!("{}/device", device))
+                        .map_or(Value::Null, Value::String),
+                    "driver".into() => self.link_name(format!("{}/driver", device)),
+                    "memory_bytes".into() => memory,
+                }),
+            );
+        }
+        Value::Map(gpus)
+    }
+
+    /// The memory of an NVIDIA GPU, which the proprietary driver reports
+    /// under `/proc/driver/nvidia` rather than in `/sys`.
+    fn nvidia_memory(&self, device: &str) -> Value {
+        let Some(uevent) = self.text(format!("{}/uevent", device)) else {
+            return Value::Null;
+        };
+        let Some(slot) = uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))
+        else {
+            return Value::Null;
+        };
+        let information = self
+            .text(format!("proc/driver/nvidia/gpus/{}/information", slot))
+            .unwrap_or_default();
+        information
+            .lines()
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                if key.trim() != "Video Memory" {
+                    return None;
+                }
+                let mb: i64 = value.trim().strip_suffix("MB")?.trim().parse().ok()?;
+                Some(Value::Integer(mb * 1024 * 1024))
+            })
+            .unwrap_or(Value::Null)
+    }
+
+    fn disks(&self) -> Value {
+        let mut disks = BTreeMap::new();
+        for name in self.entries("sys/block") {
+            // Loop and RAM devices are not hardware
+            if name.starts_with("loop") || name.starts_with("ram") {
+                continue;
+            }
+            let block = format!("sys/block/{}", name);
+            let flag = |file: &str| match self.text(format!("{}/{}", block, file)).as_deref() {
+                Some("1") => Value::Bool(true),
+                Some("0") => Value::Bool(false),
+                _ => Value::Null,
+            };
+            disks.insert(
+                name.clone(),
+                Value::Map(btree! {
+                    "model".into() => self
+                        .text(format!("{}/device/model", block))
+                        .map_or(Value::Null, Value::String),
+                    // The size file counts 512-byte sectors whatever the
+                    // device's own sector size
+                    "capacity_bytes".into() => match self.integer(format!("{}/size", block)) {
+                        Value::Integer(sectors) => Value::Integer(sectors * 512),
+                        other => other,
+                    },
+                    "rotational".into() => flag("queue/rotational"),
+                    "removable".into() => flag("removable"),
+                }),
+            );
+        }
+        Value::Map(disks)
+    }
+
+    fn nics(&self) -> Value {
+        let mut nics = BTreeMap::new();
+        for name in self.entries("sys/class/net") {
+            let net = format!("sys/class/net/{}", name);
+            // Virtual interfaces such as lo and bridges have no device
+            if !self.root.join(&net).join("device").exists() {
+                continue;
+            }
+            nics.insert(
+                name.clone(),
+                Value::Map(btree! {
+                    "mac".into() => self
+                        .text(format!("{}/address", net))
+                        .map_or(Value::Null, Value::String),
+                    "mtu".into() => self.integer(format!("{}/mtu", net)),
+                    // Negative when the link is down
+                    "speed_mbps".into() => match self.integer(format!("{}/speed", net)) {
+                        Value::Integer(speed) if speed > 0 => Value::Integer(speed),
+                        _ => Value::Null,
+                    },
+                    "up".into() => Value::Bool(
+                        self.text(format!("{}/operstate", net)).as_deref() == Some("up"),
+                    ),
+                    "driver".into() => self.link_name(format!("{}/device/driver", net)),
+                }),
+            );
+        }
+        Value::Map(nics)
+    }
+}
+
+/// The CPU fields of `/proc/cpuinfo`: model, vendor, cores, threads and
+/// features.
+fn parse_cpuinfo(cpuinfo: &str) -> BTreeMap<String, Value> {
+    let mut model = None;
+    let mut vendor = None;
+    let mut features = BTreeSet::new();
+    let mut threads = 0;
+    // (physical id, core id) of each thread, where the kernel reports them
+    let mut cores = BTreeSet::new();
+    let mut physical_id = None;
+
+    for line in cpuinfo.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "processor" => {
+                threads += 1;
+                physical_id = None;
+            }
+            // x86 and ARM name these differently
+            "model name" | "Model" | "cpu model" => {
+                model.get_or_insert_with(|| value.to_string());
+            }
+            "vendor_id" | "CPU implementer" => {
+                vendor.get_or_insert_with(|| value.to_string());
+            }
+            "flags" | "Features" => {
+                features.extend(value.split_whitespace().map(str::to_string));
+            }
+            "physical id" => physical_id = Some(value.to_string()),
+            "core id" => {
+                cores.insert((physical_id.clone(), value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    let threads = threads.max(1);
+    let cores = if cores.is_empty() {
+        threads
+    } else {
+        cores.len()
+    };
+    btree! {
+        "model".into() => model.map_or(Value::Null, Value::String),
+        "vendor".into() => vendor.map_or(Value::Null, Value::String),
+        "cores".into() => Value::Integer(cores as i64),
+        "threads".into() => Value::Integer(threads as i64),
+        "features".into() => Value::Array(features.into_iter().map(Value::String).collect()),
+    }
+}
+
+/// The name of a PCI vendor id as written in `/sys`, for the vendors that
+/// make GPUs.
+fn pci_vendor(id: &str) -> Option<&'static str> {
+    match id {
+        "0x10de" => Some("NVIDIA"),
+        "0x1002" => Some("AMD"),
+        "0x8086" => Some("Intel"),
+        "0x13b5" => Some("ARM"),
+        "0x5143" => Some("Qualcomm"),
+        "0x1af4" => Some("Virtio"),
+        _ => None,
+    }
+}
+
+impl Default for HwStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for HwStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from).map(Record::parsed))
+    }
+}
+
+impl Writer for HwStore {
+    fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+        Err(Error::store(
+            "hw",
+            "write",
+            format!("Cannot write to hw/{}: hardware inventory is read-only", to),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::{path, NoCodec};
+
+    fn read(store: &mut HwStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|record| record.into_value(&NoCodec).unwrap())
+    }
+
+    fn put(root: &FsPath, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn parse_x86_cpuinfo() {
+        let cpuinfo = "\
+processor\t: 0
+vendor_id\t: GenuineIntel
+model name\t: Intel(R) Xeon(R) CPU
+physical id\t: 0
+core id\t\t: 0
+flags\t\t: fpu sse4_2 avx2
+
+processor\t: 1
+vendor_id\t: GenuineIntel
+model name\t: Intel(R) Xeon(R) CPU
+physical id\t: 0
+core id\t\t: 0
+flags\t\t: fpu sse4_2 avx2
+";
+        let cpu = parse_cpuinfo(cpuinfo);
+        assert_eq!(cpu["model"], Value::from("Intel(R) Xeon(R) CPU"));
+        assert_eq!(cpu["vendor"], Value::from("GenuineIntel"));
+        assert_eq!(cpu["threads"], Value::Integer(2));
+        assert_eq!(cpu["cores"], Value::Integer(1));
+        assert_eq!(
+            cpu["features"],
+            Value::Array(vec!["avx2".into(), "fpu".into(), "sse4_2".into()])
+        );
+    }
+
+    #[test]
+    fn inventory_is_read_from_sysfs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        put(root, "proc/cpuinfo", "processor : 0\nFeatures : neon aes\n");
+        put(root, "sys/class/drm/card0/device/vendor", "0x10de\n");
+        put(root, "sys/class/drm/card0/device/device", "0x2204\n");
+        put(root, "sys/class/drm/card0-HDMI-A-1/status", "connected\n");
+        put(root, "sys/block/sda/size", "1000\n");
+        put(root, "sys/block/sda/queue/rotational", "0\n");
+        put(root, "sys/block/loop0/size", "8\n");
+        put(root, "sys/class/net/eth0/address", "52:54:00:12:34:56\n");
+        put(root, "sys/class/net/eth0/mtu", "1500\n");
+        put(root, "sys/class/net/eth0/speed", "-1\n");
+        put(root, "sys/class/net/eth0/operstate", "up\n");
+        fs::create_dir_all(root.join("sys/class/net/eth0/device")).unwrap();
+        fs::create_dir_all(root.join("sys/class/net/lo")).unwrap();
+        let mut store = HwStore::with_root(root);
+
+        assert_eq!(
+            read(&mut store, &path!("cpu/features")),
+            Some(Value::Array(vec!["aes".into(), "neon".into()]))
+        );
+        let Some(Value::Map(gpus)) = read(&mut store, &path!("gpus")) else {
+            panic!("Expected map");
+        };
+        assert_eq!(gpus.keys().collect::<Vec<_>>(), ["card0"]);
+        assert_eq!(
+            read(&mut store, &path!("gpus/card0/vendor")),
+            Some(Value::from("NVIDIA"))
+        );
+        assert_eq!(
+            read(&mut store, &path!("disks")),
+            Some(Value::Map(btree! {
+                "sda".into() => Value::Map(btree! {
+                    "model".into() => Value::Null,
+                    "capacity_bytes".into() => Value::Integer(512_000),
+                    "rotational".into() => Value::Bool(false),
+                    "removable".into() => Value::Null,
+                }),
+            }))
+        );
+        assert_eq!(
+            read(&mut store, &path!("nics/eth0/speed_mbps")),
+            Some(Value::Null)
+        );
+        assert_eq!(read(&mut store, &path!("nics/lo")), None);
+        assert_eq!(
+            read(&mut store, &path!("nics/eth0/up")),
+            Some(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn host_inventory_and_read_only() {
+        let mut store = HwStore::new();
+        let Some(Value::Map(inventory)) = read(&mut store, &path!("")) else {
+            panic!("Expected map");
+        };
+        assert_eq!(
+            inventory.keys().collect::<Vec<_>>(),
+            ["cpu", "disks", "gpus", "nics"]
+        );
+        assert_eq!(
+            read(&mut store, &path!("cpu/arch")),
+            Some(Value::from(std::env::consts::ARCH))
+        );
+        assert_eq!(read(&mut store, &path!("memory")), None);
+        assert!(store
+            .write(&path!("cpu"), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = HwStore::new();
+        conformance::assert_found(&mut store, &path!("cpu"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+        conformance::assert_missing(&mut store, &path!("cpu/nope"));
+    }
+}
@@ -11,28 +11,49 @@
 //! ```text
 //! /sys/
 //!     env/          # Environment variables
+//!       watch/      # Change polling for variables
 //!     time/         # Clocks and sleep
+//!       cron/       # Cron expression schedules
 //!     random/       # Random number generation
 //!     proc/         # Process information
 //!     fs/           # Filesystem operations
+//!       archive/    # tar/zip create and extract
+//!       tail/       # Follow appended lines of log files
+//!     url/          # URL parsing and building
+//!     user/         # User and group information
+//!     hw/           # Hardware inventory (read-only)
 //!     docs/         # Documentation for this store
 //! ```
 
+mod archive;
+mod cron;
 mod docs;
 mod env;
 mod fs;
+mod hw;
 mod proc;
 mod random;
+mod tail;
 mod time;
+mod url;
+mod user;
 
+pub use archive::{ArchiveFormat, ArchiveStore};
+pub use cron::{CronSchedule, CronStore};
 pub use docs::DocsStore;
 pub use env::EnvStore;
 pub use fs::{FsStore, OpenMode};
+pub use hw::HwStore;
 pub use proc::ProcStore;
 pub use random::RandomStore;
+pub use tail::TailStore;
 pub use time::TimeStore;
+pub use url::UrlStore;
+pub use user::UserStore;
 
-use structfs_core_store::{overlay_store::OverlayStore, Error, Path, Reader, Record, Writer};
+use structfs_core_store::{
+    overlay_store::OverlayStore, Deleter, Error, Lister, Path, Reader, Record, Writer,
+};
 
 /// The main system store that composes all OS primitive stores.
 ///
@@ -48,13 +69,37 @@ impl SysStore {
 
         overlay.mount(Path::parse("env").unwrap(), Box::new(EnvStore::new()));
         overlay.mount(Path::parse("time").unwrap(), Box::new(TimeStore::new()));
+        overlay.mount(
+            Path::parse("time/cron").unwrap(),
+            Box::new(CronStore::new()),
+        );
         overlay.mount(Path::parse("random").unwrap(), Box::new(RandomStore::new()));
         overlay.mount(Path::parse("proc").unwrap(), Box::new(ProcStore::new()));
-        overlay.mount(Path::parse("fs").unwrap(), Box::new(FsStore::new()));
+        overlay.mount_described(Path::parse("fs").unwrap(), FsStore::new());
+        overlay.mount(
+            Path::parse("fs/archive").unwrap(),
+            Box::new(ArchiveStore::new()),
+        );
+        overlay.mount(Path::parse("fs/tail").unwrap(), Box::new(TailStore::new()));
+        overlay.mount(Path::parse("url").unwrap(), Box::new(UrlStore::new()));
+        overlay.mount(Path::parse("user").unwrap(), Box::new(UserStore::new()));
+        overlay.mount(Path::parse("hw").unwrap(), Box::new(HwStore::new()));
         overlay.mount(Path::parse("docs").unwrap(), Box::new(DocsStore::new()));
 
         Self { inner: overlay }
     }
+
+    /// Create a system store whose exit hooks write through `writer`.
+    ///
+    /// See [`ProcStore::with_hook_writer`].
+    pub fn with_hook_writer(writer: Box<dyn Writer + Send + Sync>) -> Self {
+        let mut store = Self::new();
+        store.inner.mount(
+            Path::parse("proc").unwrap(),
+            Box::new(ProcStore::new().with_hook_writer(writer)),
+        );
+        store
+    }
 }
 
 impl Default for SysStore {
@@ -67,12 +112,20 @@ impl Reader for SysStore {
     fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
         self.inner.read(from)
     }
+
+    fn as_lister(&mut self) -> Option<&mut dyn Lister> {
+        self.inner.as_lister()
+    }
 }
 
 impl Writer for SysStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
         self.inner.write(to, data)
     }
+
+    fn as_deleter(&mut self) -> Option<&mut dyn Deleter> {
+        self.inner.as_deleter()
+    }
 }
 
 #[cfg(test)]
@@ -80,6 +133,22 @@ mod tests {
     use super::*;
     use structfs_core_store::{path, NoCodec, Value};
 
+    #[test]
+    fn sys_store_lists_and_deletes_through_mounts() {
+        use structfs_core_store::Store;
+
+        let mut store = SysStore::new();
+        let root = store.list(&path!("")).unwrap().unwrap();
+        assert!(root.contains(&"env".to_string()), "{:?}", root);
+        assert!(store.list(&path!("fs/handles")).unwrap().is_some());
+
+        std::env::set_var("STRUCTFS_SYS_DELETE_TEST", "x");
+        store
+            .delete(&path!("env/STRUCTFS_SYS_DELETE_TEST"))
+            .unwrap();
+        assert!(std::env::var("STRUCTFS_SYS_DELETE_TEST").is_err());
+    }
+
     #[test]
     fn sys_store_read_env() {
         std::env::set_var("STRUCTFS_SYS_TEST", "value");
@@ -104,6 +173,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sys_store_routes_time_cron() {
+        let mut store = SysStore::new();
+        let handle = store
+            .write(
+                &path!("time/cron"),
+                Record::parsed(Value::String("0 */5 * * * *".into())),
+            )
+            .unwrap();
+        assert_eq!(handle, path!("time/cron/crons/0"));
+        assert!(store.read(&handle.join(&path!("next"))).unwrap().is_some());
+        // time itself still answers
+        assert!(store.read(&path!("time/now")).unwrap().is_some());
+    }
+
+    #[test]
+    fn sys_store_meta_lists_fs() {
+        let mut store = SysStore::new();
+        let record = store.read(&path!("meta")).unwrap().unwrap();
+        let Value::Map(index) = record.into_value(&NoCodec).unwrap() else {
+            panic!("Expected map");
+        };
+        assert!(index.contains_key("fs"));
+
+        let record = store.read(&path!("meta/fs/open")).unwrap().unwrap();
+        let Value::Map(open) = record.into_value(&NoCodec).unwrap() else {
+            panic!("Expected map");
+        };
+        assert!(open.contains_key("accepts"));
+    }
+
     #[test]
     fn sys_store_read_random() {
         let mut store = SysStore::new();
@@ -126,6 +226,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sys_store_url_parse() {
+        let mut store = SysStore::new();
+        store
+            .write(
+                &path!("url/parse"),
+                Record::parsed(Value::String("https://example.com/x".into())),
+            )
+            .unwrap();
+        let record = store.read(&path!("url/parse")).unwrap().unwrap();
+        match record.into_value(&NoCodec).unwrap() {
+            Value::Map(map) => assert_eq!(map["host"], Value::String("example.com".into())),
+            _ => panic!("Expected map"),
+        }
+    }
+
+    #[test]
+    fn sys_store_routes_fs_archive() {
+        let mut store = SysStore::new();
+        let record = store.read(&path!("fs/archive")).unwrap().unwrap();
+        match record.into_value(&NoCodec).unwrap() {
+            Value::Map(map) => assert!(map.contains_key("create")),
+            _ => panic!("Expected map"),
+        }
+        // fs itself still answers
+        assert!(store.read(&path!("fs/handles")).unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sys_store_read_user() {
+        let mut store = SysStore::new();
+        let record = store.read(&path!("user/current/uid")).unwrap().unwrap();
+        assert!(matches!(
+            record.into_value(&NoCodec).unwrap(),
+            Value::Integer(_)
+        ));
+    }
+
     #[test]
     fn sys_store_read_docs() {
         let mut store = SysStore::new();
@@ -169,4 +308,20 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = SysStore::new();
+        conformance::assert_found(&mut store, &path!("time/now"));
+        conformance::assert_missing(&mut store, &path!("time/nope"));
+        conformance::assert_missing(&mut store, &path!("fs/archive/handles/7"));
+        conformance::assert_missing(&mut store, &path!("time/cron/crons/7"));
+        conformance::assert_deletes(
+            &mut store,
+            &path!("env/STRUCTFS_SYS_CONFORMANCE_TEST"),
+            Value::String("x".into()),
+        );
+    }
 }
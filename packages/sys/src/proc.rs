@@ -1,16 +1,172 @@
 //! Process information store.
+//!
+//! ## Exit
+//!
+//! Writing to `self/exit` ends the process gracefully: registered exit hooks
+//! run, stdout and stderr are flushed, then the process exits with the given
+//! code.
+//!
+//! ```text
+//! write proc/self/on_exit {"path": "cache/flush", "data": null}
+//!   -> proc/self/on_exit/0
+//! write proc/self/exit {"code": 0}
+//! ```
+//!
+//! An exit hook is a write: `data` (null if omitted) is written to `path`
+//! through the hook writer given to [`ProcStore::with_hook_writer`], usually
+//! the root store, so hooks can reach any mount. Hooks run in reverse order
+//! of registration, like `atexit`. A failing hook does not stop the others;
+//! its path and error are recorded under `proc/self/on_exit/failed`. Without
+//! a hook writer, registering a hook is an error.
+//!
+//! `read proc/self/on_exit` lists the registered hooks by id, and writing
+//! null to `proc/self/on_exit/{id}` removes one.
 
 use collection_literals::btree;
 use std::collections::BTreeMap;
+use std::io::Write as _;
 
 use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
 
+/// A hook to run at exit.
+struct ExitHook {
+    path: Path,
+    data: Value,
+}
+
+impl ExitHook {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        let invalid = || {
+            Error::store(
+                "proc",
+                "on_exit",
+                "exit hook must be a map with a string 'path' and optional 'data'",
+            )
+        };
+        let Value::Map(mut map) = value else {
+            return Err(invalid());
+        };
+        let path = match map.remove("path") {
+            Some(Value::String(path)) => Path::parse(&path)?,
+            _ => return Err(invalid()),
+        };
+        let data = map.remove("data").unwrap_or(Value::Null);
+        Ok(Self { path, data })
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Map(btree! {
+            "path".into() => Value::String(self.path.to_string()),
+            "data".into() => self.data.clone(),
+        })
+    }
+}
+
 /// Store for process information.
-pub struct ProcStore;
+pub struct ProcStore {
+    hook_writer: Option<Box<dyn Writer + Send + Sync>>,
+    hooks: BTreeMap<u64, ExitHook>,
+    next_hook: u64,
+    /// Hooks that failed when last run, as `{path, error}`
+    failed_hooks: Vec<Value>,
+    /// Ends the process. Replaced in tests.
+    exit: Box<dyn Fn(i32) + Send + Sync>,
+}
 
 impl ProcStore {
     pub fn new() -> Self {
-        Self
+        Self {
+            hook_writer: None,
+            hooks: BTreeMap::new(),
+            next_hook: 0,
+            failed_hooks: Vec::new(),
+            exit: Box::new(|code| std::process::exit(code)),
+        }
+    }
+
+    /// Run exit hooks by writing through `writer`.
+    pub fn with_hook_writer(mut self, writer: Box<dyn Writer + Send + Sync>) -> Self {
+        self.hook_writer = Some(writer);
+        self
+    }
+
+    fn hooks_value(&self) -> Value {
+        Value::Map(
+            self.hooks
+                .iter()
+                .map(|(id, hook)| (id.to_string(), hook.to_value()))
+                .collect(),
+        )
+    }
+
+    fn register_hook(&mut self, data: Record) -> Result<Path, Error> {
+        if self.hook_writer.is_none() {
+            return Err(Error::store(
+                "proc",
+                "on_exit",
+                "exit hooks are not available: no hook writer configured",
+            ));
+        }
+        let hook = ExitHook::from_value(data.into_value(&NoCodec)?)?;
+        let id = self.next_hook;
+        self.next_hook += 1;
+        self.hooks.insert(id, hook);
+        Path::parse(&format!("self/on_exit/{}", id)).map_err(Error::from)
+    }
+
+    fn remove_hook(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if data.into_value(&NoCodec)? != Value::Null {
+            return Err(Error::store(
+                "proc",
+                "on_exit",
+                "write null to an exit hook to remove it",
+            ));
+        }
+        if let Ok(id) = to[2].parse::<u64>() {
+            self.hooks.remove(&id);
+        }
+        Ok(to.clone())
+    }
+
+    fn exit(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let code = match data.into_value(&NoCodec)? {
+            Value::Null => 0,
+            Value::Integer(code) => code,
+            Value::Map(map) => match map.get("code") {
+                None | Some(Value::Null) => 0,
+                Some(Value::Integer(code)) => *code,
+                Some(_) => {
+                    return Err(Error::store("proc", "exit", "exit code must be an integer"))
+                }
+            },
+            _ => {
+                return Err(Error::store(
+                    "proc",
+                    "exit",
+                    "write {\"code\": N} or an integer to exit",
+                ))
+            }
+        };
+        let code = i32::try_from(code).map_err(|_| {
+            Error::store("proc", "exit", format!("exit code {} out of range", code))
+        })?;
+
+        let hooks = std::mem::take(&mut self.hooks);
+        if let Some(writer) = &mut self.hook_writer {
+            for hook in hooks.into_values().rev() {
+                if let Err(e) = writer.write(&hook.path, Record::parsed(hook.data)) {
+                    self.failed_hooks.push(Value::Map(btree! {
+                        "path".into() => Value::String(hook.path.to_string()),
+                        "error".into() => Value::String(e.to_string()),
+                    }));
+                }
+            }
+        }
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+
+        (self.exit)(code);
+        Ok(to.clone())
     }
 
     fn self_info() -> Value {
@@ -20,6 +176,8 @@ impl ProcStore {
             "args".into() => Value::String("Command line arguments".into()),
             "exe".into() => Value::String("Path to current executable".into()),
             "env".into() => Value::String("Environment variables".into()),
+            "exit".into() => Value::String("Write {code} to exit after running exit hooks".into()),
+            "on_exit".into() => Value::String("Exit hooks; write {path, data} to register".into()),
         })
     }
 
@@ -39,6 +197,19 @@ impl ProcStore {
             return Ok(Some(Self::self_info()));
         }
 
+        if path[1] == "on_exit" {
+            return match path.len() {
+                2 => Ok(Some(self.hooks_value())),
+                3 if path[2] == "failed" => Ok(Some(Value::Array(self.failed_hooks.clone()))),
+                3 => Ok(path[2]
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|id| self.hooks.get(&id))
+                    .map(ExitHook::to_value)),
+                _ => Ok(None),
+            };
+        }
+
         if path.len() != 2 {
             return Ok(None);
         }
@@ -82,6 +253,10 @@ impl Reader for ProcStore {
 
 impl Writer for ProcStore {
     fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if to.len() == 3 && to[0] == "self" && to[1] == "on_exit" {
+            return self.remove_hook(to, data);
+        }
+
         // Must be proc/self/...
         if to.len() != 2 || to[0].as_str() != "self" {
             return Err(Error::store("proc", "write", "Invalid proc path"));
@@ -102,6 +277,8 @@ impl Writer for ProcStore {
 
                 Ok(to.clone())
             }
+            "exit" => self.exit(to, data),
+            "on_exit" => self.register_hook(data),
             _ => Err(Error::store(
                 "proc",
                 "write",
@@ -114,6 +291,7 @@ impl Writer for ProcStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
     use structfs_core_store::path;
 
     #[test]
@@ -253,9 +431,124 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Writer that records every write.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Writer for Recorder {
+        fn write(&mut self, to: &Path, _data: Record) -> Result<Path, Error> {
+            if to == &path!("broken") {
+                return Err(Error::store("recorder", "write", "broken"));
+            }
+            self.0.lock().unwrap().push(to.to_string());
+            Ok(to.clone())
+        }
+    }
+
+    fn exiting_store(recorder: &Recorder) -> (ProcStore, Arc<Mutex<Option<i32>>>) {
+        let exited = Arc::new(Mutex::new(None));
+        let mut store = ProcStore::new().with_hook_writer(Box::new(recorder.clone()));
+        let record = exited.clone();
+        store.exit = Box::new(move |code| *record.lock().unwrap() = Some(code));
+        (store, exited)
+    }
+
+    fn hook(path: &str) -> Record {
+        Record::parsed(Value::Map(btree! {
+            "path".into() => Value::String(path.into()),
+        }))
+    }
+
+    #[test]
+    fn exit_runs_hooks_in_reverse_order() {
+        let recorder = Recorder::default();
+        let (mut store, exited) = exiting_store(&recorder);
+
+        for path in ["db/close", "broken", "cache/flush"] {
+            store.write(&path!("self/on_exit"), hook(path)).unwrap();
+        }
+        store
+            .write(
+                &path!("self/exit"),
+                Record::parsed(Value::Map(btree! {"code".into() => Value::Integer(3)})),
+            )
+            .unwrap();
+
+        // The failing hook does not stop the rest
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["cache/flush", "db/close"]);
+        assert_eq!(*exited.lock().unwrap(), Some(3));
+
+        let failed = store.read(&path!("self/on_exit/failed")).unwrap().unwrap();
+        let Value::Array(failed) = failed.into_value(&NoCodec).unwrap() else {
+            panic!("expected an array");
+        };
+        assert_eq!(failed.len(), 1);
+        let Value::Map(failure) = &failed[0] else {
+            panic!("expected a map");
+        };
+        assert_eq!(failure["path"], Value::String("broken".into()));
+        assert!(matches!(&failure["error"], Value::String(e) if e.contains("broken")));
+    }
+
+    #[test]
+    fn on_exit_register_list_remove() {
+        let recorder = Recorder::default();
+        let (mut store, exited) = exiting_store(&recorder);
+
+        let id = store
+            .write(&path!("self/on_exit"), hook("cache/flush"))
+            .unwrap();
+        assert_eq!(id, path!("self/on_exit/0"));
+        let listed = store.read(&path!("self/on_exit")).unwrap().unwrap();
+        let Value::Map(listed) = listed.into_value(&NoCodec).unwrap() else {
+            panic!("expected map");
+        };
+        assert!(listed.contains_key("0"));
+        assert!(store.read(&id).unwrap().is_some());
+
+        store.write(&id, Record::parsed(Value::Null)).unwrap();
+        assert!(store.read(&id).unwrap().is_none());
+
+        store
+            .write(&path!("self/exit"), Record::parsed(Value::Null))
+            .unwrap();
+        assert!(recorder.0.lock().unwrap().is_empty());
+        assert_eq!(*exited.lock().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn on_exit_without_hook_writer_error() {
+        let mut store = ProcStore::new();
+        let result = store.write(&path!("self/on_exit"), hook("cache/flush"));
+        assert!(result.unwrap_err().to_string().contains("no hook writer"));
+    }
+
+    #[test]
+    fn exit_invalid_code_error() {
+        let (mut store, exited) = exiting_store(&Recorder::default());
+        let result = store.write(
+            &path!("self/exit"),
+            Record::parsed(Value::String("now".into())),
+        );
+        assert!(result.is_err());
+        assert_eq!(*exited.lock().unwrap(), None);
+    }
+
     #[test]
     fn default_impl() {
         let store: ProcStore = Default::default();
         assert!(std::ptr::eq(&store as *const _, &store as *const _));
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = ProcStore::new();
+        conformance::assert_found(&mut store, &path!("self/pid"));
+        conformance::assert_found(&mut store, &path!("self/on_exit"));
+        conformance::assert_missing(&mut store, &path!("self/on_exit/7"));
+        conformance::assert_missing(&mut store, &path!("self/nope"));
+        conformance::assert_missing(&mut store, &path!("other"));
+    }
 }
@@ -242,4 +242,14 @@ mod tests {
         let store: RandomStore = Default::default();
         assert!(std::ptr::eq(&store as *const _, &store as *const _)); // Just verify it works
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = RandomStore::new();
+        conformance::assert_found(&mut store, &path!("uuid"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+        conformance::assert_missing(&mut store, &path!("uuid/nope"));
+    }
 }
@@ -0,0 +1,427 @@
+//! Log file tailing store.
+//!
+//! Writing `{"path": "/var/log/app.log", "follow": true}` to the store root
+//! creates a tail handle at `tails/{id}`. Reading its `next` returns the
+//! lines appended since the last read; when there are none, a following
+//! tail blocks until some are, as `tail -F` does:
+//!
+//! ```text
+//! write fs/tail {path, follow?, from?}   -> tails/{id}
+//! read  fs/tail/tails/{id}/next          -> ["line", ...]
+//! read  fs/tail/tails/{id}               -> {id, path, position, follow}
+//! write fs/tail/tails/{id} null          -> stop tailing
+//! ```
+//!
+//! `from` is `"end"` (the default) to see only lines written after the tail
+//! is created, or `"start"` to begin with the file's existing lines. A tail
+//! that does not follow reads as nothing once it reaches the end of the
+//! file; one that follows (the default) waits for the caller's deadline and
+//! honours its cancellation.
+//!
+//! A following tail survives log rotation. When the file at the path is
+//! replaced, the tail finishes the old file and continues from the start of
+//! the new one; when the file is truncated, it starts again from the top. A
+//! path that does not exist yet is waited for. A line is only returned once
+//! its newline has been written.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use collection_literals::btree;
+
+use structfs_core_store::{
+    Error, HandleBroker, NoCodec, OpContext, Path, Reader, Record, Reference, Value, Writer,
+};
+
+/// How often a following tail checks the file for new lines.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Which file is open, to notice when rotation replaces it.
+#[cfg(unix)]
+fn identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+struct Tail {
+    path: PathBuf,
+    follow: bool,
+    file: Option<File>,
+    identity: Option<(u64, u64)>,
+    /// Offset of the next byte to read from the open file
+    position: u64,
+    /// The start of a line whose newline has not been written yet
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    fn open(path: PathBuf, follow: bool, from_start: bool) -> Result<Self, Error> {
+        let mut tail = Tail {
+            path,
+            follow,
+            file: None,
+            identity: None,
+            position: 0,
+            partial: Vec::new(),
+        };
+        match tail.reopen() {
+            Ok(()) => {}
+            Err(e) if follow && e.kind() == std::io::ErrorKind::NotFound => return Ok(tail),
+            Err(e) => return Err(io_error("open", &tail.path, e)),
+        }
+        if !from_start {
+            let file = tail.file.as_mut().expect("file was just opened");
+            tail.position = file
+                .seek(SeekFrom::End(0))
+                .map_err(|e| io_error("open", &tail.path, e))?;
+        }
+        Ok(tail)
+    }
+
+    /// Open the file now at the path, from its start.
+    fn reopen(&mut self) -> std::io::Result<()> {
+        let file = File::open(&self.path)?;
+        self.identity = identity(&file.metadata()?);
+        self.file = Some(file);
+        self.position = 0;
+        Ok(())
+    }
+
+    /// Read what has been appended to the open file.
+    fn read_appended(&mut self) -> std::io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        // Truncated in place: start again from the top
+        if file.metadata()?.len() < self.position {
+            self.partial.clear();
+            self.position = file.seek(SeekFrom::Start(0))?;
+        }
+        let read = file.read_to_end(&mut self.partial)?;
+        self.position += read as u64;
+        Ok(())
+    }
+
+    /// Whether the path now names a different file than the open one.
+    fn rotated(&self) -> bool {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => {
+                self.file.is_none()
+                    || identity(&metadata).is_some_and(|id| Some(id) != self.identity)
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Complete lines read so far.
+    fn take_lines(&mut self) -> Vec<Value> {
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        complete[..end]
+            .split(|&b| b == b'\n')
+            .map(|line| {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                Value::String(String::from_utf8_lossy(line).into_owned())
+            })
+            .collect()
+    }
+
+    /// New complete lines, switching to the new file if the log rotated.
+    fn poll(&mut self) -> Result<Vec<Value>, Error> {
+        self.read_appended()
+            .map_err(|e| io_error("next", &self.path, e))?;
+        let mut lines = self.take_lines();
+        if lines.is_empty() && self.follow && self.rotated() {
+            // The old file is finished; an unterminated last line is
+            // complete now
+            if !self.partial.is_empty() {
+                let last = std::mem::take(&mut self.partial);
+                lines.push(Value::String(String::from_utf8_lossy(&last).into_owned()));
+            }
+            match self.reopen() {
+                Ok(()) => {}
+                // Replaced and removed again before it could be opened
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(lines),
+                Err(e) => return Err(io_error("next", &self.path, e)),
+            }
+            self.read_appended()
+                .map_err(|e| io_error("next", &self.path, e))?;
+            lines.extend(self.take_lines());
+        }
+        Ok(lines)
+    }
+
+    /// The next lines, waiting for them if the tail follows.
+    fn next(&mut self) -> Result<Option<Value>, Error> {
+        loop {
+            OpContext::check_current("tail")?;
+            let lines = self.poll()?;
+            if !lines.is_empty() {
+                return Ok(Some(Value::Array(lines)));
+            }
+            if !self.follow {
+                return Ok(None);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn io_error(operation: &'static str, path: &std::path::Path, e: std::io::Error) -> Error {
+    Error::store("tail", operation, format!("{}: {}", path.display(), e))
+}
+
+/// Store for following log files.
+pub struct TailStore {
+    handles: HandleBroker<Tail>,
+}
+
+impl TailStore {
+    pub fn new() -> Self {
+        Self {
+            handles: HandleBroker::new("tails", "tail"),
+        }
+    }
+
+    fn read_value(&mut self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(Value::Map(btree! {
+                "create".into() => Value::String("Write {\"path\": \"...\", \"follow\"?: true, \"from\"?: \"end\" or \"start\"} here to get tails/{id}".into()),
+                "tails".into() => Reference::with_type("tails", "collection").to_value(),
+            })));
+        }
+        if self.handles.is_listing(path) {
+            return Ok(Some(self.handles.listing()));
+        }
+
+        let Some((id, rest)) = self.handles.parse(path) else {
+            return Ok(None);
+        };
+        let Some(tail) = self.handles.touch(id) else {
+            return Ok(None);
+        };
+        match rest.len() {
+            0 => Ok(Some(Value::Map(btree! {
+                "id".into() => Value::Integer(id as i64),
+                "path".into() => Value::String(tail.path.display().to_string()),
+                "position".into() => Value::Integer(tail.position as i64),
+                "follow".into() => Value::Bool(tail.follow),
+            }))),
+            1 if rest[0] == "next" => tail.next(),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Default for TailStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for TailStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from)?.map(Record::parsed))
+    }
+}
+
+impl Writer for TailStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        let value = data.into_value(&NoCodec)?;
+
+        if to.is_empty() {
+            let (path, follow, from) = match &value {
+                Value::String(path) => (path.clone(), true, None),
+                Value::Map(map) => {
+                    let Some(Value::String(path)) = map.get("path") else {
+                        return Err(Error::store("tail", "create", "missing 'path' field"));
+                    };
+                    let follow = match map.get("follow") {
+                        None | Some(Value::Null) => true,
+                        Some(Value::Bool(follow)) => *follow,
+                        Some(_) => {
+                            return Err(Error::store("tail", "create", "'follow' must be a bool"))
+                        }
+                    };
+                    let from = match map.get("from") {
+                        None | Some(Value::Null) => None,
+                        Some(Value::String(from)) => Some(from.clone()),
+                        Some(_) => {
+                            return Err(Error::store("tail", "create", "'from' must be a string"))
+                        }
+                    };
+                    (path.clone(), follow, from)
+                }
+                _ => {
+                    return Err(Error::store(
+                        "tail",
+                        "create",
+                        "expected {\"path\": \"...\"}",
+                    ))
+                }
+            };
+            let from_start = match from.as_deref() {
+                None | Some("end") => false,
+                Some("start") => true,
+                Some(other) => {
+                    return Err(Error::store(
+                        "tail",
+                        "create",
+                        format!("'from' must be \"start\" or \"end\", not \"{}\"", other),
+                    ))
+                }
+            };
+            let tail = Tail::open(PathBuf::from(path), follow, from_start)?;
+            let id = self.handles.insert(tail);
+            return Ok(self.handles.path(id));
+        }
+
+        // Writing null to tails/{id} stops the tail
+        match self.handles.parse(to) {
+            Some((id, rest)) if rest.is_empty() && value == Value::Null => {
+                self.handles.remove(id);
+                Ok(to.clone())
+            }
+            _ => Err(Error::store(
+                "tail",
+                "write",
+                format!("Cannot write to tail/{}", to),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+    use structfs_core_store::path;
+
+    fn next(store: &mut TailStore, handle: &Path) -> Option<Value> {
+        store
+            .read(&handle.join(&path!("next")))
+            .unwrap()
+            .map(|record| record.into_value(&NoCodec).unwrap())
+    }
+
+    fn lines(lines: &[&str]) -> Option<Value> {
+        Some(Value::Array(
+            lines.iter().map(|&l| Value::from(l)).collect(),
+        ))
+    }
+
+    fn append(path: &std::path::Path, text: &str) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn create(store: &mut TailStore, log: &std::path::Path, options: Value) -> Path {
+        let Value::Map(mut map) = options else {
+            panic!("Expected map");
+        };
+        map.insert("path".into(), Value::String(log.display().to_string()));
+        store
+            .write(&path!(""), Record::parsed(Value::Map(map)))
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_complete_lines_from_the_start_or_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("app.log");
+        append(&log, "one\ntwo\n");
+        let mut store = TailStore::new();
+
+        let from_start = create(
+            &mut store,
+            &log,
+            Value::Map(btree! {
+                "follow".into() => Value::Bool(false),
+                "from".into() => Value::from("start"),
+            }),
+        );
+        assert_eq!(from_start, path!("tails/0"));
+        let from_end = create(
+            &mut store,
+            &log,
+            Value::Map(btree! { "follow".into() => Value::Bool(false) }),
+        );
+
+        assert_eq!(next(&mut store, &from_start), lines(&["one", "two"]));
+        assert_eq!(next(&mut store, &from_start), None);
+        assert_eq!(next(&mut store, &from_end), None);
+
+        // Half a line waits for its newline
+        append(&log, "thr");
+        assert_eq!(next(&mut store, &from_end), None);
+        append(&log, "ee\r\nfour\n");
+        assert_eq!(next(&mut store, &from_end), lines(&["three", "four"]));
+        assert_eq!(next(&mut store, &from_start), lines(&["three", "four"]));
+    }
+
+    #[test]
+    fn follows_rotation_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("app.log");
+        append(&log, "old\n");
+        let mut store = TailStore::new();
+        let tail = create(&mut store, &log, Value::Map(Default::default()));
+
+        // Rotate: the last lines of the old file, then the new one
+        append(&log, "last\n");
+        fs::rename(&log, dir.path().join("app.log.1")).unwrap();
+        append(&log, "new\n");
+        assert_eq!(next(&mut store, &tail), lines(&["last"]));
+        assert_eq!(next(&mut store, &tail), lines(&["new"]));
+
+        // Truncate in place
+        fs::write(&log, "a\n").unwrap();
+        assert_eq!(next(&mut store, &tail), lines(&["a"]));
+    }
+
+    #[test]
+    fn following_waits_until_the_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("later.log");
+        let mut store = TailStore::new();
+        // Following a file that does not exist yet waits for it
+        let tail = create(&mut store, &log, Value::Map(Default::default()));
+
+        let ctx = OpContext::new().with_timeout(Duration::from_millis(120));
+        let result = ctx.scope(|| store.read(&tail.join(&path!("next"))));
+        assert!(matches!(result, Err(Error::DeadlineExceeded { .. })));
+
+        append(&log, "hello\n");
+        assert_eq!(next(&mut store, &tail), lines(&["hello"]));
+
+        store.write(&tail, Record::parsed(Value::Null)).unwrap();
+        assert!(store.read(&tail).unwrap().is_none());
+        assert!(store
+            .write(&path!(""), Record::parsed(Value::Map(Default::default())))
+            .is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = TailStore::new();
+        conformance::assert_found(&mut store, &path!("tails"));
+        conformance::assert_missing(&mut store, &path!("tails/7"));
+        conformance::assert_missing(&mut store, &path!("tails/7/next"));
+    }
+}
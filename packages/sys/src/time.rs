@@ -27,6 +27,7 @@ impl TimeStore {
                 "now_unix_ms".into() => Value::String("Unix timestamp (milliseconds)".into()),
                 "monotonic".into() => Value::String("Monotonic clock (nanoseconds since start)".into()),
                 "sleep".into() => Value::String("Write {\"ms\": N} or {\"secs\": N} to sleep".into()),
+                "cron".into() => Value::String("Write {\"expr\": \"0 */5 * * * *\"} to get a schedule handle".into()),
             })));
         }
 
@@ -263,4 +264,14 @@ mod tests {
         let store: TimeStore = Default::default();
         assert!(std::ptr::eq(&store as *const _, &store as *const _)); // Just verify it works
     }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = TimeStore::new();
+        conformance::assert_found(&mut store, &path!("now"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+        conformance::assert_missing(&mut store, &path!("now/nope"));
+    }
 }
@@ -0,0 +1,493 @@
+//! URL parsing and building store.
+
+use collection_literals::btree;
+use std::collections::BTreeMap;
+
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+
+/// Store for URL parsing and construction.
+///
+/// Write a URL string to `parse`, then read `parse` to get its components.
+/// Write a components map to `build`, then read `build` to get the URL string.
+pub struct UrlStore {
+    parsed: Option<Value>,
+    built: Option<Value>,
+}
+
+impl UrlStore {
+    pub fn new() -> Self {
+        Self {
+            parsed: None,
+            built: None,
+        }
+    }
+
+    fn read_value(&self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(Value::Map(btree! {
+                "parse".into() => Value::String("Write a URL string, read back its components".into()),
+                "build".into() => Value::String("Write {\"scheme\", \"host\", ...}, read back the URL string".into()),
+            })));
+        }
+
+        if path.len() != 1 {
+            return Ok(None);
+        }
+
+        match path[0].as_str() {
+            "parse" => Ok(self.parsed.clone()),
+            "build" => Ok(self.built.clone()),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_url(input: &str) -> Result<Value, Error> {
+        let url =
+            ::url::Url::parse(input).map_err(|e| Error::store("url", "parse", e.to_string()))?;
+
+        let mut query: BTreeMap<String, Value> = BTreeMap::new();
+        for (key, value) in url.query_pairs() {
+            let value = Value::String(value.into_owned());
+            match query.remove(key.as_ref()) {
+                None => {
+                    query.insert(key.into_owned(), value);
+                }
+                Some(Value::Array(mut values)) => {
+                    values.push(value);
+                    query.insert(key.into_owned(), Value::Array(values));
+                }
+                Some(previous) => {
+                    query.insert(key.into_owned(), Value::Array(vec![previous, value]));
+                }
+            }
+        }
+
+        let optional = |s: Option<&str>| s.map_or(Value::Null, |s| Value::String(s.to_string()));
+
+        Ok(Value::Map(btree! {
+            "url".into() => Value::String(url.to_string()),
+            "scheme".into() => Value::String(url.scheme().to_string()),
+            "username".into() => match url.username() {
+                "" => Value::Null,
+                name => Value::String(name.to_string()),
+            },
+            "password".into() => optional(url.password()),
+            "host".into() => optional(url.host_str()),
+            "port".into() => url.port_or_known_default().map_or(Value::Null, |p| Value::Integer(p as i64)),
+            "path".into() => Value::String(url.path().to_string()),
+            "query".into() => Value::Map(query),
+            "fragment".into() => optional(url.fragment()),
+        }))
+    }
+
+    fn build_url(value: &Value) -> Result<Value, Error> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::store(
+                    "url",
+                    "build",
+                    "build requires a map with 'scheme' and 'host' fields",
+                ))
+            }
+        };
+
+        let field = |name: &str| -> Result<Option<&str>, Error> {
+            match map.get(name) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.as_str())),
+                Some(_) => Err(Error::store(
+                    "url",
+                    "build",
+                    format!("'{}' must be a string", name),
+                )),
+            }
+        };
+
+        let scheme = field("scheme")?
+            .ok_or_else(|| Error::store("url", "build", "build requires 'scheme' field"))?;
+        let host = field("host")?
+            .ok_or_else(|| Error::store("url", "build", "build requires 'host' field"))?;
+
+        let mut url = ::url::Url::parse(&format!("{}://{}", scheme, host))
+            .map_err(|e| Error::store("url", "build", e.to_string()))?;
+
+        match map.get("port") {
+            None | Some(Value::Null) => {}
+            Some(Value::Integer(port)) => {
+                let port = u16::try_from(*port)
+                    .map_err(|_| Error::store("url", "build", format!("invalid port: {}", port)))?;
+                url.set_port(Some(port))
+                    .map_err(|_| Error::store("url", "build", "URL cannot have a port"))?;
+            }
+            Some(_) => return Err(Error::store("url", "build", "'port' must be an integer")),
+        }
+
+        if let Some(username) = field("username")? {
+            url.set_username(username)
+                .map_err(|_| Error::store("url", "build", "URL cannot have a username"))?;
+        }
+        if let Some(password) = field("password")? {
+            url.set_password(Some(password))
+                .map_err(|_| Error::store("url", "build", "URL cannot have a password"))?;
+        }
+        if let Some(path) = field("path")? {
+            url.set_path(path);
+        }
+
+        match map.get("query") {
+            None | Some(Value::Null) => {}
+            Some(Value::String(query)) => url.set_query(Some(query)),
+            Some(Value::Map(params)) => {
+                let mut pairs = url.query_pairs_mut();
+                for (key, value) in params {
+                    let values = match value {
+                        Value::Array(values) => values.as_slice(),
+                        other => std::slice::from_ref(other),
+                    };
+                    for value in values {
+                        pairs.append_pair(key, &Self::query_value(key, value)?);
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(Error::store(
+                    "url",
+                    "build",
+                    "'query' must be a string or a map",
+                ))
+            }
+        }
+
+        url.set_fragment(field("fragment")?);
+
+        Ok(Value::String(url.to_string()))
+    }
+
+    fn query_value(key: &str, value: &Value) -> Result<String, Error> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Integer(i) => Ok(i.to_string()),
+            Value::Float(f) => Ok(f.to_string()),
+            Value::Bool(b) => Ok(b.to_string()),
+            _ => Err(Error::store(
+                "url",
+                "build",
+                format!("query parameter '{}' must be a scalar", key),
+            )),
+        }
+    }
+}
+
+impl Default for UrlStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for UrlStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from)?.map(Record::parsed))
+    }
+}
+
+impl Writer for UrlStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if to.len() != 1 {
+            return Err(Error::store("url", "write", "Invalid url path"));
+        }
+
+        match to[0].as_str() {
+            "parse" => {
+                let value = data.into_value(&NoCodec)?;
+                let input = match &value {
+                    Value::String(s) => s,
+                    _ => return Err(Error::store("url", "parse", "parse requires a URL string")),
+                };
+                self.parsed = Some(Self::parse_url(input)?);
+                Ok(to.clone())
+            }
+            "build" => {
+                let value = data.into_value(&NoCodec)?;
+                self.built = Some(Self::build_url(&value)?);
+                Ok(to.clone())
+            }
+            _ => Err(Error::store(
+                "url",
+                "write",
+                format!("Cannot write to url/{}", to[0]),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn parse(store: &mut UrlStore, url: &str) -> BTreeMap<String, Value> {
+        store
+            .write(&path!("parse"), Record::parsed(Value::String(url.into())))
+            .unwrap();
+        match store
+            .read(&path!("parse"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap()
+        {
+            Value::Map(map) => map,
+            _ => panic!("Expected map"),
+        }
+    }
+
+    fn build(store: &mut UrlStore, parts: Value) -> String {
+        store.write(&path!("build"), Record::parsed(parts)).unwrap();
+        match store
+            .read(&path!("build"))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap()
+        {
+            Value::String(s) => s,
+            _ => panic!("Expected string"),
+        }
+    }
+
+    #[test]
+    fn read_root() {
+        let mut store = UrlStore::new();
+        let value = store
+            .read(&path!(""))
+            .unwrap()
+            .unwrap()
+            .into_value(&NoCodec)
+            .unwrap();
+        match value {
+            Value::Map(map) => {
+                assert!(map.contains_key("parse"));
+                assert!(map.contains_key("build"));
+            }
+            _ => panic!("Expected map"),
+        }
+    }
+
+    #[test]
+    fn read_before_write_is_none() {
+        let mut store = UrlStore::new();
+        assert!(store.read(&path!("parse")).unwrap().is_none());
+        assert!(store.read(&path!("build")).unwrap().is_none());
+        assert!(store.read(&path!("other")).unwrap().is_none());
+        assert!(store.read(&path!("parse/host")).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_components() {
+        let mut store = UrlStore::new();
+        let map = parse(
+            &mut store,
+            "https://user:pw@example.com:8443/a/b?x=1&y=two#frag",
+        );
+        assert_eq!(map["scheme"], Value::String("https".into()));
+        assert_eq!(map["username"], Value::String("user".into()));
+        assert_eq!(map["password"], Value::String("pw".into()));
+        assert_eq!(map["host"], Value::String("example.com".into()));
+        assert_eq!(map["port"], Value::Integer(8443));
+        assert_eq!(map["path"], Value::String("/a/b".into()));
+        assert_eq!(map["fragment"], Value::String("frag".into()));
+        assert_eq!(
+            map["query"],
+            Value::Map(btree! {
+                "x".into() => Value::String("1".into()),
+                "y".into() => Value::String("two".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let mut store = UrlStore::new();
+        let map = parse(&mut store, "http://example.com");
+        assert_eq!(map["port"], Value::Integer(80));
+        assert_eq!(map["path"], Value::String("/".into()));
+        assert_eq!(map["username"], Value::Null);
+        assert_eq!(map["password"], Value::Null);
+        assert_eq!(map["fragment"], Value::Null);
+        assert_eq!(map["query"], Value::Map(BTreeMap::new()));
+    }
+
+    #[test]
+    fn parse_repeated_query_keys() {
+        let mut store = UrlStore::new();
+        let map = parse(&mut store, "http://example.com/?tag=a&tag=b&tag=c");
+        assert_eq!(
+            map["query"],
+            Value::Map(btree! {
+                "tag".into() => Value::Array(vec![
+                    Value::String("a".into()),
+                    Value::String("b".into()),
+                    Value::String("c".into()),
+                ]),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_invalid_url() {
+        let mut store = UrlStore::new();
+        let result = store.write(
+            &path!("parse"),
+            Record::parsed(Value::String("not a url".into())),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_requires_string() {
+        let mut store = UrlStore::new();
+        let result = store.write(&path!("parse"), Record::parsed(Value::Integer(1)));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_full_url() {
+        let mut store = UrlStore::new();
+        let url = build(
+            &mut store,
+            Value::Map(btree! {
+                "scheme".into() => Value::String("https".into()),
+                "host".into() => Value::String("example.com".into()),
+                "port".into() => Value::Integer(8443),
+                "path".into() => Value::String("/search".into()),
+                "query".into() => Value::Map(btree! {
+                    "q".into() => Value::String("a b".into()),
+                    "n".into() => Value::Integer(10),
+                }),
+                "fragment".into() => Value::String("top".into()),
+            }),
+        );
+        assert_eq!(url, "https://example.com:8443/search?n=10&q=a+b#top");
+    }
+
+    #[test]
+    fn build_query_string_and_repeats() {
+        let mut store = UrlStore::new();
+        let url = build(
+            &mut store,
+            Value::Map(btree! {
+                "scheme".into() => Value::String("http".into()),
+                "host".into() => Value::String("example.com".into()),
+                "query".into() => Value::String("raw=1".into()),
+            }),
+        );
+        assert_eq!(url, "http://example.com/?raw=1");
+
+        let url = build(
+            &mut store,
+            Value::Map(btree! {
+                "scheme".into() => Value::String("http".into()),
+                "host".into() => Value::String("example.com".into()),
+                "query".into() => Value::Map(btree! {
+                    "tag".into() => Value::Array(vec![
+                        Value::String("a".into()),
+                        Value::String("b".into()),
+                    ]),
+                }),
+            }),
+        );
+        assert_eq!(url, "http://example.com/?tag=a&tag=b");
+    }
+
+    #[test]
+    fn build_then_parse_roundtrip() {
+        let mut store = UrlStore::new();
+        let url = build(
+            &mut store,
+            Value::Map(btree! {
+                "scheme".into() => Value::String("https".into()),
+                "host".into() => Value::String("api.example.com".into()),
+                "path".into() => Value::String("/v1/users".into()),
+            }),
+        );
+        let map = parse(&mut store, &url);
+        assert_eq!(map["host"], Value::String("api.example.com".into()));
+        assert_eq!(map["path"], Value::String("/v1/users".into()));
+    }
+
+    #[test]
+    fn build_missing_host() {
+        let mut store = UrlStore::new();
+        let result = store.write(
+            &path!("build"),
+            Record::parsed(Value::Map(btree! {
+                "scheme".into() => Value::String("https".into()),
+            })),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_invalid_fields() {
+        let mut store = UrlStore::new();
+        for (key, value) in [
+            ("port", Value::Integer(70000)),
+            ("port", Value::String("80".into())),
+            ("path", Value::Integer(1)),
+            ("query", Value::Integer(1)),
+            (
+                "query",
+                Value::Map(btree! { "k".into() => Value::Map(BTreeMap::new()) }),
+            ),
+        ] {
+            let mut map: BTreeMap<String, Value> = btree! {
+                "scheme".into() => Value::String("https".into()),
+                "host".into() => Value::String("example.com".into()),
+            };
+            map.insert(key.into(), value);
+            let result = store.write(&path!("build"), Record::parsed(Value::Map(map)));
+            assert!(result.is_err(), "expected error for {}", key);
+        }
+    }
+
+    #[test]
+    fn build_requires_map() {
+        let mut store = UrlStore::new();
+        let result = store.write(&path!("build"), Record::parsed(Value::String("x".into())));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn write_invalid_path() {
+        let mut store = UrlStore::new();
+        assert!(store
+            .write(&path!("other"), Record::parsed(Value::Null))
+            .is_err());
+        assert!(store
+            .write(&path!("parse/extra"), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn default_impl() {
+        let mut store = UrlStore::default();
+        assert!(store.read(&path!("")).unwrap().is_some());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = UrlStore::new();
+        conformance::assert_missing(&mut store, &path!("parse"));
+        conformance::assert_missing(&mut store, &path!("nope"));
+        store
+            .write(
+                &path!("parse"),
+                Record::parsed(Value::String("https://example.com/a".into())),
+            )
+            .unwrap();
+        conformance::assert_found(&mut store, &path!("parse"));
+    }
+}
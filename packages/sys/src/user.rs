@@ -0,0 +1,311 @@
+//! User and group information store.
+//!
+//! ```text
+//! read user/current               -> {uid, gid, username, home, shell, groups}
+//! read user/current/home          -> "/home/alice"
+//! read user/lookup/user/alice     -> {uid, gid, username, home, shell}
+//! read user/lookup/user/1000      -> the same, by uid
+//! read user/lookup/group/wheel    -> {gid, name, members}
+//! read user/lookup/group/10       -> the same, by gid
+//! ```
+//!
+//! Names that are not valid path components, such as `www-data`, can be
+//! looked up by writing them to `lookup` and reading `lookup` back:
+//!
+//! ```text
+//! write user/lookup {"user": "www-data"}   (or {"group": ...})
+//! read user/lookup                -> {uid, gid, username, home, shell}
+//! ```
+//!
+//! Lookups of unknown users and groups read as nothing. The store is only
+//! available on Unix; elsewhere every read but the root is an error.
+
+use collection_literals::btree;
+
+use structfs_core_store::{Error, NoCodec, Path, Reader, Record, Value, Writer};
+
+/// Store for user and group information.
+pub struct UserStore {
+    /// Result of the last lookup written to `lookup`.
+    looked_up: Option<Value>,
+}
+
+impl UserStore {
+    pub fn new() -> Self {
+        Self { looked_up: None }
+    }
+
+    fn read_value(&self, path: &Path) -> Result<Option<Value>, Error> {
+        if path.is_empty() {
+            return Ok(Some(Value::Map(btree! {
+                "current".into() => Value::String("The user running this process: uid, gid, username, home, shell, groups".into()),
+                "lookup".into() => Value::String("Read lookup/user/{name or uid} or lookup/group/{name or gid}, or write {\"user\"} or {\"group\"} and read back".into()),
+            })));
+        }
+
+        match (path[0].as_str(), path.len()) {
+            ("current", 1) => Ok(Some(imp::current()?)),
+            ("current", 2) => match imp::current()? {
+                Value::Map(mut map) => Ok(map.remove(&path[1])),
+                _ => Ok(None),
+            },
+            ("lookup", 1) => Ok(self.looked_up.clone()),
+            ("lookup", 3) => match path[1].as_str() {
+                "user" => imp::user(&path[2]),
+                "group" => imp::group(&path[2]),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Default for UserStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reader for UserStore {
+    fn read(&mut self, from: &Path) -> Result<Option<Record>, Error> {
+        Ok(self.read_value(from)?.map(Record::parsed))
+    }
+}
+
+impl Writer for UserStore {
+    fn write(&mut self, to: &Path, data: Record) -> Result<Path, Error> {
+        if to.len() != 1 || to[0] != "lookup" {
+            return Err(Error::store(
+                "user",
+                "write",
+                format!("Cannot write to user/{}", to),
+            ));
+        }
+
+        let value = data.into_value(&NoCodec)?;
+        let key = |value: &Value| match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Integer(n) => Some(n.to_string()),
+            _ => None,
+        };
+        let Value::Map(map) = &value else {
+            return Err(Error::store(
+                "user",
+                "lookup",
+                "lookup requires {\"user\": name or uid} or {\"group\": name or gid}",
+            ));
+        };
+        self.looked_up = if let Some(user) = map.get("user").and_then(key) {
+            imp::user(&user)?
+        } else if let Some(group) = map.get("group").and_then(key) {
+            imp::group(&group)?
+        } else {
+            return Err(Error::store(
+                "user",
+                "lookup",
+                "lookup requires a 'user' or 'group' field",
+            ));
+        };
+        Ok(to.clone())
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use collection_literals::btree;
+    use std::ffi::OsStr;
+    use uzers::os::unix::{GroupExt, UserExt};
+    use uzers::{Group, User};
+
+    use structfs_core_store::{Error, Value};
+
+    fn string(s: &OsStr) -> Value {
+        Value::String(s.to_string_lossy().into_owned())
+    }
+
+    fn user_value(user: &User) -> Value {
+        Value::Map(btree! {
+            "uid".into() => Value::Integer(user.uid() as i64),
+            "gid".into() => Value::Integer(user.primary_group_id() as i64),
+            "username".into() => string(user.name()),
+            "home".into() => string(user.home_dir().as_os_str()),
+            "shell".into() => string(user.shell().as_os_str()),
+        })
+    }
+
+    fn group_value(group: &Group) -> Value {
+        Value::Map(btree! {
+            "gid".into() => Value::Integer(group.gid() as i64),
+            "name".into() => string(group.name()),
+            "members".into() => Value::Array(group.members().iter().map(|m| string(m)).collect()),
+        })
+    }
+
+    pub fn current() -> Result<Value, Error> {
+        let uid = uzers::get_current_uid();
+        let user = uzers::get_user_by_uid(uid).ok_or_else(|| {
+            Error::store("user", "current", format!("no user entry for uid {}", uid))
+        })?;
+        let groups = uzers::get_user_groups(user.name(), user.primary_group_id())
+            .unwrap_or_default()
+            .iter()
+            .map(|group| {
+                Value::Map(btree! {
+                    "gid".into() => Value::Integer(group.gid() as i64),
+                    "name".into() => string(group.name()),
+                })
+            })
+            .collect();
+
+        let Value::Map(mut map) = user_value(&user) else {
+            unreachable!("user_value returns a map");
+        };
+        // The process's gid, which may differ from the user's primary group
+        map.insert(
+            "gid".into(),
+            Value::Integer(uzers::get_current_gid() as i64),
+        );
+        map.insert("groups".into(), Value::Array(groups));
+        Ok(Value::Map(map))
+    }
+
+    pub fn user(key: &str) -> Result<Option<Value>, Error> {
+        let user = match key.parse() {
+            Ok(uid) => uzers::get_user_by_uid(uid),
+            Err(_) => uzers::get_user_by_name(key),
+        };
+        Ok(user.as_ref().map(user_value))
+    }
+
+    pub fn group(key: &str) -> Result<Option<Value>, Error> {
+        let group = match key.parse() {
+            Ok(gid) => uzers::get_group_by_gid(gid),
+            Err(_) => uzers::get_group_by_name(key),
+        };
+        Ok(group.as_ref().map(group_value))
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use structfs_core_store::{Error, Value};
+
+    fn unsupported() -> Error {
+        Error::store("user", "read", "user information is only available on Unix")
+    }
+
+    pub fn current() -> Result<Value, Error> {
+        Err(unsupported())
+    }
+
+    pub fn user(_key: &str) -> Result<Option<Value>, Error> {
+        Err(unsupported())
+    }
+
+    pub fn group(_key: &str) -> Result<Option<Value>, Error> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use structfs_core_store::path;
+
+    fn read(store: &mut UserStore, path: &Path) -> Option<Value> {
+        store
+            .read(path)
+            .unwrap()
+            .map(|record| record.into_value(&NoCodec).unwrap())
+    }
+
+    #[test]
+    fn read_current_user() {
+        let mut store = UserStore::new();
+        let Some(Value::Map(current)) = read(&mut store, &path!("current")) else {
+            panic!("Expected map");
+        };
+        assert_eq!(
+            current["uid"],
+            Value::Integer(uzers::get_current_uid() as i64)
+        );
+        assert!(matches!(current["username"], Value::String(_)));
+        assert!(matches!(current["groups"], Value::Array(_)));
+
+        assert_eq!(
+            read(&mut store, &path!("current/home")),
+            Some(current["home"].clone())
+        );
+    }
+
+    #[test]
+    fn lookup_by_name_and_id() {
+        let mut store = UserStore::new();
+        let Some(Value::Map(root)) = read(&mut store, &path!("lookup/user/0")) else {
+            panic!("Expected map");
+        };
+        let Value::String(name) = &root["username"] else {
+            panic!("Expected string");
+        };
+        let by_name = Path::parse(&format!("lookup/user/{}", name)).unwrap();
+        assert_eq!(read(&mut store, &by_name), Some(Value::Map(root.clone())));
+
+        let Some(Value::Map(group)) = read(&mut store, &path!("lookup/group/0")) else {
+            panic!("Expected map");
+        };
+        assert_eq!(group["gid"], Value::Integer(0));
+    }
+
+    #[test]
+    fn lookup_by_write() {
+        let mut store = UserStore::new();
+        assert_eq!(read(&mut store, &path!("lookup")), None);
+
+        store
+            .write(
+                &path!("lookup"),
+                Record::parsed(Value::Map(btree! { "user".into() => Value::Integer(0) })),
+            )
+            .unwrap();
+        let by_uid = read(&mut store, &path!("lookup")).unwrap();
+        assert_eq!(read(&mut store, &path!("lookup/user/0")), Some(by_uid));
+
+        store
+            .write(
+                &path!("lookup"),
+                Record::parsed(Value::Map(
+                    btree! { "group".into() => Value::String("no-such-group".into()) },
+                )),
+            )
+            .unwrap();
+        assert_eq!(read(&mut store, &path!("lookup")), None);
+
+        let result = store.write(&path!("lookup"), Record::parsed(Value::Null));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unknown_entries_read_as_nothing() {
+        let mut store = UserStore::new();
+        assert_eq!(
+            read(&mut store, &path!("lookup/user/no_such_user_structfs")),
+            None
+        );
+        assert_eq!(read(&mut store, &path!("lookup/thing/x")), None);
+        assert!(store
+            .write(&path!("current"), Record::parsed(Value::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn conforms_to_store_contract() {
+        use structfs_core_store::conformance;
+
+        let mut store = UserStore::new();
+        conformance::assert_found(&mut store, &path!("current"));
+        conformance::assert_missing(&mut store, &path!("current/nope"));
+        conformance::assert_missing(&mut store, &path!("lookup"));
+        conformance::assert_missing(&mut store, &path!("lookup/user/structfs_no_such_user"));
+        conformance::assert_missing(&mut store, &path!("lookup/nope/root"));
+    }
+}